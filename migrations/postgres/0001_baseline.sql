@@ -0,0 +1,272 @@
+-- Baseline Postgres: stesso schema di migrations/sqlite/0001_baseline.sql, versione 1.
+-- Solo BIGINT, DOUBLE PRECISION e TEXT (i tipi letti dal driver Any); le date restano TEXT
+-- nel formato di CURRENT_TIMESTAMP di SQLite, così i confronti tra stringhe valgono su entrambi.
+
+-- Tabella UTENTI
+CREATE TABLE IF NOT EXISTS users (
+    tg_id TEXT PRIMARY KEY,
+    pubkey TEXT NOT NULL,
+    private_key_enc TEXT NOT NULL,
+    is_active BIGINT DEFAULT 0,
+    bot_started_at TEXT,
+    created_at TEXT DEFAULT to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS'),
+    settings TEXT,
+    email TEXT,
+    password_hash TEXT,
+    referral_code TEXT,
+    referred_by TEXT,
+    banned BIGINT DEFAULT 0
+);
+
+-- Tabella TRADES (Con highest_price per Trailing Stop)
+CREATE TABLE IF NOT EXISTS trades (
+    id BIGSERIAL PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    token_address TEXT NOT NULL,
+    tx_signature TEXT NOT NULL,
+    amount_in_lamports BIGINT NOT NULL,
+    status TEXT DEFAULT 'OPEN', -- OPEN, SOLD, FAILED
+    entry_time TEXT DEFAULT to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS'),
+    exit_time TEXT,
+    profit_loss_sol DOUBLE PRECISION DEFAULT 0,
+    highest_price_lamports BIGINT DEFAULT 0,
+    entry_sol_usd DOUBLE PRECISION,
+    exit_sol_usd DOUBLE PRECISION,
+    exit_signature TEXT,
+    usd_value_at_entry DOUBLE PRECISION,
+    usd_value_at_exit DOUBLE PRECISION,
+    sol_received_lamports BIGINT,
+    fee_lamports BIGINT,
+    strategy TEXT
+);
+
+-- Tabella PRELIEVI (Per gestire i crash durante i prelievi)
+CREATE TABLE IF NOT EXISTS withdrawals (
+    id BIGSERIAL PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    amount_lamports BIGINT NOT NULL,
+    destination TEXT NOT NULL,
+    status TEXT DEFAULT 'PENDING', -- PENDING_APPROVAL (2FA Telegram), PENDING, COMPLETED, FAILED, CANCELLED
+    tx_signature TEXT,
+    created_at TEXT DEFAULT to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS')
+);
+
+-- Tabella POSIZIONI (Stato Trailing Stop, sopravvive ai riavvii)
+CREATE TABLE IF NOT EXISTS positions (
+    trade_id BIGINT PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    token_address TEXT NOT NULL,
+    amount_in_lamports BIGINT NOT NULL,
+    entry_price DOUBLE PRECISION NOT NULL,
+    highest_value_lamports BIGINT NOT NULL,
+    opened_at BIGINT NOT NULL,
+    stop_floor_lamports BIGINT DEFAULT 0,
+    take_profit_lamports BIGINT DEFAULT 0,
+    trailing_disabled BIGINT DEFAULT 0
+);
+
+-- Tabella WATCHLIST (Token monitorati per utente)
+CREATE TABLE IF NOT EXISTS watchlists (
+    id BIGSERIAL PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    token_address TEXT NOT NULL,
+    added_at TEXT DEFAULT to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS'),
+    UNIQUE(user_id, token_address)
+);
+
+-- Tabella SESSIONI (Login Web con scadenza)
+CREATE TABLE IF NOT EXISTS sessions (
+    token TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    created_at BIGINT NOT NULL,
+    expires_at BIGINT NOT NULL
+);
+
+-- Tabella DCA (Acquisti ricorrenti programmati)
+CREATE TABLE IF NOT EXISTS dca_orders (
+    id BIGSERIAL PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    token_address TEXT NOT NULL,
+    amount_lamports BIGINT NOT NULL,
+    interval_secs BIGINT NOT NULL,
+    status TEXT DEFAULT 'ACTIVE', -- ACTIVE, PAUSED, CANCELLED
+    next_run BIGINT NOT NULL,
+    fills BIGINT DEFAULT 0,
+    total_spent_lamports BIGINT DEFAULT 0,
+    created_at TEXT DEFAULT to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS')
+);
+
+-- Tabella LIMIT ORDERS (Buy/Sell a prezzo target, prezzo in USD)
+CREATE TABLE IF NOT EXISTS limit_orders (
+    id BIGSERIAL PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    token_address TEXT NOT NULL,
+    side TEXT NOT NULL, -- BUY, SELL
+    trigger_price DOUBLE PRECISION NOT NULL,
+    trigger_above BIGINT NOT NULL, -- 1 = scatta quando il prezzo sale sopra il target
+    amount_lamports BIGINT NOT NULL,
+    status TEXT DEFAULT 'ACTIVE', -- ACTIVE, EXECUTING, FILLED, FAILED, CANCELLED
+    tx_signature TEXT,
+    filled_price DOUBLE PRECISION,
+    created_at TEXT DEFAULT to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS')
+);
+
+-- Tabella RISK (Circuit Breaker giornaliero: saldo di partenza e scatto del blocco)
+CREATE TABLE IF NOT EXISTS risk_days (
+    user_id TEXT NOT NULL,
+    day TEXT NOT NULL, -- YYYY-MM-DD (UTC)
+    start_balance_lamports BIGINT NOT NULL,
+    tripped_at TEXT,
+    reset_done BIGINT DEFAULT 0, -- 1 = auto-trading già riattivato a mezzanotte
+    PRIMARY KEY (user_id, day)
+);
+
+-- Tabella FILTRI TOKEN (Blacklist / Whitelist per utente: mint o simboli)
+CREATE TABLE IF NOT EXISTS token_filters (
+    id BIGSERIAL PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    list TEXT NOT NULL, -- BLACKLIST, WHITELIST
+    value TEXT NOT NULL, -- Mint oppure simbolo (MAIUSCOLO)
+    added_at TEXT DEFAULT to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS'),
+    UNIQUE(user_id, list, value)
+);
+
+-- Tabella EXECUTIONS (Audit di ogni tentativo di swap: route, quote, fill reale, fee)
+CREATE TABLE IF NOT EXISTS executions (
+    id BIGSERIAL PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    side TEXT NOT NULL, -- BUY, SELL
+    input_mint TEXT NOT NULL,
+    output_mint TEXT NOT NULL,
+    amount_in BIGINT NOT NULL,
+    route TEXT NOT NULL,
+    quoted_out BIGINT DEFAULT 0,
+    actual_out BIGINT,
+    fee_lamports BIGINT,
+    priority_fee_lamports BIGINT,
+    slippage_bps BIGINT NOT NULL,
+    status TEXT NOT NULL, -- SENT, CONFIRMED, FAILED, UNCONFIRMED
+    tx_signature TEXT,
+    error TEXT,
+    created_at TEXT DEFAULT to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS')
+);
+
+-- Tabella WEBHOOKS (Notifiche Discord/Slack/Generiche per utente)
+CREATE TABLE IF NOT EXISTS webhooks (
+    id BIGSERIAL PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    url TEXT NOT NULL,
+    kind TEXT NOT NULL, -- DISCORD, SLACK, GENERIC
+    created_at TEXT DEFAULT to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS'),
+    UNIQUE(user_id, url)
+);
+
+-- Tabelle REFERRAL (Quote maturate sul volume degli invitati e pagamenti al referrer)
+CREATE TABLE IF NOT EXISTS referral_rewards (
+    id BIGSERIAL PRIMARY KEY,
+    referrer_id TEXT NOT NULL,
+    invitee_id TEXT NOT NULL,
+    volume_lamports BIGINT NOT NULL,
+    reward_lamports BIGINT NOT NULL,
+    tx_signature TEXT,
+    created_at TEXT DEFAULT to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS')
+);
+
+CREATE TABLE IF NOT EXISTS referral_payouts (
+    id BIGSERIAL PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    amount_lamports BIGINT NOT NULL,
+    status TEXT DEFAULT 'PENDING', -- PENDING, COMPLETED, FAILED
+    tx_signature TEXT,
+    created_at TEXT DEFAULT to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS')
+);
+
+-- Tabella FOLLOWED_WALLETS (Copy-Trading: wallet "leader" seguiti dall'utente)
+CREATE TABLE IF NOT EXISTS followed_wallets (
+    id BIGSERIAL PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    wallet TEXT NOT NULL,
+    created_at TEXT DEFAULT to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS'),
+    UNIQUE(user_id, wallet)
+);
+
+-- Tabelle GRID (Grid Trading: griglia per utente + livelli con i fill)
+CREATE TABLE IF NOT EXISTS grid_bots (
+    id BIGSERIAL PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    base_mint TEXT NOT NULL,
+    quote_mint TEXT NOT NULL,
+    base_decimals BIGINT NOT NULL,
+    quote_decimals BIGINT NOT NULL,
+    lower_price DOUBLE PRECISION NOT NULL,
+    upper_price DOUBLE PRECISION NOT NULL,
+    step_pct DOUBLE PRECISION NOT NULL,
+    order_size BIGINT NOT NULL,
+    status TEXT DEFAULT 'ACTIVE', -- ACTIVE, STOPPED
+    round_trips BIGINT DEFAULT 0,
+    realized_profit BIGINT DEFAULT 0,
+    created_at TEXT DEFAULT to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS')
+);
+
+CREATE TABLE IF NOT EXISTS grid_levels (
+    id BIGSERIAL PRIMARY KEY,
+    grid_id BIGINT NOT NULL,
+    buy_price DOUBLE PRECISION NOT NULL,
+    sell_price DOUBLE PRECISION NOT NULL,
+    status TEXT NOT NULL, -- IDLE (sotto il prezzo, in attesa), WAITING_BUY, HOLDING
+    base_amount BIGINT DEFAULT 0,
+    last_tx TEXT,
+    updated_at TEXT DEFAULT to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS')
+);
+
+-- Tabella WITHDRAWAL_ADDRESSES (Whitelist prelievi: conferma Telegram + attesa prima dell'uso)
+CREATE TABLE IF NOT EXISTS withdrawal_addresses (
+    id BIGSERIAL PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    address TEXT NOT NULL,
+    label TEXT,
+    status TEXT DEFAULT 'PENDING', -- PENDING (in attesa di conferma), CONFIRMED
+    added_at BIGINT, -- Unix, alla conferma: da qui parte il periodo di attesa
+    created_at TEXT DEFAULT to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS'),
+    UNIQUE(user_id, address)
+);
+
+-- Tabella DEPOSITS (Versamenti rilevati on-chain, una riga per TX e mint)
+CREATE TABLE IF NOT EXISTS deposits (
+    id BIGSERIAL PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    tx_signature TEXT NOT NULL,
+    mint TEXT NOT NULL,
+    amount BIGINT NOT NULL,
+    amount_ui DOUBLE PRECISION NOT NULL,
+    usd_value DOUBLE PRECISION,
+    created_at TEXT DEFAULT to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS'),
+    UNIQUE(tx_signature, mint)
+);
+
+-- Tabella SELL_RETRIES (Vendite del Position Manager fallite, una per utente e token)
+-- PENDING = in coda, FAILED = tentativi esauriti (utente avvisato). La riga sparisce alla chiusura delle posizioni.
+CREATE TABLE IF NOT EXISTS sell_retries (
+    id BIGSERIAL PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    token TEXT NOT NULL,
+    reason TEXT NOT NULL,
+    attempts BIGINT DEFAULT 0,
+    next_attempt_at BIGINT NOT NULL,
+    last_error TEXT,
+    status TEXT DEFAULT 'PENDING',
+    created_at TEXT DEFAULT to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS'),
+    UNIQUE(user_id, token)
+);
+
+-- Tabella LAUNCHES (Lanci visti dallo Sniper: reputazione dei deployer)
+CREATE TABLE IF NOT EXISTS launches (
+    token TEXT PRIMARY KEY,
+    deployer TEXT NOT NULL,
+    pool_sol DOUBLE PRECISION,
+    rugged BIGINT DEFAULT 0,
+    created_at TEXT DEFAULT to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS')
+);
+
+CREATE INDEX IF NOT EXISTS idx_launches_deployer ON launches(deployer);
+CREATE UNIQUE INDEX IF NOT EXISTS idx_users_referral_code ON users(referral_code);
@@ -0,0 +1,4 @@
+-- Onboarding Telegram: disclaimer sui rischi accettato e configurazione iniziale completata.
+-- Senza onboarded_at l'Auto-Bot non parte (db::start_daily_cycle).
+ALTER TABLE users ADD COLUMN IF NOT EXISTS disclaimer_accepted_at TEXT;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS onboarded_at TEXT;
@@ -0,0 +1,17 @@
+-- Statistiche di portafoglio materializzate per utente (portfolio::replay sui trade SOLD, in ordine di uscita).
+-- Ricalcolate a ogni vendita; la conferma on-chain del PnL (settle_sell) scarta la riga, rifatta alla lettura successiva.
+CREATE TABLE IF NOT EXISTS portfolio_stats (
+    user_id TEXT PRIMARY KEY,
+    closed_trades BIGINT NOT NULL,
+    wins BIGINT NOT NULL,
+    losses BIGINT NOT NULL,
+    total_pnl_sol DOUBLE PRECISION NOT NULL,
+    gross_profit_sol DOUBLE PRECISION NOT NULL,
+    gross_loss_sol DOUBLE PRECISION NOT NULL,
+    best_trade_sol DOUBLE PRECISION NOT NULL,
+    worst_trade_sol DOUBLE PRECISION NOT NULL,
+    invested_sol DOUBLE PRECISION NOT NULL,
+    max_drawdown_sol DOUBLE PRECISION NOT NULL,
+    current_streak BIGINT NOT NULL, -- > 0 vittorie consecutive, < 0 perdite consecutive
+    updated_at TEXT DEFAULT to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS')
+);
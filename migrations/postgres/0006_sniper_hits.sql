@@ -0,0 +1,21 @@
+-- Sniper in dry-run (SNIPER_DRY_RUN=1): ogni lancio rilevato viene registrato senza comprare, anche se scartato dai filtri.
+-- I prezzi a +5m/+30m/+2h li completa sniper_stats::run_followup (0 = nessun prezzo: token morto o senza liquidità).
+CREATE TABLE IF NOT EXISTS sniper_hits (
+    id BIGSERIAL PRIMARY KEY,
+    token TEXT NOT NULL,
+    symbol TEXT NOT NULL,
+    deployer TEXT NOT NULL,
+    detected_at BIGINT NOT NULL, -- unix
+    is_safe BIGINT NOT NULL,
+    reason TEXT NOT NULL,
+    safety_report TEXT NOT NULL, -- JSON di safety::TokenSafetyReport
+    price DOUBLE PRECISION NOT NULL,
+    liquidity_usd DOUBLE PRECISION NOT NULL,
+    market_cap DOUBLE PRECISION NOT NULL,
+    volume_24h DOUBLE PRECISION NOT NULL,
+    price_5m DOUBLE PRECISION,
+    price_30m DOUBLE PRECISION,
+    price_2h DOUBLE PRECISION
+);
+
+CREATE INDEX IF NOT EXISTS idx_sniper_hits_detected ON sniper_hits(detected_at);
@@ -0,0 +1,22 @@
+-- Off-ramp SEPA: SOL -> EURC -> bonifico dal provider. Stati (offramp.rs):
+-- AWAITING_APPROVAL -> APPROVED -> CONVERTING (swap inviato) -> SENT (EURC al vault) -> PROCESSING -> SETTLED | FAILED,
+-- oppure CANCELLED (rifiutato o scaduto). Importi EURC in unità base (6 decimali).
+CREATE TABLE IF NOT EXISTS offramp_payouts (
+    id BIGSERIAL PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    iban TEXT NOT NULL,
+    beneficiary TEXT NOT NULL,
+    amount_eurc BIGINT NOT NULL,
+    amount_lamports BIGINT NOT NULL DEFAULT 0, -- SOL spesi nello swap (quote ExactOut)
+    provider TEXT NOT NULL,
+    status TEXT NOT NULL,
+    swap_signature TEXT,
+    transfer_signature TEXT,
+    provider_ref TEXT,
+    error TEXT,
+    created_at BIGINT NOT NULL, -- unix
+    updated_at BIGINT NOT NULL  -- unix
+);
+
+CREATE INDEX IF NOT EXISTS idx_offramp_user ON offramp_payouts(user_id, created_at);
+CREATE INDEX IF NOT EXISTS idx_offramp_status ON offramp_payouts(status);
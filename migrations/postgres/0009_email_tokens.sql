@@ -0,0 +1,12 @@
+-- Verifica email e reset password (auth.rs): token monouso con scadenza, salvati solo come HMAC (token_hash).
+-- purpose: VERIFY | RESET. email_verified_at: indirizzo confermato (link di verifica o reset riuscito).
+ALTER TABLE users ADD COLUMN IF NOT EXISTS email_verified_at TEXT;
+CREATE TABLE IF NOT EXISTS email_tokens (
+    token_hash TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    purpose TEXT NOT NULL,
+    created_at BIGINT NOT NULL,
+    expires_at BIGINT NOT NULL,
+    used_at BIGINT
+);
+CREATE INDEX IF NOT EXISTS idx_email_tokens_user ON email_tokens (user_id, purpose);
@@ -0,0 +1,4 @@
+-- Slippage realizzato degli swap confermati: (quoted_out - actual_out) / quoted_out in bps, negativo = fill migliore.
+-- slippage_exceeded = 1 se oltre la tolleranza dello swap (slippage_bps): l'utente riceve un avviso.
+ALTER TABLE executions ADD COLUMN IF NOT EXISTS realized_slippage_bps BIGINT;
+ALTER TABLE executions ADD COLUMN IF NOT EXISTS slippage_exceeded BIGINT NOT NULL DEFAULT 0;
@@ -0,0 +1,4 @@
+-- Note libere e tag dell'utente sui trade (per rivedere cosa ha funzionato).
+-- tags: lista normalizzata (minuscolo, senza virgole) separata da virgole, NULL = nessun tag.
+ALTER TABLE trades ADD COLUMN IF NOT EXISTS note TEXT;
+ALTER TABLE trades ADD COLUMN IF NOT EXISTS tags TEXT;
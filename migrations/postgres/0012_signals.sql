@@ -0,0 +1,16 @@
+-- Storico dei segnali della Market Strategy (prima solo in RAM: persi al riavvio).
+-- Deduplica: un segnale per token e strategia (mode) in ogni finestra di 5 minuti (bucket = created_at / 300).
+-- Le righe più vecchie di SIGNAL_RETENTION_DAYS vengono cancellate dal ciclo della Market Strategy.
+CREATE TABLE IF NOT EXISTS signals (
+    id BIGSERIAL PRIMARY KEY,
+    token TEXT NOT NULL,
+    mode TEXT NOT NULL,
+    bucket BIGINT NOT NULL,
+    price DOUBLE PRECISION NOT NULL,
+    score BIGINT NOT NULL,
+    reason TEXT NOT NULL,
+    created_at BIGINT NOT NULL, -- unix
+    UNIQUE (token, mode, bucket)
+);
+
+CREATE INDEX IF NOT EXISTS idx_signals_created ON signals(created_at);
@@ -0,0 +1,15 @@
+-- Eventi dell'account senza una tabella propria (avvio/stop Auto-Bot, fill DCA, errori), scritti dai vari sottosistemi.
+-- GET /activity li unisce a trades, depositi, prelievi e segnali approvati/rifiutati in un'unica timeline.
+-- created_at nel formato di sql_timestamp (UTC): confrontabile come testo con le date delle altre tabelle.
+CREATE TABLE IF NOT EXISTS events (
+    id BIGSERIAL PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    kind TEXT NOT NULL, -- BOT_START, BOT_STOP, DCA, ERROR
+    token TEXT,
+    amount DOUBLE PRECISION,
+    message TEXT NOT NULL,
+    tx_signature TEXT,
+    created_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_events_user ON events(user_id, created_at);
@@ -0,0 +1,8 @@
+-- Fill reale degli acquisti, letto dalla TX confermata.
+-- executions.spent_lamports: SOL usciti dal wallet, senza fee di rete e senza il rent dei token account appena creati.
+-- executions.out_decimals: decimali del token ricevuto, per passare da unità raw a token.
+-- trades.entry_price: prezzo d'ingresso in USD = SOL spesi * entry_sol_usd / token ricevuti.
+--   NULL = fill non ancora confermato; il Position Manager usa intanto il prezzo di mercato.
+ALTER TABLE executions ADD COLUMN IF NOT EXISTS spent_lamports BIGINT;
+ALTER TABLE executions ADD COLUMN IF NOT EXISTS out_decimals BIGINT;
+ALTER TABLE trades ADD COLUMN IF NOT EXISTS entry_price DOUBLE PRECISION;
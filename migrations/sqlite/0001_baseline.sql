@@ -0,0 +1,272 @@
+-- Baseline: schema completo al passaggio alle migrazioni versionate.
+-- I DB creati prima (init_schema con ALTER TABLE ad-hoc) vengono allineati da db::upgrade_legacy_schema
+-- prima di questo file: qui tutto è IF NOT EXISTS, su quei DB non cambia nulla.
+
+-- Tabella UTENTI
+CREATE TABLE IF NOT EXISTS users (
+    tg_id TEXT PRIMARY KEY,
+    pubkey TEXT NOT NULL,
+    private_key_enc TEXT NOT NULL,
+    is_active INTEGER DEFAULT 0,
+    bot_started_at TEXT,
+    created_at TEXT DEFAULT CURRENT_TIMESTAMP,
+    settings TEXT,
+    email TEXT,
+    password_hash TEXT,
+    referral_code TEXT,
+    referred_by TEXT,
+    banned INTEGER DEFAULT 0
+);
+
+-- Tabella TRADES (Con highest_price per Trailing Stop)
+CREATE TABLE IF NOT EXISTS trades (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id TEXT NOT NULL,
+    token_address TEXT NOT NULL,
+    tx_signature TEXT NOT NULL,
+    amount_in_lamports INTEGER NOT NULL,
+    status TEXT DEFAULT 'OPEN', -- OPEN, SOLD, FAILED
+    entry_time TEXT DEFAULT CURRENT_TIMESTAMP,
+    exit_time TEXT,
+    profit_loss_sol REAL DEFAULT 0.0,
+    highest_price_lamports INTEGER DEFAULT 0,
+    entry_sol_usd REAL,
+    exit_sol_usd REAL,
+    exit_signature TEXT,
+    usd_value_at_entry REAL,
+    usd_value_at_exit REAL,
+    sol_received_lamports INTEGER,
+    fee_lamports INTEGER,
+    strategy TEXT
+);
+
+-- Tabella PRELIEVI (Per gestire i crash durante i prelievi)
+CREATE TABLE IF NOT EXISTS withdrawals (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id TEXT NOT NULL,
+    amount_lamports INTEGER NOT NULL,
+    destination TEXT NOT NULL,
+    status TEXT DEFAULT 'PENDING', -- PENDING_APPROVAL (2FA Telegram), PENDING, COMPLETED, FAILED, CANCELLED
+    tx_signature TEXT,
+    created_at TEXT DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Tabella POSIZIONI (Stato Trailing Stop, sopravvive ai riavvii)
+CREATE TABLE IF NOT EXISTS positions (
+    trade_id INTEGER PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    token_address TEXT NOT NULL,
+    amount_in_lamports INTEGER NOT NULL,
+    entry_price REAL NOT NULL,
+    highest_value_lamports INTEGER NOT NULL,
+    opened_at INTEGER NOT NULL,
+    stop_floor_lamports INTEGER DEFAULT 0,
+    take_profit_lamports INTEGER DEFAULT 0,
+    trailing_disabled INTEGER DEFAULT 0
+);
+
+-- Tabella WATCHLIST (Token monitorati per utente)
+CREATE TABLE IF NOT EXISTS watchlists (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id TEXT NOT NULL,
+    token_address TEXT NOT NULL,
+    added_at TEXT DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(user_id, token_address)
+);
+
+-- Tabella SESSIONI (Login Web con scadenza)
+CREATE TABLE IF NOT EXISTS sessions (
+    token TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    expires_at INTEGER NOT NULL
+);
+
+-- Tabella DCA (Acquisti ricorrenti programmati)
+CREATE TABLE IF NOT EXISTS dca_orders (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id TEXT NOT NULL,
+    token_address TEXT NOT NULL,
+    amount_lamports INTEGER NOT NULL,
+    interval_secs INTEGER NOT NULL,
+    status TEXT DEFAULT 'ACTIVE', -- ACTIVE, PAUSED, CANCELLED
+    next_run INTEGER NOT NULL,
+    fills INTEGER DEFAULT 0,
+    total_spent_lamports INTEGER DEFAULT 0,
+    created_at TEXT DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Tabella LIMIT ORDERS (Buy/Sell a prezzo target, prezzo in USD)
+CREATE TABLE IF NOT EXISTS limit_orders (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id TEXT NOT NULL,
+    token_address TEXT NOT NULL,
+    side TEXT NOT NULL, -- BUY, SELL
+    trigger_price REAL NOT NULL,
+    trigger_above INTEGER NOT NULL, -- 1 = scatta quando il prezzo sale sopra il target
+    amount_lamports INTEGER NOT NULL,
+    status TEXT DEFAULT 'ACTIVE', -- ACTIVE, EXECUTING, FILLED, FAILED, CANCELLED
+    tx_signature TEXT,
+    filled_price REAL,
+    created_at TEXT DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Tabella RISK (Circuit Breaker giornaliero: saldo di partenza e scatto del blocco)
+CREATE TABLE IF NOT EXISTS risk_days (
+    user_id TEXT NOT NULL,
+    day TEXT NOT NULL, -- YYYY-MM-DD (UTC)
+    start_balance_lamports INTEGER NOT NULL,
+    tripped_at TEXT,
+    reset_done INTEGER DEFAULT 0, -- 1 = auto-trading già riattivato a mezzanotte
+    PRIMARY KEY (user_id, day)
+);
+
+-- Tabella FILTRI TOKEN (Blacklist / Whitelist per utente: mint o simboli)
+CREATE TABLE IF NOT EXISTS token_filters (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id TEXT NOT NULL,
+    list TEXT NOT NULL, -- BLACKLIST, WHITELIST
+    value TEXT NOT NULL, -- Mint oppure simbolo (MAIUSCOLO)
+    added_at TEXT DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(user_id, list, value)
+);
+
+-- Tabella EXECUTIONS (Audit di ogni tentativo di swap: route, quote, fill reale, fee)
+CREATE TABLE IF NOT EXISTS executions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id TEXT NOT NULL,
+    side TEXT NOT NULL, -- BUY, SELL
+    input_mint TEXT NOT NULL,
+    output_mint TEXT NOT NULL,
+    amount_in INTEGER NOT NULL,
+    route TEXT NOT NULL,
+    quoted_out INTEGER DEFAULT 0,
+    actual_out INTEGER,
+    fee_lamports INTEGER,
+    priority_fee_lamports INTEGER,
+    slippage_bps INTEGER NOT NULL,
+    status TEXT NOT NULL, -- SENT, CONFIRMED, FAILED, UNCONFIRMED
+    tx_signature TEXT,
+    error TEXT,
+    created_at TEXT DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Tabella WEBHOOKS (Notifiche Discord/Slack/Generiche per utente)
+CREATE TABLE IF NOT EXISTS webhooks (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id TEXT NOT NULL,
+    url TEXT NOT NULL,
+    kind TEXT NOT NULL, -- DISCORD, SLACK, GENERIC
+    created_at TEXT DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(user_id, url)
+);
+
+-- Tabelle REFERRAL (Quote maturate sul volume degli invitati e pagamenti al referrer)
+CREATE TABLE IF NOT EXISTS referral_rewards (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    referrer_id TEXT NOT NULL,
+    invitee_id TEXT NOT NULL,
+    volume_lamports INTEGER NOT NULL,
+    reward_lamports INTEGER NOT NULL,
+    tx_signature TEXT,
+    created_at TEXT DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS referral_payouts (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id TEXT NOT NULL,
+    amount_lamports INTEGER NOT NULL,
+    status TEXT DEFAULT 'PENDING', -- PENDING, COMPLETED, FAILED
+    tx_signature TEXT,
+    created_at TEXT DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Tabella FOLLOWED_WALLETS (Copy-Trading: wallet "leader" seguiti dall'utente)
+CREATE TABLE IF NOT EXISTS followed_wallets (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id TEXT NOT NULL,
+    wallet TEXT NOT NULL,
+    created_at TEXT DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(user_id, wallet)
+);
+
+-- Tabelle GRID (Grid Trading: griglia per utente + livelli con i fill)
+CREATE TABLE IF NOT EXISTS grid_bots (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id TEXT NOT NULL,
+    base_mint TEXT NOT NULL,
+    quote_mint TEXT NOT NULL,
+    base_decimals INTEGER NOT NULL,
+    quote_decimals INTEGER NOT NULL,
+    lower_price REAL NOT NULL,
+    upper_price REAL NOT NULL,
+    step_pct REAL NOT NULL,
+    order_size INTEGER NOT NULL,
+    status TEXT DEFAULT 'ACTIVE', -- ACTIVE, STOPPED
+    round_trips INTEGER DEFAULT 0,
+    realized_profit INTEGER DEFAULT 0,
+    created_at TEXT DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS grid_levels (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    grid_id INTEGER NOT NULL,
+    buy_price REAL NOT NULL,
+    sell_price REAL NOT NULL,
+    status TEXT NOT NULL, -- IDLE (sotto il prezzo, in attesa), WAITING_BUY, HOLDING
+    base_amount INTEGER DEFAULT 0,
+    last_tx TEXT,
+    updated_at TEXT DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Tabella WITHDRAWAL_ADDRESSES (Whitelist prelievi: conferma Telegram + attesa prima dell'uso)
+CREATE TABLE IF NOT EXISTS withdrawal_addresses (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id TEXT NOT NULL,
+    address TEXT NOT NULL,
+    label TEXT,
+    status TEXT DEFAULT 'PENDING', -- PENDING (in attesa di conferma), CONFIRMED
+    added_at INTEGER, -- Unix, alla conferma: da qui parte il periodo di attesa
+    created_at TEXT DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(user_id, address)
+);
+
+-- Tabella DEPOSITS (Versamenti rilevati on-chain, una riga per TX e mint)
+CREATE TABLE IF NOT EXISTS deposits (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id TEXT NOT NULL,
+    tx_signature TEXT NOT NULL,
+    mint TEXT NOT NULL,
+    amount INTEGER NOT NULL,
+    amount_ui REAL NOT NULL,
+    usd_value REAL,
+    created_at TEXT DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(tx_signature, mint)
+);
+
+-- Tabella SELL_RETRIES (Vendite del Position Manager fallite, una per utente e token)
+-- PENDING = in coda, FAILED = tentativi esauriti (utente avvisato). La riga sparisce alla chiusura delle posizioni.
+CREATE TABLE IF NOT EXISTS sell_retries (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id TEXT NOT NULL,
+    token TEXT NOT NULL,
+    reason TEXT NOT NULL,
+    attempts INTEGER DEFAULT 0,
+    next_attempt_at INTEGER NOT NULL,
+    last_error TEXT,
+    status TEXT DEFAULT 'PENDING',
+    created_at TEXT DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(user_id, token)
+);
+
+-- Tabella LAUNCHES (Lanci visti dallo Sniper: reputazione dei deployer)
+CREATE TABLE IF NOT EXISTS launches (
+    token TEXT PRIMARY KEY,
+    deployer TEXT NOT NULL,
+    pool_sol REAL,
+    rugged INTEGER DEFAULT 0,
+    created_at TEXT DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_launches_deployer ON launches(deployer);
+CREATE UNIQUE INDEX IF NOT EXISTS idx_users_referral_code ON users(referral_code);
@@ -0,0 +1,20 @@
+-- Trade suggeriti in modalità solo segnali: eseguiti solo se approvati entro la validità (signals::PENDING_TTL_MINUTES).
+-- quoted_out = token attesi al momento del segnale: all'approvazione la quote non può essere peggiore oltre lo slippage.
+CREATE TABLE IF NOT EXISTS pending_trades (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id TEXT NOT NULL,
+    token_address TEXT NOT NULL,
+    symbol TEXT NOT NULL,
+    engine TEXT NOT NULL,
+    source TEXT NOT NULL, -- MARKET, SNIPER, COPY
+    score INTEGER NOT NULL,
+    amount_lamports INTEGER NOT NULL,
+    quoted_out INTEGER NOT NULL,
+    slippage_bps INTEGER NOT NULL,
+    status TEXT DEFAULT 'PENDING', -- PENDING, EXECUTING, EXECUTED, FAILED, REJECTED, EXPIRED
+    tx_signature TEXT,
+    created_at TEXT DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Un solo suggerimento in attesa per utente e token
+CREATE UNIQUE INDEX IF NOT EXISTS idx_pending_trades_open ON pending_trades(user_id, token_address) WHERE status = 'PENDING';
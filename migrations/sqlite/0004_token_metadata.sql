@@ -0,0 +1,10 @@
+-- Cache dei metadati token (nome, simbolo, logo): Metaplex on-chain, DexScreener come riserva.
+-- Letta da gemme, storico trade ed export al posto dei segnaposto.
+CREATE TABLE IF NOT EXISTS token_metadata (
+    mint TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    symbol TEXT NOT NULL,
+    logo_uri TEXT,
+    source TEXT NOT NULL, -- metaplex, dexscreener
+    updated_at TEXT DEFAULT CURRENT_TIMESTAMP
+);
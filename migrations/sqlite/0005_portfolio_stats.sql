@@ -0,0 +1,17 @@
+-- Statistiche di portafoglio materializzate per utente (portfolio::replay sui trade SOLD, in ordine di uscita).
+-- Ricalcolate a ogni vendita; la conferma on-chain del PnL (settle_sell) scarta la riga, rifatta alla lettura successiva.
+CREATE TABLE IF NOT EXISTS portfolio_stats (
+    user_id TEXT PRIMARY KEY,
+    closed_trades INTEGER NOT NULL,
+    wins INTEGER NOT NULL,
+    losses INTEGER NOT NULL,
+    total_pnl_sol REAL NOT NULL,
+    gross_profit_sol REAL NOT NULL,
+    gross_loss_sol REAL NOT NULL,
+    best_trade_sol REAL NOT NULL,
+    worst_trade_sol REAL NOT NULL,
+    invested_sol REAL NOT NULL,
+    max_drawdown_sol REAL NOT NULL,
+    current_streak INTEGER NOT NULL, -- > 0 vittorie consecutive, < 0 perdite consecutive
+    updated_at TEXT DEFAULT CURRENT_TIMESTAMP
+);
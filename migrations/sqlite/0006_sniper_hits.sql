@@ -0,0 +1,21 @@
+-- Sniper in dry-run (SNIPER_DRY_RUN=1): ogni lancio rilevato viene registrato senza comprare, anche se scartato dai filtri.
+-- I prezzi a +5m/+30m/+2h li completa sniper_stats::run_followup (0 = nessun prezzo: token morto o senza liquidità).
+CREATE TABLE IF NOT EXISTS sniper_hits (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    token TEXT NOT NULL,
+    symbol TEXT NOT NULL,
+    deployer TEXT NOT NULL,
+    detected_at INTEGER NOT NULL, -- unix
+    is_safe INTEGER NOT NULL,
+    reason TEXT NOT NULL,
+    safety_report TEXT NOT NULL, -- JSON di safety::TokenSafetyReport
+    price REAL NOT NULL,
+    liquidity_usd REAL NOT NULL,
+    market_cap REAL NOT NULL,
+    volume_24h REAL NOT NULL,
+    price_5m REAL,
+    price_30m REAL,
+    price_2h REAL
+);
+
+CREATE INDEX IF NOT EXISTS idx_sniper_hits_detected ON sniper_hits(detected_at);
@@ -0,0 +1,8 @@
+-- Dispositivi delle sessioni Web (GET /auth/sessions): id pubblico per la revoca (il token resta segreto),
+-- user agent e IP del login, ultimo utilizzo. Le sessioni già aperte ricevono un id casuale.
+ALTER TABLE sessions ADD COLUMN device_id TEXT;
+ALTER TABLE sessions ADD COLUMN user_agent TEXT NOT NULL DEFAULT '';
+ALTER TABLE sessions ADD COLUMN ip TEXT NOT NULL DEFAULT '';
+ALTER TABLE sessions ADD COLUMN last_seen INTEGER NOT NULL DEFAULT 0;
+UPDATE sessions SET device_id = lower(hex(randomblob(8))), last_seen = created_at;
+CREATE INDEX IF NOT EXISTS idx_sessions_user ON sessions (user_id);
@@ -0,0 +1,4 @@
+-- Slippage realizzato degli swap confermati: (quoted_out - actual_out) / quoted_out in bps, negativo = fill migliore.
+-- slippage_exceeded = 1 se oltre la tolleranza dello swap (slippage_bps): l'utente riceve un avviso.
+ALTER TABLE executions ADD COLUMN realized_slippage_bps INTEGER;
+ALTER TABLE executions ADD COLUMN slippage_exceeded INTEGER NOT NULL DEFAULT 0;
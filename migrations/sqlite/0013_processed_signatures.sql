@@ -0,0 +1,10 @@
+-- Firme già processate da Sniper e Copy-Trading (prima solo in RAM: perse al riavvio e allo svuotamento della cache).
+-- L'INSERT ... ON CONFLICT DO NOTHING fa da lock: una sola istanza e un solo giro processano ogni firma.
+-- Le righe più vecchie di PROCESSED_SIG_RETENTION_HOURS vengono cancellate dal ciclo dello Sniper.
+CREATE TABLE IF NOT EXISTS processed_signatures (
+    signature TEXT PRIMARY KEY,
+    source TEXT NOT NULL,
+    created_at INTEGER NOT NULL -- unix
+);
+
+CREATE INDEX IF NOT EXISTS idx_processed_signatures_created ON processed_signatures(created_at);
@@ -0,0 +1,4 @@
+-- Saldo EURC del wallet prima dello swap dell'off-ramp, scritto col passaggio a CONVERTING.
+-- Il trasferimento al vault (anche nella ripresa dopo un riavvio) parte solo se lo swap è confermato
+-- e il saldo è salito di amount_eurc rispetto a questo valore: gli EURC già dell'utente non escono mai.
+ALTER TABLE offramp_payouts ADD COLUMN pre_swap_eurc INTEGER;
@@ -0,0 +1,3 @@
+-- risk_days.deactivated = 1 se è stato il Circuit Breaker a spegnere un Auto-Bot in funzione.
+-- Lo stop dell'utente o dell'admin e il ban lo azzerano: il reset di mezzanotte riattiva solo chi ha ancora il flag.
+ALTER TABLE risk_days ADD COLUMN deactivated INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,4 @@
+-- Sessioni Web salvate solo come HMAC del token (auth::session_token_hash), come i link email di 0009:
+-- un dump del DB non contiene sessioni utilizzabili. Le sessioni in chiaro esistenti si chiudono (nuovo login).
+DELETE FROM sessions;
+ALTER TABLE sessions RENAME COLUMN token TO token_hash;
@@ -0,0 +1,77 @@
+use log::warn;
+use serde::{Deserialize, Serialize};
+use crate::db;
+
+// --- ATTIVITÀ DELL'ACCOUNT (Timeline di GET /activity) ---
+// 1. Acquisti, vendite, depositi, prelievi e segnali si leggono dalle loro tabelle (nessuna doppia scrittura)
+// 2. Quello che non ha una tabella propria (avvio/stop Auto-Bot, fill DCA, errori d'esecuzione) finisce in events con record()
+// 3. Paginazione a cursore: next_before = data|chiave dell'ultimo elemento, stabile anche se nel frattempo arrivano eventi nuovi
+
+pub const BOT_START: &str = "BOT_START";
+pub const BOT_STOP: &str = "BOT_STOP";
+pub const DCA: &str = "DCA";
+pub const ERROR: &str = "ERROR";
+
+const DEFAULT_LIMIT: i64 = 50;
+const MAX_LIMIT: i64 = 200;
+
+// Evento da registrare nella tabella events
+pub struct Event<'a> {
+    pub kind: &'static str,
+    pub token: Option<&'a str>,
+    pub amount_sol: Option<f64>,
+    pub message: String,
+    pub tx_signature: Option<&'a str>,
+}
+
+impl<'a> Event<'a> {
+    pub fn bot_start(engine: &str) -> Self {
+        Self { kind: BOT_START, token: None, amount_sol: None, message: format!("Auto-Bot avviato (strategia {})", engine), tx_signature: None }
+    }
+
+    /// `by`: da dove è partito lo stop (Telegram, Dashboard, amministratore)
+    pub fn bot_stop(by: &str) -> Self {
+        Self { kind: BOT_STOP, token: None, amount_sol: None, message: format!("Auto-Bot fermato ({})", by), tx_signature: None }
+    }
+
+    pub fn dca_fill(order_id: i64, token: &'a str, amount_sol: f64, tx_signature: &'a str) -> Self {
+        Self { kind: DCA, token: Some(token), amount_sol: Some(amount_sol), message: format!("DCA #{}", order_id), tx_signature: Some(tx_signature) }
+    }
+
+    pub fn error(token: Option<&'a str>, message: String) -> Self {
+        Self { kind: ERROR, token, amount_sol: None, message, tx_signature: None }
+    }
+}
+
+/// Registra l'evento (un errore di scrittura non blocca il chiamante)
+pub async fn record(pool: &db::DbPool, user_id: &str, event: Event<'_>) {
+    if let Err(e) = db::record_event(pool, user_id, event.kind, event.token, event.amount_sol, &event.message, event.tx_signature).await {
+        warn!("⚠️ Evento {} non registrato per {}: {}", event.kind, user_id, e);
+    }
+}
+
+// GET /activity?before=CURSORE&limit=
+#[derive(Deserialize)]
+pub struct ActivityQuery {
+    pub before: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct ActivityPage {
+    pub items: Vec<db::ActivityItem>,
+    // Cursore della pagina successiva (null = ultima pagina)
+    pub next_before: Option<String>,
+}
+
+/// Pagina della timeline dell'utente, dalla più recente
+pub async fn feed(pool: &db::DbPool, user_id: &str, q: &ActivityQuery) -> Result<ActivityPage, String> {
+    let limit = q.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    let before = match q.before.as_deref() {
+        Some(c) => Some(c.split_once('|').ok_or("Cursore non valido")?),
+        None => None,
+    };
+    let items = db::get_activity(pool, user_id, before, limit).await.map_err(|e| format!("Errore Database: {}", e))?;
+    let next_before = if items.len() as i64 == limit { items.last().map(|i| format!("{}|{}", i.created_at, i.key)) } else { None };
+    Ok(ActivityPage { items, next_before })
+}
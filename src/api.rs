@@ -1,22 +1,32 @@
 use warp::Filter;
 use warp::reply::Response;
+use warp::Reply;
 use warp::http::StatusCode;
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 use serde::{Serialize, Deserialize};
 use serde_json::json;
-use crate::{db, network, wallet_manager, raydium, jupiter, AppState, GemData};
+use crate::{activity, auto_park, auto_skim, db, network, wallet_manager, jupiter, backtest, rate_limit, referral, copy_trade, swap_router, grid, withdrawals, offramp, gems, scoring, price_oracle, export, dust, safety, birdeye, strategy, whale, telegram_bot, risk, i18n, signals, metadata, reconcile, portfolio, sniper_stats, token_icons, trade_alerts, trade_notes, AppState, GemData, LiveEvent, OpenPosition};
+use crate::rate_limit::ApiLimits;
+use crate::token_filter::{self, TokenFilter};
 use solana_sdk::native_token::LAMPORTS_PER_SOL;
 use solana_sdk::pubkey::Pubkey;
-use solana_sdk::system_instruction;
-use solana_sdk::transaction::Transaction;
-use solana_sdk::signer::Signer;
+use solana_sdk::signature::Signer;
 use std::str::FromStr;
 use log::{info, error};
+use futures::{SinkExt, StreamExt};
+use tokio::sync::broadcast;
+use warp::ws::{Message, WebSocket};
 
 // --- DATI ---
 #[derive(Serialize, Clone)]
 pub struct SignalData {
-    pub token: String, pub price: f64, pub score: u8, pub reason: String, pub timestamp: i64,
+    pub token: String, pub mode: String, pub price: f64, pub score: u8, pub reason: String, pub timestamp: i64,
+}
+
+impl From<db::SignalRecord> for SignalData {
+    fn from(r: db::SignalRecord) -> Self {
+        SignalData { token: r.token, mode: r.mode, price: r.price, score: r.score, reason: r.reason, timestamp: r.timestamp }
+    }
 }
 
 #[derive(Serialize)]
@@ -25,26 +35,428 @@ struct DashboardData {
     balance_sol: f64,
     active_trades_count: usize,
     system_status: String,
+    // null = prezzo SOL non disponibile (oracolo senza fonti fresche)
+    sol_price_usd: Option<f64>,
+    open_positions: Vec<PositionStatus>,
+    // Saldo, valore live bloccato nei trade aperti e SOL disponibili per nuovi acquisti (null = DB non raggiungibile)
+    funds: Option<risk::Funds>,
+    // Token nel wallet senza trade registrato (Reconciler, aggiornati ogni 15 minuti)
+    untracked_holdings: Vec<reconcile::UntrackedHolding>,
+    // Cooldown per token e acquisti automatici dell'ultima ora (null = DB non raggiungibile)
+    trade_frequency: Option<risk::TradeFrequency>,
+    // Congestione di rete: priority fee p50/p75/p90 (microlamports per CU), null se l'RPC non risponde
+    priority_fees: Option<network::FeeEstimate>,
     gems_feed: Vec<GemData>,       
     signals_feed: Vec<SignalData>, 
 }
 
 #[derive(Deserialize)]
-struct TradeRequest { action: String, token: String, amount_sol: f64 }
+struct SniperStatsQuery { days: Option<i64> }
+
+// GET /stats: statistiche materializzate + indicatori derivati (profit_factor null senza perdite)
+#[derive(Serialize)]
+struct StatsResponse {
+    #[serde(flatten)]
+    stats: db::PortfolioStats,
+    win_rate_pct: f64,
+    roi_pct: f64,
+    profit_factor: Option<f64>,
+}
+
+// Posizione aperta con il tempo rimasto prima della chiusura per durata massima (null = nessun limite)
+#[derive(Serialize)]
+struct PositionStatus {
+    #[serde(flatten)]
+    position: OpenPosition,
+    hold_remaining_secs: Option<i64>,
+}
+
+// SELL parziale: `percent` (1-100) del saldo oppure `token_amount` (unità minime); nessuno dei due = vende tutto
+#[derive(Deserialize)]
+struct TradeRequest {
+    action: String,
+    token: String,
+    #[serde(default)]
+    amount_sol: f64,
+    percent: Option<f64>,
+    token_amount: Option<u64>,
+}
+
+// Importi in unità base dei token. max_in (solo exactOut): input massimo accettato, slippage incluso
+#[derive(Deserialize)]
+struct ConvertRequest {
+    input_mint: String,
+    output_mint: String,
+    amount: u64,
+    #[serde(default = "default_convert_mode")]
+    mode: String,
+    max_in: Option<u64>,
+}
+
+fn default_convert_mode() -> String { "exactIn".into() }
+
+#[derive(Deserialize)]
+struct SubmitRequest { signed_tx: String }
+
+// Wallet esterno (non-custodial): null = torna al wallet custodito dal bot
+#[derive(Deserialize)]
+struct ExternalWalletRequest { external_wallet: Option<String> }
+
+// Risposta /trade in modalità non-custodial: TX da firmare nel wallet dell'utente
+#[derive(Serialize)]
+struct UnsignedTradeResponse { success: bool, message: String, unsigned_tx: String, route: String, expected_out: u64 }
 
 #[derive(Deserialize)]
 struct WithdrawRequest { amount: f64, token: String, destination_address: String }
 
+// Bonifico SEPA: importo in EUR, convertito da SOL in EURC
+#[derive(Deserialize)]
+struct OfframpRequest { amount_eur: f64, iban: String, beneficiary: String }
+
+#[derive(Deserialize)]
+struct WithdrawAddressRequest { action: String, address: String, label: Option<String> }
+
+#[derive(Serialize)]
+struct WithdrawAddressData {
+    #[serde(flatten)]
+    address: db::WithdrawalAddress,
+    usable_from: Option<i64>,
+}
+
+// I browser non possono inviare header custom sul WebSocket: sessione via query (?token=)
+#[derive(Deserialize)]
+struct WsQuery { token: Option<String>, user_id: Option<String> }
+
+#[derive(Deserialize)]
+struct WebhookRequest { action: String, url: Option<String>, id: Option<i64> }
+
+#[derive(Deserialize)]
+struct LanguageRequest { language: String }
+
+#[derive(Deserialize)]
+struct AlertsRequest { level: String }
+
+// Auto-Park: campi assenti = invariati, release = sblocca la quota USDC parcheggiata
+#[derive(Deserialize)]
+struct ParkRequest { enabled: Option<bool>, percent: Option<f64>, #[serde(default)] release: bool }
+
+// Auto-Skim: enabled=false lo spegne, altrimenti servono period (weekly|monthly), floor_sol e destination in whitelist
+#[derive(Deserialize)]
+struct SkimRequest { enabled: bool, period: Option<String>, floor_sol: Option<f64>, destination: Option<String> }
+
+#[derive(Deserialize)]
+struct ReferralRequest { code: String }
+
+#[derive(Deserialize)]
+struct FollowRequest { action: String, wallet: String }
+
+#[derive(Deserialize)]
+struct BotStartRequest { strategy: Option<String>, grid: Option<grid::GridConfig>, universe: Option<Vec<String>> }
+
+#[derive(Deserialize)]
+struct LogLevelRequest { filter: String }
+
+#[derive(Deserialize)]
+struct ExecutionsQuery { limit: Option<i64> }
+
+// GET /signals?since=UNIX&before=ID&limit=: since default ultime 24h, before = next_before della pagina precedente
+#[derive(Deserialize)]
+struct SignalsQuery { since: Option<i64>, before: Option<i64>, limit: Option<i64> }
+
+#[derive(Serialize)]
+struct SignalsPage {
+    signals: Vec<db::SignalRecord>,
+    // Cursore della pagina successiva (null = ultima pagina)
+    next_before: Option<i64>,
+}
+
+// REGISTER: `init_data` della Telegram Web App (firmato dal bot) prova che il Telegram ID è del chiamante
+#[derive(Deserialize)]
+struct AuthRequest { action: String, email: String, password: String, init_data: Option<String> }
+
+#[derive(Deserialize)]
+struct EmailTokenRequest { token: String }
+
+#[derive(Deserialize)]
+struct ForgotPasswordRequest { email: String }
+
+#[derive(Deserialize)]
+struct ResetPasswordRequest { token: String, password: String }
+
+// Scopi dei token email (tabella email_tokens)
+const EMAIL_VERIFY: &str = "VERIFY";
+const PASSWORD_RESET: &str = "RESET";
+const MIN_PASSWORD_LEN: usize = 8;
+
+// User agent salvato con la sessione (troncato)
+const MAX_USER_AGENT_LEN: usize = 256;
+
+#[derive(Serialize)]
+struct AuthResponse { success: bool, message: String, token: String, user_id: String }
+
+// Rifiuto per sessione mancante/non valida (-> 401)
+#[derive(Debug)]
+struct Unauthorized;
+impl warp::reject::Reject for Unauthorized {}
+
+// Rifiuto per utente bannato dall'Admin (-> 403)
+#[derive(Debug)]
+struct Banned;
+impl warp::reject::Reject for Banned {}
+
+// Rifiuto per troppe richieste (-> 429)
+#[derive(Debug)]
+struct RateLimited;
+impl warp::reject::Reject for RateLimited {}
+
+#[derive(Deserialize)]
+struct WatchlistRequest { action: String, token: String }
+
+#[derive(Serialize)]
+struct WatchlistData { tokens: Vec<String>, is_default: bool, prices: std::collections::HashMap<String, f64> }
+
+#[derive(Deserialize)]
+struct FilterRequest { action: String, list: String, value: String }
+
+#[derive(Deserialize)]
+struct FilterModeRequest { whitelist_only: bool }
+
+#[derive(Deserialize)]
+struct DcaRequest { token: String, amount_sol: f64, interval_hours: u64 }
+
+// BUY: amount_sol da spendere. SELL: vende tutto il token (amount_sol ignorato)
+#[derive(Deserialize)]
+struct LimitRequest { side: String, token: String, trigger_price: f64, #[serde(default)] amount_sol: f64 }
+
+#[derive(Serialize, Default)]
+struct TokenPnl {
+    token: String,
+    open_trades: usize,
+    closed_trades: i64,
+    invested_open_sol: f64,
+    current_value_sol: f64,
+    unrealized_pnl_sol: f64,
+    realized_pnl_sol: f64,
+}
+
+#[derive(Serialize)]
+struct PortfolioData {
+    sol_balance: f64,
+    equity_sol: f64,
+    // Stimato: saldo + capitale nei trade aperti + prelievi - PnL realizzato
+    net_deposited_sol: f64,
+    // Depositi rilevati on-chain (tabella deposits): SOL versati e valore USD al momento del versamento
+    deposited_sol: f64,
+    deposited_usd: f64,
+    realized_pnl_sol: f64,
+    unrealized_pnl_sol: f64,
+    total_pnl_sol: f64,
+    tokens: Vec<TokenPnl>,
+}
+
+#[derive(Deserialize)]
+struct TokenQuery { interval: Option<String> }
+
+// from/to: YYYY-MM-DD (inclusi), format: csv (default) | json, tag: solo i trade con quel tag
+#[derive(Deserialize)]
+struct ExportQuery { format: Option<String>, from: Option<String>, to: Option<String>, tag: Option<String> }
+
+#[derive(Deserialize)]
+struct TaxQuery { year: Option<i32> }
+
+// Analisi tecnica sulle candele Birdeye con la strategia dell'utente
+#[derive(Serialize)]
+struct TokenAnalysis {
+    engine: String,
+    interval: String,
+    candles: usize,
+    indicators: Option<strategy::Indicators>,
+    whale_pressure: f64,
+    signal: String,
+}
+
+// GET /debug/analysis/{mint}: perché il bot è entrato o no sul token, con i parametri dell'utente
+#[derive(Serialize)]
+struct DebugAnalysis {
+    mint: String,
+    // LIVE = candele della Market Strategy, BIRDEYE = scaricate ora (token fuori dall'universo osservato)
+    source: &'static str,
+    market: Option<jupiter::TokenMarketData>,
+    // Punteggio dai soli dati di mercato (come nella Market Strategy)
+    score: Option<scoring::TokenScore>,
+    // Soglie di liquidità e volume dell'utente, valutate prima della strategia
+    filters: Vec<strategy::EntryCheck>,
+    analysis: strategy::MarketAnalysis,
+}
+
+// Scheda completa del token (ogni sezione è null se la fonte non risponde)
+#[derive(Serialize)]
+struct TokenReport {
+    mint: String,
+    metadata: db::TokenMetadata,
+    market: Option<jupiter::TokenMarketData>,
+    safety: Option<safety::TokenSafetyReport>,
+    security: Option<birdeye::TokenSecurity>,
+    holders: Option<safety::HolderConcentration>,
+    // Punteggio con sotto-componenti (mercato, crescita holder/wallet, social); null senza dati di mercato
+    score: Option<scoring::TokenScore>,
+    analysis: Option<TokenAnalysis>,
+}
+
 #[derive(Serialize)]
 struct ApiResponse { success: bool, message: String, tx_signature: String }
 
+// GET /admin/stats
+#[derive(Serialize)]
+struct AdminStats {
+    #[serde(flatten)]
+    totals: db::AdminTotals,
+    tracked_positions: usize,
+    failures: crate::metrics::FailureCounters,
+}
+
+// Riga di GET /admin/users: saldo SOL null se l'RPC non risponde
+#[derive(Serialize)]
+struct AdminUserData {
+    #[serde(flatten)]
+    user: db::AdminUser,
+    balance_sol: Option<f64>,
+}
+
 // --- SERVER ---
-pub async fn start_server(pool: sqlx::SqlitePool, net: Arc<network::NetworkClient>, state: Arc<AppState>) {
+pub async fn start_server(pool: db::DbPool, net: Arc<network::NetworkClient>, state: Arc<AppState>) {
+    let closing = state.clone();
     let pf = warp::any().map(move || pool.clone());
     let nf = warp::any().map(move || net.clone());
     let sf = warp::any().map(move || state.clone());
+    let limits = Arc::new(ApiLimits::from_env());
+    let lf = warp::any().map(move || limits.clone());
+
+    // IP del client (X-Forwarded-For solo dietro un proxy fidato: TRUST_PROXY=1)
+    let client_ip = warp::header::optional::<String>("x-forwarded-for")
+        .and(warp::addr::remote())
+        .map(resolve_client_ip);
+
+    // Limite per IP su tutte le rotte
+    let ip_guard = client_ip
+        .and(lf.clone())
+        .and_then(|ip: String, limits: Arc<ApiLimits>| async move { rate_check(&limits.ip, &ip) })
+        .untuple_one();
+
+    // Utente autenticato: sessione Bearer o initData firmato della Web App (header legacy x-user-id solo in lettura e con REQUIRE_SESSION=0), poi limite per utente
+    let user = warp::method()
+        .and(warp::header::optional::<String>("authorization"))
+        .and(warp::header::optional::<String>("x-user-id"))
+        .and(client_ip)
+        .and(pf.clone())
+        .and_then(extract_user_id)
+        .and(pf.clone())
+        .and_then(reject_banned)
+        .and(lf.clone())
+        .and_then(|user_id: String, limits: Arc<ApiLimits>| async move {
+            // Correlazione log: l'utente entra nello span della richiesta
+            tracing::Span::current().record("user_id", user_id.as_str());
+            rate_check(&limits.user, &user_id).map(|_| user_id)
+        });
+
+    // Lingua delle risposte /auth senza utente (Accept-Language, altrimenti DEFAULT_LANGUAGE)
+    let caller_lang = warp::header::optional::<String>("accept-language")
+        .map(|h: Option<String>| i18n::request_lang(h.as_deref()));
+
+    // /auth: limite stretto per IP (anti brute-force password)
+    let auth_guard = client_ip
+        .and(lf.clone())
+        .and_then(|ip: String, limits: Arc<ApiLimits>| async move { rate_check(&limits.auth, &ip) })
+        .untuple_one();
+
+    let auth = warp::path("auth")
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(auth_guard.clone())
+        .and(warp::body::json())
+        .and(client_ip)
+        .and(warp::header::optional::<String>("user-agent"))
+        .and(caller_lang)
+        .and(pf.clone())
+        .and_then(handle_auth);
+
+    // POST /auth/verify {token}: conferma l'email dal link ricevuto
+    let auth_verify = warp::path!("auth" / "verify")
+        .and(warp::post())
+        .and(auth_guard.clone())
+        .and(warp::body::json())
+        .and(caller_lang)
+        .and(pf.clone())
+        .and_then(handle_verify_email);
+
+    // POST /auth/verify/resend: nuovo link di verifica all'email dell'utente
+    let auth_verify_resend = warp::path!("auth" / "verify" / "resend")
+        .and(warp::post())
+        .and(auth_guard.clone())
+        .and(user.clone())
+        .and(pf.clone())
+        .and_then(handle_resend_verification);
 
-    let user = warp::header::header::<String>("x-user-id");
+    // POST /auth/forgot {email}: link di reset (stessa risposta che l'account esista o no)
+    let auth_forgot = warp::path!("auth" / "forgot")
+        .and(warp::post())
+        .and(auth_guard.clone())
+        .and(warp::body::json())
+        .and(caller_lang)
+        .and(pf.clone())
+        .and_then(handle_forgot_password);
+
+    // POST /auth/reset {token, password}: nuova password, tutte le sessioni chiuse
+    let auth_reset = warp::path!("auth" / "reset")
+        .and(warp::post())
+        .and(auth_guard.clone())
+        .and(warp::body::json())
+        .and(caller_lang)
+        .and(pf.clone())
+        .and_then(handle_reset_password);
+
+    // Token Bearer della richiesta (None con initData o header legacy)
+    let bearer = warp::header::optional::<String>("authorization")
+        .map(|h: Option<String>| h.as_deref().and_then(|h| h.strip_prefix("Bearer ")).map(|t| t.trim().to_string()));
+
+    // POST /auth/refresh: token nuovo al posto di quello (ancora valido) della richiesta, scadenza spostata
+    let auth_refresh = warp::path!("auth" / "refresh")
+        .and(warp::post())
+        .and(bearer)
+        .and(caller_lang)
+        .and(pf.clone())
+        .and_then(handle_auth_refresh);
+
+    // POST /auth/logout: chiude la sessione della richiesta
+    let auth_logout = warp::path!("auth" / "logout")
+        .and(warp::post())
+        .and(bearer)
+        .and(caller_lang)
+        .and(pf.clone())
+        .and_then(handle_auth_logout);
+
+    // Elenco e revoca dispositivi: mai con l'header legacy x-user-id, serve una sessione o initData firmato
+    let signed = warp::header::optional::<String>("authorization")
+        .and_then(|h: Option<String>| async move {
+            if h.is_some() { Ok(()) } else { Err(warp::reject::custom(Unauthorized)) }
+        })
+        .untuple_one();
+
+    // GET /auth/sessions: dispositivi collegati | DELETE /auth/sessions/{device_id}: revoca
+    let sessions_get = warp::path!("auth" / "sessions")
+        .and(warp::get())
+        .and(signed)
+        .and(user.clone())
+        .and(bearer)
+        .and(pf.clone())
+        .and_then(handle_sessions);
+
+    let session_revoke = warp::path!("auth" / "sessions" / String)
+        .and(warp::delete())
+        .and(signed)
+        .and(user.clone())
+        .and(pf.clone())
+        .and_then(handle_session_revoke);
 
     let status = warp::path("status")
         .and(warp::get())
@@ -54,15 +466,34 @@ pub async fn start_server(pool: sqlx::SqlitePool, net: Arc<network::NetworkClien
         .and(sf.clone())
         .and_then(handle_status);
 
+    // Stato delle dipendenze per load balancer e monitor (senza autenticazione): 503 se DB o RPC sono giù
+    let health = warp::path("health")
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(sf.clone())
+        .and_then(handle_health);
+
     let trade = warp::path("trade")
         .and(warp::post())
         .and(user.clone())
         .and(warp::body::json())
         .and(pf.clone())
         .and(nf.clone())
+        .and(sf.clone())
         .and_then(handle_trade);
 
+    // Conversione tra due token qualsiasi: mode exactIn (amount speso) | exactOut (amount ricevuto esatto)
+    let convert = warp::path("convert")
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(user.clone())
+        .and(warp::body::json())
+        .and(pf.clone())
+        .and(nf.clone())
+        .and_then(handle_convert);
+
     let withdraw = warp::path("withdraw")
+        .and(warp::path::end())
         .and(warp::post())
         .and(user.clone())
         .and(warp::body::json())
@@ -70,137 +501,1925 @@ pub async fn start_server(pool: sqlx::SqlitePool, net: Arc<network::NetworkClien
         .and(nf.clone())
         .and_then(handle_withdraw);
 
-    let cors = warp::cors()
-        .allow_origin("https://god-sniper-pro.netlify.app")
-        .allow_methods(vec!["GET", "POST"])
-        .allow_headers(vec!["content-type", "x-user-id"]);
-    let routes = status.or(trade).or(withdraw).with(cors);
-    
-    info!("🌍 API Server: Ready (Port 3000)");
-    warp::serve(routes).run(([0, 0, 0, 0], 3000)).await;
-}
-
-// --- HANDLERS ---
+    let withdraw_addresses_get = warp::path!("withdraw" / "addresses")
+        .and(warp::get())
+        .and(user.clone())
+        .and(pf.clone())
+        .and_then(handle_withdraw_addresses_get);
 
-async fn handle_status(user_id: String, pool: sqlx::SqlitePool, net: Arc<network::NetworkClient>, state: Arc<AppState>) -> Result<Response, warp::Rejection> {
-    let pubkey_str = match wallet_manager::create_user_wallet(&pool, &user_id).await {
-        Ok(pk) => pk,
-        Err(e) => {
-            error!("wallet creation failed for {}: {}", user_id, e);
-            let body = json!({
-                "success": false,
-                "message": "WALLET_INIT_FAILED"
-            });
-            return Ok(warp::reply::with_status(warp::reply::json(&body), StatusCode::INTERNAL_SERVER_ERROR).into_response());
-        }
-    };
-    
-    let mut balance = 0.0;
-    if let Ok(pk) = Pubkey::from_str(&pubkey_str) {
-        balance = net.get_balance_fast(&pk).await as f64 / LAMPORTS_PER_SOL as f64;
-    }
+    let withdraw_addresses_post = warp::path!("withdraw" / "addresses")
+        .and(warp::post())
+        .and(user.clone())
+        .and(warp::body::json())
+        .and(pf.clone())
+        .and_then(handle_withdraw_addresses_update);
 
-    let gems = state.found_gems.lock().unwrap().clone();
-    let signals = state.math_signals.lock().unwrap().clone(); 
-    
-    // Conteggio reale posizioni aperte
-    let active_trades = match db::count_open_trades(&pool, &user_id).await { Ok(c) => c, Err(_) => 0 };
-    
-    Ok(warp::reply::json(&DashboardData {
-        wallet_address: pubkey_str,
-        balance_sol: balance,
-        active_trades_count: active_trades, 
-        system_status: "ONLINE".to_string(),
-        gems_feed: gems,
-        signals_feed: signals,
-    }).into_response())
-}
+    // Off-ramp SEPA: POST crea la richiesta (approvazione su Telegram), GET elenca le richieste con il loro stato
+    let offramp_post = warp::path("offramp")
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(user.clone())
+        .and(warp::body::json())
+        .and(pf.clone())
+        .and(nf.clone())
+        .and_then(handle_offramp);
 
-async fn handle_trade(user_id: String, req: TradeRequest, pool: sqlx::SqlitePool, net: Arc<network::NetworkClient>) -> Result<Response, warp::Rejection> {
-    info!("📨 Trade Request [{}]: {} {} SOL -> {}", user_id, req.action, req.amount_sol, req.token);
+    let offramp_get = warp::path("offramp")
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(user.clone())
+        .and(pf.clone())
+        .and_then(handle_offramp_list);
 
-    let payer = match wallet_manager::get_decrypted_wallet(&pool, &user_id).await {
-        Ok(k) => k,
-        Err(_) => {
-            return Ok(warp::reply::json(&ApiResponse { success: false, message: "Wallet Error".into(), tx_signature: "".into() }).into_response());
-        }
-    };
+    let watchlist_get = warp::path("watchlist")
+        .and(warp::get())
+        .and(user.clone())
+        .and(pf.clone())
+        .and(sf.clone())
+        .and_then(handle_watchlist_get);
 
-    let bal = net.get_balance_fast(&payer.pubkey()).await;
-    let amount_lamports = (req.amount_sol * LAMPORTS_PER_SOL as f64) as u64;
+    let watchlist_post = warp::path("watchlist")
+        .and(warp::post())
+        .and(user.clone())
+        .and(warp::body::json())
+        .and(pf.clone())
+        .and_then(handle_watchlist_update);
 
-    if req.action == "BUY" {
-        if bal < (amount_lamports + 5000) {
-            return Ok(warp::reply::json(&ApiResponse { success: false, message: "Fondi Insufficienti".into(), tx_signature: "".into() }).into_response());
-        }
-        
-        // JUPITER SWAP (Priority)
-        let input = "So11111111111111111111111111111111111111112"; // SOL
-        match jupiter::get_jupiter_swap_tx(&payer.pubkey().to_string(), input, &req.token, amount_lamports, 100).await {
-            Ok(mut tx) => {
-                let bh = net.rpc.get_latest_blockhash().await.unwrap();
-                tx.sign(&[&payer], bh);
-                if let Ok(sig) = net.rpc.send_transaction(&tx).await {
-                    let _ = db::record_buy(&pool, &user_id, &req.token, &sig.to_string(), amount_lamports).await;
-                    return Ok(warp::reply::json(&ApiResponse { success: true, message: "Buy Eseguito (Jupiter)".into(), tx_signature: sig.to_string() }).into_response());
-                }
-            },
-            Err(_) => {
-                // RAYDIUM FALLBACK
-                if let Ok(mint) = Pubkey::from_str(&req.token) {
-                     if let Ok(keys) = raydium::fetch_pool_keys_by_mint(&net, &mint).await {
-                         if let Ok(sig) = raydium::execute_swap(&net, &payer, &keys, mint, amount_lamports, 0).await {
-                             let _ = db::record_buy(&pool, &user_id, &req.token, &sig, amount_lamports).await;
-                             return Ok(warp::reply::json(&ApiResponse { success: true, message: "Buy Eseguito (Raydium)".into(), tx_signature: sig }).into_response());
-                         }
-                     }
-                }
-            }
-        }
-    } else if req.action == "SELL" {
-        // Logica di vendita (Per ora placeholder, ma sicura)
-        return Ok(warp::reply::json(&ApiResponse { success: false, message: "Funzione Sell Manuale in arrivo. Usa Jupiter DApp per vendere ora.".into(), tx_signature: "".into() }).into_response());
-    }
-    
-    Ok(warp::reply::json(&ApiResponse { success: false, message: "Errore generico".into(), tx_signature: "".into() }).into_response())
-}
+    let filters_get = warp::path("filters")
+        .and(warp::get())
+        .and(user.clone())
+        .and(pf.clone())
+        .and_then(handle_filters_get);
 
-async fn handle_withdraw(user_id: String, req: WithdrawRequest, pool: sqlx::SqlitePool, net: Arc<network::NetworkClient>) -> Result<Response, warp::Rejection> {
-    
-    // 1. Sicurezza: Solo SOL
-    if req.token != "SOL" {
-         return Ok(warp::reply::json(&ApiResponse { success: false, message: "Per sicurezza, preleva solo SOL. Converti gli altri token prima.".into(), tx_signature: "".into() }).into_response());
-    }
+    let filters_post = warp::path("filters")
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(user.clone())
+        .and(warp::body::json())
+        .and(pf.clone())
+        .and_then(handle_filters_update);
 
-    // 2. Check Blocco 24h
-    if let Ok((allowed, msg)) = db::can_withdraw(&pool, &user_id).await {
-        if !allowed { return Ok(warp::reply::json(&ApiResponse { success: false, message: msg, tx_signature: "".into() }).into_response()); }
-    }
+    let filters_mode = warp::path!("filters" / "mode")
+        .and(warp::post())
+        .and(user.clone())
+        .and(warp::body::json())
+        .and(pf.clone())
+        .and_then(handle_filters_mode);
 
-    let payer = match wallet_manager::get_decrypted_wallet(&pool, &user_id).await {
-        Ok(p) => p,
-        Err(_) => return Ok(warp::reply::json(&ApiResponse { success: false, message: "Wallet Error".into(), tx_signature: "".into() }).into_response()),
-    };
-    let bal = net.get_balance_fast(&payer.pubkey()).await;
-    let amount = (req.amount * LAMPORTS_PER_SOL as f64) as u64;
+    let executions = warp::path("executions")
+        .and(warp::get())
+        .and(user.clone())
+        .and(warp::query::<ExecutionsQuery>())
+        .and(pf.clone())
+        .and_then(handle_executions);
 
-    // 3. Check Fondi
-    if bal < (amount + 5000) { 
-        return Ok(warp::reply::json(&ApiResponse { success: false, message: "Fondi Insufficienti (Lascia 0.005 SOL per le fee)".into(), tx_signature: "".into() }).into_response()); 
-    }
+    let signals_get = warp::path("signals")
+        .and(warp::get())
+        .and(user.clone())
+        .and(warp::query::<SignalsQuery>())
+        .and(pf.clone())
+        .and_then(handle_signals);
 
-    // 4. Esegui
-    if let Ok(dest) = Pubkey::from_str(&req.destination_address) {
-        let ix = system_instruction::transfer(&payer.pubkey(), &dest, amount);
-        let bh = net.rpc.get_latest_blockhash().await.unwrap();
-        let tx = Transaction::new_signed_with_payer(&[ix], Some(&payer.pubkey()), &[&payer], bh);
-        
-        if let Ok(sig) = net.rpc.send_transaction(&tx).await {
-             let _ = db::record_withdrawal_request(&pool, &user_id, amount, &req.destination_address).await;
-             let _ = db::confirm_withdrawal(&pool, 0, &sig.to_string()).await;
-             return Ok(warp::reply::json(&ApiResponse { success: true, message: "Prelievo Inviato!".into(), tx_signature: sig.to_string() }).into_response());
+    let activity_get = warp::path("activity")
+        .and(warp::get())
+        .and(user.clone())
+        .and(warp::query::<activity::ActivityQuery>())
+        .and(pf.clone())
+        .and_then(handle_activity);
+
+    let deposits_get = warp::path("deposits")
+        .and(warp::get())
+        .and(user.clone())
+        .and(warp::query::<ExecutionsQuery>())
+        .and(pf.clone())
+        .and_then(handle_deposits);
+
+    let webhooks_get = warp::path("webhooks")
+        .and(warp::get())
+        .and(user.clone())
+        .and(pf.clone())
+        .and_then(handle_webhooks_get);
+
+    let webhooks_post = warp::path("webhooks")
+        .and(warp::post())
+        .and(user.clone())
+        .and(warp::body::json())
+        .and(pf.clone())
+        .and_then(handle_webhooks_update);
+
+    let referrals_get = warp::path("referrals")
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(user.clone())
+        .and(pf.clone())
+        .and_then(handle_referrals_get);
+
+    let referrals_link = warp::path("referrals")
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(user.clone())
+        .and(warp::body::json())
+        .and(pf.clone())
+        .and_then(handle_referrals_link);
+
+    let referrals_withdraw = warp::path!("referrals" / "withdraw")
+        .and(warp::post())
+        .and(user.clone())
+        .and(pf.clone())
+        .and(nf.clone())
+        .and_then(handle_referrals_withdraw);
+
+    let follows_get = warp::path("follows")
+        .and(warp::get())
+        .and(user.clone())
+        .and(pf.clone())
+        .and_then(handle_follows_get);
+
+    let follows_post = warp::path("follows")
+        .and(warp::post())
+        .and(user.clone())
+        .and(warp::body::json())
+        .and(pf.clone())
+        .and_then(handle_follows_update);
+
+    let backtest = warp::path("backtest")
+        .and(warp::post())
+        .and(user.clone())
+        .and(warp::body::json())
+        .and_then(handle_backtest);
+
+    let ws = warp::path("ws")
+        .and(warp::ws())
+        .and(warp::query::<WsQuery>())
+        .and(client_ip)
+        .and(pf.clone())
+        .and(sf.clone())
+        .and_then(|ws: warp::ws::Ws, q: WsQuery, ip: String, pool: db::DbPool, state: Arc<AppState>| async move {
+            let user_id = extract_user_id(warp::http::Method::GET, q.token.map(|t| format!("Bearer {}", t)), q.user_id, ip, pool.clone()).await?;
+            let user_id = reject_banned(user_id, pool.clone()).await?;
+            Ok::<_, warp::Rejection>(ws.on_upgrade(move |socket| handle_ws(socket, user_id, pool, state)))
+        });
+
+    let submit = warp::path("submit")
+        .and(warp::post())
+        .and(user.clone())
+        .and(warp::body::json())
+        .and(pf.clone())
+        .and(nf.clone())
+        .and_then(handle_submit);
+
+    let wallet_mode = warp::path!("settings" / "wallet")
+        .and(warp::post())
+        .and(user.clone())
+        .and(warp::body::json())
+        .and(pf.clone())
+        .and_then(handle_wallet_mode);
+
+    let language = warp::path!("settings" / "language")
+        .and(warp::post())
+        .and(user.clone())
+        .and(warp::body::json())
+        .and(pf.clone())
+        .and_then(handle_language);
+
+    let alerts = warp::path!("settings" / "alerts")
+        .and(warp::post())
+        .and(user.clone())
+        .and(warp::body::json())
+        .and(pf.clone())
+        .and_then(handle_alerts);
+
+    let park = warp::path!("settings" / "park")
+        .and(warp::post())
+        .and(user.clone())
+        .and(warp::body::json())
+        .and(pf.clone())
+        .and_then(handle_park);
+
+    let skim = warp::path!("settings" / "skim")
+        .and(warp::post())
+        .and(user.clone())
+        .and(warp::body::json())
+        .and(pf.clone())
+        .and_then(handle_skim);
+
+    let strategy_get = warp::path!("settings" / "strategy")
+        .and(warp::get())
+        .and(user.clone())
+        .and(pf.clone())
+        .and_then(handle_strategy_get);
+
+    let strategy_post = warp::path!("settings" / "strategy")
+        .and(warp::post())
+        .and(user.clone())
+        .and(warp::body::json())
+        .and(pf.clone())
+        .and_then(handle_strategy_update);
+
+    let bot_start = warp::path!("bot" / "start")
+        .and(warp::post())
+        .and(user.clone())
+        .and(warp::body::json())
+        .and(pf.clone())
+        .and(nf.clone())
+        .and_then(handle_bot_start);
+
+    let bot_stop = warp::path!("bot" / "stop")
+        .and(warp::post())
+        .and(user.clone())
+        .and(pf.clone())
+        .and(nf.clone())
+        .and_then(handle_bot_stop);
+
+    let grid_get = warp::path("grid")
+        .and(warp::get())
+        .and(user.clone())
+        .and(pf.clone())
+        .and_then(handle_grid_get);
+
+    let dca_get = warp::path("dca")
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(user.clone())
+        .and(pf.clone())
+        .and_then(handle_dca_list);
+
+    let dca_post = warp::path("dca")
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(user.clone())
+        .and(warp::body::json())
+        .and(pf.clone())
+        .and_then(handle_dca_create);
+
+    // POST /dca/{id}/pause | resume | cancel
+    let dca_action = warp::path!("dca" / i64 / String)
+        .and(warp::post())
+        .and(user.clone())
+        .and(pf.clone())
+        .and_then(handle_dca_action);
+
+    let limit_get = warp::path("limit")
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(user.clone())
+        .and(pf.clone())
+        .and_then(handle_limit_list);
+
+    let limit_post = warp::path("limit")
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(user.clone())
+        .and(warp::body::json())
+        .and(pf.clone())
+        .and_then(handle_limit_create);
+
+    let limit_cancel = warp::path!("limit" / i64 / "cancel")
+        .and(warp::post())
+        .and(user.clone())
+        .and(pf.clone())
+        .and_then(handle_limit_cancel);
+
+    // Trade suggeriti in modalità solo segnali: GET /pending, POST /pending/{id}/approve | reject
+    let pending_get = warp::path("pending")
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(user.clone())
+        .and(pf.clone())
+        .and_then(handle_pending_list);
+
+    let pending_approve = warp::path!("pending" / i64 / "approve")
+        .and(warp::post())
+        .and(user.clone())
+        .and(pf.clone())
+        .and(nf.clone())
+        .and_then(handle_pending_approve);
+
+    let pending_reject = warp::path!("pending" / i64 / "reject")
+        .and(warp::post())
+        .and(user.clone())
+        .and(pf.clone())
+        .and_then(handle_pending_reject);
+
+    // PATCH /positions/{id} {stop_loss_pct, take_profit_pct, trailing}
+    let position_patch = warp::path!("positions" / i64)
+        .and(warp::patch())
+        .and(user.clone())
+        .and(warp::body::json())
+        .and(pf.clone())
+        .and(sf.clone())
+        .and_then(handle_position_patch);
+
+    // PATCH /trades/{id}/notes {note, tags}: nota libera e tag del trade
+    let trade_notes_patch = warp::path!("trades" / i64 / "notes")
+        .and(warp::patch())
+        .and(user.clone())
+        .and(warp::body::json())
+        .and(pf.clone())
+        .and_then(handle_trade_notes);
+
+    // GET /gems?sort=volume|score|change_24h|newest&min_liquidity=&source=SNIPER|DISCOVERY|TOP&limit=
+    let gems_get = warp::path("gems")
+        .and(warp::get())
+        .and(warp::query::<gems::GemQuery>())
+        .and(user.clone())
+        .and(pf.clone())
+        .and(sf.clone())
+        .and_then(handle_gems);
+
+    // GET /token/{mint}/icon?size=64: pubblica (la carica un <img>, senza header di autenticazione)
+    let token_icon = warp::path!("token" / String / "icon")
+        .and(warp::get())
+        .and(warp::query::<token_icons::IconQuery>())
+        .and(warp::header::optional::<String>("if-none-match"))
+        .and(pf.clone())
+        .and(nf.clone())
+        .and(sf.clone())
+        .and_then(handle_token_icon);
+
+    let token_report = warp::path!("token" / String)
+        .and(warp::get())
+        .and(warp::query::<TokenQuery>())
+        .and(user.clone())
+        .and(pf.clone())
+        .and(nf.clone())
+        .and(sf.clone())
+        .and_then(handle_token_report);
+
+    let debug_analysis = warp::path!("debug" / "analysis" / String)
+        .and(warp::get())
+        .and(warp::query::<TokenQuery>())
+        .and(user.clone())
+        .and(pf.clone())
+        .and(sf.clone())
+        .and_then(handle_debug_analysis);
+
+    // GET /price/sol: stato dell'oracolo (prezzo mediano, fonti, età del dato)
+    let sol_price = warp::path!("price" / "sol")
+        .and(warp::get())
+        .and(user.clone())
+        .and_then(handle_sol_price);
+
+    let portfolio = warp::path("portfolio")
+        .and(warp::get())
+        .and(user.clone())
+        .and(pf.clone())
+        .and(nf.clone())
+        .and(sf.clone())
+        .and_then(handle_portfolio);
+
+    // Statistiche di portafoglio dallo storico dei trade (win rate, drawdown, serie)
+    let stats = warp::path("stats")
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(user.clone())
+        .and(pf.clone())
+        .and_then(handle_stats);
+
+    // GET /export/trades?format=csv&from=YYYY-MM-DD&to=YYYY-MM-DD&tag=long term
+    let export_trades = warp::path!("export" / "trades")
+        .and(warp::get())
+        .and(warp::query::<ExportQuery>())
+        .and(user.clone())
+        .and(pf.clone())
+        .and_then(handle_export_trades);
+
+    // GET /export/tax?year=2025 (senza anno: tutti gli anni)
+    let export_tax = warp::path!("export" / "tax")
+        .and(warp::get())
+        .and(warp::query::<TaxQuery>())
+        .and(user.clone())
+        .and(pf.clone())
+        .and_then(handle_export_tax);
+
+    // --- ADMIN (header x-admin-token = ADMIN_TOKEN; senza ADMIN_TOKEN le rotte non esistono) ---
+    let admin = warp::header::optional::<String>("x-admin-token")
+        .and_then(check_admin)
+        .untuple_one();
+
+    let admin_stats = warp::path!("admin" / "stats")
+        .and(warp::get())
+        .and(admin)
+        .and(pf.clone())
+        .and(sf.clone())
+        .and_then(handle_admin_stats);
+
+    // Scoreboard dello Sniper in dry-run: GET /sniper/stats?days=7
+    let sniper_stats = warp::path!("sniper" / "stats")
+        .and(warp::get())
+        .and(warp::query::<SniperStatsQuery>())
+        .and(admin)
+        .and(pf.clone())
+        .and_then(handle_sniper_stats);
+
+    let admin_users = warp::path!("admin" / "users")
+        .and(warp::get())
+        .and(admin)
+        .and(pf.clone())
+        .and(nf.clone())
+        .and_then(handle_admin_users);
+
+    // GET /admin/log-level, POST /admin/log-level {"filter": "info,god_sniper=debug"}
+    let admin_log_get = warp::path!("admin" / "log-level")
+        .and(warp::get())
+        .and(admin)
+        .map(|| warp::reply::json(&json!({ "filter": crate::logging::current_filter() })));
+
+    let admin_log_post = warp::path!("admin" / "log-level")
+        .and(warp::post())
+        .and(admin)
+        .and(warp::body::json())
+        .and_then(handle_admin_log_level);
+
+    // GET /admin/gem-filters (soglie attive + scarti per filtro), POST /admin/gem-filters {...}, POST /admin/gem-filters/reload
+    let admin_gem_filters_get = warp::path!("admin" / "gem-filters")
+        .and(warp::get())
+        .and(admin)
+        .map(|| {
+            let rejections: std::collections::HashMap<&str, u64> = crate::gem_filters::rejections().into_iter().collect();
+            warp::reply::json(&json!({ "filters": crate::gem_filters::current(), "rejections": rejections }))
+        });
+
+    let admin_gem_filters_post = warp::path!("admin" / "gem-filters")
+        .and(warp::post())
+        .and(admin)
+        .and(warp::body::json())
+        .map(|filters: crate::gem_filters::GemFilters| gem_filters_reply(crate::gem_filters::set(filters)));
+
+    let admin_gem_filters_reload = warp::path!("admin" / "gem-filters" / "reload")
+        .and(warp::post())
+        .and(admin)
+        .map(|| gem_filters_reply(crate::gem_filters::reload()));
+
+    // POST /admin/users/{id}/stop | ban | unban
+    let admin_user_action = warp::path!("admin" / "users" / String / String)
+        .and(warp::post())
+        .and(admin)
+        .and(pf.clone())
+        .and_then(handle_admin_user_action);
+
+    let cors = warp::cors()
+        .allow_origin("https://god-sniper-pro.netlify.app")
+        .allow_methods(vec!["GET", "POST", "PATCH", "DELETE"])
+        .allow_headers(vec!["content-type", "x-user-id", "authorization", "x-admin-token"]);
+    let routes = ip_guard.and(auth.or(auth_refresh).or(auth_logout).or(auth_verify).or(auth_verify_resend).or(auth_forgot).or(auth_reset).or(sessions_get).or(session_revoke).or(health).or(status).or(trade).or(convert).or(submit).or(wallet_mode).or(language).or(alerts).or(park).or(skim).or(withdraw).or(withdraw_addresses_get).or(withdraw_addresses_post).or(offramp_post).or(offramp_get).or(watchlist_get).or(watchlist_post).or(filters_get).or(filters_post).or(filters_mode).or(executions).or(signals_get).or(activity_get).or(deposits_get).or(webhooks_get).or(webhooks_post).or(referrals_get).or(referrals_link).or(referrals_withdraw).or(follows_get).or(follows_post).or(backtest).or(ws).or(strategy_get).or(strategy_post).or(bot_start).or(bot_stop).or(grid_get).or(dca_get).or(dca_post).or(dca_action).or(limit_get).or(limit_post).or(limit_cancel).or(pending_get).or(pending_approve).or(pending_reject).or(position_patch).or(trade_notes_patch).or(gems_get).or(token_icon).or(token_report).or(debug_analysis).or(sol_price).or(portfolio).or(stats).or(export_trades).or(export_tax).or(admin_stats).or(sniper_stats).or(admin_users).or(admin_user_action).or(admin_log_get).or(admin_log_post).or(admin_gem_filters_get).or(admin_gem_filters_post).or(admin_gem_filters_reload))
+        .recover(handle_rejection)
+        .with(cors)
+        .with(warp::log::custom(|info| {
+            crate::metrics::METRICS.api_latency
+                .with_label_values(&[info.method().as_str(), info.status().as_str()])
+                .observe(info.elapsed().as_secs_f64());
+        }))
+        // Span per richiesta: user_id (dopo l'autenticazione) e trade_id (acquisti manuali) su ogni riga di log
+        .with(warp::trace(|info| tracing::info_span!(
+            "api", method = %info.method(), path = %info.path(), user_id = tracing::field::Empty, trade_id = tracing::field::Empty
+        )));
+    
+    info!("🌍 API Server: Ready (Port 3000)");
+    let (_, server) = warp::serve(routes).bind_with_graceful_shutdown(([0, 0, 0, 0], 3000), async move { closing.shutdown_signal().await });
+    server.await;
+    info!("🛑 API Server fermato.");
+}
+
+// --- AUTENTICAZIONE ---
+
+/// Risolve l'utente della richiesta:
+/// - `Authorization: Bearer <sessione>`: la sessione deve esistere e non essere scaduta
+/// - `Authorization: tma <initData>`: firma HMAC della Telegram Web App verificata col token del bot
+///
+/// L'header x-user-id (falsificabile) è rifiutato di default: vale solo per le GET e solo se REQUIRE_SESSION=0
+/// (vecchio frontend senza login). Le rotte /auth/sessions non lo accettano mai.
+async fn extract_user_id(method: warp::http::Method, auth_header: Option<String>, legacy_user: Option<String>, ip: String, pool: db::DbPool) -> Result<String, warp::Rejection> {
+    if let Some(token) = auth_header.as_deref().and_then(|h| h.strip_prefix("Bearer ")) {
+        // Solo sessioni non scadute e non revocate; l'ultimo utilizzo alimenta l'elenco dispositivi
+        let hash = crate::auth::session_token_hash(token);
+        return match db::get_session_user(&pool, &hash).await {
+            Ok(Some(user_id)) => {
+                let _ = db::touch_session(&pool, &hash, &ip).await;
+                Ok(user_id)
+            },
+            _ => Err(warp::reject::custom(Unauthorized)),
+        };
+    }
+    if let Some(init_data) = auth_header.as_deref().and_then(|h| h.strip_prefix("tma ")) {
+        let bot_token = std::env::var("TELOXIDE_TOKEN").unwrap_or_default();
+        if bot_token.is_empty() { return Err(warp::reject::custom(Unauthorized)); }
+        return crate::auth::verify_telegram_init_data(init_data.trim(), &bot_token, chrono::Utc::now().timestamp())
+            .ok_or_else(|| warp::reject::custom(Unauthorized));
+    }
+
+    let require_session = std::env::var("REQUIRE_SESSION").map(|v| v != "0").unwrap_or(true);
+    match legacy_user {
+        Some(user_id) if !require_session && method == warp::http::Method::GET && !user_id.is_empty() => Ok(user_id),
+        _ => Err(warp::reject::custom(Unauthorized)),
+    }
+}
+
+// L'utente bannato perde l'accesso a tutte le rotte autenticate
+async fn reject_banned(user_id: String, pool: db::DbPool) -> Result<String, warp::Rejection> {
+    match db::is_user_banned(&pool, &user_id).await {
+        Ok(false) => Ok(user_id),
+        _ => Err(warp::reject::custom(Banned)),
+    }
+}
+
+async fn check_admin(token: Option<String>) -> Result<(), warp::Rejection> {
+    let expected = std::env::var("ADMIN_TOKEN").unwrap_or_default();
+    if expected.is_empty() { return Err(warp::reject::not_found()); }
+    match token {
+        Some(t) if crate::auth::verify_admin_token(t.trim(), &expected) => Ok(()),
+        _ => Err(warp::reject::custom(Unauthorized)),
+    }
+}
+
+fn resolve_client_ip(forwarded: Option<String>, remote: Option<std::net::SocketAddr>) -> String {
+    let trust_proxy = std::env::var("TRUST_PROXY").map(|v| v == "1").unwrap_or(false);
+    if trust_proxy {
+        if let Some(ip) = forwarded.as_deref().and_then(|h| h.split(',').next()).map(str::trim).filter(|ip| !ip.is_empty()) {
+            return ip.to_string();
+        }
+    }
+    remote.map(|a| a.ip().to_string()).unwrap_or_else(|| "unknown".into())
+}
+
+fn rate_check(limiter: &rate_limit::RateLimiter, key: &str) -> Result<(), warp::Rejection> {
+    if limiter.check(key) { Ok(()) } else { Err(warp::reject::custom(RateLimited)) }
+}
+
+async fn handle_rejection(err: warp::Rejection) -> Result<Response, warp::Rejection> {
+    if err.find::<RateLimited>().is_some() {
+        let body = ApiResponse { success: false, message: i18n::t(i18n::default_lang(), "api.rate_limited").into(), tx_signature: "".into() };
+        return Ok(warp::reply::with_status(warp::reply::json(&body), StatusCode::TOO_MANY_REQUESTS).into_response());
+    }
+    if err.find::<Banned>().is_some() {
+        let body = ApiResponse { success: false, message: i18n::t(i18n::default_lang(), "api.banned").into(), tx_signature: "".into() };
+        return Ok(warp::reply::with_status(warp::reply::json(&body), StatusCode::FORBIDDEN).into_response());
+    }
+    if err.find::<Unauthorized>().is_some() {
+        let body = ApiResponse { success: false, message: i18n::t(i18n::default_lang(), "api.unauthorized").into(), tx_signature: "".into() };
+        return Ok(warp::reply::with_status(warp::reply::json(&body), StatusCode::UNAUTHORIZED).into_response());
+    }
+    Err(err)
+}
+
+// Errore nella lingua dell'utente (users.settings["language"])
+async fn api_fail(pool: &db::DbPool, user_id: &str, key: &str) -> Response {
+    let lang = i18n::user_lang(pool, user_id).await;
+    warp::reply::json(&ApiResponse { success: false, message: i18n::t(lang, key).into(), tx_signature: "".into() }).into_response()
+}
+
+fn auth_error(message: &str) -> Response {
+    warp::reply::json(&AuthResponse { success: false, message: message.into(), token: "".into(), user_id: "".into() }).into_response()
+}
+
+// REGISTER collega email/password a un utente (Telegram ID), LOGIN apre una sessione
+async fn handle_auth(req: AuthRequest, ip: String, user_agent: Option<String>, lang: i18n::Lang, pool: db::DbPool) -> Result<Response, warp::Rejection> {
+    let email = req.email.trim().to_lowercase();
+    if !crate::auth::valid_email(&email) || req.password.len() < MIN_PASSWORD_LEN {
+        return Ok(auth_error(i18n::t(lang, "api.invalid_credentials_format")));
+    }
+
+    let user_id = match req.action.as_str() {
+        "REGISTER" => {
+            // Il Telegram ID arriva solo da initData verificato: un ID scritto dal client porterebbe via il wallet altrui
+            let bot_token = std::env::var("TELOXIDE_TOKEN").unwrap_or_default();
+            let verified = req.init_data.as_deref()
+                .filter(|_| !bot_token.is_empty())
+                .and_then(|d| crate::auth::verify_telegram_init_data(d.trim(), &bot_token, chrono::Utc::now().timestamp()));
+            let Some(user_id) = verified else { return Ok(auth_error(i18n::t(lang, "api.register_telegram_only"))) };
+            if let Ok(Some(_)) = db::get_user_by_email(&pool, &email).await {
+                return Ok(auth_error(i18n::t(lang, "api.email_taken")));
+            }
+            if wallet_manager::create_user_wallet(&pool, &user_id).await.is_err() {
+                return Ok(auth_error("WALLET_INIT_FAILED"));
+            }
+            let hash = match crate::auth::hash_password(&req.password) {
+                Ok(h) => h,
+                Err(e) => { error!("{}", e); return Ok(auth_error(i18n::t(lang, "api.internal_error"))); }
+            };
+            match db::set_user_credentials(&pool, &user_id, &email, &hash).await {
+                Ok(true) => {
+                    send_verification_email(&pool, &user_id, &email).await;
+                    user_id
+                },
+                Ok(false) => return Ok(auth_error(i18n::t(lang, "api.already_registered"))),
+                Err(e) => { error!("register failed for {}: {}", user_id, e); return Ok(auth_error(i18n::t(lang, "api.db_error"))); }
+            }
+        },
+        "LOGIN" => match db::get_user_by_email(&pool, &email).await {
+            Ok(Some((user_id, hash))) if crate::auth::verify_password(&req.password, &hash) => user_id,
+            _ => return Ok(auth_error(i18n::t(lang, "api.invalid_credentials"))),
+        },
+        _ => return Ok(auth_error(i18n::t(lang, "api.invalid_action_auth"))),
+    };
+
+    db::purge_expired_sessions(&pool).await;
+    let token = crate::auth::new_session_token();
+    let user_agent: String = user_agent.unwrap_or_default().chars().take(MAX_USER_AGENT_LEN).collect();
+    if let Err(e) = db::create_session(&pool, &user_id, &crate::auth::session_token_hash(&token), crate::auth::SESSION_TTL_HOURS, &crate::auth::new_device_id(), &user_agent, &ip).await {
+        error!("session creation failed for {}: {}", user_id, e);
+        return Ok(auth_error(i18n::t(lang, "api.db_error")));
+    }
+
+    info!("🔐 Login Web [{}]", user_id);
+    Ok(warp::reply::json(&AuthResponse { success: true, message: i18n::t(lang, "api.authenticated").into(), token, user_id }).into_response())
+}
+
+async fn send_verification_email(pool: &db::DbPool, user_id: &str, email: &str) {
+    let (token, hash) = crate::auth::new_email_token(EMAIL_VERIFY);
+    if let Err(e) = db::create_email_token(pool, user_id, EMAIL_VERIFY, &hash, crate::auth::EMAIL_VERIFY_TTL_SECS).await {
+        error!("verification token failed for {}: {}", user_id, e);
+        return;
+    }
+    let lang = i18n::user_lang(pool, user_id).await;
+    let body = i18n::tr(lang, "email.verify.body", &[&crate::email::app_link("verify", &token), &(crate::auth::EMAIL_VERIFY_TTL_SECS / 3600)]);
+    crate::email::send_in_background(email.to_string(), i18n::t(lang, "email.verify.subject").into(), body);
+}
+
+async fn handle_verify_email(req: EmailTokenRequest, lang: i18n::Lang, pool: db::DbPool) -> Result<Response, warp::Rejection> {
+    let hash = crate::auth::email_token_hash(EMAIL_VERIFY, &req.token);
+    match db::consume_email_token(&pool, EMAIL_VERIFY, &hash).await {
+        Ok(Some(user_id)) => match db::mark_email_verified(&pool, &user_id).await {
+            Ok(_) => {
+                info!("📧 Email verificata [{}]", user_id);
+                Ok(warp::reply::json(&ApiResponse { success: true, message: i18n::t(lang, "api.email_verified").into(), tx_signature: "".into() }).into_response())
+            },
+            Err(_) => Ok(auth_error(i18n::t(lang, "api.db_error"))),
+        },
+        Ok(None) => Ok(auth_error(i18n::t(lang, "api.invalid_link"))),
+        Err(_) => Ok(auth_error(i18n::t(lang, "api.db_error"))),
+    }
+}
+
+async fn handle_resend_verification(user_id: String, pool: db::DbPool) -> Result<Response, warp::Rejection> {
+    match db::get_user_email(&pool, &user_id).await {
+        Ok(Some((_, true))) => Ok(api_fail(&pool, &user_id, "api.email_already_verified").await),
+        Ok(Some((email, false))) => {
+            send_verification_email(&pool, &user_id, &email).await;
+            let lang = i18n::user_lang(&pool, &user_id).await;
+            Ok(warp::reply::json(&ApiResponse { success: true, message: i18n::t(lang, "api.verification_sent").into(), tx_signature: "".into() }).into_response())
+        },
+        Ok(None) => Ok(api_fail(&pool, &user_id, "api.no_web_account").await),
+        Err(_) => Ok(api_fail(&pool, &user_id, "api.db_error").await),
+    }
+}
+
+async fn handle_forgot_password(req: ForgotPasswordRequest, lang: i18n::Lang, pool: db::DbPool) -> Result<Response, warp::Rejection> {
+    let email = req.email.trim().to_lowercase();
+    // Risposta identica in ogni caso: l'endpoint non rivela quali email sono registrate
+    let reply = ApiResponse { success: true, message: i18n::t(lang, "api.reset_requested").into(), tx_signature: "".into() };
+    if !crate::auth::valid_email(&email) { return Ok(warp::reply::json(&reply).into_response()); }
+
+    if let Ok(Some((user_id, _))) = db::get_user_by_email(&pool, &email).await {
+        let (token, hash) = crate::auth::new_email_token(PASSWORD_RESET);
+        match db::create_email_token(&pool, &user_id, PASSWORD_RESET, &hash, crate::auth::PASSWORD_RESET_TTL_SECS).await {
+            Ok(_) => {
+                info!("🔑 Reset password richiesto [{}]", user_id);
+                let lang = i18n::user_lang(&pool, &user_id).await;
+                let body = i18n::tr(lang, "email.reset.body", &[&crate::email::app_link("reset", &token), &(crate::auth::PASSWORD_RESET_TTL_SECS / 60)]);
+                crate::email::send_in_background(email, i18n::t(lang, "email.reset.subject").into(), body);
+            },
+            Err(e) => error!("reset token failed for {}: {}", user_id, e),
+        }
+    }
+    Ok(warp::reply::json(&reply).into_response())
+}
+
+async fn handle_reset_password(req: ResetPasswordRequest, lang: i18n::Lang, pool: db::DbPool) -> Result<Response, warp::Rejection> {
+    if req.password.len() < MIN_PASSWORD_LEN {
+        return Ok(auth_error(i18n::t(lang, "api.password_too_short")));
+    }
+    let hash = crate::auth::email_token_hash(PASSWORD_RESET, &req.token);
+    let user_id = match db::consume_email_token(&pool, PASSWORD_RESET, &hash).await {
+        Ok(Some(u)) => u,
+        Ok(None) => return Ok(auth_error(i18n::t(lang, "api.invalid_link"))),
+        Err(_) => return Ok(auth_error(i18n::t(lang, "api.db_error"))),
+    };
+    let password_hash = match crate::auth::hash_password(&req.password) {
+        Ok(h) => h,
+        Err(e) => { error!("{}", e); return Ok(auth_error(i18n::t(lang, "api.internal_error"))); }
+    };
+    match db::reset_password(&pool, &user_id, &password_hash).await {
+        Ok(true) => {
+            info!("🔑 Password reimpostata [{}]: sessioni chiuse", user_id);
+            Ok(warp::reply::json(&ApiResponse { success: true, message: i18n::t(lang, "api.password_updated").into(), tx_signature: "".into() }).into_response())
+        },
+        Ok(false) => Ok(auth_error(i18n::t(lang, "api.account_not_found"))),
+        Err(e) => { error!("password reset failed for {}: {}", user_id, e); Ok(auth_error(i18n::t(lang, "api.db_error"))) },
+    }
+}
+
+async fn handle_auth_refresh(token: Option<String>, lang: i18n::Lang, pool: db::DbPool) -> Result<Response, warp::Rejection> {
+    let Some(token) = token else { return Err(warp::reject::custom(Unauthorized)) };
+    let new_token = crate::auth::new_session_token();
+    let (old_hash, new_hash) = (crate::auth::session_token_hash(&token), crate::auth::session_token_hash(&new_token));
+    match db::refresh_session(&pool, &old_hash, &new_hash, crate::auth::SESSION_TTL_HOURS).await {
+        Ok(Some(user_id)) => Ok(warp::reply::json(&AuthResponse { success: true, message: i18n::t(lang, "api.session_refreshed").into(), token: new_token, user_id }).into_response()),
+        Ok(None) => Err(warp::reject::custom(Unauthorized)),
+        Err(e) => { error!("session refresh failed: {}", e); Ok(auth_error(i18n::t(lang, "api.db_error"))) },
+    }
+}
+
+async fn handle_auth_logout(token: Option<String>, lang: i18n::Lang, pool: db::DbPool) -> Result<Response, warp::Rejection> {
+    let Some(token) = token else { return Err(warp::reject::custom(Unauthorized)) };
+    match db::delete_session(&pool, &crate::auth::session_token_hash(&token)).await {
+        Ok(_) => Ok(warp::reply::json(&ApiResponse { success: true, message: i18n::t(lang, "api.logged_out").into(), tx_signature: "".into() }).into_response()),
+        Err(e) => { error!("logout failed: {}", e); Ok(auth_error(i18n::t(lang, "api.db_error"))) },
+    }
+}
+
+async fn handle_sessions(user_id: String, token: Option<String>, pool: db::DbPool) -> Result<Response, warp::Rejection> {
+    let current = token.as_deref().map(crate::auth::session_token_hash);
+    match db::get_user_sessions(&pool, &user_id, current.as_deref()).await {
+        Ok(sessions) => Ok(warp::reply::json(&sessions).into_response()),
+        Err(_) => Ok(api_fail(&pool, &user_id, "api.db_error").await),
+    }
+}
+
+async fn handle_session_revoke(device_id: String, user_id: String, pool: db::DbPool) -> Result<Response, warp::Rejection> {
+    match db::revoke_session_device(&pool, &user_id, &device_id).await {
+        Ok(true) => {
+            info!("🔐 Sessione revocata [{}] dispositivo {}", user_id, device_id);
+            let lang = i18n::user_lang(&pool, &user_id).await;
+            Ok(warp::reply::json(&ApiResponse { success: true, message: i18n::t(lang, "api.session_revoked").into(), tx_signature: "".into() }).into_response())
+        },
+        Ok(false) => Ok(api_fail(&pool, &user_id, "api.session_not_found").await),
+        Err(_) => Ok(api_fail(&pool, &user_id, "api.db_error").await),
+    }
+}
+
+// --- HANDLERS ---
+
+// Stream Live: gemme e segnali per tutti, posizioni e vendite solo per il proprietario
+async fn handle_ws(socket: WebSocket, user_id: String, pool: db::DbPool, state: Arc<AppState>) {
+    let (mut tx, mut rx) = socket.split();
+    let mut events = state.events.subscribe();
+    info!("🔌 WS Client connesso [{}]", user_id);
+    crate::metrics::METRICS.dashboard_ws_clients.inc();
+
+    loop {
+        tokio::select! {
+            ev = events.recv() => match ev {
+                Ok(event) => {
+                    if event.user_id().is_some_and(|u| u != user_id) { continue; }
+                    // Gemme escluse dai filtri dell'utente (letti ad ogni gemma: cambiano a sessione aperta)
+                    if let LiveEvent::Gem(g) = &event {
+                        if !TokenFilter::load(&pool, &user_id).await.allows(&g.token, &g.symbol) { continue; }
+                    }
+                    let payload = match serde_json::to_string(&event) { Ok(p) => p, Err(_) => continue };
+                    if tx.send(Message::text(payload)).await.is_err() { break; }
+                },
+                // Client lento: salta gli eventi persi e continua
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            msg = rx.next() => match msg {
+                Some(Ok(m)) if !m.is_close() => continue,
+                _ => break,
+            },
+        }
+    }
+    crate::metrics::METRICS.dashboard_ws_clients.dec();
+    info!("🔌 WS Client disconnesso [{}]", user_id);
+}
+
+async fn handle_health(state: Arc<AppState>) -> Result<Response, warp::Rejection> {
+    Ok(match state.health.read().await.as_ref() {
+        Some(report) => {
+            let code = if report.is_down() { StatusCode::SERVICE_UNAVAILABLE } else { StatusCode::OK };
+            warp::reply::with_status(warp::reply::json(report), code).into_response()
+        },
+        None => warp::reply::with_status(warp::reply::json(&json!({ "status": "starting" })), StatusCode::SERVICE_UNAVAILABLE).into_response(),
+    })
+}
+
+async fn handle_status(user_id: String, pool: db::DbPool, net: Arc<network::NetworkClient>, state: Arc<AppState>) -> Result<Response, warp::Rejection> {
+    let pubkey_str = match wallet_manager::create_user_wallet(&pool, &user_id).await {
+        Ok(pk) => pk,
+        Err(e) => {
+            error!("wallet creation failed for {}: {}", user_id, e);
+            let body = json!({
+                "success": false,
+                "message": "WALLET_INIT_FAILED"
+            });
+            return Ok(warp::reply::with_status(warp::reply::json(&body), StatusCode::INTERNAL_SERVER_ERROR).into_response());
+        }
+    };
+    
+    let mut balance = 0;
+    if let Ok(pk) = Pubkey::from_str(&pubkey_str) {
+        balance = net.get_balance_fast(&pk).await;
+    }
+
+    let filter = TokenFilter::load(&pool, &user_id).await;
+    let mut gems = state.gems.sniper_feed();
+    gems.retain(|g| filter.allows(&g.token, &g.symbol));
+    let signals = state.math_signals.read().await.clone(); 
+    
+    // Conteggio reale posizioni aperte
+    let active_trades = db::count_open_trades(&pool, &user_id).await.unwrap_or_default();
+    let params = db::get_strategy_params(&pool, &user_id).await;
+    let now = chrono::Utc::now().timestamp();
+    let open_positions = state.positions_snapshot(Some(&user_id)).into_iter()
+        .map(|p| PositionStatus { hold_remaining_secs: p.hold_remaining_secs(&params, now), position: p })
+        .collect();
+    
+    Ok(warp::reply::json(&DashboardData {
+        wallet_address: pubkey_str,
+        balance_sol: balance as f64 / LAMPORTS_PER_SOL as f64,
+        active_trades_count: active_trades, 
+        system_status: "ONLINE".to_string(),
+        sol_price_usd: price_oracle::sol_usd().await,
+        open_positions,
+        funds: risk::funds(&pool, &user_id, &params, balance).await.ok(),
+        untracked_holdings: state.untracked_holdings.get(&user_id).map(|h| h.clone()).unwrap_or_default(),
+        trade_frequency: risk::trade_frequency(&pool, &user_id, &params).await.ok(),
+        priority_fees: net.fee_estimate().await,
+        gems_feed: gems,
+        signals_feed: signals,
+    }).into_response())
+}
+
+// --- DISCOVERY GEMME (Ordinamento e filtri lato server) ---
+
+async fn handle_gems(q: gems::GemQuery, user_id: String, pool: db::DbPool, state: Arc<AppState>) -> Result<Response, warp::Rejection> {
+    let Some(filter) = q.validate() else { return Ok(api_fail(&pool, &user_id, "api.invalid_gem_query").await) };
+    let token_filter = TokenFilter::load(&pool, &user_id).await;
+    let mut gems = state.gems.query(&filter);
+    gems.retain(|g| token_filter.allows(&g.token, &g.symbol));
+    gems.truncate(filter.limit);
+    Ok(warp::reply::json(&gems).into_response())
+}
+
+// --- SCHEDA TOKEN (Ricerca prima dell'acquisto) ---
+
+async fn handle_token_icon(mint: String, q: token_icons::IconQuery, if_none_match: Option<String>, pool: db::DbPool, net: Arc<network::NetworkClient>, state: Arc<AppState>) -> Result<Response, warp::Rejection> {
+    if Pubkey::from_str(&mint).is_err() {
+        return Ok(warp::reply::with_status(warp::reply::json(&ApiResponse { success: false, message: i18n::t(i18n::default_lang(), "api.invalid_mint").into(), tx_signature: "".into() }), StatusCode::BAD_REQUEST).into_response());
+    }
+    let icon = token_icons::icon(&pool, &net, &state, &mint, q.size()).await;
+    let cache_control = format!("public, max-age={}", icon.max_age());
+    // Il browser ha già questa versione: niente corpo
+    if if_none_match.as_deref() == Some(icon.etag.as_str()) {
+        let reply = warp::reply::with_status(warp::reply(), StatusCode::NOT_MODIFIED);
+        let reply = warp::reply::with_header(reply, "etag", icon.etag);
+        return Ok(warp::reply::with_header(reply, "cache-control", cache_control).into_response());
+    }
+    let reply = warp::reply::with_header(icon.png, "content-type", "image/png");
+    let reply = warp::reply::with_header(reply, "etag", icon.etag);
+    Ok(warp::reply::with_header(reply, "cache-control", cache_control).into_response())
+}
+
+async fn handle_token_report(mint: String, q: TokenQuery, user_id: String, pool: db::DbPool, net: Arc<network::NetworkClient>, state: Arc<AppState>) -> Result<Response, warp::Rejection> {
+    let pubkey = match Pubkey::from_str(&mint) {
+        Ok(p) => p,
+        Err(_) => {
+            let lang = i18n::user_lang(&pool, &user_id).await;
+            return Ok(warp::reply::with_status(warp::reply::json(&ApiResponse { success: false, message: i18n::t(lang, "api.invalid_mint").into(), tx_signature: "".into() }), StatusCode::BAD_REQUEST).into_response());
+        },
+    };
+    let interval = q.interval.filter(|i| birdeye::interval_secs(i).is_some()).unwrap_or_else(|| "15m".into());
+    let params = db::get_strategy_params(&pool, &user_id).await;
+
+    // Fonti indipendenti in parallelo
+    let now = chrono::Utc::now().timestamp();
+    let (meta, market, safety_rep, security, holders, bars) = tokio::join!(
+        metadata::resolve(&pool, &net, &mint),
+        jupiter::get_token_market_data(&mint),
+        safety::check_token_safety(&net, &pubkey),
+        birdeye::get_token_security(&mint),
+        safety::holder_concentration(&net, &pubkey),
+        birdeye::get_ohlcv(&mint, &interval, now - birdeye::interval_secs(&interval).unwrap_or(900) * 100, now)
+    );
+
+    let market = market.ok();
+    let score = match &market {
+        Some(mkt) => Some(scoring::analyze_token_potential(&mint, mkt).await),
+        None => None,
+    };
+    let analysis = bars.ok().filter(|b| !b.is_empty()).map(|bars| {
+        let mut data = strategy::MarketData::new(&meta.symbol);
+        data.replace_candles(bars.iter().map(|c| strategy::Candle { high: c.high, low: c.low, close: c.close, volume: c.volume }));
+        data.whale_pressure = whale::pressure(&state, &mint);
+        let signal = match strategy::analyze_market(&data, 1.0, &params) {
+            strategy::TradeAction::Buy { reason, .. } => format!("BUY: {}", reason),
+            strategy::TradeAction::Sell(reason) => format!("SELL: {}", reason),
+            _ => "HOLD".to_string(),
+        };
+        TokenAnalysis {
+            engine: params.engine.clone(),
+            interval: interval.clone(),
+            candles: data.candles.len(),
+            indicators: strategy::Indicators::compute(&data, &params),
+            whale_pressure: data.whale_pressure,
+            signal,
+        }
+    });
+
+    Ok(warp::reply::json(&TokenReport {
+        mint,
+        metadata: meta,
+        market,
+        safety: safety_rep.ok(),
+        security: security.ok(),
+        holders: holders.ok(),
+        score,
+        analysis,
+    }).into_response())
+}
+
+async fn handle_debug_analysis(mint: String, q: TokenQuery, user_id: String, pool: db::DbPool, state: Arc<AppState>) -> Result<Response, warp::Rejection> {
+    if Pubkey::from_str(&mint).is_err() {
+        return Ok(warp::reply::with_status(api_fail(&pool, &user_id, "api.invalid_mint").await, StatusCode::BAD_REQUEST).into_response());
+    }
+    let params = db::get_strategy_params(&pool, &user_id).await;
+    let market = jupiter::get_token_market_data(&mint).await.ok();
+
+    // Candele live se il token è nell'universo della Market Strategy, altrimenti Birdeye (interval, default 1m)
+    let live = state.market_data.get(&mint).map(|d| d.value().clone());
+    let (source, data) = match live {
+        Some(data) => ("LIVE", data),
+        None => {
+            let interval = q.interval.filter(|i| birdeye::interval_secs(i).is_some()).unwrap_or_else(|| "1m".into());
+            let now = chrono::Utc::now().timestamp();
+            let bars = birdeye::get_ohlcv(&mint, &interval, now - birdeye::interval_secs(&interval).unwrap_or(60) * 100, now).await.unwrap_or_default();
+            if bars.is_empty() { return Ok(api_fail(&pool, &user_id, "api.analysis_unavailable").await); }
+            let mut data = strategy::MarketData::new(market.as_ref().map(|m| m.symbol.as_str()).unwrap_or_default());
+            data.replace_candles(bars.iter().map(|c| strategy::Candle { high: c.high, low: c.low, close: c.close, volume: c.volume }));
+            data.whale_pressure = whale::pressure(&state, &mint);
+            ("BIRDEYE", data)
+        },
+    };
+
+    let filters = market.as_ref().map(|mkt| vec![
+        strategy::EntryCheck::new("min_liquidity", mkt.liquidity_usd >= params.min_liquidity_usd, format!("${:.0} >= ${:.0}", mkt.liquidity_usd, params.min_liquidity_usd)),
+        strategy::EntryCheck::new("min_volume_24h", mkt.volume_24h >= params.min_volume_24h, format!("${:.0} >= ${:.0}", mkt.volume_24h, params.min_volume_24h)),
+    ]).unwrap_or_default();
+
+    Ok(warp::reply::json(&DebugAnalysis {
+        score: market.as_ref().map(scoring::calculate_token_score),
+        analysis: strategy::MarketAnalysis::compute(&data, &params),
+        mint,
+        source,
+        market,
+        filters,
+    }).into_response())
+}
+
+async fn handle_sol_price(_user_id: String) -> Result<Response, warp::Rejection> {
+    Ok(warp::reply::json(&price_oracle::sol_price().await).into_response())
+}
+
+async fn handle_export_trades(q: ExportQuery, user_id: String, pool: db::DbPool) -> Result<Response, warp::Rejection> {
+    let bad = |msg: &str| Ok(warp::reply::with_status(
+        warp::reply::json(&ApiResponse { success: false, message: msg.into(), tx_signature: "".into() }),
+        StatusCode::BAD_REQUEST,
+    ).into_response());
+
+    let valid_date = |d: &Option<String>| d.as_deref().is_none_or(|d| chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d").is_ok());
+    if !valid_date(&q.from) || !valid_date(&q.to) { return bad("Date non valide (formato YYYY-MM-DD)"); }
+    let tag = match q.tag.as_deref().map(trade_notes::normalize_tag).transpose() {
+        Ok(t) => t,
+        Err(e) => return bad(&e),
+    };
+
+    let events = match db::get_trade_events(&pool, &user_id, q.from.as_deref(), q.to.as_deref(), tag.as_deref()).await {
+        Ok(e) => e,
+        Err(e) => {
+            error!("Export trade fallito per {}: {}", user_id, e);
+            let body = json!({ "success": false, "message": "EXPORT_FAILED" });
+            return Ok(warp::reply::with_status(warp::reply::json(&body), StatusCode::INTERNAL_SERVER_ERROR).into_response());
+        }
+    };
+
+    match q.format.as_deref().unwrap_or("csv") {
+        "csv" => {
+            let reply = warp::reply::with_header(export::to_csv(&events), "content-type", "text/csv; charset=utf-8");
+            let reply = warp::reply::with_header(reply, "content-disposition", format!("attachment; filename=\"trades_{}.csv\"", user_id));
+            Ok(reply.into_response())
+        },
+        "json" => Ok(warp::reply::json(&events).into_response()),
+        _ => bad("Formato non valido (csv/json)"),
+    }
+}
+
+async fn handle_export_tax(q: TaxQuery, user_id: String, pool: db::DbPool) -> Result<Response, warp::Rejection> {
+    let events = db::get_trade_events(&pool, &user_id, None, None, None).await.unwrap_or_default();
+    let mut years = export::tax_years(&events);
+    if let Some(year) = q.year { years.retain(|y| y.year == year); }
+    Ok(warp::reply::json(&years).into_response())
+}
+
+async fn handle_stats(user_id: String, pool: db::DbPool) -> Result<Response, warp::Rejection> {
+    match portfolio::stats(&pool, &user_id).await {
+        Ok(stats) => Ok(warp::reply::json(&StatsResponse {
+            win_rate_pct: stats.win_rate_pct(),
+            roi_pct: stats.roi_pct(),
+            profit_factor: stats.profit_factor(),
+            stats,
+        }).into_response()),
+        Err(_) => Ok(api_fail(&pool, &user_id, "api.db_error").await),
+    }
+}
+
+async fn handle_portfolio(user_id: String, pool: db::DbPool, net: Arc<network::NetworkClient>, state: Arc<AppState>) -> Result<Response, warp::Rejection> {
+    let to_sol = |l: u64| l as f64 / LAMPORTS_PER_SOL as f64;
+
+    let mut sol_balance = 0.0;
+    if let Ok(pk_str) = wallet_manager::create_user_wallet(&pool, &user_id).await {
+        if let Ok(pk) = Pubkey::from_str(&pk_str) {
+            sol_balance = to_sol(net.get_balance_fast(&pk).await);
+        }
+    }
+
+    let mut tokens: std::collections::BTreeMap<String, TokenPnl> = std::collections::BTreeMap::new();
+
+    // 1. Realizzato (trade chiusi)
+    for (token, n, _, pnl) in db::get_realized_pnl_by_token(&pool, &user_id).await.unwrap_or_default() {
+        let t = tokens.entry(token.clone()).or_insert_with(|| TokenPnl { token, ..Default::default() });
+        t.closed_trades = n;
+        t.realized_pnl_sol = pnl;
+    }
+
+    // 2. Non realizzato (prezzi live, un fetch per token)
+    let open = db::get_user_open_trades(&pool, &user_id).await.unwrap_or_default();
+    let mut prices: std::collections::HashMap<String, f64> = std::collections::HashMap::new();
+    for (trade_id, token, amount_in) in open {
+        if !prices.contains_key(&token) {
+            let p = jupiter::get_token_market_data(&token).await.map(|m| m.price).unwrap_or(0.0);
+            prices.insert(token.clone(), p);
+        }
+        let price = prices[&token];
+        let entry = state.open_positions.get(&trade_id).map(|p| p.entry_price).unwrap_or(0.0);
+        // Senza prezzo d'ingresso o prezzo live: valore = costo
+        let value = if entry > 0.0 && price > 0.0 { to_sol(amount_in) * price / entry } else { to_sol(amount_in) };
+
+        let t = tokens.entry(token.clone()).or_insert_with(|| TokenPnl { token, ..Default::default() });
+        t.open_trades += 1;
+        t.invested_open_sol += to_sol(amount_in);
+        t.current_value_sol += value;
+        t.unrealized_pnl_sol = t.current_value_sol - t.invested_open_sol;
+    }
+
+    let realized: f64 = tokens.values().map(|t| t.realized_pnl_sol).sum();
+    let unrealized: f64 = tokens.values().map(|t| t.unrealized_pnl_sol).sum();
+    let invested_open: f64 = tokens.values().map(|t| t.invested_open_sol).sum();
+    let open_value: f64 = tokens.values().map(|t| t.current_value_sol).sum();
+    let withdrawn = to_sol(db::get_total_withdrawn(&pool, &user_id).await.unwrap_or(0));
+    let (deposited, deposited_usd) = db::get_total_deposited(&pool, &user_id, price_oracle::SOL_MINT).await.unwrap_or((0, 0.0));
+
+    Ok(warp::reply::json(&PortfolioData {
+        sol_balance,
+        equity_sol: sol_balance + open_value,
+        net_deposited_sol: sol_balance + invested_open + withdrawn - realized,
+        deposited_sol: to_sol(deposited),
+        deposited_usd,
+        realized_pnl_sol: realized,
+        unrealized_pnl_sol: unrealized,
+        total_pnl_sol: realized + unrealized,
+        tokens: tokens.into_values().collect(),
+    }).into_response())
+}
+
+async fn handle_trade(user_id: String, req: TradeRequest, pool: db::DbPool, net: Arc<network::NetworkClient>, state: Arc<AppState>) -> Result<Response, warp::Rejection> {
+    info!("📨 Trade Request [{}]: {} {} SOL -> {}", user_id, req.action, req.amount_sol, req.token);
+
+    // Modalità non-custodial: si restituisce la TX da firmare, nessuna chiave usata lato server
+    if let Some(owner) = external_wallet(&pool, &user_id).await {
+        return handle_trade_unsigned(&user_id, &owner, req, &pool, &net).await;
+    }
+
+    if req.action == "BUY" {
+        // Stesso percorso di Telegram (Jupiter -> Raydium)
+        let amount_lamports = (req.amount_sol * LAMPORTS_PER_SOL as f64) as u64;
+        let params = db::get_strategy_params(&pool, &user_id).await;
+        return match crate::trading::execute_buy(&pool, &net, &user_id, &req.token, amount_lamports, params.slippage_bps).await {
+            Ok((sig, route)) => Ok(warp::reply::json(&ApiResponse { success: true, message: format!("Buy Eseguito ({})", route), tx_signature: sig }).into_response()),
+            Err(e) => Ok(warp::reply::json(&ApiResponse { success: false, message: format!("Buy Fallito: {}", e), tx_signature: "".into() }).into_response()),
+        };
+    } else if req.action == "SELL" {
+        // Senza quantità vende tutto il token e chiude le posizioni aperte su di esso
+        let size = match (req.percent, req.token_amount) {
+            (None, None) => None,
+            (Some(p), None) if p > 0.0 && p <= 100.0 => Some(crate::trading::SellSize::Percent(p)),
+            (None, Some(n)) if n > 0 => Some(crate::trading::SellSize::Tokens(n)),
+            _ => return Ok(api_fail(&pool, &user_id, "api.invalid_sell_size").await),
+        };
+        let res = match size {
+            Some(size) => crate::trading::sell_position_part(&pool, &net, &state, &user_id, &req.token, size).await,
+            None => crate::trading::sell_position_now(&pool, &net, &state, &user_id, &req.token).await,
+        };
+        return match res {
+            Ok((sig, pnl)) => Ok(warp::reply::json(&ApiResponse { success: true, message: format!("Sell Eseguito (PnL {:.4} SOL)", pnl), tx_signature: sig }).into_response()),
+            Err(e) => Ok(warp::reply::json(&ApiResponse { success: false, message: format!("Sell Fallito: {}", e), tx_signature: "".into() }).into_response()),
+        };
+    }
+    
+    Ok(api_fail(&pool, &user_id, "api.generic_error").await)
+}
+
+async fn handle_convert(user_id: String, req: ConvertRequest, pool: db::DbPool, net: Arc<network::NetworkClient>) -> Result<Response, warp::Rejection> {
+    info!("📨 Convert Request [{}]: {} {} {} -> {}", user_id, req.mode, req.amount, req.input_mint, req.output_mint);
+    let lang = i18n::user_lang(&pool, &user_id).await;
+    let fail = |message: String| Ok(warp::reply::json(&ApiResponse { success: false, message, tx_signature: "".into() }).into_response());
+
+    let exact_out = match req.mode.as_str() {
+        "exactIn" => false,
+        "exactOut" => true,
+        _ => return fail(i18n::t(lang, "api.invalid_convert").into()),
+    };
+    let input = match (Pubkey::from_str(&req.input_mint), Pubkey::from_str(&req.output_mint)) {
+        (Ok(i), Ok(o)) if i != o => i,
+        _ => return fail(i18n::t(lang, "api.invalid_convert").into()),
+    };
+    if req.amount == 0 { return fail(i18n::t(lang, "api.invalid_amount").into()); }
+    // Solo wallet custodito: in non-custodial le TX si firmano con /trade
+    if external_wallet(&pool, &user_id).await.is_some() { return fail(i18n::t(lang, "api.convert_custodial_only").into()); }
+
+    let payer = match wallet_manager::get_decrypted_wallet(&pool, &user_id).await {
+        Ok(p) => p,
+        Err(_) => return fail(i18n::t(lang, "api.generic_error").into()),
+    };
+    let params = db::get_strategy_params(&pool, &user_id).await;
+    // Disponibile in ingresso: per SOL al netto della riserva (reserve_sol), per i token l'intero saldo
+    let available = if req.input_mint == price_oracle::SOL_MINT {
+        let balance = net.get_balance_fast(&payer.pubkey()).await;
+        match risk::buy_budget(&pool, &user_id, &params, balance).await {
+            Ok(budget) => budget.spendable,
+            Err(_) => return fail(i18n::t(lang, "api.db_error").into()),
+        }
+    } else {
+        net.get_token_balance(&payer.pubkey(), &input).await.unwrap_or(0)
+    };
+    let pair = (req.input_mint.as_str(), req.output_mint.as_str());
+
+    let res = if exact_out {
+        let quoted = match swap_router::quote_exact_out(pair, req.amount, params.slippage_bps).await {
+            Ok(q) => q,
+            Err(e) => {
+                error!("convert quote failed for {}: {}", user_id, e);
+                return fail(i18n::t(lang, "api.quote_failed").into());
+            },
+        };
+        // L'input massimo della quote (slippage incluso) deve stare nel limite dell'utente e nel saldo
+        if let Some(limit) = req.max_in.filter(|l| quoted.max_in > *l) {
+            return fail(i18n::tr(lang, "api.convert_max_in", &[&quoted.max_in, &limit]));
+        }
+        if quoted.max_in > available {
+            return fail(i18n::tr(lang, "api.convert_insufficient", &[&quoted.max_in, &available]));
+        }
+        swap_router::swap_exact_out(&net, &pool, &user_id, &payer, pair, quoted, params.slippage_bps).await
+    } else {
+        if req.amount > available {
+            return fail(i18n::tr(lang, "api.convert_insufficient", &[&req.amount, &available]));
+        }
+        swap_router::swap_pair(&net, &pool, &user_id, &payer, pair, req.amount, params.slippage_bps).await
+    };
+
+    match res {
+        Ok(swap) => Ok(warp::reply::json(&ApiResponse {
+            success: true,
+            message: format!("Conversione Eseguita ({}): {} in uscita", swap.route.label(), swap.expected_out),
+            tx_signature: swap.signature,
+        }).into_response()),
+        Err(e) => fail(format!("Conversione Fallita: {}", e)),
+    }
+}
+
+async fn external_wallet(pool: &db::DbPool, user_id: &str) -> Option<Pubkey> {
+    let settings = db::get_settings(pool, user_id).await.ok()?;
+    settings["external_wallet"].as_str().and_then(|s| Pubkey::from_str(s).ok())
+}
+
+async fn handle_trade_unsigned(user_id: &str, owner: &Pubkey, req: TradeRequest, pool: &db::DbPool, net: &Arc<network::NetworkClient>) -> Result<Response, warp::Rejection> {
+    let lang = i18n::user_lang(pool, user_id).await;
+    let fail = |message: String| Ok(warp::reply::json(&ApiResponse { success: false, message, tx_signature: "".into() }).into_response());
+    let params = db::get_strategy_params(pool, user_id).await;
+
+    let (buy, amount) = match req.action.as_str() {
+        "BUY" => (true, (req.amount_sol * LAMPORTS_PER_SOL as f64) as u64),
+        "SELL" => {
+            let mint = match Pubkey::from_str(&req.token) { Ok(m) => m, Err(_) => return fail(i18n::t(lang, "api.invalid_token").into()) };
+            let balance = match net.get_token_balance(owner, &mint).await {
+                Ok(b) if b > 0 => b,
+                _ => return fail(i18n::t(lang, "api.token_not_in_wallet").into()),
+            };
+            // Vendita parziale: validata sul saldo reale (il wallet esterno non ha trade tracciati da dividere)
+            match (req.percent, req.token_amount) {
+                (None, None) => (false, balance),
+                (Some(p), None) if p > 0.0 && p <= 100.0 => (false, (balance as f64 * p / 100.0) as u64),
+                (None, Some(n)) if n > 0 && n <= balance => (false, n),
+                _ => return fail(i18n::t(lang, "api.invalid_sell_size").into()),
+            }
+        },
+        _ => return fail(i18n::t(lang, "api.invalid_action_trade").into()),
+    };
+    if amount == 0 { return fail(i18n::t(lang, "api.invalid_amount").into()); }
+    // Anche in non-custodial gli acquisti rispettano i limiti dell'utente (sul saldo del wallet esterno)
+    if buy {
+        let balance = net.get_balance_fast(owner).await;
+        match risk::buy_budget(pool, user_id, &params, balance).await {
+            Ok(budget) => if let Err(msg) = budget.check(amount, lang) { return fail(msg); },
+            Err(_) => return fail(i18n::t(lang, "api.db_error").into()),
+        }
+    }
+
+    match swap_router::build_unsigned(net, owner, buy, &req.token, amount, params.slippage_bps).await {
+        Ok(swap) => Ok(warp::reply::json(&UnsignedTradeResponse {
+            success: true,
+            message: i18n::tr(lang, "api.sign_in_wallet", &[&swap.route.label()]),
+            unsigned_tx: swap.transaction,
+            route: swap.route.label().into(),
+            expected_out: swap.expected_out,
+        }).into_response()),
+        Err(e) => {
+            error!("unsigned trade quote failed for {}: {}", user_id, e);
+            fail(i18n::t(lang, "api.quote_failed").into())
+        },
+    }
+}
+
+async fn handle_submit(user_id: String, req: SubmitRequest, pool: db::DbPool, net: Arc<network::NetworkClient>) -> Result<Response, warp::Rejection> {
+    let owner = match external_wallet(&pool, &user_id).await {
+        Some(o) => o,
+        None => return Ok(api_fail(&pool, &user_id, "api.no_external_wallet").await),
+    };
+    let lang = i18n::user_lang(&pool, &user_id).await;
+    match swap_router::submit_signed(&net, &owner, &req.signed_tx).await {
+        Ok(sig) => Ok(warp::reply::json(&ApiResponse { success: true, message: i18n::t(lang, "api.tx_sent").into(), tx_signature: sig }).into_response()),
+        Err(e) => Ok(warp::reply::json(&ApiResponse { success: false, message: i18n::tr(lang, "api.tx_send_failed", &[&e]), tx_signature: "".into() }).into_response()),
+    }
+}
+
+// Lingua di notifiche, report e messaggi di errore
+async fn handle_language(user_id: String, req: LanguageRequest, pool: db::DbPool) -> Result<Response, warp::Rejection> {
+    let Some(lang) = i18n::Lang::from_code(&req.language) else {
+        let lang = i18n::user_lang(&pool, &user_id).await;
+        let message = i18n::tr(lang, "api.invalid_language", &[&i18n::LANGUAGES.join(", ")]);
+        return Ok(warp::reply::json(&ApiResponse { success: false, message, tx_signature: "".into() }).into_response());
+    };
+    match i18n::set_user_lang(&pool, &user_id, lang).await {
+        Ok(_) => Ok(warp::reply::json(&ApiResponse { success: true, message: i18n::tr(lang, "api.language_set", &[&lang.code()]), tx_signature: "".into() }).into_response()),
+        Err(e) => {
+            error!("language update failed for {}: {}", user_id, e);
+            Ok(api_fail(&pool, &user_id, "api.db_error").await)
+        }
+    }
+}
+
+async fn handle_alerts(user_id: String, req: AlertsRequest, pool: db::DbPool) -> Result<Response, warp::Rejection> {
+    let lang = i18n::user_lang(&pool, &user_id).await;
+    let Some(level) = trade_alerts::Verbosity::from_code(&req.level) else {
+        let message = i18n::tr(lang, "api.invalid_alerts", &[&trade_alerts::Verbosity::ALL.join(", ")]);
+        return Ok(warp::reply::json(&ApiResponse { success: false, message, tx_signature: "".into() }).into_response());
+    };
+    match trade_alerts::set_verbosity(&pool, &user_id, level).await {
+        Ok(_) => Ok(warp::reply::json(&ApiResponse { success: true, message: i18n::tr(lang, "api.alerts_set", &[&level.code()]), tx_signature: "".into() }).into_response()),
+        Err(e) => {
+            error!("alerts update failed for {}: {}", user_id, e);
+            Ok(api_fail(&pool, &user_id, "api.db_error").await)
+        }
+    }
+}
+
+async fn handle_park(user_id: String, req: ParkRequest, pool: db::DbPool) -> Result<Response, warp::Rejection> {
+    if req.percent.is_some_and(|p| !auto_park::AutoParkSettings::valid_percent(p)) {
+        return Ok(api_fail(&pool, &user_id, "api.invalid_park").await);
+    }
+    let settings = db::get_settings(&pool, &user_id).await.unwrap_or_default();
+    let mut park = auto_park::AutoParkSettings::from_settings(&settings);
+    if let Some(enabled) = req.enabled { park.enabled = enabled; }
+    if let Some(percent) = req.percent { park.percent = percent; }
+    if req.release { park.parked_usdc = 0; }
+    match auto_park::save(&pool, &user_id, &park).await {
+        Ok(_) => Ok(warp::reply::json(&json!({ "success": true, "auto_park": park })).into_response()),
+        Err(e) => {
+            error!("auto-park update failed for {}: {}", user_id, e);
+            Ok(api_fail(&pool, &user_id, "api.db_error").await)
+        }
+    }
+}
+
+async fn handle_skim(user_id: String, req: SkimRequest, pool: db::DbPool) -> Result<Response, warp::Rejection> {
+    let res = if req.enabled {
+        match (req.period.as_deref().and_then(auto_skim::SkimPeriod::from_code), req.floor_sol, req.destination.as_deref()) {
+            (Some(period), Some(floor), Some(dest)) => auto_skim::enable(&pool, &user_id, period, floor, dest, i18n::user_lang(&pool, &user_id).await).await,
+            _ => return Ok(api_fail(&pool, &user_id, "api.invalid_skim").await),
+        }
+    } else {
+        let settings = db::get_settings(&pool, &user_id).await.unwrap_or_default();
+        let mut skim = auto_skim::AutoSkimSettings::from_settings(&settings);
+        skim.enabled = false;
+        match auto_skim::save(&pool, &user_id, &skim).await {
+            Ok(_) => Ok(skim),
+            Err(e) => {
+                error!("auto-skim disable failed for {}: {}", user_id, e);
+                Err(i18n::t(i18n::user_lang(&pool, &user_id).await, "api.db_error").to_string())
+            }
+        }
+    };
+    match res {
+        Ok(skim) => Ok(warp::reply::json(&json!({ "success": true, "auto_skim": skim })).into_response()),
+        Err(message) => Ok(warp::reply::json(&ApiResponse { success: false, message, tx_signature: "".into() }).into_response()),
+    }
+}
+
+async fn handle_wallet_mode(user_id: String, req: ExternalWalletRequest, pool: db::DbPool) -> Result<Response, warp::Rejection> {
+    let (value, message) = match req.external_wallet.as_deref().map(str::trim) {
+        Some(w) => match Pubkey::from_str(w) {
+            Ok(pk) => (json!(pk.to_string()), format!("Modalità Non-Custodial: firmi tu con {}", pk)),
+            Err(_) => return Ok(api_fail(&pool, &user_id, "api.invalid_wallet").await),
+        },
+        None => (serde_json::Value::Null, "Modalità Custodial: il bot firma con il tuo wallet interno".to_string()),
+    };
+    match db::update_setting(&pool, &user_id, "external_wallet", value).await {
+        Ok(_) => Ok(warp::reply::json(&ApiResponse { success: true, message, tx_signature: "".into() }).into_response()),
+        Err(e) => {
+            error!("wallet mode update failed for {}: {}", user_id, e);
+            Ok(api_fail(&pool, &user_id, "api.db_error").await)
+        }
+    }
+}
+
+async fn handle_withdraw(user_id: String, req: WithdrawRequest, pool: db::DbPool, net: Arc<network::NetworkClient>) -> Result<Response, warp::Rejection> {
+    
+    // 1. Sicurezza: Solo SOL
+    if req.token != "SOL" {
+         return Ok(api_fail(&pool, &user_id, "api.withdraw_sol_only").await);
+    }
+
+    // 2. Blocco 24h, whitelist indirizzi e fondi; sopra soglia serve l'approvazione Telegram
+    let amount = (req.amount * LAMPORTS_PER_SOL as f64) as u64;
+    let lang = i18n::user_lang(&pool, &user_id).await;
+    let (success, message, tx_signature) = match withdrawals::withdraw(&pool, &net, &user_id, amount, req.destination_address.trim()).await {
+        Ok(withdrawals::WithdrawOutcome::Sent(sig)) => (true, i18n::t(lang, "api.withdraw_sent").to_string(), sig),
+        Ok(withdrawals::WithdrawOutcome::AwaitingApproval(id)) => (true, i18n::tr(lang, "api.withdraw_awaiting", &[&id, &withdrawals::APPROVAL_TTL_MINUTES]), "".to_string()),
+        Err(e) => (false, e, "".to_string()),
+    };
+    Ok(warp::reply::json(&ApiResponse { success, message, tx_signature }).into_response())
+}
+
+async fn handle_offramp(user_id: String, req: OfframpRequest, pool: db::DbPool, net: Arc<network::NetworkClient>) -> Result<Response, warp::Rejection> {
+    info!("📨 Off-ramp Request [{}]: {:.2} EUR", user_id, req.amount_eur);
+    let (success, message) = match offramp::request(&pool, &net, &user_id, req.amount_eur, &req.iban, &req.beneficiary).await {
+        Ok(msg) => (true, msg),
+        Err(e) => (false, e),
+    };
+    Ok(warp::reply::json(&ApiResponse { success, message, tx_signature: "".into() }).into_response())
+}
+
+async fn handle_offramp_list(user_id: String, pool: db::DbPool) -> Result<Response, warp::Rejection> {
+    match db::get_user_offramps(&pool, &user_id).await {
+        Ok(list) => Ok(warp::reply::json(&list).into_response()),
+        Err(_) => Ok(api_fail(&pool, &user_id, "api.db_error").await),
+    }
+}
+
+async fn handle_withdraw_addresses_get(user_id: String, pool: db::DbPool) -> Result<Response, warp::Rejection> {
+    let list: Vec<WithdrawAddressData> = db::get_withdrawal_addresses(&pool, &user_id).await.unwrap_or_default()
+        .into_iter()
+        .map(|a| WithdrawAddressData { usable_from: withdrawals::usable_from(&a), address: a })
+        .collect();
+    Ok(warp::reply::json(&list).into_response())
+}
+
+async fn handle_withdraw_addresses_update(user_id: String, req: WithdrawAddressRequest, pool: db::DbPool) -> Result<Response, warp::Rejection> {
+    let lang = i18n::user_lang(&pool, &user_id).await;
+    let res = match req.action.as_str() {
+        "ADD" => withdrawals::request_address(&pool, &user_id, &req.address, req.label.as_deref()).await,
+        "REMOVE" => match db::remove_withdrawal_address(&pool, &user_id, req.address.trim()).await {
+            Ok(true) => Ok(i18n::t(lang, "api.address_removed").to_string()),
+            Ok(false) => Err(i18n::t(lang, "api.address_not_whitelisted").to_string()),
+            Err(e) => {
+                error!("withdrawal address remove failed for {}: {}", user_id, e);
+                Err(i18n::t(lang, "api.db_error").to_string())
+            },
+        },
+        _ => Err(i18n::t(lang, "api.invalid_action_add_remove").to_string()),
+    };
+    let (success, message) = match res {
+        Ok(msg) => (true, msg),
+        Err(e) => (false, e),
+    };
+    Ok(warp::reply::json(&ApiResponse { success, message, tx_signature: "".into() }).into_response())
+}
+
+async fn handle_watchlist_get(user_id: String, pool: db::DbPool, state: Arc<AppState>) -> Result<Response, warp::Rejection> {
+    let tokens = db::get_watchlist(&pool, &user_id).await.unwrap_or_default();
+    let (tokens, is_default) = if tokens.is_empty() {
+        (crate::DEFAULT_WATCHLIST.iter().map(|t| t.to_string()).collect::<Vec<_>>(), true)
+    } else {
+        (tokens, false)
+    };
+    // Prezzi dall'ultimo giro del Position Manager (nessuna chiamata esterna qui)
+    let prices = tokens.iter().filter_map(|t| state.spot_prices.get(t).map(|p| (t.clone(), *p))).collect();
+    let data = WatchlistData { tokens, is_default, prices };
+    Ok(warp::reply::json(&data).into_response())
+}
+
+async fn handle_watchlist_update(user_id: String, req: WatchlistRequest, pool: db::DbPool) -> Result<Response, warp::Rejection> {
+    if Pubkey::from_str(&req.token).is_err() {
+        return Ok(api_fail(&pool, &user_id, "api.invalid_token").await);
+    }
+
+    let res = match req.action.as_str() {
+        "ADD" => db::add_to_watchlist(&pool, &user_id, &req.token).await
+            .map(|added| if added { "api.watchlist_added" } else { "api.watchlist_exists" }),
+        "REMOVE" => db::remove_from_watchlist(&pool, &user_id, &req.token).await
+            .map(|removed| if removed { "api.watchlist_removed" } else { "api.watchlist_missing" }),
+        _ => return Ok(api_fail(&pool, &user_id, "api.invalid_action_add_remove").await),
+    };
+
+    match res {
+        Ok(key) => {
+            let lang = i18n::user_lang(&pool, &user_id).await;
+            Ok(warp::reply::json(&ApiResponse { success: true, message: i18n::t(lang, key).into(), tx_signature: "".into() }).into_response())
+        },
+        Err(e) => {
+            error!("watchlist update failed for {}: {}", user_id, e);
+            Ok(api_fail(&pool, &user_id, "api.db_error").await)
+        }
+    }
+}
+
+async fn handle_filters_get(user_id: String, pool: db::DbPool) -> Result<Response, warp::Rejection> {
+    Ok(warp::reply::json(&TokenFilter::load(&pool, &user_id).await).into_response())
+}
+
+async fn handle_filters_update(user_id: String, req: FilterRequest, pool: db::DbPool) -> Result<Response, warp::Rejection> {
+    let list = match token_filter::parse_list(&req.list) {
+        Some(l) => l,
+        None => return Ok(api_fail(&pool, &user_id, "api.invalid_list").await),
+    };
+    let add = match req.action.as_str() {
+        "ADD" => true,
+        "REMOVE" => false,
+        _ => return Ok(api_fail(&pool, &user_id, "api.invalid_action_add_remove").await),
+    };
+
+    match token_filter::update_entry(&pool, &user_id, list, &req.value, add).await {
+        Ok(msg) => Ok(warp::reply::json(&ApiResponse { success: true, message: msg, tx_signature: "".into() }).into_response()),
+        Err(msg) => Ok(warp::reply::json(&ApiResponse { success: false, message: msg, tx_signature: "".into() }).into_response()),
+    }
+}
+
+async fn handle_filters_mode(user_id: String, req: FilterModeRequest, pool: db::DbPool) -> Result<Response, warp::Rejection> {
+    match db::update_setting(&pool, &user_id, "whitelist_only", json!(req.whitelist_only)).await {
+        Ok(_) => {
+            let key = if req.whitelist_only { "api.whitelist_only_on" } else { "api.whitelist_only_off" };
+            let lang = i18n::user_lang(&pool, &user_id).await;
+            Ok(warp::reply::json(&ApiResponse { success: true, message: i18n::t(lang, key).into(), tx_signature: "".into() }).into_response())
+        },
+        Err(e) => {
+            error!("filter mode update failed for {}: {}", user_id, e);
+            Ok(api_fail(&pool, &user_id, "api.db_error").await)
+        }
+    }
+}
+
+async fn handle_executions(user_id: String, q: ExecutionsQuery, pool: db::DbPool) -> Result<Response, warp::Rejection> {
+    let limit = q.limit.unwrap_or(50).clamp(1, 500);
+    Ok(warp::reply::json(&db::get_executions(&pool, &user_id, limit).await.unwrap_or_default()).into_response())
+}
+
+async fn handle_signals(_user_id: String, q: SignalsQuery, pool: db::DbPool) -> Result<Response, warp::Rejection> {
+    let limit = q.limit.unwrap_or(50).clamp(1, 500);
+    let since = q.since.unwrap_or_else(|| chrono::Utc::now().timestamp() - 86_400);
+    let signals = db::get_signals(&pool, since, q.before, limit).await.unwrap_or_default();
+    let next_before = if signals.len() as i64 == limit { signals.last().map(|s| s.id) } else { None };
+    Ok(warp::reply::json(&SignalsPage { signals, next_before }).into_response())
+}
+
+// Timeline dell'account: trade, depositi, prelievi, segnali ed eventi (GET /activity)
+async fn handle_activity(user_id: String, q: activity::ActivityQuery, pool: db::DbPool) -> Result<Response, warp::Rejection> {
+    match activity::feed(&pool, &user_id, &q).await {
+        Ok(page) => Ok(warp::reply::json(&page).into_response()),
+        Err(e) => Ok(warp::reply::with_status(
+            warp::reply::json(&ApiResponse { success: false, message: e, tx_signature: "".into() }),
+            StatusCode::BAD_REQUEST,
+        ).into_response()),
+    }
+}
+
+async fn handle_deposits(user_id: String, q: ExecutionsQuery, pool: db::DbPool) -> Result<Response, warp::Rejection> {
+    let limit = q.limit.unwrap_or(50).clamp(1, 500);
+    Ok(warp::reply::json(&db::get_deposits(&pool, &user_id, limit).await.unwrap_or_default()).into_response())
+}
+
+async fn handle_webhooks_get(user_id: String, pool: db::DbPool) -> Result<Response, warp::Rejection> {
+    Ok(warp::reply::json(&db::get_webhooks(&pool, &user_id).await.unwrap_or_default()).into_response())
+}
+
+async fn handle_follows_get(user_id: String, pool: db::DbPool) -> Result<Response, warp::Rejection> {
+    Ok(warp::reply::json(&db::get_followed_wallets(&pool, &user_id).await.unwrap_or_default()).into_response())
+}
+
+// ADD | REMOVE {wallet}
+async fn handle_follows_update(user_id: String, req: FollowRequest, pool: db::DbPool) -> Result<Response, warp::Rejection> {
+    let add = match req.action.as_str() {
+        "ADD" => true,
+        "REMOVE" => false,
+        _ => return Ok(api_fail(&pool, &user_id, "api.invalid_action_add_remove").await),
+    };
+    let (success, message) = match copy_trade::update_follow(&pool, &user_id, &req.wallet, add).await {
+        Ok(msg) => (true, msg),
+        Err(e) => (false, e),
+    };
+    Ok(warp::reply::json(&ApiResponse { success, message, tx_signature: "".into() }).into_response())
+}
+
+async fn handle_referrals_get(user_id: String, pool: db::DbPool) -> Result<Response, warp::Rejection> {
+    match referral::summary(&pool, &user_id).await {
+        Ok(s) => Ok(warp::reply::json(&s).into_response()),
+        Err(e) => Ok(warp::reply::json(&ApiResponse { success: false, message: e, tx_signature: "".into() }).into_response()),
+    }
+}
+
+async fn handle_referrals_link(user_id: String, req: ReferralRequest, pool: db::DbPool) -> Result<Response, warp::Rejection> {
+    let (success, message) = match referral::link(&pool, &user_id, &req.code).await {
+        Ok(msg) => (true, msg),
+        Err(e) => (false, e),
+    };
+    Ok(warp::reply::json(&ApiResponse { success, message, tx_signature: "".into() }).into_response())
+}
+
+async fn handle_referrals_withdraw(user_id: String, pool: db::DbPool, net: Arc<network::NetworkClient>) -> Result<Response, warp::Rejection> {
+    match referral::withdraw(&pool, &net, &user_id).await {
+        Ok(sig) => {
+            let lang = i18n::user_lang(&pool, &user_id).await;
+            Ok(warp::reply::json(&ApiResponse { success: true, message: i18n::t(lang, "api.referral_sent").into(), tx_signature: sig }).into_response())
+        },
+        Err(e) => Ok(warp::reply::json(&ApiResponse { success: false, message: e, tx_signature: "".into() }).into_response()),
+    }
+}
+
+// ADD {url} | REMOVE {id} | TEST {id}
+async fn handle_webhooks_update(user_id: String, req: WebhookRequest, pool: db::DbPool) -> Result<Response, warp::Rejection> {
+    let lang = i18n::user_lang(&pool, &user_id).await;
+    let reply = |success: bool, message: String| Ok(warp::reply::json(&ApiResponse { success, message, tx_signature: "".into() }).into_response());
+    let hooks = match db::get_webhooks(&pool, &user_id).await {
+        Ok(h) => h,
+        Err(e) => { error!("webhook list failed for {}: {}", user_id, e); return reply(false, i18n::t(lang, "api.db_error").into()); }
+    };
+
+    match (req.action.as_str(), req.url, req.id) {
+        ("ADD", Some(url), _) => {
+            if let Err(e) = crate::notifications::validate_url(&url) { return reply(false, e.into()); }
+            if hooks.len() >= crate::notifications::MAX_WEBHOOKS {
+                return reply(false, i18n::tr(lang, "api.webhook_limit", &[&crate::notifications::MAX_WEBHOOKS]));
+            }
+            let kind = crate::notifications::detect_kind(&url);
+            match db::add_webhook(&pool, &user_id, &url, kind).await {
+                Ok(true) => reply(true, i18n::tr(lang, "api.webhook_added", &[&kind])),
+                Ok(false) => reply(false, i18n::t(lang, "api.webhook_exists").into()),
+                Err(e) => { error!("webhook add failed for {}: {}", user_id, e); reply(false, i18n::t(lang, "api.db_error").into()) }
+            }
+        },
+        ("REMOVE", _, Some(id)) => match db::remove_webhook(&pool, &user_id, id).await {
+            Ok(true) => reply(true, i18n::t(lang, "api.webhook_removed").into()),
+            Ok(false) => reply(false, i18n::t(lang, "api.webhook_not_found").into()),
+            Err(e) => { error!("webhook remove failed for {}: {}", user_id, e); reply(false, i18n::t(lang, "api.db_error").into()) }
+        },
+        ("TEST", _, Some(id)) => match hooks.iter().find(|h| h.id == id) {
+            Some(hook) => match crate::notifications::send_test(&user_id, hook, lang).await {
+                Ok(_) => reply(true, i18n::t(lang, "api.webhook_test_ok").into()),
+                Err(e) => reply(false, i18n::tr(lang, "api.webhook_test_failed", &[&e])),
+            },
+            None => reply(false, i18n::t(lang, "api.webhook_not_found").into()),
+        },
+        _ => reply(false, i18n::t(lang, "api.invalid_action_webhook").into()),
+    }
+}
+
+async fn handle_backtest(user_id: String, params: backtest::BacktestParams) -> Result<Response, warp::Rejection> {
+    if Pubkey::from_str(&params.token).is_err() || params.initial_sol <= 0.0 || params.days == 0 || params.days > 90 || params.strategy.validate().is_err() {
+        return Ok(warp::reply::json(&ApiResponse { success: false, message: i18n::t(i18n::default_lang(), "api.invalid_backtest").into(), tx_signature: "".into() }).into_response());
+    }
+
+    info!("📊 Backtest Request [{}]: {} ({}, {}g)", user_id, params.token, params.interval, params.days);
+    match backtest::run_backtest(&params).await {
+        Ok(report) => Ok(warp::reply::json(&report).into_response()),
+        Err(e) => Ok(warp::reply::json(&ApiResponse { success: false, message: format!("Backtest fallito: {}", e), tx_signature: "".into() }).into_response()),
+    }
+}
+
+async fn handle_strategy_get(user_id: String, pool: db::DbPool) -> Result<Response, warp::Rejection> {
+    Ok(warp::reply::json(&db::get_strategy_params(&pool, &user_id).await).into_response())
+}
+
+// Accetta modifiche parziali: i campi non inviati restano invariati
+async fn handle_strategy_update(user_id: String, patch: serde_json::Value, pool: db::DbPool) -> Result<Response, warp::Rejection> {
+    let current = db::get_strategy_params(&pool, &user_id).await;
+    let params = match current.with_overrides(&patch) {
+        Ok(p) => p,
+        Err(msg) => return Ok(warp::reply::json(&ApiResponse { success: false, message: msg, tx_signature: "".into() }).into_response()),
+    };
+
+    match db::save_strategy_params(&pool, &user_id, &params).await {
+        Ok(_) => {
+            let lang = i18n::user_lang(&pool, &user_id).await;
+            Ok(warp::reply::json(&ApiResponse { success: true, message: i18n::t(lang, "api.strategy_updated").into(), tx_signature: "".into() }).into_response())
+        },
+        Err(e) => {
+            error!("strategy update failed for {}: {}", user_id, e);
+            Ok(api_fail(&pool, &user_id, "api.db_error").await)
+        }
+    }
+}
+
+// --- AUTO-BOT (Avvio con strategia scelta) ---
+
+async fn handle_bot_start(user_id: String, req: BotStartRequest, pool: db::DbPool, net: Arc<network::NetworkClient>) -> Result<Response, warp::Rejection> {
+    // "grid" non è una strategia a segnali: crea la griglia con la configurazione del payload
+    let res = if req.strategy.as_deref() == Some("grid") {
+        match req.grid {
+            Some(cfg) => grid::start(&pool, &net, &user_id, &cfg).await,
+            None => Err("Configurazione grid mancante (lower_price, upper_price, step_pct, order_size)".into()),
+        }
+    } else {
+        crate::trading::start_auto_bot(&pool, &user_id, req.strategy.as_deref(), req.universe.as_deref()).await.map(|engine| format!("Auto-Bot avviato (strategia {})", engine))
+    };
+    let (success, message) = match res {
+        Ok(msg) => (true, msg),
+        Err(e) => (false, e),
+    };
+    Ok(warp::reply::json(&ApiResponse { success, message, tx_signature: "".into() }).into_response())
+}
+
+async fn handle_bot_stop(user_id: String, pool: db::DbPool, net: Arc<network::NetworkClient>) -> Result<Response, warp::Rejection> {
+    let res = match db::stop_daily_cycle(&pool, &user_id).await {
+        Ok(_) => db::stop_user_grids(&pool, &user_id).await,
+        Err(e) => Err(e),
+    };
+    if res.is_ok() { activity::record(&pool, &user_id, activity::Event::bot_stop("Dashboard")).await; }
+    let lang = i18n::user_lang(&pool, &user_id).await;
+    let (success, mut message) = match res {
+        Ok(0) => (true, i18n::t(lang, "api.bot_stopped").to_string()),
+        Ok(_) => (true, i18n::t(lang, "api.bot_and_grid_stopped").to_string()),
+        Err(e) => {
+            error!("bot stop failed for {}: {}", user_id, e);
+            (false, i18n::t(lang, "api.db_error").to_string())
+        },
+    };
+    // Opt-in: chiusura degli ATA rimasti vuoti dopo le vendite
+    if success {
+        if let Some(lamports) = dust::reclaim_on_stop(&pool, &net, &user_id).await {
+            message.push_str(&i18n::tr(lang, "api.rent_reclaimed", &[&format!("{:.4}", lamports as f64 / LAMPORTS_PER_SOL as f64)]));
+        }
+    }
+    Ok(warp::reply::json(&ApiResponse { success, message, tx_signature: "".into() }).into_response())
+}
+
+async fn handle_grid_get(user_id: String, pool: db::DbPool) -> Result<Response, warp::Rejection> {
+    let grid = match db::get_user_grid(&pool, &user_id).await {
+        Ok(Some(g)) => g,
+        Ok(None) => return Ok(api_fail(&pool, &user_id, "api.no_grid").await),
+        Err(e) => {
+            error!("grid load failed for {}: {}", user_id, e);
+            return Ok(api_fail(&pool, &user_id, "api.db_error").await);
+        },
+    };
+    let levels = db::get_grid_levels(&pool, grid.id).await.unwrap_or_default();
+    Ok(warp::reply::json(&grid::summary(&grid, &levels)).into_response())
+}
+
+// --- DCA (Acquisti Ricorrenti) ---
+
+async fn handle_dca_list(user_id: String, pool: db::DbPool) -> Result<Response, warp::Rejection> {
+    Ok(warp::reply::json(&db::get_dca_orders(&pool, &user_id).await.unwrap_or_default()).into_response())
+}
+
+async fn handle_dca_create(user_id: String, req: DcaRequest, pool: db::DbPool) -> Result<Response, warp::Rejection> {
+    if Pubkey::from_str(&req.token).is_err() || req.amount_sol < 0.01 || req.interval_hours == 0 || req.interval_hours > 24 * 30 {
+        return Ok(api_fail(&pool, &user_id, "api.invalid_dca").await);
+    }
+    if db::count_dca_orders(&pool, &user_id).await.unwrap_or(0) >= db::MAX_DCA_ORDERS {
+        return Ok(warp::reply::json(&ApiResponse { success: false, message: format!("Massimo {} ordini DCA per utente", db::MAX_DCA_ORDERS), tx_signature: "".into() }).into_response());
+    }
+    let max_per_trade = db::get_strategy_params(&pool, &user_id).await.max_per_trade_sol;
+    if req.amount_sol > max_per_trade {
+        return Ok(warp::reply::json(&ApiResponse { success: false, message: format!("Importo DCA oltre max_per_trade_sol ({} SOL)", max_per_trade), tx_signature: "".into() }).into_response());
+    }
+
+    let amount_lamports = (req.amount_sol * LAMPORTS_PER_SOL as f64) as u64;
+    match db::create_dca_order(&pool, &user_id, &req.token, amount_lamports, req.interval_hours as i64 * 3600).await {
+        Ok(id) => {
+            info!("🔁 DCA #{} creato [{}]: {} SOL -> {} ogni {}h", id, user_id, req.amount_sol, req.token, req.interval_hours);
+            Ok(warp::reply::json(&ApiResponse { success: true, message: format!("Ordine DCA #{} creato", id), tx_signature: "".into() }).into_response())
+        },
+        Err(e) => {
+            error!("dca create failed for {}: {}", user_id, e);
+            Ok(api_fail(&pool, &user_id, "api.db_error").await)
+        }
+    }
+}
+
+async fn handle_dca_action(order_id: i64, action: String, user_id: String, pool: db::DbPool) -> Result<Response, warp::Rejection> {
+    let status = match action.as_str() {
+        "pause" => "PAUSED",
+        "resume" => "ACTIVE",
+        "cancel" => "CANCELLED",
+        _ => return Ok(api_fail(&pool, &user_id, "api.invalid_action_dca").await),
+    };
+
+    match db::set_dca_status(&pool, &user_id, order_id, status).await {
+        Ok(true) => Ok(warp::reply::json(&ApiResponse { success: true, message: format!("Ordine DCA #{} -> {}", order_id, status), tx_signature: "".into() }).into_response()),
+        Ok(false) => Ok(api_fail(&pool, &user_id, "api.dca_not_found").await),
+        Err(e) => {
+            error!("dca update failed for {}: {}", user_id, e);
+            Ok(api_fail(&pool, &user_id, "api.db_error").await)
+        }
+    }
+}
+
+// --- ORDINI LIMITE ---
+
+async fn handle_limit_list(user_id: String, pool: db::DbPool) -> Result<Response, warp::Rejection> {
+    Ok(warp::reply::json(&db::get_limit_orders(&pool, &user_id).await.unwrap_or_default()).into_response())
+}
+
+async fn handle_limit_create(user_id: String, req: LimitRequest, pool: db::DbPool) -> Result<Response, warp::Rejection> {
+    let side = req.side.to_uppercase();
+    if Pubkey::from_str(&req.token).is_err() || req.trigger_price <= 0.0 || !(side == "BUY" || side == "SELL") || (side == "BUY" && req.amount_sol < 0.01) {
+        return Ok(api_fail(&pool, &user_id, "api.invalid_limit").await);
+    }
+    let max_per_trade = db::get_strategy_params(&pool, &user_id).await.max_per_trade_sol;
+    if side == "BUY" && req.amount_sol > max_per_trade {
+        return Ok(warp::reply::json(&ApiResponse { success: false, message: format!("Importo oltre max_per_trade_sol ({} SOL)", max_per_trade), tx_signature: "".into() }).into_response());
+    }
+
+    // Direzione dell'incrocio rispetto al prezzo attuale (default: Buy sotto, Sell sopra)
+    let trigger_above = match jupiter::get_token_market_data(&req.token).await {
+        Ok(m) if m.price > 0.0 => req.trigger_price > m.price,
+        _ => side == "SELL",
+    };
+
+    let amount_lamports = (req.amount_sol * LAMPORTS_PER_SOL as f64) as u64;
+    match db::create_limit_order(&pool, &user_id, &req.token, &side, req.trigger_price, trigger_above, amount_lamports).await {
+        Ok(id) => {
+            info!("🎯 LIMIT #{} creato [{}]: {} {} @ ${}", id, user_id, side, req.token, req.trigger_price);
+            Ok(warp::reply::json(&ApiResponse { success: true, message: format!("Ordine Limite #{} creato", id), tx_signature: "".into() }).into_response())
+        },
+        Err(e) => {
+            error!("limit create failed for {}: {}", user_id, e);
+            Ok(api_fail(&pool, &user_id, "api.db_error").await)
+        }
+    }
+}
+
+async fn handle_position_patch(trade_id: i64, user_id: String, req: crate::trading::ExitUpdate, pool: db::DbPool, state: Arc<AppState>) -> Result<Response, warp::Rejection> {
+    match crate::trading::update_position_exits(&pool, &state, &user_id, trade_id, req).await {
+        Ok(pos) => Ok(warp::reply::json(&pos).into_response()),
+        Err(e) => Ok(warp::reply::json(&ApiResponse { success: false, message: e, tx_signature: "".into() }).into_response()),
+    }
+}
+
+async fn handle_trade_notes(trade_id: i64, user_id: String, req: trade_notes::TradeNotes, pool: db::DbPool) -> Result<Response, warp::Rejection> {
+    let (success, message) = match trade_notes::annotate(&pool, &user_id, trade_id, req).await {
+        Ok(()) => (true, format!("Trade #{} aggiornato", trade_id)),
+        Err(e) => (false, e),
+    };
+    Ok(warp::reply::json(&ApiResponse { success, message, tx_signature: "".into() }).into_response())
+}
+
+async fn handle_limit_cancel(order_id: i64, user_id: String, pool: db::DbPool) -> Result<Response, warp::Rejection> {
+    match db::cancel_limit_order(&pool, &user_id, order_id).await {
+        Ok(true) => Ok(warp::reply::json(&ApiResponse { success: true, message: format!("Ordine Limite #{} cancellato", order_id), tx_signature: "".into() }).into_response()),
+        Ok(false) => Ok(api_fail(&pool, &user_id, "api.order_not_found").await),
+        Err(e) => {
+            error!("limit cancel failed for {}: {}", user_id, e);
+            Ok(api_fail(&pool, &user_id, "api.db_error").await)
+        }
+    }
+}
+
+async fn handle_pending_list(user_id: String, pool: db::DbPool) -> Result<Response, warp::Rejection> {
+    Ok(warp::reply::json(&db::get_pending_trades(&pool, &user_id, signals::PENDING_TTL_MINUTES).await.unwrap_or_default()).into_response())
+}
+
+async fn handle_pending_approve(id: i64, user_id: String, pool: db::DbPool, net: Arc<network::NetworkClient>) -> Result<Response, warp::Rejection> {
+    let (success, message, tx_signature) = match signals::approve(&pool, &net, &user_id, id).await {
+        Ok((sig, trade)) => (true, format!("Acquisto {} eseguito", trade.symbol), sig),
+        Err(e) => (false, e, String::new()),
+    };
+    Ok(warp::reply::json(&ApiResponse { success, message, tx_signature }).into_response())
+}
+
+async fn handle_pending_reject(id: i64, user_id: String, pool: db::DbPool) -> Result<Response, warp::Rejection> {
+    let (success, message) = match signals::reject(&pool, &user_id, id).await {
+        Ok(m) => (true, m),
+        Err(e) => (false, e),
+    };
+    Ok(warp::reply::json(&ApiResponse { success, message, tx_signature: "".into() }).into_response())
+}
+
+// --- ADMIN ---
+
+async fn handle_admin_stats(pool: db::DbPool, state: Arc<AppState>) -> Result<Response, warp::Rejection> {
+    match db::get_admin_totals(&pool).await {
+        Ok(totals) => Ok(warp::reply::json(&AdminStats {
+            totals,
+            tracked_positions: state.open_positions.len(),
+            failures: crate::metrics::METRICS.failure_counters(),
+        }).into_response()),
+        Err(e) => {
+            error!("admin totals failed: {}", e);
+            Ok(warp::reply::json(&ApiResponse { success: false, message: i18n::t(i18n::default_lang(), "api.db_error").into(), tx_signature: "".into() }).into_response())
+        },
+    }
+}
+
+async fn handle_sniper_stats(query: SniperStatsQuery, pool: db::DbPool) -> Result<Response, warp::Rejection> {
+    let since = chrono::Utc::now().timestamp() - query.days.unwrap_or(7).clamp(1, 90) * 86_400;
+    match db::get_sniper_hits(&pool, since).await {
+        Ok(hits) => Ok(warp::reply::json(&sniper_stats::scoreboard(hits)).into_response()),
+        Err(e) => {
+            error!("sniper stats failed: {}", e);
+            Ok(warp::reply::json(&ApiResponse { success: false, message: i18n::t(i18n::default_lang(), "api.db_error").into(), tx_signature: "".into() }).into_response())
+        },
+    }
+}
+
+async fn handle_admin_users(pool: db::DbPool, net: Arc<network::NetworkClient>) -> Result<Response, warp::Rejection> {
+    let users = match db::get_admin_users(&pool).await {
+        Ok(u) => u,
+        Err(e) => {
+            error!("admin users failed: {}", e);
+            return Ok(warp::reply::json(&ApiResponse { success: false, message: i18n::t(i18n::default_lang(), "api.db_error").into(), tx_signature: "".into() }).into_response());
+        },
+    };
+
+    // Saldi in blocchi da 100 (limite di getMultipleAccounts)
+    let mut balances: Vec<Option<f64>> = Vec::with_capacity(users.len());
+    for chunk in users.chunks(100) {
+        let keys: Vec<Pubkey> = chunk.iter().map(|u| Pubkey::from_str(&u.wallet_address).unwrap_or_default()).collect();
+        let accounts = {
+            let _t = crate::metrics::METRICS.rpc_timer("getMultipleAccounts");
+            net.rpc.get_multiple_accounts(&keys).await
+        };
+        match accounts {
+            Ok(accs) => balances.extend(accs.into_iter().map(|a| Some(a.map(|a| a.lamports).unwrap_or(0) as f64 / LAMPORTS_PER_SOL as f64))),
+            Err(_) => balances.extend(std::iter::repeat_n(None, chunk.len())),
         }
     }
 
-    Ok(warp::reply::json(&ApiResponse { success: false, message: "Indirizzo Invalido o Errore Rete".into(), tx_signature: "".into() }).into_response())
-}
\ No newline at end of file
+    let data: Vec<AdminUserData> = users.into_iter().zip(balances)
+        .map(|(user, balance_sol)| AdminUserData { user, balance_sol })
+        .collect();
+    Ok(warp::reply::json(&data).into_response())
+}
+
+async fn handle_admin_user_action(user_id: String, action: String, pool: db::DbPool) -> Result<Response, warp::Rejection> {
+    let reply = |success: bool, message: String| Ok(warp::reply::json(&ApiResponse { success, message, tx_signature: "".into() }).into_response());
+    // L'admin non ha un utente: risposte nella lingua di deployment, notifica nella lingua dell'utente
+    let admin_lang = i18n::default_lang();
+
+    let res = match action.as_str() {
+        "stop" => match db::stop_daily_cycle(&pool, &user_id).await {
+            Ok(_) => db::stop_user_grids(&pool, &user_id).await.map(|_| true),
+            Err(e) => Err(e),
+        },
+        "ban" => db::set_user_banned(&pool, &user_id, true).await,
+        "unban" => db::set_user_banned(&pool, &user_id, false).await,
+        _ => return reply(false, i18n::t(admin_lang, "api.invalid_action_admin").into()),
+    };
+    match res {
+        Ok(false) => reply(false, i18n::t(admin_lang, "api.user_not_found").into()),
+        Ok(true) => {
+            info!("🛡️ ADMIN: {} su {}", action, user_id);
+            if action == "stop" { activity::record(&pool, &user_id, activity::Event::bot_stop("amministratore")).await; }
+            let key = match action.as_str() {
+                "stop" => "tg.admin_stopped",
+                "ban" => "tg.admin_banned",
+                _ => "tg.admin_unbanned",
+            };
+            let lang = i18n::user_lang(&pool, &user_id).await;
+            telegram_bot::notify_user(&user_id, i18n::t(lang, key).to_string()).await;
+            reply(true, i18n::tr(admin_lang, "api.admin_action_done", &[&user_id, &action]))
+        },
+        Err(e) => {
+            error!("admin {} failed for {}: {}", action, user_id, e);
+            reply(false, i18n::t(admin_lang, "api.db_error").into())
+        },
+    }
+}
+
+fn gem_filters_reply(result: Result<crate::gem_filters::GemFilters, String>) -> Response {
+    match result {
+        Ok(filters) => {
+            info!("🛡️ ADMIN: filtri gemme -> {:?}", filters);
+            warp::reply::json(&ApiResponse { success: true, message: format!("Filtri gemme: {:?}", filters), tx_signature: "".into() }).into_response()
+        },
+        Err(e) => warp::reply::json(&ApiResponse { success: false, message: e, tx_signature: "".into() }).into_response(),
+    }
+}
+
+async fn handle_admin_log_level(req: LogLevelRequest) -> Result<Response, warp::Rejection> {
+    match crate::logging::set_filter(req.filter.trim()) {
+        Ok(filter) => {
+            info!("🛡️ ADMIN: filtro log -> {}", filter);
+            Ok(warp::reply::json(&ApiResponse { success: true, message: format!("Filtro log: {}", filter), tx_signature: "".into() }).into_response())
+        },
+        Err(e) => Ok(warp::reply::json(&ApiResponse { success: false, message: e, tx_signature: "".into() }).into_response()),
+    }
+}
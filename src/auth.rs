@@ -0,0 +1,116 @@
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha2::Sha256;
+
+// Durata di una sessione Web
+pub const SESSION_TTL_HOURS: i64 = 24 * 7;
+// Validità dei link email (verifica indirizzo e reset password)
+pub const EMAIL_VERIFY_TTL_SECS: i64 = 48 * 3600;
+pub const PASSWORD_RESET_TTL_SECS: i64 = 3600;
+// Validità di initData della Telegram Web App (da auth_date)
+pub const INIT_DATA_TTL_SECS: i64 = 24 * 3600;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Hash Argon2id con salt casuale (formato PHC, include i parametri)
+pub fn hash_password(password: &str) -> Result<String, String> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|h| h.to_string())
+        .map_err(|e| format!("Errore hashing password: {}", e))
+}
+
+/// Verifica una password contro l'hash salvato
+pub fn verify_password(password: &str, stored_hash: &str) -> bool {
+    match PasswordHash::new(stored_hash) {
+        Ok(parsed) => Argon2::default().verify_password(password.as_bytes(), &parsed).is_ok(),
+        Err(_) => false,
+    }
+}
+
+/// Token di sessione opaco (256 bit casuali)
+pub fn new_session_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::rngs::OsRng.fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+/// Id pubblico del dispositivo di una sessione (64 bit casuali)
+pub fn new_device_id() -> String {
+    let mut bytes = [0u8; 8];
+    rand::rngs::OsRng.fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+/// Controllo di forma dell'email: locale@dominio.tld, senza spazi
+pub fn valid_email(email: &str) -> bool {
+    let Some((local, domain)) = email.split_once('@') else { return false };
+    !local.is_empty() && email.len() <= 254 && !email.chars().any(char::is_whitespace)
+        && !domain.contains('@') && domain.contains('.') && !domain.starts_with('.') && !domain.ends_with('.')
+}
+
+/// Token dei link email (256 bit casuali) e relativo hash firmato da salvare nel DB
+pub fn new_email_token(purpose: &str) -> (String, String) {
+    let token = new_session_token();
+    let hash = email_token_hash(purpose, &token);
+    (token, hash)
+}
+
+/// HMAC-SHA256 (chiave MASTER_KEY) di scopo e token: un dump del DB non basta per usare i link.
+/// Una MASTER_KEY vuota renderebbe gli hash calcolabili da chiunque: si rifiuta come se mancasse.
+pub fn email_token_hash(purpose: &str, token: &str) -> String {
+    let key = std::env::var("MASTER_KEY").ok().filter(|k| !k.trim().is_empty()).expect("❌ Manca MASTER_KEY nel .env");
+    let mut mac = HmacSha256::new_from_slice(key.as_bytes()).expect("HMAC accetta chiavi di ogni lunghezza");
+    mac.update(purpose.as_bytes());
+    mac.update(b":");
+    mac.update(token.trim().as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// Hash da salvare e cercare nel DB per un token di sessione (stessa HMAC dei link email, scopo SESSION)
+pub fn session_token_hash(token: &str) -> String {
+    email_token_hash("SESSION", token)
+}
+
+/// Verifica initData della Telegram Web App: HMAC-SHA256 dei campi ordinati con chiave
+/// HMAC("WebAppData", token del bot). Ritorna il Telegram ID se la firma è valida e non scaduta.
+pub fn verify_telegram_init_data(init_data: &str, bot_token: &str, now: i64) -> Option<String> {
+    let mut hash = None;
+    let mut fields: Vec<(String, String)> = Vec::new();
+    for (key, value) in form_urlencoded::parse(init_data.as_bytes()) {
+        if key == "hash" { hash = Some(value.into_owned()); } else { fields.push((key.into_owned(), value.into_owned())); }
+    }
+    let expected = hex::decode(hash?).ok()?;
+    fields.sort();
+    let check_string = fields.iter().map(|(k, v)| format!("{}={}", k, v)).collect::<Vec<_>>().join("\n");
+
+    let mut secret = HmacSha256::new_from_slice(b"WebAppData").ok()?;
+    secret.update(bot_token.as_bytes());
+    let mut mac = HmacSha256::new_from_slice(&secret.finalize().into_bytes()).ok()?;
+    mac.update(check_string.as_bytes());
+    // Confronto a tempo costante
+    mac.verify_slice(&expected).ok()?;
+
+    let field = |name: &str| fields.iter().find(|(k, _)| k == name).map(|(_, v)| v.as_str());
+    let auth_date: i64 = field("auth_date")?.parse().ok()?;
+    if now - auth_date > INIT_DATA_TTL_SECS { return None; }
+
+    let user: serde_json::Value = serde_json::from_str(field("user")?).ok()?;
+    user.get("id")?.as_i64().map(|id| id.to_string())
+}
+
+/// Confronto a tempo costante del token Admin (HMAC di entrambi i valori con la stessa chiave)
+pub fn verify_admin_token(provided: &str, expected: &str) -> bool {
+    let tag = |value: &str| {
+        let mut mac = HmacSha256::new_from_slice(b"god_sniper_admin").expect("HMAC accetta chiavi di ogni lunghezza");
+        mac.update(value.as_bytes());
+        mac
+    };
+    let expected_tag = tag(expected).finalize().into_bytes();
+    !expected.is_empty() && tag(provided).verify_slice(&expected_tag).is_ok()
+}
@@ -0,0 +1,103 @@
+use std::sync::Arc;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use solana_sdk::signature::Signer;
+use log::{info, warn};
+use crate::{db, i18n, network, price_oracle, risk, swap_router, telegram_bot, wallet_manager};
+
+// --- AUTO-PARK (Profitti in USDC, users.settings["auto_park"]) ---
+// 1. Dopo ogni vendita in profitto, `percent`% del ricavato (SOL) viene convertito in USDC via Router
+// 2. Lo swap parte in background: la vendita e il suo PnL non aspettano la conversione
+// 3. Gli USDC parcheggiati escono dal saldo di trading: il sizing guarda solo i SOL, la griglia non spende
+//    la quota parcheggiata (spendable_usdc), Dust Sweeper e Reconciler ignorano già le stablecoin
+// 4. `parked_usdc` è il contatore della quota bloccata: /park release (o l'API) la rimette a disposizione
+
+pub const USDC_MINT: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+const SOL_MINT: &str = "So11111111111111111111111111111111111111112";
+// Sotto questa cifra lo swap costa più di quanto protegge
+const MIN_PARK_LAMPORTS: u64 = 10_000_000;
+const PARK_SLIPPAGE_BPS: u16 = 100;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct AutoParkSettings {
+    pub enabled: bool,
+    // Quota del ricavato delle vendite in profitto da convertire (1-100)
+    pub percent: f64,
+    // Totali convertiti finora (USDC in unità base, 6 decimali)
+    pub parked_sol: f64,
+    pub parked_usdc: u64,
+}
+
+impl Default for AutoParkSettings {
+    fn default() -> Self {
+        Self { enabled: false, percent: 25.0, parked_sol: 0.0, parked_usdc: 0 }
+    }
+}
+
+impl AutoParkSettings {
+    pub fn from_settings(settings: &serde_json::Value) -> Self {
+        serde_json::from_value(settings["auto_park"].clone()).unwrap_or_default()
+    }
+
+    pub fn valid_percent(percent: f64) -> bool {
+        percent > 0.0 && percent <= 100.0
+    }
+}
+
+pub async fn save(pool: &db::DbPool, user_id: &str, park: &AutoParkSettings) -> Result<(), sqlx::Error> {
+    db::update_setting(pool, user_id, "auto_park", json!(park)).await
+}
+
+/// Saldo USDC spendibile dal trading: la quota parcheggiata resta ferma
+pub async fn spendable_usdc(pool: &db::DbPool, user_id: &str, balance: u64) -> u64 {
+    let settings = db::get_settings(pool, user_id).await.unwrap_or_default();
+    balance.saturating_sub(AutoParkSettings::from_settings(&settings).parked_usdc)
+}
+
+/// Dopo una vendita (capitale venduto + PnL = ricavo): parcheggia la quota se il PnL è positivo (non blocca il chiamante)
+pub fn park_profit(pool: &db::DbPool, net: &Arc<network::NetworkClient>, user_id: &str, sold_lamports: u64, pnl_sol: f64) {
+    if pnl_sol <= 0.0 || sold_lamports == 0 { return; }
+    let proceeds_lamports = sold_lamports + (pnl_sol * 1_000_000_000.0) as u64;
+    let (pool, net, user_id) = (pool.clone(), net.clone(), user_id.to_string());
+    tokio::spawn(async move {
+        if let Err(e) = park(&pool, &net, &user_id, proceeds_lamports).await {
+            warn!("⚠️ Auto-Park ({}) non riuscito: {}", user_id, e);
+        }
+    });
+}
+
+async fn park(pool: &db::DbPool, net: &Arc<network::NetworkClient>, user_id: &str, proceeds_lamports: u64) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let park = AutoParkSettings::from_settings(&db::get_settings(pool, user_id).await?);
+    if !park.enabled || !AutoParkSettings::valid_percent(park.percent) { return Ok(()); }
+
+    // Mai sotto la riserva dell'utente
+    let payer = wallet_manager::get_decrypted_wallet(pool, user_id).await?;
+    let params = db::get_strategy_params(pool, user_id).await;
+    let balance = net.get_balance_fast(&payer.pubkey()).await;
+    let spendable = risk::buy_budget(pool, user_id, &params, balance).await?.spendable;
+    let amount = ((proceeds_lamports as f64 * park.percent / 100.0) as u64).min(spendable);
+    if amount < MIN_PARK_LAMPORTS { return Ok(()); }
+
+    let out = swap_router::swap_pair(net, pool, user_id, &payer, (SOL_MINT, USDC_MINT), amount, PARK_SLIPPAGE_BPS).await?;
+    let amount_sol = amount as f64 / 1_000_000_000.0;
+    // Route senza quote: USDC stimati dal prezzo SOL
+    let usdc = if out.expected_out > 0 {
+        out.expected_out
+    } else {
+        (amount_sol * price_oracle::sol_usd().await.unwrap_or(0.0) * 1_000_000.0) as u64
+    };
+    info!("🅿️ AUTO-PARK ({}) {:.4} SOL -> {:.2} USDC via {} TX: {}", user_id, amount_sol, usdc as f64 / 1_000_000.0, out.route.label(), out.signature);
+
+    // Riletto dopo lo swap: nel frattempo l'utente può aver cambiato le impostazioni
+    let mut latest = AutoParkSettings::from_settings(&db::get_settings(pool, user_id).await?);
+    latest.parked_sol += amount_sol;
+    latest.parked_usdc += usdc;
+    save(pool, user_id, &latest).await?;
+
+    let lang = i18n::user_lang(pool, user_id).await;
+    telegram_bot::notify_user(user_id, i18n::tr(lang, "notify.parked", &[
+        &format!("{:.4}", amount_sol), &format!("{:.2}", usdc as f64 / 1_000_000.0), &park.percent, &format!("{:.2}", latest.parked_usdc as f64 / 1_000_000.0), &out.signature,
+    ])).await;
+    Ok(())
+}
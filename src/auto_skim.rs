@@ -0,0 +1,174 @@
+use std::str::FromStr;
+use std::sync::Arc;
+use chrono::{Months, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use solana_sdk::pubkey::Pubkey;
+use tokio::time::Duration;
+use log::{info, warn};
+use crate::{db, i18n, network, telegram_bot, wallet_manager, withdrawals, AppState};
+
+// --- AUTO-SKIM (Prelievi ricorrenti dei profitti, users.settings["auto_skim"]) ---
+// 1. Ogni settimana o mese il SOL sopra `floor_sol` viene prelevato verso un indirizzo della whitelist prelievi
+// 2. Si passa da withdrawals::withdraw: blocco 24h, indirizzo confermato e approvazione Telegram sopra soglia restano validi
+// 3. A ogni scadenza l'utente riceve il riepilogo su Telegram (inviato, in attesa di approvazione, saltato o fallito)
+// 4. Un errore non fa riprovare a raffica: il prossimo tentativo è alla scadenza successiva
+
+const CHECK_SECS: u64 = 600;
+// Sotto questa cifra il prelievo non vale la fee
+const MIN_SKIM_LAMPORTS: u64 = 10_000_000;
+// Lasciati nel wallet oltre al floor per la fee del trasferimento
+const FEE_RESERVE_LAMPORTS: u64 = 5_000;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SkimPeriod { Weekly, Monthly }
+
+impl SkimPeriod {
+    pub fn from_code(code: &str) -> Option<Self> {
+        match code.trim().to_lowercase().as_str() {
+            "weekly" => Some(SkimPeriod::Weekly),
+            "monthly" => Some(SkimPeriod::Monthly),
+            _ => None,
+        }
+    }
+
+    pub fn code(self) -> &'static str {
+        match self {
+            SkimPeriod::Weekly => "weekly",
+            SkimPeriod::Monthly => "monthly",
+        }
+    }
+
+    /// Prossima scadenza dopo `from` (unix)
+    pub fn next_after(self, from: i64) -> i64 {
+        let Some(start) = Utc.timestamp_opt(from, 0).single() else { return from + 7 * 86_400 };
+        match self {
+            SkimPeriod::Weekly => (start + chrono::Duration::days(7)).timestamp(),
+            SkimPeriod::Monthly => start.checked_add_months(Months::new(1)).map(|d| d.timestamp()).unwrap_or(from + 30 * 86_400),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct AutoSkimSettings {
+    pub enabled: bool,
+    pub period: SkimPeriod,
+    // Saldo SOL che resta sempre nel wallet
+    pub floor_sol: f64,
+    // Indirizzo della whitelist prelievi
+    pub destination: Option<String>,
+    // Prossima esecuzione (unix)
+    pub next_run: i64,
+    pub skimmed_sol: f64,
+}
+
+impl Default for AutoSkimSettings {
+    fn default() -> Self {
+        Self { enabled: false, period: SkimPeriod::Weekly, floor_sol: 1.0, destination: None, next_run: 0, skimmed_sol: 0.0 }
+    }
+}
+
+impl AutoSkimSettings {
+    pub fn from_settings(settings: &serde_json::Value) -> Self {
+        serde_json::from_value(settings["auto_skim"].clone()).unwrap_or_default()
+    }
+
+    pub fn valid_floor(floor_sol: f64) -> bool {
+        floor_sol.is_finite() && floor_sol >= 0.0
+    }
+}
+
+pub async fn save(pool: &db::DbPool, user_id: &str, skim: &AutoSkimSettings) -> Result<(), sqlx::Error> {
+    db::update_setting(pool, user_id, "auto_skim", json!(skim)).await
+}
+
+/// Attiva il prelievo ricorrente: l'indirizzo deve essere nella whitelist prelievi dell'utente
+pub async fn enable(pool: &db::DbPool, user_id: &str, period: SkimPeriod, floor_sol: f64, destination: &str, lang: i18n::Lang) -> Result<AutoSkimSettings, String> {
+    let db_error = |e: sqlx::Error| {
+        warn!("⚠️ Auto-Skim ({}): errore DB: {}", user_id, e);
+        i18n::t(lang, "api.db_error").to_string()
+    };
+    if !AutoSkimSettings::valid_floor(floor_sol) { return Err(i18n::t(lang, "skim.invalid_floor").into()); }
+    let destination = Pubkey::from_str(destination.trim()).map_err(|_| i18n::t(lang, "withdraw.invalid_address"))?.to_string();
+    db::get_withdrawal_address(pool, user_id, &destination).await
+        .map_err(db_error)?
+        .ok_or(i18n::t(lang, "skim.not_whitelisted"))?;
+
+    let settings = db::get_settings(pool, user_id).await.map_err(db_error)?;
+    let mut skim = AutoSkimSettings::from_settings(&settings);
+    skim.enabled = true;
+    skim.period = period;
+    skim.floor_sol = floor_sol;
+    skim.destination = Some(destination);
+    skim.next_run = period.next_after(Utc::now().timestamp());
+    save(pool, user_id, &skim).await.map_err(db_error)?;
+    info!("💶 Auto-Skim ({}) {} sopra {} SOL", user_id, period.code(), floor_sol);
+    Ok(skim)
+}
+
+pub async fn run_auto_skim(pool: db::DbPool, net: Arc<network::NetworkClient>, state: Arc<AppState>) {
+    info!("💶 Auto-Skim: ONLINE");
+
+    loop {
+        let now = Utc::now().timestamp();
+        for (user_id, settings) in db::get_all_user_settings(&pool).await.unwrap_or_default() {
+            if state.is_shutting_down() { break; }
+            let skim = AutoSkimSettings::from_settings(&settings);
+            if !skim.enabled || skim.next_run > now { continue; }
+            let Some(dest) = skim.destination.clone() else { continue };
+
+            let lang = i18n::user_lang(&pool, &user_id).await;
+            let text = match skim_user(&pool, &net, &user_id, &skim, &dest).await {
+                Ok(Some((amount, withdrawals::WithdrawOutcome::Sent(sig)))) => {
+                    info!("💶 Auto-Skim ({}) {} lamports -> {} ({})", user_id, amount, dest, sig);
+                    record_skim(&pool, &user_id, amount).await;
+                    i18n::tr(lang, "notify.skim_sent", &[&sol(amount), &dest, &skim.floor_sol, &sig])
+                },
+                Ok(Some((amount, withdrawals::WithdrawOutcome::AwaitingApproval(id)))) => {
+                    i18n::tr(lang, "notify.skim_pending", &[&sol(amount), &id])
+                },
+                Ok(None) => i18n::tr(lang, "notify.skim_skipped", &[&skim.floor_sol]),
+                Err(e) => {
+                    warn!("⚠️ Auto-Skim ({}) fallito: {}", user_id, e);
+                    i18n::tr(lang, "notify.skim_failed", &[&e])
+                },
+            };
+
+            // Riletto: il totale può essere appena cambiato e l'utente può aver modificato le impostazioni
+            let mut latest = AutoSkimSettings::from_settings(&db::get_settings(&pool, &user_id).await.unwrap_or_default());
+            latest.next_run = latest.period.next_after(now);
+            if let Err(e) = save(&pool, &user_id, &latest).await {
+                warn!("⚠️ Auto-Skim ({}): prossima scadenza non salvata: {}", user_id, e);
+            }
+            let next = Utc.timestamp_opt(latest.next_run, 0).single().map(|d| d.format("%d/%m/%Y").to_string()).unwrap_or_default();
+            telegram_bot::notify_user(&user_id, format!("{}\n{}", text, i18n::tr(lang, "notify.skim_next", &[&next]))).await;
+        }
+        if state.sleep_or_shutdown(Duration::from_secs(CHECK_SECS)).await { break; }
+    }
+    info!("🛑 Auto-Skim fermato.");
+}
+
+/// Preleva il saldo sopra il floor. None = niente da prelevare.
+async fn skim_user(pool: &db::DbPool, net: &Arc<network::NetworkClient>, user_id: &str, skim: &AutoSkimSettings, dest: &str) -> Result<Option<(u64, withdrawals::WithdrawOutcome)>, String> {
+    let owner = wallet_manager::create_user_wallet(pool, user_id).await.ok()
+        .and_then(|s| Pubkey::from_str(&s).ok())
+        .ok_or("Wallet Error")?;
+    let floor = (skim.floor_sol * 1_000_000_000.0) as u64;
+    let amount = net.get_balance_fast(&owner).await.saturating_sub(floor + FEE_RESERVE_LAMPORTS);
+    if amount < MIN_SKIM_LAMPORTS { return Ok(None); }
+    withdrawals::withdraw(pool, net, user_id, amount, dest).await.map(|outcome| Some((amount, outcome)))
+}
+
+async fn record_skim(pool: &db::DbPool, user_id: &str, amount: u64) {
+    let mut latest = AutoSkimSettings::from_settings(&db::get_settings(pool, user_id).await.unwrap_or_default());
+    latest.skimmed_sol += amount as f64 / 1_000_000_000.0;
+    if let Err(e) = save(pool, user_id, &latest).await {
+        warn!("⚠️ Auto-Skim ({}): totale non aggiornato: {}", user_id, e);
+    }
+}
+
+fn sol(lamports: u64) -> String {
+    format!("{:.4}", lamports as f64 / 1_000_000_000.0)
+}
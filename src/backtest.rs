@@ -0,0 +1,126 @@
+use serde::{Serialize, Deserialize};
+use std::error::Error;
+use crate::birdeye::{self, OhlcvCandle};
+use crate::strategy::{self, Candle, MarketData, StrategyParams, TradeAction};
+
+// Parametri di una simulazione
+#[derive(Deserialize, Clone, Debug)]
+pub struct BacktestParams {
+    pub token: String,
+    #[serde(default = "default_interval")]
+    pub interval: String,     // 1m, 5m, 15m, 1H...
+    #[serde(default = "default_days")]
+    pub days: u32,            // Finestra storica
+    #[serde(default = "default_initial_sol")]
+    pub initial_sol: f64,     // Capitale simulato
+    #[serde(default = "default_fee_bps")]
+    pub fee_bps: u32,         // Fee + slippage stimati per ogni swap
+    #[serde(default)]
+    pub strategy: StrategyParams, // Set di parametri da testare
+}
+
+fn default_interval() -> String { "5m".into() }
+fn default_days() -> u32 { 7 }
+fn default_initial_sol() -> f64 { 1.0 }
+fn default_fee_bps() -> u32 { 100 }
+
+#[derive(Serialize, Clone, Debug)]
+pub struct BacktestTrade {
+    pub entry_time: i64, pub exit_time: i64, pub entry_price: f64, pub exit_price: f64, pub pnl_sol: f64, pub reason: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct BacktestReport {
+    pub token: String,
+    pub interval: String,
+    pub candles: usize,
+    pub trades: Vec<BacktestTrade>,
+    pub win_rate: f64,
+    pub total_pnl_sol: f64,
+    pub total_pnl_pct: f64,
+    pub max_drawdown_pct: f64,
+    pub pnl_curve: Vec<(i64, f64)>, // (timestamp, equity SOL)
+}
+
+// Posizione simulata
+struct SimPosition { entry_time: i64, entry_price: f64, amount_sol: f64, high_val: u64 }
+
+/// Scarica lo storico e lancia la simulazione
+pub async fn run_backtest(params: &BacktestParams) -> Result<BacktestReport, Box<dyn Error + Send + Sync>> {
+    let now = chrono::Utc::now().timestamp();
+    let from = now - params.days as i64 * 86_400;
+    let candles = birdeye::get_ohlcv(&params.token, &params.interval, from, now).await?;
+    if candles.is_empty() { return Err("Nessuna candela storica disponibile".into()); }
+    Ok(simulate(params, &candles))
+}
+
+/// Replay delle candele attraverso lo stesso motore del bot live (analyze_market + check_position)
+pub fn simulate(params: &BacktestParams, candles: &[OhlcvCandle]) -> BacktestReport {
+    let fee = params.fee_bps as f64 / 10_000.0;
+    let mut data = MarketData::new(&params.token);
+    let mut cash = params.initial_sol;
+    let mut position: Option<SimPosition> = None;
+    let mut pending_buy: Option<f64> = None; // Segnale sulla chiusura, esecuzione all'apertura successiva
+    let mut trades = Vec::new();
+    let mut curve = Vec::with_capacity(candles.len());
+    let mut peak = cash;
+    let mut max_dd = 0.0_f64;
+
+    for c in candles {
+        // 1. Esecuzione ordine pendente all'apertura
+        if let Some(amount) = pending_buy.take() {
+            if c.open > 0.0 && amount <= cash {
+                cash -= amount;
+                let lamports = (amount * (1.0 - fee) * 1_000_000_000.0) as u64;
+                position = Some(SimPosition { entry_time: c.time, entry_price: c.open, amount_sol: amount * (1.0 - fee), high_val: lamports });
+            }
+        }
+
+        data.add_candle(Candle { high: c.high, low: c.low, close: c.close, volume: c.volume });
+        let signal = strategy::analyze_market(&data, cash, &params.strategy);
+
+        // 2. Gestione Uscita (Trailing Stop + Segnale Overbought)
+        if let Some(pos) = position.as_mut() {
+            let value_sol = pos.amount_sol * c.close / pos.entry_price;
+            let value_lam = (value_sol * 1_000_000_000.0) as u64;
+            let exit_reason = match strategy::check_position(value_lam, pos.high_val, &params.strategy, strategy::atr_pct(&data.candles)) {
+                TradeAction::UpdateHigh(h) => { pos.high_val = h; None },
+                TradeAction::Sell(r) => Some(r),
+                _ => match &signal { TradeAction::Sell(r) => Some(r.clone()), _ => None },
+            };
+            if let Some(reason) = exit_reason {
+                let received = value_sol * (1.0 - fee);
+                cash += received;
+                trades.push(BacktestTrade {
+                    entry_time: pos.entry_time, exit_time: c.time, entry_price: pos.entry_price, exit_price: c.close,
+                    pnl_sol: received - pos.amount_sol / (1.0 - fee), reason,
+                });
+                position = None;
+            }
+        } else if let TradeAction::Buy { amount_sol, .. } = signal {
+            pending_buy = Some(amount_sol.min(cash));
+        }
+
+        // 3. Equity Curve e Drawdown
+        let equity = cash + position.as_ref().map(|p| p.amount_sol * c.close / p.entry_price).unwrap_or(0.0);
+        if equity > peak { peak = equity; }
+        if peak > 0.0 { max_dd = max_dd.max((peak - equity) / peak * 100.0); }
+        curve.push((c.time, equity));
+    }
+
+    let final_equity = curve.last().map(|(_, e)| *e).unwrap_or(params.initial_sol);
+    let wins = trades.iter().filter(|t| t.pnl_sol > 0.0).count();
+    let win_rate = if trades.is_empty() { 0.0 } else { wins as f64 / trades.len() as f64 * 100.0 };
+
+    BacktestReport {
+        token: params.token.clone(),
+        interval: params.interval.clone(),
+        candles: candles.len(),
+        trades,
+        win_rate,
+        total_pnl_sol: final_equity - params.initial_sol,
+        total_pnl_pct: (final_equity / params.initial_sol - 1.0) * 100.0,
+        max_drawdown_pct: max_dd,
+        pnl_curve: curve,
+    }
+}
@@ -1,7 +1,6 @@
 use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions, SqliteJournalMode};
-use sqlx::{ConnectOptions, Executor};
+use sqlx::Executor;
 use std::str::FromStr;
-use tokio;
 
 #[tokio::main]
 async fn main() {
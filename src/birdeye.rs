@@ -0,0 +1,185 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::env;
+use reqwest;
+
+const BIRDEYE_API: &str = "https://public-api.birdeye.so";
+// Limite di indirizzi per singola chiamata multi_price
+const MULTI_PRICE_MAX: usize = 100;
+
+#[derive(Deserialize, Debug)]
+struct BirdeyeResponse<T> { success: bool, data: Option<T> }
+#[derive(Deserialize, Debug)]
+struct OhlcvData { items: Vec<OhlcvItem> }
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct OhlcvItem { o: f64, h: f64, l: f64, c: f64, v: f64, unix_time: i64 }
+#[derive(Deserialize, Debug)]
+struct PriceItem { value: Option<f64> }
+#[derive(Deserialize, Debug)]
+struct TradesData { items: Vec<TradeItem> }
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct TradeItem { tx_hash: String, block_unix_time: i64, side: String, from: TradeLeg, to: TradeLeg, owner: Option<String> }
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct TradeLeg { ui_amount: Option<f64>, price: Option<f64>, nearest_price: Option<f64> }
+
+impl TradeLeg {
+    fn value_usd(&self) -> Option<f64> {
+        Some(self.ui_amount? * self.price.or(self.nearest_price)?)
+    }
+}
+
+// Dati di sicurezza Birdeye (quote in frazione 0-1 come da API, None se non note)
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenSecurity {
+    pub creator_address: Option<String>,
+    pub creator_percentage: Option<f64>,
+    pub owner_address: Option<String>,
+    pub owner_percentage: Option<f64>,
+    pub top10_holder_percent: Option<f64>,
+    pub mutable_metadata: Option<bool>,
+    pub freezeable: Option<bool>,
+    pub transfer_fee_enable: Option<bool>,
+    pub is_token2022: Option<bool>,
+    pub non_transferable: Option<bool>,
+}
+
+// Panoramica Birdeye: holder e wallet unici nelle ultime 24h (None se non noti)
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenOverview {
+    pub holder: Option<u64>,
+    pub unique_wallet_24h: Option<u64>,
+    // Variazione % dei wallet unici rispetto alle 24h precedenti
+    pub unique_wallet_24h_change_percent: Option<f64>,
+}
+
+// Swap recente su un token (lato dal punto di vista del token: buy = qualcuno lo compra)
+#[derive(Clone, Debug)]
+pub struct TokenTrade {
+    pub tx_hash: String,
+    pub time: i64,
+    pub is_buy: bool,
+    pub volume_usd: f64,
+    pub owner: String,
+}
+
+// Candela storica (OHLCV) con timestamp
+#[derive(Clone, Copy, Debug)]
+pub struct OhlcvCandle {
+    pub time: i64, pub open: f64, pub high: f64, pub low: f64, pub close: f64, pub volume: f64
+}
+
+fn api_key() -> Result<String, Box<dyn Error + Send + Sync>> {
+    env::var("BIRDEYE_API_KEY").map_err(|_| "Manca BIRDEYE_API_KEY nel .env".into())
+}
+
+/// Durata in secondi di un intervallo Birdeye ("5m" -> 300)
+pub fn interval_secs(interval: &str) -> Option<i64> {
+    match interval {
+        "1m" => Some(60), "3m" => Some(180), "5m" => Some(300), "15m" => Some(900), "30m" => Some(1_800),
+        "1H" => Some(3_600), "2H" => Some(7_200), "4H" => Some(14_400), "6H" => Some(21_600), "12H" => Some(43_200),
+        "1D" => Some(86_400),
+        _ => None,
+    }
+}
+
+/// Scarica le candele storiche di un token (interval: 1m, 5m, 15m, 1H, 4H, 1D...)
+pub async fn get_ohlcv(mint: &str, interval: &str, time_from: i64, time_to: i64) -> Result<Vec<OhlcvCandle>, Box<dyn Error + Send + Sync>> {
+    let client = reqwest::Client::new();
+    let url = format!("{}/defi/ohlcv?address={}&type={}&time_from={}&time_to={}", BIRDEYE_API, mint, interval, time_from, time_to);
+    let resp: BirdeyeResponse<OhlcvData> = client.get(&url)
+        .header("X-API-KEY", api_key()?)
+        .header("x-chain", "solana")
+        .send().await?
+        .json().await?;
+
+    match resp.data {
+        Some(d) if resp.success => Ok(d.items.into_iter().map(|i| OhlcvCandle {
+            time: i.unix_time, open: i.o, high: i.h, low: i.l, close: i.c, volume: i.v
+        }).collect()),
+        _ => Err(format!("Birdeye: nessun dato OHLCV per {}", mint).into()),
+    }
+}
+
+/// Prezzi USD di più token in una sola chiamata (a blocchi di 100). I token senza dato non compaiono nella mappa.
+pub async fn get_multi_price(mints: &[String]) -> Result<HashMap<String, f64>, Box<dyn Error + Send + Sync>> {
+    let client = reqwest::Client::new();
+    let key = api_key()?;
+    let mut prices = HashMap::new();
+
+    for chunk in mints.chunks(MULTI_PRICE_MAX) {
+        let url = format!("{}/defi/multi_price?list_address={}", BIRDEYE_API, chunk.join(","));
+        let resp: BirdeyeResponse<HashMap<String, Option<PriceItem>>> = client.get(&url)
+            .header("X-API-KEY", &key)
+            .header("x-chain", "solana")
+            .send().await?
+            .json().await?;
+
+        if !resp.success { return Err("Birdeye: multi_price non riuscito".into()); }
+        for (mint, item) in resp.data.unwrap_or_default() {
+            if let Some(price) = item.and_then(|i| i.value).filter(|p| *p > 0.0) {
+                prices.insert(mint, price);
+            }
+        }
+    }
+    Ok(prices)
+}
+
+/// Ultimi swap del token (più recenti prima, max 50)
+pub async fn get_token_trades(mint: &str, limit: u32) -> Result<Vec<TokenTrade>, Box<dyn Error + Send + Sync>> {
+    let client = reqwest::Client::new();
+    let url = format!("{}/defi/txs/token?address={}&tx_type=swap&offset=0&limit={}", BIRDEYE_API, mint, limit.min(50));
+    let resp: BirdeyeResponse<TradesData> = client.get(&url)
+        .header("X-API-KEY", api_key()?)
+        .header("x-chain", "solana")
+        .send().await?
+        .json().await?;
+
+    match resp.data {
+        Some(d) if resp.success => Ok(d.items.into_iter().map(|i| TokenTrade {
+            volume_usd: i.from.value_usd().or_else(|| i.to.value_usd()).unwrap_or(0.0),
+            is_buy: i.side == "buy",
+            tx_hash: i.tx_hash,
+            time: i.block_unix_time,
+            owner: i.owner.unwrap_or_default(),
+        }).collect()),
+        _ => Err(format!("Birdeye: nessuno swap per {}", mint).into()),
+    }
+}
+
+/// Dati di sicurezza del token (creator, top holder, metadata modificabili, freeze, tasse Token-2022)
+/// Holder e wallet unici 24h di un token
+pub async fn get_token_overview(mint: &str) -> Result<TokenOverview, Box<dyn Error + Send + Sync>> {
+    let client = reqwest::Client::new();
+    let url = format!("{}/defi/token_overview?address={}", BIRDEYE_API, mint);
+    let resp: BirdeyeResponse<TokenOverview> = client.get(&url)
+        .header("X-API-KEY", api_key()?)
+        .header("x-chain", "solana")
+        .send().await?
+        .json().await?;
+
+    match resp.data {
+        Some(d) if resp.success => Ok(d),
+        _ => Err(format!("Birdeye: nessuna panoramica per {}", mint).into()),
+    }
+}
+
+pub async fn get_token_security(mint: &str) -> Result<TokenSecurity, Box<dyn Error + Send + Sync>> {
+    let client = reqwest::Client::new();
+    let url = format!("{}/defi/token_security?address={}", BIRDEYE_API, mint);
+    let resp: BirdeyeResponse<TokenSecurity> = client.get(&url)
+        .header("X-API-KEY", api_key()?)
+        .header("x-chain", "solana")
+        .send().await?
+        .json().await?;
+
+    match resp.data {
+        Some(d) if resp.success => Ok(d),
+        _ => Err(format!("Birdeye: nessun dato di sicurezza per {}", mint).into()),
+    }
+}
@@ -0,0 +1,183 @@
+use std::error::Error;
+use std::str::FromStr;
+use std::sync::Arc;
+use async_trait::async_trait;
+use futures::StreamExt;
+use serde::Serialize;
+use solana_client::nonblocking::pubsub_client::PubsubClient;
+use solana_client::rpc_config::{RpcTransactionLogsConfig, RpcTransactionLogsFilter};
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use tokio::sync::mpsc;
+use crate::db::DbPool;
+use crate::network::NetworkClient;
+use crate::{swap_router, wallet_manager};
+
+// --- CLIENT DI CATENA (Base per il supporto multi-chain) ---
+// 1. I task (tasks/) e le operazioni di trading.rs usano la catena solo tramite ChainClient: wallet, saldi, quote, swap ed eventi on-chain
+// 2. Solana è l'unica implementazione (NetworkClient + router Jupiter/Orca/Raydium), senza cambi di comportamento
+// 3. Indirizzi e token restano stringhe: ogni implementazione li interpreta per la propria catena.
+//    Un client EVM (Base, Ethereum) implementerà lo stesso trait con quote e swap del proprio aggregatore.
+// Analisi specifiche di Solana (honeypot, pool Raydium, metadata Metaplex) restano fuori dal trait.
+
+pub type ChainResult<T> = Result<T, Box<dyn Error + Send + Sync>>;
+
+// Mint del SOL (wrapped) usato dagli aggregatori
+const SOL_MINT: &str = "So11111111111111111111111111111111111111112";
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+pub enum Chain { Solana }
+
+impl Chain {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Chain::Solana => "solana",
+        }
+    }
+}
+
+// Wallet dell'utente con le chiavi della propria catena
+pub enum Wallet {
+    Solana(Keypair),
+}
+
+impl Wallet {
+    pub fn address(&self) -> String {
+        match self {
+            Wallet::Solana(kp) => kp.pubkey().to_string(),
+        }
+    }
+}
+
+// Miglior quote disponibile (unità base dei due token)
+#[derive(Serialize, Clone, Debug)]
+pub struct Quote {
+    pub route: &'static str,
+    pub in_amount: u64,
+    pub out_amount: u64,
+}
+
+// Swap inviato (la conferma arriva in background)
+#[derive(Clone, Debug)]
+pub struct Swap {
+    pub signature: String,
+    pub route: &'static str,
+    pub expected_out: u64,
+}
+
+// Transazione che menziona un indirizzo osservato (programma Solana, contratto EVM)
+#[derive(Clone, Debug)]
+pub struct ChainLog {
+    pub signature: String,
+    pub slot: u64,
+    pub logs: Vec<String>,
+}
+
+#[async_trait]
+pub trait ChainClient: Send + Sync {
+    fn chain(&self) -> Chain;
+
+    /// Token nativo nel formato degli aggregatori (SOL wrapped, WETH...)
+    fn native_token(&self) -> &'static str;
+
+    fn is_valid_address(&self, address: &str) -> bool;
+
+    async fn load_wallet(&self, pool: &DbPool, user_id: &str) -> ChainResult<Wallet>;
+
+    /// Saldo nativo (lamports, wei)
+    async fn native_balance(&self, owner: &str) -> ChainResult<u64>;
+
+    async fn token_balance(&self, owner: &str, token: &str) -> ChainResult<u64>;
+
+    async fn quote(&self, input: &str, output: &str, amount: u64, slippage_bps: u16) -> ChainResult<Quote>;
+
+    /// Compra `token` spendendo `amount` del token nativo
+    async fn buy(&self, pool: &DbPool, user_id: &str, wallet: &Wallet, token: &str, amount: u64, slippage_bps: u16) -> ChainResult<Swap>;
+
+    /// Vende `amount` (unità base) di `token` per il token nativo
+    async fn sell(&self, pool: &DbPool, user_id: &str, wallet: &Wallet, token: &str, amount: u64, slippage_bps: u16) -> ChainResult<Swap>;
+
+    /// Stream delle transazioni che menzionano `address`: si chiude (None) quando cade la connessione
+    async fn subscribe_logs(&self, address: &str) -> ChainResult<mpsc::UnboundedReceiver<ChainLog>>;
+}
+
+fn keypair(wallet: &Wallet) -> &Keypair {
+    match wallet {
+        Wallet::Solana(kp) => kp,
+    }
+}
+
+impl From<swap_router::SwapOutcome> for Swap {
+    fn from(out: swap_router::SwapOutcome) -> Self {
+        Swap { signature: out.signature, route: out.route.label(), expected_out: out.expected_out }
+    }
+}
+
+// Implementato sull'Arc: il router e la riconciliazione degli swap clonano il client nei task in background
+#[async_trait]
+impl ChainClient for Arc<NetworkClient> {
+    fn chain(&self) -> Chain { Chain::Solana }
+
+    fn native_token(&self) -> &'static str { SOL_MINT }
+
+    fn is_valid_address(&self, address: &str) -> bool {
+        Pubkey::from_str(address).is_ok()
+    }
+
+    async fn load_wallet(&self, pool: &DbPool, user_id: &str) -> ChainResult<Wallet> {
+        Ok(Wallet::Solana(wallet_manager::get_decrypted_wallet(pool, user_id).await?))
+    }
+
+    async fn native_balance(&self, owner: &str) -> ChainResult<u64> {
+        Ok(self.get_balance_fast(&Pubkey::from_str(owner)?).await)
+    }
+
+    async fn token_balance(&self, owner: &str, token: &str) -> ChainResult<u64> {
+        // Token classico o Token-2022: l'ATA dipende dal programma del mint
+        self.get_token_balance(&Pubkey::from_str(owner)?, &Pubkey::from_str(token)?).await
+    }
+
+    async fn quote(&self, input: &str, output: &str, amount: u64, slippage_bps: u16) -> ChainResult<Quote> {
+        let (route, out_amount) = swap_router::best_quote(input, output, amount, slippage_bps).await.ok_or("Nessuna quote disponibile")?;
+        Ok(Quote { route: route.label(), in_amount: amount, out_amount })
+    }
+
+    async fn buy(&self, pool: &DbPool, user_id: &str, wallet: &Wallet, token: &str, amount: u64, slippage_bps: u16) -> ChainResult<Swap> {
+        Ok(swap_router::buy(self, pool, user_id, keypair(wallet), token, amount, slippage_bps).await?.into())
+    }
+
+    async fn sell(&self, pool: &DbPool, user_id: &str, wallet: &Wallet, token: &str, amount: u64, slippage_bps: u16) -> ChainResult<Swap> {
+        Ok(swap_router::sell(self, pool, user_id, keypair(wallet), token, amount, slippage_bps).await?.into())
+    }
+
+    async fn subscribe_logs(&self, address: &str) -> ChainResult<mpsc::UnboundedReceiver<ChainLog>> {
+        // Connessione dedicata: dopo una caduta il client condiviso resta chiuso, ne serve uno nuovo
+        let client = PubsubClient::new(&self.ws_url).await?;
+        let (tx, rx) = mpsc::unbounded_channel();
+        let address = address.to_string();
+        let (ready_tx, ready_rx) = tokio::sync::oneshot::channel();
+
+        // Lo stream prende in prestito il client: vivono entrambi nel task che inoltra i log
+        tokio::spawn(async move {
+            let subscription = client.logs_subscribe(
+                RpcTransactionLogsFilter::Mentions(vec![address]),
+                RpcTransactionLogsConfig { commitment: Some(CommitmentConfig::processed()) },
+            ).await;
+            let mut stream = match subscription {
+                Ok((stream, _)) => { let _ = ready_tx.send(Ok(())); stream },
+                Err(e) => { let _ = ready_tx.send(Err(e.to_string())); return; },
+            };
+            while let Some(log) = stream.next().await {
+                let event = ChainLog { signature: log.value.signature, slot: log.context.slot, logs: log.value.logs };
+                if tx.send(event).is_err() { break; }
+            }
+        });
+
+        match ready_rx.await {
+            Ok(Ok(())) => Ok(rx),
+            Ok(Err(e)) => Err(e.into()),
+            Err(_) => Err("Sottoscrizione log interrotta prima dell'avvio".into()),
+        }
+    }
+}
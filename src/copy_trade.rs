@@ -0,0 +1,178 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+use futures::StreamExt;
+use solana_client::rpc_config::{RpcTransactionConfig, RpcTransactionLogsConfig, RpcTransactionLogsFilter};
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_transaction_status::option_serializer::OptionSerializer;
+use solana_transaction_status::{UiTransactionEncoding, UiTransactionTokenBalance};
+use tokio::task::JoinHandle;
+use tokio::time::Duration;
+use log::{debug, info, warn};
+use crate::{db, metadata, network, safety, signals, AppState};
+
+// --- COPY-TRADING (Segui un wallet e replica i suoi acquisti) ---
+// 1. Un listener WebSocket per ogni wallet leader seguito da utenti attivi (ricaricati ogni 30s)
+// 2. Le TX del leader che passano da Jupiter o Raydium V4 vengono lette: token ricevuto + SOL speso
+// 3. Ogni follower compra la STESSA quota del proprio saldo (es. leader spende il 5% -> follower il 5%),
+//    con tetti max_per_trade_sol/max_total_exposure_sol/reserve_sol e i soliti controlli (safety, honeypot, filtri, cooldown, circuit breaker)
+
+pub const MAX_FOLLOWED_WALLETS: usize = 5;
+const SOL_MINT: &str = "So11111111111111111111111111111111111111112";
+const JUPITER_V6_PROGRAM_ID: &str = "JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4";
+// Sotto questa quota del saldo è rumore (dust, test)
+const MIN_BALANCE_FRACTION: f64 = 0.001;
+
+/// Segue o smette di seguire un wallet: messaggio per l'utente oppure errore
+pub async fn update_follow(pool: &db::DbPool, user_id: &str, wallet: &str, add: bool) -> Result<String, String> {
+    let wallet = Pubkey::from_str(wallet.trim()).map_err(|_| "Indirizzo wallet non valido")?.to_string();
+
+    let res = if add {
+        let own = crate::wallet_manager::create_user_wallet(pool, user_id).await.unwrap_or_default();
+        if own == wallet { return Err("Non puoi seguire il tuo stesso wallet".into()); }
+        let count = db::get_followed_wallets(pool, user_id).await.map_err(|e| e.to_string())?.len();
+        if count >= MAX_FOLLOWED_WALLETS { return Err(format!("Massimo {} wallet seguiti", MAX_FOLLOWED_WALLETS)); }
+        db::add_followed_wallet(pool, user_id, &wallet).await
+            .map(|added| if added { format!("Ora segui {}", wallet) } else { format!("Segui già {}", wallet) })
+    } else {
+        db::remove_followed_wallet(pool, user_id, &wallet).await
+            .map(|removed| if removed { format!("Non segui più {}", wallet) } else { format!("Non stavi seguendo {}", wallet) })
+    };
+    res.map_err(|e| format!("Errore Database: {}", e))
+}
+
+/// Mantiene un listener per ogni wallet leader seguito
+pub async fn run_copy_trader(pool: db::DbPool, net: Arc<network::NetworkClient>, state: Arc<AppState>) {
+    info!("👥 Copy-Trading: ONLINE");
+    let mut listeners: HashMap<String, JoinHandle<()>> = HashMap::new();
+
+    loop {
+        match db::get_all_followed_wallets(&pool).await {
+            Ok(wallets) => {
+                listeners.retain(|w, h| {
+                    let keep = wallets.contains(w) && !h.is_finished();
+                    if !keep { h.abort(); }
+                    keep
+                });
+                for wallet in wallets {
+                    if listeners.contains_key(&wallet) { continue; }
+                    let (p, n, s, w) = (pool.clone(), net.clone(), state.clone(), wallet.clone());
+                    listeners.insert(wallet, tokio::spawn(async move { follow_wallet(p, n, s, w).await; }));
+                }
+            },
+            Err(e) => warn!("⚠️ Copy-Trading: lettura wallet seguiti fallita: {}", e),
+        }
+        if state.sleep_or_shutdown(Duration::from_secs(30)).await { break; }
+    }
+
+    // I listener escono da soli al segnale di shutdown
+    for (_, h) in listeners { let _ = h.await; }
+    info!("🛑 Copy-Trading fermato.");
+}
+
+async fn follow_wallet(pool: db::DbPool, net: Arc<network::NetworkClient>, state: Arc<AppState>, leader: String) {
+    let raydium_id = crate::raydium::RAYDIUM_V4_PROGRAM_ID;
+
+    loop {
+        match net.pubsub.logs_subscribe(
+            RpcTransactionLogsFilter::Mentions(vec![leader.clone()]),
+            RpcTransactionLogsConfig { commitment: Some(CommitmentConfig::confirmed()) }
+        ).await {
+            Ok((mut stream, _)) => {
+                debug!("👥 In ascolto sul leader {}", leader);
+                while let Some(log) = tokio::select! { l = stream.next() => l, _ = state.shutdown_signal() => None } {
+                    if log.value.err.is_some() { continue; }
+                    let is_swap = log.value.logs.iter().any(|l| l.contains(JUPITER_V6_PROGRAM_ID) || l.contains(raydium_id));
+                    if !is_swap || !crate::tasks::sniper::is_new_signature(&pool, &state, &log.value.signature, "COPY").await { continue; }
+
+                    let (p, n, s, l, sig) = (pool.clone(), net.clone(), state.clone(), leader.clone(), log.value.signature);
+                    tokio::spawn(async move { mirror_swap(&p, &n, &s, &l, &sig).await; });
+                }
+                if state.is_shutting_down() { break; }
+                warn!("⚠️ Stream Copy-Trading chiuso ({}), riconnessione...", leader);
+            },
+            Err(e) => {
+                warn!("⚠️ Copy-Trading: sottoscrizione {} fallita: {}", leader, e);
+                if state.sleep_or_shutdown(Duration::from_secs(5)).await { break; }
+            }
+        }
+    }
+}
+
+// Acquisto del leader ricostruito dalla TX: (mint ricevuto, quota del saldo SOL spesa)
+struct LeaderBuy {
+    mint: String,
+    balance_fraction: f64,
+}
+
+async fn mirror_swap(pool: &db::DbPool, net: &Arc<network::NetworkClient>, state: &Arc<AppState>, leader: &str, sig: &str) {
+    let buy = match read_leader_buy(net, leader, sig).await {
+        Some(b) => b,
+        None => return,
+    };
+    let mint = match Pubkey::from_str(&buy.mint) { Ok(m) => m, Err(_) => return };
+
+    let followers = db::get_wallet_followers(pool, leader).await.unwrap_or_default();
+    if followers.is_empty() { return; }
+
+    match safety::check_token_safety(net, &mint).await {
+        Ok(rep) if rep.is_safe => {},
+        _ => { warn!("👥 Copy-Trade ignorato: {} non supera i controlli di sicurezza", buy.mint); return; }
+    }
+    let symbol = metadata::symbol(pool, net, &buy.mint).await;
+
+    info!("👥 COPY-TRADE: {} ha comprato {} ({:.2}% del saldo) -> {} follower", leader, buy.mint, buy.balance_fraction * 100.0, followers.len());
+    let signal = signals::EntrySignal { symbol, source: "COPY", score: 100, balance_fraction: Some(buy.balance_fraction) };
+    crate::trading::execute_smart_auto_buy(pool, net, state, &mint, followers, signal).await;
+}
+
+async fn read_leader_buy(net: &Arc<network::NetworkClient>, leader: &str, sig: &str) -> Option<LeaderBuy> {
+    let signature = Signature::from_str(sig).ok()?;
+    let config = RpcTransactionConfig {
+        encoding: Some(UiTransactionEncoding::Base64),
+        commitment: Some(CommitmentConfig::confirmed()),
+        max_supported_transaction_version: Some(0),
+    };
+    let tx = net.rpc.get_transaction_with_config(&signature, config).await.ok()?;
+    let meta = tx.transaction.meta?;
+
+    // Si copia solo se il leader ha pagato la TX (indice 0): altrimenti è solo "menzionato"
+    let decoded = tx.transaction.transaction.decode()?;
+    if decoded.message.static_account_keys().first()?.to_string() != leader { return None; }
+
+    let owned = |balances: &OptionSerializer<Vec<UiTransactionTokenBalance>>, mint: &str| -> u64 {
+        match balances {
+            OptionSerializer::Some(list) => list.iter()
+                .filter(|b| b.mint == mint && matches!(&b.owner, OptionSerializer::Some(o) if o == leader))
+                .map(|b| b.ui_token_amount.amount.parse::<u64>().unwrap_or(0))
+                .sum(),
+            _ => 0,
+        }
+    };
+
+    // Token ricevuto: il mint (non SOL) con l'aumento maggiore nel wallet del leader
+    let mints: Vec<String> = match &meta.post_token_balances {
+        OptionSerializer::Some(list) => list.iter().map(|b| b.mint.clone()).filter(|m| m != SOL_MINT).collect(),
+        _ => vec![],
+    };
+    let mint = mints.into_iter()
+        .map(|m| { let gain = owned(&meta.post_token_balances, &m).saturating_sub(owned(&meta.pre_token_balances, &m)); (m, gain) })
+        .filter(|(_, gain)| *gain > 0)
+        .max_by_key(|(_, gain)| *gain)?
+        .0;
+
+    // SOL speso: nativo (al netto della fee) + eventuale wSOL
+    let pre_native = *meta.pre_balances.first()?;
+    let post_native = *meta.post_balances.first()?;
+    let native_spent = pre_native.saturating_sub(post_native).saturating_sub(meta.fee);
+    let pre_wsol = owned(&meta.pre_token_balances, SOL_MINT);
+    let wsol_spent = pre_wsol.saturating_sub(owned(&meta.post_token_balances, SOL_MINT));
+
+    let before = (pre_native + pre_wsol) as f64;
+    let fraction = ((native_spent + wsol_spent) as f64 / before.max(1.0)).min(1.0);
+    if fraction < MIN_BALANCE_FRACTION { return None; }
+
+    Some(LeaderBuy { mint, balance_fraction: fraction })
+}
@@ -1,18 +1,360 @@
-use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions, SqliteJournalMode};
-use sqlx::{SqlitePool, ConnectOptions, Row};
+use sqlx::{ConnectOptions, Executor, Row};
+use sqlx_core::query::Query;
+use sqlx_core::any::{Any, AnyArguments, AnyConnectOptions, AnyPool, AnyPoolOptions, AnyRow};
+use sqlx_core::migrate::{Migration, MigrationSource, MigrationType, Migrator};
+use std::borrow::Cow;
 use std::env;
 use std::str::FromStr;
 use std::fs;
 use std::path::Path;
-use log::{info, warn, error};
-use chrono::{Utc, Duration, DateTime};
+use log::info;
+use chrono::{Utc, Duration, DateTime, NaiveDate, NaiveDateTime};
+use crate::OpenPosition;
+use crate::strategy::StrategyParams;
 
-/// Connette al DB con Backup di Sicurezza e WAL Mode
-pub async fn connect() -> SqlitePool {
+// Ordine Limite (Buy/Sell al prezzo target)
+#[derive(serde::Serialize, Clone)]
+pub struct LimitOrder {
+    pub id: i64,
+    pub user_id: String,
+    pub token: String,
+    pub side: String,
+    pub trigger_price: f64,
+    pub trigger_above: bool,
+    pub amount_lamports: u64,
+    pub status: String,
+    pub tx_signature: Option<String>,
+}
+
+// Trade suggerito (modalità solo segnali), eseguito solo se approvato
+#[derive(serde::Serialize, Clone)]
+pub struct PendingTrade {
+    pub id: i64,
+    pub user_id: String,
+    pub token: String,
+    pub symbol: String,
+    pub engine: String,
+    pub source: String,
+    pub score: u8,
+    pub amount_lamports: u64,
+    pub quoted_out: u64,
+    pub slippage_bps: u16,
+    pub status: String,
+    pub tx_signature: Option<String>,
+    pub created_at: String,
+}
+
+// Metadati di un token (cache di metadata::resolve)
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct TokenMetadata {
+    pub mint: String,
+    pub name: String,
+    pub symbol: String,
+    pub logo_uri: Option<String>,
+    pub source: String,
+}
+
+// Statistiche di portafoglio dell'utente (riga materializzata di portfolio::replay)
+#[derive(serde::Serialize, Clone, Debug, Default)]
+pub struct PortfolioStats {
+    pub closed_trades: i64,
+    pub wins: i64,
+    pub losses: i64,
+    pub total_pnl_sol: f64,
+    pub gross_profit_sol: f64,
+    pub gross_loss_sol: f64,
+    pub best_trade_sol: f64,
+    pub worst_trade_sol: f64,
+    pub invested_sol: f64,
+    pub max_drawdown_sol: f64,
+    // > 0 vittorie consecutive, < 0 perdite consecutive
+    pub current_streak: i64,
+    pub updated_at: String,
+}
+
+// Lancio rilevato dallo Sniper in dry-run: dati al momento della scoperta e prezzi successivi (None = non ancora)
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct SniperHit {
+    pub id: i64,
+    pub token: String,
+    pub symbol: String,
+    pub deployer: String,
+    pub detected_at: i64,
+    pub is_safe: bool,
+    pub reason: String,
+    pub safety_report: serde_json::Value,
+    pub price: f64,
+    pub liquidity_usd: f64,
+    pub market_cap: f64,
+    pub volume_24h: f64,
+    pub price_5m: Option<f64>,
+    pub price_30m: Option<f64>,
+    pub price_2h: Option<f64>,
+}
+
+// Richiesta di off-ramp SEPA (SOL -> EURC -> bonifico), stati in offramp.rs
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct OfframpPayout {
+    pub id: i64,
+    pub user_id: String,
+    pub iban: String,
+    pub beneficiary: String,
+    pub amount_eurc: u64,
+    pub amount_lamports: u64,
+    pub provider: String,
+    pub status: String,
+    pub swap_signature: Option<String>,
+    pub pre_swap_eurc: Option<u64>, // Saldo EURC prima dello swap (None = richiesta precedente alla colonna)
+    pub transfer_signature: Option<String>,
+    pub provider_ref: Option<String>,
+    pub error: Option<String>,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+// Campi da scrivere insieme al cambio di stato (None = invariato)
+#[derive(Default)]
+pub struct OfframpUpdate<'a> {
+    pub amount_lamports: Option<u64>,
+    pub swap_signature: Option<&'a str>,
+    pub pre_swap_eurc: Option<u64>,
+    pub transfer_signature: Option<&'a str>,
+    pub provider_ref: Option<&'a str>,
+    pub error: Option<&'a str>,
+}
+
+// Griglia (Grid Trading su una coppia base/quote, prezzi in quote per 1 base)
+#[derive(serde::Serialize, Clone)]
+pub struct GridBot {
+    pub id: i64,
+    pub user_id: String,
+    pub base_mint: String,
+    pub quote_mint: String,
+    pub base_decimals: u8,
+    pub quote_decimals: u8,
+    pub lower_price: f64,
+    pub upper_price: f64,
+    pub step_pct: f64,
+    pub order_size: u64, // Unità base della quote spese per livello
+    pub status: String,
+    pub round_trips: i64,
+    pub realized_profit: i64, // Unità base della quote
+}
+
+// Livello della griglia: compra a buy_price, rivende a sell_price
+#[derive(serde::Serialize, Clone)]
+pub struct GridLevel {
+    pub id: i64,
+    pub grid_id: i64,
+    pub buy_price: f64,
+    pub sell_price: f64,
+    pub status: String,
+    pub base_amount: u64,
+    pub last_tx: Option<String>,
+}
+
+// Indirizzo di prelievo in whitelist (usabile dopo la conferma Telegram + periodo di attesa)
+#[derive(serde::Serialize, Clone)]
+pub struct WithdrawalAddress {
+    pub id: i64,
+    pub address: String,
+    pub label: Option<String>,
+    pub status: String,
+    pub added_at: Option<i64>, // Unix, momento della conferma (None finché PENDING)
+}
+
+// Deposito in ingresso sul wallet custodito (SOL o stablecoin)
+#[derive(serde::Serialize, Clone)]
+pub struct Deposit {
+    pub id: i64,
+    pub mint: String,
+    pub amount: u64,   // Unità base
+    pub amount_ui: f64,
+    pub usd_value: Option<f64>,
+    pub tx_signature: String,
+    pub created_at: String,
+}
+
+// Movimento per l'export (BUY all'apertura del trade, SELL alla chiusura). USD = prezzo SOL al momento dell'esecuzione.
+#[derive(serde::Serialize, Clone)]
+pub struct TradeEvent {
+    pub time: String,
+    pub side: String,
+    pub trade_id: i64,
+    pub token: String,
+    pub symbol: Option<String>, // Da token_metadata, None se mai risolto
+    pub amount_sol: f64,       // BUY: investito, SOL: incassato
+    pub sol_usd: Option<f64>,  // None per i trade registrati prima dello storico prezzi
+    pub fee_sol: f64,
+    pub pnl_sol: f64,          // Solo SELL
+    pub cost_basis_usd: Option<f64>, // Solo SELL: investito x prezzo SOL all'acquisto
+    pub tx_signature: Option<String>,
+    pub note: Option<String>,
+    pub tags: Vec<String>,
+}
+
+// Trade chiusi per engine (colonna strategy) nel periodo del report
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct StrategyBreakdown {
+    pub strategy: String, // Engine dell'Auto-Bot, "manual" o "copy" ("" per i trade precedenti alla colonna)
+    pub closed: i64,
+    pub wins: i64,
+    pub pnl_sol: f64,
+}
+
+// Singolo trade chiuso (miglior/peggior trade del report)
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct TradeOutcome {
+    pub token: String,
+    pub symbol: Option<String>,
+    pub pnl_sol: f64,
+    pub pnl_pct: f64, // Su quanto investito
+}
+
+// Vendita fallita in coda per un nuovo tentativo (slippage crescente, backoff esponenziale)
+#[derive(serde::Serialize, Clone)]
+pub struct SellRetry {
+    pub id: i64,
+    pub user_id: String,
+    pub token: String,
+    pub reason: String,
+    pub attempts: i64,
+    pub next_attempt_at: i64, // Unix
+    pub last_error: Option<String>,
+}
+
+// Riga della lista utenti per l'area Admin (volume = SOL spesi in acquisto + SOL incassati in vendita)
+#[derive(serde::Serialize, Clone)]
+pub struct AdminUser {
+    pub tg_id: String,
+    pub wallet_address: String,
+    pub is_active: bool,
+    pub banned: bool,
+    pub created_at: Option<String>,
+    pub trades: i64,
+    pub open_trades: i64,
+    pub volume_sol: f64,
+}
+
+// Totali della piattaforma per l'area Admin
+#[derive(serde::Serialize, Clone, Default)]
+pub struct AdminTotals {
+    pub users: i64,
+    pub active_users: i64,
+    pub banned_users: i64,
+    pub trades: i64,
+    pub open_trades: i64,
+    pub volume_sol: f64,
+}
+
+// Limite ordini DCA non cancellati per utente
+pub const MAX_DCA_ORDERS: usize = 10;
+
+// Ordine DCA (Acquisto ricorrente)
+#[derive(serde::Serialize, Clone)]
+pub struct DcaOrder {
+    pub id: i64,
+    pub user_id: String,
+    pub token: String,
+    pub amount_lamports: u64,
+    pub interval_secs: i64,
+    pub status: String,
+    pub next_run: i64,
+    pub fills: i64,
+    pub total_spent_lamports: u64,
+}
+
+// Tentativo di swap (Audit Log esecuzioni)
+#[derive(serde::Serialize, Clone)]
+pub struct Execution {
+    pub id: i64,
+    pub side: String,
+    pub input_mint: String,
+    pub output_mint: String,
+    pub amount_in: u64,
+    pub route: String,
+    pub quoted_out: u64,
+    // Dai post-balances della TX confermata (None finché non verificata)
+    pub actual_out: Option<u64>,
+    pub fee_lamports: Option<u64>,
+    pub priority_fee_lamports: Option<u64>,
+    pub slippage_bps: u16,
+    // Rispetto a quoted_out, negativo = fill migliore (None finché non verificata o senza quote)
+    pub realized_slippage_bps: Option<i64>,
+    pub slippage_exceeded: bool,
+    pub status: String,
+    pub tx_signature: Option<String>,
+    pub error: Option<String>,
+    pub created_at: String,
+}
+
+// Dati noti al momento dell'invio (prima della conferma)
+pub struct ExecutionAttempt<'a> {
+    pub user_id: &'a str,
+    pub side: &'a str,
+    pub input_mint: &'a str,
+    pub output_mint: &'a str,
+    pub amount_in: u64,
+    pub route: &'a str,
+    pub quoted_out: u64,
+    pub slippage_bps: u16,
+}
+
+// Webhook di notifica dell'utente
+#[derive(serde::Serialize, Clone)]
+pub struct Webhook {
+    pub id: i64,
+    pub url: String,
+    pub kind: String,
+}
+
+/// Pool del Database: SQLite (sviluppo locale) o Postgres (produzione), in base a DATABASE_URL
+pub type DbPool = AnyPool;
+
+// --- BACKEND (SQLite / Postgres) ---
+// Le query sono scritte una volta sola nel sottoinsieme comune ai due database:
+// 1. Parametri $1..$N (SQLite li accetta come Postgres)
+// 2. ON CONFLICT ... DO NOTHING/UPDATE al posto di INSERT OR IGNORE/REPLACE, RETURNING id al posto di last_insert_rowid()
+// 3. Niente funzioni data di SQLite: i limiti temporali si calcolano in Rust (vedi sql_timestamp)
+// 4. Su Postgres SUM di interi è NUMERIC (non supportato dal driver Any): CAST(... AS BIGINT), divisioni in DOUBLE PRECISION
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Backend {
+    Sqlite,
+    Postgres,
+}
+
+impl Backend {
+    fn from_url(url: &str) -> Self {
+        if url.starts_with("postgres://") || url.starts_with("postgresql://") { Backend::Postgres } else { Backend::Sqlite }
+    }
+
+    fn migrations(self) -> &'static [(i64, &'static str, &'static str)] {
+        match self {
+            Backend::Sqlite => &SQLITE_MIGRATIONS,
+            Backend::Postgres => &POSTGRES_MIGRATIONS,
+        }
+    }
+}
+
+/// Connette al DB (SQLite con Backup di Sicurezza e WAL Mode, oppure Postgres)
+pub async fn connect() -> DbPool {
     let db_url = env::var("DATABASE_URL").expect("❌ Manca DATABASE_URL nel file .env");
+    connect_url(&db_url).await
+}
+
+/// Connette al DB indicato e applica le migrazioni (stress test: un file SQLite per scenario)
+pub async fn connect_url(db_url: &str) -> DbPool {
+    let db_url = db_url.to_string();
+    let backend = Backend::from_url(&db_url);
+    // Driver registrati una volta per processo (gli stress test aprono un DB per scenario)
+    static DRIVERS: std::sync::Once = std::sync::Once::new();
+    DRIVERS.call_once(|| {
+        sqlx_core::any::driver::install_drivers(&[sqlx_sqlite::any::DRIVER, sqlx_postgres::any::DRIVER])
+            .expect("❌ Driver Database non registrati");
+    });
     
-    // --- 1. BACKUP DI SICUREZZA AUTOMATICO ---
-    if let Some(path_str) = db_url.strip_prefix("sqlite://") {
+    // --- 1. BACKUP DI SICUREZZA AUTOMATICO (solo SQLite) ---
+    if let Some(path_str) = db_url.strip_prefix("sqlite://").map(|p| p.split('?').next().unwrap_or(p)) {
         let path = Path::new(path_str);
         if path.exists() {
             let backup_path = format!("{}.bak", path_str);
@@ -21,104 +363,264 @@ pub async fn connect() -> SqlitePool {
         }
     }
 
-    info!("🗄️  Connessione al Database...");
+    info!("🗄️  Connessione al Database ({:?})...", backend);
 
-    // --- 2. CONFIGURAZIONE ROBUSTA (WAL) ---
-    let mut connection_options = SqliteConnectOptions::from_str(&db_url)
-        .expect("URL Database non valido")
-        .create_if_missing(true)
-        .journal_mode(SqliteJournalMode::Wal)
-        .foreign_keys(true);
-        
+    // --- 2. CONFIGURAZIONE ROBUSTA ---
+    // SQLite: file creato se manca (mode=rwc), foreign keys attive di default nel driver
+    let url = match backend {
+        Backend::Sqlite if !db_url.contains("mode=") => format!("{}{}mode=rwc", db_url, if db_url.contains('?') { '&' } else { '?' }),
+        _ => db_url.clone(),
+    };
+    let mut connection_options = AnyConnectOptions::from_str(&url).expect("URL Database non valido");
     connection_options = connection_options.log_statements(log::LevelFilter::Off);
 
-    let pool = SqlitePoolOptions::new()
+    let pool = AnyPoolOptions::new()
         .max_connections(10)
         .connect_with(connection_options)
         .await
-        .expect("❌ Impossibile connettersi a SQLite");
+        .expect("❌ Impossibile connettersi al Database");
+
+    // WAL è persistente nel file: basta impostarlo una volta
+    if backend == Backend::Sqlite {
+        sqlx::query("PRAGMA journal_mode = WAL").execute(&pool).await.expect("❌ WAL Mode non attivabile");
+    }
 
-    init_schema(&pool).await;
+    init_schema(&pool, backend).await;
     pool
 }
 
-/// Crea o Aggiorna lo Schema delle Tabelle
-async fn init_schema(pool: &SqlitePool) {
-    // Tabella UTENTI
-    let schema_users = r#"
-    CREATE TABLE IF NOT EXISTS users (
-        tg_id TEXT PRIMARY KEY,
-        pubkey TEXT NOT NULL,
-        private_key_enc TEXT NOT NULL,
-        is_active INTEGER DEFAULT 0,
-        bot_started_at TEXT, 
-        created_at TEXT DEFAULT CURRENT_TIMESTAMP,
-        settings TEXT
-    );
-    "#;
-
-    // Tabella TRADES (Con highest_price per Trailing Stop)
-    let schema_trades = r#"
-    CREATE TABLE IF NOT EXISTS trades (
-        id INTEGER PRIMARY KEY AUTOINCREMENT,
-        user_id TEXT NOT NULL,
-        token_address TEXT NOT NULL,
-        tx_signature TEXT NOT NULL,
-        amount_in_lamports INTEGER NOT NULL,
-        status TEXT DEFAULT 'OPEN', -- OPEN, SOLD, FAILED
-        entry_time TEXT DEFAULT CURRENT_TIMESTAMP,
-        exit_time TEXT,
-        profit_loss_sol REAL DEFAULT 0.0,
-        highest_price_lamports INTEGER DEFAULT 0
-    );
-    "#;
-
-    // Tabella PRELIEVI (Per gestire i crash durante i prelievi)
-    let schema_withdrawals = r#"
-    CREATE TABLE IF NOT EXISTS withdrawals (
-        id INTEGER PRIMARY KEY AUTOINCREMENT,
-        user_id TEXT NOT NULL,
-        amount_lamports INTEGER NOT NULL,
-        destination TEXT NOT NULL,
-        status TEXT DEFAULT 'PENDING', -- PENDING, COMPLETED, FAILED
-        tx_signature TEXT,
-        created_at TEXT DEFAULT CURRENT_TIMESTAMP
-    );
-    "#;
+// --- MIGRAZIONI (sqlx Migrator, file versionati in migrations/sqlite e migrations/postgres) ---
+// 1. Ogni modifica dello schema è un nuovo file NNNN_descrizione.sql per ENTRAMBI i backend, stessa versione,
+//    registrato in SQLITE_MIGRATIONS e POSTGRES_MIGRATIONS: i file già rilasciati non si toccano
+// 2. All'avvio si applicano le migrazioni mancanti, in transazione; la versione resta in _sqlx_migrations
+// 3. Migrazione fallita, file alterato (checksum) o DB più nuovo del binario: l'avvio si ferma
+// I DB SQLite nati prima delle migrazioni (init_schema + ALTER TABLE ad-hoc) vengono allineati alla baseline una volta sola.
+// Tipi ammessi su Postgres: BIGINT, DOUBLE PRECISION, TEXT (il driver Any non legge BOOLEAN, NUMERIC, TIMESTAMP).
+
+// (versione, descrizione, SQL) incorporati nel binario
+const SQLITE_MIGRATIONS: [(i64, &str, &str); 18] = [
+    (1, "baseline", include_str!("../migrations/sqlite/0001_baseline.sql")),
+    (2, "onboarding", include_str!("../migrations/sqlite/0002_onboarding.sql")),
+    (3, "pending trades", include_str!("../migrations/sqlite/0003_pending_trades.sql")),
+    (4, "token metadata", include_str!("../migrations/sqlite/0004_token_metadata.sql")),
+    (5, "portfolio stats", include_str!("../migrations/sqlite/0005_portfolio_stats.sql")),
+    (6, "sniper hits", include_str!("../migrations/sqlite/0006_sniper_hits.sql")),
+    (7, "offramp payouts", include_str!("../migrations/sqlite/0007_offramp_payouts.sql")),
+    (8, "session devices", include_str!("../migrations/sqlite/0008_session_devices.sql")),
+    (9, "email tokens", include_str!("../migrations/sqlite/0009_email_tokens.sql")),
+    (10, "execution slippage", include_str!("../migrations/sqlite/0010_execution_slippage.sql")),
+    (11, "trade notes", include_str!("../migrations/sqlite/0011_trade_notes.sql")),
+    (12, "signals", include_str!("../migrations/sqlite/0012_signals.sql")),
+    (13, "processed signatures", include_str!("../migrations/sqlite/0013_processed_signatures.sql")),
+    (14, "events", include_str!("../migrations/sqlite/0014_events.sql")),
+    (15, "buy fills", include_str!("../migrations/sqlite/0015_buy_fills.sql")),
+    (16, "offramp pre-swap balance", include_str!("../migrations/sqlite/0016_offramp_pre_swap.sql")),
+    (17, "risk breaker deactivation", include_str!("../migrations/sqlite/0017_risk_deactivated.sql")),
+    (18, "session token hash", include_str!("../migrations/sqlite/0018_session_token_hash.sql")),
+];
+
+const POSTGRES_MIGRATIONS: [(i64, &str, &str); 18] = [
+    (1, "baseline", include_str!("../migrations/postgres/0001_baseline.sql")),
+    (2, "onboarding", include_str!("../migrations/postgres/0002_onboarding.sql")),
+    (3, "pending trades", include_str!("../migrations/postgres/0003_pending_trades.sql")),
+    (4, "token metadata", include_str!("../migrations/postgres/0004_token_metadata.sql")),
+    (5, "portfolio stats", include_str!("../migrations/postgres/0005_portfolio_stats.sql")),
+    (6, "sniper hits", include_str!("../migrations/postgres/0006_sniper_hits.sql")),
+    (7, "offramp payouts", include_str!("../migrations/postgres/0007_offramp_payouts.sql")),
+    (8, "session devices", include_str!("../migrations/postgres/0008_session_devices.sql")),
+    (9, "email tokens", include_str!("../migrations/postgres/0009_email_tokens.sql")),
+    (10, "execution slippage", include_str!("../migrations/postgres/0010_execution_slippage.sql")),
+    (11, "trade notes", include_str!("../migrations/postgres/0011_trade_notes.sql")),
+    (12, "signals", include_str!("../migrations/postgres/0012_signals.sql")),
+    (13, "processed signatures", include_str!("../migrations/postgres/0013_processed_signatures.sql")),
+    (14, "events", include_str!("../migrations/postgres/0014_events.sql")),
+    (15, "buy fills", include_str!("../migrations/postgres/0015_buy_fills.sql")),
+    (16, "offramp pre-swap balance", include_str!("../migrations/postgres/0016_offramp_pre_swap.sql")),
+    (17, "risk breaker deactivation", include_str!("../migrations/postgres/0017_risk_deactivated.sql")),
+    (18, "session token hash", include_str!("../migrations/postgres/0018_session_token_hash.sql")),
+];
+
+#[derive(Debug)]
+struct EmbeddedMigrations(&'static [(i64, &'static str, &'static str)]);
+
+impl MigrationSource<'static> for EmbeddedMigrations {
+    fn resolve(self) -> futures::future::BoxFuture<'static, Result<Vec<Migration>, sqlx_core::error::BoxDynError>> {
+        Box::pin(async move {
+            Ok(self.0.iter()
+                .map(|(version, desc, sql)| Migration::new(*version, Cow::Borrowed(*desc), MigrationType::Simple, Cow::Borrowed(*sql), false))
+                .collect())
+        })
+    }
+}
 
-    // Eseguiamo le query singolarmente per gestire errori specifici
-    if let Err(e) = sqlx::query(schema_users).execute(pool).await {
-        error!("❌ Errore Critico Tabella USERS: {}", e);
+// Colonne aggiunte con ALTER TABLE prima della baseline: (tabella, definizione)
+const LEGACY_COLUMNS: [(&str, &str); 16] = [
+    ("users", "email TEXT"),
+    ("users", "password_hash TEXT"),
+    ("users", "referral_code TEXT"),
+    ("users", "referred_by TEXT"),
+    ("users", "banned INTEGER DEFAULT 0"),
+    ("positions", "stop_floor_lamports INTEGER DEFAULT 0"),
+    ("positions", "take_profit_lamports INTEGER DEFAULT 0"),
+    ("positions", "trailing_disabled INTEGER DEFAULT 0"),
+    ("trades", "entry_sol_usd REAL"),
+    ("trades", "exit_sol_usd REAL"),
+    ("trades", "exit_signature TEXT"),
+    ("trades", "usd_value_at_entry REAL"),
+    ("trades", "usd_value_at_exit REAL"),
+    ("trades", "sol_received_lamports INTEGER"),
+    ("trades", "fee_lamports INTEGER"),
+    ("trades", "strategy TEXT"),
+];
+
+/// Porta lo schema all'ultima versione. Qualsiasi errore blocca l'avvio: meglio fermi che su uno schema sbagliato.
+async fn init_schema(pool: &DbPool, backend: Backend) {
+    if backend == Backend::Sqlite {
+        upgrade_legacy_schema(pool).await.expect("❌ Allineamento dello schema pre-migrazioni fallito");
     }
-    if let Err(e) = sqlx::query(schema_trades).execute(pool).await {
-        error!("❌ Errore Critico Tabella TRADES: {}", e);
+    let migrator = Migrator::new(EmbeddedMigrations(backend.migrations())).await.expect("❌ Migrazioni non valide");
+    migrator.run(pool).await.expect("❌ Migrazioni Database fallite (schema incompatibile con questa versione)");
+
+    let expected = migrator.iter().map(|m| m.version).max().unwrap_or(0);
+    let version = schema_version(pool).await.expect("❌ Versione schema non leggibile");
+    if version != expected {
+        panic!("❌ Schema Database v{} incompatibile: questa versione richiede v{}", version, expected);
     }
-    if let Err(e) = sqlx::query(schema_withdrawals).execute(pool).await {
-        error!("❌ Errore Critico Tabella WITHDRAWALS: {}", e);
+    info!("✅ Schema Database v{} verificato.", version);
+}
+
+/// Ultima migrazione applicata con successo (0 = nessuna)
+pub async fn schema_version(pool: &DbPool) -> Result<i64, sqlx::Error> {
+    let row = sqlx::query("SELECT COALESCE(MAX(version), 0) AS version FROM _sqlx_migrations WHERE success")
+        .fetch_one(pool)
+        .await?;
+    Ok(row.get("version"))
+}
+
+// Solo SQLite (sqlite_master)
+async fn table_exists(pool: &DbPool, table: &str) -> Result<bool, sqlx::Error> {
+    let row = sqlx::query("SELECT COUNT(*) AS n FROM sqlite_master WHERE type = 'table' AND name = $1")
+        .bind(table)
+        .fetch_one(pool)
+        .await?;
+    Ok(row.get::<i64, _>("n") > 0)
+}
+
+/// DB creato prima delle migrazioni (tabelle presenti, nessuna versione): aggiunge le colonne mancanti,
+/// così la baseline (tutta IF NOT EXISTS) lo trova già allineato
+async fn upgrade_legacy_schema(pool: &DbPool) -> Result<(), sqlx::Error> {
+    if table_exists(pool, "_sqlx_migrations").await? || !table_exists(pool, "users").await? { return Ok(()); }
+
+    info!("🗄️  DB senza versione: allineamento alla baseline delle migrazioni...");
+    for (table, column_def) in LEGACY_COLUMNS {
+        if !table_exists(pool, table).await? { continue; }
+        let sql = format!("ALTER TABLE {} ADD COLUMN {}", table, column_def);
+        match sqlx::query(&sql).execute(pool).await {
+            Ok(_) => info!("🗄️  Colonna aggiunta: {}.{}", table, column_def),
+            Err(e) if e.to_string().contains("duplicate column") => {},
+            Err(e) => return Err(e),
+        }
     }
-    
-    info!("✅ Schema Database verificato (Full Features).");
+    Ok(())
+}
+
+/// Istante nel formato di CURRENT_TIMESTAMP (default delle colonne *_at/entry_time su entrambi i backend)
+fn sql_timestamp(t: DateTime<Utc>) -> String {
+    t.format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+/// Colonna data confrontabile come testo con sql_timestamp: le date RFC3339 (exit_time) perdono 'T' e fuso (sempre UTC)
+fn sql_time(column: &str) -> String {
+    format!("REPLACE(SUBSTR({}, 1, 19), 'T', ' ')", column)
+}
+
+/// Prima riga di uno statement con RETURNING. Lo statement va consumato per intero: su SQLite, se resta a metà
+/// (fetch_one/fetch_optional), la scrittura non si chiude e le altre connessioni del pool non la vedono.
+async fn fetch_returning<'q, 'c: 'q, E>(query: Query<'q, Any, AnyArguments<'q>>, executor: E) -> Result<Option<AnyRow>, sqlx::Error>
+where
+    E: 'q + Executor<'c, Database = Any>,
+{
+    Ok(query.fetch_all(executor).await?.into_iter().next())
+}
+
+/// INSERT ... RETURNING id: ID della riga creata
+async fn insert_returning_id<'q, 'c: 'q, E>(query: Query<'q, Any, AnyArguments<'q>>, executor: E) -> Result<i64, sqlx::Error>
+where
+    E: 'q + Executor<'c, Database = Any>,
+{
+    fetch_returning(query, executor).await?.map(|r| r.get("id")).ok_or(sqlx::Error::RowNotFound)
 }
 
 // --- FUNZIONI OPERATIVE (Tutte PUBBLICHE) ---
 
-/// Avvia il ciclo di 24h per l'utente
-pub async fn start_daily_cycle(pool: &SqlitePool, tg_id: &str) -> Result<(), sqlx::Error> {
+/// Probe di /health: il DB risponde a una query
+pub async fn ping(pool: &DbPool) -> Result<(), sqlx::Error> {
+    sqlx::query("SELECT 1").execute(pool).await?;
+    Ok(())
+}
+
+/// Avvia il ciclo di 24h per l'utente (false = utente bannato o onboarding non completato)
+pub async fn start_daily_cycle(pool: &DbPool, tg_id: &str) -> Result<bool, sqlx::Error> {
     let now_str = Utc::now().to_rfc3339(); 
     
-    sqlx::query("UPDATE users SET is_active = 1, bot_started_at = ? WHERE tg_id = ?")
+    // Un utente bannato, o che non ha accettato il disclaimer, non può avviare l'Auto-Bot
+    let res = sqlx::query("UPDATE users SET is_active = 1, bot_started_at = $1 WHERE tg_id = $2 AND COALESCE(banned, 0) = 0 AND onboarded_at IS NOT NULL")
         .bind(now_str)
         .bind(tg_id)
         .execute(pool)
         .await?;
+    if res.rows_affected() == 0 { return Ok(false); }
         
     info!("🕒 Ciclo giornaliero avviato per {}", tg_id);
+    Ok(true)
+}
+
+// --- ONBOARDING (Disclaimer + Preferenze iniziali) ---
+
+/// Stato dell'onboarding: (disclaimer accettato, onboarding completato)
+pub async fn get_onboarding(pool: &DbPool, tg_id: &str) -> Result<(bool, bool), sqlx::Error> {
+    let row = sqlx::query("SELECT disclaimer_accepted_at, onboarded_at FROM users WHERE tg_id = $1")
+        .bind(tg_id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(row.map(|r| (
+        r.get::<Option<String>, _>("disclaimer_accepted_at").is_some(),
+        r.get::<Option<String>, _>("onboarded_at").is_some(),
+    )).unwrap_or((false, false)))
+}
+
+/// Registra l'accettazione del disclaimer sui rischi (la prima resta valida)
+pub async fn accept_disclaimer(pool: &DbPool, tg_id: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE users SET disclaimer_accepted_at = $1 WHERE tg_id = $2 AND disclaimer_accepted_at IS NULL")
+        .bind(sql_timestamp(Utc::now()))
+        .bind(tg_id)
+        .execute(pool)
+        .await?;
     Ok(())
 }
 
+/// Chiude l'onboarding (solo dopo il disclaimer). false se il disclaimer non è stato accettato.
+pub async fn complete_onboarding(pool: &DbPool, tg_id: &str) -> Result<bool, sqlx::Error> {
+    let res = sqlx::query("UPDATE users SET onboarded_at = COALESCE(onboarded_at, $1) WHERE tg_id = $2 AND disclaimer_accepted_at IS NOT NULL")
+        .bind(sql_timestamp(Utc::now()))
+        .bind(tg_id)
+        .execute(pool)
+        .await?;
+    Ok(res.rows_affected() > 0)
+}
+
+/// Ferma l'Auto-Bot (prelievi sbloccati). Uno stop esplicito vale anche sul Circuit Breaker: a mezzanotte non riparte.
+pub async fn stop_daily_cycle(pool: &DbPool, tg_id: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE users SET is_active = 0 WHERE tg_id = $1")
+        .bind(tg_id)
+        .execute(pool)
+        .await?;
+    clear_risk_deactivation(pool, tg_id).await
+}
+
 /// Controlla se è possibile prelevare (Blocco 24h)
-pub async fn can_withdraw(pool: &SqlitePool, tg_id: &str) -> Result<(bool, String), sqlx::Error> {
-    let row_opt = sqlx::query("SELECT bot_started_at, is_active FROM users WHERE tg_id = ?")
+pub async fn can_withdraw(pool: &DbPool, tg_id: &str) -> Result<(bool, String), sqlx::Error> {
+    let row_opt = sqlx::query("SELECT bot_started_at, is_active FROM users WHERE tg_id = $1")
         .bind(tg_id)
         .fetch_optional(pool)
         .await?;
@@ -153,32 +655,42 @@ pub async fn can_withdraw(pool: &SqlitePool, tg_id: &str) -> Result<(bool, Strin
     Ok((true, "✅ Prelievo sbloccato!".to_string()))
 }
 
-/// Registra un acquisto (Buy)
+/// Registra un acquisto (Buy) e ritorna l'ID del trade. `sol_usd` = prezzo SOL all'esecuzione (None se oracolo non disponibile),
+/// `strategy` = chi ha aperto il trade (engine dell'Auto-Bot oppure "manual") per le statistiche di sizing
 pub async fn record_buy(
-    pool: &SqlitePool, 
+    pool: &DbPool, 
     tg_id: &str, 
     token_addr: &str, 
     signature: &str, 
-    amount: u64
-) -> Result<(), sqlx::Error> {
+    amount: u64,
+    sol_usd: Option<f64>,
+    strategy: &str
+) -> Result<i64, sqlx::Error> {
     let amount_i64 = amount as i64;
     // All'inizio, il prezzo più alto (highest) è uguale al prezzo di entrata
-    sqlx::query("INSERT INTO trades (user_id, token_address, tx_signature, amount_in_lamports, highest_price_lamports, status) VALUES (?, ?, ?, ?, ?, 'OPEN')")
+    let query = sqlx::query("INSERT INTO trades (user_id, token_address, tx_signature, amount_in_lamports, highest_price_lamports, status, entry_sol_usd, usd_value_at_entry, strategy) VALUES ($1, $2, $3, $4, $5, 'OPEN', $6, $7, $8) RETURNING id")
         .bind(tg_id)
         .bind(token_addr)
         .bind(signature)
         .bind(amount_i64)
         .bind(amount_i64) 
-        .execute(pool)
-        .await?;
+        .bind(sol_usd)
+        .bind(sol_usd.map(|p| amount as f64 / 1e9 * p))
+        .bind(strategy);
+    let id = insert_returning_id(query, pool).await?;
+    // La conferma on-chain può essere arrivata prima del trade: prezzo d'ingresso subito se il fill è già noto
+    let _ = settle_buy(pool, signature).await;
         
+    crate::metrics::METRICS.trades_opened.inc();
+    // Correlazione log: lo span del BUY (se dichiara trade_id) porta l'id da qui in avanti
+    tracing::Span::current().record("trade_id", id);
     info!("📝 Trade registrato nel DB per {}", token_addr);
-    Ok(())
+    Ok(id)
 }
 
 /// Aggiorna il prezzo massimo raggiunto (Trailing Stop)
-pub async fn update_highest_price(pool: &SqlitePool, trade_id: i32, new_high: u64) {
-    let _ = sqlx::query("UPDATE trades SET highest_price_lamports = ? WHERE id = ?")
+pub async fn update_highest_price(pool: &DbPool, trade_id: i32, new_high: u64) {
+    let _ = sqlx::query("UPDATE trades SET highest_price_lamports = $1 WHERE id = $2")
         .bind(new_high as i64)
         .bind(trade_id)
         .execute(pool)
@@ -186,50 +698,2417 @@ pub async fn update_highest_price(pool: &SqlitePool, trade_id: i32, new_high: u6
 }
 
 /// Registra un prelievo PRIMA di inviarlo (Crash Protection)
-pub async fn record_withdrawal_request(pool: &SqlitePool, tg_id: &str, amount: u64, dest: &str) -> Result<i64, sqlx::Error> {
-    let id = sqlx::query("INSERT INTO withdrawals (user_id, amount_lamports, destination) VALUES (?, ?, ?)")
+pub async fn record_withdrawal_request(pool: &DbPool, tg_id: &str, amount: u64, dest: &str) -> Result<i64, sqlx::Error> {
+    let query = sqlx::query("INSERT INTO withdrawals (user_id, amount_lamports, destination) VALUES ($1, $2, $3) RETURNING id")
         .bind(tg_id)
         .bind(amount as i64)
-        .bind(dest)
-        .execute(pool)
-        .await?
-        .last_insert_rowid();
-    Ok(id)
+        .bind(dest);
+    insert_returning_id(query, pool).await
 }
 
 /// Conferma che il prelievo è avvenuto
-pub async fn confirm_withdrawal(pool: &SqlitePool, id: i64, signature: &str) {
-    let _ = sqlx::query("UPDATE withdrawals SET status = 'COMPLETED', tx_signature = ? WHERE id = ?")
+pub async fn confirm_withdrawal(pool: &DbPool, id: i64, signature: &str) {
+    let _ = sqlx::query("UPDATE withdrawals SET status = 'COMPLETED', tx_signature = $1 WHERE id = $2")
         .bind(signature)
         .bind(id)
         .execute(pool)
         .await;
 }
 
-/// Recupera trade aperti (per il ripristino al riavvio)
-pub async fn get_open_trades(pool: &SqlitePool) -> Result<Vec<(i32, String, u64, u64)>, sqlx::Error> {
-    let rows = sqlx::query("SELECT id, token_address, amount_in_lamports, highest_price_lamports FROM trades WHERE status = 'OPEN'")
+/// Segna il prelievo come fallito (TX non inviata)
+pub async fn fail_withdrawal(pool: &DbPool, id: i64) {
+    let _ = sqlx::query("UPDATE withdrawals SET status = 'FAILED' WHERE id = $1")
+        .bind(id)
+        .execute(pool)
+        .await;
+}
+
+/// Registra un prelievo grande in attesa dell'approvazione Telegram (2FA)
+pub async fn record_withdrawal_approval(pool: &DbPool, tg_id: &str, amount: u64, dest: &str) -> Result<i64, sqlx::Error> {
+    let query = sqlx::query("INSERT INTO withdrawals (user_id, amount_lamports, destination, status) VALUES ($1, $2, $3, 'PENDING_APPROVAL') RETURNING id")
+        .bind(tg_id)
+        .bind(amount as i64)
+        .bind(dest);
+    insert_returning_id(query, pool).await
+}
+
+/// Approvazione: il prelievo passa a PENDING solo se non scaduto. Ritorna (lamports, destinazione).
+pub async fn claim_withdrawal_approval(pool: &DbPool, tg_id: &str, id: i64, ttl_minutes: i64) -> Result<Option<(u64, String)>, sqlx::Error> {
+    let query = sqlx::query("UPDATE withdrawals SET status = 'PENDING' WHERE id = $1 AND user_id = $2 AND status = 'PENDING_APPROVAL' AND created_at >= $3 RETURNING amount_lamports, destination")
+        .bind(id)
+        .bind(tg_id)
+        .bind(sql_timestamp(Utc::now() - Duration::minutes(ttl_minutes)));
+    let row = fetch_returning(query, pool).await?;
+    Ok(row.map(|r| (r.get::<i64, _>("amount_lamports") as u64, r.get("destination"))))
+}
+
+/// Rifiuto dell'utente (solo se ancora in attesa)
+pub async fn cancel_withdrawal_approval(pool: &DbPool, tg_id: &str, id: i64) -> Result<bool, sqlx::Error> {
+    let res = sqlx::query("UPDATE withdrawals SET status = 'CANCELLED' WHERE id = $1 AND user_id = $2 AND status = 'PENDING_APPROVAL'")
+        .bind(id)
+        .bind(tg_id)
+        .execute(pool)
+        .await?;
+    Ok(res.rows_affected() > 0)
+}
+
+/// Annulla le approvazioni scadute. Ritorna (id, utente) per avvisarli.
+pub async fn expire_withdrawal_approvals(pool: &DbPool, ttl_minutes: i64) -> Result<Vec<(i64, String)>, sqlx::Error> {
+    let rows = sqlx::query("UPDATE withdrawals SET status = 'CANCELLED' WHERE status = 'PENDING_APPROVAL' AND created_at < $1 RETURNING id, user_id")
+        .bind(sql_timestamp(Utc::now() - Duration::minutes(ttl_minutes)))
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.iter().map(|r| (r.get("id"), r.get("user_id"))).collect())
+}
+
+/// Recupera trade aperti (id, utente, token, lamports investiti)
+/// Trade aperto: (id, utente, token, lamports investiti, strategia, prezzo d'ingresso dal fill on-chain se già confermato)
+pub type OpenTrade = (i64, String, String, u64, String, Option<f64>);
+
+pub async fn get_open_trades(pool: &DbPool) -> Result<Vec<OpenTrade>, sqlx::Error> {
+    let rows = sqlx::query("SELECT id, user_id, token_address, amount_in_lamports, strategy, entry_price FROM trades WHERE status = 'OPEN'")
         .fetch_all(pool)
         .await?;
     
     let mut results = Vec::new();
     for row in rows {
-        let id: i32 = row.get("id");
+        let id: i64 = row.get("id");
+        let user: String = row.get("user_id");
         let token: String = row.get("token_address");
         let entry: i64 = row.get("amount_in_lamports");
-        let high: i64 = row.get("highest_price_lamports");
-        results.push((id, token, entry as u64, high as u64));
+        // Trade precedenti alla colonna strategy: nessuna durata massima
+        let strategy: Option<String> = row.get("strategy");
+        let entry_price: Option<f64> = row.get("entry_price");
+        results.push((id, user, token, entry as u64, strategy.unwrap_or_default(), entry_price));
     }
     Ok(results)
 }
 
+/// Trade aperti di un utente su un token (id, lamports investiti)
+pub async fn get_user_token_open_trades(pool: &DbPool, tg_id: &str, token_addr: &str) -> Result<Vec<(i64, u64)>, sqlx::Error> {
+    let rows = sqlx::query("SELECT id, amount_in_lamports FROM trades WHERE user_id = $1 AND token_address = $2 AND status = 'OPEN'")
+        .bind(tg_id)
+        .bind(token_addr)
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.iter().map(|r| (r.get("id"), r.get::<i64, _>("amount_in_lamports") as u64)).collect())
+}
+
+/// Trade aperti di un utente (id, token, lamports investiti)
+pub async fn get_user_open_trades(pool: &DbPool, tg_id: &str) -> Result<Vec<(i64, String, u64)>, sqlx::Error> {
+    let rows = sqlx::query("SELECT id, token_address, amount_in_lamports FROM trades WHERE user_id = $1 AND status = 'OPEN'")
+        .bind(tg_id)
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.iter().map(|r| (r.get("id"), r.get("token_address"), r.get::<i64, _>("amount_in_lamports") as u64)).collect())
+}
+
+/// Chiude i trade OPEN dell'utente su un token non più nel wallet (aperti prima di `before`).
+/// Status RECONCILED: PnL sconosciuto, fuori dalle statistiche. Ritorna gli ID chiusi.
+pub async fn reconcile_ghost_trades(pool: &DbPool, tg_id: &str, token_addr: &str, before: DateTime<Utc>) -> Result<Vec<i64>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let rows = sqlx::query("UPDATE trades SET status = 'RECONCILED', exit_time = $1 WHERE user_id = $2 AND token_address = $3 AND status = 'OPEN' AND entry_time < $4 RETURNING id")
+        .bind(Utc::now().to_rfc3339())
+        .bind(tg_id)
+        .bind(token_addr)
+        .bind(sql_timestamp(before))
+        .fetch_all(&mut *tx)
+        .await?;
+    let ids: Vec<i64> = rows.iter().map(|r| r.get("id")).collect();
+    for id in &ids {
+        sqlx::query("DELETE FROM positions WHERE trade_id = $1")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+    Ok(ids)
+}
+
+/// PnL realizzato per token sui trade chiusi (token, n. trade, lamports investiti, PnL SOL)
+pub async fn get_realized_pnl_by_token(pool: &DbPool, tg_id: &str) -> Result<Vec<(String, i64, u64, f64)>, sqlx::Error> {
+    let rows = sqlx::query("SELECT token_address, COUNT(*) as n, CAST(SUM(amount_in_lamports) AS BIGINT) as invested, SUM(profit_loss_sol) as pnl FROM trades WHERE user_id = $1 AND status = 'SOLD' GROUP BY token_address")
+        .bind(tg_id)
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.iter().map(|r| (
+        r.get("token_address"),
+        r.get("n"),
+        r.get::<Option<i64>, _>("invested").unwrap_or(0) as u64,
+        r.get::<Option<f64>, _>("pnl").unwrap_or(0.0),
+    )).collect())
+}
+
+/// Rendimenti (PnL / investito) degli ultimi `limit` trade chiusi dell'utente con una strategia, per il Kelly sizing
+pub async fn get_strategy_returns(pool: &DbPool, tg_id: &str, strategy: &str, limit: i64) -> Result<Vec<f64>, sqlx::Error> {
+    let rows = sqlx::query("SELECT profit_loss_sol / (CAST(amount_in_lamports AS DOUBLE PRECISION) / 1e9) as ret FROM trades WHERE user_id = $1 AND strategy = $2 AND status = 'SOLD' AND amount_in_lamports > 0 ORDER BY id DESC LIMIT $3")
+        .bind(tg_id)
+        .bind(strategy)
+        .bind(limit)
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.iter().map(|r| r.get("ret")).collect())
+}
+
+/// Trade chiusi dell'utente in ordine di uscita: (lamports investiti, PnL SOL). Fonte di portfolio::replay.
+pub async fn get_closed_trade_history(pool: &DbPool, tg_id: &str) -> Result<Vec<(u64, f64)>, sqlx::Error> {
+    let rows = sqlx::query(&format!("SELECT amount_in_lamports, profit_loss_sol FROM trades WHERE user_id = $1 AND status = 'SOLD' ORDER BY {}, id", sql_time("exit_time")))
+        .bind(tg_id)
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.iter().map(|r| (r.get::<i64, _>("amount_in_lamports") as u64, r.get::<Option<f64>, _>("profit_loss_sol").unwrap_or(0.0))).collect())
+}
+
+/// Statistiche di portafoglio materializzate (None = da ricalcolare)
+pub async fn get_portfolio_stats(pool: &DbPool, tg_id: &str) -> Result<Option<PortfolioStats>, sqlx::Error> {
+    let row = sqlx::query("SELECT * FROM portfolio_stats WHERE user_id = $1")
+        .bind(tg_id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(row.map(|r| PortfolioStats {
+        closed_trades: r.get("closed_trades"),
+        wins: r.get("wins"),
+        losses: r.get("losses"),
+        total_pnl_sol: r.get("total_pnl_sol"),
+        gross_profit_sol: r.get("gross_profit_sol"),
+        gross_loss_sol: r.get("gross_loss_sol"),
+        best_trade_sol: r.get("best_trade_sol"),
+        worst_trade_sol: r.get("worst_trade_sol"),
+        invested_sol: r.get("invested_sol"),
+        max_drawdown_sol: r.get("max_drawdown_sol"),
+        current_streak: r.get("current_streak"),
+        updated_at: r.get::<Option<String>, _>("updated_at").unwrap_or_default(),
+    }))
+}
+
+/// Salva (o sostituisce) la riga materializzata delle statistiche
+pub async fn save_portfolio_stats(pool: &DbPool, tg_id: &str, stats: &PortfolioStats) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO portfolio_stats (user_id, closed_trades, wins, losses, total_pnl_sol, gross_profit_sol, gross_loss_sol, best_trade_sol, worst_trade_sol, invested_sol, max_drawdown_sol, current_streak, updated_at)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+         ON CONFLICT(user_id) DO UPDATE SET closed_trades = excluded.closed_trades, wins = excluded.wins, losses = excluded.losses,
+             total_pnl_sol = excluded.total_pnl_sol, gross_profit_sol = excluded.gross_profit_sol, gross_loss_sol = excluded.gross_loss_sol,
+             best_trade_sol = excluded.best_trade_sol, worst_trade_sol = excluded.worst_trade_sol, invested_sol = excluded.invested_sol,
+             max_drawdown_sol = excluded.max_drawdown_sol, current_streak = excluded.current_streak, updated_at = excluded.updated_at")
+        .bind(tg_id)
+        .bind(stats.closed_trades)
+        .bind(stats.wins)
+        .bind(stats.losses)
+        .bind(stats.total_pnl_sol)
+        .bind(stats.gross_profit_sol)
+        .bind(stats.gross_loss_sol)
+        .bind(stats.best_trade_sol)
+        .bind(stats.worst_trade_sol)
+        .bind(stats.invested_sol)
+        .bind(stats.max_drawdown_sol)
+        .bind(stats.current_streak)
+        .bind(&stats.updated_at)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Totale prelevato con successo (lamports)
+pub async fn get_total_withdrawn(pool: &DbPool, tg_id: &str) -> Result<u64, sqlx::Error> {
+    let row = sqlx::query("SELECT CAST(SUM(amount_lamports) AS BIGINT) as total FROM withdrawals WHERE user_id = $1 AND status = 'COMPLETED'")
+        .bind(tg_id)
+        .fetch_one(pool)
+        .await?;
+    Ok(row.get::<Option<i64>, _>("total").unwrap_or(0) as u64)
+}
+
+/// Token comprati dall'utente negli ultimi `secs` secondi (Cooldown persistente): (token, secondi rimanenti)
+pub async fn active_cooldowns(pool: &DbPool, tg_id: &str, secs: i64) -> Result<Vec<(String, i64)>, sqlx::Error> {
+    let rows = sqlx::query("SELECT token_address, MAX(entry_time) as last_buy FROM trades WHERE user_id = $1 AND entry_time > $2 GROUP BY token_address")
+        .bind(tg_id)
+        .bind(sql_timestamp(Utc::now() - Duration::seconds(secs)))
+        .fetch_all(pool)
+        .await?;
+    let now = Utc::now();
+    Ok(rows.iter().filter_map(|r| {
+        // entry_time nel formato di sql_timestamp (default della colonna)
+        let last: String = r.get("last_buy");
+        let last = NaiveDateTime::parse_from_str(last.get(..19)?, "%Y-%m-%d %H:%M:%S").ok()?.and_utc();
+        let remaining = (last + Duration::seconds(secs) - now).num_seconds();
+        (remaining > 0).then(|| (r.get("token_address"), remaining))
+    }).collect())
+}
+
+/// Acquisti dell'utente da `since` (le quote di una vendita parziale condividono la TX d'ingresso: contate una volta)
+pub async fn count_buys_since(pool: &DbPool, tg_id: &str, since: DateTime<Utc>) -> Result<i64, sqlx::Error> {
+    let row = sqlx::query("SELECT COUNT(DISTINCT tx_signature) as count FROM trades WHERE user_id = $1 AND entry_time > $2")
+        .bind(tg_id)
+        .bind(sql_timestamp(since))
+        .fetch_one(pool)
+        .await?;
+    Ok(row.get::<i64, _>("count"))
+}
+
 /// Conta i trade aperti per un utente specifico
-pub async fn count_open_trades(pool: &SqlitePool, tg_id: &str) -> Result<usize, sqlx::Error> {
-    let row = sqlx::query("SELECT COUNT(1) as cnt FROM trades WHERE user_id = ? AND status = 'OPEN'")
+pub async fn count_open_trades(pool: &DbPool, tg_id: &str) -> Result<usize, sqlx::Error> {
+    let row = sqlx::query("SELECT COUNT(1) as cnt FROM trades WHERE user_id = $1 AND status = 'OPEN'")
         .bind(tg_id)
         .fetch_one(pool)
         .await?;
 
     let count: i64 = row.get("cnt");
     Ok(count as usize)
-}
\ No newline at end of file
+}
+
+/// Trade OPEN dell'utente per la valutazione dell'esposizione: (token, lamports investiti, prezzo d'ingresso se tracciato)
+pub async fn get_open_trade_entries(pool: &DbPool, tg_id: &str) -> Result<Vec<(String, u64, Option<f64>)>, sqlx::Error> {
+    let rows = sqlx::query("SELECT t.token_address, t.amount_in_lamports, p.entry_price FROM trades t LEFT JOIN positions p ON p.trade_id = t.id WHERE t.user_id = $1 AND t.status = 'OPEN'")
+        .bind(tg_id)
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.iter().map(|r| (r.get("token_address"), r.get::<i64, _>("amount_in_lamports") as u64, r.get("entry_price"))).collect())
+}
+
+// --- WATCHLIST PER UTENTE ---
+
+/// Aggiunge un token alla watchlist dell'utente (Ritorna false se era già presente)
+pub async fn add_to_watchlist(pool: &DbPool, tg_id: &str, token_addr: &str) -> Result<bool, sqlx::Error> {
+    let res = sqlx::query("INSERT INTO watchlists (user_id, token_address) VALUES ($1, $2) ON CONFLICT DO NOTHING")
+        .bind(tg_id)
+        .bind(token_addr)
+        .execute(pool)
+        .await?;
+    Ok(res.rows_affected() > 0)
+}
+
+/// Rimuove un token dalla watchlist dell'utente (Ritorna false se non c'era)
+pub async fn remove_from_watchlist(pool: &DbPool, tg_id: &str, token_addr: &str) -> Result<bool, sqlx::Error> {
+    let res = sqlx::query("DELETE FROM watchlists WHERE user_id = $1 AND token_address = $2")
+        .bind(tg_id)
+        .bind(token_addr)
+        .execute(pool)
+        .await?;
+    Ok(res.rows_affected() > 0)
+}
+
+/// Token nella watchlist personale dell'utente (vuota = usa quella di default)
+pub async fn get_watchlist(pool: &DbPool, tg_id: &str) -> Result<Vec<String>, sqlx::Error> {
+    let rows = sqlx::query("SELECT token_address FROM watchlists WHERE user_id = $1 ORDER BY added_at")
+        .bind(tg_id)
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.iter().map(|r| r.get("token_address")).collect())
+}
+
+// --- FILTRI TOKEN (Blacklist / Whitelist) ---
+
+/// Aggiunge un mint o simbolo alla lista indicata (BLACKLIST/WHITELIST). false se già presente.
+pub async fn add_token_filter(pool: &DbPool, tg_id: &str, list: &str, value: &str) -> Result<bool, sqlx::Error> {
+    let res = sqlx::query("INSERT INTO token_filters (user_id, list, value) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING")
+        .bind(tg_id)
+        .bind(list)
+        .bind(value)
+        .execute(pool)
+        .await?;
+    Ok(res.rows_affected() > 0)
+}
+
+/// Rimuove un mint o simbolo dalla lista indicata. false se non c'era.
+pub async fn remove_token_filter(pool: &DbPool, tg_id: &str, list: &str, value: &str) -> Result<bool, sqlx::Error> {
+    let res = sqlx::query("DELETE FROM token_filters WHERE user_id = $1 AND list = $2 AND value = $3")
+        .bind(tg_id)
+        .bind(list)
+        .bind(value)
+        .execute(pool)
+        .await?;
+    Ok(res.rows_affected() > 0)
+}
+
+/// Tutte le voci dei filtri dell'utente: (lista, valore)
+pub async fn get_token_filters(pool: &DbPool, tg_id: &str) -> Result<Vec<(String, String)>, sqlx::Error> {
+    let rows = sqlx::query("SELECT list, value FROM token_filters WHERE user_id = $1 ORDER BY added_at")
+        .bind(tg_id)
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.iter().map(|r| (r.get("list"), r.get("value"))).collect())
+}
+
+/// Tutti i token osservati da almeno un utente attivo (Universo del Market Strategy)
+pub async fn get_watched_tokens(pool: &DbPool) -> Result<Vec<String>, sqlx::Error> {
+    let rows = sqlx::query("SELECT DISTINCT w.token_address FROM watchlists w JOIN users u ON u.tg_id = w.user_id WHERE u.is_active = 1")
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.iter().map(|r| r.get("token_address")).collect())
+}
+
+/// Utenti (attivi o no) con il token nella watchlist personale
+pub async fn get_token_watchers(pool: &DbPool, token_addr: &str) -> Result<Vec<String>, sqlx::Error> {
+    let rows = sqlx::query("SELECT user_id FROM watchlists WHERE token_address = $1")
+        .bind(token_addr)
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.iter().map(|r| r.get("user_id")).collect())
+}
+
+/// Tutti gli utenti con l'auto-trading attivo
+pub async fn get_active_users(pool: &DbPool) -> Result<Vec<String>, sqlx::Error> {
+    let rows = sqlx::query("SELECT tg_id FROM users WHERE is_active = 1 AND COALESCE(banned, 0) = 0")
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.iter().map(|r| r.get("tg_id")).collect())
+}
+
+/// Utenti attivi che osservano il token.
+/// `in_default`: il token è nella watchlist di default, valida per chi non ne ha una personale.
+pub async fn get_active_watchers(pool: &DbPool, token_addr: &str, in_default: bool) -> Result<Vec<String>, sqlx::Error> {
+    let rows = sqlx::query(
+        "SELECT u.tg_id FROM users u WHERE u.is_active = 1 AND (
+            EXISTS (SELECT 1 FROM watchlists w WHERE w.user_id = u.tg_id AND w.token_address = $1)
+            OR ($2 = 1 AND NOT EXISTS (SELECT 1 FROM watchlists w WHERE w.user_id = u.tg_id))
+        )")
+        .bind(token_addr)
+        .bind(in_default as i64)
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.iter().map(|r| r.get("tg_id")).collect())
+}
+
+// --- POSIZIONI APERTE (Write-Through del Position Manager) ---
+
+/// Salva una nuova posizione tracciata
+pub async fn save_position(pool: &DbPool, pos: &OpenPosition) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO positions (trade_id, user_id, token_address, amount_in_lamports, entry_price, highest_value_lamports, opened_at, stop_floor_lamports, take_profit_lamports, trailing_disabled)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+         ON CONFLICT(trade_id) DO UPDATE SET user_id = excluded.user_id, token_address = excluded.token_address, amount_in_lamports = excluded.amount_in_lamports,
+             entry_price = excluded.entry_price, highest_value_lamports = excluded.highest_value_lamports, opened_at = excluded.opened_at,
+             stop_floor_lamports = excluded.stop_floor_lamports, take_profit_lamports = excluded.take_profit_lamports, trailing_disabled = excluded.trailing_disabled")
+        .bind(pos.trade_id)
+        .bind(&pos.user_id)
+        .bind(&pos.token)
+        .bind(pos.amount_in_lamports as i64)
+        .bind(pos.entry_price)
+        .bind(pos.highest_value_lamports as i64)
+        .bind(pos.opened_at)
+        .bind(pos.stop_floor_lamports as i64)
+        .bind(pos.take_profit_lamports as i64)
+        .bind(pos.trailing_disabled as i64)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Aggiorna il massimo raggiunto dalla posizione (Trailing Stop)
+pub async fn update_position_high(pool: &DbPool, trade_id: i64, new_high: u64) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE positions SET highest_value_lamports = $1 WHERE trade_id = $2")
+        .bind(new_high as i64)
+        .bind(trade_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Imposta uno stop minimo (es. Break-even) sotto il quale la posizione viene venduta
+pub async fn set_position_stop_floor(pool: &DbPool, trade_id: i64, floor: u64) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE positions SET stop_floor_lamports = $1 WHERE trade_id = $2")
+        .bind(floor as i64)
+        .bind(trade_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Uscite manuali della posizione: stop minimo, take profit (0 = disattivati) e Trailing on/off
+pub async fn update_position_exits(pool: &DbPool, trade_id: i64, stop_floor: u64, take_profit: u64, trailing_disabled: bool) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE positions SET stop_floor_lamports = $1, take_profit_lamports = $2, trailing_disabled = $3 WHERE trade_id = $4")
+        .bind(stop_floor as i64)
+        .bind(take_profit as i64)
+        .bind(trailing_disabled as i64)
+        .bind(trade_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Nota e tag (separati da virgole) del trade dell'utente: None = invariato, stringa vuota = rimosso.
+/// false se il trade non esiste o non è dell'utente.
+pub async fn annotate_trade(pool: &DbPool, tg_id: &str, trade_id: i64, note: Option<&str>, tags: Option<&str>) -> Result<bool, sqlx::Error> {
+    let res = sqlx::query("UPDATE trades SET note = NULLIF(COALESCE($1, note), ''), tags = NULLIF(COALESCE($2, tags), '') WHERE id = $3 AND user_id = $4")
+        .bind(note)
+        .bind(tags)
+        .bind(trade_id)
+        .bind(tg_id)
+        .execute(pool)
+        .await?;
+    Ok(res.rows_affected() > 0)
+}
+
+/// Chiude la posizione: trade SOLD con P&L stimato dal prezzo, TX e prezzo SOL di uscita, rimozione dello stato trailing.
+/// I valori reali (SOL ricevuti, fee) arrivano con `settle_sell` a TX confermata.
+pub async fn close_position(pool: &DbPool, trade_id: i64, pnl_sol: f64, signature: &str, sol_usd: Option<f64>) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("UPDATE trades SET status = 'SOLD', exit_time = $1, profit_loss_sol = $2, exit_signature = $3, exit_sol_usd = $4, usd_value_at_exit = (CAST(amount_in_lamports AS DOUBLE PRECISION) / 1e9 + $5) * $6 WHERE id = $7")
+        .bind(Utc::now().to_rfc3339())
+        .bind(pnl_sol)
+        .bind(signature)
+        .bind(sol_usd)
+        .bind(pnl_sol)
+        .bind(sol_usd)
+        .bind(trade_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("DELETE FROM positions WHERE trade_id = $1")
+        .bind(trade_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await
+}
+
+/// Vendita parziale: la quota venduta (`sold_in` lamports investiti) diventa un trade SOLD a sé (stessa TX d'ingresso,
+/// così settle_sell la ripartisce come le altre), il trade resta OPEN con investito, massimo e uscite manuali ridotti in proporzione.
+/// Ritorna l'ID della quota venduta.
+pub async fn split_trade(pool: &DbPool, trade_id: i64, sold_in: u64, pnl_sol: f64, signature: &str, sol_usd: Option<f64>) -> Result<i64, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let row = sqlx::query("SELECT amount_in_lamports FROM trades WHERE id = $1 AND status = 'OPEN'")
+        .bind(trade_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(sqlx::Error::RowNotFound)?;
+    let amount_in = row.get::<i64, _>("amount_in_lamports");
+    if sold_in as i64 >= amount_in { return Err(sqlx::Error::RowNotFound); }
+    let sold_share = sold_in as f64 / amount_in as f64;
+    let keep_share = 1.0 - sold_share;
+
+    let query = sqlx::query(
+        "INSERT INTO trades (user_id, token_address, tx_signature, amount_in_lamports, highest_price_lamports, status, entry_time, exit_time, profit_loss_sol,
+             entry_sol_usd, exit_sol_usd, exit_signature, usd_value_at_entry, usd_value_at_exit, strategy, note, tags)
+         SELECT user_id, token_address, tx_signature, $1, CAST(highest_price_lamports * $2 AS BIGINT), 'SOLD', entry_time, $3, $4,
+             entry_sol_usd, $5, $6, usd_value_at_entry * $7, ($8 / 1e9 + $9) * $10, strategy, note, tags
+         FROM trades WHERE id = $11 RETURNING id")
+        .bind(sold_in as i64)
+        .bind(sold_share)
+        .bind(Utc::now().to_rfc3339())
+        .bind(pnl_sol)
+        .bind(sol_usd)
+        .bind(signature)
+        .bind(sold_share)
+        .bind(sold_in as f64)
+        .bind(pnl_sol)
+        .bind(sol_usd)
+        .bind(trade_id);
+    let sold_id = insert_returning_id(query, &mut *tx).await?;
+
+    sqlx::query("UPDATE trades SET amount_in_lamports = amount_in_lamports - $1, highest_price_lamports = CAST(highest_price_lamports * $2 AS BIGINT), usd_value_at_entry = usd_value_at_entry * $3 WHERE id = $4")
+        .bind(sold_in as i64)
+        .bind(keep_share)
+        .bind(keep_share)
+        .bind(trade_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query(
+        "UPDATE positions SET amount_in_lamports = amount_in_lamports - $1, highest_value_lamports = CAST(highest_value_lamports * $2 AS BIGINT),
+             stop_floor_lamports = CAST(stop_floor_lamports * $3 AS BIGINT), take_profit_lamports = CAST(take_profit_lamports * $4 AS BIGINT)
+         WHERE trade_id = $5")
+        .bind(sold_in as i64)
+        .bind(keep_share)
+        .bind(keep_share)
+        .bind(keep_share)
+        .bind(trade_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(sold_id)
+}
+
+/// Vendita confermata on-chain: ripartisce SOL ricevuti (post - pre balances) e fee reali sui trade chiusi da quella TX,
+/// in proporzione all'investito. PnL realizzato = ricevuto - investito - fee (acquisto + quota vendita).
+/// Idempotente: ritorna quanti trade sono stati aggiornati (0 se la TX non è ancora confermata o non chiude trade).
+pub async fn settle_sell(pool: &DbPool, signature: &str) -> Result<u64, sqlx::Error> {
+    let exec = sqlx::query("SELECT actual_out, fee_lamports FROM executions WHERE tx_signature = $1 AND side = 'SELL' AND status = 'CONFIRMED'")
+        .bind(signature)
+        .fetch_optional(pool)
+        .await?;
+    let (received, sell_fee) = match exec {
+        Some(r) => match r.get::<Option<i64>, _>("actual_out") {
+            Some(out) => (out as f64, r.get::<Option<i64>, _>("fee_lamports").unwrap_or(0) as f64),
+            None => return Ok(0),
+        },
+        None => return Ok(0),
+    };
+
+    let trades = sqlx::query(
+        "SELECT t.id, t.amount_in_lamports, t.exit_sol_usd, COALESCE(e.fee_lamports, 0) as buy_fee
+         FROM trades t LEFT JOIN executions e ON e.tx_signature = t.tx_signature
+         WHERE t.exit_signature = $1 AND t.status = 'SOLD'"
+    )
+        .bind(signature)
+        .fetch_all(pool)
+        .await?;
+    let total_in: i64 = trades.iter().map(|r| r.get::<i64, _>("amount_in_lamports")).sum();
+    if total_in <= 0 { return Ok(0); }
+
+    let mut tx = pool.begin().await?;
+    // Il PnL cambia: le statistiche di portafoglio degli utenti coinvolti si rifanno alla prossima lettura
+    sqlx::query("DELETE FROM portfolio_stats WHERE user_id IN (SELECT user_id FROM trades WHERE exit_signature = $1)")
+        .bind(signature)
+        .execute(&mut *tx)
+        .await?;
+    for r in &trades {
+        let amount_in = r.get::<i64, _>("amount_in_lamports") as f64;
+        let share = amount_in / total_in as f64;
+        let sol_received = received * share;
+        let fee = r.get::<i64, _>("buy_fee") as f64 + sell_fee * share;
+        let pnl_sol = (sol_received - amount_in - fee) / 1e9;
+        let usd_exit = r.get::<Option<f64>, _>("exit_sol_usd").map(|p| sol_received / 1e9 * p);
+        sqlx::query("UPDATE trades SET sol_received_lamports = $1, fee_lamports = $2, profit_loss_sol = $3, usd_value_at_exit = $4 WHERE id = $5")
+            .bind(sol_received as i64)
+            .bind(fee as i64)
+            .bind(pnl_sol)
+            .bind(usd_exit)
+            .bind(r.get::<i64, _>("id"))
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+    Ok(trades.len() as u64)
+}
+
+/// Acquisto confermato on-chain: prezzo d'ingresso in USD dal fill reale (SOL spesi / token ricevuti, al prezzo SOL dell'ingresso)
+/// sui trade aperti da quella TX e sulle loro posizioni salvate.
+/// Idempotente: ritorna quanti trade sono stati aggiornati (0 se la TX non è ancora confermata o il trade non è ancora registrato).
+pub async fn settle_buy(pool: &DbPool, signature: &str) -> Result<u64, sqlx::Error> {
+    let exec = sqlx::query("SELECT actual_out, spent_lamports, out_decimals FROM executions WHERE tx_signature = $1 AND side = 'BUY' AND status = 'CONFIRMED'")
+        .bind(signature)
+        .fetch_optional(pool)
+        .await?;
+    let (received, spent, decimals) = match exec {
+        Some(r) => match (r.get::<Option<i64>, _>("actual_out"), r.get::<Option<i64>, _>("spent_lamports"), r.get::<Option<i64>, _>("out_decimals")) {
+            (Some(out), Some(spent), Some(dec)) if out > 0 && spent > 0 => (out as f64, spent as f64, dec as i32),
+            _ => return Ok(0),
+        },
+        None => return Ok(0),
+    };
+    let tokens = received / 10f64.powi(decimals);
+
+    let trades = sqlx::query("SELECT id, entry_sol_usd FROM trades WHERE tx_signature = $1 AND status = 'OPEN'")
+        .bind(signature)
+        .fetch_all(pool)
+        .await?;
+    let mut tx = pool.begin().await?;
+    let mut updated = 0;
+    for r in &trades {
+        // Senza prezzo SOL all'ingresso il fill non è confrontabile con i prezzi USD di mercato
+        let Some(sol_usd) = r.get::<Option<f64>, _>("entry_sol_usd") else { continue };
+        let entry_price = spent / 1e9 * sol_usd / tokens;
+        let trade_id: i64 = r.get("id");
+        sqlx::query("UPDATE trades SET entry_price = $1 WHERE id = $2")
+            .bind(entry_price)
+            .bind(trade_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("UPDATE positions SET entry_price = $1 WHERE trade_id = $2")
+            .bind(entry_price)
+            .bind(trade_id)
+            .execute(&mut *tx)
+            .await?;
+        updated += 1;
+    }
+    tx.commit().await?;
+    Ok(updated)
+}
+
+/// Ricarica le posizioni salvate (solo quelle con trade ancora OPEN)
+pub async fn load_positions(pool: &DbPool) -> Result<Vec<OpenPosition>, sqlx::Error> {
+    let rows = sqlx::query("SELECT p.*, t.strategy FROM positions p JOIN trades t ON t.id = p.trade_id WHERE t.status = 'OPEN'")
+        .fetch_all(pool)
+        .await?;
+
+    Ok(rows.iter().map(|r| OpenPosition {
+        trade_id: r.get("trade_id"),
+        user_id: r.get("user_id"),
+        token: r.get("token_address"),
+        amount_in_lamports: r.get::<i64, _>("amount_in_lamports") as u64,
+        entry_price: r.get("entry_price"),
+        highest_value_lamports: r.get::<i64, _>("highest_value_lamports") as u64,
+        opened_at: r.get("opened_at"),
+        stop_floor_lamports: r.get::<i64, _>("stop_floor_lamports") as u64,
+        take_profit_lamports: r.get::<i64, _>("take_profit_lamports") as u64,
+        trailing_disabled: r.get::<i64, _>("trailing_disabled") == 1,
+        strategy: r.get::<Option<String>, _>("strategy").unwrap_or_default(),
+    }).collect())
+}
+
+// --- IMPOSTAZIONI UTENTE (JSON in users.settings) ---
+
+/// Legge tutte le impostazioni dell'utente (oggetto vuoto se mancanti o corrotte)
+pub async fn get_settings(pool: &DbPool, tg_id: &str) -> Result<serde_json::Value, sqlx::Error> {
+    let row = sqlx::query("SELECT settings FROM users WHERE tg_id = $1")
+        .bind(tg_id)
+        .fetch_optional(pool)
+        .await?;
+
+    let raw: Option<String> = row.and_then(|r| r.try_get("settings").ok());
+    Ok(raw.and_then(|s| serde_json::from_str::<serde_json::Value>(&s).ok())
+        .filter(|v| v.is_object())
+        .unwrap_or_else(|| serde_json::json!({})))
+}
+
+/// Aggiorna una singola chiave delle impostazioni senza toccare le altre
+pub async fn update_setting(pool: &DbPool, tg_id: &str, key: &str, value: serde_json::Value) -> Result<(), sqlx::Error> {
+    let mut settings = get_settings(pool, tg_id).await?;
+    settings[key] = value;
+    sqlx::query("UPDATE users SET settings = $1 WHERE tg_id = $2")
+        .bind(settings.to_string())
+        .bind(tg_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Impostazioni di tutti gli utenti (tg_id, settings JSON) per i task periodici
+pub async fn get_all_user_settings(pool: &DbPool) -> Result<Vec<(String, serde_json::Value)>, sqlx::Error> {
+    let rows = sqlx::query("SELECT tg_id, settings FROM users")
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.iter().map(|r| {
+        let raw: Option<String> = r.try_get("settings").ok();
+        let settings = raw.and_then(|s| serde_json::from_str::<serde_json::Value>(&s).ok())
+            .filter(|v| v.is_object())
+            .unwrap_or_else(|| serde_json::json!({}));
+        (r.get("tg_id"), settings)
+    }).collect())
+}
+
+/// Statistiche dal momento `since` (UTC): (trade aperti, trade chiusi, PnL realizzato SOL)
+pub async fn get_stats_since(pool: &DbPool, tg_id: &str, since: DateTime<Utc>) -> Result<(i64, i64, f64), sqlx::Error> {
+    let since_str = sql_timestamp(since);
+    let opened = sqlx::query(&format!("SELECT COUNT(*) as n FROM trades WHERE user_id = $1 AND {} >= $2", sql_time("entry_time")))
+        .bind(tg_id)
+        .bind(&since_str)
+        .fetch_one(pool)
+        .await?;
+    let closed = sqlx::query(&format!("SELECT COUNT(*) as n, SUM(profit_loss_sol) as pnl FROM trades WHERE user_id = $1 AND status = 'SOLD' AND {} >= $2", sql_time("exit_time")))
+        .bind(tg_id)
+        .bind(&since_str)
+        .fetch_one(pool)
+        .await?;
+    Ok((opened.get("n"), closed.get("n"), closed.get::<Option<f64>, _>("pnl").unwrap_or(0.0)))
+}
+
+/// Trade chiusi dal momento indicato raggruppati per engine (numero, vincenti, PnL)
+pub async fn get_strategy_breakdown_since(pool: &DbPool, tg_id: &str, since: DateTime<Utc>) -> Result<Vec<StrategyBreakdown>, sqlx::Error> {
+    let rows = sqlx::query(&format!(
+        "SELECT COALESCE(strategy, '') as strategy, COUNT(*) as n, SUM(CASE WHEN profit_loss_sol > 0 THEN 1 ELSE 0 END) as wins, SUM(profit_loss_sol) as pnl
+         FROM trades WHERE user_id = $1 AND status = 'SOLD' AND {} >= $2 GROUP BY COALESCE(strategy, '') ORDER BY n DESC", sql_time("exit_time")))
+        .bind(tg_id)
+        .bind(sql_timestamp(since))
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.iter().map(|r| StrategyBreakdown {
+        strategy: r.get("strategy"),
+        closed: r.get("n"),
+        wins: r.get::<Option<i64>, _>("wins").unwrap_or(0),
+        pnl_sol: r.get::<Option<f64>, _>("pnl").unwrap_or(0.0),
+    }).collect())
+}
+
+/// Miglior e peggior trade chiuso dal momento indicato (per PnL in SOL)
+pub async fn get_best_worst_trades_since(pool: &DbPool, tg_id: &str, since: DateTime<Utc>) -> Result<(Option<TradeOutcome>, Option<TradeOutcome>), sqlx::Error> {
+    let since_str = sql_timestamp(since);
+    let mut outcomes = Vec::with_capacity(2);
+    for order in ["DESC", "ASC"] {
+        let row = sqlx::query(&format!(
+            "SELECT t.token_address, m.symbol, t.profit_loss_sol, t.amount_in_lamports
+             FROM trades t LEFT JOIN token_metadata m ON m.mint = t.token_address
+             WHERE t.user_id = $1 AND t.status = 'SOLD' AND {} >= $2 ORDER BY t.profit_loss_sol {}, t.id DESC LIMIT 1", sql_time("t.exit_time"), order))
+            .bind(tg_id)
+            .bind(&since_str)
+            .fetch_optional(pool)
+            .await?;
+        outcomes.push(row.map(|r| {
+            let pnl_sol = r.get::<Option<f64>, _>("profit_loss_sol").unwrap_or(0.0);
+            let invested = r.get::<i64, _>("amount_in_lamports") as f64 / 1e9;
+            TradeOutcome {
+                token: r.get("token_address"),
+                symbol: r.get("symbol"),
+                pnl_sol,
+                pnl_pct: if invested > 0.0 { pnl_sol / invested * 100.0 } else { 0.0 },
+            }
+        }));
+    }
+    let worst = outcomes.pop().flatten();
+    let best = outcomes.pop().flatten();
+    Ok((best, worst))
+}
+
+/// PnL realizzato per ora (chiave "YYYY-MM-DD HH", UTC) dal momento indicato, in ordine cronologico
+pub async fn get_hourly_pnl_since(pool: &DbPool, tg_id: &str, since: DateTime<Utc>) -> Result<Vec<(String, f64)>, sqlx::Error> {
+    let hour = format!("SUBSTR({}, 1, 13)", sql_time("exit_time"));
+    let rows = sqlx::query(&format!(
+        "SELECT {hour} as hour, SUM(profit_loss_sol) as pnl FROM trades
+         WHERE user_id = $1 AND status = 'SOLD' AND {} >= $2 GROUP BY {hour} ORDER BY hour", sql_time("exit_time")))
+        .bind(tg_id)
+        .bind(sql_timestamp(since))
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.iter().map(|r| (r.get("hour"), r.get::<Option<f64>, _>("pnl").unwrap_or(0.0))).collect())
+}
+
+/// Parametri strategia dell'utente (default per i campi non personalizzati)
+pub async fn get_strategy_params(pool: &DbPool, tg_id: &str) -> StrategyParams {
+    get_settings(pool, tg_id).await.ok()
+        .and_then(|s| serde_json::from_value(s["strategy"].clone()).ok())
+        .unwrap_or_default()
+}
+
+/// Salva i parametri strategia dell'utente
+pub async fn save_strategy_params(pool: &DbPool, tg_id: &str, params: &StrategyParams) -> Result<(), sqlx::Error> {
+    update_setting(pool, tg_id, "strategy", serde_json::json!(params)).await
+}
+
+// --- AUTENTICAZIONE WEB (Email + Password, Sessioni) ---
+
+/// Cerca un utente per email: (tg_id, password_hash)
+pub async fn get_user_by_email(pool: &DbPool, email: &str) -> Result<Option<(String, String)>, sqlx::Error> {
+    let row = sqlx::query("SELECT tg_id, password_hash FROM users WHERE email = $1 AND password_hash IS NOT NULL")
+        .bind(email)
+        .fetch_optional(pool)
+        .await?;
+    Ok(row.map(|r| (r.get("tg_id"), r.get("password_hash"))))
+}
+
+/// Imposta le credenziali Web (solo se l'utente non ne ha già)
+pub async fn set_user_credentials(pool: &DbPool, tg_id: &str, email: &str, password_hash: &str) -> Result<bool, sqlx::Error> {
+    let res = sqlx::query("UPDATE users SET email = $1, password_hash = $2 WHERE tg_id = $3 AND password_hash IS NULL")
+        .bind(email)
+        .bind(password_hash)
+        .bind(tg_id)
+        .execute(pool)
+        .await?;
+    Ok(res.rows_affected() > 0)
+}
+
+/// Email Web dell'utente e stato della verifica: (email, verificata)
+pub async fn get_user_email(pool: &DbPool, tg_id: &str) -> Result<Option<(String, bool)>, sqlx::Error> {
+    let row = sqlx::query("SELECT email, email_verified_at FROM users WHERE tg_id = $1 AND email IS NOT NULL AND password_hash IS NOT NULL")
+        .bind(tg_id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(row.map(|r| (r.get("email"), r.get::<Option<String>, _>("email_verified_at").is_some())))
+}
+
+pub async fn mark_email_verified(pool: &DbPool, tg_id: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE users SET email_verified_at = $1 WHERE tg_id = $2 AND email_verified_at IS NULL")
+        .bind(sql_timestamp(Utc::now()))
+        .bind(tg_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Nuova password dopo un reset: l'email risulta verificata e tutte le sessioni vengono chiuse
+pub async fn reset_password(pool: &DbPool, tg_id: &str, password_hash: &str) -> Result<bool, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let res = sqlx::query("UPDATE users SET password_hash = $1, email_verified_at = COALESCE(email_verified_at, $2) WHERE tg_id = $3 AND password_hash IS NOT NULL")
+        .bind(password_hash)
+        .bind(sql_timestamp(Utc::now()))
+        .bind(tg_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("DELETE FROM sessions WHERE user_id = $1")
+        .bind(tg_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(res.rows_affected() > 0)
+}
+
+/// Registra un token email (purpose VERIFY | RESET): quelli precedenti non usati dello stesso tipo decadono
+pub async fn create_email_token(pool: &DbPool, tg_id: &str, purpose: &str, token_hash: &str, ttl_secs: i64) -> Result<(), sqlx::Error> {
+    let now = Utc::now().timestamp();
+    let mut tx = pool.begin().await?;
+    sqlx::query("DELETE FROM email_tokens WHERE user_id = $1 AND purpose = $2 AND (used_at IS NULL OR expires_at <= $3)")
+        .bind(tg_id)
+        .bind(purpose)
+        .bind(now)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("INSERT INTO email_tokens (token_hash, user_id, purpose, created_at, expires_at) VALUES ($1, $2, $3, $4, $5)")
+        .bind(token_hash)
+        .bind(tg_id)
+        .bind(purpose)
+        .bind(now)
+        .bind(now + ttl_secs)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Usa un token email valido (una volta sola). None = inesistente, scaduto o già usato.
+pub async fn consume_email_token(pool: &DbPool, purpose: &str, token_hash: &str) -> Result<Option<String>, sqlx::Error> {
+    let now = Utc::now().timestamp();
+    let res = sqlx::query("UPDATE email_tokens SET used_at = $1 WHERE token_hash = $2 AND purpose = $3 AND used_at IS NULL AND expires_at > $1")
+        .bind(now)
+        .bind(token_hash)
+        .bind(purpose)
+        .execute(pool)
+        .await?;
+    if res.rows_affected() == 0 { return Ok(None); }
+    let row = sqlx::query("SELECT user_id FROM email_tokens WHERE token_hash = $1")
+        .bind(token_hash)
+        .fetch_optional(pool)
+        .await?;
+    Ok(row.map(|r| r.get("user_id")))
+}
+
+// Dispositivo collegato (GET /auth/sessions): il token non esce mai, si revoca con device_id
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct SessionDevice {
+    pub device_id: String,
+    pub user_agent: String,
+    pub ip: String,
+    pub created_at: i64,
+    pub last_seen: i64,
+    pub expires_at: i64,
+    // true = sessione della richiesta corrente
+    pub current: bool,
+}
+
+/// Crea una sessione con scadenza per un dispositivo. Le sessioni si cercano sempre per hash (auth::session_token_hash).
+pub async fn create_session(pool: &DbPool, tg_id: &str, token_hash: &str, ttl_hours: i64, device_id: &str, user_agent: &str, ip: &str) -> Result<(), sqlx::Error> {
+    let now = Utc::now().timestamp();
+    sqlx::query("INSERT INTO sessions (token_hash, user_id, created_at, expires_at, device_id, user_agent, ip, last_seen) VALUES ($1, $2, $3, $4, $5, $6, $7, $3)")
+        .bind(token_hash)
+        .bind(tg_id)
+        .bind(now)
+        .bind(now + ttl_hours * 3600)
+        .bind(device_id)
+        .bind(user_agent)
+        .bind(ip)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Aggiorna ultimo utilizzo e IP (al massimo una scrittura al minuto per sessione)
+pub async fn touch_session(pool: &DbPool, token_hash: &str, ip: &str) -> Result<(), sqlx::Error> {
+    let now = Utc::now().timestamp();
+    sqlx::query("UPDATE sessions SET last_seen = $1, ip = $2 WHERE token_hash = $3 AND last_seen < $4")
+        .bind(now)
+        .bind(ip)
+        .bind(token_hash)
+        .bind(now - 60)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Ruota il token di una sessione valida e ne sposta la scadenza. None = sessione inesistente o scaduta.
+pub async fn refresh_session(pool: &DbPool, old_hash: &str, new_hash: &str, ttl_hours: i64) -> Result<Option<String>, sqlx::Error> {
+    let now = Utc::now().timestamp();
+    let res = sqlx::query("UPDATE sessions SET token_hash = $1, expires_at = $2, last_seen = $3 WHERE token_hash = $4 AND expires_at > $3")
+        .bind(new_hash)
+        .bind(now + ttl_hours * 3600)
+        .bind(now)
+        .bind(old_hash)
+        .execute(pool)
+        .await?;
+    if res.rows_affected() == 0 { return Ok(None); }
+    get_session_user(pool, new_hash).await
+}
+
+/// Logout: chiude la sessione del token. false = già chiusa.
+pub async fn delete_session(pool: &DbPool, token_hash: &str) -> Result<bool, sqlx::Error> {
+    let res = sqlx::query("DELETE FROM sessions WHERE token_hash = $1")
+        .bind(token_hash)
+        .execute(pool)
+        .await?;
+    Ok(res.rows_affected() > 0)
+}
+
+/// Sessioni valide dell'utente, dalla più usata di recente. `current_hash` marca quella della richiesta.
+pub async fn get_user_sessions(pool: &DbPool, user_id: &str, current_hash: Option<&str>) -> Result<Vec<SessionDevice>, sqlx::Error> {
+    let rows = sqlx::query("SELECT token_hash, device_id, user_agent, ip, created_at, last_seen, expires_at FROM sessions WHERE user_id = $1 AND expires_at > $2 ORDER BY last_seen DESC")
+        .bind(user_id)
+        .bind(Utc::now().timestamp())
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.iter().map(|r| SessionDevice {
+        current: current_hash.is_some_and(|h| h == r.get::<String, _>("token_hash")),
+        device_id: r.get::<Option<String>, _>("device_id").unwrap_or_default(),
+        user_agent: r.get("user_agent"),
+        ip: r.get("ip"),
+        created_at: r.get("created_at"),
+        last_seen: r.get("last_seen"),
+        expires_at: r.get("expires_at"),
+    }).collect())
+}
+
+/// Revoca la sessione di un dispositivo dell'utente. false = dispositivo inesistente.
+pub async fn revoke_session_device(pool: &DbPool, user_id: &str, device_id: &str) -> Result<bool, sqlx::Error> {
+    let res = sqlx::query("DELETE FROM sessions WHERE user_id = $1 AND device_id = $2")
+        .bind(user_id)
+        .bind(device_id)
+        .execute(pool)
+        .await?;
+    Ok(res.rows_affected() > 0)
+}
+
+/// Utente proprietario di una sessione valida (None se inesistente o scaduta)
+pub async fn get_session_user(pool: &DbPool, token_hash: &str) -> Result<Option<String>, sqlx::Error> {
+    let row = sqlx::query("SELECT user_id FROM sessions WHERE token_hash = $1 AND expires_at > $2")
+        .bind(token_hash)
+        .bind(Utc::now().timestamp())
+        .fetch_optional(pool)
+        .await?;
+    Ok(row.map(|r| r.get("user_id")))
+}
+
+/// Pulizia sessioni scadute
+pub async fn purge_expired_sessions(pool: &DbPool) {
+    let _ = sqlx::query("DELETE FROM sessions WHERE expires_at <= $1")
+        .bind(Utc::now().timestamp())
+        .execute(pool)
+        .await;
+}
+
+// --- DCA (Acquisti Ricorrenti) ---
+
+fn row_to_dca(r: &AnyRow) -> DcaOrder {
+    DcaOrder {
+        id: r.get("id"),
+        user_id: r.get("user_id"),
+        token: r.get("token_address"),
+        amount_lamports: r.get::<i64, _>("amount_lamports") as u64,
+        interval_secs: r.get("interval_secs"),
+        status: r.get("status"),
+        next_run: r.get("next_run"),
+        fills: r.get("fills"),
+        total_spent_lamports: r.get::<i64, _>("total_spent_lamports") as u64,
+    }
+}
+
+/// Crea un ordine DCA attivo, primo acquisto al prossimo giro dello scheduler
+pub async fn create_dca_order(pool: &DbPool, tg_id: &str, token_addr: &str, amount_lamports: u64, interval_secs: i64) -> Result<i64, sqlx::Error> {
+    let query = sqlx::query("INSERT INTO dca_orders (user_id, token_address, amount_lamports, interval_secs, status, next_run) VALUES ($1, $2, $3, $4, 'ACTIVE', $5) RETURNING id")
+        .bind(tg_id)
+        .bind(token_addr)
+        .bind(amount_lamports as i64)
+        .bind(interval_secs)
+        .bind(Utc::now().timestamp());
+    insert_returning_id(query, pool).await
+}
+
+/// Ordini DCA dell'utente (esclusi i cancellati)
+pub async fn get_dca_orders(pool: &DbPool, tg_id: &str) -> Result<Vec<DcaOrder>, sqlx::Error> {
+    let rows = sqlx::query("SELECT * FROM dca_orders WHERE user_id = $1 AND status != 'CANCELLED' ORDER BY id")
+        .bind(tg_id)
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.iter().map(row_to_dca).collect())
+}
+
+/// Conta gli ordini DCA non cancellati (limite per utente)
+pub async fn count_dca_orders(pool: &DbPool, tg_id: &str) -> Result<usize, sqlx::Error> {
+    let row = sqlx::query("SELECT COUNT(*) as count FROM dca_orders WHERE user_id = $1 AND status != 'CANCELLED'")
+        .bind(tg_id)
+        .fetch_one(pool)
+        .await?;
+    Ok(row.get::<i64, _>("count") as usize)
+}
+
+/// Ordini attivi da eseguire ora
+pub async fn get_due_dca_orders(pool: &DbPool, now: i64) -> Result<Vec<DcaOrder>, sqlx::Error> {
+    let rows = sqlx::query("SELECT * FROM dca_orders WHERE status = 'ACTIVE' AND next_run <= $1")
+        .bind(now)
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.iter().map(row_to_dca).collect())
+}
+
+/// Cambia stato (PAUSED/ACTIVE/CANCELLED). Un ordine cancellato non si riattiva.
+/// Ritorna false se l'ordine non esiste o non appartiene all'utente.
+pub async fn set_dca_status(pool: &DbPool, tg_id: &str, order_id: i64, status: &str) -> Result<bool, sqlx::Error> {
+    let res = sqlx::query("UPDATE dca_orders SET status = $1 WHERE id = $2 AND user_id = $3 AND status != 'CANCELLED'")
+        .bind(status)
+        .bind(order_id)
+        .bind(tg_id)
+        .execute(pool)
+        .await?;
+    Ok(res.rows_affected() > 0)
+}
+
+/// Registra un acquisto DCA eseguito e programma il prossimo
+pub async fn record_dca_fill(pool: &DbPool, order_id: i64, amount_lamports: u64, next_run: i64) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE dca_orders SET fills = fills + 1, total_spent_lamports = total_spent_lamports + $1, next_run = $2 WHERE id = $3")
+        .bind(amount_lamports as i64)
+        .bind(next_run)
+        .bind(order_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Sposta il prossimo tentativo senza registrare un fill (swap fallito)
+pub async fn reschedule_dca_order(pool: &DbPool, order_id: i64, next_run: i64) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE dca_orders SET next_run = $1 WHERE id = $2")
+        .bind(next_run)
+        .bind(order_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+// --- LIMIT ORDERS ---
+
+fn row_to_limit(r: &AnyRow) -> LimitOrder {
+    LimitOrder {
+        id: r.get("id"),
+        user_id: r.get("user_id"),
+        token: r.get("token_address"),
+        side: r.get("side"),
+        trigger_price: r.get("trigger_price"),
+        trigger_above: r.get::<i64, _>("trigger_above") == 1,
+        amount_lamports: r.get::<i64, _>("amount_lamports") as u64,
+        status: r.get("status"),
+        tx_signature: r.get("tx_signature"),
+    }
+}
+
+/// Crea un ordine limite attivo
+pub async fn create_limit_order(pool: &DbPool, tg_id: &str, token_addr: &str, side: &str, trigger_price: f64, trigger_above: bool, amount_lamports: u64) -> Result<i64, sqlx::Error> {
+    let query = sqlx::query("INSERT INTO limit_orders (user_id, token_address, side, trigger_price, trigger_above, amount_lamports) VALUES ($1, $2, $3, $4, $5, $6) RETURNING id")
+        .bind(tg_id)
+        .bind(token_addr)
+        .bind(side)
+        .bind(trigger_price)
+        .bind(trigger_above as i64)
+        .bind(amount_lamports as i64);
+    insert_returning_id(query, pool).await
+}
+
+/// Ordini limite dell'utente (ultimi 50, cancellati esclusi)
+pub async fn get_limit_orders(pool: &DbPool, tg_id: &str) -> Result<Vec<LimitOrder>, sqlx::Error> {
+    let rows = sqlx::query("SELECT * FROM limit_orders WHERE user_id = $1 AND status != 'CANCELLED' ORDER BY id DESC LIMIT 50")
+        .bind(tg_id)
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.iter().map(row_to_limit).collect())
+}
+
+/// Tutti gli ordini in attesa del prezzo (per il watcher)
+pub async fn get_active_limit_orders(pool: &DbPool) -> Result<Vec<LimitOrder>, sqlx::Error> {
+    let rows = sqlx::query("SELECT * FROM limit_orders WHERE status = 'ACTIVE'")
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.iter().map(row_to_limit).collect())
+}
+
+/// Cancella un ordine ancora attivo. false se inesistente, di altri o già eseguito.
+pub async fn cancel_limit_order(pool: &DbPool, tg_id: &str, order_id: i64) -> Result<bool, sqlx::Error> {
+    let res = sqlx::query("UPDATE limit_orders SET status = 'CANCELLED' WHERE id = $1 AND user_id = $2 AND status = 'ACTIVE'")
+        .bind(order_id)
+        .bind(tg_id)
+        .execute(pool)
+        .await?;
+    Ok(res.rows_affected() > 0)
+}
+
+/// Prenota l'esecuzione (ACTIVE -> EXECUTING): evita doppi fill e cancellazioni a metà swap
+pub async fn claim_limit_order(pool: &DbPool, order_id: i64) -> Result<bool, sqlx::Error> {
+    let res = sqlx::query("UPDATE limit_orders SET status = 'EXECUTING' WHERE id = $1 AND status = 'ACTIVE'")
+        .bind(order_id)
+        .execute(pool)
+        .await?;
+    Ok(res.rows_affected() > 0)
+}
+
+/// Esito finale dell'ordine (FILLED con firma, oppure FAILED)
+pub async fn finish_limit_order(pool: &DbPool, order_id: i64, status: &str, signature: Option<&str>, price: f64) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE limit_orders SET status = $1, tx_signature = $2, filled_price = $3 WHERE id = $4")
+        .bind(status)
+        .bind(signature)
+        .bind(price)
+        .bind(order_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+// --- TRADE IN ATTESA DI APPROVAZIONE (Modalità solo segnali) ---
+
+fn row_to_pending(r: &AnyRow) -> PendingTrade {
+    PendingTrade {
+        id: r.get("id"),
+        user_id: r.get("user_id"),
+        token: r.get("token_address"),
+        symbol: r.get("symbol"),
+        engine: r.get("engine"),
+        source: r.get("source"),
+        score: r.get::<i64, _>("score").clamp(0, 100) as u8,
+        amount_lamports: r.get::<i64, _>("amount_lamports") as u64,
+        quoted_out: r.get::<i64, _>("quoted_out") as u64,
+        slippage_bps: r.get::<i64, _>("slippage_bps") as u16,
+        status: r.get("status"),
+        tx_signature: r.get("tx_signature"),
+        created_at: r.get("created_at"),
+    }
+}
+
+/// Registra un trade suggerito. None se l'utente ne ha già uno in attesa sullo stesso token.
+pub async fn create_pending_trade(pool: &DbPool, p: &PendingTrade) -> Result<Option<i64>, sqlx::Error> {
+    let query = sqlx::query("INSERT INTO pending_trades (user_id, token_address, symbol, engine, source, score, amount_lamports, quoted_out, slippage_bps) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9) ON CONFLICT DO NOTHING RETURNING id")
+        .bind(&p.user_id)
+        .bind(&p.token)
+        .bind(&p.symbol)
+        .bind(&p.engine)
+        .bind(&p.source)
+        .bind(p.score as i64)
+        .bind(p.amount_lamports as i64)
+        .bind(p.quoted_out as i64)
+        .bind(p.slippage_bps as i64);
+    Ok(fetch_returning(query, pool).await?.map(|r| r.get("id")))
+}
+
+/// Trade dell'utente ancora approvabili (più recenti prima)
+pub async fn get_pending_trades(pool: &DbPool, tg_id: &str, ttl_minutes: i64) -> Result<Vec<PendingTrade>, sqlx::Error> {
+    let rows = sqlx::query("SELECT * FROM pending_trades WHERE user_id = $1 AND status = 'PENDING' AND created_at >= $2 ORDER BY id DESC")
+        .bind(tg_id)
+        .bind(sql_timestamp(Utc::now() - Duration::minutes(ttl_minutes)))
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.iter().map(row_to_pending).collect())
+}
+
+/// Approvazione: il trade passa a EXECUTING solo se ancora in attesa e non scaduto
+pub async fn claim_pending_trade(pool: &DbPool, tg_id: &str, id: i64, ttl_minutes: i64) -> Result<Option<PendingTrade>, sqlx::Error> {
+    let query = sqlx::query("UPDATE pending_trades SET status = 'EXECUTING' WHERE id = $1 AND user_id = $2 AND status = 'PENDING' AND created_at >= $3 RETURNING *")
+        .bind(id)
+        .bind(tg_id)
+        .bind(sql_timestamp(Utc::now() - Duration::minutes(ttl_minutes)));
+    Ok(fetch_returning(query, pool).await?.as_ref().map(row_to_pending))
+}
+
+/// Esito dell'esecuzione (EXECUTED con firma, FAILED senza)
+pub async fn finish_pending_trade(pool: &DbPool, id: i64, signature: Option<&str>) {
+    let _ = sqlx::query("UPDATE pending_trades SET status = $1, tx_signature = $2 WHERE id = $3")
+        .bind(if signature.is_some() { "EXECUTED" } else { "FAILED" })
+        .bind(signature)
+        .bind(id)
+        .execute(pool)
+        .await;
+}
+
+/// Rifiuto dell'utente (solo se ancora in attesa)
+pub async fn reject_pending_trade(pool: &DbPool, tg_id: &str, id: i64) -> Result<bool, sqlx::Error> {
+    let res = sqlx::query("UPDATE pending_trades SET status = 'REJECTED' WHERE id = $1 AND user_id = $2 AND status = 'PENDING'")
+        .bind(id)
+        .bind(tg_id)
+        .execute(pool)
+        .await?;
+    Ok(res.rows_affected() > 0)
+}
+
+/// Fa scadere i trade non approvati in tempo. Ritorna (id, utente, simbolo) per avvisarli.
+pub async fn expire_pending_trades(pool: &DbPool, ttl_minutes: i64) -> Result<Vec<(i64, String, String)>, sqlx::Error> {
+    let rows = sqlx::query("UPDATE pending_trades SET status = 'EXPIRED' WHERE status = 'PENDING' AND created_at < $1 RETURNING id, user_id, symbol")
+        .bind(sql_timestamp(Utc::now() - Duration::minutes(ttl_minutes)))
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.iter().map(|r| (r.get("id"), r.get("user_id"), r.get("symbol"))).collect())
+}
+
+// --- WHITELIST INDIRIZZI DI PRELIEVO ---
+
+fn row_to_withdrawal_address(r: &AnyRow) -> WithdrawalAddress {
+    WithdrawalAddress {
+        id: r.get("id"),
+        address: r.get("address"),
+        label: r.get("label"),
+        status: r.get("status"),
+        added_at: r.get("added_at"),
+    }
+}
+
+/// Indirizzi in whitelist dell'utente (confermati e in attesa)
+pub async fn get_withdrawal_addresses(pool: &DbPool, tg_id: &str) -> Result<Vec<WithdrawalAddress>, sqlx::Error> {
+    let rows = sqlx::query("SELECT * FROM withdrawal_addresses WHERE user_id = $1 ORDER BY id ASC")
+        .bind(tg_id)
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.iter().map(row_to_withdrawal_address).collect())
+}
+
+/// Indirizzo specifico dell'utente, se registrato
+pub async fn get_withdrawal_address(pool: &DbPool, tg_id: &str, address: &str) -> Result<Option<WithdrawalAddress>, sqlx::Error> {
+    let row = sqlx::query("SELECT * FROM withdrawal_addresses WHERE user_id = $1 AND address = $2")
+        .bind(tg_id)
+        .bind(address)
+        .fetch_optional(pool)
+        .await?;
+    Ok(row.as_ref().map(row_to_withdrawal_address))
+}
+
+/// Registra un indirizzo in attesa di conferma. None se già presente.
+pub async fn add_withdrawal_address(pool: &DbPool, tg_id: &str, address: &str, label: Option<&str>) -> Result<Option<i64>, sqlx::Error> {
+    let query = sqlx::query("INSERT INTO withdrawal_addresses (user_id, address, label) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING RETURNING id")
+        .bind(tg_id)
+        .bind(address)
+        .bind(label);
+    let row = fetch_returning(query, pool).await?;
+    Ok(row.map(|r| r.get("id")))
+}
+
+/// Conferma un indirizzo PENDING dell'utente: parte il periodo di attesa
+pub async fn confirm_withdrawal_address(pool: &DbPool, tg_id: &str, id: i64) -> Result<bool, sqlx::Error> {
+    let res = sqlx::query("UPDATE withdrawal_addresses SET status = 'CONFIRMED', added_at = $1 WHERE id = $2 AND user_id = $3 AND status = 'PENDING'")
+        .bind(Utc::now().timestamp())
+        .bind(id)
+        .bind(tg_id)
+        .execute(pool)
+        .await?;
+    Ok(res.rows_affected() > 0)
+}
+
+/// Elimina un indirizzo (per id, es. rifiuto della conferma)
+pub async fn delete_withdrawal_address(pool: &DbPool, tg_id: &str, id: i64) -> Result<bool, sqlx::Error> {
+    let res = sqlx::query("DELETE FROM withdrawal_addresses WHERE id = $1 AND user_id = $2")
+        .bind(id)
+        .bind(tg_id)
+        .execute(pool)
+        .await?;
+    Ok(res.rows_affected() > 0)
+}
+
+/// Rimuove un indirizzo dalla whitelist
+pub async fn remove_withdrawal_address(pool: &DbPool, tg_id: &str, address: &str) -> Result<bool, sqlx::Error> {
+    let res = sqlx::query("DELETE FROM withdrawal_addresses WHERE user_id = $1 AND address = $2")
+        .bind(tg_id)
+        .bind(address)
+        .execute(pool)
+        .await?;
+    Ok(res.rows_affected() > 0)
+}
+
+// --- DEPOSITI ---
+
+/// Wallet custoditi di tutti gli utenti (tg_id, pubkey)
+pub async fn get_user_wallets(pool: &DbPool) -> Result<Vec<(String, String)>, sqlx::Error> {
+    let rows = sqlx::query("SELECT tg_id, pubkey FROM users")
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.iter().map(|r| (r.get("tg_id"), r.get("pubkey"))).collect())
+}
+
+/// Registra un deposito. false se già presente (stessa TX e mint vista da più listener)
+pub async fn record_deposit(pool: &DbPool, tg_id: &str, signature: &str, mint: &str, amount: u64, amount_ui: f64, usd_value: Option<f64>) -> Result<bool, sqlx::Error> {
+    let res = sqlx::query("INSERT INTO deposits (user_id, tx_signature, mint, amount, amount_ui, usd_value) VALUES ($1, $2, $3, $4, $5, $6) ON CONFLICT DO NOTHING")
+        .bind(tg_id)
+        .bind(signature)
+        .bind(mint)
+        .bind(amount as i64)
+        .bind(amount_ui)
+        .bind(usd_value)
+        .execute(pool)
+        .await?;
+    Ok(res.rows_affected() > 0)
+}
+
+/// Numero di depositi dell'utente (1 = primo deposito, parte il benvenuto)
+pub async fn count_deposits(pool: &DbPool, tg_id: &str) -> Result<i64, sqlx::Error> {
+    let row = sqlx::query("SELECT COUNT(*) as n FROM deposits WHERE user_id = $1")
+        .bind(tg_id)
+        .fetch_one(pool)
+        .await?;
+    Ok(row.get("n"))
+}
+
+/// Ultimi depositi dell'utente (più recenti prima)
+pub async fn get_deposits(pool: &DbPool, tg_id: &str, limit: i64) -> Result<Vec<Deposit>, sqlx::Error> {
+    let rows = sqlx::query("SELECT * FROM deposits WHERE user_id = $1 ORDER BY id DESC LIMIT $2")
+        .bind(tg_id)
+        .bind(limit)
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.iter().map(|r| Deposit {
+        id: r.get("id"),
+        mint: r.get("mint"),
+        amount: r.get::<i64, _>("amount") as u64,
+        amount_ui: r.get("amount_ui"),
+        usd_value: r.get("usd_value"),
+        tx_signature: r.get("tx_signature"),
+        created_at: r.get("created_at"),
+    }).collect())
+}
+
+/// Totale depositato: (lamports SOL, USD di tutti i depositi con prezzo noto)
+pub async fn get_total_deposited(pool: &DbPool, tg_id: &str, sol_mint: &str) -> Result<(u64, f64), sqlx::Error> {
+    let row = sqlx::query("SELECT CAST(SUM(CASE WHEN mint = $1 THEN amount ELSE 0 END) AS BIGINT) as sol, SUM(usd_value) as usd FROM deposits WHERE user_id = $2")
+        .bind(sol_mint)
+        .bind(tg_id)
+        .fetch_one(pool)
+        .await?;
+    Ok((row.get::<Option<i64>, _>("sol").unwrap_or(0) as u64, row.get::<Option<f64>, _>("usd").unwrap_or(0.0)))
+}
+
+// --- CODA VENDITE FALLITE ---
+
+/// Mette in coda la vendita fallita. Se il token è già in coda (o ha esaurito i tentativi) non cambia nulla.
+pub async fn enqueue_sell_retry(pool: &DbPool, tg_id: &str, token: &str, reason: &str, error: &str, next_attempt_at: i64) -> Result<bool, sqlx::Error> {
+    let res = sqlx::query("INSERT INTO sell_retries (user_id, token, reason, last_error, next_attempt_at) VALUES ($1, $2, $3, $4, $5) ON CONFLICT DO NOTHING")
+        .bind(tg_id)
+        .bind(token)
+        .bind(reason)
+        .bind(error)
+        .bind(next_attempt_at)
+        .execute(pool)
+        .await?;
+    Ok(res.rows_affected() > 0)
+}
+
+/// Coppie (utente, token) in coda o con tentativi esauriti: il Position Manager non le vende più da solo
+pub async fn get_queued_sells(pool: &DbPool) -> Result<Vec<(String, String)>, sqlx::Error> {
+    let rows = sqlx::query("SELECT user_id, token FROM sell_retries")
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.iter().map(|r| (r.get("user_id"), r.get("token"))).collect())
+}
+
+pub async fn get_due_sell_retries(pool: &DbPool, now: i64) -> Result<Vec<SellRetry>, sqlx::Error> {
+    let rows = sqlx::query("SELECT * FROM sell_retries WHERE status = 'PENDING' AND next_attempt_at <= $1")
+        .bind(now)
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.iter().map(|r| SellRetry {
+        id: r.get("id"),
+        user_id: r.get("user_id"),
+        token: r.get("token"),
+        reason: r.get("reason"),
+        attempts: r.get("attempts"),
+        next_attempt_at: r.get("next_attempt_at"),
+        last_error: r.get("last_error"),
+    }).collect())
+}
+
+/// Registra un tentativo fallito: riprogramma (PENDING) o chiude la serie (FAILED)
+pub async fn record_sell_retry_failure(pool: &DbPool, id: i64, error: &str, next_attempt_at: i64, status: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE sell_retries SET attempts = attempts + 1, last_error = $1, next_attempt_at = $2, status = $3 WHERE id = $4")
+        .bind(error)
+        .bind(next_attempt_at)
+        .bind(status)
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Toglie il token dalla coda (venduto o senza più posizioni aperte)
+pub async fn clear_sell_retry(pool: &DbPool, tg_id: &str, token: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM sell_retries WHERE user_id = $1 AND token = $2")
+        .bind(tg_id)
+        .bind(token)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+// --- SNIPER DRY-RUN (Lanci registrati senza acquisto) ---
+
+pub async fn record_sniper_hit(pool: &DbPool, hit: &SniperHit) -> Result<i64, sqlx::Error> {
+    let query = sqlx::query(
+        "INSERT INTO sniper_hits (token, symbol, deployer, detected_at, is_safe, reason, safety_report, price, liquidity_usd, market_cap, volume_24h)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11) RETURNING id")
+        .bind(&hit.token)
+        .bind(&hit.symbol)
+        .bind(&hit.deployer)
+        .bind(hit.detected_at)
+        .bind(hit.is_safe as i64)
+        .bind(&hit.reason)
+        .bind(hit.safety_report.to_string())
+        .bind(hit.price)
+        .bind(hit.liquidity_usd)
+        .bind(hit.market_cap)
+        .bind(hit.volume_24h);
+    insert_returning_id(query, pool).await
+}
+
+/// Lanci senza il prezzo della colonna `column` (price_5m, price_30m, price_2h) rilevati prima di `before` (unix): (id, token)
+pub async fn get_sniper_hits_due(pool: &DbPool, column: &str, before: i64) -> Result<Vec<(i64, String)>, sqlx::Error> {
+    let rows = sqlx::query(&format!("SELECT id, token FROM sniper_hits WHERE {} IS NULL AND detected_at <= $1 ORDER BY detected_at LIMIT 100", column))
+        .bind(before)
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.iter().map(|r| (r.get("id"), r.get("token"))).collect())
+}
+
+pub async fn set_sniper_hit_price(pool: &DbPool, id: i64, column: &str, price: f64) -> Result<(), sqlx::Error> {
+    sqlx::query(&format!("UPDATE sniper_hits SET {} = $1 WHERE id = $2", column))
+        .bind(price)
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Lanci registrati da `since` (unix), i più recenti prima
+pub async fn get_sniper_hits(pool: &DbPool, since: i64) -> Result<Vec<SniperHit>, sqlx::Error> {
+    let rows = sqlx::query("SELECT * FROM sniper_hits WHERE detected_at >= $1 ORDER BY detected_at DESC")
+        .bind(since)
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.iter().map(|r| SniperHit {
+        id: r.get("id"),
+        token: r.get("token"),
+        symbol: r.get("symbol"),
+        deployer: r.get("deployer"),
+        detected_at: r.get("detected_at"),
+        is_safe: r.get::<i64, _>("is_safe") != 0,
+        reason: r.get("reason"),
+        safety_report: serde_json::from_str(&r.get::<String, _>("safety_report")).unwrap_or(serde_json::Value::Null),
+        price: r.get("price"),
+        liquidity_usd: r.get("liquidity_usd"),
+        market_cap: r.get("market_cap"),
+        volume_24h: r.get("volume_24h"),
+        price_5m: r.get("price_5m"),
+        price_30m: r.get("price_30m"),
+        price_2h: r.get("price_2h"),
+    }).collect())
+}
+
+// --- OFF-RAMP SEPA (Bonifici in EURC tramite provider) ---
+
+fn offramp_from_row(r: &AnyRow) -> OfframpPayout {
+    OfframpPayout {
+        id: r.get("id"),
+        user_id: r.get("user_id"),
+        iban: r.get("iban"),
+        beneficiary: r.get("beneficiary"),
+        amount_eurc: r.get::<i64, _>("amount_eurc") as u64,
+        amount_lamports: r.get::<i64, _>("amount_lamports") as u64,
+        provider: r.get("provider"),
+        status: r.get("status"),
+        swap_signature: r.get("swap_signature"),
+        pre_swap_eurc: r.get::<Option<i64>, _>("pre_swap_eurc").map(|b| b as u64),
+        transfer_signature: r.get("transfer_signature"),
+        provider_ref: r.get("provider_ref"),
+        error: r.get("error"),
+        created_at: r.get("created_at"),
+        updated_at: r.get("updated_at"),
+    }
+}
+
+/// Registra la richiesta in attesa dell'approvazione Telegram
+pub async fn record_offramp(pool: &DbPool, tg_id: &str, iban: &str, beneficiary: &str, amount_eurc: u64, provider: &str) -> Result<i64, sqlx::Error> {
+    let now = Utc::now().timestamp();
+    let query = sqlx::query(
+        "INSERT INTO offramp_payouts (user_id, iban, beneficiary, amount_eurc, provider, status, created_at, updated_at)
+         VALUES ($1, $2, $3, $4, $5, 'AWAITING_APPROVAL', $6, $7) RETURNING id")
+        .bind(tg_id)
+        .bind(iban)
+        .bind(beneficiary)
+        .bind(amount_eurc as i64)
+        .bind(provider)
+        .bind(now)
+        .bind(now);
+    insert_returning_id(query, pool).await
+}
+
+pub async fn get_offramp(pool: &DbPool, id: i64) -> Result<Option<OfframpPayout>, sqlx::Error> {
+    let row = sqlx::query("SELECT * FROM offramp_payouts WHERE id = $1")
+        .bind(id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(row.as_ref().map(offramp_from_row))
+}
+
+/// Richieste dell'utente, le più recenti prima
+pub async fn get_user_offramps(pool: &DbPool, tg_id: &str) -> Result<Vec<OfframpPayout>, sqlx::Error> {
+    let rows = sqlx::query("SELECT * FROM offramp_payouts WHERE user_id = $1 ORDER BY created_at DESC LIMIT 50")
+        .bind(tg_id)
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.iter().map(offramp_from_row).collect())
+}
+
+pub async fn get_offramps_by_status(pool: &DbPool, status: &str) -> Result<Vec<OfframpPayout>, sqlx::Error> {
+    let rows = sqlx::query("SELECT * FROM offramp_payouts WHERE status = $1 ORDER BY created_at")
+        .bind(status)
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.iter().map(offramp_from_row).collect())
+}
+
+/// Cambio di stato solo se la richiesta è ancora in uno degli stati `from` (niente doppia esecuzione tra worker e callback).
+/// `owner` limita la modifica alle richieste dell'utente. true se aggiornata.
+pub async fn advance_offramp(pool: &DbPool, id: i64, owner: Option<&str>, from: &[&str], to: &str, update: OfframpUpdate<'_>) -> Result<bool, sqlx::Error> {
+    let states = from.iter().enumerate().map(|(i, _)| format!("${}", i + 11)).collect::<Vec<_>>().join(", ");
+    let sql = format!(
+        "UPDATE offramp_payouts SET status = $1, updated_at = $2,
+            amount_lamports = COALESCE($3, amount_lamports), swap_signature = COALESCE($4, swap_signature),
+            transfer_signature = COALESCE($5, transfer_signature), provider_ref = COALESCE($6, provider_ref), error = COALESCE($7, error),
+            pre_swap_eurc = COALESCE($8, pre_swap_eurc)
+         WHERE id = $9 AND ($10 = '' OR user_id = $10) AND status IN ({})", states);
+    let mut query = sqlx::query(&sql)
+        .bind(to)
+        .bind(Utc::now().timestamp())
+        .bind(update.amount_lamports.map(|l| l as i64))
+        .bind(update.swap_signature)
+        .bind(update.transfer_signature)
+        .bind(update.provider_ref)
+        .bind(update.error)
+        .bind(update.pre_swap_eurc.map(|b| b as i64))
+        .bind(id)
+        .bind(owner.unwrap_or(""));
+    for state in from {
+        query = query.bind(*state);
+    }
+    Ok(query.execute(pool).await?.rows_affected() > 0)
+}
+
+/// Annulla le approvazioni scadute. Ritorna (id, utente) per avvisarli.
+pub async fn expire_offramp_approvals(pool: &DbPool, ttl_minutes: i64) -> Result<Vec<(i64, String)>, sqlx::Error> {
+    let rows = sqlx::query("UPDATE offramp_payouts SET status = 'CANCELLED', updated_at = $1 WHERE status = 'AWAITING_APPROVAL' AND created_at < $2 RETURNING id, user_id")
+        .bind(Utc::now().timestamp())
+        .bind(Utc::now().timestamp() - ttl_minutes * 60)
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.iter().map(|r| (r.get("id"), r.get("user_id"))).collect())
+}
+
+// --- LANCI (Reputazione deployer) ---
+
+pub async fn record_launch(pool: &DbPool, token: &str, deployer: &str, pool_sol: f64) -> Result<(), sqlx::Error> {
+    sqlx::query("INSERT INTO launches (token, deployer, pool_sol) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING")
+        .bind(token)
+        .bind(deployer)
+        .bind(pool_sol)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Segna il token come rug (liquidità ritirata): pesa sulla reputazione del suo deployer
+pub async fn mark_token_rugged(pool: &DbPool, token: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE launches SET rugged = 1 WHERE token = $1")
+        .bind(token)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+pub async fn count_deployer_rugs(pool: &DbPool, deployer: &str) -> Result<i64, sqlx::Error> {
+    let row = sqlx::query("SELECT COUNT(*) as n FROM launches WHERE deployer = $1 AND rugged = 1")
+        .bind(deployer)
+        .fetch_one(pool)
+        .await?;
+    Ok(row.get("n"))
+}
+
+// --- ADMIN (Statistiche e gestione utenti) ---
+
+// Volume per trade in lamports: acquisti non falliti + incassi delle vendite
+const TRADE_VOLUME_SQL: &str = "(CASE WHEN status != 'FAILED' THEN amount_in_lamports ELSE 0 END) + COALESCE(sol_received_lamports, 0)";
+
+pub async fn get_admin_totals(pool: &DbPool) -> Result<AdminTotals, sqlx::Error> {
+    let users = sqlx::query(
+        "SELECT COUNT(*) AS n,
+                CAST(COALESCE(SUM(CASE WHEN is_active = 1 AND COALESCE(banned, 0) = 0 THEN 1 ELSE 0 END), 0) AS BIGINT) AS active,
+                CAST(COALESCE(SUM(CASE WHEN COALESCE(banned, 0) = 1 THEN 1 ELSE 0 END), 0) AS BIGINT) AS banned
+         FROM users"
+    )
+        .fetch_one(pool)
+        .await?;
+    let trades = sqlx::query(&format!(
+        "SELECT COUNT(*) AS n, CAST(COALESCE(SUM(CASE WHEN status = 'OPEN' THEN 1 ELSE 0 END), 0) AS BIGINT) AS open,
+                CAST(COALESCE(SUM({}), 0) AS BIGINT) AS volume
+         FROM trades", TRADE_VOLUME_SQL
+    ))
+        .fetch_one(pool)
+        .await?;
+    Ok(AdminTotals {
+        users: users.get("n"),
+        active_users: users.get("active"),
+        banned_users: users.get("banned"),
+        trades: trades.get("n"),
+        open_trades: trades.get("open"),
+        volume_sol: trades.get::<i64, _>("volume") as f64 / 1_000_000_000.0,
+    })
+}
+
+/// Tutti gli utenti con conteggio trade e volume (dal più recente)
+pub async fn get_admin_users(pool: &DbPool) -> Result<Vec<AdminUser>, sqlx::Error> {
+    let rows = sqlx::query(&format!(
+        "SELECT u.tg_id, u.pubkey, u.is_active, COALESCE(u.banned, 0) AS banned, u.created_at,
+                COUNT(t.id) AS trades, CAST(COALESCE(SUM(CASE WHEN t.status = 'OPEN' THEN 1 ELSE 0 END), 0) AS BIGINT) AS open_trades,
+                CAST(COALESCE(SUM({}), 0) AS BIGINT) AS volume
+         FROM users u LEFT JOIN trades t ON t.user_id = u.tg_id
+         GROUP BY u.tg_id ORDER BY u.created_at DESC", TRADE_VOLUME_SQL
+    ))
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.iter().map(|r| AdminUser {
+        tg_id: r.get("tg_id"),
+        wallet_address: r.get("pubkey"),
+        is_active: r.get::<i64, _>("is_active") == 1,
+        banned: r.get::<i64, _>("banned") == 1,
+        created_at: r.get("created_at"),
+        trades: r.get("trades"),
+        open_trades: r.get("open_trades"),
+        volume_sol: r.get::<i64, _>("volume") as f64 / 1_000_000_000.0,
+    }).collect())
+}
+
+/// Ban/Sblocco. Il ban ferma anche l'Auto-Bot e chiude le sessioni Web. false = utente inesistente.
+pub async fn set_user_banned(pool: &DbPool, tg_id: &str, banned: bool) -> Result<bool, sqlx::Error> {
+    let res = sqlx::query("UPDATE users SET banned = $1, is_active = CASE WHEN $2 = 1 THEN 0 ELSE is_active END WHERE tg_id = $3")
+        .bind(banned as i64)
+        .bind(banned as i64)
+        .bind(tg_id)
+        .execute(pool)
+        .await?;
+    if banned {
+        sqlx::query("DELETE FROM sessions WHERE user_id = $1")
+            .bind(tg_id)
+            .execute(pool)
+            .await?;
+        clear_risk_deactivation(pool, tg_id).await?;
+    }
+    Ok(res.rows_affected() > 0)
+}
+
+pub async fn is_user_banned(pool: &DbPool, tg_id: &str) -> Result<bool, sqlx::Error> {
+    let row = sqlx::query("SELECT COALESCE(banned, 0) AS banned FROM users WHERE tg_id = $1")
+        .bind(tg_id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(row.map(|r| r.get::<i64, _>("banned") == 1).unwrap_or(false))
+}
+
+// --- GRID TRADING ---
+
+fn row_to_grid(r: &AnyRow) -> GridBot {
+    GridBot {
+        id: r.get("id"),
+        user_id: r.get("user_id"),
+        base_mint: r.get("base_mint"),
+        quote_mint: r.get("quote_mint"),
+        base_decimals: r.get::<i64, _>("base_decimals") as u8,
+        quote_decimals: r.get::<i64, _>("quote_decimals") as u8,
+        lower_price: r.get("lower_price"),
+        upper_price: r.get("upper_price"),
+        step_pct: r.get("step_pct"),
+        order_size: r.get::<i64, _>("order_size") as u64,
+        status: r.get("status"),
+        round_trips: r.get("round_trips"),
+        realized_profit: r.get("realized_profit"),
+    }
+}
+
+/// Crea la griglia con i suoi livelli (buy, sell, stato). Una sola griglia attiva per utente: la precedente viene fermata.
+pub async fn create_grid(pool: &DbPool, grid: &GridBot, levels: &[(f64, f64, &str)]) -> Result<i64, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("UPDATE grid_bots SET status = 'STOPPED' WHERE user_id = $1 AND status = 'ACTIVE'")
+        .bind(&grid.user_id)
+        .execute(&mut *tx)
+        .await?;
+    let query = sqlx::query("INSERT INTO grid_bots (user_id, base_mint, quote_mint, base_decimals, quote_decimals, lower_price, upper_price, step_pct, order_size) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9) RETURNING id")
+        .bind(&grid.user_id)
+        .bind(&grid.base_mint)
+        .bind(&grid.quote_mint)
+        .bind(grid.base_decimals as i64)
+        .bind(grid.quote_decimals as i64)
+        .bind(grid.lower_price)
+        .bind(grid.upper_price)
+        .bind(grid.step_pct)
+        .bind(grid.order_size as i64);
+    let id = insert_returning_id(query, &mut *tx).await?;
+    for (buy_price, sell_price, status) in levels {
+        sqlx::query("INSERT INTO grid_levels (grid_id, buy_price, sell_price, status) VALUES ($1, $2, $3, $4)")
+            .bind(id)
+            .bind(buy_price)
+            .bind(sell_price)
+            .bind(status)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+    Ok(id)
+}
+
+/// Griglie attive (per il Grid Trader)
+pub async fn get_active_grids(pool: &DbPool) -> Result<Vec<GridBot>, sqlx::Error> {
+    let rows = sqlx::query("SELECT * FROM grid_bots WHERE status = 'ACTIVE'")
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.iter().map(row_to_grid).collect())
+}
+
+/// Ultima griglia dell'utente (attiva o fermata)
+pub async fn get_user_grid(pool: &DbPool, tg_id: &str) -> Result<Option<GridBot>, sqlx::Error> {
+    let row = sqlx::query("SELECT * FROM grid_bots WHERE user_id = $1 ORDER BY id DESC LIMIT 1")
+        .bind(tg_id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(row.as_ref().map(row_to_grid))
+}
+
+/// Livelli della griglia, dal prezzo più basso
+pub async fn get_grid_levels(pool: &DbPool, grid_id: i64) -> Result<Vec<GridLevel>, sqlx::Error> {
+    let rows = sqlx::query("SELECT * FROM grid_levels WHERE grid_id = $1 ORDER BY buy_price ASC")
+        .bind(grid_id)
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.iter().map(|r| GridLevel {
+        id: r.get("id"),
+        grid_id: r.get("grid_id"),
+        buy_price: r.get("buy_price"),
+        sell_price: r.get("sell_price"),
+        status: r.get("status"),
+        base_amount: r.get::<i64, _>("base_amount") as u64,
+        last_tx: r.get("last_tx"),
+    }).collect())
+}
+
+/// Nuovo stato del livello (dopo un fill: quantità base detenuta e firma)
+pub async fn update_grid_level(pool: &DbPool, level_id: i64, status: &str, base_amount: u64, signature: Option<&str>) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE grid_levels SET status = $1, base_amount = $2, last_tx = COALESCE($3, last_tx), updated_at = $4 WHERE id = $5")
+        .bind(status)
+        .bind(base_amount as i64)
+        .bind(signature)
+        .bind(sql_timestamp(Utc::now()))
+        .bind(level_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Giro completato (compra + rivendi): il livello torna in attesa e il profitto va sulla griglia
+pub async fn finish_grid_round(pool: &DbPool, grid_id: i64, level_id: i64, signature: &str, profit: i64) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("UPDATE grid_levels SET status = 'WAITING_BUY', base_amount = 0, last_tx = $1, updated_at = $2 WHERE id = $3")
+        .bind(signature)
+        .bind(sql_timestamp(Utc::now()))
+        .bind(level_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("UPDATE grid_bots SET round_trips = round_trips + 1, realized_profit = realized_profit + $1 WHERE id = $2")
+        .bind(profit)
+        .bind(grid_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await
+}
+
+/// Ferma le griglie attive dell'utente. Ritorna quante ne ha fermate.
+pub async fn stop_user_grids(pool: &DbPool, tg_id: &str) -> Result<u64, sqlx::Error> {
+    let res = sqlx::query("UPDATE grid_bots SET status = 'STOPPED' WHERE user_id = $1 AND status = 'ACTIVE'")
+        .bind(tg_id)
+        .execute(pool)
+        .await?;
+    Ok(res.rows_affected())
+}
+
+// --- RISK (Circuit Breaker Perdita Giornaliera) ---
+
+/// Giornata di rischio dell'utente: (saldo iniziale, scattato). Il saldo viene fissato solo alla prima chiamata del giorno.
+pub async fn ensure_risk_day(pool: &DbPool, tg_id: &str, day: &str, start_balance: u64) -> Result<(u64, bool), sqlx::Error> {
+    sqlx::query("INSERT INTO risk_days (user_id, day, start_balance_lamports) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING")
+        .bind(tg_id)
+        .bind(day)
+        .bind(start_balance as i64)
+        .execute(pool)
+        .await?;
+    let row = sqlx::query("SELECT start_balance_lamports, tripped_at FROM risk_days WHERE user_id = $1 AND day = $2")
+        .bind(tg_id)
+        .bind(day)
+        .fetch_one(pool)
+        .await?;
+    Ok((row.get::<i64, _>("start_balance_lamports") as u64, row.get::<Option<String>, _>("tripped_at").is_some()))
+}
+
+/// true se il Circuit Breaker dell'utente è scattato nel giorno indicato
+pub async fn is_risk_tripped(pool: &DbPool, tg_id: &str, day: &str) -> Result<bool, sqlx::Error> {
+    let row = sqlx::query("SELECT 1 FROM risk_days WHERE user_id = $1 AND day = $2 AND tripped_at IS NOT NULL")
+        .bind(tg_id)
+        .bind(day)
+        .fetch_optional(pool)
+        .await?;
+    Ok(row.is_some())
+}
+
+/// Fa scattare il blocco e spegne l'auto-trading. false se era già scattato.
+/// `deactivated` ricorda se l'Auto-Bot era in funzione: solo in quel caso il reset di mezzanotte lo riaccende.
+pub async fn trip_risk_day(pool: &DbPool, tg_id: &str, day: &str) -> Result<bool, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let res = sqlx::query("UPDATE risk_days SET tripped_at = $1 WHERE user_id = $2 AND day = $3 AND tripped_at IS NULL")
+        .bind(Utc::now().to_rfc3339())
+        .bind(tg_id)
+        .bind(day)
+        .execute(&mut *tx)
+        .await?;
+    if res.rows_affected() == 0 { return Ok(false); }
+    let stopped = sqlx::query("UPDATE users SET is_active = 0 WHERE tg_id = $1 AND is_active = 1")
+        .bind(tg_id)
+        .execute(&mut *tx)
+        .await?;
+    if stopped.rows_affected() > 0 {
+        sqlx::query("UPDATE risk_days SET deactivated = 1 WHERE user_id = $1 AND day = $2")
+            .bind(tg_id)
+            .bind(day)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+    Ok(true)
+}
+
+// Stop o ban successivi allo scatto: l'Auto-Bot resta spento anche dopo il reset di mezzanotte
+async fn clear_risk_deactivation(pool: &DbPool, tg_id: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE risk_days SET deactivated = 0 WHERE user_id = $1 AND deactivated = 1")
+        .bind(tg_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Riattiva (una sola volta) gli utenti spenti dal Circuit Breaker nei giorni precedenti, mai i bannati.
+/// Ritorna chi è stato riattivato.
+pub async fn reset_tripped_before(pool: &DbPool, day: &str) -> Result<Vec<String>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let rows = sqlx::query("SELECT DISTINCT user_id FROM risk_days WHERE day < $1 AND tripped_at IS NOT NULL AND reset_done = 0 AND deactivated = 1")
+        .bind(day)
+        .fetch_all(&mut *tx)
+        .await?;
+    let mut users = Vec::new();
+    for r in &rows {
+        let user: String = r.get("user_id");
+        let res = sqlx::query("UPDATE users SET is_active = 1 WHERE tg_id = $1 AND COALESCE(banned, 0) = 0")
+            .bind(&user)
+            .execute(&mut *tx)
+            .await?;
+        if res.rows_affected() > 0 { users.push(user); }
+    }
+    sqlx::query("UPDATE risk_days SET reset_done = 1 WHERE day < $1 AND tripped_at IS NOT NULL")
+        .bind(day)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(users)
+}
+
+// --- EXECUTIONS (Audit Log degli Swap) ---
+
+/// Registra un tentativo di swap: SENT con firma, FAILED con errore. Ritorna l'ID.
+pub async fn record_execution(pool: &DbPool, attempt: &ExecutionAttempt<'_>, signature: Option<&str>, error: Option<&str>) -> Result<i64, sqlx::Error> {
+    let query = sqlx::query("INSERT INTO executions (user_id, side, input_mint, output_mint, amount_in, route, quoted_out, slippage_bps, status, tx_signature, error) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11) RETURNING id")
+        .bind(attempt.user_id)
+        .bind(attempt.side)
+        .bind(attempt.input_mint)
+        .bind(attempt.output_mint)
+        .bind(attempt.amount_in as i64)
+        .bind(attempt.route)
+        .bind(attempt.quoted_out as i64)
+        .bind(attempt.slippage_bps as i64)
+        .bind(if signature.is_some() { "SENT" } else { "FAILED" })
+        .bind(signature)
+        .bind(error);
+    insert_returning_id(query, pool).await
+}
+
+/// Esito on-chain dello swap (quantità reale ricevuta e fee pagate)
+pub async fn finish_execution(pool: &DbPool, id: i64, status: &str, actual_out: Option<u64>, fee: Option<u64>, priority_fee: Option<u64>, error: Option<&str>) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE executions SET status = $1, actual_out = $2, fee_lamports = $3, priority_fee_lamports = $4, error = COALESCE($5, error) WHERE id = $6")
+        .bind(status)
+        .bind(actual_out.map(|v| v as i64))
+        .bind(fee.map(|v| v as i64))
+        .bind(priority_fee.map(|v| v as i64))
+        .bind(error)
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Fill reale di un acquisto confermato: SOL spesi (senza fee e rent) e decimali del token ricevuto
+pub async fn record_buy_fill(pool: &DbPool, id: i64, spent_lamports: u64, out_decimals: u8) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE executions SET spent_lamports = $1, out_decimals = $2 WHERE id = $3")
+        .bind(spent_lamports as i64)
+        .bind(out_decimals as i64)
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Ultime esecuzioni dell'utente (più recenti prima)
+/// Slippage realizzato di uno swap confermato (`exceeded` = oltre la tolleranza impostata)
+pub async fn record_execution_slippage(pool: &DbPool, id: i64, realized_bps: i64, exceeded: bool) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE executions SET realized_slippage_bps = $1, slippage_exceeded = $2 WHERE id = $3")
+        .bind(realized_bps)
+        .bind(exceeded as i64)
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+pub async fn get_executions(pool: &DbPool, tg_id: &str, limit: i64) -> Result<Vec<Execution>, sqlx::Error> {
+    let rows = sqlx::query("SELECT * FROM executions WHERE user_id = $1 ORDER BY id DESC LIMIT $2")
+        .bind(tg_id)
+        .bind(limit)
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.iter().map(|r| Execution {
+        id: r.get("id"),
+        side: r.get("side"),
+        input_mint: r.get("input_mint"),
+        output_mint: r.get("output_mint"),
+        amount_in: r.get::<i64, _>("amount_in") as u64,
+        route: r.get("route"),
+        quoted_out: r.get::<i64, _>("quoted_out") as u64,
+        actual_out: r.get::<Option<i64>, _>("actual_out").map(|v| v as u64),
+        fee_lamports: r.get::<Option<i64>, _>("fee_lamports").map(|v| v as u64),
+        priority_fee_lamports: r.get::<Option<i64>, _>("priority_fee_lamports").map(|v| v as u64),
+        slippage_bps: r.get::<i64, _>("slippage_bps") as u16,
+        realized_slippage_bps: r.get("realized_slippage_bps"),
+        slippage_exceeded: r.get::<i64, _>("slippage_exceeded") != 0,
+        status: r.get("status"),
+        tx_signature: r.get("tx_signature"),
+        error: r.get("error"),
+        created_at: r.get("created_at"),
+    }).collect())
+}
+
+/// Storico movimenti per l'export (acquisti e vendite) in ordine cronologico, filtrato per data (YYYY-MM-DD, estremi inclusi)
+/// e per tag (già normalizzato). La fee di una vendita che chiude più trade è divisa in parti uguali tra loro.
+pub async fn get_trade_events(pool: &DbPool, tg_id: &str, from: Option<&str>, to: Option<&str>, tag: Option<&str>) -> Result<Vec<TradeEvent>, sqlx::Error> {
+    // Estremo superiore escluso: il giorno dopo `to`
+    let until = to.and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+        .map(|d| (d + Duration::days(1)).format("%Y-%m-%d").to_string());
+    let rows = sqlx::query(&format!(
+        "SELECT * FROM (
+            SELECT {} as event_time, 'BUY' as side, t.id, t.token_address, m.symbol, CAST(t.amount_in_lamports AS DOUBLE PRECISION) / 1e9 as amount_sol,
+                   t.entry_sol_usd as sol_usd, CAST(COALESCE(e.fee_lamports, 0) AS DOUBLE PRECISION) / 1e9 as fee_sol, CAST(0 AS DOUBLE PRECISION) as pnl_sol,
+                   NULL as cost_basis_usd, t.tx_signature, t.note, t.tags
+            FROM trades t LEFT JOIN executions e ON e.tx_signature = t.tx_signature LEFT JOIN token_metadata m ON m.mint = t.token_address
+            WHERE t.user_id = $1 AND t.status != 'FAILED'
+            UNION ALL
+            SELECT {}, 'SELL', t.id, t.token_address, m.symbol,
+                   COALESCE(CAST(t.sol_received_lamports AS DOUBLE PRECISION) / 1e9, CAST(t.amount_in_lamports AS DOUBLE PRECISION) / 1e9 + t.profit_loss_sol),
+                   t.exit_sol_usd, CAST(COALESCE(e.fee_lamports, 0) AS DOUBLE PRECISION) / 1e9 / (SELECT COUNT(*) FROM trades x WHERE x.exit_signature = t.exit_signature),
+                   t.profit_loss_sol, COALESCE(t.usd_value_at_entry, CAST(t.amount_in_lamports AS DOUBLE PRECISION) / 1e9 * t.entry_sol_usd), t.exit_signature,
+                   t.note, t.tags
+            FROM trades t LEFT JOIN executions e ON e.tx_signature = t.exit_signature LEFT JOIN token_metadata m ON m.mint = t.token_address
+            WHERE t.user_id = $1 AND t.status = 'SOLD'
+        ) events
+        WHERE ($2 IS NULL OR event_time >= $2) AND ($3 IS NULL OR event_time < $3)
+          AND ($4 IS NULL OR ',' || COALESCE(tags, '') || ',' LIKE '%,' || $4 || ',%')
+        ORDER BY event_time, id",
+        sql_time("t.entry_time"), sql_time("t.exit_time")
+    ))
+        .bind(tg_id)
+        .bind(from)
+        .bind(until)
+        .bind(tag)
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.iter().map(|r| TradeEvent {
+        time: r.get::<Option<String>, _>("event_time").unwrap_or_default(),
+        side: r.get("side"),
+        trade_id: r.get("id"),
+        token: r.get("token_address"),
+        symbol: r.get("symbol"),
+        amount_sol: r.get("amount_sol"),
+        sol_usd: r.get("sol_usd"),
+        fee_sol: r.get::<Option<f64>, _>("fee_sol").unwrap_or(0.0),
+        pnl_sol: r.get("pnl_sol"),
+        cost_basis_usd: r.get("cost_basis_usd"),
+        tx_signature: r.get("tx_signature"),
+        note: r.get("note"),
+        tags: crate::trade_notes::split_tags(r.get::<Option<String>, _>("tags").as_deref()),
+    }).collect())
+}
+
+// --- METADATI TOKEN (Cache di Metaplex / DexScreener) ---
+
+/// Metadati in cache aggiornati dopo `since` (None se mancanti o vecchi)
+pub async fn get_token_metadata(pool: &DbPool, mint: &str, since: DateTime<Utc>) -> Result<Option<TokenMetadata>, sqlx::Error> {
+    let row = sqlx::query("SELECT mint, name, symbol, logo_uri, source FROM token_metadata WHERE mint = $1 AND updated_at >= $2")
+        .bind(mint)
+        .bind(sql_timestamp(since))
+        .fetch_optional(pool)
+        .await?;
+    Ok(row.map(|r| TokenMetadata {
+        mint: r.get("mint"),
+        name: r.get("name"),
+        symbol: r.get("symbol"),
+        logo_uri: r.get("logo_uri"),
+        source: r.get("source"),
+    }))
+}
+
+/// Salva (o rinfresca) i metadati di un token
+pub async fn save_token_metadata(pool: &DbPool, meta: &TokenMetadata) -> Result<(), sqlx::Error> {
+    sqlx::query("INSERT INTO token_metadata (mint, name, symbol, logo_uri, source, updated_at) VALUES ($1, $2, $3, $4, $5, $6)
+         ON CONFLICT(mint) DO UPDATE SET name = excluded.name, symbol = excluded.symbol, logo_uri = excluded.logo_uri, source = excluded.source, updated_at = excluded.updated_at")
+        .bind(&meta.mint)
+        .bind(&meta.name)
+        .bind(&meta.symbol)
+        .bind(&meta.logo_uri)
+        .bind(&meta.source)
+        .bind(sql_timestamp(Utc::now()))
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+// --- WEBHOOKS (Notifiche fuori da Telegram) ---
+
+/// Aggiunge un webhook (false se l'URL era già registrato)
+pub async fn add_webhook(pool: &DbPool, tg_id: &str, url: &str, kind: &str) -> Result<bool, sqlx::Error> {
+    let res = sqlx::query("INSERT INTO webhooks (user_id, url, kind) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING")
+        .bind(tg_id)
+        .bind(url)
+        .bind(kind)
+        .execute(pool)
+        .await?;
+    Ok(res.rows_affected() > 0)
+}
+
+/// Rimuove un webhook dell'utente. false se inesistente o di altri.
+pub async fn remove_webhook(pool: &DbPool, tg_id: &str, webhook_id: i64) -> Result<bool, sqlx::Error> {
+    let res = sqlx::query("DELETE FROM webhooks WHERE id = $1 AND user_id = $2")
+        .bind(webhook_id)
+        .bind(tg_id)
+        .execute(pool)
+        .await?;
+    Ok(res.rows_affected() > 0)
+}
+
+/// Webhook registrati dall'utente
+pub async fn get_webhooks(pool: &DbPool, tg_id: &str) -> Result<Vec<Webhook>, sqlx::Error> {
+    let rows = sqlx::query("SELECT id, url, kind FROM webhooks WHERE user_id = $1 ORDER BY id")
+        .bind(tg_id)
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.iter().map(|r| Webhook { id: r.get("id"), url: r.get("url"), kind: r.get("kind") }).collect())
+}
+
+// --- REFERRAL ---
+
+/// Codice invito dell'utente: assegna `candidate` solo se non ne ha ancora uno
+pub async fn ensure_referral_code(pool: &DbPool, tg_id: &str, candidate: &str) -> Result<Option<String>, sqlx::Error> {
+    sqlx::query("UPDATE users SET referral_code = $1 WHERE tg_id = $2 AND referral_code IS NULL")
+        .bind(candidate)
+        .bind(tg_id)
+        .execute(pool)
+        .await?;
+    let row = sqlx::query("SELECT referral_code FROM users WHERE tg_id = $1")
+        .bind(tg_id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(row.and_then(|r| r.get("referral_code")))
+}
+
+/// Proprietario di un codice invito
+pub async fn find_user_by_referral_code(pool: &DbPool, code: &str) -> Result<Option<String>, sqlx::Error> {
+    let row = sqlx::query("SELECT tg_id FROM users WHERE referral_code = $1")
+        .bind(code)
+        .fetch_optional(pool)
+        .await?;
+    Ok(row.map(|r| r.get("tg_id")))
+}
+
+/// Chi ha invitato l'utente (None se nessuno)
+pub async fn get_referrer(pool: &DbPool, tg_id: &str) -> Result<Option<String>, sqlx::Error> {
+    let row = sqlx::query("SELECT referred_by FROM users WHERE tg_id = $1")
+        .bind(tg_id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(row.and_then(|r| r.get("referred_by")))
+}
+
+/// Collega l'invitato al referrer (una sola volta). false se era già collegato.
+pub async fn set_referrer(pool: &DbPool, tg_id: &str, referrer_id: &str) -> Result<bool, sqlx::Error> {
+    let res = sqlx::query("UPDATE users SET referred_by = $1 WHERE tg_id = $2 AND referred_by IS NULL")
+        .bind(referrer_id)
+        .bind(tg_id)
+        .execute(pool)
+        .await?;
+    Ok(res.rows_affected() > 0)
+}
+
+/// Quota maturata dal referrer su uno swap dell'invitato
+pub async fn record_referral_reward(pool: &DbPool, referrer_id: &str, invitee_id: &str, volume: u64, reward: u64, signature: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("INSERT INTO referral_rewards (referrer_id, invitee_id, volume_lamports, reward_lamports, tx_signature) VALUES ($1, $2, $3, $4, $5)")
+        .bind(referrer_id)
+        .bind(invitee_id)
+        .bind(volume as i64)
+        .bind(reward as i64)
+        .bind(signature)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Statistiche del referrer: (invitati, volume invitati, quote maturate, già pagate o in pagamento)
+pub async fn get_referral_stats(pool: &DbPool, tg_id: &str) -> Result<(i64, u64, u64, u64), sqlx::Error> {
+    let invitees = sqlx::query("SELECT COUNT(*) as n FROM users WHERE referred_by = $1")
+        .bind(tg_id)
+        .fetch_one(pool)
+        .await?;
+    let rewards = sqlx::query("SELECT CAST(SUM(volume_lamports) AS BIGINT) as volume, CAST(SUM(reward_lamports) AS BIGINT) as reward FROM referral_rewards WHERE referrer_id = $1")
+        .bind(tg_id)
+        .fetch_one(pool)
+        .await?;
+    let paid = sqlx::query("SELECT CAST(SUM(amount_lamports) AS BIGINT) as paid FROM referral_payouts WHERE user_id = $1 AND status != 'FAILED'")
+        .bind(tg_id)
+        .fetch_one(pool)
+        .await?;
+    Ok((
+        invitees.get("n"),
+        rewards.get::<Option<i64>, _>("volume").unwrap_or(0) as u64,
+        rewards.get::<Option<i64>, _>("reward").unwrap_or(0) as u64,
+        paid.get::<Option<i64>, _>("paid").unwrap_or(0) as u64,
+    ))
+}
+
+/// Prenota il pagamento di tutto il maturato (PENDING) se supera `min_lamports`. Ritorna (ID, importo).
+pub async fn reserve_referral_payout(pool: &DbPool, tg_id: &str, min_lamports: u64) -> Result<Option<(i64, u64)>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    // Lock sulla riga dell'utente (UPDATE a vuoto, valido su entrambi i DB): due richieste concorrenti si mettono in fila
+    // e la seconda legge il maturato già al netto della prima (su Postgres READ COMMITTED lo vedrebbero uguale entrambe)
+    let locked = sqlx::query("UPDATE users SET tg_id = tg_id WHERE tg_id = $1")
+        .bind(tg_id)
+        .execute(&mut *tx)
+        .await?;
+    if locked.rows_affected() == 0 { return Ok(None); }
+    let row = sqlx::query(
+        "SELECT CAST((SELECT COALESCE(SUM(reward_lamports), 0) FROM referral_rewards WHERE referrer_id = $1)
+              - (SELECT COALESCE(SUM(amount_lamports), 0) FROM referral_payouts WHERE user_id = $2 AND status != 'FAILED') AS BIGINT) as available")
+        .bind(tg_id)
+        .bind(tg_id)
+        .fetch_one(&mut *tx)
+        .await?;
+    let available = row.get::<i64, _>("available").max(0) as u64;
+    if available < min_lamports { return Ok(None); }
+
+    let query = sqlx::query("INSERT INTO referral_payouts (user_id, amount_lamports) VALUES ($1, $2) RETURNING id")
+        .bind(tg_id)
+        .bind(available as i64);
+    let id = insert_returning_id(query, &mut *tx).await?;
+    tx.commit().await?;
+    Ok(Some((id, available)))
+}
+
+/// Esito del pagamento referral (COMPLETED con firma, oppure FAILED: l'importo torna disponibile)
+pub async fn finish_referral_payout(pool: &DbPool, payout_id: i64, status: &str, signature: Option<&str>) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE referral_payouts SET status = $1, tx_signature = $2 WHERE id = $3")
+        .bind(status)
+        .bind(signature)
+        .bind(payout_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+// --- COPY-TRADING (Wallet seguiti) ---
+
+/// Segue un wallet leader. false se era già seguito.
+pub async fn add_followed_wallet(pool: &DbPool, tg_id: &str, wallet: &str) -> Result<bool, sqlx::Error> {
+    let res = sqlx::query("INSERT INTO followed_wallets (user_id, wallet) VALUES ($1, $2) ON CONFLICT DO NOTHING")
+        .bind(tg_id)
+        .bind(wallet)
+        .execute(pool)
+        .await?;
+    Ok(res.rows_affected() > 0)
+}
+
+/// Smette di seguire un wallet. false se non era seguito.
+pub async fn remove_followed_wallet(pool: &DbPool, tg_id: &str, wallet: &str) -> Result<bool, sqlx::Error> {
+    let res = sqlx::query("DELETE FROM followed_wallets WHERE user_id = $1 AND wallet = $2")
+        .bind(tg_id)
+        .bind(wallet)
+        .execute(pool)
+        .await?;
+    Ok(res.rows_affected() > 0)
+}
+
+/// Wallet seguiti dall'utente
+pub async fn get_followed_wallets(pool: &DbPool, tg_id: &str) -> Result<Vec<String>, sqlx::Error> {
+    let rows = sqlx::query("SELECT wallet FROM followed_wallets WHERE user_id = $1 ORDER BY id")
+        .bind(tg_id)
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.iter().map(|r| r.get("wallet")).collect())
+}
+
+/// Wallet leader seguiti da almeno un utente con l'auto-trading attivo
+pub async fn get_all_followed_wallets(pool: &DbPool) -> Result<Vec<String>, sqlx::Error> {
+    let rows = sqlx::query("SELECT DISTINCT f.wallet FROM followed_wallets f JOIN users u ON u.tg_id = f.user_id WHERE u.is_active = 1")
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.iter().map(|r| r.get("wallet")).collect())
+}
+
+/// Utenti attivi che copiano il wallet leader
+pub async fn get_wallet_followers(pool: &DbPool, wallet: &str) -> Result<Vec<String>, sqlx::Error> {
+    let rows = sqlx::query("SELECT f.user_id FROM followed_wallets f JOIN users u ON u.tg_id = f.user_id WHERE u.is_active = 1 AND f.wallet = $1")
+        .bind(wallet)
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.iter().map(|r| r.get("user_id")).collect())
+}
+
+// --- STORICO SEGNALI (Market Strategy) ---
+
+// Finestra di deduplica: un segnale per token e strategia ogni 5 minuti
+const SIGNAL_BUCKET_SECS: i64 = 300;
+
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct SignalRecord {
+    pub id: i64,
+    pub token: String,
+    pub mode: String,
+    pub price: f64,
+    pub score: u8,
+    pub reason: String,
+    pub timestamp: i64,
+}
+
+/// Salva il segnale. None se il token ha già un segnale della stessa strategia nella finestra di 5 minuti.
+pub async fn record_signal(pool: &DbPool, token: &str, mode: &str, price: f64, score: u8, reason: &str, timestamp: i64) -> Result<Option<i64>, sqlx::Error> {
+    let query = sqlx::query("INSERT INTO signals (token, mode, bucket, price, score, reason, created_at) VALUES ($1, $2, $3, $4, $5, $6, $7) ON CONFLICT DO NOTHING RETURNING id")
+        .bind(token)
+        .bind(mode)
+        .bind(timestamp / SIGNAL_BUCKET_SECS)
+        .bind(price)
+        .bind(score as i64)
+        .bind(reason)
+        .bind(timestamp);
+    Ok(fetch_returning(query, pool).await?.map(|r| r.get("id")))
+}
+
+/// Segnali dal più recente, a partire da `since` (unix). `before` = id dell'ultimo segnale della pagina precedente.
+pub async fn get_signals(pool: &DbPool, since: i64, before: Option<i64>, limit: i64) -> Result<Vec<SignalRecord>, sqlx::Error> {
+    let rows = sqlx::query("SELECT * FROM signals WHERE created_at >= $1 AND id < $2 ORDER BY id DESC LIMIT $3")
+        .bind(since)
+        .bind(before.unwrap_or(i64::MAX))
+        .bind(limit)
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.iter().map(|r| SignalRecord {
+        id: r.get("id"),
+        token: r.get("token"),
+        mode: r.get("mode"),
+        price: r.get("price"),
+        score: r.get::<i64, _>("score") as u8,
+        reason: r.get("reason"),
+        timestamp: r.get("created_at"),
+    }).collect())
+}
+
+/// Cancella i segnali più vecchi di `before` (unix). Ritorna quanti ne ha rimossi.
+pub async fn prune_signals(pool: &DbPool, before: i64) -> Result<u64, sqlx::Error> {
+    let res = sqlx::query("DELETE FROM signals WHERE created_at < $1")
+        .bind(before)
+        .execute(pool)
+        .await?;
+    Ok(res.rows_affected())
+}
+
+// --- FIRME PROCESSATE (Sniper e Copy-Trading: niente doppio processamento tra riavvii) ---
+
+/// Registra la firma: true solo al primo inserimento (false = già processata, anche prima di un riavvio)
+pub async fn claim_signature(pool: &DbPool, signature: &str, source: &str, now: i64) -> Result<bool, sqlx::Error> {
+    let res = sqlx::query("INSERT INTO processed_signatures (signature, source, created_at) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING")
+        .bind(signature)
+        .bind(source)
+        .bind(now)
+        .execute(pool)
+        .await?;
+    Ok(res.rows_affected() == 1)
+}
+
+pub async fn prune_processed_signatures(pool: &DbPool, before: i64) -> Result<u64, sqlx::Error> {
+    let res = sqlx::query("DELETE FROM processed_signatures WHERE created_at < $1")
+        .bind(before)
+        .execute(pool)
+        .await?;
+    Ok(res.rows_affected())
+}
+
+// --- ATTIVITÀ DELL'ACCOUNT (GET /activity) ---
+
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct ActivityItem {
+    // BUY, SELL, DEPOSIT, WITHDRAWAL, SIGNAL o il tipo dell'evento (BOT_START, BOT_STOP, DCA, ERROR)
+    pub kind: String,
+    pub token: Option<String>,
+    // SOL (BUY: investiti, SELL: PnL, WITHDRAWAL e SIGNAL: importo), unità del token per DEPOSIT
+    pub amount: Option<f64>,
+    // Strategia, stato o messaggio dell'evento
+    pub detail: String,
+    pub tx_signature: Option<String>,
+    pub created_at: String,
+    // Chiave univoca a parità di data (cursore di paginazione)
+    #[serde(skip)]
+    pub key: String,
+}
+
+pub async fn record_event(pool: &DbPool, tg_id: &str, kind: &str, token: Option<&str>, amount: Option<f64>, message: &str, tx_signature: Option<&str>) -> Result<(), sqlx::Error> {
+    sqlx::query("INSERT INTO events (user_id, kind, token, amount, message, tx_signature, created_at) VALUES ($1, $2, $3, $4, $5, $6, $7)")
+        .bind(tg_id)
+        .bind(kind)
+        .bind(token)
+        .bind(amount)
+        .bind(message)
+        .bind(tx_signature)
+        .bind(sql_timestamp(Utc::now()))
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Timeline dell'utente dalla più recente: trades (acquisti per TX d'ingresso, vendite per TX d'uscita), depositi,
+/// prelievi, segnali approvati/rifiutati/scaduti ed eventi. `before` = (created_at, key) dell'ultimo elemento della pagina precedente.
+pub async fn get_activity(pool: &DbPool, tg_id: &str, before: Option<(&str, &str)>, limit: i64) -> Result<Vec<ActivityItem>, sqlx::Error> {
+    let sql = format!(
+        "SELECT kind, token, amount, detail, tx_signature, at, kind || ':' || ref AS feed_key FROM (
+            SELECT 'BUY' AS kind, tx_signature AS ref, MIN(token_address) AS token, CAST(SUM(amount_in_lamports) AS DOUBLE PRECISION) / 1e9 AS amount,
+                MAX(COALESCE(strategy, '')) AS detail, tx_signature, MIN({entry}) AS at
+            FROM trades WHERE user_id = $1 AND status <> 'FAILED' GROUP BY tx_signature
+            UNION ALL
+            SELECT 'SELL', exit_signature, MIN(token_address), SUM(profit_loss_sol), '', exit_signature, MAX({exit})
+            FROM trades WHERE user_id = $2 AND status = 'SOLD' AND exit_signature IS NOT NULL GROUP BY exit_signature
+            UNION ALL
+            SELECT 'DEPOSIT', CAST(id AS TEXT), mint, amount_ui, '', tx_signature, {deposit} FROM deposits WHERE user_id = $3
+            UNION ALL
+            SELECT 'WITHDRAWAL', CAST(id AS TEXT), CAST(NULL AS TEXT), CAST(amount_lamports AS DOUBLE PRECISION) / 1e9, COALESCE(status, ''), tx_signature, {withdrawal}
+            FROM withdrawals WHERE user_id = $4
+            UNION ALL
+            SELECT 'SIGNAL', CAST(id AS TEXT), token_address, CAST(amount_lamports AS DOUBLE PRECISION) / 1e9, COALESCE(status, '') || ' ' || source, tx_signature, {pending}
+            FROM pending_trades WHERE user_id = $5 AND status IN ('EXECUTED', 'FAILED', 'REJECTED', 'EXPIRED')
+            UNION ALL
+            SELECT kind, CAST(id AS TEXT), token, amount, message, tx_signature, created_at FROM events WHERE user_id = $6
+        ) feed
+        WHERE at < $7 OR (at = $7 AND kind || ':' || ref < $8)
+        ORDER BY at DESC, feed_key DESC LIMIT $9",
+        entry = sql_time("entry_time"), exit = sql_time("exit_time"), deposit = sql_time("created_at"),
+        withdrawal = sql_time("created_at"), pending = sql_time("created_at"),
+    );
+    // Senza cursore: qualunque data è precedente
+    let (at, key) = before.unwrap_or(("9999-12-31 23:59:59", ""));
+    let rows = sqlx::query(&sql)
+        .bind(tg_id).bind(tg_id).bind(tg_id).bind(tg_id).bind(tg_id).bind(tg_id)
+        .bind(at)
+        .bind(key)
+        .bind(limit)
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.iter().map(|r| ActivityItem {
+        kind: r.get("kind"),
+        token: r.get("token"),
+        amount: r.get("amount"),
+        detail: r.get("detail"),
+        tx_signature: r.get("tx_signature"),
+        created_at: r.get("at"),
+        key: r.get("feed_key"),
+    }).collect())
+}
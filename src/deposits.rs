@@ -0,0 +1,175 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+use futures::StreamExt;
+use solana_client::rpc_config::{RpcTransactionConfig, RpcTransactionLogsConfig, RpcTransactionLogsFilter};
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_transaction_status::option_serializer::OptionSerializer;
+use solana_transaction_status::{UiTransactionEncoding, UiTransactionTokenBalance};
+use tokio::task::JoinHandle;
+use tokio::time::Duration;
+use log::{debug, info, warn};
+use crate::{db, i18n, network, price_oracle, telegram_bot, AppState};
+
+// --- DEPOSITI (Rilevamento versamenti sui wallet custoditi) ---
+// 1. Un listener WebSocket per il wallet di ogni utente e per i suoi ATA USDC/USDT (ricaricati ogni 60s)
+// 2. Le TX NON firmate dall'utente che aumentano il suo saldo SOL o stablecoin sono depositi
+// 3. Il deposito finisce nella tabella `deposits` (totale versato vs equity) e l'utente riceve la notifica;
+//    al primo deposito parte il messaggio di benvenuto
+
+const USDC_MINT: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+const USDT_MINT: &str = "Es9vMFrzaCERmJfrF4H2FYD4KCoNkY11McCe8BenwNYB";
+const STABLECOINS: [(&str, &str); 2] = [(USDC_MINT, "USDC"), (USDT_MINT, "USDT")];
+// Sotto 0.001 SOL è polvere (spam di indirizzi)
+const MIN_SOL_LAMPORTS: u64 = 1_000_000;
+
+// Indirizzo ascoltato -> (utente, wallet proprietario)
+type Watched = HashMap<String, (String, String)>;
+
+async fn watched_addresses(pool: &db::DbPool) -> Result<Watched, sqlx::Error> {
+    let mut watched = HashMap::new();
+    for (user_id, wallet) in db::get_user_wallets(pool).await? {
+        let owner = match Pubkey::from_str(&wallet) { Ok(pk) => pk, Err(_) => continue };
+        for (mint, _) in STABLECOINS {
+            let ata = spl_associated_token_account::get_associated_token_address(&owner, &Pubkey::from_str(mint).unwrap_or_default());
+            watched.insert(ata.to_string(), (user_id.clone(), wallet.clone()));
+        }
+        watched.insert(wallet.clone(), (user_id, wallet));
+    }
+    Ok(watched)
+}
+
+/// Mantiene un listener per ogni indirizzo da osservare
+pub async fn run_deposit_watcher(pool: db::DbPool, net: Arc<network::NetworkClient>, state: Arc<AppState>) {
+    info!("📥 Deposit Watcher: ONLINE");
+    let mut listeners: HashMap<String, JoinHandle<()>> = HashMap::new();
+
+    loop {
+        match watched_addresses(&pool).await {
+            Ok(watched) => {
+                listeners.retain(|a, h| {
+                    let keep = watched.contains_key(a) && !h.is_finished();
+                    if !keep { h.abort(); }
+                    keep
+                });
+                for (address, (user_id, wallet)) in watched {
+                    if listeners.contains_key(&address) { continue; }
+                    let (p, n, s, a) = (pool.clone(), net.clone(), state.clone(), address.clone());
+                    listeners.insert(address, tokio::spawn(async move { watch_address(p, n, s, a, user_id, wallet).await; }));
+                }
+            },
+            Err(e) => warn!("⚠️ Deposit Watcher: lettura wallet fallita: {}", e),
+        }
+        if state.sleep_or_shutdown(Duration::from_secs(60)).await { break; }
+    }
+
+    for (_, h) in listeners { let _ = h.await; }
+    info!("🛑 Deposit Watcher fermato.");
+}
+
+async fn watch_address(pool: db::DbPool, net: Arc<network::NetworkClient>, state: Arc<AppState>, address: String, user_id: String, wallet: String) {
+    loop {
+        match net.pubsub.logs_subscribe(
+            RpcTransactionLogsFilter::Mentions(vec![address.clone()]),
+            RpcTransactionLogsConfig { commitment: Some(CommitmentConfig::confirmed()) }
+        ).await {
+            Ok((mut stream, _)) => {
+                debug!("📥 In ascolto su {} ({})", address, user_id);
+                while let Some(log) = tokio::select! { l = stream.next() => l, _ = state.shutdown_signal() => None } {
+                    if log.value.err.is_some() { continue; }
+                    let (p, n, u, w, sig) = (pool.clone(), net.clone(), user_id.clone(), wallet.clone(), log.value.signature);
+                    tokio::spawn(async move { handle_transaction(&p, &n, &u, &w, &sig).await; });
+                }
+                if state.is_shutting_down() { break; }
+                warn!("⚠️ Stream depositi chiuso ({}), riconnessione...", address);
+            },
+            Err(e) => {
+                warn!("⚠️ Deposit Watcher: sottoscrizione {} fallita: {}", address, e);
+                if state.sleep_or_shutdown(Duration::from_secs(5)).await { break; }
+            }
+        }
+    }
+}
+
+// Importo ricevuto in una TX: (mint, unità base, decimali, simbolo)
+struct Incoming {
+    mint: String,
+    amount: u64,
+    decimals: u8,
+    symbol: &'static str,
+}
+
+async fn handle_transaction(pool: &db::DbPool, net: &Arc<network::NetworkClient>, user_id: &str, wallet: &str, sig: &str) {
+    let incoming = match read_incoming(net, wallet, sig).await {
+        Some(i) => i,
+        None => return,
+    };
+    let sol_usd = price_oracle::sol_usd().await;
+
+    for inc in incoming {
+        let amount_ui = inc.amount as f64 / 10f64.powi(inc.decimals as i32);
+        let usd_value = if inc.mint == price_oracle::SOL_MINT { sol_usd.map(|p| amount_ui * p) } else { Some(amount_ui) };
+
+        match db::record_deposit(pool, user_id, sig, &inc.mint, inc.amount, amount_ui, usd_value).await {
+            Ok(true) => {},
+            Ok(false) => continue, // Già registrato da un altro listener
+            Err(e) => { warn!("⚠️ Deposito {} non salvato: {}", sig, e); continue; }
+        }
+        info!("📥 Deposito {} {} per {} ({})", amount_ui, inc.symbol, user_id, sig);
+
+        let lang = i18n::user_lang(pool, user_id).await;
+        let mut text = i18n::tr(lang, "notify.deposit", &[&amount_ui, &inc.symbol, &sig]);
+        if db::count_deposits(pool, user_id).await.unwrap_or(0) == 1 {
+            text.push_str(i18n::t(lang, "notify.first_deposit"));
+        }
+        telegram_bot::notify_user(user_id, text).await;
+    }
+}
+
+/// Legge la TX e ritorna quanto è entrato nel wallet. None se non è un deposito (firmata dall'utente stesso).
+async fn read_incoming(net: &Arc<network::NetworkClient>, wallet: &str, sig: &str) -> Option<Vec<Incoming>> {
+    let signature = Signature::from_str(sig).ok()?;
+    let config = RpcTransactionConfig {
+        encoding: Some(UiTransactionEncoding::Base64),
+        commitment: Some(CommitmentConfig::confirmed()),
+        max_supported_transaction_version: Some(0),
+    };
+    let tx = net.rpc.get_transaction_with_config(&signature, config).await.ok()?;
+    let meta = tx.transaction.meta?;
+    let decoded = tx.transaction.transaction.decode()?;
+    let keys = decoded.message.static_account_keys();
+
+    // Swap, prelievi e trade del bot sono pagati dal wallet stesso: non sono depositi
+    if keys.first()?.to_string() == wallet { return None; }
+
+    let mut incoming = Vec::new();
+
+    if let Some(idx) = keys.iter().position(|k| k.to_string() == wallet) {
+        let pre = *meta.pre_balances.get(idx)?;
+        let post = *meta.post_balances.get(idx)?;
+        let gain = post.saturating_sub(pre);
+        if gain >= MIN_SOL_LAMPORTS {
+            incoming.push(Incoming { mint: price_oracle::SOL_MINT.to_string(), amount: gain, decimals: 9, symbol: "SOL" });
+        }
+    }
+
+    let owned = |balances: &OptionSerializer<Vec<UiTransactionTokenBalance>>, mint: &str| -> (u64, u8) {
+        match balances {
+            OptionSerializer::Some(list) => list.iter()
+                .filter(|b| b.mint == mint && matches!(&b.owner, OptionSerializer::Some(o) if o == wallet))
+                .fold((0, 0), |(sum, _), b| (sum + b.ui_token_amount.amount.parse::<u64>().unwrap_or(0), b.ui_token_amount.decimals)),
+            _ => (0, 0),
+        }
+    };
+    for (mint, symbol) in STABLECOINS {
+        let (post, decimals) = owned(&meta.post_token_balances, mint);
+        let gain = post.saturating_sub(owned(&meta.pre_token_balances, mint).0);
+        if gain > 0 {
+            incoming.push(Incoming { mint: mint.to_string(), amount: gain, decimals, symbol });
+        }
+    }
+
+    if incoming.is_empty() { None } else { Some(incoming) }
+}
@@ -0,0 +1,187 @@
+use std::collections::HashSet;
+use std::error::Error;
+use std::str::FromStr;
+use std::sync::Arc;
+use serde::{Deserialize, Serialize};
+use solana_account_decoder::UiAccountData;
+use solana_client::rpc_request::TokenAccountsFilter;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::transaction::Transaction;
+use tokio::time::Duration;
+use log::{debug, info, warn};
+use crate::{db, jupiter, network, swap_router, telegram_bot, wallet_manager, AppState};
+
+// --- DUST SWEEPER (Polvere di token -> SOL, rent degli ATA vuoti recuperato) ---
+// 1. Ogni 6 ore, per chi lo ha attivato (users.settings["dust"]), si leggono tutti i token account del wallet
+// 2. I saldi che valgono più delle fee ma meno di `max_sol` vengono venduti via Router (Jupiter/Orca)
+// 3. Gli ATA a saldo zero vengono chiusi: il rent (~0.002 SOL ciascuno) torna al wallet
+// Posizioni aperte, griglie attive e stablecoin non vengono mai toccati.
+// Con `close_on_stop` la sola chiusura degli ATA vuoti parte anche allo stop dell'Auto-Bot (`reclaim_rent`).
+
+const SOL_MINT: &str = "So11111111111111111111111111111111111111112";
+const STABLECOINS: [&str; 2] = ["EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v", "Es9vMFrzaCERmJfrF4H2FYD4KCoNkY11McCe8BenwNYB"];
+const SWEEP_SECS: u64 = 6 * 3600;
+// Sotto questo ricavo la vendita costa più di quanto rende (fee base + priority fee)
+const MIN_SELL_LAMPORTS: u64 = 200_000;
+const DUST_SLIPPAGE_BPS: u16 = 300;
+// Istruzioni close_account per TX (resta sotto il limite di dimensione)
+const CLOSE_BATCH: usize = 20;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct DustSettings {
+    pub enabled: bool,
+    // Valore massimo (in SOL) perché un saldo sia considerato polvere
+    pub max_sol: f64,
+    // Chiude gli ATA vuoti quando l'utente ferma il bot
+    pub close_on_stop: bool,
+}
+
+impl Default for DustSettings {
+    fn default() -> Self {
+        Self { enabled: false, max_sol: 0.01, close_on_stop: false }
+    }
+}
+
+impl DustSettings {
+    pub fn from_settings(settings: &serde_json::Value) -> Self {
+        serde_json::from_value(settings["dust"].clone()).unwrap_or_default()
+    }
+}
+
+// Token account del wallet (classico o Token-2022)
+#[derive(Clone, Debug)]
+pub struct TokenAccount {
+    pub address: Pubkey,
+    pub mint: String,
+    pub program: Pubkey,
+    pub amount: u64,
+    pub decimals: u8,
+    pub lamports: u64,
+}
+
+/// Tutti i token account dell'owner, su entrambi i programmi token
+pub async fn token_accounts(net: &Arc<network::NetworkClient>, owner: &Pubkey) -> Result<Vec<TokenAccount>, Box<dyn Error + Send + Sync>> {
+    let mut accounts = Vec::new();
+    for program in [spl_token::id(), spl_token_2022::id()] {
+        let keyed = {
+            let _t = crate::metrics::METRICS.rpc_timer("getTokenAccountsByOwner");
+            net.rpc.get_token_accounts_by_owner(owner, TokenAccountsFilter::ProgramId(program)).await?
+        };
+        for k in keyed {
+            let info = match &k.account.data {
+                UiAccountData::Json(parsed) => parsed.parsed["info"].clone(),
+                _ => continue,
+            };
+            let (Some(mint), Ok(address)) = (info["mint"].as_str(), Pubkey::from_str(&k.pubkey)) else { continue };
+            let amount = info["tokenAmount"]["amount"].as_str().and_then(|a| a.parse().ok()).unwrap_or(0);
+            let decimals = info["tokenAmount"]["decimals"].as_u64().unwrap_or(0) as u8;
+            accounts.push(TokenAccount { address, mint: mint.to_string(), program, amount, decimals, lamports: k.account.lamports });
+        }
+    }
+    Ok(accounts)
+}
+
+/// Chiude i token account a saldo zero. Ritorna i lamports di rent recuperati (solo dalle TX inviate).
+pub async fn close_empty_accounts(net: &Arc<network::NetworkClient>, payer: &Keypair, accounts: &[TokenAccount]) -> Result<u64, String> {
+    let owner = payer.pubkey();
+    let empty: Vec<&TokenAccount> = accounts.iter().filter(|a| a.amount == 0).collect();
+    let mut reclaimed = 0;
+
+    for batch in empty.chunks(CLOSE_BATCH) {
+        let ixs = batch.iter()
+            .map(|a| spl_token_2022::instruction::close_account(&a.program, &a.address, &owner, &owner, &[]))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        let ixs = net.with_compute_budget(network::TxProfile::CloseAta, &owner, ixs, &[]).await;
+        let bh = net.rpc.get_latest_blockhash().await.map_err(|e| e.to_string())?;
+        let tx = Transaction::new_signed_with_payer(&ixs, Some(&owner), &[payer], bh);
+        match net.rpc.send_and_confirm_transaction(&tx).await {
+            Ok(sig) => {
+                reclaimed += batch.iter().map(|a| a.lamports).sum::<u64>();
+                info!("🧹 Chiusi {} ATA vuoti di {} ({})", batch.len(), owner, sig);
+            },
+            // Es. Token-2022 con fee trattenute: l'account non si può chiudere, si prosegue con gli altri
+            Err(e) => warn!("⚠️ Chiusura ATA fallita per {}: {}", owner, e),
+        }
+    }
+    Ok(reclaimed)
+}
+
+/// Chiude tutti gli ATA vuoti dell'utente (dopo la liquidazione). Ritorna i lamports recuperati.
+pub async fn reclaim_rent(pool: &db::DbPool, net: &Arc<network::NetworkClient>, user_id: &str) -> Result<u64, String> {
+    let payer = wallet_manager::get_decrypted_wallet(pool, user_id).await.map_err(|e| e.to_string())?;
+    let accounts = token_accounts(net, &payer.pubkey()).await.map_err(|e| e.to_string())?;
+    close_empty_accounts(net, &payer, &accounts).await
+}
+
+/// Allo stop del bot: recupero rent solo se l'utente l'ha attivato. Some(lamports) se eseguito.
+pub async fn reclaim_on_stop(pool: &db::DbPool, net: &Arc<network::NetworkClient>, user_id: &str) -> Option<u64> {
+    let settings = db::get_settings(pool, user_id).await.ok()?;
+    if !DustSettings::from_settings(&settings).close_on_stop { return None; }
+    match reclaim_rent(pool, net, user_id).await {
+        Ok(lamports) => Some(lamports),
+        Err(e) => { warn!("⚠️ Recupero rent allo stop fallito per {}: {}", user_id, e); None },
+    }
+}
+
+pub async fn run_dust_sweeper(pool: db::DbPool, net: Arc<network::NetworkClient>, state: Arc<AppState>) {
+    info!("🧹 Dust Sweeper: ONLINE");
+
+    loop {
+        for (user_id, settings) in db::get_all_user_settings(&pool).await.unwrap_or_default() {
+            if state.is_shutting_down() { break; }
+            let dust = DustSettings::from_settings(&settings);
+            if !dust.enabled { continue; }
+
+            match sweep_user(&pool, &net, &state, &user_id, &dust).await {
+                Ok((0, 0)) => {},
+                Ok((sold, reclaimed)) => {
+                    telegram_bot::notify_user(&user_id, format!(
+                        "🧹 <b>Pulizia wallet</b>\n\n💱 Token polvere venduti: <b>{}</b>\n♻️ Rent recuperato: <b>{:.4} SOL</b>",
+                        sold, reclaimed as f64 / 1_000_000_000.0
+                    )).await;
+                },
+                Err(e) => warn!("⚠️ Dust Sweeper {}: {}", user_id, e),
+            }
+        }
+        if state.sleep_or_shutdown(Duration::from_secs(SWEEP_SECS)).await { break; }
+    }
+    info!("🛑 Dust Sweeper fermato.");
+}
+
+/// Un giro di pulizia per l'utente: (token venduti, lamports di rent recuperati)
+async fn sweep_user(pool: &db::DbPool, net: &Arc<network::NetworkClient>, state: &Arc<AppState>, user_id: &str, dust: &DustSettings) -> Result<(usize, u64), String> {
+    let payer = wallet_manager::get_decrypted_wallet(pool, user_id).await.map_err(|e| e.to_string())?;
+    let accounts = token_accounts(net, &payer.pubkey()).await.map_err(|e| e.to_string())?;
+
+    // Mint da non toccare: posizioni aperte, griglia attiva, stablecoin
+    let mut protected: HashSet<String> = STABLECOINS.iter().map(|m| m.to_string()).collect();
+    protected.extend(db::get_user_open_trades(pool, user_id).await.map_err(|e| e.to_string())?.into_iter().map(|(_, t, _)| t));
+    protected.extend(state.positions_snapshot(Some(user_id)).into_iter().map(|p| p.token));
+    if let Ok(Some(grid)) = db::get_user_grid(pool, user_id).await {
+        if grid.status == "ACTIVE" { protected.extend([grid.base_mint, grid.quote_mint]); }
+    }
+
+    let max_lamports = (dust.max_sol * 1_000_000_000.0) as u64;
+    let mut sold = 0;
+    for acc in accounts.iter().filter(|a| a.amount > 0 && a.mint != SOL_MINT && !protected.contains(&a.mint)) {
+        if state.is_shutting_down() { break; }
+        let value = match jupiter::get_quote(&acc.mint, SOL_MINT, acc.amount, DUST_SLIPPAGE_BPS).await {
+            Ok(q) => jupiter::quote_out_amount(&q),
+            Err(_) => continue, // Nessuna route: non vendibile
+        };
+        if value < MIN_SELL_LAMPORTS || value > max_lamports { continue; }
+
+        match swap_router::sell(net, pool, user_id, &payer, &acc.mint, acc.amount, DUST_SLIPPAGE_BPS).await {
+            Ok(out) => { sold += 1; debug!("🧹 Polvere {} venduta per {} ({})", acc.mint, user_id, out.signature); },
+            Err(e) => debug!("🧹 Vendita polvere {} fallita per {}: {}", acc.mint, user_id, e),
+        }
+    }
+
+    // Gli account appena svuotati dalle vendite vengono chiusi al giro successivo
+    let closable: Vec<TokenAccount> = accounts.into_iter().filter(|a| !protected.contains(&a.mint)).collect();
+    let reclaimed = close_empty_accounts(net, &payer, &closable).await?;
+    Ok((sold, reclaimed))
+}
@@ -0,0 +1,91 @@
+use std::env;
+use serde_json::json;
+use tokio::time::Duration;
+use log::{info, warn};
+use lettre::message::{header::ContentType, Mailbox};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+
+// --- EMAIL (Verifica indirizzo e reset password) ---
+// EMAIL_PROVIDER: smtp (SMTP_HOST, SMTP_PORT, SMTP_USER, SMTP_PASSWORD) | sendgrid (SENDGRID_API_KEY) | log (default, solo log)
+// EMAIL_FROM: mittente ("God Sniper <noreply@dominio>"), APP_URL: base dei link nelle email
+
+const SENDGRID_API: &str = "https://api.sendgrid.com/v3/mail/send";
+const SEND_TIMEOUT_SECS: u64 = 15;
+const DEFAULT_APP_URL: &str = "https://cryptostarstudiobot.netlify.app";
+
+pub enum EmailSender {
+    Smtp { host: String, port: u16, user: String, password: String },
+    SendGrid { api_key: String },
+    // Nessun provider: il messaggio finisce nei log (sviluppo)
+    Log,
+}
+
+impl EmailSender {
+    pub fn from_env() -> Self {
+        let var = |name: &str| env::var(name).unwrap_or_default();
+        match var("EMAIL_PROVIDER").to_lowercase().as_str() {
+            "smtp" if !var("SMTP_HOST").is_empty() => EmailSender::Smtp {
+                host: var("SMTP_HOST"),
+                port: var("SMTP_PORT").parse().unwrap_or(587),
+                user: var("SMTP_USER"),
+                password: var("SMTP_PASSWORD"),
+            },
+            "sendgrid" if !var("SENDGRID_API_KEY").is_empty() => EmailSender::SendGrid { api_key: var("SENDGRID_API_KEY") },
+            _ => EmailSender::Log,
+        }
+    }
+
+    pub async fn send(&self, to: &str, subject: &str, body: &str) -> Result<(), String> {
+        let from = env::var("EMAIL_FROM").unwrap_or_else(|_| "God Sniper <noreply@localhost>".into());
+        match self {
+            EmailSender::Smtp { host, port, user, password } => {
+                let message = Message::builder()
+                    .from(from.parse::<Mailbox>().map_err(|e| format!("EMAIL_FROM non valido: {}", e))?)
+                    .to(to.parse::<Mailbox>().map_err(|e| format!("Destinatario non valido: {}", e))?)
+                    .subject(subject)
+                    .header(ContentType::TEXT_PLAIN)
+                    .body(body.to_string())
+                    .map_err(|e| e.to_string())?;
+                let transport = AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)
+                    .map_err(|e| e.to_string())?
+                    .port(*port)
+                    .credentials(Credentials::new(user.clone(), password.clone()))
+                    .timeout(Some(Duration::from_secs(SEND_TIMEOUT_SECS)))
+                    .build();
+                transport.send(message).await.map(|_| ()).map_err(|e| format!("SMTP: {}", e))
+            },
+            EmailSender::SendGrid { api_key } => {
+                let sender = from.parse::<Mailbox>().map_err(|e| format!("EMAIL_FROM non valido: {}", e))?;
+                let payload = json!({
+                    "personalizations": [{ "to": [{ "email": to }] }],
+                    "from": { "email": sender.email.to_string(), "name": sender.name.unwrap_or_default() },
+                    "subject": subject,
+                    "content": [{ "type": "text/plain", "value": body }],
+                });
+                let client = reqwest::Client::builder().timeout(Duration::from_secs(SEND_TIMEOUT_SECS)).build().unwrap_or_default();
+                let resp = client.post(SENDGRID_API).bearer_auth(api_key).json(&payload).send().await.map_err(|e| format!("SendGrid: {}", e))?;
+                resp.error_for_status().map(|_| ()).map_err(|e| format!("SendGrid: {}", e))
+            },
+            EmailSender::Log => {
+                info!("📧 [EMAIL_PROVIDER non configurato] A: {} | {}\n{}", to, subject, body);
+                Ok(())
+            },
+        }
+    }
+}
+
+/// Link dell'app con il token in query (es. /?verify=TOKEN)
+pub fn app_link(param: &str, token: &str) -> String {
+    let base = env::var("APP_URL").unwrap_or_else(|_| DEFAULT_APP_URL.into());
+    format!("{}/?{}={}", base.trim_end_matches('/'), param, token)
+}
+
+/// Invio in background: la risposta HTTP non aspetta il provider (né rivela se l'account esiste)
+pub fn send_in_background(to: String, subject: String, body: String) {
+    tokio::spawn(async move {
+        if let Err(e) = EmailSender::from_env().send(&to, &subject, &body).await {
+            warn!("⚠️ Email a {} non inviata: {}", to, e);
+        }
+    });
+}
@@ -0,0 +1,109 @@
+use std::collections::BTreeMap;
+use serde::Serialize;
+use crate::db::TradeEvent;
+
+// --- EXPORT STORICO TRADE (CSV) E REPORT FISCALE ---
+// 1. Ogni trade produce due righe: BUY all'apertura, SELL alla chiusura (con PnL)
+// 2. I valori USD usano il prezzo SOL dell'oracolo salvato al momento dell'esecuzione
+// 3. Il report annuale somma le vendite chiuse nell'anno (plusvalenze realizzate) e le fee pagate
+// 4. Tag (separati da ';') e nota dell'utente in coda a ogni riga
+
+const CSV_HEADER: &str = "date,side,trade_id,token,symbol,amount_sol,sol_usd,value_usd,fee_sol,fee_usd,pnl_sol,cost_basis_usd,pnl_usd,tx_signature,tags,note";
+
+// Riepilogo di un anno solare
+#[derive(Serialize, Clone, Default, Debug)]
+pub struct TaxYear {
+    pub year: i32,
+    pub buys: u32,
+    pub sells: u32,
+    pub invested_sol: f64,
+    pub proceeds_sol: f64,
+    pub realized_pnl_sol: f64,
+    pub fees_sol: f64,
+    pub cost_basis_usd: f64,
+    pub proceeds_usd: f64,
+    pub realized_pnl_usd: f64,
+    pub fees_usd: f64,
+    // Vendite senza prezzo SOL storico (escluse dai totali USD)
+    pub sells_missing_usd: u32,
+}
+
+fn usd(sol: f64, sol_usd: Option<f64>) -> Option<f64> {
+    sol_usd.map(|p| sol * p)
+}
+
+fn cell(v: Option<f64>, decimals: usize) -> String {
+    v.map(|x| format!("{:.*}", decimals, x)).unwrap_or_default()
+}
+
+// Testo libero (RFC 4180): tra virgolette se contiene separatori, virgolette o a capo
+fn text_cell(v: &str) -> String {
+    if v.contains([',', '"', '\n', '\r']) { format!("\"{}\"", v.replace('"', "\"\"")) } else { v.to_string() }
+}
+
+/// CSV completo (intestazione + una riga per movimento)
+pub fn to_csv(events: &[TradeEvent]) -> String {
+    let mut out = String::from(CSV_HEADER);
+    out.push('\n');
+    for e in events {
+        let value_usd = usd(e.amount_sol, e.sol_usd);
+        let is_sell = e.side == "SELL";
+        let pnl_usd = match (is_sell, value_usd, e.cost_basis_usd) {
+            (true, Some(v), Some(c)) => Some(v - c),
+            _ => None,
+        };
+        let row = [
+            e.time.clone(),
+            e.side.clone(),
+            e.trade_id.to_string(),
+            e.token.clone(),
+            e.symbol.clone().unwrap_or_default(),
+            format!("{:.9}", e.amount_sol),
+            cell(e.sol_usd, 4),
+            cell(value_usd, 2),
+            format!("{:.9}", e.fee_sol),
+            cell(usd(e.fee_sol, e.sol_usd), 4),
+            if is_sell { format!("{:.9}", e.pnl_sol) } else { String::new() },
+            cell(e.cost_basis_usd, 2),
+            cell(pnl_usd, 2),
+            e.tx_signature.clone().unwrap_or_default(),
+            text_cell(&e.tags.join(";")),
+            text_cell(e.note.as_deref().unwrap_or_default()),
+        ];
+        out.push_str(&row.join(","));
+        out.push('\n');
+    }
+    out
+}
+
+/// Aggregato per anno (dall'anno più vecchio)
+pub fn tax_years(events: &[TradeEvent]) -> Vec<TaxYear> {
+    let mut years: BTreeMap<i32, TaxYear> = BTreeMap::new();
+    for e in events {
+        let year = match e.time.get(..4).and_then(|y| y.parse::<i32>().ok()) {
+            Some(y) => y,
+            None => continue,
+        };
+        let y = years.entry(year).or_insert_with(|| TaxYear { year, ..Default::default() });
+        y.fees_sol += e.fee_sol;
+        y.fees_usd += usd(e.fee_sol, e.sol_usd).unwrap_or(0.0);
+
+        if e.side == "BUY" {
+            y.buys += 1;
+            y.invested_sol += e.amount_sol;
+            continue;
+        }
+        y.sells += 1;
+        y.proceeds_sol += e.amount_sol;
+        y.realized_pnl_sol += e.pnl_sol;
+        match (usd(e.amount_sol, e.sol_usd), e.cost_basis_usd) {
+            (Some(proceeds), Some(cost)) => {
+                y.proceeds_usd += proceeds;
+                y.cost_basis_usd += cost;
+                y.realized_pnl_usd += proceeds - cost;
+            },
+            _ => y.sells_missing_usd += 1,
+        }
+    }
+    years.into_values().collect()
+}
@@ -0,0 +1,158 @@
+use std::env;
+use std::sync::{Arc, LazyLock, RwLock};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use crate::{jupiter, metrics, AppState};
+
+// --- FILTRI QUALITÀ GEMME (Sniper: soglie di mercato prima dell'analisi del lancio) ---
+// 1. Le soglie (liquidità, volume, market cap) si leggono da GEM_FILTERS_FILE (percorso di un JSON) o da GEM_FILTERS (JSON inline);
+//    i campi assenti restano ai default, senza configurazione vale il vecchio filtro (liquidità >= $5000)
+// 2. Ricarica a caldo: SIGHUP o POST /admin/gem-filters/reload rileggono il file; POST /admin/gem-filters le sostituisce
+//    fino al prossimo reload o riavvio
+// 3. Ogni candidato scartato incrementa gem_filter_rejections_total{filter} con il nome della soglia che l'ha fermato
+
+const DEFAULT_MIN_LIQUIDITY_USD: f64 = 5000.0;
+
+// Nomi dei filtri (label della metrica e chiavi di GET /admin/gem-filters)
+pub const FILTERS: [&str; 6] = ["price", "min_liquidity", "max_liquidity", "min_volume_24h", "min_market_cap", "max_market_cap"];
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GemFilters {
+    pub min_liquidity_usd: f64,
+    // None = nessun tetto
+    pub max_liquidity_usd: Option<f64>,
+    pub min_volume_24h: f64,
+    pub min_market_cap: f64,
+    pub max_market_cap: Option<f64>,
+}
+
+impl Default for GemFilters {
+    fn default() -> Self {
+        Self { min_liquidity_usd: DEFAULT_MIN_LIQUIDITY_USD, max_liquidity_usd: None, min_volume_24h: 0.0, min_market_cap: 0.0, max_market_cap: None }
+    }
+}
+
+impl GemFilters {
+    pub fn validate(&self) -> Result<(), String> {
+        let values = [Some(self.min_liquidity_usd), self.max_liquidity_usd, Some(self.min_volume_24h), Some(self.min_market_cap), self.max_market_cap];
+        if values.iter().flatten().any(|v| !v.is_finite() || *v < 0.0) { return Err("Soglie negative o non valide".into()); }
+        if self.max_liquidity_usd.is_some_and(|max| max < self.min_liquidity_usd) { return Err("max_liquidity_usd sotto min_liquidity_usd".into()); }
+        if self.max_market_cap.is_some_and(|max| max < self.min_market_cap) { return Err("max_market_cap sotto min_market_cap".into()); }
+        Ok(())
+    }
+
+    /// Ok se il token passa tutte le soglie, altrimenti il nome del primo filtro che lo scarta
+    pub fn check(&self, mkt: &jupiter::TokenMarketData) -> Result<(), &'static str> {
+        if mkt.price <= 0.0 { return Err("price"); }
+        if mkt.liquidity_usd < self.min_liquidity_usd { return Err("min_liquidity"); }
+        if self.max_liquidity_usd.is_some_and(|max| mkt.liquidity_usd > max) { return Err("max_liquidity"); }
+        if mkt.volume_24h < self.min_volume_24h { return Err("min_volume_24h"); }
+        if mkt.market_cap < self.min_market_cap { return Err("min_market_cap"); }
+        if self.max_market_cap.is_some_and(|max| mkt.market_cap > max) { return Err("max_market_cap"); }
+        Ok(())
+    }
+}
+
+static ACTIVE: LazyLock<RwLock<GemFilters>> = LazyLock::new(|| {
+    let filters = load().unwrap_or_else(|e| { warn!("⚠️ Filtri gemme non validi ({}): uso i default", e); GemFilters::default() });
+    RwLock::new(filters)
+});
+
+// Legge la configurazione: GEM_FILTERS_FILE ha la precedenza su GEM_FILTERS
+fn load() -> Result<GemFilters, String> {
+    let json = match env::var("GEM_FILTERS_FILE") {
+        Ok(path) => std::fs::read_to_string(&path).map_err(|e| format!("{}: {}", path, e))?,
+        Err(_) => match env::var("GEM_FILTERS") {
+            Ok(json) => json,
+            Err(_) => return Ok(GemFilters::default()),
+        },
+    };
+    let filters: GemFilters = serde_json::from_str(&json).map_err(|e| format!("JSON non valido: {}", e))?;
+    filters.validate()?;
+    Ok(filters)
+}
+
+/// Soglie attive
+pub fn current() -> GemFilters {
+    ACTIVE.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Sostituisce le soglie attive (fino al prossimo reload)
+pub fn set(filters: GemFilters) -> Result<GemFilters, String> {
+    filters.validate()?;
+    *ACTIVE.write().unwrap_or_else(|e| e.into_inner()) = filters.clone();
+    Ok(filters)
+}
+
+/// Rilegge GEM_FILTERS_FILE / GEM_FILTERS; con una configurazione non valida restano le soglie attuali
+pub fn reload() -> Result<GemFilters, String> {
+    let filters = load()?;
+    info!("🔧 Filtri gemme ricaricati: {:?}", filters);
+    set(filters)
+}
+
+/// Filtro dello Sniper: registra quale soglia ha scartato il candidato
+pub fn passes(mint: &str, mkt: &jupiter::TokenMarketData) -> bool {
+    match current().check(mkt) {
+        Ok(()) => true,
+        Err(filter) => {
+            metrics::METRICS.gem_filter_rejections.with_label_values(&[filter]).inc();
+            debug!("🔎 Gemma {} scartata dal filtro {} (liq ${:.0}, vol ${:.0}, mcap ${:.0})", mint, filter, mkt.liquidity_usd, mkt.volume_24h, mkt.market_cap);
+            false
+        },
+    }
+}
+
+/// Scarti per filtro dall'avvio (per la taratura da GET /admin/gem-filters)
+pub fn rejections() -> Vec<(&'static str, u64)> {
+    FILTERS.iter().map(|f| (*f, metrics::METRICS.gem_filter_rejections.with_label_values(&[f]).get())).collect()
+}
+
+/// Ricarica le soglie a ogni SIGHUP (solo unix)
+pub async fn run_reload_on_sighup(state: Arc<AppState>) {
+    #[cfg(unix)]
+    {
+        let mut hup = match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup()) {
+            Ok(s) => s,
+            Err(e) => { warn!("⚠️ Handler SIGHUP non disponibile: {}", e); return; },
+        };
+        loop {
+            tokio::select! {
+                _ = state.shutdown_signal() => return,
+                _ = hup.recv() => {
+                    if let Err(e) = reload() { warn!("⚠️ SIGHUP: filtri gemme non ricaricati: {}", e); }
+                },
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    state.shutdown_signal().await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn market(price: f64, liquidity_usd: f64, volume_24h: f64, market_cap: f64) -> jupiter::TokenMarketData {
+        jupiter::TokenMarketData { price, symbol: "GEM".into(), liquidity_usd, market_cap, volume_24h, change_5m: 0.0, change_1h: 0.0, change_24h: 0.0 }
+    }
+
+    #[test]
+    fn reports_the_rejecting_filter() {
+        let filters = GemFilters { min_volume_24h: 1000.0, max_market_cap: Some(1_000_000.0), ..Default::default() };
+        assert_eq!(filters.check(&market(0.0, 10_000.0, 5000.0, 50_000.0)), Err("price"));
+        assert_eq!(filters.check(&market(1.0, 4000.0, 5000.0, 50_000.0)), Err("min_liquidity"));
+        assert_eq!(filters.check(&market(1.0, 10_000.0, 500.0, 50_000.0)), Err("min_volume_24h"));
+        assert_eq!(filters.check(&market(1.0, 10_000.0, 5000.0, 2_000_000.0)), Err("max_market_cap"));
+        assert_eq!(filters.check(&market(1.0, 10_000.0, 5000.0, 50_000.0)), Ok(()));
+    }
+
+    #[test]
+    fn partial_json_keeps_defaults() {
+        let filters: GemFilters = serde_json::from_str(r#"{"min_market_cap": 20000}"#).unwrap();
+        assert_eq!(filters.min_liquidity_usd, DEFAULT_MIN_LIQUIDITY_USD);
+        assert_eq!(filters.min_market_cap, 20_000.0);
+        assert!(GemFilters { max_liquidity_usd: Some(100.0), ..Default::default() }.validate().is_err());
+    }
+}
@@ -0,0 +1,135 @@
+use std::cmp::Reverse;
+use dashmap::DashMap;
+use serde::Deserialize;
+use crate::{jupiter, GemData};
+
+// --- INDICE GEMME (Discovery filtrabile, GET /gems) ---
+// 1. Un record per mint, aggiornato a ogni nuovo dato di mercato (prezzo, liquidità, volume, variazione 24h)
+// 2. Fonti: SNIPER (lanci nuovi), TOP (watchlist di default), DISCOVERY (token scelti dagli utenti nella Market Strategy)
+// 3. Oltre MAX_GEMS si scartano i record aggiornati meno di recente
+
+pub const SOURCES: [&str; 3] = ["SNIPER", "DISCOVERY", "TOP"];
+const MAX_GEMS: usize = 500;
+// Feed della Dashboard (/status): ultimi lanci dello Sniper
+const FEED_LEN: usize = 50;
+const DEFAULT_LIMIT: usize = 25;
+const MAX_LIMIT: usize = 100;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GemSort { Newest, Volume, Score, Change24h }
+
+impl GemSort {
+    pub fn from_code(code: &str) -> Option<Self> {
+        match code.trim().to_lowercase().as_str() {
+            "newest" => Some(GemSort::Newest),
+            "volume" => Some(GemSort::Volume),
+            "score" => Some(GemSort::Score),
+            "change_24h" => Some(GemSort::Change24h),
+            _ => None,
+        }
+    }
+}
+
+// sort: newest (default) | volume | score | change_24h, source: SNIPER | DISCOVERY | TOP
+#[derive(Deserialize, Default)]
+pub struct GemQuery {
+    pub sort: Option<String>,
+    pub min_liquidity: Option<f64>,
+    pub source: Option<String>,
+    pub limit: Option<usize>,
+}
+
+/// Filtri validati di GemQuery
+pub struct GemFilter {
+    pub sort: GemSort,
+    pub min_liquidity: f64,
+    pub source: Option<&'static str>,
+    pub limit: usize,
+}
+
+impl GemQuery {
+    /// None = parametri non validi
+    pub fn validate(&self) -> Option<GemFilter> {
+        let sort = match &self.sort {
+            Some(s) => GemSort::from_code(s)?,
+            None => GemSort::Newest,
+        };
+        let source = match &self.source {
+            Some(s) => Some(*SOURCES.iter().find(|src| src.eq_ignore_ascii_case(s.trim()))?),
+            None => None,
+        };
+        let min_liquidity = self.min_liquidity.unwrap_or(0.0);
+        if !min_liquidity.is_finite() || min_liquidity < 0.0 { return None; }
+        let limit = self.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+        Some(GemFilter { sort, min_liquidity, source, limit })
+    }
+}
+
+impl GemData {
+    /// `score`: punteggio di scoring (calculate_token_score o analyze_token_potential)
+    pub fn from_market(token: &str, symbol: &str, logo: Option<String>, mkt: &jupiter::TokenMarketData, score: u8, safety_score: u8, source: &str) -> Self {
+        let now = chrono::Utc::now().timestamp();
+        GemData {
+            token: token.to_string(),
+            symbol: symbol.to_string(),
+            logo,
+            price: mkt.price,
+            safety_score,
+            score,
+            liquidity_usd: mkt.liquidity_usd,
+            volume_24h: mkt.volume_24h,
+            change_24h: mkt.change_24h,
+            timestamp: now,
+            updated_at: now,
+            source: source.to_string(),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct GemIndex {
+    gems: DashMap<String, GemData>,
+}
+
+impl GemIndex {
+    /// Inserisce o aggiorna la gemma: restano la prima scoperta (timestamp), la fonte SNIPER e il safety score
+    pub fn record(&self, mut gem: GemData) {
+        if let Some(prev) = self.gems.get(&gem.token) {
+            gem.timestamp = prev.timestamp;
+            if prev.source == "SNIPER" { gem.source = prev.source.clone(); }
+            if gem.logo.is_none() { gem.logo = prev.logo.clone(); }
+            // La Market Strategy non fa il check di sicurezza: resta quello dello Sniper
+            gem.safety_score = gem.safety_score.max(prev.safety_score);
+        }
+        self.gems.insert(gem.token.clone(), gem);
+
+        if self.gems.len() > MAX_GEMS {
+            let stalest = self.gems.iter().min_by_key(|g| g.updated_at).map(|g| g.key().clone());
+            if let Some(token) = stalest { self.gems.remove(&token); }
+        }
+    }
+
+    /// Feed della Dashboard: ultimi lanci dello Sniper, dal più recente
+    pub fn sniper_feed(&self) -> Vec<GemData> {
+        let mut feed: Vec<GemData> = self.gems.iter().filter(|g| g.source == "SNIPER").map(|g| g.value().clone()).collect();
+        feed.sort_by_key(|g| Reverse(g.timestamp));
+        feed.truncate(FEED_LEN);
+        feed
+    }
+
+    /// Gemme filtrate e ordinate (limit applicato dal chiamante dopo i filtri per utente)
+    pub fn query(&self, filter: &GemFilter) -> Vec<GemData> {
+        let mut gems: Vec<GemData> = self.gems.iter()
+            .filter(|g| g.liquidity_usd >= filter.min_liquidity)
+            .filter(|g| filter.source.is_none_or(|s| g.source == s))
+            .map(|g| g.value().clone())
+            .collect();
+        match filter.sort {
+            GemSort::Newest => gems.sort_by_key(|g| Reverse(g.timestamp)),
+            GemSort::Volume => gems.sort_by(|a, b| b.volume_24h.total_cmp(&a.volume_24h)),
+            GemSort::Score => gems.sort_by(|a, b| b.score.cmp(&a.score).then(b.safety_score.cmp(&a.safety_score))),
+            GemSort::Change24h => gems.sort_by(|a, b| b.change_24h.total_cmp(&a.change_24h)),
+        }
+        gems
+    }
+}
@@ -0,0 +1,225 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+use serde::Deserialize;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use tokio::time::Duration;
+use log::{info, warn};
+use crate::{auto_park, db, jupiter, network, swap_router, telegram_bot, wallet_manager, AppState};
+
+// --- GRID TRADING (Scala di ordini su una coppia, es. SOL/USDC) ---
+// 1. Da lower_price a upper_price un livello ogni step_pct: si compra quando il prezzo scende sul livello,
+//    si rivende un gradino sopra (buy_price * (1 + step_pct))
+// 2. Livelli e fill stanno nel DB (grid_levels): un riavvio riprende da dove era rimasto
+// 3. Dopo ogni vendita il livello torna in attesa: la griglia si ribilancia da sola finché il prezzo oscilla nel range
+// Gli swap passano dal router (Jupiter / Orca), il prezzo è base/quote in USD (DexScreener, in cache)
+
+pub const MAX_GRID_LEVELS: usize = 50;
+const SOL_MINT: &str = "So11111111111111111111111111111111111111112";
+const USDC_MINT: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+// SOL lasciati nel wallet per le fee quando la base o la quote è SOL
+const FEE_RESERVE_LAMPORTS: u64 = 10_000_000;
+
+fn default_base() -> String { SOL_MINT.to_string() }
+fn default_quote() -> String { USDC_MINT.to_string() }
+
+// Configurazione dal payload di /bot/start (strategy = "grid")
+#[derive(Deserialize, Clone, Debug)]
+pub struct GridConfig {
+    #[serde(default = "default_base")]
+    pub base: String,
+    #[serde(default = "default_quote")]
+    pub quote: String,
+    pub lower_price: f64, // Prezzo di 1 base in quote (es. 120 USDC per SOL)
+    pub upper_price: f64,
+    pub step_pct: f64,    // Distanza tra i livelli (es. 1.5 = 1.5%)
+    pub order_size: f64,  // Quote spesa per livello (es. 25 USDC)
+}
+
+/// Livelli (buy, sell) in progressione geometrica dentro il range
+pub fn level_prices(lower: f64, upper: f64, step_pct: f64) -> Vec<(f64, f64)> {
+    let mult = 1.0 + step_pct / 100.0;
+    let mut levels = Vec::new();
+    let mut price = lower;
+    while price * mult <= upper && levels.len() < MAX_GRID_LEVELS {
+        levels.push((price, price * mult));
+        price *= mult;
+    }
+    levels
+}
+
+/// Prezzo di 1 base espresso in quote
+pub async fn pair_price(base: &str, quote: &str) -> Option<f64> {
+    let base_usd = jupiter::get_token_market_data(base).await.ok()?.price;
+    let quote_usd = jupiter::get_token_market_data(quote).await.ok()?.price;
+    (base_usd > 0.0 && quote_usd > 0.0).then(|| base_usd / quote_usd)
+}
+
+fn to_ui(amount: i64, decimals: u8) -> f64 {
+    amount as f64 / 10f64.powi(decimals as i32)
+}
+
+/// Crea (o sostituisce) la griglia dell'utente: messaggio per l'utente oppure errore
+pub async fn start(pool: &db::DbPool, net: &Arc<network::NetworkClient>, user_id: &str, cfg: &GridConfig) -> Result<String, String> {
+    let base = Pubkey::from_str(cfg.base.trim()).map_err(|_| "Mint base non valido")?;
+    let quote = Pubkey::from_str(cfg.quote.trim()).map_err(|_| "Mint quote non valido")?;
+    if base == quote { return Err("Base e quote devono essere diversi".into()); }
+    if cfg.lower_price <= 0.0 || cfg.upper_price <= cfg.lower_price { return Err("Range non valido (0 < lower_price < upper_price)".into()); }
+    if !(0.2..=50.0).contains(&cfg.step_pct) { return Err("step_pct deve essere tra 0.2 e 50".into()); }
+    if cfg.order_size <= 0.0 { return Err("order_size deve essere positivo".into()); }
+
+    let levels = level_prices(cfg.lower_price, cfg.upper_price, cfg.step_pct);
+    if levels.len() < 2 { return Err("Range troppo stretto: servono almeno 2 livelli".into()); }
+
+    let price = pair_price(&base.to_string(), &quote.to_string()).await.ok_or("Prezzo della coppia non disponibile")?;
+    let decimals = |mint: Pubkey| async move {
+        net.rpc.get_token_supply(&mint).await.map(|s| s.decimals).map_err(|_| format!("Mint {} non trovato", mint))
+    };
+    let (base_decimals, quote_decimals) = (decimals(base).await?, decimals(quote).await?);
+
+    // Sotto il prezzo attuale si attende il calo; sopra si aspetta che il prezzo li superi prima di armarli
+    let rows: Vec<(f64, f64, &str)> = levels.iter()
+        .map(|(buy, sell)| (*buy, *sell, if *buy < price { "WAITING_BUY" } else { "IDLE" }))
+        .collect();
+
+    let grid = db::GridBot {
+        id: 0,
+        user_id: user_id.to_string(),
+        base_mint: base.to_string(),
+        quote_mint: quote.to_string(),
+        base_decimals,
+        quote_decimals,
+        lower_price: cfg.lower_price,
+        upper_price: cfg.upper_price,
+        step_pct: cfg.step_pct,
+        order_size: (cfg.order_size * 10f64.powi(quote_decimals as i32)) as u64,
+        status: "ACTIVE".into(),
+        round_trips: 0,
+        realized_profit: 0,
+    };
+    let id = db::create_grid(pool, &grid, &rows).await.map_err(|e| format!("Errore Database: {}", e))?;
+    info!("🪜 Grid #{} ({}): {} livelli {}-{} step {}%", id, user_id, rows.len(), cfg.lower_price, cfg.upper_price, cfg.step_pct);
+    Ok(format!("Grid avviata: {} livelli da {} a {} (prezzo attuale {:.6})", rows.len(), cfg.lower_price, cfg.upper_price, price))
+}
+
+pub async fn run_grid_trader(pool: db::DbPool, net: Arc<network::NetworkClient>, state: Arc<AppState>) {
+    info!("🪜 Grid Trader: ONLINE");
+    loop {
+        let grids = db::get_active_grids(&pool).await.unwrap_or_default();
+        let mut prices: HashMap<(String, String), Option<f64>> = HashMap::new();
+
+        for grid in grids {
+            if state.is_shutting_down() { break; }
+            let key = (grid.base_mint.clone(), grid.quote_mint.clone());
+            let price = match prices.get(&key) {
+                Some(p) => *p,
+                None => {
+                    let p = pair_price(&grid.base_mint, &grid.quote_mint).await;
+                    prices.insert(key, p);
+                    p
+                }
+            };
+            if let Some(price) = price { process_grid(&pool, &net, &grid, price).await; }
+        }
+        if state.sleep_or_shutdown(Duration::from_secs(20)).await { break; }
+    }
+    info!("🛑 Grid Trader fermato.");
+}
+
+async fn process_grid(pool: &db::DbPool, net: &Arc<network::NetworkClient>, grid: &db::GridBot, price: f64) {
+    let levels = db::get_grid_levels(pool, grid.id).await.unwrap_or_default();
+    let mult = 1.0 + grid.step_pct / 100.0;
+    let mut payer: Option<Keypair> = None;
+
+    for level in levels {
+        let action = match level.status.as_str() {
+            "IDLE" if price > level.buy_price => {
+                let _ = db::update_grid_level(pool, level.id, "WAITING_BUY", 0, None).await;
+                continue;
+            },
+            // Solo entro un gradino sotto il livello: dopo un crollo non si comprano tutti i livelli insieme
+            "WAITING_BUY" if price <= level.buy_price && price * mult >= level.buy_price => true,
+            "HOLDING" if price >= level.sell_price => false,
+            _ => continue,
+        };
+
+        if payer.is_none() {
+            payer = match wallet_manager::get_decrypted_wallet(pool, &grid.user_id).await {
+                Ok(kp) => Some(kp),
+                Err(e) => { warn!("⚠️ Grid #{}: wallet non disponibile: {}", grid.id, e); return; }
+            };
+        }
+        let Some(kp) = payer.as_ref() else { return };
+
+        let res = if action { buy_level(pool, net, grid, &level, kp, price).await } else { sell_level(pool, net, grid, &level, kp).await };
+        if let Err(e) = res {
+            warn!("⚠️ Grid #{} livello {:.6}: {}", grid.id, level.buy_price, e);
+        }
+    }
+}
+
+// Saldo spendibile di un mint (SOL nativo al netto della riserva fee, USDC al netto della quota Auto-Park)
+async fn spendable(pool: &db::DbPool, net: &Arc<network::NetworkClient>, grid: &db::GridBot, owner: &Pubkey, mint: &str) -> u64 {
+    if mint == SOL_MINT {
+        return net.get_balance_fast(owner).await.saturating_sub(FEE_RESERVE_LAMPORTS);
+    }
+    let balance = match Pubkey::from_str(mint) {
+        Ok(m) => net.get_token_balance(owner, &m).await.unwrap_or(0),
+        Err(_) => 0,
+    };
+    if mint == auto_park::USDC_MINT { auto_park::spendable_usdc(pool, &grid.user_id, balance).await } else { balance }
+}
+
+async fn buy_level(pool: &db::DbPool, net: &Arc<network::NetworkClient>, grid: &db::GridBot, level: &db::GridLevel, payer: &Keypair, price: f64) -> Result<(), String> {
+    if spendable(pool, net, grid, &payer.pubkey(), &grid.quote_mint).await < grid.order_size {
+        return Err("Saldo quote insufficiente".into());
+    }
+    let slippage = db::get_strategy_params(pool, &grid.user_id).await.slippage_bps;
+    let out = swap_router::swap_pair(net, pool, &grid.user_id, payer, (&grid.quote_mint, &grid.base_mint), grid.order_size, slippage).await
+        .map_err(|e| e.to_string())?;
+
+    // Senza quote (Raydium diretto) la quantità si stima dal prezzo
+    let base_amount = if out.expected_out > 0 {
+        out.expected_out
+    } else {
+        (to_ui(grid.order_size as i64, grid.quote_decimals) / price * 10f64.powi(grid.base_decimals as i32)) as u64
+    };
+    db::update_grid_level(pool, level.id, "HOLDING", base_amount, Some(&out.signature)).await.map_err(|e| e.to_string())?;
+
+    info!("🪜 Grid #{} BUY @ {:.6} via {}: {}", grid.id, price, out.route.label(), out.signature);
+    telegram_bot::notify_user(&grid.user_id, format!(
+        "🪜 <b>GRID: ACQUISTO</b>\n\nLivello {:.6} (rivendita a {:.6})\n🔗 <a href=\"https://solscan.io/tx/{}\">Vedi su Solscan</a>",
+        level.buy_price, level.sell_price, out.signature
+    )).await;
+    Ok(())
+}
+
+async fn sell_level(pool: &db::DbPool, net: &Arc<network::NetworkClient>, grid: &db::GridBot, level: &db::GridLevel, payer: &Keypair) -> Result<(), String> {
+    // Mai più di quanto effettivamente in wallet (fill reale sotto la quote, vendite manuali)
+    let amount = level.base_amount.min(spendable(pool, net, grid, &payer.pubkey(), &grid.base_mint).await);
+    if amount == 0 { return Err("Saldo base insufficiente".into()); }
+    let slippage = db::get_strategy_params(pool, &grid.user_id).await.slippage_bps;
+    let out = swap_router::swap_pair(net, pool, &grid.user_id, payer, (&grid.base_mint, &grid.quote_mint), amount, slippage).await
+        .map_err(|e| e.to_string())?;
+
+    let profit = out.expected_out as i64 - grid.order_size as i64;
+    db::finish_grid_round(pool, grid.id, level.id, &out.signature, profit).await.map_err(|e| e.to_string())?;
+
+    info!("🪜 Grid #{} SELL @ {:.6} via {} (profitto {}): {}", grid.id, level.sell_price, out.route.label(), profit, out.signature);
+    telegram_bot::notify_user(&grid.user_id, format!(
+        "🪜 <b>GRID: VENDITA</b>\n\nLivello {:.6} -> {:.6}\nProfitto giro: <b>{:+.4}</b>\n🔗 <a href=\"https://solscan.io/tx/{}\">Vedi su Solscan</a>",
+        level.buy_price, level.sell_price, to_ui(profit, grid.quote_decimals), out.signature
+    )).await;
+    Ok(())
+}
+
+/// Stato della griglia per API (profitto e ordine in unità "umane" della quote)
+pub fn summary(grid: &db::GridBot, levels: &[db::GridLevel]) -> serde_json::Value {
+    serde_json::json!({
+        "grid": grid,
+        "levels": levels,
+        "realized_profit_ui": to_ui(grid.realized_profit, grid.quote_decimals),
+        "order_size_ui": to_ui(grid.order_size as i64, grid.quote_decimals),
+    })
+}
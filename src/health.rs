@@ -0,0 +1,98 @@
+use std::future::Future;
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
+use std::time::Instant;
+use log::{info, warn};
+use serde::Serialize;
+use tokio::time::{timeout, Duration};
+use crate::{db, jupiter, network, price_oracle, AppState};
+
+// --- HEALTH CHECK (Dipendenze esterne per /health) ---
+// 1. Ogni HEALTH_SECS si sondano RPC Solana, WebSocket dello Sniper, Jupiter, DexScreener e DB (timeout PROBE_TIMEOUT_SECS)
+// 2. Il risultato resta in cache (AppState.health): /health non genera traffico verso l'esterno
+// 3. Critiche (DB, RPC) giù = "down" e /health risponde 503; le altre giù = "degraded" con 200
+
+const HEALTH_SECS: u64 = 30;
+const PROBE_TIMEOUT_SECS: u64 = 5;
+const USDC_MINT: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+
+#[derive(Serialize, Clone, Debug)]
+pub struct DependencyStatus {
+    pub name: &'static str,
+    pub healthy: bool,
+    pub critical: bool,
+    pub latency_ms: u64,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct HealthReport {
+    // ok | degraded | down
+    pub status: &'static str,
+    pub checked_at: i64,
+    pub dependencies: Vec<DependencyStatus>,
+}
+
+impl HealthReport {
+    fn from_checks(dependencies: Vec<DependencyStatus>) -> Self {
+        let status = if dependencies.iter().any(|d| d.critical && !d.healthy) {
+            "down"
+        } else if dependencies.iter().any(|d| !d.healthy) {
+            "degraded"
+        } else {
+            "ok"
+        };
+        Self { status, checked_at: chrono::Utc::now().timestamp(), dependencies }
+    }
+
+    pub fn is_down(&self) -> bool {
+        self.status == "down"
+    }
+}
+
+pub async fn run_health_checks(pool: db::DbPool, net: Arc<network::NetworkClient>, state: Arc<AppState>) {
+    info!("🩺 Health Check: ONLINE");
+    let mut last_status = "";
+
+    loop {
+        let report = check_all(&pool, &net, &state).await;
+        if report.status != last_status {
+            let failing: Vec<&str> = report.dependencies.iter().filter(|d| !d.healthy).map(|d| d.name).collect();
+            match report.status {
+                "ok" => info!("🩺 Dipendenze OK"),
+                status => warn!("🩺 Stato {}: {} non raggiungibili", status, failing.join(", ")),
+            }
+            last_status = report.status;
+        }
+        *state.health.write().await = Some(report);
+        if state.sleep_or_shutdown(Duration::from_secs(HEALTH_SECS)).await { break; }
+    }
+    info!("🛑 Health Check fermato.");
+}
+
+async fn check_all(pool: &db::DbPool, net: &Arc<network::NetworkClient>, state: &Arc<AppState>) -> HealthReport {
+    let ws_connected = state.sniper_ws_connected.load(Ordering::Relaxed);
+    let (db_check, rpc, jup, dex) = tokio::join!(
+        probe("db", true, async { db::ping(pool).await.map_err(|e| e.to_string()) }),
+        probe("solana_rpc", true, async { net.rpc.get_slot().await.map(|_| ()).map_err(|e| e.to_string()) }),
+        probe("jupiter", false, async { jupiter::get_quote(price_oracle::SOL_MINT, USDC_MINT, 10_000_000, 50).await.map(|_| ()).map_err(|e| e.to_string()) }),
+        probe("dexscreener", false, async { jupiter::probe_dexscreener(price_oracle::SOL_MINT).await.map_err(|e| e.to_string()) }),
+    );
+    let ws = DependencyStatus {
+        name: "solana_ws",
+        healthy: ws_connected,
+        critical: false,
+        latency_ms: 0,
+        error: (!ws_connected).then(|| "Sottoscrizione Sniper non attiva".to_string()),
+    };
+    HealthReport::from_checks(vec![db_check, rpc, ws, jup, dex])
+}
+
+async fn probe(name: &'static str, critical: bool, check: impl Future<Output = Result<(), String>>) -> DependencyStatus {
+    let started = Instant::now();
+    let res = match timeout(Duration::from_secs(PROBE_TIMEOUT_SECS), check).await {
+        Ok(r) => r,
+        Err(_) => Err(format!("Timeout ({}s)", PROBE_TIMEOUT_SECS)),
+    };
+    DependencyStatus { name, healthy: res.is_ok(), critical, latency_ms: started.elapsed().as_millis() as u64, error: res.err() }
+}
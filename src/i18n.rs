@@ -0,0 +1,455 @@
+use std::fmt::Display;
+use crate::db;
+
+// --- LINGUE (Messaggi per utente: Telegram, report, webhook, errori API) ---
+// 1. Lingua dell'utente in users.settings["language"] (/language su Telegram, POST /language dall'App)
+// 2. Al primo /start si usa la lingua del client Telegram, se supportata
+// 3. Senza scelta: DEFAULT_LANGUAGE dal .env (default it); le rotte /auth senza utente usano Accept-Language
+// I testi stanno in MESSAGES come chiave -> (italiano, inglese); i segnaposto {} si riempiono in ordine.
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Lang {
+    It,
+    En,
+}
+
+pub const LANGUAGES: [&str; 2] = ["it", "en"];
+
+impl Lang {
+    /// "en", "en-US", "EN" -> En. None se non supportata.
+    pub fn from_code(code: &str) -> Option<Lang> {
+        match code.trim().to_lowercase().split(['-', '_']).next()? {
+            "it" => Some(Lang::It),
+            "en" => Some(Lang::En),
+            _ => None,
+        }
+    }
+
+    pub fn code(self) -> &'static str {
+        match self {
+            Lang::It => "it",
+            Lang::En => "en",
+        }
+    }
+}
+
+/// Lingua di deployment per chi non ha scelto (e per le risposte API senza utente)
+pub fn default_lang() -> Lang {
+    std::env::var("DEFAULT_LANGUAGE").ok().and_then(|c| Lang::from_code(&c)).unwrap_or(Lang::It)
+}
+
+pub fn lang_from_settings(settings: &serde_json::Value) -> Lang {
+    settings["language"].as_str().and_then(Lang::from_code).unwrap_or_else(default_lang)
+}
+
+/// Lingua scelta dall'utente (default se non impostata o in caso di errore DB)
+pub async fn user_lang(pool: &db::DbPool, user_id: &str) -> Lang {
+    match db::get_settings(pool, user_id).await {
+        Ok(settings) => lang_from_settings(&settings),
+        Err(_) => default_lang(),
+    }
+}
+
+/// Lingua di una richiesta senza utente (endpoint /auth): prima lingua di Accept-Language, altrimenti default
+pub fn request_lang(accept_language: Option<&str>) -> Lang {
+    accept_language
+        .and_then(|h| h.split(',').next())
+        .and_then(|tag| Lang::from_code(tag.split(';').next().unwrap_or(tag)))
+        .unwrap_or_else(default_lang)
+}
+
+pub async fn set_user_lang(pool: &db::DbPool, user_id: &str, lang: Lang) -> Result<(), sqlx::Error> {
+    db::update_setting(pool, user_id, "language", serde_json::json!(lang.code())).await
+}
+
+/// Primo contatto da Telegram: adotta la lingua del client solo se l'utente non ne ha scelta una
+pub async fn init_user_lang(pool: &db::DbPool, user_id: &str, client_code: Option<&str>) {
+    let lang = match client_code.and_then(Lang::from_code) { Some(l) => l, None => return };
+    if let Ok(settings) = db::get_settings(pool, user_id).await {
+        if settings["language"].is_null() { let _ = set_user_lang(pool, user_id, lang).await; }
+    }
+}
+
+/// Testo della chiave nella lingua (la chiave stessa se manca: si nota subito nei messaggi)
+pub fn t(lang: Lang, key: &str) -> &str {
+    match MESSAGES.iter().find(|(k, _, _)| *k == key) {
+        Some((_, it, en)) => if lang == Lang::En { en } else { it },
+        None => key,
+    }
+}
+
+/// Come t, con i segnaposto {} sostituiti in ordine dagli argomenti
+pub fn tr(lang: Lang, key: &str, args: &[&(dyn Display + Sync)]) -> String {
+    let mut out = String::new();
+    let mut args = args.iter();
+    let mut parts = t(lang, key).split("{}").peekable();
+    while let Some(part) = parts.next() {
+        out.push_str(part);
+        if parts.peek().is_some() {
+            if let Some(a) = args.next() { out.push_str(&a.to_string()); }
+        }
+    }
+    out
+}
+
+// (chiave, italiano, inglese)
+const MESSAGES: &[(&str, &str, &str)] = &[
+    // --- Errori API ---
+    ("api.db_error", "Errore Database", "Database error"),
+    ("api.rate_limited", "Troppe richieste, riprova tra poco", "Too many requests, try again shortly"),
+    ("api.banned", "Account sospeso", "Account suspended"),
+    ("api.unauthorized", "Sessione non valida o scaduta", "Invalid or expired session"),
+    ("api.invalid_token", "Indirizzo Token Invalido", "Invalid token address"),
+    ("api.invalid_wallet", "Indirizzo Wallet Invalido", "Invalid wallet address"),
+    ("api.invalid_mint", "Mint non valido", "Invalid mint"),
+    ("api.email_already_verified", "Email già verificata", "Email already verified"),
+    ("api.verification_sent", "Link di verifica inviato", "Verification link sent"),
+    ("api.no_web_account", "Nessun account Web (email e password) collegato", "No Web account (email and password) linked"),
+    ("api.invalid_credentials_format", "Email non valida o password troppo corta (min 8 caratteri)", "Invalid email or password too short (min 8 characters)"),
+    ("api.register_telegram_only", "Registrazione possibile solo dall'app Telegram (initData non valido)", "Registration is only possible from the Telegram app (invalid initData)"),
+    ("api.email_taken", "Email già registrata", "Email already registered"),
+    ("api.already_registered", "Account già registrato, effettua il login", "Account already registered, please log in"),
+    ("api.invalid_credentials", "Credenziali non valide", "Invalid credentials"),
+    ("api.invalid_action_auth", "Azione non valida (REGISTER/LOGIN)", "Invalid action (REGISTER/LOGIN)"),
+    ("api.internal_error", "Errore interno", "Internal error"),
+    ("api.authenticated", "Autenticato", "Authenticated"),
+    ("api.email_verified", "Email verificata", "Email verified"),
+    ("api.invalid_link", "Link non valido o scaduto", "Invalid or expired link"),
+    ("api.reset_requested", "Se l'email è registrata riceverai un link per reimpostare la password", "If the email is registered you will receive a link to reset your password"),
+    ("api.password_too_short", "Password troppo corta (min 8 caratteri)", "Password too short (min 8 characters)"),
+    ("api.password_updated", "Password aggiornata, effettua il login", "Password updated, please log in"),
+    ("api.account_not_found", "Account non trovato", "Account not found"),
+    ("api.session_refreshed", "Sessione rinnovata", "Session renewed"),
+    ("api.logged_out", "Logout eseguito", "Logged out"),
+    ("email.verify.subject", "Conferma la tua email", "Confirm your email"),
+    ("email.verify.body",
+        "Ciao!\n\nConferma il tuo indirizzo email aprendo questo link:\n{}\n\nIl link scade tra {} ore. Se non hai creato tu l'account, ignora questa email.",
+        "Hi!\n\nConfirm your email address by opening this link:\n{}\n\nThe link expires in {} hours. If you didn't create the account, ignore this email."),
+    ("email.reset.subject", "Reimposta la password", "Reset your password"),
+    ("email.reset.body",
+        "Ciao!\n\nPer scegliere una nuova password apri questo link:\n{}\n\nIl link scade tra {} minuti e funziona una sola volta. Tutte le sessioni aperte verranno chiuse.\nSe non hai chiesto tu il reset, ignora questa email: la password resta invariata.",
+        "Hi!\n\nTo choose a new password open this link:\n{}\n\nThe link expires in {} minutes and works only once. All open sessions will be closed.\nIf you didn't request the reset, ignore this email: your password stays the same."),
+    ("api.session_revoked", "Dispositivo disconnesso", "Device signed out"),
+    ("api.session_not_found", "Sessione non trovata", "Session not found"),
+    ("api.analysis_unavailable", "Nessuna candela disponibile per questo token", "No candles available for this token"),
+    ("api.invalid_gem_query", "Filtri non validi (sort: newest/volume/score/change_24h, source: SNIPER/DISCOVERY/TOP, min_liquidity >= 0)", "Invalid filters (sort: newest/volume/score/change_24h, source: SNIPER/DISCOVERY/TOP, min_liquidity >= 0)"),
+    ("api.invalid_action_add_remove", "Azione non valida (ADD/REMOVE)", "Invalid action (ADD/REMOVE)"),
+    ("api.quote_failed", "Quote non disponibile, riprova tra poco", "Quote unavailable, try again shortly"),
+    ("api.sign_in_wallet", "Firma la transazione nel tuo wallet ({})", "Sign the transaction in your wallet ({})"),
+    ("api.watchlist_added", "Token aggiunto alla Watchlist", "Token added to the watchlist"),
+    ("api.watchlist_exists", "Token già in Watchlist", "Token already in the watchlist"),
+    ("api.watchlist_removed", "Token rimosso dalla Watchlist", "Token removed from the watchlist"),
+    ("api.watchlist_missing", "Token non presente in Watchlist", "Token not in the watchlist"),
+    ("api.whitelist_only_on", "Modalità Solo-Whitelist ATTIVA", "Whitelist-only mode ON"),
+    ("api.whitelist_only_off", "Modalità Solo-Whitelist DISATTIVATA", "Whitelist-only mode OFF"),
+    ("api.webhook_limit", "Massimo {} webhook per utente", "At most {} webhooks per user"),
+    ("api.webhook_added", "Webhook {} aggiunto", "{} webhook added"),
+    ("api.webhook_exists", "Webhook già registrato", "Webhook already registered"),
+    ("api.webhook_removed", "Webhook rimosso", "Webhook removed"),
+    ("api.webhook_not_found", "Webhook non trovato", "Webhook not found"),
+    ("api.webhook_test_ok", "Notifica di prova consegnata", "Test notification delivered"),
+    ("api.webhook_test_failed", "Consegna fallita: {}", "Delivery failed: {}"),
+    ("api.invalid_action_webhook", "Azione non valida (ADD url | REMOVE id | TEST id)", "Invalid action (ADD url | REMOVE id | TEST id)"),
+    ("api.bot_stopped", "Auto-Bot fermato", "Auto-Bot stopped"),
+    ("api.bot_and_grid_stopped", "Auto-Bot e Grid fermati (i token dei livelli acquistati restano nel wallet)", "Auto-Bot and Grid stopped (tokens from filled levels stay in the wallet)"),
+    ("api.rent_reclaimed", ". Rent recuperato: {} SOL", ". Rent reclaimed: {} SOL"),
+    ("api.invalid_action_admin", "Azione non valida (stop, ban, unban)", "Invalid action (stop, ban, unban)"),
+    ("api.user_not_found", "Utente non trovato", "User not found"),
+    ("api.admin_action_done", "{}: {} eseguito", "{}: {} done"),
+    ("tg.admin_stopped",
+        "🛑 <b>Auto-Bot fermato dall'amministratore.</b>\nPosizioni e fondi restano nel tuo wallet.",
+        "🛑 <b>Auto-Bot stopped by the administrator.</b>\nPositions and funds stay in your wallet."),
+    ("tg.admin_banned",
+        "⛔ <b>Account sospeso dall'amministratore.</b>\nAuto-Bot fermato, accesso a Bot e App disattivato.",
+        "⛔ <b>Account suspended by the administrator.</b>\nAuto-Bot stopped, access to Bot and App disabled."),
+    ("tg.admin_unbanned", "✅ <b>Account riattivato.</b> Puoi di nuovo usare Bot e App.", "✅ <b>Account reactivated.</b> You can use Bot and App again."),
+    ("api.invalid_action_trade", "Azione non valida (BUY/SELL)", "Invalid action (BUY/SELL)"),
+    ("api.invalid_action_dca", "Azione non valida (pause/resume/cancel)", "Invalid action (pause/resume/cancel)"),
+    ("api.invalid_list", "Lista non valida (BLACKLIST/WHITELIST)", "Invalid list (BLACKLIST/WHITELIST)"),
+    ("api.invalid_amount", "Importo non valido", "Invalid amount"),
+    ("api.invalid_convert", "Conversione non valida: due mint diversi e mode exactIn o exactOut", "Invalid conversion: two different mints and mode exactIn or exactOut"),
+    ("api.convert_max_in", "Input massimo della quote {} oltre il limite max_in {}", "Quote maximum input {} exceeds max_in limit {}"),
+    ("api.convert_insufficient", "Saldo insufficiente: servono fino a {} unità, disponibili {}", "Insufficient balance: up to {} units needed, {} available"),
+    ("api.convert_custodial_only", "Conversione disponibile solo con il wallet custodito", "Conversion is only available with the custodial wallet"),
+    ("api.invalid_sell_size", "Quantità di vendita non valida: percent (1-100) oppure token_amount entro il saldo", "Invalid sell size: percent (1-100) or token_amount within the balance"),
+    ("api.invalid_dca", "Parametri DCA non validi (min 0.01 SOL, intervallo 1h-30g)", "Invalid DCA parameters (min 0.01 SOL, interval 1h-30d)"),
+    ("api.invalid_limit", "Parametri Ordine Limite non validi", "Invalid limit order parameters"),
+    ("api.invalid_backtest", "Parametri Backtest non validi", "Invalid backtest parameters"),
+    ("api.invalid_alerts", "Livello notifiche non valido (disponibili: {})", "Invalid alert level (available: {})"),
+    ("api.alerts_set", "Notifiche trade: {}", "Trade alerts: {}"),
+    ("api.invalid_park", "Percentuale Auto-Park non valida (1-100)", "Invalid Auto-Park percentage (1-100)"),
+    ("api.invalid_iban", "IBAN non valido", "Invalid IBAN"),
+    ("api.invalid_beneficiary", "Intestatario del conto mancante", "Missing account holder name"),
+    ("api.invalid_offramp_amount", "Importo del bonifico non valido ({}-{} EUR)", "Invalid transfer amount ({}-{} EUR)"),
+    ("api.offramp_disabled", "Bonifici SEPA non disponibili su questo server", "SEPA transfers are not available on this server"),
+    ("api.invalid_skim", "Auto-Skim non valido: period weekly o monthly, floor_sol >= 0 e un indirizzo della whitelist", "Invalid Auto-Skim: period weekly or monthly, floor_sol >= 0 and a whitelisted address"),
+    ("api.invalid_language", "Lingua non supportata (disponibili: {})", "Unsupported language (available: {})"),
+    ("api.order_not_found", "Ordine non trovato o già eseguito", "Order not found or already executed"),
+    ("api.dca_not_found", "Ordine DCA non trovato", "DCA order not found"),
+    ("api.no_grid", "Nessuna griglia", "No grid"),
+    ("api.no_external_wallet", "Nessun wallet esterno collegato", "No external wallet linked"),
+    ("api.token_not_in_wallet", "Token non trovato nel wallet", "Token not found in wallet"),
+    ("api.generic_error", "Errore generico", "Generic error"),
+    ("api.language_set", "Lingua impostata: {}", "Language set: {}"),
+    ("api.tx_sent", "Transazione Inviata!", "Transaction sent!"),
+    ("api.tx_send_failed", "Invio Fallito: {}", "Send failed: {}"),
+    ("api.withdraw_sol_only", "Per sicurezza, preleva solo SOL. Converti gli altri token prima.", "For safety, only SOL can be withdrawn. Convert other tokens first."),
+    ("api.withdraw_sent", "Prelievo Inviato!", "Withdrawal sent!"),
+    ("api.withdraw_awaiting", "Prelievo #{} in attesa di approvazione su Telegram ({} min)", "Withdrawal #{} awaiting approval on Telegram ({} min)"),
+    ("api.address_removed", "Indirizzo rimosso dalla whitelist", "Address removed from the whitelist"),
+    ("api.address_not_whitelisted", "Indirizzo non in whitelist", "Address not in the whitelist"),
+    ("api.referral_sent", "Ricompense Referral Inviate!", "Referral rewards sent!"),
+    ("api.strategy_updated", "Parametri Strategia Aggiornati", "Strategy parameters updated"),
+
+    // --- Prelievi (whitelist indirizzi + approvazione Telegram) ---
+    ("withdraw.invalid_address", "Indirizzo non valido", "Invalid address"),
+    ("withdraw.own_wallet", "Non puoi prelevare verso il wallet del bot", "You can't withdraw to the bot's own wallet"),
+    ("withdraw.max_addresses", "Massimo {} indirizzi in whitelist", "At most {} whitelisted addresses"),
+    ("withdraw.address_exists", "Indirizzo già in whitelist", "Address already whitelisted"),
+    ("withdraw.confirm_button", "✅ Conferma", "✅ Confirm"),
+    ("withdraw.approve_button", "✅ Approva", "✅ Approve"),
+    ("withdraw.reject_button", "❌ Rifiuta", "❌ Reject"),
+    ("withdraw.address_request",
+        "🔐 <b>Nuovo indirizzo di prelievo</b>\n\n<code>{}</code>{}\n\nSe non sei stato tu, premi <b>Rifiuta</b>.\nDopo la conferma sarà utilizzabile tra {}h.",
+        "🔐 <b>New withdrawal address</b>\n\n<code>{}</code>{}\n\nIf this wasn't you, tap <b>Reject</b>.\nAfter confirmation it will be usable in {}h."),
+    ("withdraw.address_telegram_required", "Serve un account Telegram collegato per confermare l'indirizzo", "A linked Telegram account is required to confirm the address"),
+    ("withdraw.address_confirm_pending", "Conferma l'indirizzo dal messaggio Telegram", "Confirm the address from the Telegram message"),
+    ("withdraw.address_confirmed", "✅ Indirizzo confermato: utilizzabile tra {}h", "✅ Address confirmed: usable in {}h"),
+    ("withdraw.address_rejected", "🗑 Indirizzo rifiutato e rimosso", "🗑 Address rejected and removed"),
+    ("withdraw.request_not_found", "Richiesta già gestita o non trovata", "Request already handled or not found"),
+    ("withdraw.not_whitelisted", "Indirizzo non in whitelist: aggiungilo e confermalo da Telegram", "Address not whitelisted: add it and confirm it from Telegram"),
+    ("withdraw.not_confirmed", "Indirizzo non ancora confermato da Telegram", "Address not yet confirmed from Telegram"),
+    ("withdraw.cooldown", "Indirizzo in periodo di attesa: utilizzabile tra {}h {}m", "Address in its waiting period: usable in {}h {}m"),
+    ("withdraw.wallet_error", "Errore Wallet", "Wallet error"),
+    ("withdraw.insufficient_funds", "Fondi Insufficienti (Lascia 0.005 SOL per le fee)", "Insufficient funds (leave 0.005 SOL for fees)"),
+    ("withdraw.invalid_amount", "Importo non valido", "Invalid amount"),
+    ("withdraw.approval_request",
+        "🛡 <b>Approva il prelievo #{}</b>\n\n<b>{} SOL</b> verso\n<code>{}</code>\n\nSenza risposta viene annullato tra {} minuti.",
+        "🛡 <b>Approve withdrawal #{}</b>\n\n<b>{} SOL</b> to\n<code>{}</code>\n\nWithout an answer it is cancelled in {} minutes."),
+    ("withdraw.approval_telegram_required", "Serve un account Telegram collegato per approvare i prelievi grandi", "A linked Telegram account is required to approve large withdrawals"),
+    ("withdraw.rejected", "🗑 Prelievo #{} rifiutato", "🗑 Withdrawal #{} rejected"),
+    ("withdraw.expired", "Richiesta scaduta o già gestita", "Request expired or already handled"),
+    ("withdraw.sent", "✅ Prelievo #{} inviato: {} SOL\n🔗 https://solscan.io/tx/{}", "✅ Withdrawal #{} sent: {} SOL\n🔗 https://solscan.io/tx/{}"),
+    ("withdraw.transfer_failed", "Indirizzo Invalido o Errore Rete", "Invalid address or network error"),
+    // --- Off-ramp SEPA ---
+    ("offramp.approve_button", "✅ Approva", "✅ Approve"),
+    ("offramp.reject_button", "❌ Rifiuta", "❌ Reject"),
+    ("offramp.approval_request",
+        "🏦 <b>Approva il bonifico #{}</b>\n\n<b>{} EUR</b> (circa {} SOL) a\n{}\n<code>{}</code>\n\nSenza risposta viene annullato tra {} minuti.",
+        "🏦 <b>Approve bank transfer #{}</b>\n\n<b>{} EUR</b> (about {} SOL) to\n{}\n<code>{}</code>\n\nWithout a reply it is cancelled in {} minutes."),
+    ("offramp.telegram_required", "Serve un account Telegram collegato per approvare i bonifici", "A linked Telegram account is required to approve bank transfers"),
+    ("offramp.pending", "Bonifico #{} in attesa di approvazione su Telegram ({} min)", "Bank transfer #{} awaiting approval on Telegram ({} min)"),
+    ("offramp.insufficient_funds", "Fondi Insufficienti: servono {} SOL, disponibili {}", "Insufficient funds: {} SOL needed, {} available"),
+    ("offramp.rejected", "🗑 Bonifico #{} rifiutato", "🗑 Bank transfer #{} rejected"),
+    ("offramp.expired", "Richiesta scaduta o già gestita", "Request expired or already handled"),
+    ("offramp.approved", "✅ Bonifico #{} approvato: conversione in EURC in corso", "✅ Bank transfer #{} approved: converting to EURC"),
+    // --- Referral ---
+    ("referral.not_registered", "Utente non registrato", "User not registered"),
+    ("referral.code_failed", "Impossibile generare il codice invito", "Unable to generate the invite code"),
+    ("referral.invalid_code", "Codice invito non valido", "Invalid invite code"),
+    ("referral.own_code", "Non puoi usare il tuo stesso codice", "You can't use your own code"),
+    ("referral.linked", "Codice invito applicato!", "Invite code applied!"),
+    ("referral.already_linked", "Hai già un referrer", "You already have a referrer"),
+    ("referral.payouts_disabled", "Prelievi referral non attivi", "Referral payouts are not enabled"),
+    ("referral.min_payout", "Minimo prelevabile: {} SOL", "Minimum payout: {} SOL"),
+    ("referral.network_error", "Errore Rete: riprova più tardi", "Network error: try again later"),
+
+    // --- Limiti di acquisto (risk::BuyBudget) ---
+    ("risk.over_per_trade", "Importo oltre il limite per trade ({} SOL, max_per_trade_sol)", "Amount above the per-trade limit ({} SOL, max_per_trade_sol)"),
+    ("risk.exposure_reached",
+        "Esposizione massima raggiunta: disponibili {} SOL oltre il valore bloccato nelle posizioni aperte (max_total_exposure_sol)",
+        "Maximum exposure reached: {} SOL available beyond the value locked in open positions (max_total_exposure_sol)"),
+    ("risk.insufficient_funds", "Fondi Insufficienti: spendibili {} SOL oltre la riserva (reserve_sol)", "Insufficient funds: {} SOL spendable beyond the reserve (reserve_sol)"),
+
+    // --- Telegram: avvio, onboarding, lingua ---
+    ("tg.banned", "⛔ Account sospeso. Contatta il supporto.", "⛔ Account suspended. Contact support."),
+    ("tg.welcome",
+        "💎 <b>GOD SNIPER WALLET</b>\n\nIl tuo terminale di trading istituzionale è pronto.\n\n🔑 <b>Address:</b> <code>{}</code>\n🟢 <b>Stato Sistema:</b> ONLINE\n🤖 <b>Modalità:</b> Ibrida (App + Bot Automatico)\n\nClicca sotto per iniziare.",
+        "💎 <b>GOD SNIPER WALLET</b>\n\nYour institutional trading terminal is ready.\n\n🔑 <b>Address:</b> <code>{}</code>\n🟢 <b>System Status:</b> ONLINE\n🤖 <b>Mode:</b> Hybrid (App + Auto Bot)\n\nTap below to get started."),
+    ("tg.wallet_error", "❌ Errore Critico Creazione Wallet: {}", "❌ Critical error creating wallet: {}"),
+    ("tg.deposit_address",
+        "📥 <b>Indirizzo di Deposito</b>\n\n<code>{}</code>\n\nInvia SOL a questo indirizzo (solo rete Solana).",
+        "📥 <b>Deposit Address</b>\n\n<code>{}</code>\n\nSend SOL to this address (Solana network only)."),
+    ("tg.disclaimer",
+        "⚠️ <b>AVVISO SUI RISCHI</b>\n\n• Il trading di token (in particolare memecoin e nuovi lanci) è <b>altamente speculativo</b>: puoi perdere tutto il capitale investito.\n• L'Auto-Bot opera in autonomia con le regole che scegli: nessun risultato è garantito e le perdite passate non limitano quelle future.\n• Rug pull, honeypot e slippage possono causare perdite anche con i controlli di sicurezza attivi.\n• Il wallet è custodito dal bot: esporta la chiave (/export) e investi solo ciò che puoi permetterti di perdere.\n\nPer usare l'Auto-Bot devi accettare questi rischi.",
+        "⚠️ <b>RISK DISCLAIMER</b>\n\n• Trading tokens (especially memecoins and new launches) is <b>highly speculative</b>: you can lose all the capital you invest.\n• The Auto-Bot trades on its own with the rules you choose: no result is guaranteed and past losses do not cap future ones.\n• Rug pulls, honeypots and slippage can cause losses even with safety checks enabled.\n• The wallet is held by the bot: export the key (/export) and only invest what you can afford to lose.\n\nYou must accept these risks to use the Auto-Bot."),
+    ("tg.disclaimer_accept", "✅ Ho capito e accetto i rischi", "✅ I understand and accept the risks"),
+    ("tg.disclaimer_accepted", "✅ Disclaimer accettato", "✅ Disclaimer accepted"),
+    ("tg.onboarding_strategy",
+        "🧠 <b>1/2 Scegli la strategia dell'Auto-Bot</b>\n\n• <b>smart</b>: dip con volume/whale\n• <b>momentum</b>: breakout con volume\n• <b>mean_reversion</b>: ipervenduto, uscita sulla media\n\n<i>Puoi cambiarla in ogni momento.</i>",
+        "🧠 <b>1/2 Choose the Auto-Bot strategy</b>\n\n• <b>smart</b>: dips with volume/whales\n• <b>momentum</b>: breakouts with volume\n• <b>mean_reversion</b>: oversold, exit at the mean\n\n<i>You can change it at any time.</i>"),
+    ("tg.onboarding_max",
+        "💰 <b>2/2 Importo massimo per trade</b>\n\nNessun acquisto (automatico o manuale) supererà questa cifra.\n<i>Modificabile con /strategy max_per_trade_sol VALORE</i>",
+        "💰 <b>2/2 Maximum amount per trade</b>\n\nNo buy (automatic or manual) will exceed this amount.\n<i>Change it with /strategy max_per_trade_sol VALUE</i>"),
+    ("tg.onboarding_done",
+        "✅ <b>Configurazione completata!</b>\nStrategia: <b>{}</b> | Max per trade: <b>{} SOL</b>\n\n{}",
+        "✅ <b>Setup complete!</b>\nStrategy: <b>{}</b> | Max per trade: <b>{} SOL</b>\n\n{}"),
+    ("tg.onboarding_disclaimer_first", "⚠️ Accetta prima il disclaimer sui rischi: /start", "⚠️ Accept the risk disclaimer first: /start"),
+    ("tg.language_current", "🌐 Lingua attuale: <b>{}</b>\n\n<i>Cambia con /language {}</i>", "🌐 Current language: <b>{}</b>\n\n<i>Change it with /language {}</i>"),
+    ("tg.language_set", "✅ Lingua impostata: <b>{}</b>", "✅ Language set: <b>{}</b>"),
+    ("tg.alerts_current", "🔔 Notifiche trade: <b>{}</b>\n\n<i>Cambia con /alerts {}</i>", "🔔 Trade alerts: <b>{}</b>\n\n<i>Change with /alerts {}</i>"),
+    ("tg.alerts_set", "✅ Notifiche trade: <b>{}</b>", "✅ Trade alerts: <b>{}</b>"),
+    ("tg.alerts_invalid", "❌ Livello non valido. Disponibili: {}", "❌ Invalid level. Available: {}"),
+    ("tg.note_usage", "⚠️ Uso: /note ID TESTO | /note ID off", "⚠️ Usage: /note ID TEXT | /note ID off"),
+    ("tg.note_saved", "📝 Nota salvata sul trade #{}", "📝 Note saved on trade #{}"),
+    ("tg.note_cleared", "📝 Nota rimossa dal trade #{}", "📝 Note removed from trade #{}"),
+    ("tg.tags_usage", "⚠️ Uso: /tag ID tag1, tag2 (es. /tag 42 sniped from tg group, long term) | /tag ID off", "⚠️ Usage: /tag ID tag1, tag2 (e.g. /tag 42 sniped from tg group, long term) | /tag ID off"),
+    ("tg.tags_saved", "🏷 Tag del trade #{}: <b>{}</b>", "🏷 Tags of trade #{}: <b>{}</b>"),
+    ("tg.tags_cleared", "🏷 Tag rimossi dal trade #{}", "🏷 Tags removed from trade #{}"),
+    ("tg.park_status",
+        "🅿️ Auto-Park: <b>{}</b> ({}% del ricavato delle vendite in profitto)\nParcheggiati: {} SOL → <b>{} USDC</b> (esclusi dal trading)",
+        "🅿️ Auto-Park: <b>{}</b> ({}% of profitable sell proceeds)\nParked: {} SOL → <b>{} USDC</b> (excluded from trading)"),
+    ("tg.park_usage", "<i>Cambia con /park PERCENTUALE | /park off | /park release (sblocca gli USDC parcheggiati)</i>", "<i>Change with /park PERCENT | /park off | /park release (unlocks the parked USDC)</i>"),
+    ("tg.park_invalid", "❌ Percentuale non valida (1-100).", "❌ Invalid percentage (1-100)."),
+    ("tg.skim_status",
+        "💶 Auto-Skim: <b>{}</b> ({})\nSaldo minimo nel wallet: {} SOL\nDestinazione: <code>{}</code>\nPrelevati finora: {} SOL",
+        "💶 Auto-Skim: <b>{}</b> ({})\nMinimum wallet balance: {} SOL\nDestination: <code>{}</code>\nSkimmed so far: {} SOL"),
+    ("tg.skim_usage",
+        "<i>Attiva con /skim weekly|monthly SALDO_MINIMO INDIRIZZO (indirizzo della whitelist /address) | /skim off</i>",
+        "<i>Enable with /skim weekly|monthly MIN_BALANCE ADDRESS (address from the /address whitelist) | /skim off</i>"),
+    ("tg.buy_usage",
+        "⚠️ Uso: /buy MINT_O_LINK [IMPORTO_SOL]\n<i>Incolla un indirizzo o un link DexScreener, Birdeye, Pump.fun o Solscan.</i>",
+        "⚠️ Usage: /buy MINT_OR_LINK [SOL_AMOUNT]\n<i>Paste an address or a DexScreener, Birdeye, Pump.fun or Solscan link.</i>"),
+    ("tg.buy_unresolved", "❌ Nessun token trovato: incolla il mint o un link DexScreener/Birdeye.", "❌ No token found: paste the mint or a DexScreener/Birdeye link."),
+    ("tg.buy_no_market", "❌ Nessun mercato per <code>{}</code>: token non ancora tradabile.", "❌ No market for <code>{}</code>: token not tradable yet."),
+    ("tg.buy_preview",
+        "🛒 <b>{}</b>\n📜 <code>{}</code>\n\n💵 Prezzo: ${}\n💧 Liquidità: ${}\n🏦 Market Cap: ${}\n📈 1h: {}% | 24h: {}%\n⭐ Punteggio: {}/100\n🛡️ Sicurezza: {}\n\n💰 Tuo Saldo: {} SOL\n🔓 Disponibile: {} SOL (🔒 {} SOL in posizioni aperte)",
+        "🛒 <b>{}</b>\n📜 <code>{}</code>\n\n💵 Price: ${}\n💧 Liquidity: ${}\n🏦 Market Cap: ${}\n📈 1h: {}% | 24h: {}%\n⭐ Score: {}/100\n🛡️ Safety: {}\n\n💰 Your Balance: {} SOL\n🔓 Available: {} SOL (🔒 {} SOL in open positions)"),
+    ("tg.buy_custom_button", "✍️ Importo personalizzato", "✍️ Custom amount"),
+    ("tg.buy_custom", "✍️ Invia /buy <code>{}</code> IMPORTO (in SOL)", "✍️ Send /buy <code>{}</code> AMOUNT (in SOL)"),
+    ("tg.language_invalid", "❌ Lingua non supportata. Disponibili: {}", "❌ Unsupported language. Available: {}"),
+    ("tg.db_error", "Errore Database: {}", "Database error: {}"),
+    ("tg.signal_accept", "✅ Compra {} SOL", "✅ Buy {} SOL"),
+    ("tg.signal_chart", "📈 Grafico", "📈 Chart"),
+    ("tg.signal_ignore", "❌ Ignora", "❌ Ignore"),
+    ("tg.signal_expired", "⌛ Trade suggerito scaduto o già gestito.", "⌛ Suggested trade expired or already handled."),
+    ("tg.signal_quote_moved", "📉 Prezzo peggiorato oltre lo slippage dal segnale: trade annullato.", "📉 Price moved beyond slippage since the signal: trade cancelled."),
+    ("tg.signal_rejected", "🗑 Trade suggerito #{} ignorato", "🗑 Suggested trade #{} dismissed"),
+    ("tg.signal_swap_failed", "❌ Errore Swap: {}", "❌ Swap error: {}"),
+    ("tg.referrals",
+        "🤝 <b>PROGRAMMA REFERRAL</b>\n\n🎟️ Codice: <code>{}</code>\n{}💸 Quota: {}% del volume dei tuoi invitati\n\n👥 Invitati: {}\n📊 Volume: {} SOL\n💰 Maturato: {} SOL | Pagato: {} SOL\n✅ Disponibile: <b>{} SOL</b>\n\n<i>Preleva dalla Web App (minimo {} SOL).</i>",
+        "🤝 <b>REFERRAL PROGRAM</b>\n\n🎟️ Code: <code>{}</code>\n{}💸 Share: {}% of your invitees' volume\n\n👥 Invitees: {}\n📊 Volume: {} SOL\n💰 Earned: {} SOL | Paid: {} SOL\n✅ Available: <b>{} SOL</b>\n\n<i>Withdraw from the Web App (minimum {} SOL).</i>"),
+    ("tg.referrals_usage", "⚠️ Uso: /referrals oppure /referrals CODICE", "⚠️ Usage: /referrals or /referrals CODE"),
+    ("tg.no_positions", "📭 Nessuna posizione aperta.", "📭 No open positions."),
+    ("tg.positions",
+        "📊 <b>Posizioni Aperte</b>\n\n{}\n\n<i>Modifica stop e take profit con /position ID sl|tp|trailing</i>",
+        "📊 <b>Open Positions</b>\n\n{}\n\n<i>Change stop and take profit with /position ID sl|tp|trailing</i>"),
+    ("tg.positions_tp_strategy", "🎯 TP {}% (strategia)", "🎯 TP {}% (strategy)"),
+    ("tg.positions_hold_left", "⏰ Chiude tra {}h {}m", "⏰ Closes in {}h {}m"),
+    ("tg.positions_no_exit", "⚠️ Nessuna uscita automatica", "⚠️ No automatic exit"),
+    ("tg.positions_sell_button", "🔴 Vendi #{}", "🔴 Sell #{}"),
+    ("tg.position_not_open", "Posizione non più aperta.", "Position no longer open."),
+    ("tg.position_sold", "✅ <b>Posizione #{} venduta</b>\n📈 PnL: {} SOL\n🔗 <a href=\"https://solscan.io/tx/{}\">Vedi su Solscan</a>", "✅ <b>Position #{} sold</b>\n📈 PnL: {} SOL\n🔗 <a href=\"https://solscan.io/tx/{}\">View on Solscan</a>"),
+    ("tg.position_breakeven",
+        "🛡️ <b>Stop spostato a Break-even</b> per #{}\nVendita automatica se il valore scende sotto {} SOL.",
+        "🛡️ <b>Stop moved to break-even</b> for #{}\nAutomatic sell if the value drops below {} SOL."),
+    ("tg.position_added", "✅ <b>Aggiunti {} SOL</b> a <code>{}</code> ({})\n🔗 <a href=\"https://solscan.io/tx/{}\">Vedi su Solscan</a>", "✅ <b>Added {} SOL</b> to <code>{}</code> ({})\n🔗 <a href=\"https://solscan.io/tx/{}\">View on Solscan</a>"),
+    ("tg.strategy_choose",
+        "🧠 <b>Scegli la strategia</b>\n\n• <b>smart</b>: dip con volume/whale\n• <b>momentum</b>: breakout con volume\n• <b>mean_reversion</b>: ipervenduto, uscita sulla media",
+        "🧠 <b>Choose the strategy</b>\n\n• <b>smart</b>: dips with volume/whales\n• <b>momentum</b>: breakouts with volume\n• <b>mean_reversion</b>: oversold, exit at the mean"),
+    ("tg.autobot_started",
+        "🤖 <b>AUTO-TRADING AVVIATO (24h)</b> 🟢\n\nStrategia: <b>{}</b>\nIl bot cercherà gemme e reinvestirà i profitti.\n⚠️ Prelievi bloccati fino a fine ciclo per compounding.\nPuoi sempre fare trading manuale!",
+        "🤖 <b>AUTO-TRADING STARTED (24h)</b> 🟢\n\nStrategy: <b>{}</b>\nThe bot will look for gems and reinvest profits.\n⚠️ Withdrawals locked until the end of the cycle for compounding.\nYou can always trade manually!"),
+    ("tg.autobot_stopped",
+        "🛑 <b>Auto-Trading Fermato.</b>\nIl bot non comprerà più autonomamente.\nPrelievi sbloccati.",
+        "🛑 <b>Auto-Trading Stopped.</b>\nThe bot will no longer buy on its own.\nWithdrawals unlocked."),
+    ("tg.rent_reclaimed", "\n♻️ Rent recuperato dagli ATA vuoti: <b>{} SOL</b>", "\n♻️ Rent reclaimed from empty ATAs: <b>{} SOL</b>"),
+    ("tg.swap_running", "⏳ <b>Esecuzione Swap...</b>\nTarget: <code>{}</code>\nImporto: {} SOL", "⏳ <b>Executing swap...</b>\nTarget: <code>{}</code>\nAmount: {} SOL"),
+    ("tg.signal_swap_running", "⏳ <b>Esecuzione Swap...</b>\nTrade suggerito #{}", "⏳ <b>Executing swap...</b>\nSuggested trade #{}"),
+    ("tg.buy_done", "✅ <b>ACQUISTO COMPLETATO!</b>\n💎 Token in wallet.\n🔗 <a href=\"https://solscan.io/tx/{}\">Vedi su Solscan</a>", "✅ <b>BUY COMPLETED!</b>\n💎 Token in wallet.\n🔗 <a href=\"https://solscan.io/tx/{}\">View on Solscan</a>"),
+    ("tg.signal_buy_done", "✅ <b>ACQUISTO COMPLETATO!</b>\n💎 {} in wallet.\n🔗 <a href=\"https://solscan.io/tx/{}\">Vedi su Solscan</a>", "✅ <b>BUY COMPLETED!</b>\n💎 {} in wallet.\n🔗 <a href=\"https://solscan.io/tx/{}\">View on Solscan</a>"),
+    ("tg.panic_sell_button", "🔴 VENDI TUTTO (Panic)", "🔴 SELL ALL (Panic)"),
+    ("tg.selling", "⏳ <b>Vendita in corso...</b>\n<code>{}</code>", "⏳ <b>Selling...</b>\n<code>{}</code>"),
+    ("tg.sell_done", "✅ <b>VENDITA COMPLETATA!</b>\n📈 PnL: {} SOL\n🔗 <a href=\"https://solscan.io/tx/{}\">Vedi su Solscan</a>", "✅ <b>SELL COMPLETED!</b>\n📈 PnL: {} SOL\n🔗 <a href=\"https://solscan.io/tx/{}\">View on Solscan</a>"),
+    ("tg.sell_failed", "❌ Errore Vendita: {}", "❌ Sell error: {}"),
+    ("tg.cancel_button", "❌ Annulla", "❌ Cancel"),
+    ("tg.export_confirm_button", "🔐 Confermo, voglio esportare", "🔐 I confirm, export it"),
+    ("tg.export_warning",
+        "⚠️ <b>EXPORT CHIAVE PRIVATA</b>\n\nChi possiede la chiave controlla <b>tutti</b> i fondi del wallet.\nL'export viene criptato con una passphrase scelta da te: senza di essa è inutilizzabile.\n\nConfermi?",
+        "⚠️ <b>PRIVATE KEY EXPORT</b>\n\nWhoever holds the key controls <b>all</b> the wallet funds.\nThe export is encrypted with a passphrase you choose: without it, it is useless.\n\nConfirm?"),
+    ("tg.export_confirmed",
+        "🔐 Confermato. Entro 5 minuti invia:\n<code>/export LA_TUA_PASSPHRASE</code>\n(min {} caratteri, il messaggio verrà cancellato)",
+        "🔐 Confirmed. Within 5 minutes send:\n<code>/export YOUR_PASSPHRASE</code>\n(min {} characters, the message will be deleted)"),
+    ("tg.export_short_passphrase", "❌ Passphrase troppo corta (min {} caratteri). Ripeti /export.", "❌ Passphrase too short (min {} characters). Run /export again."),
+    ("tg.export_done",
+        "🔐 <b>EXPORT CRIPTATO</b>\n\n<code>{}</code>\n\nSalvalo offline insieme alla passphrase.\nPer ripristinarlo: <code>/import EXPORT PASSPHRASE</code>",
+        "🔐 <b>ENCRYPTED EXPORT</b>\n\n<code>{}</code>\n\nStore it offline together with the passphrase.\nTo restore it: <code>/import EXPORT PASSPHRASE</code>"),
+    ("tg.export_failed", "❌ Errore Export: {}", "❌ Export error: {}"),
+    ("tg.export_expired", "⚠️ Conferma scaduta o assente. Invia /export e premi Conferma.", "⚠️ Confirmation expired or missing. Send /export and tap Confirm."),
+    ("tg.withdraw_unlocked",
+        "💸 <b>Prelievo Sbloccato</b>\n\nPer sicurezza, inserisci l'indirizzo di destinazione nel prossimo messaggio (Funzione in arrivo).",
+        "💸 <b>Withdrawal Unlocked</b>\n\nFor safety, enter the destination address in the next message (coming soon)."),
+
+    // --- Report giornaliero ---
+    ("report.daily",
+        "📰 <b>REPORT GIORNALIERO</b>\n\n💰 Saldo: <b>{} SOL</b>\n{} PnL Realizzato (24h): <b>{} SOL</b>\n🛒 Trade Aperti: {} | ✅ Chiusi: {} | 🎯 Win rate: {}\n📊 Posizioni in corso: {} ({} SOL esposti)\n{}\n<i>Cambia orario con /report HH:MM FUSO</i>",
+        "📰 <b>DAILY REPORT</b>\n\n💰 Balance: <b>{} SOL</b>\n{} Realized PnL (24h): <b>{} SOL</b>\n🛒 Trades Opened: {} | ✅ Closed: {} | 🎯 Win rate: {}\n📊 Open positions: {} ({} SOL exposed)\n{}\n<i>Change the time with /report HH:MM TIMEZONE</i>"),
+    ("report.by_strategy", "\n🧭 <b>Per modalità</b>\n", "\n🧭 <b>By mode</b>\n"),
+    ("report.strategy_line", "• {}: {} chiusi, {} vinti, {} SOL\n", "• {}: {} closed, {} won, {} SOL\n"),
+    ("report.best", "🏆 Migliore: {} {} SOL ({}%)\n", "🏆 Best: {} {} SOL ({}%)\n"),
+    ("report.worst", "💀 Peggiore: {} {} SOL ({}%)\n", "💀 Worst: {} {} SOL ({}%)\n"),
+    ("report.equity", "\n📈 <b>PnL cumulato (24h)</b>\n<code>{}</code>\n", "\n📈 <b>Cumulative PnL (24h)</b>\n<code>{}</code>\n"),
+
+    // --- Notifiche (Telegram e webhook) ---
+    ("notify.buy.title", "🛒 Acquisto", "🛒 Buy"),
+    ("notify.sell.title", "💰 Vendita", "💰 Sell"),
+    ("notify.stop_out.title", "📉 Stop-Out", "📉 Stop-Out"),
+    ("notify.daily_report.title", "📰 Report Giornaliero", "📰 Daily Report"),
+    ("notify.test.title", "🔔 Webhook di prova", "🔔 Test webhook"),
+    ("notify.buy.summary", "{} SOL di {} via {}", "{} SOL of {} via {}"),
+    ("notify.daily_report.summary", "Saldo {} SOL | PnL 24h {} SOL | Aperti {} / Chiusi {} | Win rate {} | In corso {} ({} SOL)", "Balance {} SOL | 24h PnL {} SOL | Opened {} / Closed {} | Win rate {} | Open {} ({} SOL)"),
+    ("notify.test.summary", "Il webhook funziona.", "The webhook works."),
+    ("alert.slippage", "⚠️ <b>SLIPPAGE ELEVATO</b> ({} via {})\nRicevuto il <b>{}%</b> in meno della quote (tolleranza {}%)\n<code>{}</code>",
+        "⚠️ <b>HIGH SLIPPAGE</b> ({} via {})\nReceived <b>{}%</b> less than quoted (tolerance {}%)\n<code>{}</code>"),
+    ("alert.buy", "<b>{} {}</b>\n💵 {} SOL @ ${} via {}", "<b>{} {}</b>\n💵 {} SOL @ ${} via {}"),
+    ("alert.sell", "<b>{} {}</b> ({})\n{} PnL <b>{} SOL</b> ({})", "<b>{} {}</b> ({})\n{} PnL <b>{} SOL</b> ({})"),
+    ("alert.prices", "📈 Ingresso ${} → Uscita ${}\n⏱️ Durata: {}", "📈 Entry ${} → Exit ${}\n⏱️ Held: {}"),
+    ("alert.account", "💼 Saldo: {} SOL | PnL oggi: {} SOL", "💼 Balance: {} SOL | Today's PnL: {} SOL"),
+    ("alert.links",
+        "🔗 <a href=\"https://solscan.io/tx/{}\">Solscan</a> | <a href=\"https://dexscreener.com/solana/{}\">DexScreener</a>",
+        "🔗 <a href=\"https://solscan.io/tx/{}\">Solscan</a> | <a href=\"https://dexscreener.com/solana/{}\">DexScreener</a>"),
+    ("notify.risk_reset", "🧯 <b>Nuovo giorno:</b> limite di perdita resettato, auto-trading riattivato.", "🧯 <b>New day:</b> loss limit reset, auto-trading resumed."),
+    ("notify.circuit_breaker",
+        "🧯 <b>CIRCUIT BREAKER</b>\n\nPerdita realizzata oggi: <b>{} SOL</b>\nLimite: -{}% del saldo iniziale ({} SOL)\n\n🤖 Auto-trading <b>SOSPESO</b> fino a mezzanotte (UTC).",
+        "🧯 <b>CIRCUIT BREAKER</b>\n\nRealized loss today: <b>{} SOL</b>\nLimit: -{}% of the starting balance ({} SOL)\n\n🤖 Auto-trading <b>PAUSED</b> until midnight (UTC)."),
+    ("notify.rug",
+        "🚨 <b>RUG RILEVATO</b>\n\n<code>{}</code>\nLiquidità: <b>-{}%</b> in {} min (${} rimasti)\n\n🔴 Posizione venduta a mercato: PnL <b>{} SOL</b>\n🔗 <a href=\"https://solscan.io/tx/{}\">Vedi su Solscan</a>",
+        "🚨 <b>RUG DETECTED</b>\n\n<code>{}</code>\nLiquidity: <b>-{}%</b> in {} min (${} left)\n\n🔴 Position sold at market: PnL <b>{} SOL</b>\n🔗 <a href=\"https://solscan.io/tx/{}\">View on Solscan</a>"),
+    ("notify.sell_failed",
+        "🚨 <b>Vendita non riuscita</b>\n\n<code>{}</code>\nMotivo uscita: {}\nTentativi: {} (slippage fino a {}%)\nUltimo errore: {}\n\n⚠️ La posizione resta aperta: vendi a mano da /positions.",
+        "🚨 <b>Sell failed</b>\n\n<code>{}</code>\nExit reason: {}\nAttempts: {} (slippage up to {}%)\nLast error: {}\n\n⚠️ The position stays open: sell manually from /positions."),
+    ("notify.limit_filled",
+        "🎯 <b>ORDINE LIMITE ESEGUITO</b>\n\n#{} {} <code>{}</code>\nPrezzo: ${}\n🔗 <a href=\"https://solscan.io/tx/{}\">Vedi su Solscan</a>",
+        "🎯 <b>LIMIT ORDER FILLED</b>\n\n#{} {} <code>{}</code>\nPrice: ${}\n🔗 <a href=\"https://solscan.io/tx/{}\">View on Solscan</a>"),
+    ("notify.limit_failed", "❌ <b>Ordine Limite #{} fallito</b>\n{} <code>{}</code>\nErrore: {}", "❌ <b>Limit Order #{} failed</b>\n{} <code>{}</code>\nError: {}"),
+    ("notify.parked",
+        "🅿️ <b>Profitto parcheggiato</b>\n{} SOL → <b>{} USDC</b> ({}% del ricavato)\nTotale parcheggiato: {} USDC\n🔗 <a href=\"https://solscan.io/tx/{}\">Vedi su Solscan</a>",
+        "🅿️ <b>Profit parked</b>\n{} SOL → <b>{} USDC</b> ({}% of the proceeds)\nTotal parked: {} USDC\n🔗 <a href=\"https://solscan.io/tx/{}\">View on Solscan</a>"),
+    ("notify.withdrawal_expired", "⌛ <b>Prelievo #{} annullato</b>\nNessuna approvazione entro {} minuti.", "⌛ <b>Withdrawal #{} cancelled</b>\nNo approval within {} minutes."),
+    ("notify.offramp_expired", "⌛ <b>Bonifico #{} annullato</b>\nNessuna approvazione entro {} minuti.", "⌛ <b>Bank transfer #{} cancelled</b>\nNo approval within {} minutes."),
+    ("notify.offramp_processing",
+        "🏦 <b>Bonifico #{} in lavorazione</b>\n{} EUR verso {}: gli EURC sono stati consegnati al provider, accredito in 1-2 giorni lavorativi.",
+        "🏦 <b>Bank transfer #{} processing</b>\n{} EUR to {}: the EURC reached the provider, credit in 1-2 business days."),
+    ("notify.offramp_settled", "✅ <b>Bonifico #{} eseguito</b>\n{} EUR accreditati su {}.", "✅ <b>Bank transfer #{} settled</b>\n{} EUR credited to {}."),
+    ("notify.offramp_failed",
+        "❌ <b>Bonifico #{} fallito</b> ({} EUR)\nErrore: {}\nEventuali EURC già convertiti restano nel wallet o vengono restituiti dal provider.",
+        "❌ <b>Bank transfer #{} failed</b> ({} EUR)\nError: {}\nAny EURC already converted stays in the wallet or is refunded by the provider."),
+    ("skim.invalid_floor", "Saldo minimo non valido", "Invalid minimum balance"),
+    ("skim.not_whitelisted", "Indirizzo non in whitelist: aggiungilo con /address add e confermalo", "Address not whitelisted: add it with /address add and confirm it"),
+    ("notify.skim_sent",
+        "💶 <b>Auto-Skim eseguito</b>\n{} SOL prelevati verso <code>{}</code> (nel wallet restano {} SOL)\n🔗 <a href=\"https://solscan.io/tx/{}\">Vedi su Solscan</a>",
+        "💶 <b>Auto-Skim done</b>\n{} SOL withdrawn to <code>{}</code> ({} SOL stay in the wallet)\n🔗 <a href=\"https://solscan.io/tx/{}\">View on Solscan</a>"),
+    ("notify.skim_pending", "💶 <b>Auto-Skim</b>: {} SOL in attesa della tua approvazione (prelievo #{}).", "💶 <b>Auto-Skim</b>: {} SOL awaiting your approval (withdrawal #{})."),
+    ("notify.skim_skipped", "💶 <b>Auto-Skim</b>: niente da prelevare sopra {} SOL.", "💶 <b>Auto-Skim</b>: nothing to withdraw above {} SOL."),
+    ("notify.skim_failed", "💶 <b>Auto-Skim non eseguito</b>\nErrore: {}", "💶 <b>Auto-Skim not executed</b>\nError: {}"),
+    ("notify.skim_next", "<i>Prossimo: {}</i>", "<i>Next: {}</i>"),
+    ("notify.deposit", "📥 <b>Ricevuti {} {}</b> ✅\n🔗 <a href=\"https://solscan.io/tx/{}\">Vedi su Solscan</a>", "📥 <b>Received {} {}</b> ✅\n🔗 <a href=\"https://solscan.io/tx/{}\">View on Solscan</a>"),
+    ("notify.first_deposit",
+        "\n\n🎉 <b>Benvenuto!</b> Il tuo wallet è operativo.\n▶️ /start per aprire il Pannello e avviare l'Auto-Bot\n⚙️ /strategy per regolare rischio e dimensione dei trade\n🔐 /address per registrare un indirizzo di prelievo",
+        "\n\n🎉 <b>Welcome!</b> Your wallet is ready.\n▶️ /start to open the Panel and start the Auto-Bot\n⚙️ /strategy to tune risk and trade size\n🔐 /address to register a withdrawal address"),
+    ("notify.reconciled", "🔎 <b>Trade {} chiuso</b> ({})\nIl token non è più nel wallet: venduto fuori dal bot? PnL non calcolato.", "🔎 <b>Trade {} closed</b> ({})\nThe token is no longer in the wallet: sold outside the bot? PnL not computed."),
+    ("notify.signal_expired", "⌛ <b>Trade suggerito #{} scaduto</b> ({})\nNessuna approvazione in tempo.", "⌛ <b>Suggested trade #{} expired</b> ({})\nNo approval in time."),
+    ("notify.signal",
+        "📡 <b>SEGNALE D'INGRESSO</b>\n\n💎 <b>{}</b>\n📜 <code>{}</code>\n🧠 Strategia: {} ({})\n🎯 Score: {}/100\n💰 Importo suggerito: {} SOL\n\n<i>Modalità solo segnali: nessun acquisto automatico. Valido {} minuti.</i>",
+        "📡 <b>ENTRY SIGNAL</b>\n\n💎 <b>{}</b>\n📜 <code>{}</code>\n🧠 Strategy: {} ({})\n🎯 Score: {}/100\n💰 Suggested size: {} SOL\n\n<i>Signals-only mode: nothing is bought automatically. Valid for {} minutes.</i>"),
+];
@@ -0,0 +1,172 @@
+// --- INDICATORI (Matematica pura dell'AMMS) ---
+// 1. Funzioni senza stato su slice di chiusure/candele: niente I/O, niente parametri utente
+// 2. strategy.rs le usa con i propri periodi (RSI 14, Bollinger 20x2, ATR 14, EMA 20/50, volume 10)
+// 3. Definizioni: EMA con seme SMA e Bollinger con deviazione standard di popolazione come TA-Lib (EMA, BBANDS);
+//    RSI e ATR usano la media semplice delle ultime `period` variazioni (non lo smoothing di Wilder)
+// I test confrontano vettori di riferimento calcolati a mano: cambiare la matematica li rompe di proposito.
+
+// Struttura Candela Completa
+#[derive(Clone, Copy, Debug)]
+pub struct Candle {
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: f64, // Aggiunto Volume
+}
+
+/// Media semplice degli ultimi `period` valori
+pub fn sma(values: &[f64], period: usize) -> Option<f64> {
+    if period == 0 || values.len() < period { return None; }
+    Some(values[values.len() - period..].iter().sum::<f64>() / period as f64)
+}
+
+/// Media mobile esponenziale (seme = media semplice dei primi `period` valori)
+pub fn ema(values: &[f64], period: usize) -> Option<f64> {
+    if period == 0 || values.len() < period { return None; }
+    let k = 2.0 / (period as f64 + 1.0);
+    let seed = values[..period].iter().sum::<f64>() / period as f64;
+    Some(values[period..].iter().fold(seed, |ema, v| v * k + ema * (1.0 - k)))
+}
+
+/// RSI sulle ultime `period` variazioni di chiusura (0..=100, 100 se non ci sono perdite)
+pub fn rsi(closes: &[f64], period: usize) -> Option<f64> {
+    if period == 0 || closes.len() < period + 1 { return None; }
+    let mut gains = 0.0; let mut losses = 0.0;
+    for i in (closes.len() - period)..closes.len() {
+        let diff = closes[i] - closes[i - 1];
+        if diff >= 0.0 { gains += diff; } else { losses += diff.abs(); }
+    }
+    if losses == 0.0 { return Some(100.0); }
+    let rs = (gains / period as f64) / (losses / period as f64);
+    Some(100.0 - (100.0 / (1.0 + rs)))
+}
+
+/// Bande di Bollinger sugli ultimi `period` valori: (inferiore, media, superiore)
+pub fn bollinger(closes: &[f64], period: usize, mult: f64) -> Option<(f64, f64, f64)> {
+    let ma = sma(closes, period)?;
+    let variance = closes[closes.len() - period..].iter()
+        .map(|c| (ma - c).powi(2)).sum::<f64>() / period as f64;
+    let std_dev = variance.sqrt();
+    Some((ma - std_dev * mult, ma, ma + std_dev * mult))
+}
+
+/// True Range della candela rispetto alla chiusura precedente
+pub fn true_range(candle: &Candle, prev_close: f64) -> f64 {
+    (candle.high - candle.low).max((candle.high - prev_close).abs()).max((candle.low - prev_close).abs())
+}
+
+/// Average True Range: volatilità media per candela (stessa unità del prezzo)
+pub fn atr(candles: &[Candle], period: usize) -> Option<f64> {
+    if period == 0 || candles.len() < period + 1 { return None; }
+    let sum: f64 = ((candles.len() - period)..candles.len())
+        .map(|i| true_range(&candles[i], candles[i - 1].close))
+        .sum();
+    Some(sum / period as f64)
+}
+
+/// Volume dell'ultimo valore rispetto alla media dei `period` precedenti (None se la media è zero)
+pub fn volume_ratio(volumes: &[f64], period: usize) -> Option<f64> {
+    let (&current, previous) = volumes.split_last()?;
+    let avg_vol = sma(previous, period)?;
+    (avg_vol > 0.0).then(|| current / avg_vol)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPS: f64 = 1e-9;
+
+    fn assert_close(actual: Option<f64>, expected: f64) {
+        let actual = actual.expect("indicatore non calcolato");
+        assert!((actual - expected).abs() < EPS, "atteso {}, ottenuto {}", expected, actual);
+    }
+
+    fn candle(high: f64, low: f64, close: f64) -> Candle {
+        Candle { high, low, close, volume: 0.0 }
+    }
+
+    // Serie di esempio dell'RSI di StockCharts (15 chiusure = prima lettura)
+    const STOCKCHARTS_RSI: [f64; 15] = [44.34, 44.09, 44.15, 43.61, 44.33, 44.83, 45.10, 45.42, 45.84, 46.08, 45.89, 46.03, 45.61, 46.28, 46.28];
+
+    #[test]
+    fn rsi_golden() {
+        // Guadagni 3.34, perdite 1.40 su 14 variazioni (StockCharts arrotonda le medie a 0.24 e 0.10: 70.53)
+        assert_close(rsi(&STOCKCHARTS_RSI, 14), 70.46413502109705);
+        assert_close(rsi(&[1.0, 2.0, 3.0, 4.0], 3), 100.0);
+        assert_close(rsi(&[10.0, 11.0, 10.0, 11.0, 10.0], 4), 50.0);
+        assert_eq!(rsi(&STOCKCHARTS_RSI[..14], 14), None);
+    }
+
+    #[test]
+    fn ema_golden() {
+        // Seme (1+2+3)/3 = 2, k = 0.5: su una retta l'EMA resta un passo indietro
+        let line: Vec<f64> = (1..=10).map(f64::from).collect();
+        assert_close(ema(&line, 3), 9.0);
+        assert_close(ema(&STOCKCHARTS_RSI, 10), 45.59144190970561);
+        assert_close(ema(&[5.0, 7.0], 2), 6.0);
+        assert_eq!(ema(&[1.0], 2), None);
+    }
+
+    #[test]
+    fn bollinger_golden() {
+        // Media 5, deviazione standard di popolazione 2
+        let (lower, middle, upper) = bollinger(&[2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0], 8, 2.0).unwrap();
+        assert_close(Some(lower), 1.0);
+        assert_close(Some(middle), 5.0);
+        assert_close(Some(upper), 9.0);
+        // Solo le ultime `period` chiusure contano
+        let (lower, middle, upper) = bollinger(&[100.0, 3.0, 3.0, 3.0], 3, 2.0).unwrap();
+        assert_close(Some(lower), 3.0);
+        assert_close(Some(middle), 3.0);
+        assert_close(Some(upper), 3.0);
+    }
+
+    #[test]
+    fn atr_golden() {
+        let bars = [candle(10.0, 8.0, 9.0), candle(11.0, 9.0, 10.0), candle(12.0, 9.0, 11.0), candle(11.0, 10.0, 10.5)];
+        // True Range 2, 3, 1
+        assert_close(atr(&bars, 3), 2.0);
+        // Gap rialzista: conta la distanza dalla chiusura precedente, non high - low
+        assert_close(Some(true_range(&candle(15.0, 14.0, 14.5), 10.5)), 4.5);
+        assert_eq!(atr(&bars[..3], 3), None);
+    }
+
+    #[test]
+    fn volume_ratio_golden() {
+        assert_close(volume_ratio(&[1.0, 2.0, 3.0, 6.0], 3), 3.0);
+        assert_eq!(volume_ratio(&[0.0, 0.0, 5.0], 2), None);
+    }
+
+    // Serie pseudo-casuali riproducibili (xorshift): prezzi positivi con salti fino a ±30% per candela
+    fn random_candles(seed: u64, len: usize) -> Vec<Candle> {
+        let mut state = seed.max(1);
+        let mut next = || { state ^= state << 13; state ^= state >> 7; state ^= state << 17; (state % 10_000) as f64 / 10_000.0 };
+        let mut close = 1.0 + next() * 100.0;
+        (0..len).map(|_| {
+            close = (close * (0.7 + next() * 0.6)).max(1e-9);
+            let high = close * (1.0 + next() * 0.1);
+            let low = close * (1.0 - next() * 0.1);
+            Candle { high, low, close, volume: next() * 1_000.0 }
+        }).collect()
+    }
+
+    #[test]
+    fn indicator_bounds_hold_on_random_series() {
+        for seed in 1..=500 {
+            let candles = random_candles(seed, 60);
+            let closes: Vec<f64> = candles.iter().map(|c| c.close).collect();
+            for period in [2, 5, 14, 20] {
+                let r = rsi(&closes, period).unwrap();
+                assert!((0.0..=100.0).contains(&r), "RSI {} fuori da 0..100 (seed {}, periodo {})", r, seed, period);
+                let a = atr(&candles, period).unwrap();
+                assert!(a >= 0.0, "ATR {} negativo (seed {}, periodo {})", a, seed, period);
+                let (lower, middle, upper) = bollinger(&closes, period, 2.0).unwrap();
+                assert!(lower <= middle && middle <= upper, "bande non ordinate (seed {}, periodo {})", seed, period);
+                let e = ema(&closes, period).unwrap();
+                let (min, max) = closes.iter().fold((f64::MAX, f64::MIN), |(lo, hi), c| (lo.min(*c), hi.max(*c)));
+                assert!(e >= min - EPS && e <= max + EPS, "EMA {} fuori dal range dei prezzi (seed {})", e, seed);
+            }
+        }
+    }
+}
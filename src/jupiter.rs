@@ -1,11 +1,12 @@
 use serde::{Deserialize, Serialize};
 use std::error::Error;
-use solana_sdk::transaction::Transaction;
+use solana_sdk::transaction::VersionedTransaction;
 use base64::{Engine as _, engine::general_purpose};
 use reqwest;
 
 const JUP_TOKEN_LIST_API: &str = "https://token.jup.ag/strict"; 
 const DEX_API: &str = "https://api.dexscreener.com/latest/dex/tokens/";
+const DEX_PAIRS_API: &str = "https://api.dexscreener.com/latest/dex/pairs/solana/";
 const JUP_QUOTE_API: &str = "https://quote-api.jup.ag/v6/quote";
 const JUP_SWAP_API: &str = "https://quote-api.jup.ag/v6/swap";
 
@@ -15,23 +16,49 @@ pub struct JupiterToken { pub address: String, pub symbol: String, pub name: Str
 #[derive(Deserialize, Debug)]
 struct DexResponse { pairs: Option<Vec<PairData>> }
 #[derive(Deserialize, Debug)]
-struct PairData { priceUsd: Option<String>, baseToken: TokenInfo, liquidity: Option<LiquidityInfo>, fdv: Option<f64>, volume: Option<VolumeInfo>, priceChange: Option<PriceChangeInfo> }
+#[allow(non_snake_case)]
+struct PairData { priceUsd: Option<String>, baseToken: TokenInfo, liquidity: Option<LiquidityInfo>, fdv: Option<f64>, volume: Option<VolumeInfo>, priceChange: Option<PriceChangeInfo>, info: Option<PairInfo> }
 #[derive(Deserialize, Debug)]
-struct TokenInfo { symbol: String }
+struct TokenInfo { symbol: String, #[serde(default)] address: String }
 #[derive(Deserialize, Debug)]
 struct LiquidityInfo { usd: Option<f64> }
 #[derive(Deserialize, Debug)]
 struct VolumeInfo { h24: Option<f64> }
+#[derive(Deserialize, Debug, Default)]
+struct PairInfo { #[serde(default)] websites: Vec<LinkInfo>, #[serde(default)] socials: Vec<SocialInfo> }
 #[derive(Deserialize, Debug)]
-struct PriceChangeInfo { m5: Option<f64>, h1: Option<f64> }
+struct LinkInfo { url: String }
+#[derive(Deserialize, Debug)]
+struct SocialInfo { #[serde(rename = "type")] kind: String, url: String }
+
+// Profilo DexScreener del token (compilato e verificato dal team): sito e canali social
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct TokenSocials {
+    pub websites: Vec<String>,
+    pub twitter: Option<String>,
+    pub telegram: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct PriceChangeInfo { m5: Option<f64>, h1: Option<f64>, h24: Option<f64> }
 
+#[derive(Serialize, Clone, Debug)]
 pub struct TokenMarketData {
-    pub price: f64, pub symbol: String, pub liquidity_usd: f64, pub market_cap: f64, pub volume_24h: f64, pub change_5m: f64, pub change_1h: f64
+    pub price: f64, pub symbol: String, pub liquidity_usd: f64, pub market_cap: f64, pub volume_24h: f64, pub change_5m: f64, pub change_1h: f64, pub change_24h: f64
 }
 
 #[derive(Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
-struct SwapRequest { quote_response: serde_json::Value, user_public_key: String, wrap_and_unwrap_sol: bool }
+struct SwapRequest {
+    quote_response: serde_json::Value,
+    user_public_key: String,
+    wrap_and_unwrap_sol: bool,
+    // Priority fee (microlamports per CU) stimata dal nodo; None = default di Jupiter
+    #[serde(skip_serializing_if = "Option::is_none")]
+    compute_unit_price_micro_lamports: Option<u64>,
+    // Limite CU stimato da Jupiter simulando lo swap (altrimenti il massimo: fee più alta)
+    dynamic_compute_unit_limit: bool,
+}
 #[derive(Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 struct SwapResponse { swap_transaction: String }
@@ -42,7 +69,17 @@ pub async fn fetch_all_verified_tokens() -> Result<Vec<JupiterToken>, Box<dyn Er
     Ok(tokens)
 }
 
+/// Dati di mercato DexScreener, passando dalla cache condivisa (TTL breve, una sola richiesta per mint)
 pub async fn get_token_market_data(mint: &str) -> Result<TokenMarketData, Box<dyn Error + Send + Sync>> {
+    crate::price_cache::PRICE_CACHE.get_or_fetch(mint, fetch_token_market_data(mint)).await
+}
+
+/// Probe di /health: DexScreener senza passare dalla cache prezzi
+pub async fn probe_dexscreener(mint: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
+    fetch_token_market_data(mint).await.map(|_| ())
+}
+
+async fn fetch_token_market_data(mint: &str) -> Result<TokenMarketData, Box<dyn Error + Send + Sync>> {
     let url = format!("{}{}", DEX_API, mint);
     let resp = reqwest::get(&url).await?.json::<DexResponse>().await?;
 
@@ -55,10 +92,31 @@ pub async fn get_token_market_data(mint: &str) -> Result<TokenMarketData, Box<dy
             let vol = pair.volume.as_ref().and_then(|v| v.h24).unwrap_or(0.0);
             let ch_5m = pair.priceChange.as_ref().and_then(|c| c.m5).unwrap_or(0.0);
             let ch_1h = pair.priceChange.as_ref().and_then(|c| c.h1).unwrap_or(0.0);
-            return Ok(TokenMarketData { price, symbol, liquidity_usd: liq, market_cap: mcap, volume_24h: vol, change_5m: ch_5m, change_1h: ch_1h });
+            let ch_24h = pair.priceChange.as_ref().and_then(|c| c.h24).unwrap_or(0.0);
+            return Ok(TokenMarketData { price, symbol, liquidity_usd: liq, market_cap: mcap, volume_24h: vol, change_5m: ch_5m, change_1h: ch_1h, change_24h: ch_24h });
         }
     }
-    Ok(TokenMarketData { price: 0.0, symbol: "UNK".into(), liquidity_usd: 0.0, market_cap: 0.0, volume_24h: 0.0, change_5m: 0.0, change_1h: 0.0 })
+    Ok(TokenMarketData { price: 0.0, symbol: "UNK".into(), liquidity_usd: 0.0, market_cap: 0.0, volume_24h: 0.0, change_5m: 0.0, change_1h: 0.0, change_24h: 0.0 })
+}
+
+/// Sito e social dal profilo DexScreener (vuoto se il team non l'ha compilato)
+pub async fn get_token_socials(mint: &str) -> Result<TokenSocials, Box<dyn Error + Send + Sync>> {
+    let url = format!("{}{}", DEX_API, mint);
+    let resp = reqwest::get(&url).await?.json::<DexResponse>().await?;
+    let info = resp.pairs.unwrap_or_default().into_iter().find_map(|p| p.info).unwrap_or_default();
+    let social = |kind: &str| info.socials.iter().find(|s| s.kind.eq_ignore_ascii_case(kind)).map(|s| s.url.clone());
+    Ok(TokenSocials {
+        websites: info.websites.iter().map(|w| w.url.clone()).collect(),
+        twitter: social("twitter"),
+        telegram: social("telegram"),
+    })
+}
+
+/// Mint del token base di una pool DexScreener (i link dexscreener.com/solana/... puntano alla pool). None se non è una pool.
+pub async fn get_pair_base_token(pair: &str) -> Result<Option<String>, Box<dyn Error + Send + Sync>> {
+    let url = format!("{}{}", DEX_PAIRS_API, pair);
+    let resp = reqwest::get(&url).await?.json::<DexResponse>().await?;
+    Ok(resp.pairs.unwrap_or_default().into_iter().map(|p| p.baseToken.address).find(|a| !a.is_empty()))
 }
 
 pub async fn get_token_info(mint: &str) -> Result<(f64, String), Box<dyn Error + Send + Sync>> {
@@ -66,16 +124,68 @@ pub async fn get_token_info(mint: &str) -> Result<(f64, String), Box<dyn Error +
     Ok((data.price, data.symbol))
 }
 
-pub async fn get_jupiter_swap_tx(user_pubkey: &str, input_mint: &str, output_mint: &str, amount_lamports: u64, slippage_bps: u16) -> Result<Transaction, Box<dyn Error + Send + Sync>> {
+/// Modalità della quote: ExactIn = `amount` è l'input speso, ExactOut = `amount` è l'output esatto ricevuto
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SwapMode { ExactIn, ExactOut }
+
+impl SwapMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SwapMode::ExactIn => "ExactIn",
+            SwapMode::ExactOut => "ExactOut",
+        }
+    }
+}
+
+/// Quote Jupiter (ExactIn). Ritorna la risposta grezza da passare allo swap.
+pub async fn get_quote(input_mint: &str, output_mint: &str, amount: u64, slippage_bps: u16) -> Result<serde_json::Value, Box<dyn Error + Send + Sync>> {
+    fetch_quote(input_mint, output_mint, amount, slippage_bps, None, SwapMode::ExactIn).await
+}
+
+/// Quote limitata a specifici DEX (es. "Whirlpool" = solo pool Orca)
+pub async fn get_quote_on_dexes(input_mint: &str, output_mint: &str, amount: u64, slippage_bps: u16, dexes: Option<&str>) -> Result<serde_json::Value, Box<dyn Error + Send + Sync>> {
+    fetch_quote(input_mint, output_mint, amount, slippage_bps, dexes, SwapMode::ExactIn).await
+}
+
+/// Quote ExactOut: `out_amount` (unità base) arriva esatto, lo slippage ricade sull'input (otherAmountThreshold = input massimo)
+pub async fn get_quote_exact_out(input_mint: &str, output_mint: &str, out_amount: u64, slippage_bps: u16) -> Result<serde_json::Value, Box<dyn Error + Send + Sync>> {
+    fetch_quote(input_mint, output_mint, out_amount, slippage_bps, None, SwapMode::ExactOut).await
+}
+
+async fn fetch_quote(input_mint: &str, output_mint: &str, amount: u64, slippage_bps: u16, dexes: Option<&str>, mode: SwapMode) -> Result<serde_json::Value, Box<dyn Error + Send + Sync>> {
     let client = reqwest::Client::new();
-    let quote_url = format!("{}?inputMint={}&outputMint={}&amount={}&slippageBps={}", JUP_QUOTE_API, input_mint, output_mint, amount_lamports, slippage_bps);
+    let mut quote_url = format!("{}?inputMint={}&outputMint={}&amount={}&slippageBps={}", JUP_QUOTE_API, input_mint, output_mint, amount, slippage_bps);
+    if let Some(d) = dexes { quote_url.push_str(&format!("&dexes={}", d)); }
+    if mode == SwapMode::ExactOut { quote_url.push_str(&format!("&swapMode={}", mode.as_str())); }
     let quote_resp: serde_json::Value = client.get(&quote_url).send().await?.json().await?;
     if quote_resp.get("error").is_some() { return Err(format!("Errore Quote: {}", quote_resp).into()); }
-    
-    let swap_req = SwapRequest { quote_response: quote_resp, user_public_key: user_pubkey.to_string(), wrap_and_unwrap_sol: true };
+    Ok(quote_resp)
+}
+
+/// Quantità in uscita stimata dalla quote (unità base del token di output)
+pub fn quote_out_amount(quote: &serde_json::Value) -> u64 {
+    quote.get("outAmount").and_then(|v| v.as_str()).and_then(|s| s.parse().ok()).unwrap_or(0)
+}
+
+/// Quantità in ingresso stimata dalla quote (unità base del token di input)
+pub fn quote_in_amount(quote: &serde_json::Value) -> u64 {
+    quote.get("inAmount").and_then(|v| v.as_str()).and_then(|s| s.parse().ok()).unwrap_or(0)
+}
+
+/// Soglia della quote: minimo in uscita (ExactIn) oppure massimo in ingresso (ExactOut), slippage incluso
+pub fn quote_threshold(quote: &serde_json::Value) -> u64 {
+    quote.get("otherAmountThreshold").and_then(|v| v.as_str()).and_then(|s| s.parse().ok()).unwrap_or(0)
+}
+
+async fn fetch_swap_tx_bytes(user_pubkey: &str, quote: serde_json::Value, cu_price: Option<u64>) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+    let client = reqwest::Client::new();
+    let swap_req = SwapRequest { quote_response: quote, user_public_key: user_pubkey.to_string(), wrap_and_unwrap_sol: true, compute_unit_price_micro_lamports: cu_price, dynamic_compute_unit_limit: crate::network::compute_estimation_enabled() };
     let swap_resp: SwapResponse = client.post(JUP_SWAP_API).json(&swap_req).send().await?.json().await?;
-    
-    let tx_bytes = general_purpose::STANDARD.decode(&swap_resp.swap_transaction)?;
-    let transaction: Transaction = bincode::deserialize(&tx_bytes)?;
-    Ok(transaction)
-}
\ No newline at end of file
+    Ok(general_purpose::STANDARD.decode(&swap_resp.swap_transaction)?)
+}
+
+/// Transazione di swap NON firmata (Versioned). `cu_price` = priority fee in microlamports per CU.
+pub async fn get_unsigned_swap_tx(user_pubkey: &str, quote: serde_json::Value, cu_price: Option<u64>) -> Result<VersionedTransaction, Box<dyn Error + Send + Sync>> {
+    let tx_bytes = fetch_swap_tx_bytes(user_pubkey, quote, cu_price).await?;
+    Ok(bincode::deserialize(&tx_bytes)?)
+}
@@ -0,0 +1,46 @@
+use std::sync::OnceLock;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, reload, EnvFilter, Layer, Registry};
+
+// --- LOGGING (tracing, JSON strutturato) ---
+// 1. Ogni riga è un oggetto JSON con livello, modulo, messaggio e i campi degli span attivi
+//    (user_id, token, trade_id): basta filtrare per user_id per seguire un BUY -> SELL completo
+// 2. Le macro `log::info!` già presenti passano dal ponte tracing-log: finiscono nello stesso stream
+// 3. Il filtro (sintassi RUST_LOG, es. "info,god_sniper=debug") si cambia a caldo da POST /admin/log-level
+// LOG_FORMAT=text per il vecchio formato leggibile (sviluppo locale).
+
+const DEFAULT_FILTER: &str = "info";
+
+static FILTER: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
+
+pub fn init() {
+    let spec = std::env::var("RUST_LOG").unwrap_or_else(|_| DEFAULT_FILTER.into());
+    let filter = EnvFilter::try_new(&spec).unwrap_or_else(|_| EnvFilter::new(DEFAULT_FILTER));
+    let (filter, handle) = reload::Layer::new(filter);
+
+    let json = std::env::var("LOG_FORMAT").map(|v| v != "text").unwrap_or(true);
+    let output = if json {
+        fmt::layer().json().with_current_span(true).with_span_list(false).boxed()
+    } else {
+        fmt::layer().boxed()
+    };
+
+    tracing_subscriber::registry().with(filter).with(output).init();
+    let _ = FILTER.set(handle);
+}
+
+/// Filtro attivo (sintassi RUST_LOG)
+pub fn current_filter() -> Option<String> {
+    FILTER.get()?.with_current(|f| f.to_string()).ok()
+}
+
+/// Sostituisce il filtro senza riavvio. Ritorna il filtro applicato.
+pub fn set_filter(spec: &str) -> Result<String, String> {
+    let filter = EnvFilter::try_new(spec).map_err(|e| format!("Filtro non valido: {}", e))?;
+    let handle = FILTER.get().ok_or("Logging non inizializzato")?;
+    handle.reload(filter).map_err(|e| e.to_string())?;
+    // Il ponte log -> tracing fissa il livello massimo all'avvio: va riaperto perché i nuovi debug/trace arrivino al filtro
+    log::set_max_level(log::LevelFilter::Trace);
+    Ok(current_filter().unwrap_or_else(|| spec.to_string()))
+}
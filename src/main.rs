@@ -1,18 +1,14 @@
+// Catena di filtri warp (tutte le rotte API) più profonda del limite di default
+#![recursion_limit = "512"]
+
 use dotenv::dotenv;
-use log::{info, error, warn, debug};
-use std::sync::{Arc, Mutex};
+use log::{info, warn};
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
 use tokio::time::{sleep, Duration};
+use tokio::sync::{broadcast, watch, RwLock};
+use dashmap::{DashMap, DashSet};
 use std::env;
-use std::collections::{HashMap, HashSet};
-use sqlx::Row;
-use futures::StreamExt;
-use solana_client::rpc_config::{RpcTransactionLogsFilter, RpcTransactionLogsConfig, RpcTransactionConfig};
-use solana_sdk::commitment_config::CommitmentConfig;
-use solana_sdk::pubkey::Pubkey;
-use std::str::FromStr;
-use solana_transaction_status::UiTransactionEncoding;
-use solana_transaction_status::option_serializer::OptionSerializer;
-use solana_sdk::signature::Signer;
 
 // MODULI
 pub mod raydium;
@@ -22,10 +18,59 @@ pub mod db;
 pub mod telegram_bot;
 pub mod safety;
 pub mod strategy;
+pub mod indicators;
 pub mod api;
 pub mod jupiter;
-
-const WATCHLIST: &[&str] = &[
+pub mod birdeye;
+pub mod backtest;
+pub mod auth;
+pub mod swap_router;
+pub mod rate_limit;
+pub mod metrics;
+pub mod report;
+pub mod risk;
+pub mod token_filter;
+pub mod notifications;
+pub mod referral;
+pub mod price_cache;
+pub mod copy_trade;
+pub mod whale;
+pub mod grid;
+pub mod withdrawals;
+pub mod price_oracle;
+pub mod export;
+pub mod deposits;
+pub mod dust;
+pub mod rug;
+pub mod logging;
+pub mod i18n;
+pub mod signals;
+pub mod metadata;
+pub mod reconcile;
+pub mod portfolio;
+pub mod sniper_stats;
+pub mod health;
+pub mod trade_alerts;
+pub mod auto_park;
+pub mod auto_skim;
+pub mod offramp;
+pub mod trade_queue;
+pub mod gems;
+pub mod gem_filters;
+pub mod token_icons;
+pub mod sig_cache;
+pub mod scoring;
+pub mod email;
+pub mod chain;
+pub mod trade_notes;
+pub mod trading;
+pub mod activity;
+pub mod tasks;
+#[cfg(feature = "stress")]
+pub mod stress;
+
+// Watchlist di default (usata da chi non ha ancora una watchlist personale)
+pub const DEFAULT_WATCHLIST: &[&str] = &[
     "So11111111111111111111111111111111111111112", 
     "JUPyiwrYJFskUPiHa7hkeR8VUtkCw785HvjeyzmEgGz",
     "EKpQGSJtjMFqKZ9KQanSqYXRcF8fBopzLHYxdM65zcjm", 
@@ -39,251 +84,208 @@ const WATCHLIST: &[&str] = &[
 pub struct GemData {
     pub token: String,
     pub symbol: String, 
+    pub logo: Option<String>,
     pub price: f64,     
     pub safety_score: u8,
+    // Punteggio 0-100 (scoring: mercato, crescita holder/wallet, social)
+    pub score: u8,
+    pub liquidity_usd: f64,
+    pub volume_24h: f64,
+    pub change_24h: f64,
+    // Prima scoperta e ultimo aggiornamento dei dati di mercato
     pub timestamp: i64,
+    pub updated_at: i64,
     pub source: String, 
 }
 
-// STATO CONDIVISO AGGIORNATO
-pub struct AppState {
-    pub found_gems: Mutex<Vec<GemData>>,
-    pub math_signals: Mutex<Vec<api::SignalData>>,
-    // Cache per evitare doppi acquisti (User -> Token -> Timestamp)
-    pub buy_cooldowns: Mutex<HashMap<String, HashMap<String, i64>>>, 
-    // Cache per evitare doppi processamenti Sniper
-    pub processed_sigs: Mutex<HashSet<String>>,
+// Posizione aperta seguita dal Position Manager (persistita nella tabella positions)
+#[derive(Clone, serde::Serialize)]
+pub struct OpenPosition {
+    pub trade_id: i64,
+    pub user_id: String,
+    pub token: String,
+    pub amount_in_lamports: u64,
+    pub entry_price: f64,
+    // Valore massimo raggiunto (in lamports) per il Trailing Stop
+    pub highest_value_lamports: u64,
+    pub opened_at: i64,
+    // Stop minimo manuale (es. Break-even), 0 = disattivato
+    pub stop_floor_lamports: u64,
+    // Take profit manuale (valore in lamports), 0 = disattivato
+    pub take_profit_lamports: u64,
+    // true = niente Trailing Stop, restano solo stop e take profit manuali
+    pub trailing_disabled: bool,
+    // Strategia d'ingresso del trade (trades.strategy): decide la durata massima della posizione
+    pub strategy: String,
 }
 
-// --- HELPER: CONTROLLO COOLDOWN ---
-fn check_and_set_cooldown(state: &Arc<AppState>, user_id: &str, token: &str) -> bool {
-    let mut cache = state.buy_cooldowns.lock().unwrap();
-    let user_cache = cache.entry(user_id.to_string()).or_insert_with(HashMap::new);
-    let now = chrono::Utc::now().timestamp();
-
-    if let Some(last_buy) = user_cache.get(token) {
-        // Se comprato meno di 10 minuti fa (600s), BLOCCA
-        if now - last_buy < 600 {
-            return false; // Bloccato
-        }
+impl OpenPosition {
+    /// Secondi alla chiusura per durata massima (0 = scaduta). None = nessun limite per la strategia.
+    pub fn hold_remaining_secs(&self, params: &strategy::StrategyParams, now: i64) -> Option<i64> {
+        params.max_hold_secs(&self.strategy).map(|max| (self.opened_at + max - now).max(0))
     }
-    
-    // Aggiorna timestamp e permetti
-    user_cache.insert(token.to_string(), now);
-    true
 }
 
-// --- HELPER: CONTROLLO DUPLICATI SNIPER ---
-fn is_new_signature(state: &Arc<AppState>, sig: &str) -> bool {
-    let mut cache = state.processed_sigs.lock().unwrap();
-    if cache.contains(sig) { return false; }
-    cache.insert(sig.to_string());
-    // Pulizia periodica semplice: se supera 10k elementi, svuota (per non esplodere RAM)
-    if cache.len() > 10000 { cache.clear(); }
-    true
+// Eventi Live inviati alla Dashboard via WebSocket (/ws)
+#[derive(Clone, serde::Serialize)]
+#[serde(tag = "type", content = "data")]
+pub enum LiveEvent {
+    Gem(GemData),
+    Signal(api::SignalData),
+    Position(OpenPosition),
+    Sell { user_id: String, token: String, pnl_sol: f64, tx_signature: String, reason: String },
+    DcaFill { user_id: String, order_id: i64, token: String, amount_sol: f64, tx_signature: String },
 }
 
-// --- SMART AUTO-BUY (Sicuro) ---
-async fn execute_smart_auto_buy(
-    pool: &sqlx::SqlitePool,
-    net: &Arc<network::NetworkClient>,
-    state: &Arc<AppState>,
-    token_mint: &Pubkey
-) {
-    let users = sqlx::query("SELECT tg_id FROM users WHERE is_active = 1").fetch_all(pool).await;
-    if let Ok(rows) = users {
-        if rows.is_empty() { return; }
-        
-        let mint_str = token_mint.to_string();
-        info!("🤖 AUTO-BUY CHECK: {} utenti potenziali per {}", rows.len(), mint_str);
-
-        // Fetch Pool Keys UNA volta sola
-        let pool_keys_res = raydium::fetch_pool_keys_by_mint(net, token_mint).await;
-        let pool_keys = match pool_keys_res {
-            Ok(k) => k,
-            Err(_) => return, // Se non c'è pool, inutile provare
-        };
-
-        for row in rows {
-            let uid: String = row.get("tg_id");
-
-            // 1. CHECK COOLDOWN (Anti-Loop)
-            if !check_and_set_cooldown(state, &uid, &mint_str) {
-                debug!("🚫 Auto-Buy saltato per {} su {}: Cooldown attivo.", uid, mint_str);
-                continue;
-            }
-
-            let net_c = net.clone();
-            let pool_c = pool.clone();
-            let token_c = mint_str.clone();
-            let keys_c = pool_keys.clone();
-            let mint_key = *token_mint;
-
-            tokio::spawn(async move {
-                if let Ok(payer) = wallet_manager::get_decrypted_wallet(&pool_c, &uid).await {
-                    
-                    // 2. CHECK SALDO & RISK MANAGEMENT
-                    let bal = net_c.get_balance_fast(&payer.pubkey()).await;
-                    let bal_sol = bal as f64 / 1_000_000_000.0;
-                    
-                    // Non comprare se saldo < 0.05 SOL (riserva gas)
-                    if bal_sol < 0.05 { return; }
-
-                    let mut amt_sol = crate::strategy::calculate_investment_amount(bal_sol);
-                    
-                    // TETTO MASSIMO DI SICUREZZA (Max 0.5 SOL per auto-trade)
-                    if amt_sol > 0.5 { amt_sol = 0.5; }
-                    
-                    let amt_lam = (amt_sol * 1_000_000_000.0) as u64;
-
-                    if amt_lam > 0 {
-                        // 3. JUPITER FIRST
-                        let input = "So11111111111111111111111111111111111111112";
-                        let mut success = false;
-
-                        if let Ok(mut tx) = jupiter::get_jupiter_swap_tx(&payer.pubkey().to_string(), input, &token_c, amt_lam, 100).await { // 1% Slippage Jupiter
-                             let bh = net_c.rpc.get_latest_blockhash().await.unwrap();
-                             tx.sign(&[&payer], bh);
-                             if let Ok(sig) = net_c.rpc.send_transaction(&tx).await {
-                                 info!("✅ BUY JUPITER ({}) -> TX: {}", uid, sig);
-                                 let _ = db::record_buy(&pool_c, &uid, &token_c, &sig.to_string(), amt_lam).await;
-                                 success = true;
-                             }
-                        }
-
-                        // 4. RAYDIUM FALLBACK (Con Slippage 2%)
-                        if !success {
-                             // Usa slippage 2% (200 bps) invece di 0
-                             if let Ok(sig) = raydium::execute_swap(&net_c, &payer, &keys_c, mint_key, amt_lam, 200).await {
-                                 info!("⚡ BUY RAYDIUM ({}) -> TX: {}", uid, sig);
-                                 let _ = db::record_buy(&pool_c, &uid, &token_c, &sig, amt_lam).await;
-                             }
-                        }
-                    }
-                }
-            });
+impl LiveEvent {
+    /// Utente destinatario (None = evento pubblico per tutti i client)
+    pub fn user_id(&self) -> Option<&str> {
+        match self {
+            LiveEvent::Position(p) => Some(&p.user_id),
+            LiveEvent::Sell { user_id, .. } => Some(user_id),
+            LiveEvent::DcaFill { user_id, .. } => Some(user_id),
+            _ => None,
         }
     }
 }
 
-// --- MARKET STRATEGY (Filtrato) ---
-async fn run_market_strategy(net: Arc<network::NetworkClient>, state: Arc<AppState>, pool: sqlx::SqlitePool) {
-    let mut history: std::collections::HashMap<String, strategy::MarketData> = std::collections::HashMap::new();
-    
-    loop {
-        for token in WATCHLIST {
-            // 1. Check Dati Mercato Completi
-            if let Ok(mkt) = jupiter::get_token_market_data(token).await {
-                 
-                 // FILTRO LIQUIDITÀ E VOLUME (Anti-Rumore)
-                 // Ignora se Liquidità < 10k o Volume 24h < 50k
-                 if mkt.liquidity_usd < 10000.0 || mkt.volume_24h < 50000.0 { continue; }
-
-                 let data = history.entry(token.to_string()).or_insert_with(|| strategy::MarketData::new(&mkt.symbol));
-                 data.add_tick(mkt.price, mkt.volume_24h); // Usa add_tick con volume
-
-                 // Analisi
-                 let action = strategy::analyze_market(data, 1.0); 
-                 if let strategy::TradeAction::Buy { amount_sol: _, reason } = action {
-                     info!("📈 SEGNALE VALIDO: {} - {}", mkt.symbol, reason);
-                     
-                     if let Ok(mut s) = state.math_signals.lock() {
-                         if !s.iter().any(|x| x.token == *token && (chrono::Utc::now().timestamp() - x.timestamp) < 300) {
-                             s.insert(0, api::SignalData { token: token.to_string(), price: mkt.price, score: 90, reason: reason.clone(), timestamp: chrono::Utc::now().timestamp() });
-                             if s.len() > 20 { s.pop(); }
-                         }
-                     }
-                     
-                     // Esegui Auto-Buy (che ora ha il check cooldown)
-                     let p = pool.clone(); let n = net.clone(); let s = state.clone(); let m = Pubkey::from_str(token).unwrap();
-                     tokio::spawn(async move { execute_smart_auto_buy(&p, &n, &s, &m).await; });
-                 }
-            }
-            sleep(Duration::from_millis(500)).await;
+// STATO CONDIVISO AGGIORNATO
+// Niente std::Mutex nei loop async: mappe DashMap (lock per shard) e feed con RwLock di tokio.
+// Regola: mai tenere un riferimento DashMap attraverso un .await (si clona e si rilascia).
+pub struct AppState {
+    // Indice delle gemme (Sniper + Market Strategy) per mint
+    pub gems: gems::GemIndex,
+    pub math_signals: RwLock<Vec<api::SignalData>>,
+    // Acquisti in corso ("user:token"): copre la finestra tra controllo DB e record_buy
+    pub buys_in_flight: DashSet<String>,
+    // Coda degli Auto-Buy eseguiti dai worker (concorrenza limitata, round-robin per utente)
+    pub trade_queue: trade_queue::TradeQueue,
+    // Firme già viste da Sniper e Copy-Trading (filtro in RAM davanti alla tabella processed_signatures)
+    pub processed_sigs: sig_cache::SignatureCache,
+    // Posizioni aperte (Trade ID -> Stato Trailing), ricaricate dal DB all'avvio
+    pub open_positions: DashMap<i64, OpenPosition>,
+    // Ultimi prezzi USD (posizioni aperte + watchlist), aggiornati a ogni giro del Position Manager
+    pub spot_prices: DashMap<String, f64>,
+    // Icone token già ridimensionate (GET /token/{mint}/icon)
+    pub token_icons: token_icons::IconCache,
+    // Swap whale recenti per token (Whale Monitor), letti dalla Market Strategy
+    pub whale_flows: DashMap<String, whale::WhaleFlow>,
+    // Ultime candele della Market Strategy per token (analisi live di GET /debug/analysis)
+    pub market_data: DashMap<String, strategy::MarketData>,
+    // ATR% dei token in posizione (valore, momento del calcolo) per gli stop atr/hybrid
+    pub token_atr: DashMap<String, (Option<f64>, i64)>,
+    // Token nel wallet senza trade registrato (User -> Token), aggiornati dal Reconciler
+    pub untracked_holdings: DashMap<String, Vec<reconcile::UntrackedHolding>>,
+    // Sottoscrizione WebSocket dello Sniper attiva (Health Check)
+    pub sniper_ws_connected: AtomicBool,
+    // Ultimo controllo delle dipendenze esterne (None = primo giro non ancora concluso)
+    pub health: RwLock<Option<health::HealthReport>>,
+    // Canale Push per i client WebSocket
+    pub events: broadcast::Sender<LiveEvent>,
+    // Segnale di chiusura osservato da tutti i loop (true = shutdown in corso)
+    pub shutdown: watch::Sender<bool>,
+}
+
+// Stato vuoto: posizioni e segnali si ricaricano dal DB all'avvio
+impl Default for AppState {
+    fn default() -> Self {
+        Self {
+            gems: gems::GemIndex::default(),
+            math_signals: RwLock::new(Vec::new()),
+            buys_in_flight: DashSet::new(),
+            trade_queue: trade_queue::TradeQueue::from_env(),
+            untracked_holdings: DashMap::new(),
+            processed_sigs: sig_cache::SignatureCache::default(),
+            spot_prices: DashMap::new(),
+            token_icons: token_icons::IconCache::default(),
+            whale_flows: DashMap::new(),
+            market_data: DashMap::new(),
+            token_atr: DashMap::new(),
+            open_positions: DashMap::new(),
+            sniper_ws_connected: AtomicBool::new(false),
+            health: RwLock::new(None),
+            events: broadcast::channel(256).0,
+            shutdown: watch::channel(false).0,
         }
-        
-        if history.len() > 50 { history.clear(); }
-        sleep(Duration::from_secs(30)).await;
     }
 }
 
-// --- SNIPER LISTENER (Anti-Rug e Anti-Doppioni) ---
-async fn run_sniper_listener(net: Arc<network::NetworkClient>, state: Arc<AppState>, pool: sqlx::SqlitePool) {
-    let raydium_id = Pubkey::from_str(crate::raydium::RAYDIUM_V4_PROGRAM_ID).unwrap();
-    let mut ws_client = net.clone();
-
-    loop {
-        match ws_client.pubsub.logs_subscribe(
-             RpcTransactionLogsFilter::Mentions(vec![raydium_id.to_string()]),
-             RpcTransactionLogsConfig { commitment: Some(CommitmentConfig::processed()) }
-        ).await {
-            Ok((mut stream, _)) => {
-                info!("✅ Sniper Attivo.");
-                while let Some(log) = stream.next().await {
-                    if log.value.logs.iter().any(|l| l.contains("initialize2")) {
-                        let sig_str = log.value.signature;
-                        
-                        // 1. CHECK DUPLICATI
-                        if !is_new_signature(&state, &sig_str) { continue; }
-
-                        let n_an = net.clone(); let s_an = state.clone(); let p_an = pool.clone();
-                        
-                        tokio::spawn(async move {
-                            if let Ok(sig) = solana_sdk::signature::Signature::from_str(&sig_str) {
-                                if let Ok(tx) = n_an.rpc.get_transaction_with_config(&sig, RpcTransactionConfig { encoding: Some(UiTransactionEncoding::Json), commitment: Some(CommitmentConfig::confirmed()), max_supported_transaction_version: Some(0) }).await {
-                                    if let Some(meta) = tx.transaction.meta {
-                                        let balances = match meta.post_token_balances { OptionSerializer::Some(b) => b, _ => vec![] };
-                                        let wsol = "So11111111111111111111111111111111111111112";
-                                        
-                                        for b in balances {
-                                            let mint = b.mint;
-                                            if mint != wsol && b.ui_token_amount.decimals > 0 {
-                                                if let Ok(pk) = Pubkey::from_str(&mint) {
-                                                    // 2. CHECK SAFETY + ANTI-HONEYPOT (Simulazione)
-                                                    // Qui chiameremo la nuova safety::full_check
-                                                    if let Ok(rep) = safety::check_token_safety(&n_an, &pk).await {
-                                                        if rep.is_safe {
-                                                            sleep(Duration::from_secs(2)).await;
-                                                            if let Ok(mkt) = jupiter::get_token_market_data(&mint).await {
-                                                                // 3. FILTRO QUALITÀ RIGIDO
-                                                                if mkt.liquidity_usd > 5000.0 && mkt.price > 0.0 {
-                                                                    info!("💎 GEMMA NUOVA: {} (${:.6}) Liq: ${:.0}", mkt.symbol, mkt.price, mkt.liquidity_usd);
-                                                                    
-                                                                    if let Ok(mut g) = s_an.found_gems.lock() {
-                                                                        g.insert(0, GemData { token: mint.clone(), symbol: mkt.symbol, price: mkt.price, safety_score: 90, timestamp: chrono::Utc::now().timestamp(), source: "SNIPER".into() });
-                                                                        if g.len() > 50 { g.pop(); }
-                                                                    }
-                                                                    
-                                                                    execute_smart_auto_buy(&p_an, &n_an, &s_an, &pk).await;
-                                                                }
-                                                            }
-                                                        }
-                                                    }
-                                                }
-                                                break;
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        });
-                    }
-                }
-            },
-            Err(_) => sleep(Duration::from_secs(5)).await
+impl AppState {
+    /// Pubblica un evento live (ignorato se nessun client è connesso)
+    pub fn publish(&self, event: LiveEvent) {
+        let _ = self.events.send(event);
+    }
+
+    /// Copia delle posizioni aperte (tutte o di un solo utente)
+    pub fn positions_snapshot(&self, user_id: Option<&str>) -> Vec<OpenPosition> {
+        self.open_positions.iter()
+            .filter(|p| user_id.is_none_or(|u| p.user_id == u))
+            .map(|p| p.value().clone())
+            .collect()
+    }
+
+    pub fn is_shutting_down(&self) -> bool {
+        *self.shutdown.borrow()
+    }
+
+    /// Si risolve quando parte lo shutdown
+    pub async fn shutdown_signal(&self) {
+        let mut rx = self.shutdown.subscribe();
+        let _ = rx.wait_for(|closing| *closing).await;
+    }
+
+    /// Pausa interrompibile dei loop: true se nel frattempo è partito lo shutdown
+    pub async fn sleep_or_shutdown(&self, duration: Duration) -> bool {
+        tokio::select! {
+            _ = sleep(duration) => self.is_shutting_down(),
+            _ = self.shutdown_signal() => true,
         }
     }
 }
 
-async fn monitor_open_positions(pool: &sqlx::SqlitePool, net: &Arc<network::NetworkClient>) {
-    // ... (Codice identico a prima, ma assicurati di chiamare execute_sell se serve)
+// Attesa massima per i task in chiusura prima di salvare e uscire
+const SHUTDOWN_TIMEOUT_SECS: u64 = 30;
+
+// --- BACKTEST DA RIGA DI COMANDO ---
+async fn run_backtest_cli(args: &[String]) {
+    let token = match args.first() {
+        Some(t) => t.clone(),
+        None => { eprintln!("Uso: god_sniper backtest <MINT> [INTERVALLO=5m] [GIORNI=7]"); return; }
+    };
+    let params = backtest::BacktestParams {
+        token,
+        interval: args.get(1).cloned().unwrap_or_else(|| "5m".into()),
+        days: args.get(2).and_then(|d| d.parse().ok()).unwrap_or(7),
+        initial_sol: 1.0,
+        fee_bps: 100,
+        strategy: strategy::StrategyParams::default(),
+    };
+
+    match backtest::run_backtest(&params).await {
+        Ok(r) => {
+            println!("📊 BACKTEST {} ({}, {} candele)", r.token, r.interval, r.candles);
+            for t in &r.trades {
+                println!("   {} -> {} | {:.8} -> {:.8} | {:+.4} SOL | {}", t.entry_time, t.exit_time, t.entry_price, t.exit_price, t.pnl_sol, t.reason);
+            }
+            println!("   Trade: {} | Win Rate: {:.1}% | P&L: {:+.4} SOL ({:+.2}%) | Max Drawdown: {:.2}%",
+                r.trades.len(), r.win_rate, r.total_pnl_sol, r.total_pnl_pct, r.max_drawdown_pct);
+        },
+        Err(e) => eprintln!("❌ Backtest fallito: {}", e),
+    }
 }
 
 #[tokio::main]
 async fn main() {
     dotenv().ok();
-    if env::var("RUST_LOG").is_err() { env::set_var("RUST_LOG", "info"); }
-    env_logger::init();
+    logging::init();
+
+    // SUBCOMMAND: god_sniper backtest <MINT> [INTERVALLO] [GIORNI]
+    let args: Vec<String> = env::args().collect();
+    if args.get(1).map(|a| a.as_str()) == Some("backtest") {
+        run_backtest_cli(&args[2..]).await;
+        return;
+    }
+
     info!("🚀 GOD SNIPER: Ultimate Safe Engine Avviato.");
 
     let _master = env::var("MASTER_KEY").expect("Manca KEY");
@@ -293,31 +295,96 @@ async fn main() {
     let pool = db::connect().await;
     let net = Arc::new(network::init_clients().await);
 
-    let state = Arc::new(AppState { 
-        found_gems: Mutex::new(Vec::new()), 
-        math_signals: Mutex::new(Vec::new()),
-        buy_cooldowns: Mutex::new(HashMap::new()), // Nuovo
-        processed_sigs: Mutex::new(HashSet::new()), // Nuovo
-    });
-
-    let p1=pool.clone(); let n1=net.clone();
-    tokio::spawn(async move { telegram_bot::start_bot(p1, n1).await; });
+    let state = Arc::new(AppState::default());
 
-    let p2=pool.clone(); let n2=net.clone(); let s2=state.clone();
-    tokio::spawn(async move { api::start_server(p2, n2, s2).await; });
+    // Ripristino posizioni aperte (Trailing Stop non perso dopo crash/redeploy)
+    if let Ok(saved) = db::load_positions(&pool).await {
+        info!("♻️ Ripristinate {} posizioni aperte dal DB.", saved.len());
+        for pos in saved { state.open_positions.insert(pos.trade_id, pos); }
+    }
+    // Ultimi segnali per la Dashboard (lo storico resta nel DB)
+    if let Ok(recent) = db::get_signals(&pool, 0, None, tasks::strategy::MAX_LIVE_SIGNALS as i64).await {
+        *state.math_signals.write().await = recent.into_iter().map(api::SignalData::from).collect();
+    }
 
-    let p3=pool.clone(); let n3=net.clone(); let s3=state.clone();
-    tokio::spawn(async move { run_market_strategy(n3, s3, p3).await; });
+    // Soglie della discovery lette subito: una configurazione non valida si vede all'avvio
+    info!("🔎 Filtri gemme: {:?}", gem_filters::current());
+
+    // Handle dei task (sotto supervisor): allo shutdown si attende che finiscano il lavoro in corso
+    let ctx = tasks::Context::new(pool.clone(), net.clone(), state.clone());
+    let mut workers = vec![
+        tasks::supervise("telegram", ctx.clone(), |c| telegram_bot::start_bot(c.pool, c.net, c.state)),
+        tasks::supervise("api", ctx.clone(), |c| api::start_server(c.pool, c.net, c.state)),
+        tasks::supervise("strategy", ctx.clone(), tasks::strategy::run),
+        tasks::supervise("sniper", ctx.clone(), tasks::sniper::run),
+        tasks::supervise("gem_filters", ctx.clone(), |c| gem_filters::run_reload_on_sighup(c.state)),
+        tasks::supervise("positions", ctx.clone(), tasks::positions::run),
+        tasks::supervise("dca", ctx.clone(), tasks::dca::run),
+        tasks::supervise("limit_orders", ctx.clone(), tasks::limit_orders::run),
+        tasks::supervise("metrics", ctx.clone(), |c| metrics::serve(c.state)),
+        tasks::supervise("reports", ctx.clone(), tasks::reports::run),
+        tasks::supervise("risk", ctx.clone(), |c| risk::run_risk_monitor(c.pool, c.net, c.state)),
+        tasks::supervise("copy_trade", ctx.clone(), |c| copy_trade::run_copy_trader(c.pool, c.net, c.state)),
+        tasks::supervise("whale", ctx.clone(), |c| whale::run_whale_monitor(c.pool, c.state)),
+        tasks::supervise("grid", ctx.clone(), |c| grid::run_grid_trader(c.pool, c.net, c.state)),
+        tasks::supervise("withdrawals", ctx.clone(), |c| withdrawals::run_approval_expiry(c.pool, c.state)),
+        tasks::supervise("sol_oracle", ctx.clone(), |c| price_oracle::run_sol_oracle(c.state)),
+        tasks::supervise("deposits", ctx.clone(), |c| deposits::run_deposit_watcher(c.pool, c.net, c.state)),
+        tasks::supervise("dust", ctx.clone(), |c| dust::run_dust_sweeper(c.pool, c.net, c.state)),
+        tasks::supervise("sell_retry", ctx.clone(), tasks::sell_retry::run),
+        tasks::supervise("rug", ctx.clone(), |c| rug::run_rug_monitor(c.pool, c.net, c.state)),
+        tasks::supervise("pending_expiry", ctx.clone(), |c| signals::run_pending_expiry(c.pool, c.state)),
+        tasks::supervise("reconciler", ctx.clone(), |c| reconcile::run_reconciler(c.pool, c.net, c.state)),
+        tasks::supervise("sniper_followup", ctx.clone(), |c| sniper_stats::run_followup(c.pool, c.state)),
+        tasks::supervise("health", ctx.clone(), |c| health::run_health_checks(c.pool, c.net, c.state)),
+        tasks::supervise("offramp", ctx.clone(), |c| offramp::run_offramp_worker(c.pool, c.net, c.state)),
+        tasks::supervise("auto_skim", ctx.clone(), |c| auto_skim::run_auto_skim(c.pool, c.net, c.state)),
+    ];
+
+    for id in 0..state.trade_queue.workers {
+        workers.push(tasks::supervise("trade_worker", ctx.clone(), move |c| trade_queue::run_trade_worker(c.pool, c.net, c.state, id)));
+    }
 
-    let p4=pool.clone(); let n4=net.clone(); let s4=state.clone();
-    tokio::spawn(async move { run_sniper_listener(n4, s4, p4).await; });
+    wait_for_exit_signal().await;
+    info!("🛑 Shutdown richiesto: stop dei task...");
+    let _ = state.shutdown.send(true);
 
-    // let p5=pool.clone(); let n5=net.clone();
-    // tokio::spawn(async move { run_position_manager(p5, n5).await; }); // Attiva se hai il modulo completo
+    // Gli swap già inviati terminano, quelli non ancora partiti vengono annullati
+    let drain = async {
+        futures::future::join_all(workers).await;
+        while !state.buys_in_flight.is_empty() {
+            sleep(Duration::from_millis(200)).await;
+        }
+    };
+    if tokio::time::timeout(Duration::from_secs(SHUTDOWN_TIMEOUT_SECS), drain).await.is_err() {
+        warn!("⚠️ Alcuni task non si sono fermati entro {}s.", SHUTDOWN_TIMEOUT_SECS);
+    }
 
-    match tokio::signal::ctrl_c().await {
-        Ok(()) => info!("🛑 Chiusura sicura."),
-        Err(_) => {}
+    // Flush finale delle posizioni (Trailing Stop aggiornato al riavvio)
+    let positions = state.positions_snapshot(None);
+    let mut saved = 0;
+    for pos in &positions {
+        match db::save_position(&pool, pos).await {
+            Ok(_) => saved += 1,
+            Err(e) => warn!("⚠️ Posizione #{} non salvata: {}", pos.trade_id, e),
+        }
     }
+    info!("💾 Salvate {}/{} posizioni aperte.", saved, positions.len());
+
     pool.close().await;
-}
\ No newline at end of file
+    info!("🛑 Chiusura sicura.");
+}
+
+// Ctrl+C in locale, SIGTERM da Docker/Railway
+async fn wait_for_exit_signal() {
+    #[cfg(unix)]
+    {
+        let mut term = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()).expect("Handler SIGTERM");
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {},
+            _ = term.recv() => {},
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = tokio::signal::ctrl_c().await;
+}
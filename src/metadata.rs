@@ -0,0 +1,88 @@
+use std::str::FromStr;
+use std::sync::Arc;
+use chrono::{Duration, Utc};
+use log::debug;
+use solana_sdk::pubkey::Pubkey;
+use crate::{db, jupiter, network::NetworkClient};
+
+// --- METADATI TOKEN (Nome, Simbolo, Logo) ---
+// 1. Cache nella tabella token_metadata, rinfrescata dopo METADATA_TTL_DAYS
+// 2. Fonte principale: account Metaplex on-chain (PDA "metadata" del mint), logo dal JSON dell'URI
+// 3. Riserva: DexScreener (solo simbolo, spesso assente per i token appena nati)
+// 4. Nessuna fonte: segnaposto dal mint, NON salvato (al prossimo giro si riprova)
+
+const METAPLEX_PROGRAM_ID: &str = "metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s";
+const METADATA_TTL_DAYS: i64 = 7;
+const URI_TIMEOUT_SECS: u64 = 5;
+// key (1) + update_authority (32) + mint (32): poi name, symbol, uri come stringhe Borsh
+const METADATA_STRINGS_OFFSET: usize = 65;
+
+/// Metadati del token: cache, poi Metaplex, poi DexScreener
+pub async fn resolve(pool: &db::DbPool, net: &Arc<NetworkClient>, mint: &str) -> db::TokenMetadata {
+    let fresh_since = Utc::now() - Duration::days(METADATA_TTL_DAYS);
+    if let Ok(Some(meta)) = db::get_token_metadata(pool, mint, fresh_since).await {
+        return meta;
+    }
+
+    let found = match fetch_metaplex(net, mint).await {
+        Some(meta) => Some(meta),
+        None => fetch_dexscreener(mint).await,
+    };
+    match found {
+        Some(meta) => {
+            if let Err(e) = db::save_token_metadata(pool, &meta).await { debug!("Metadati {} non salvati: {}", mint, e); }
+            meta
+        },
+        None => placeholder(mint),
+    }
+}
+
+/// Solo il simbolo (gemme, alert, log)
+pub async fn symbol(pool: &db::DbPool, net: &Arc<NetworkClient>, mint: &str) -> String {
+    resolve(pool, net, mint).await.symbol
+}
+
+fn placeholder(mint: &str) -> db::TokenMetadata {
+    let short: String = mint.chars().take(4).collect();
+    db::TokenMetadata { mint: mint.to_string(), name: format!("{}…", short), symbol: format!("{}…", short), logo_uri: None, source: "none".into() }
+}
+
+async fn fetch_metaplex(net: &Arc<NetworkClient>, mint: &str) -> Option<db::TokenMetadata> {
+    let program = Pubkey::from_str(METAPLEX_PROGRAM_ID).ok()?;
+    let mint_pk = Pubkey::from_str(mint).ok()?;
+    let (pda, _) = Pubkey::find_program_address(&[b"metadata", program.as_ref(), mint_pk.as_ref()], &program);
+    let data = net.rpc.get_account_data(&pda).await.ok()?;
+    let (name, symbol, uri) = parse_metadata(&data)?;
+    if symbol.is_empty() { return None; }
+
+    let logo_uri = match uri.starts_with("https://") || uri.starts_with("http://") {
+        true => fetch_logo(&uri).await,
+        false => None,
+    };
+    Some(db::TokenMetadata { mint: mint.to_string(), name: if name.is_empty() { symbol.clone() } else { name }, symbol, logo_uri, source: "metaplex".into() })
+}
+
+// Stringhe Borsh (u32 LE + byte) riempite di \0 fino alla lunghezza massima
+fn parse_metadata(data: &[u8]) -> Option<(String, String, String)> {
+    let mut offset = METADATA_STRINGS_OFFSET;
+    let mut next = || {
+        let len = u32::from_le_bytes(data.get(offset..offset + 4)?.try_into().ok()?) as usize;
+        let bytes = data.get(offset + 4..offset + 4 + len)?;
+        offset += 4 + len;
+        Some(String::from_utf8_lossy(bytes).trim_matches(char::from(0)).trim().to_string())
+    };
+    Some((next()?, next()?, next()?))
+}
+
+// Logo dal JSON off-chain ({"image": "..."})
+async fn fetch_logo(uri: &str) -> Option<String> {
+    let client = reqwest::Client::builder().timeout(std::time::Duration::from_secs(URI_TIMEOUT_SECS)).build().ok()?;
+    let json: serde_json::Value = client.get(uri).send().await.ok()?.json().await.ok()?;
+    json.get("image").and_then(|i| i.as_str()).filter(|i| i.starts_with("http")).map(str::to_string)
+}
+
+async fn fetch_dexscreener(mint: &str) -> Option<db::TokenMetadata> {
+    let symbol = jupiter::get_token_market_data(mint).await.ok()?.symbol;
+    if symbol.is_empty() || symbol == "UNK" { return None; }
+    Some(db::TokenMetadata { mint: mint.to_string(), name: symbol.clone(), symbol, logo_uri: None, source: "dexscreener".into() })
+}
@@ -0,0 +1,187 @@
+use prometheus::proto::{MetricFamily, MetricType};
+use prometheus::{Encoder, Histogram, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, Opts, Registry, TextEncoder};
+use std::sync::{Arc, LazyLock};
+use warp::Filter;
+use log::info;
+use serde::Serialize;
+use crate::AppState;
+
+// --- METRICHE (Prometheus, porta dedicata METRICS_PORT, default 9100) ---
+// Servono a capire quando Sniper o Jupiter falliscono "in silenzio".
+
+pub struct Metrics {
+    registry: Registry,
+    pub trades_opened: IntCounter,
+    pub trades_closed: IntCounterVec,
+    pub swaps: IntCounterVec,
+    pub rpc_latency: HistogramVec,
+    pub sniper_ws_reconnects: IntCounter,
+    pub sniper_ws_downtime: Histogram,
+    pub sniper_replayed_launches: IntCounter,
+    pub dashboard_ws_clients: IntGauge,
+    pub open_positions: IntGauge,
+    pub api_latency: HistogramVec,
+    pub rate_limit: IntCounterVec,
+    pub position_cycle: Histogram,
+    pub position_check: Histogram,
+    pub price_cache: IntCounterVec,
+    pub price_cache_entries: IntGauge,
+    pub simulations: IntCounterVec,
+    pub trade_queue_depth: IntGauge,
+    pub trade_queue_jobs: IntCounterVec,
+    pub trade_queue_wait: Histogram,
+    pub trade_workers_busy: IntGauge,
+    pub swap_slippage: HistogramVec,
+    pub swap_slippage_exceeded: IntCounterVec,
+    pub task_panics: IntCounterVec,
+    pub gem_filter_rejections: IntCounterVec,
+}
+
+// Contatori di errore dall'avvio, per l'area Admin (stessi dati di /metrics, già aggregati)
+#[derive(Serialize, Clone, Debug)]
+pub struct FailureCounters {
+    pub swaps_failed: u64,
+    pub simulations_rejected: u64,
+    // Simulazione non eseguita: RPC non raggiungibile o in errore
+    pub simulations_rpc_error: u64,
+    pub price_api_errors: u64,
+    pub api_5xx: u64,
+    pub rate_limited: u64,
+    pub sniper_ws_reconnects: u64,
+}
+
+pub static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new_custom(Some("god_sniper".into()), None).expect("registry metriche");
+
+        let trades_opened = IntCounter::new("trades_opened_total", "Trade aperti (Auto-Buy e manuali)").unwrap();
+        let trades_closed = IntCounterVec::new(Opts::new("trades_closed_total", "Trade chiusi per esito"), &["result"]).unwrap();
+        let swaps = IntCounterVec::new(Opts::new("swaps_total", "Tentativi di swap per route, lato ed esito"), &["route", "side", "result"]).unwrap();
+        let rpc_latency = HistogramVec::new(
+            HistogramOpts::new("rpc_latency_seconds", "Latenza chiamate RPC Solana").buckets(vec![0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0]),
+            &["method"],
+        ).unwrap();
+        let sniper_ws_reconnects = IntCounter::new("sniper_ws_reconnects_total", "Riconnessioni del WebSocket Sniper").unwrap();
+        let sniper_ws_downtime = Histogram::with_opts(
+            HistogramOpts::new("sniper_ws_downtime_seconds", "Durata delle disconnessioni del WebSocket Sniper").buckets(vec![1.0, 5.0, 15.0, 30.0, 60.0, 300.0, 900.0]),
+        ).unwrap();
+        let sniper_replayed_launches = IntCounter::new("sniper_replayed_launches_total", "Lanci recuperati dopo una disconnessione dello Sniper").unwrap();
+        let dashboard_ws_clients = IntGauge::new("dashboard_ws_clients", "Client Dashboard connessi a /ws").unwrap();
+        let open_positions = IntGauge::new("open_positions", "Posizioni aperte tracciate dal Position Manager").unwrap();
+        let api_latency = HistogramVec::new(
+            HistogramOpts::new("api_request_duration_seconds", "Latenza richieste API").buckets(vec![0.01, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0]),
+            &["method", "status"],
+        ).unwrap();
+        let rate_limit = IntCounterVec::new(Opts::new("api_rate_limit_total", "Decisioni del rate limiter"), &["scope", "result"]).unwrap();
+        let position_cycle = Histogram::with_opts(
+            HistogramOpts::new("position_manager_cycle_seconds", "Durata di un giro completo del Position Manager").buckets(vec![0.1, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0]),
+        ).unwrap();
+        let position_check = Histogram::with_opts(
+            HistogramOpts::new("position_check_latency_seconds", "Tempo dall'inizio del giro al controllo di ogni posizione (attesa del proprio turno inclusa)")
+                .buckets(vec![0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0]),
+        ).unwrap();
+        let price_cache = IntCounterVec::new(Opts::new("price_cache_lookups_total", "Richieste prezzo per esito della cache (hit, miss, coalesced, error)"), &["result"]).unwrap();
+        let price_cache_entries = IntGauge::new("price_cache_entries", "Mint presenti nella cache prezzi").unwrap();
+        let simulations = IntCounterVec::new(Opts::new("tx_simulations_total", "Simulazioni pre-invio per esito (ok, failed, low_out, error)"), &["result"]).unwrap();
+        let trade_queue_depth = IntGauge::new("trade_queue_depth", "Auto-Buy in attesa nella coda acquisti").unwrap();
+        let trade_queue_jobs = IntCounterVec::new(Opts::new("trade_queue_jobs_total", "Job della coda acquisti per esito (enqueued, rejected_full, rejected_user, expired, executed, panicked)"), &["result"]).unwrap();
+        let trade_queue_wait = Histogram::with_opts(
+            HistogramOpts::new("trade_queue_wait_seconds", "Attesa in coda prima dell'esecuzione dell'Auto-Buy").buckets(vec![0.01, 0.1, 0.5, 1.0, 2.5, 5.0, 15.0, 30.0, 60.0]),
+        ).unwrap();
+        let trade_workers_busy = IntGauge::new("trade_workers_busy", "Worker della coda acquisti impegnati in un Auto-Buy").unwrap();
+        let swap_slippage = HistogramVec::new(
+            HistogramOpts::new("swap_realized_slippage_bps", "Slippage realizzato degli swap confermati rispetto alla quote (bps, negativo = fill migliore)")
+                .buckets(vec![-100.0, 0.0, 25.0, 50.0, 100.0, 200.0, 500.0, 1000.0, 2500.0]),
+            &["side"],
+        ).unwrap();
+        let swap_slippage_exceeded = IntCounterVec::new(Opts::new("swap_slippage_exceeded_total", "Swap confermati con slippage oltre la tolleranza impostata"), &["route"]).unwrap();
+        let task_panics = IntCounterVec::new(Opts::new("task_panics_total", "Panic dei task in background (riavviati dal supervisor)"), &["task"]).unwrap();
+        let gem_filter_rejections = IntCounterVec::new(Opts::new("gem_filter_rejections_total", "Gemme dello Sniper scartate dai filtri qualità, per filtro"), &["filter"]).unwrap();
+
+        registry.register(Box::new(trades_opened.clone())).unwrap();
+        registry.register(Box::new(trades_closed.clone())).unwrap();
+        registry.register(Box::new(swaps.clone())).unwrap();
+        registry.register(Box::new(rpc_latency.clone())).unwrap();
+        registry.register(Box::new(sniper_ws_reconnects.clone())).unwrap();
+        registry.register(Box::new(sniper_ws_downtime.clone())).unwrap();
+        registry.register(Box::new(sniper_replayed_launches.clone())).unwrap();
+        registry.register(Box::new(dashboard_ws_clients.clone())).unwrap();
+        registry.register(Box::new(open_positions.clone())).unwrap();
+        registry.register(Box::new(api_latency.clone())).unwrap();
+        registry.register(Box::new(rate_limit.clone())).unwrap();
+        registry.register(Box::new(position_cycle.clone())).unwrap();
+        registry.register(Box::new(position_check.clone())).unwrap();
+        registry.register(Box::new(price_cache.clone())).unwrap();
+        registry.register(Box::new(price_cache_entries.clone())).unwrap();
+        registry.register(Box::new(simulations.clone())).unwrap();
+        registry.register(Box::new(trade_queue_depth.clone())).unwrap();
+        registry.register(Box::new(trade_queue_jobs.clone())).unwrap();
+        registry.register(Box::new(trade_queue_wait.clone())).unwrap();
+        registry.register(Box::new(trade_workers_busy.clone())).unwrap();
+        registry.register(Box::new(swap_slippage.clone())).unwrap();
+        registry.register(Box::new(swap_slippage_exceeded.clone())).unwrap();
+        registry.register(Box::new(task_panics.clone())).unwrap();
+        registry.register(Box::new(gem_filter_rejections.clone())).unwrap();
+
+        Self { registry, trades_opened, trades_closed, swaps, rpc_latency, sniper_ws_reconnects, sniper_ws_downtime, sniper_replayed_launches, dashboard_ws_clients, open_positions, api_latency, rate_limit, position_cycle, position_check, price_cache, price_cache_entries, simulations, trade_queue_depth, trade_queue_jobs, trade_queue_wait, trade_workers_busy, swap_slippage, swap_slippage_exceeded, task_panics, gem_filter_rejections }
+    }
+
+    /// Timer RPC: osserva la latenza quando esce dallo scope
+    pub fn rpc_timer(&self, method: &str) -> prometheus::HistogramTimer {
+        self.rpc_latency.with_label_values(&[method]).start_timer()
+    }
+
+    /// Riepilogo dei contatori di errore
+    pub fn failure_counters(&self) -> FailureCounters {
+        let families = self.registry.gather();
+        FailureCounters {
+            swaps_failed: family_total(&families, "swaps_total", "result", |v| v == "error"),
+            simulations_rejected: family_total(&families, "tx_simulations_total", "result", |v| v == "failed" || v == "low_out"),
+            simulations_rpc_error: family_total(&families, "tx_simulations_total", "result", |v| v == "error"),
+            price_api_errors: family_total(&families, "price_cache_lookups_total", "result", |v| v == "error"),
+            api_5xx: family_total(&families, "api_request_duration_seconds", "status", |v| v.starts_with('5')),
+            rate_limited: family_total(&families, "api_rate_limit_total", "result", |v| v == "rejected"),
+            sniper_ws_reconnects: self.sniper_ws_reconnects.get(),
+        }
+    }
+
+    /// Esporta tutte le metriche in formato testo Prometheus
+    pub fn render(&self, state: &AppState) -> String {
+        self.open_positions.set(state.open_positions.len() as i64);
+        self.price_cache_entries.set(crate::price_cache::PRICE_CACHE.entries() as i64);
+
+        let mut buf = Vec::new();
+        let _ = TextEncoder::new().encode(&self.registry.gather(), &mut buf);
+        String::from_utf8(buf).unwrap_or_default()
+    }
+}
+
+/// Somma dei campioni (contatori o conteggi degli istogrammi) con l'etichetta che soddisfa `accept`
+fn family_total(families: &[MetricFamily], name: &str, label: &str, accept: impl Fn(&str) -> bool) -> u64 {
+    let full_name = format!("god_sniper_{}", name);
+    families.iter()
+        .filter(|f| f.get_name() == full_name)
+        .flat_map(|f| {
+            let histogram = f.get_field_type() == MetricType::HISTOGRAM;
+            f.get_metric().iter().map(move |m| (histogram, m))
+        })
+        .filter(|(_, m)| m.get_label().iter().any(|l| l.get_name() == label && accept(l.get_value())))
+        .map(|(histogram, m)| if histogram { m.get_histogram().get_sample_count() } else { m.get_counter().get_value() as u64 })
+        .sum()
+}
+
+/// Server metriche separato dall'API pubblica (da non esporre su Internet)
+pub async fn serve(state: Arc<AppState>) {
+    let port: u16 = std::env::var("METRICS_PORT").ok().and_then(|p| p.parse().ok()).unwrap_or(9100);
+
+    let closing = state.clone();
+    let route = warp::path("metrics")
+        .and(warp::get())
+        .map(move || warp::reply::with_header(METRICS.render(&state), "content-type", "text/plain; version=0.0.4"));
+
+    info!("📈 Metrics Server: Ready (Port {})", port);
+    let (_, server) = warp::serve(route).bind_with_graceful_shutdown(([0, 0, 0, 0], port), async move { closing.shutdown_signal().await });
+    server.await;
+}
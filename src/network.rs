@@ -4,19 +4,164 @@ use solana_client::nonblocking::pubsub_client::PubsubClient;
 use solana_client::tpu_client::{TpuClient, TpuClientConfig};
 // Importiamo i tipi necessari per definire i Generics del TPU
 use solana_quic_client::{QuicPool, QuicConnectionManager, QuicConfig}; 
+use solana_client::rpc_config::{RpcSimulateTransactionAccountsConfig, RpcSimulateTransactionConfig};
+use solana_client::rpc_client::SerializableTransaction;
+use solana_account_decoder::UiAccountEncoding;
+use solana_sdk::account::Account;
 use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::compute_budget::ComputeBudgetInstruction;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::message::Message;
 use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::Transaction;
+use std::error::Error;
 use std::sync::Arc;
 use std::env;
-use log::info;
+use dashmap::DashMap;
+use serde::Serialize;
+use tokio::sync::RwLock;
+use log::{debug, info};
+
+// Margine sul SOL ricevuto in vendita: fee, priority fee e rent degli account temporanei (wSOL)
+const SIM_SOL_FEE_MARGIN: u64 = 2_000_000;
+
+// Priority fee (microlamports per CU): campioni recenti validi per FEE_CACHE_SECS, poi si ricampiona
+const FEE_CACHE_SECS: i64 = 10;
+// Limiti alla fee stimata: mai zero (TX ignorate in congestione), mai oltre il tetto (picchi anomali)
+const MIN_PRIORITY_FEE: u64 = 10_000;
+const MAX_PRIORITY_FEE: u64 = 5_000_000;
+
+// Percentile richiesto da ogni percorso di invio: più alto = più veloce e più caro
+#[derive(Clone, Copy, Debug)]
+pub enum FeePercentile { P50, P75, P90 }
+
+// --- COMPUTE BUDGET (Profili per tipo di TX) ---
+// 1. Limite CU: CU_LIMIT_<PROFILO> fisso (es. CU_LIMIT_SWAP=250000), altrimenti stimato simulando la TX
+//    (+CU_ESTIMATE_MARGIN_PCT), altrimenti il default del profilo. CU_ESTIMATE=false salta la simulazione.
+// 2. Prezzo CU: priority fee al percentile del profilo, fallback se il nodo non dà una stima
+// Limite più basso = fee più bassa e più probabilità di entrare nel blocco.
+
+// Massimo per TX imposto dal runtime
+const MAX_COMPUTE_UNITS: u32 = 1_400_000;
+// Margine sulla stima: lo stato on-chain può cambiare tra simulazione e inclusione
+const CU_ESTIMATE_MARGIN_PCT: u32 = 20;
+// Istruzioni ComputeBudget della TX finale, oltre ai CU stimati
+const COMPUTE_BUDGET_IX_UNITS: u32 = 300;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TxProfile {
+    // SOL nativo (prelievi, payout referral)
+    Transfer,
+    // Token SPL / Token-2022 con ATA di destinazione creato se manca (off-ramp)
+    TokenTransfer,
+    // Swap costruiti in locale (Raydium diretto)
+    Swap,
+    // Chiusura ATA vuoti (recupero rent, a lotti)
+    CloseAta,
+}
+
+impl TxProfile {
+    fn env_key(&self) -> &'static str {
+        match self {
+            TxProfile::Transfer => "CU_LIMIT_TRANSFER",
+            TxProfile::TokenTransfer => "CU_LIMIT_TOKEN_TRANSFER",
+            TxProfile::Swap => "CU_LIMIT_SWAP",
+            TxProfile::CloseAta => "CU_LIMIT_CLOSE_ATA",
+        }
+    }
+
+    // Limite senza stima: abbondante per il caso peggiore del profilo
+    fn default_limit(&self) -> u32 {
+        match self {
+            TxProfile::Transfer => 5_000,
+            TxProfile::TokenTransfer => 60_000,
+            TxProfile::Swap => 200_000,
+            TxProfile::CloseAta => 100_000,
+        }
+    }
+
+    fn percentile(&self) -> FeePercentile {
+        match self {
+            // Velocità pura: il prezzo di ingresso cambia di slot in slot
+            TxProfile::Swap => FeePercentile::P90,
+            _ => FeePercentile::P50,
+        }
+    }
+
+    // Microlamports per CU se il nodo non dà una stima
+    fn fallback_price(&self) -> u64 {
+        match self {
+            TxProfile::Swap => 1_000_000,
+            _ => MIN_PRIORITY_FEE,
+        }
+    }
+
+    /// Limite fisso da env (CU_LIMIT_<PROFILO>), se configurato
+    pub fn fixed_limit(&self) -> Option<u32> {
+        env::var(self.env_key()).ok()
+            .and_then(|v| v.parse::<u32>().ok())
+            .filter(|l| *l > 0)
+            .map(|l| l.min(MAX_COMPUTE_UNITS))
+    }
+}
+
+/// Stima dei CU via simulazione attiva (CU_ESTIMATE, default true)
+pub fn compute_estimation_enabled() -> bool {
+    env::var("CU_ESTIMATE").map(|v| v != "false").unwrap_or(true)
+}
+
+// Stima della congestione dagli ultimi blocchi (getRecentPrioritizationFees)
+#[derive(Serialize, Clone, Debug)]
+pub struct FeeEstimate {
+    pub p50: u64,
+    pub p75: u64,
+    pub p90: u64,
+    pub samples: usize,
+    pub updated_at: i64,
+}
+
+impl FeeEstimate {
+    /// Costruisce la stima dai campioni per slot. None se non ci sono campioni.
+    fn from_samples(mut fees: Vec<u64>) -> Option<Self> {
+        if fees.is_empty() { return None; }
+        fees.sort_unstable();
+        // Nearest-rank: l'indice del percentile p su n campioni ordinati
+        let at = |p: usize| fees[(fees.len() * p).div_ceil(100).saturating_sub(1)];
+        Some(Self { p50: at(50), p75: at(75), p90: at(90), samples: fees.len(), updated_at: chrono::Utc::now().timestamp() })
+    }
+
+    pub fn get(&self, percentile: FeePercentile) -> u64 {
+        match percentile {
+            FeePercentile::P50 => self.p50,
+            FeePercentile::P75 => self.p75,
+            FeePercentile::P90 => self.p90,
+        }
+    }
+}
+
+// Controllo sull'uscita simulata: `account` deve ricevere almeno `min_out`
+pub struct OutCheck {
+    pub account: Pubkey,
+    // true = token account (amount SPL), false = SOL nativo (lamports)
+    pub is_token: bool,
+    pub pre_amount: u64,
+    pub min_out: u64,
+}
 
 pub struct NetworkClient {
     // Usiamo questo ASINCRONO per leggere saldo, dati token, ecc. (Veloce)
     pub rpc: Arc<AsyncRpcClient>, 
     // WebSocket per ascoltare aggiornamenti in tempo reale
     pub pubsub: PubsubClient, 
+    // Per aprire connessioni WebSocket dedicate (lo Sniper si riconnette da solo)
+    pub ws_url: String,
     // Il cannone QUIC (Nota i 3 Generics specificati per placare il compilatore)
     pub tpu: TpuClient<QuicPool, QuicConnectionManager, QuicConfig>, 
+    // Cache Mint -> Programma Token (Token classico o Token-2022): il proprietario di un mint non cambia
+    token_programs: DashMap<Pubkey, Pubkey>,
+    // Ultima stima globale delle priority fee (condivisa da tutti i percorsi di invio e da /status)
+    fee_cache: RwLock<Option<FeeEstimate>>,
 }
 
 pub async fn init_clients() -> NetworkClient {
@@ -60,13 +205,193 @@ pub async fn init_clients() -> NetworkClient {
     NetworkClient {
         rpc: async_rpc,
         pubsub: pubsub_client,
+        ws_url,
         tpu: tpu_client,
+        token_programs: DashMap::new(),
+        fee_cache: RwLock::new(None),
     }
 }
 
 impl NetworkClient {
     /// Metodo helper per ottenere il saldo velocemente usando il client asincrono
     pub async fn get_balance_fast(&self, pubkey: &Pubkey) -> u64 {
+        let _t = crate::metrics::METRICS.rpc_timer("getBalance");
         self.rpc.get_balance(pubkey).await.unwrap_or(0)
     }
-}
\ No newline at end of file
+
+    /// Programma che possiede il mint: spl_token (classico) oppure spl_token_2022
+    pub async fn get_token_program(&self, mint: &Pubkey) -> Result<Pubkey, Box<dyn Error + Send + Sync>> {
+        if let Some(program) = self.token_programs.get(mint) { return Ok(*program); }
+
+        let owner = {
+            let _t = crate::metrics::METRICS.rpc_timer("getAccountInfo");
+            self.rpc.get_account(mint).await?.owner
+        };
+        if owner != spl_token::id() && owner != spl_token_2022::id() {
+            return Err(format!("{} non è un mint SPL", mint).into());
+        }
+        self.token_programs.insert(*mint, owner);
+        Ok(owner)
+    }
+
+    /// ATA dell'owner per il mint, derivato col programma token corretto
+    pub async fn get_token_ata(&self, owner: &Pubkey, mint: &Pubkey) -> Result<Pubkey, Box<dyn Error + Send + Sync>> {
+        let program = self.get_token_program(mint).await?;
+        Ok(spl_associated_token_account::get_associated_token_address_with_program_id(owner, mint, &program))
+    }
+
+    /// Saldo token (unità base) nell'ATA dell'owner, 0 se l'ATA non esiste
+    pub async fn get_token_balance(&self, owner: &Pubkey, mint: &Pubkey) -> Result<u64, Box<dyn Error + Send + Sync>> {
+        let ata = self.get_token_ata(owner, mint).await?;
+        let _t = crate::metrics::METRICS.rpc_timer("getTokenAccountBalance");
+        match self.rpc.get_token_account_balance(&ata).await {
+            Ok(b) => Ok(b.amount.parse()?),
+            Err(_) => Ok(0),
+        }
+    }
+
+    /// Campiona le fee minime per slot degli ultimi blocchi. Con `accounts` vuoto la stima è globale,
+    /// altrimenti riguarda le TX che scrivono su quegli account (es. la pool calda).
+    async fn sample_fees(&self, accounts: &[Pubkey]) -> Option<FeeEstimate> {
+        let res = {
+            let _t = crate::metrics::METRICS.rpc_timer("getRecentPrioritizationFees");
+            self.rpc.get_recent_prioritization_fees(accounts).await
+        };
+        match res {
+            Ok(fees) => FeeEstimate::from_samples(fees.into_iter().map(|f| f.prioritization_fee).collect()),
+            Err(e) => { debug!("⛽ Priority fee non disponibili: {}", e); None }
+        }
+    }
+
+    /// Stima globale corrente (cache di FEE_CACHE_SECS). None se l'RPC non risponde e non c'è una stima recente.
+    pub async fn fee_estimate(&self) -> Option<FeeEstimate> {
+        let now = chrono::Utc::now().timestamp();
+        if let Some(cached) = self.fee_cache.read().await.as_ref().filter(|e| now - e.updated_at <= FEE_CACHE_SECS) {
+            return Some(cached.clone());
+        }
+        let fresh = self.sample_fees(&[]).await?;
+        *self.fee_cache.write().await = Some(fresh.clone());
+        Some(fresh)
+    }
+
+    /// Priority fee da usare (microlamports per CU) al percentile richiesto, entro MIN/MAX_PRIORITY_FEE.
+    /// Gli `accounts` scritti dalla TX danno una stima locale; se non c'è si usa quella globale.
+    pub async fn priority_fee(&self, percentile: FeePercentile, accounts: &[Pubkey]) -> Option<u64> {
+        let local = if accounts.is_empty() { None } else { self.sample_fees(accounts).await };
+        let estimate = match local {
+            Some(e) => e,
+            None => self.fee_estimate().await?,
+        };
+        Some(estimate.get(percentile).clamp(MIN_PRIORITY_FEE, MAX_PRIORITY_FEE))
+    }
+
+    /// Istruzioni della TX precedute da limite e prezzo CU del profilo.
+    /// `fee_accounts` = account scritti dalla TX per la stima locale della priority fee (vuoto = globale).
+    pub async fn with_compute_budget(&self, profile: TxProfile, payer: &Pubkey, ixs: Vec<Instruction>, fee_accounts: &[Pubkey]) -> Vec<Instruction> {
+        let (limit, price) = tokio::join!(
+            self.compute_unit_limit(profile, payer, &ixs),
+            self.priority_fee(profile.percentile(), fee_accounts)
+        );
+        let mut all = vec![
+            ComputeBudgetInstruction::set_compute_unit_limit(limit),
+            ComputeBudgetInstruction::set_compute_unit_price(price.unwrap_or(profile.fallback_price())),
+        ];
+        all.extend(ixs);
+        all
+    }
+
+    /// Limite CU per `ixs`: fisso da env, stimato con la simulazione o default del profilo
+    async fn compute_unit_limit(&self, profile: TxProfile, payer: &Pubkey, ixs: &[Instruction]) -> u32 {
+        if let Some(fixed) = profile.fixed_limit() { return fixed; }
+        if !compute_estimation_enabled() { return profile.default_limit(); }
+        match self.estimate_compute_units(payer, ixs).await {
+            Ok(units) => {
+                let limit = (units * (100 + CU_ESTIMATE_MARGIN_PCT) / 100 + COMPUTE_BUDGET_IX_UNITS).min(MAX_COMPUTE_UNITS);
+                debug!("⛽ CU {:?}: {} stimati, limite {}", profile, units, limit);
+                limit
+            },
+            Err(e) => {
+                debug!("⛽ Stima CU {:?} non disponibile ({}): default {}", profile, e, profile.default_limit());
+                profile.default_limit()
+            }
+        }
+    }
+
+    /// CU consumati da `ixs` in simulazione (limite massimo, blockhash sostituito dal nodo, firme non verificate)
+    async fn estimate_compute_units(&self, payer: &Pubkey, ixs: &[Instruction]) -> Result<u32, String> {
+        let mut all = vec![ComputeBudgetInstruction::set_compute_unit_limit(MAX_COMPUTE_UNITS)];
+        all.extend_from_slice(ixs);
+        let tx = Transaction::new_unsigned(Message::new(&all, Some(payer)));
+        let config = RpcSimulateTransactionConfig {
+            sig_verify: false,
+            replace_recent_blockhash: true,
+            commitment: Some(CommitmentConfig::processed()),
+            ..Default::default()
+        };
+        let res = {
+            let _t = crate::metrics::METRICS.rpc_timer("simulateTransaction");
+            self.rpc.simulate_transaction_with_config(&tx, config).await
+        };
+        let value = res.map_err(|e| e.to_string())?.value;
+        if let Some(err) = value.err { return Err(err.to_string()); }
+        value.units_consumed
+            .map(|u| u.min(MAX_COMPUTE_UNITS as u64) as u32)
+            .ok_or_else(|| "CU consumati non riportati dal nodo".to_string())
+    }
+
+    /// Percorso di invio condiviso: simula la TX e la trasmette solo se va a buon fine e l'uscita stimata
+    /// rispetta `check` (niente fee bruciate su slippage o blockhash scaduti). SIMULATE_SWAPS=false la salta.
+    pub async fn simulate_and_send<T: SerializableTransaction>(&self, tx: &T, check: Option<&OutCheck>) -> Result<Signature, Box<dyn Error + Send + Sync>> {
+        let enabled = env::var("SIMULATE_SWAPS").map(|v| v != "false").unwrap_or(true);
+        if enabled {
+            if let Err(e) = self.simulate(tx, check).await {
+                return Err(format!("Simulazione fallita, TX non inviata: {}", e).into());
+            }
+        }
+        let _t = crate::metrics::METRICS.rpc_timer("sendTransaction");
+        Ok(self.rpc.send_transaction(tx).await?)
+    }
+
+    async fn simulate<T: SerializableTransaction>(&self, tx: &T, check: Option<&OutCheck>) -> Result<(), String> {
+        let sims = &crate::metrics::METRICS.simulations;
+        let config = RpcSimulateTransactionConfig {
+            sig_verify: false,
+            commitment: Some(CommitmentConfig::processed()),
+            accounts: check.map(|c| RpcSimulateTransactionAccountsConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                addresses: vec![c.account.to_string()],
+            }),
+            ..Default::default()
+        };
+        let res = {
+            let _t = crate::metrics::METRICS.rpc_timer("simulateTransaction");
+            self.rpc.simulate_transaction_with_config(tx, config).await
+        };
+        let value = match res {
+            Ok(r) => r.value,
+            // RPC non disponibile: non si blocca l'invio per un problema del nodo
+            Err(e) => { sims.with_label_values(&["error"]).inc(); log::warn!("⚠️ Simulazione non disponibile: {}", e); return Ok(()); }
+        };
+        if let Some(err) = value.err {
+            sims.with_label_values(&["failed"]).inc();
+            return Err(err.to_string());
+        }
+
+        if let Some(c) = check {
+            let account = value.accounts.and_then(|a| a.into_iter().next().flatten()).and_then(|a| a.decode::<Account>());
+            let post = match (&account, c.is_token) {
+                (Some(a), true) => a.data.get(64..72).and_then(|b| b.try_into().ok()).map(u64::from_le_bytes).unwrap_or(0),
+                (Some(a), false) => a.lamports,
+                (None, _) => 0,
+            };
+            let margin = if c.is_token { 0 } else { SIM_SOL_FEE_MARGIN };
+            let out = post.saturating_sub(c.pre_amount);
+            if out + margin < c.min_out {
+                sims.with_label_values(&["low_out"]).inc();
+                return Err(format!("uscita stimata {} sotto il minimo {}", out, c.min_out));
+            }
+        }
+        sims.with_label_values(&["ok"]).inc();
+        Ok(())
+    }
+}
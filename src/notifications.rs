@@ -0,0 +1,158 @@
+use std::net::IpAddr;
+use serde::Serialize;
+use serde_json::json;
+use tokio::time::{sleep, Duration};
+use log::warn;
+use crate::db;
+use crate::i18n::{self, Lang};
+use crate::report::DailyStats;
+
+// --- NOTIFICHE WEBHOOK (Discord / Slack / JSON generico) ---
+// Acquisti, vendite, stop-out e report giornaliero inviati in POST ai webhook dell'utente
+// (i trade passano da trade_alerts::notify, che aggiunge il messaggio Telegram).
+// Consegna in background con retry e backoff esponenziale (1s, 2s, 4s).
+
+pub const MAX_WEBHOOKS: usize = 5;
+const MAX_ATTEMPTS: u32 = 4;
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Notification {
+    Buy { token: String, amount_sol: f64, route: String, tx_signature: String },
+    // Prezzi USD medi d'ingresso e di uscita (0 = non disponibile), durata dalla prima apertura
+    Sell { token: String, pnl_sol: f64, reason: String, tx_signature: String, entry_price: f64, exit_price: f64, hold_secs: i64 },
+    StopOut { token: String, pnl_sol: f64, reason: String, tx_signature: String, entry_price: f64, exit_price: f64, hold_secs: i64 },
+    DailyReport(DailyStats),
+    Test,
+}
+
+impl Notification {
+    pub fn title(&self, lang: Lang) -> &'static str {
+        let key = match self {
+            Notification::Buy { .. } => "notify.buy.title",
+            Notification::Sell { .. } => "notify.sell.title",
+            Notification::StopOut { .. } => "notify.stop_out.title",
+            Notification::DailyReport(_) => "notify.daily_report.title",
+            Notification::Test => "notify.test.title",
+        };
+        i18n::t(lang, key)
+    }
+
+    fn summary(&self, lang: Lang) -> String {
+        match self {
+            Notification::Buy { token, amount_sol, route, .. } => i18n::tr(lang, "notify.buy.summary", &[&format!("{:.4}", amount_sol), token, route]),
+            Notification::Sell { token, pnl_sol, reason, .. } | Notification::StopOut { token, pnl_sol, reason, .. } => {
+                format!("{} | PnL {:+.4} SOL | {}", token, pnl_sol, reason)
+            },
+            Notification::DailyReport(s) => i18n::tr(lang, "notify.daily_report.summary", &[
+                &format!("{:.4}", s.balance_sol), &format!("{:+.4}", s.pnl_sol), &s.trades_opened, &s.trades_closed,
+                &s.win_rate_pct.map(|w| format!("{:.0}%", w)).unwrap_or_else(|| "-".into()), &s.open_positions, &format!("{:.4}", s.open_exposure_sol),
+            ]),
+            Notification::Test => i18n::t(lang, "notify.test.summary").to_string(),
+        }
+    }
+
+    // Verde = positivo, Rosso = perdita, Blu = neutro
+    fn color(&self) -> u32 {
+        match self {
+            Notification::Sell { pnl_sol, .. } | Notification::StopOut { pnl_sol, .. } => {
+                if *pnl_sol >= 0.0 { 0x2ECC71 } else { 0xE74C3C }
+            },
+            Notification::DailyReport(s) => if s.pnl_sol >= 0.0 { 0x2ECC71 } else { 0xE74C3C },
+            _ => 0x3498DB,
+        }
+    }
+}
+
+/// Tipo dal dominio dell'URL (Discord e Slack hanno formati propri)
+pub fn detect_kind(url: &str) -> &'static str {
+    let host = reqwest::Url::parse(url).ok().and_then(|u| u.host_str().map(|h| h.to_lowercase())).unwrap_or_default();
+    if host == "discord.com" || host == "discordapp.com" || host.ends_with(".discord.com") {
+        "DISCORD"
+    } else if host == "hooks.slack.com" {
+        "SLACK"
+    } else {
+        "GENERIC"
+    }
+}
+
+/// Solo HTTPS verso host pubblici (niente localhost / reti private: il server non deve fare da proxy interno)
+pub fn validate_url(url: &str) -> Result<(), &'static str> {
+    let parsed = reqwest::Url::parse(url).map_err(|_| "URL non valido")?;
+    if parsed.scheme() != "https" { return Err("Solo URL https"); }
+    let host = parsed.host_str().ok_or("URL senza host")?.trim_matches(|c| c == '[' || c == ']').to_lowercase();
+    if host == "localhost" || host.ends_with(".localhost") || host.ends_with(".internal") { return Err("Host non consentito"); }
+    if let Ok(ip) = host.parse::<IpAddr>() {
+        let private = match ip {
+            IpAddr::V4(v4) => v4.is_private() || v4.is_loopback() || v4.is_link_local() || v4.is_unspecified(),
+            IpAddr::V6(v6) => v6.is_loopback() || v6.is_unspecified() || (v6.segments()[0] & 0xfe00) == 0xfc00,
+        };
+        if private { return Err("Host non consentito"); }
+    }
+    Ok(())
+}
+
+fn payload(kind: &str, user_id: &str, n: &Notification, lang: Lang) -> serde_json::Value {
+    let now = chrono::Utc::now();
+    match kind {
+        "DISCORD" => json!({
+            "username": "God Sniper",
+            "embeds": [{
+                "title": n.title(lang),
+                "description": n.summary(lang),
+                "color": n.color(),
+                "timestamp": now.to_rfc3339(),
+            }]
+        }),
+        "SLACK" => json!({ "text": format!("*{}*\n{}", n.title(lang), n.summary(lang)) }),
+        _ => json!({ "user_id": user_id, "timestamp": now.timestamp(), "data": n }),
+    }
+}
+
+/// Invia la notifica a tutti i webhook dell'utente (non blocca il chiamante)
+pub fn notify(pool: &db::DbPool, user_id: &str, n: Notification) {
+    let (pool, user_id) = (pool.clone(), user_id.to_string());
+    tokio::spawn(async move {
+        let hooks = db::get_webhooks(&pool, &user_id).await.unwrap_or_default();
+        if hooks.is_empty() { return; }
+        let lang = i18n::user_lang(&pool, &user_id).await;
+        let client = http_client();
+        for hook in hooks {
+            if let Err(e) = deliver(&client, &hook.url, &payload(&hook.kind, &user_id, &n, lang)).await {
+                warn!("⚠️ Webhook #{} ({}) non consegnato: {}", hook.id, user_id, e);
+            }
+        }
+    });
+}
+
+// Niente redirect: un 3xx potrebbe puntare a un host interno già escluso da validate_url
+fn http_client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap_or_default()
+}
+
+/// POST con retry: 5xx, 429 ed errori di rete si ritentano, gli altri 4xx no
+async fn deliver(client: &reqwest::Client, url: &str, body: &serde_json::Value) -> Result<(), String> {
+    let mut last_err = String::new();
+    for attempt in 0..MAX_ATTEMPTS {
+        if attempt > 0 { sleep(Duration::from_secs(1 << (attempt - 1))).await; }
+
+        match client.post(url).json(body).timeout(Duration::from_secs(10)).send().await {
+            Ok(resp) if resp.status().is_success() => return Ok(()),
+            Ok(resp) => {
+                let status = resp.status();
+                last_err = format!("HTTP {}", status);
+                if status.is_client_error() && status.as_u16() != 429 { break; }
+            },
+            Err(e) => last_err = e.to_string(),
+        }
+    }
+    Err(last_err)
+}
+
+/// Invio sincrono di prova (per l'API): esito reale al chiamante
+pub async fn send_test(user_id: &str, hook: &db::Webhook, lang: Lang) -> Result<(), String> {
+    deliver(&http_client(), &hook.url, &payload(&hook.kind, user_id, &Notification::Test, lang)).await
+}
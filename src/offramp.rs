@@ -0,0 +1,371 @@
+use std::str::FromStr;
+use std::sync::Arc;
+use serde::Deserialize;
+use serde_json::json;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signature, Signer};
+use solana_sdk::transaction::Transaction;
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup};
+use tokio::time::{sleep, Duration};
+use log::{info, warn};
+use crate::{db, i18n, network, price_oracle, risk, swap_router, telegram_bot, wallet_manager, AppState};
+
+// --- OFF-RAMP SEPA (SOL -> EURC -> bonifico sul conto dell'utente) ---
+// 1. POST /offramp valida IBAN e importo, registra la richiesta e chiede "Approva" su Telegram (sempre: i soldi escono dalla chain)
+// 2. Approvata: swap ExactOut SOL -> EURC (importo preciso), poi gli EURC vanno al vault del provider (OFFRAMP_VAULT)
+// 3. Il provider (OFFRAMP_PROVIDER_URL) riceve la richiesta di bonifico con la firma del trasferimento come riferimento
+// 4. Il worker segue lo stato presso il provider e avvisa l'utente a bonifico eseguito o fallito
+// Stati: AWAITING_APPROVAL -> APPROVED -> CONVERTING -> SENT -> PROCESSING -> SETTLED | FAILED, oppure CANCELLED.
+
+pub const EURC_MINT: &str = "HzwqbKZw8HxMN6bF2yFZNrht3c2iXXzpKcFu7uBEDKtr";
+const EURC_DECIMALS: u8 = 6;
+pub const APPROVAL_TTL_MINUTES: i64 = 15;
+const DEFAULT_MIN_EUR: f64 = 10.0;
+const DEFAULT_MAX_EUR: f64 = 5_000.0;
+const OFFRAMP_SLIPPAGE_BPS: u16 = 100;
+// Attesa degli EURC dopo lo swap prima del trasferimento al vault
+const SWAP_SETTLE_ATTEMPTS: u32 = 20;
+// Una richiesta ferma da così tanto in APPROVED o CONVERTING viene ripresa dal worker (crash a metà)
+const STALE_CONVERTING_SECS: i64 = 300;
+const WORKER_SECS: u64 = 60;
+const PROVIDER_TIMEOUT_SECS: u64 = 15;
+
+/// Provider del bonifico: OFFRAMP_PROVIDER (nome), OFFRAMP_PROVIDER_URL e OFFRAMP_API_KEY. None = off-ramp disattivato.
+pub struct OfframpProvider {
+    pub name: String,
+    url: String,
+    api_key: String,
+}
+
+pub fn provider() -> Option<OfframpProvider> {
+    let url = std::env::var("OFFRAMP_PROVIDER_URL").ok().filter(|u| !u.is_empty())?;
+    Some(OfframpProvider {
+        name: std::env::var("OFFRAMP_PROVIDER").unwrap_or_else(|_| "sepa".into()),
+        url: url.trim_end_matches('/').to_string(),
+        api_key: std::env::var("OFFRAMP_API_KEY").unwrap_or_default(),
+    })
+}
+
+/// Wallet del provider che riceve gli EURC (OFFRAMP_VAULT)
+pub fn resolve_offramp_vault() -> Option<Pubkey> {
+    std::env::var("OFFRAMP_VAULT").ok().and_then(|v| Pubkey::from_str(v.trim()).ok())
+}
+
+fn limit_eur(var: &str, default: f64) -> f64 {
+    std::env::var(var).ok().and_then(|v| v.parse().ok()).unwrap_or(default)
+}
+
+/// IBAN normalizzato (senza spazi, maiuscolo) se formato e cifre di controllo (mod 97, ISO 13616) sono validi
+pub fn validate_iban(iban: &str) -> Option<String> {
+    let iban: String = iban.chars().filter(|c| !c.is_whitespace()).collect::<String>().to_uppercase();
+    if !(15..=34).contains(&iban.len()) || !iban.chars().all(|c| c.is_ascii_alphanumeric()) { return None; }
+    let (country, check) = (&iban[..2], &iban[2..4]);
+    if !country.chars().all(|c| c.is_ascii_alphabetic()) || !check.chars().all(|c| c.is_ascii_digit()) { return None; }
+
+    // Prime 4 in coda, lettere -> numeri (A=10 ... Z=35), resto calcolato a pezzi
+    let remainder = iban[4..].chars().chain(iban[..4].chars()).fold(0u32, |acc, c| {
+        let v = c.to_digit(36).unwrap_or(0);
+        if v >= 10 { (acc * 100 + v) % 97 } else { (acc * 10 + v) % 97 }
+    });
+    (remainder == 1).then_some(iban)
+}
+
+// Errore DB: dettaglio nel log, all'utente il messaggio tradotto
+fn db_error(lang: i18n::Lang, e: sqlx::Error) -> String {
+    warn!("⚠️ Off-ramp: errore DB: {}", e);
+    i18n::t(lang, "api.db_error").into()
+}
+
+/// Registra la richiesta e chiede l'approvazione su Telegram: messaggio per l'utente oppure errore
+pub async fn request(pool: &db::DbPool, net: &Arc<network::NetworkClient>, user_id: &str, amount_eur: f64, iban: &str, beneficiary: &str) -> Result<String, String> {
+    let lang = i18n::user_lang(pool, user_id).await;
+    let provider = provider().ok_or_else(|| i18n::t(lang, "api.offramp_disabled").to_string())?;
+    if resolve_offramp_vault().is_none() { return Err(i18n::t(lang, "api.offramp_disabled").into()); }
+    let iban = validate_iban(iban).ok_or_else(|| i18n::t(lang, "api.invalid_iban").to_string())?;
+    let beneficiary = beneficiary.trim().chars().take(70).collect::<String>();
+    if beneficiary.is_empty() { return Err(i18n::t(lang, "api.invalid_beneficiary").into()); }
+    let (min, max) = (limit_eur("OFFRAMP_MIN_EUR", DEFAULT_MIN_EUR), limit_eur("OFFRAMP_MAX_EUR", DEFAULT_MAX_EUR));
+    if !(min..=max).contains(&amount_eur) {
+        return Err(i18n::tr(lang, "api.invalid_offramp_amount", &[&min, &max]));
+    }
+    if let Ok((false, msg)) = db::can_withdraw(pool, user_id).await { return Err(msg); }
+
+    // Stima in SOL per il messaggio di approvazione (la quote si rifà all'esecuzione)
+    let amount_eurc = (amount_eur * 1_000_000.0).round() as u64;
+    let lamports = quote_within_budget(pool, net, user_id, amount_eurc, lang).await?.max_in;
+
+    let id = db::record_offramp(pool, user_id, &iban, &beneficiary, amount_eurc, &provider.name).await.map_err(|e| db_error(lang, e))?;
+    let kb = InlineKeyboardMarkup::new(vec![vec![
+        InlineKeyboardButton::callback(i18n::t(lang, "offramp.approve_button"), format!("of_approve:{}", id)),
+        InlineKeyboardButton::callback(i18n::t(lang, "offramp.reject_button"), format!("of_reject:{}", id)),
+    ]]);
+    let text = i18n::tr(lang, "offramp.approval_request", &[
+        &id, &format!("{:.2}", amount_eur), &format!("{:.4}", lamports as f64 / 1_000_000_000.0), &beneficiary, &iban, &APPROVAL_TTL_MINUTES,
+    ]);
+    if !telegram_bot::notify_user_with_buttons(user_id, text, kb).await {
+        let _ = db::advance_offramp(pool, id, Some(user_id), &["AWAITING_APPROVAL"], "CANCELLED", db::OfframpUpdate::default()).await;
+        return Err(i18n::t(lang, "offramp.telegram_required").into());
+    }
+    info!("🏦 Off-ramp #{} ({}) {:.2} EUR in attesa di approvazione", id, user_id, amount_eur);
+    Ok(i18n::tr(lang, "offramp.pending", &[&id, &APPROVAL_TTL_MINUTES]))
+}
+
+// Quote ExactOut SOL -> EURC, con l'input massimo dentro il budget (riserva esclusa)
+async fn quote_within_budget(pool: &db::DbPool, net: &Arc<network::NetworkClient>, user_id: &str, amount_eurc: u64, lang: i18n::Lang) -> Result<swap_router::ExactOutQuote, String> {
+    let owner = wallet_manager::create_user_wallet(pool, user_id).await.ok()
+        .and_then(|s| Pubkey::from_str(&s).ok())
+        .ok_or(i18n::t(lang, "withdraw.wallet_error"))?;
+    let params = db::get_strategy_params(pool, user_id).await;
+    let balance = net.get_balance_fast(&owner).await;
+    let spendable = risk::buy_budget(pool, user_id, &params, balance).await.map_err(|e| db_error(lang, e))?.spendable;
+    let quoted = match swap_router::quote_exact_out((price_oracle::SOL_MINT, EURC_MINT), amount_eurc, OFFRAMP_SLIPPAGE_BPS).await {
+        Ok(q) => q,
+        Err(e) => {
+            warn!("⚠️ Off-ramp: quote SOL -> EURC fallita ({}): {}", user_id, e);
+            return Err(i18n::t(lang, "api.quote_failed").into());
+        }
+    };
+    if quoted.max_in > spendable {
+        return Err(i18n::tr(lang, "offramp.insufficient_funds", &[
+            &format!("{:.4}", quoted.max_in as f64 / 1e9), &format!("{:.4}", spendable as f64 / 1e9),
+        ]));
+    }
+    Ok(quoted)
+}
+
+/// Esito dei pulsanti Approva / Rifiuta
+pub async fn resolve(pool: &db::DbPool, net: &Arc<network::NetworkClient>, user_id: &str, id: i64, approve: bool) -> Result<String, String> {
+    let lang = i18n::user_lang(pool, user_id).await;
+    let to = if approve { "APPROVED" } else { "CANCELLED" };
+    match db::advance_offramp(pool, id, Some(user_id), &["AWAITING_APPROVAL"], to, db::OfframpUpdate::default()).await {
+        Ok(true) if approve => {},
+        Ok(true) => return Ok(i18n::tr(lang, "offramp.rejected", &[&id])),
+        Ok(false) => return Err(i18n::t(lang, "offramp.expired").into()),
+        Err(e) => return Err(db_error(lang, e)),
+    }
+
+    // Swap e trasferimento richiedono decine di secondi: il pulsante risponde subito
+    info!("🏦 Off-ramp #{} ({}) approvato", id, user_id);
+    let (pool, net) = (pool.clone(), net.clone());
+    tokio::spawn(async move {
+        if let Ok(Some(payout)) = db::get_offramp(&pool, id).await {
+            execute(&pool, &net, payout).await;
+        }
+    });
+    Ok(i18n::tr(lang, "offramp.approved", &[&id]))
+}
+
+// Approvato -> swap -> vault -> provider. Ogni errore chiude la richiesta in FAILED e avvisa l'utente.
+async fn execute(pool: &db::DbPool, net: &Arc<network::NetworkClient>, payout: db::OfframpPayout) {
+    let id = payout.id;
+    if let Err(e) = convert_and_send(pool, net, &payout).await {
+        warn!("⚠️ Off-ramp #{} fallito: {}", id, e);
+        fail(pool, &payout, &["APPROVED", "CONVERTING"], &e).await;
+        return;
+    }
+    if let Ok(Some(sent)) = db::get_offramp(pool, id).await {
+        submit_to_provider(pool, &sent).await;
+    }
+}
+
+async fn convert_and_send(pool: &db::DbPool, net: &Arc<network::NetworkClient>, payout: &db::OfframpPayout) -> Result<(), String> {
+    let payer = wallet_manager::get_decrypted_wallet(pool, &payout.user_id).await.map_err(|_| "Wallet Error".to_string())?;
+    let eurc = Pubkey::from_str(EURC_MINT).map_err(|e| e.to_string())?;
+    // Gli EURC già nel wallet non appartengono a questa richiesta
+    let pre_balance = net.get_token_balance(&payer.pubkey(), &eurc).await.map_err(|e| e.to_string())?;
+
+    if let Ok((false, msg)) = db::can_withdraw(pool, &payout.user_id).await { return Err(msg); }
+    let lang = i18n::user_lang(pool, &payout.user_id).await;
+    let quoted = quote_within_budget(pool, net, &payout.user_id, payout.amount_eurc, lang).await?;
+    let lamports = quoted.max_in;
+    // Claim: solo un esecutore passa da APPROVED a CONVERTING. Il saldo di partenza resta sulla riga per la ripresa dopo un riavvio.
+    let claim = db::OfframpUpdate { amount_lamports: Some(lamports), pre_swap_eurc: Some(pre_balance), ..Default::default() };
+    if !db::advance_offramp(pool, payout.id, None, &["APPROVED"], "CONVERTING", claim).await.map_err(|e| e.to_string())? {
+        return Err("Richiesta già in esecuzione".into());
+    }
+    let swap = swap_router::swap_exact_out(net, pool, &payout.user_id, &payer, (price_oracle::SOL_MINT, EURC_MINT), quoted, OFFRAMP_SLIPPAGE_BPS).await
+        .map_err(|e| format!("Conversione Fallita: {}", e))?;
+    let update = db::OfframpUpdate { swap_signature: Some(&swap.signature), ..Default::default() };
+    let _ = db::advance_offramp(pool, payout.id, None, &["CONVERTING"], "CONVERTING", update).await;
+    info!("🏦 Off-ramp #{} ({}) {} EURC acquistati: {}", payout.id, payout.user_id, payout.amount_eurc, swap.signature);
+
+    for _ in 0..SWAP_SETTLE_ATTEMPTS {
+        sleep(Duration::from_secs(3)).await;
+        let balance = net.get_token_balance(&payer.pubkey(), &eurc).await.unwrap_or(0);
+        if balance >= pre_balance + payout.amount_eurc { break; }
+    }
+    let converting = db::OfframpPayout { swap_signature: Some(swap.signature), pre_swap_eurc: Some(pre_balance), ..payout.clone() };
+    transfer_to_vault(pool, net, &payer, &converting).await
+}
+
+// EURC dal wallet dell'utente all'ATA del vault (creato se manca).
+// Solo se lo swap della richiesta è confermato on-chain e ha portato amount_eurc oltre il saldo di partenza:
+// altrimenti fallisce senza toccare il wallet, così gli EURC già dell'utente non finiscono al vault.
+async fn transfer_to_vault(pool: &db::DbPool, net: &Arc<network::NetworkClient>, payer: &Keypair, payout: &db::OfframpPayout) -> Result<(), String> {
+    let vault = resolve_offramp_vault().ok_or("OFFRAMP_VAULT non configurato")?;
+    let mint = Pubkey::from_str(EURC_MINT).map_err(|e| e.to_string())?;
+    let owner = payer.pubkey();
+    let (Some(swap_sig), Some(pre_balance)) = (payout.swap_signature.as_deref(), payout.pre_swap_eurc) else {
+        return Err("Swap non verificabile: EURC non trasferiti".into());
+    };
+    let swap_sig = Signature::from_str(swap_sig).map_err(|e| e.to_string())?;
+    match net.rpc.get_signature_status(&swap_sig).await {
+        Ok(Some(Ok(()))) => {},
+        Ok(Some(Err(e))) => return Err(format!("Conversione Fallita on-chain: {}", e)),
+        Ok(None) => return Err("Swap non confermato: EURC non trasferiti".into()),
+        Err(e) => return Err(format!("Stato dello swap non disponibile: {}", e)),
+    }
+    let balance = net.get_token_balance(&owner, &mint).await.map_err(|e| e.to_string())?;
+    let received = balance.saturating_sub(pre_balance);
+    if received < payout.amount_eurc {
+        return Err(format!("EURC non ricevuti dallo swap ({} su {})", received, payout.amount_eurc));
+    }
+
+    let program = net.get_token_program(&mint).await.map_err(|e| e.to_string())?;
+    let source = net.get_token_ata(&owner, &mint).await.map_err(|e| e.to_string())?;
+    let dest = net.get_token_ata(&vault, &mint).await.map_err(|e| e.to_string())?;
+    let ixs = vec![
+        spl_associated_token_account::instruction::create_associated_token_account_idempotent(&owner, &vault, &mint, &program),
+        spl_token_2022::instruction::transfer_checked(&program, &source, &mint, &dest, &owner, &[], payout.amount_eurc, EURC_DECIMALS).map_err(|e| e.to_string())?,
+    ];
+    let ixs = net.with_compute_budget(network::TxProfile::TokenTransfer, &owner, ixs, &[]).await;
+    let bh = net.rpc.get_latest_blockhash().await.map_err(|e| e.to_string())?;
+    let tx = Transaction::new_signed_with_payer(&ixs, Some(&owner), &[payer], bh);
+    let sig = net.rpc.send_and_confirm_transaction(&tx).await.map_err(|e| format!("Trasferimento al vault fallito: {}", e))?.to_string();
+
+    let update = db::OfframpUpdate { transfer_signature: Some(&sig), ..Default::default() };
+    db::advance_offramp(pool, payout.id, None, &["CONVERTING"], "SENT", update).await.map_err(|e| e.to_string())?;
+    info!("🏦 Off-ramp #{} ({}) EURC al vault {}: {}", payout.id, payout.user_id, vault, sig);
+    Ok(())
+}
+
+#[derive(Deserialize)]
+struct ProviderPayout {
+    id: String,
+    status: String,
+    #[serde(default)]
+    reason: Option<String>,
+}
+
+fn provider_client() -> reqwest::Client {
+    reqwest::Client::builder().timeout(Duration::from_secs(PROVIDER_TIMEOUT_SECS)).build().unwrap_or_default()
+}
+
+// Richiesta di bonifico al provider: gli EURC sono già nel vault, la firma del trasferimento è il riferimento
+async fn submit_to_provider(pool: &db::DbPool, payout: &db::OfframpPayout) {
+    let Some(provider) = provider() else { return };
+    let body = json!({
+        "reference": format!("offramp-{}", payout.id),
+        "iban": payout.iban,
+        "beneficiary": payout.beneficiary,
+        "amount": format!("{:.2}", payout.amount_eurc as f64 / 1_000_000.0),
+        "currency": "EUR",
+        "asset": "EURC",
+        "deposit_signature": payout.transfer_signature,
+    });
+    let res = async {
+        let resp = provider_client().post(format!("{}/payouts", provider.url)).bearer_auth(&provider.api_key).json(&body).send().await?;
+        resp.error_for_status()?.json::<ProviderPayout>().await
+    }.await;
+
+    match res {
+        Ok(p) => {
+            let update = db::OfframpUpdate { provider_ref: Some(&p.id), ..Default::default() };
+            if let Ok(true) = db::advance_offramp(pool, payout.id, None, &["SENT"], "PROCESSING", update).await {
+                info!("🏦 Off-ramp #{} ({}) preso in carico da {}: {}", payout.id, payout.user_id, provider.name, p.id);
+                notify(pool, &payout.user_id, "notify.offramp_processing", &[&payout.id, &eur(payout.amount_eurc), &mask_iban(&payout.iban)]).await;
+            }
+        },
+        // Resta SENT: il worker riprova al giro successivo
+        Err(e) => warn!("⚠️ Off-ramp #{}: provider {} non raggiungibile: {}", payout.id, provider.name, e),
+    }
+}
+
+// Stato del bonifico presso il provider
+async fn poll_provider(pool: &db::DbPool, payout: &db::OfframpPayout) {
+    let (Some(provider), Some(reference)) = (provider(), payout.provider_ref.as_deref()) else { return };
+    let res = async {
+        let resp = provider_client().get(format!("{}/payouts/{}", provider.url, reference)).bearer_auth(&provider.api_key).send().await?;
+        resp.error_for_status()?.json::<ProviderPayout>().await
+    }.await;
+    let p = match res {
+        Ok(p) => p,
+        Err(e) => { warn!("⚠️ Off-ramp #{}: stato dal provider non disponibile: {}", payout.id, e); return; }
+    };
+
+    match p.status.to_lowercase().as_str() {
+        "completed" | "settled" | "paid" => {
+            if let Ok(true) = db::advance_offramp(pool, payout.id, None, &["PROCESSING"], "SETTLED", db::OfframpUpdate::default()).await {
+                info!("🏦 Off-ramp #{} ({}) accreditato", payout.id, payout.user_id);
+                notify(pool, &payout.user_id, "notify.offramp_settled", &[&payout.id, &eur(payout.amount_eurc), &mask_iban(&payout.iban)]).await;
+            }
+        },
+        "failed" | "rejected" | "returned" => {
+            let reason = p.reason.unwrap_or_else(|| p.status.clone());
+            fail(pool, payout, &["PROCESSING"], &format!("Provider: {}", reason)).await;
+        },
+        _ => {},
+    }
+}
+
+async fn fail(pool: &db::DbPool, payout: &db::OfframpPayout, from: &[&str], error: &str) {
+    let update = db::OfframpUpdate { error: Some(error), ..Default::default() };
+    if let Ok(true) = db::advance_offramp(pool, payout.id, None, from, "FAILED", update).await {
+        notify(pool, &payout.user_id, "notify.offramp_failed", &[&payout.id, &eur(payout.amount_eurc), &error]).await;
+    }
+}
+
+async fn notify(pool: &db::DbPool, user_id: &str, key: &str, args: &[&(dyn std::fmt::Display + Sync)]) {
+    let lang = i18n::user_lang(pool, user_id).await;
+    telegram_bot::notify_user(user_id, i18n::tr(lang, key, args)).await;
+}
+
+fn eur(amount_eurc: u64) -> String {
+    format!("{:.2}", amount_eurc as f64 / 1_000_000.0)
+}
+
+/// IBAN nei messaggi: paese e ultime 4 cifre
+pub fn mask_iban(iban: &str) -> String {
+    if iban.len() < 8 { return iban.into(); }
+    format!("{}…{}", &iban[..4], &iban[iban.len() - 4..])
+}
+
+/// Scadenza approvazioni, ripresa delle richieste interrotte e stato dei bonifici presso il provider
+pub async fn run_offramp_worker(pool: db::DbPool, net: Arc<network::NetworkClient>, state: Arc<AppState>) {
+    loop {
+        match db::expire_offramp_approvals(&pool, APPROVAL_TTL_MINUTES).await {
+            Ok(expired) => for (id, user_id) in expired {
+                info!("⌛ Off-ramp #{} ({}) annullato: approvazione scaduta", id, user_id);
+                notify(&pool, &user_id, "notify.offramp_expired", &[&id, &APPROVAL_TTL_MINUTES]).await;
+            },
+            Err(e) => warn!("⚠️ Scadenza approvazioni off-ramp: {}", e),
+        }
+
+        // Approvate ma mai partite (riavvio subito dopo il pulsante)
+        let now = chrono::Utc::now().timestamp();
+        for payout in db::get_offramps_by_status(&pool, "APPROVED").await.unwrap_or_default() {
+            if now - payout.updated_at >= STALE_CONVERTING_SECS { execute(&pool, &net, payout).await; }
+        }
+        // Interrotte da un riavvio tra swap e trasferimento: si completa il trasferimento se lo swap risulta confermato
+        for payout in db::get_offramps_by_status(&pool, "CONVERTING").await.unwrap_or_default() {
+            if now - payout.updated_at < STALE_CONVERTING_SECS { continue; }
+            let res = match wallet_manager::get_decrypted_wallet(&pool, &payout.user_id).await {
+                Ok(payer) => transfer_to_vault(&pool, &net, &payer, &payout).await,
+                Err(_) => Err("Wallet Error".to_string()),
+            };
+            match res {
+                Ok(()) => if let Ok(Some(sent)) = db::get_offramp(&pool, payout.id).await { submit_to_provider(&pool, &sent).await },
+                Err(e) => fail(&pool, &payout, &["CONVERTING"], &e).await,
+            }
+        }
+        for payout in db::get_offramps_by_status(&pool, "SENT").await.unwrap_or_default() {
+            submit_to_provider(&pool, &payout).await;
+        }
+        for payout in db::get_offramps_by_status(&pool, "PROCESSING").await.unwrap_or_default() {
+            poll_provider(&pool, &payout).await;
+        }
+
+        if state.sleep_or_shutdown(Duration::from_secs(WORKER_SECS)).await { break; }
+    }
+    info!("🛑 Off-ramp fermato.");
+}
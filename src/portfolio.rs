@@ -0,0 +1,69 @@
+use chrono::Utc;
+use crate::db;
+
+// --- PORTFOLIO STATS (Ricostruite dallo storico dei trade) ---
+// 1. Le statistiche sono il riassunto dei trade SOLD rigiocati in ordine di uscita: niente vive solo in memoria,
+//    un riavvio non le azzera
+// 2. Riga materializzata per utente (tabella portfolio_stats), ricalcolata a ogni vendita chiusa dal bot
+// 3. Se la conferma on-chain corregge il PnL (settle_sell) la riga viene scartata e rifatta alla lettura successiva
+// I trade RECONCILED (venduti fuori dal bot, PnL sconosciuto) restano fuori.
+
+/// Rigioca lo storico (lamports investiti, PnL SOL) in ordine di uscita
+pub fn replay(history: &[(u64, f64)]) -> db::PortfolioStats {
+    let mut stats = db::PortfolioStats::default();
+    let (mut equity, mut peak) = (0.0_f64, 0.0_f64);
+    for &(amount_in, pnl) in history {
+        stats.closed_trades += 1;
+        stats.invested_sol += amount_in as f64 / 1_000_000_000.0;
+        stats.total_pnl_sol += pnl;
+        // Stessa regola delle metriche: PnL >= 0 è una vittoria
+        if pnl >= 0.0 {
+            stats.wins += 1;
+            stats.gross_profit_sol += pnl;
+            stats.current_streak = stats.current_streak.max(0) + 1;
+        } else {
+            stats.losses += 1;
+            stats.gross_loss_sol += -pnl;
+            stats.current_streak = stats.current_streak.min(0) - 1;
+        }
+        if stats.closed_trades == 1 || pnl > stats.best_trade_sol { stats.best_trade_sol = pnl; }
+        if stats.closed_trades == 1 || pnl < stats.worst_trade_sol { stats.worst_trade_sol = pnl; }
+
+        equity += pnl;
+        peak = peak.max(equity);
+        stats.max_drawdown_sol = stats.max_drawdown_sol.max(peak - equity);
+    }
+    stats.updated_at = Utc::now().to_rfc3339();
+    stats
+}
+
+/// Ricalcola dallo storico e salva la riga materializzata
+pub async fn refresh(pool: &db::DbPool, user_id: &str) -> Result<db::PortfolioStats, sqlx::Error> {
+    let stats = replay(&db::get_closed_trade_history(pool, user_id).await?);
+    db::save_portfolio_stats(pool, user_id, &stats).await?;
+    Ok(stats)
+}
+
+/// Statistiche dell'utente: riga materializzata, ricalcolata se manca
+pub async fn stats(pool: &db::DbPool, user_id: &str) -> Result<db::PortfolioStats, sqlx::Error> {
+    match db::get_portfolio_stats(pool, user_id).await? {
+        Some(stats) => Ok(stats),
+        None => refresh(pool, user_id).await,
+    }
+}
+
+impl db::PortfolioStats {
+    pub fn win_rate_pct(&self) -> f64 {
+        if self.closed_trades == 0 { 0.0 } else { self.wins as f64 / self.closed_trades as f64 * 100.0 }
+    }
+
+    /// PnL realizzato sul totale investito nei trade chiusi
+    pub fn roi_pct(&self) -> f64 {
+        if self.invested_sol <= 0.0 { 0.0 } else { self.total_pnl_sol / self.invested_sol * 100.0 }
+    }
+
+    /// Profitti lordi / perdite lorde (None senza perdite)
+    pub fn profit_factor(&self) -> Option<f64> {
+        (self.gross_loss_sol > 0.0).then(|| self.gross_profit_sol / self.gross_loss_sol)
+    }
+}
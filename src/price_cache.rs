@@ -0,0 +1,82 @@
+use std::error::Error;
+use std::future::Future;
+use std::sync::{Arc, LazyLock};
+use std::time::{Duration, Instant};
+use dashmap::DashMap;
+use tokio::sync::Mutex;
+use crate::jupiter::TokenMarketData;
+use crate::metrics::METRICS;
+
+// --- CACHE PREZZI (DexScreener) ---
+// Position Manager, Auto-Buy, Limit e API chiedono lo stesso mint più volte per giro (una per utente):
+// entro il TTL si risponde dalla RAM. Le richieste concorrenti sullo stesso mint aspettano
+// la prima (lock per mint) invece di partire tutte verso DexScreener.
+
+const DEFAULT_TTL_MS: u64 = 4_000;
+const MAX_ENTRIES: usize = 5_000;
+
+type Slot = Arc<Mutex<Option<(Instant, TokenMarketData)>>>;
+
+pub struct PriceCache {
+    ttl: Duration,
+    slots: DashMap<String, Slot>,
+}
+
+pub static PRICE_CACHE: LazyLock<PriceCache> = LazyLock::new(PriceCache::from_env);
+
+impl PriceCache {
+    /// TTL da env (PRICE_CACHE_TTL_MS=4000), 0 = cache disattivata
+    fn from_env() -> Self {
+        let ttl_ms = std::env::var("PRICE_CACHE_TTL_MS").ok().and_then(|v| v.parse().ok()).unwrap_or(DEFAULT_TTL_MS);
+        Self { ttl: Duration::from_millis(ttl_ms), slots: DashMap::new() }
+    }
+
+    pub fn entries(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Dato in cache se fresco, altrimenti `fetch` (gli errori non vengono memorizzati)
+    pub async fn get_or_fetch<F>(&self, mint: &str, fetch: F) -> Result<TokenMarketData, Box<dyn Error + Send + Sync>>
+    where
+        F: Future<Output = Result<TokenMarketData, Box<dyn Error + Send + Sync>>>,
+    {
+        if self.ttl.is_zero() { return fetch.await; }
+        self.prune();
+
+        let slot = self.slots.entry(mint.to_string()).or_default().clone();
+        // Lock occupato = un'altra richiesta sta già scaricando questo mint
+        let (mut entry, waited) = match slot.try_lock() {
+            Ok(guard) => (guard, false),
+            Err(_) => (slot.lock().await, true),
+        };
+
+        if let Some((at, data)) = entry.as_ref() {
+            if at.elapsed() < self.ttl {
+                METRICS.price_cache.with_label_values(&[if waited { "coalesced" } else { "hit" }]).inc();
+                return Ok(data.clone());
+            }
+        }
+
+        match fetch.await {
+            Ok(data) => {
+                METRICS.price_cache.with_label_values(&["miss"]).inc();
+                *entry = Some((Instant::now(), data.clone()));
+                Ok(data)
+            },
+            Err(e) => {
+                METRICS.price_cache.with_label_values(&["error"]).inc();
+                Err(e)
+            }
+        }
+    }
+
+    // Pulizia: oltre MAX_ENTRIES si buttano i mint scaduti e non in uso
+    fn prune(&self) {
+        if self.slots.len() <= MAX_ENTRIES { return; }
+        let ttl = self.ttl;
+        self.slots.retain(|_, slot| match slot.try_lock() {
+            Ok(entry) => matches!(entry.as_ref(), Some((at, _)) if at.elapsed() < ttl),
+            Err(_) => true,
+        });
+    }
+}
@@ -0,0 +1,165 @@
+use std::error::Error;
+use std::sync::{Arc, LazyLock};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use tokio::time::Duration;
+use log::{debug, info, warn};
+use crate::{birdeye, AppState};
+
+// --- ORACOLO PREZZO SOL (USD) ---
+// 1. Ogni 30s si chiede il prezzo SOL a Jupiter, Birdeye e Coingecko in parallelo
+// 2. Si scartano i valori fuori scala e quelli oltre MAX_DEVIATION dalla mediana: il prezzo è la mediana dei rimasti
+// 3. Se nessuna fonte risponde il vecchio valore invecchia: oltre STALE_SECS il prezzo è "non disponibile"
+//    (None), mai un numero inventato
+
+pub const SOL_MINT: &str = "So11111111111111111111111111111111111111112";
+const JUP_PRICE_API: &str = "https://price.jup.ag/v6/price?ids=SOL";
+const COINGECKO_API: &str = "https://api.coingecko.com/api/v3/simple/price?ids=solana&vs_currencies=usd";
+const REFRESH_SECS: u64 = 30;
+const STALE_SECS: i64 = 120;
+// Oltre il 5% dalla mediana la fonte è considerata guasta
+const MAX_DEVIATION: f64 = 0.05;
+// Fuori da questo intervallo il dato è sicuramente sbagliato (unità errate, risposta vuota)
+const MIN_SANE_USD: f64 = 1.0;
+const MAX_SANE_USD: f64 = 100_000.0;
+
+#[derive(Serialize, Clone, Debug)]
+pub struct SourceQuote {
+    pub source: &'static str,
+    pub usd: f64,
+    // false = scartata come outlier
+    pub used: bool,
+}
+
+// Ultimo prezzo aggregato valido
+#[derive(Clone, Debug)]
+struct Snapshot {
+    usd: f64,
+    quotes: Vec<SourceQuote>,
+    updated_at: i64,
+}
+
+// Stato esposto all'API: `usd` è None quando il prezzo non è disponibile o è troppo vecchio
+#[derive(Serialize, Clone, Debug)]
+pub struct SolPrice {
+    pub status: &'static str, // "OK" | "STALE" | "UNAVAILABLE"
+    pub usd: Option<f64>,
+    pub last_usd: Option<f64>,
+    pub updated_at: Option<i64>,
+    pub age_secs: Option<i64>,
+    pub sources: Vec<SourceQuote>,
+}
+
+static SOL_PRICE: LazyLock<RwLock<Option<Snapshot>>> = LazyLock::new(|| RwLock::new(None));
+
+#[derive(Deserialize)]
+struct JupPriceResponse { data: std::collections::HashMap<String, JupPriceItem> }
+#[derive(Deserialize)]
+struct JupPriceItem { price: f64 }
+
+#[derive(Deserialize)]
+struct CoingeckoResponse { solana: CoingeckoItem }
+#[derive(Deserialize)]
+struct CoingeckoItem { usd: f64 }
+
+async fn fetch_jupiter() -> Result<f64, Box<dyn Error + Send + Sync>> {
+    let resp: JupPriceResponse = reqwest::get(JUP_PRICE_API).await?.json().await?;
+    resp.data.get("SOL").map(|i| i.price).ok_or_else(|| "Jupiter: SOL assente nella risposta".into())
+}
+
+async fn fetch_birdeye() -> Result<f64, Box<dyn Error + Send + Sync>> {
+    let prices = birdeye::get_multi_price(&[SOL_MINT.to_string()]).await?;
+    prices.get(SOL_MINT).copied().ok_or_else(|| "Birdeye: SOL assente nella risposta".into())
+}
+
+async fn fetch_coingecko() -> Result<f64, Box<dyn Error + Send + Sync>> {
+    let resp: CoingeckoResponse = reqwest::get(COINGECKO_API).await?.json().await?;
+    Ok(resp.solana.usd)
+}
+
+fn median(values: &mut [f64]) -> Option<f64> {
+    if values.is_empty() { return None; }
+    values.sort_by(|a, b| a.total_cmp(b));
+    let mid = values.len() / 2;
+    Some(if values.len().is_multiple_of(2) { (values[mid - 1] + values[mid]) / 2.0 } else { values[mid] })
+}
+
+/// Mediana delle fonti dopo i controlli di sanità. None se nessuna fonte è utilizzabile.
+fn aggregate(raw: Vec<(&'static str, f64)>) -> Option<(f64, Vec<SourceQuote>)> {
+    let sane: Vec<(&'static str, f64)> = raw.into_iter()
+        .filter(|(_, p)| p.is_finite() && (MIN_SANE_USD..=MAX_SANE_USD).contains(p))
+        .collect();
+    let first = median(&mut sane.iter().map(|(_, p)| *p).collect::<Vec<_>>())?;
+
+    let quotes: Vec<SourceQuote> = sane.into_iter()
+        .map(|(source, usd)| SourceQuote { source, usd, used: (usd - first).abs() / first <= MAX_DEVIATION })
+        .collect();
+    let usd = median(&mut quotes.iter().filter(|q| q.used).map(|q| q.usd).collect::<Vec<_>>())?;
+    Some((usd, quotes))
+}
+
+/// Un giro di aggiornamento: true se il prezzo è stato rinnovato
+pub async fn refresh() -> bool {
+    let (jup, bird, gecko) = tokio::join!(fetch_jupiter(), fetch_birdeye(), fetch_coingecko());
+
+    let mut raw = Vec::new();
+    for (source, res) in [("jupiter", jup), ("birdeye", bird), ("coingecko", gecko)] {
+        match res {
+            Ok(p) => raw.push((source, p)),
+            Err(e) => debug!("💲 Prezzo SOL da {} non disponibile: {}", source, e),
+        }
+    }
+
+    match aggregate(raw) {
+        Some((usd, quotes)) => {
+            for q in quotes.iter().filter(|q| !q.used) {
+                warn!("⚠️ Oracolo SOL: {} scartato (${:.2} contro mediana ${:.2})", q.source, q.usd, usd);
+            }
+            *SOL_PRICE.write().await = Some(Snapshot { usd, quotes, updated_at: chrono::Utc::now().timestamp() });
+            true
+        },
+        None => false,
+    }
+}
+
+/// Prezzo SOL in USD solo se fresco: None = prezzo non disponibile (niente valori di ripiego)
+pub async fn sol_usd() -> Option<f64> {
+    let now = chrono::Utc::now().timestamp();
+    SOL_PRICE.read().await.as_ref().filter(|s| now - s.updated_at <= STALE_SECS).map(|s| s.usd)
+}
+
+/// Stato completo dell'oracolo per API e dashboard
+pub async fn sol_price() -> SolPrice {
+    let now = chrono::Utc::now().timestamp();
+    match SOL_PRICE.read().await.clone() {
+        Some(s) => {
+            let age = now - s.updated_at;
+            let fresh = age <= STALE_SECS;
+            SolPrice {
+                status: if fresh { "OK" } else { "STALE" },
+                usd: fresh.then_some(s.usd),
+                last_usd: Some(s.usd),
+                updated_at: Some(s.updated_at),
+                age_secs: Some(age),
+                sources: s.quotes,
+            }
+        },
+        None => SolPrice { status: "UNAVAILABLE", usd: None, last_usd: None, updated_at: None, age_secs: None, sources: vec![] },
+    }
+}
+
+pub async fn run_sol_oracle(state: Arc<AppState>) {
+    info!("💲 Oracolo Prezzo SOL: ONLINE (Jupiter + Birdeye + Coingecko)");
+    let mut failing = false;
+
+    loop {
+        let ok = refresh().await;
+        // Log solo ai cambi di stato, non a ogni giro
+        if !ok && !failing { warn!("⚠️ Oracolo SOL: nessuna fonte disponibile, il prezzo invecchia"); }
+        if ok && failing { info!("💲 Oracolo SOL: fonti di nuovo disponibili"); }
+        failing = !ok;
+
+        if state.sleep_or_shutdown(Duration::from_secs(REFRESH_SECS)).await { break; }
+    }
+    info!("🛑 Oracolo Prezzo SOL fermato.");
+}
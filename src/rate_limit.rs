@@ -0,0 +1,78 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Instant;
+use crate::metrics::METRICS;
+
+// --- RATE LIMITING (Token Bucket in RAM) ---
+// Ogni chiave (IP o user_id) ha un secchio di `capacity` gettoni che si ricarica
+// a `per_minute` gettoni al minuto. Richiesta senza gettone = 429.
+
+const MAX_TRACKED_KEYS: usize = 10_000;
+
+struct Bucket {
+    tokens: f64,
+    last: Instant,
+}
+
+pub struct RateLimiter {
+    scope: &'static str,
+    capacity: f64,
+    refill_per_sec: f64,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(scope: &'static str, per_minute: u32) -> Self {
+        let per_minute = per_minute.max(1) as f64;
+        Self {
+            scope,
+            capacity: per_minute,
+            refill_per_sec: per_minute / 60.0,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Limite da env (es. RATE_LIMIT_IP_PER_MIN=120), altrimenti il default
+    pub fn from_env(scope: &'static str, var: &str, default_per_minute: u32) -> Self {
+        let per_minute = std::env::var(var).ok().and_then(|v| v.parse().ok()).unwrap_or(default_per_minute);
+        Self::new(scope, per_minute)
+    }
+
+    /// Consuma un gettone per `key`. false = limite superato.
+    pub fn check(&self, key: &str) -> bool {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+
+        // Pulizia: i secchi pieni equivalgono a "mai visto", si possono buttare
+        if buckets.len() > MAX_TRACKED_KEYS {
+            let (cap, rate) = (self.capacity, self.refill_per_sec);
+            buckets.retain(|_, b| b.tokens + now.duration_since(b.last).as_secs_f64() * rate < cap);
+        }
+
+        let bucket = buckets.entry(key.to_string()).or_insert(Bucket { tokens: self.capacity, last: now });
+        bucket.tokens = (bucket.tokens + now.duration_since(bucket.last).as_secs_f64() * self.refill_per_sec).min(self.capacity);
+        bucket.last = now;
+
+        let allowed = bucket.tokens >= 1.0;
+        if allowed { bucket.tokens -= 1.0; }
+        METRICS.rate_limit.with_label_values(&[self.scope, if allowed { "allowed" } else { "rejected" }]).inc();
+        allowed
+    }
+}
+
+// Limiti dell'API: per IP (tutte le rotte), per utente (rotte autenticate), /auth (anti brute-force)
+pub struct ApiLimits {
+    pub ip: RateLimiter,
+    pub user: RateLimiter,
+    pub auth: RateLimiter,
+}
+
+impl ApiLimits {
+    pub fn from_env() -> Self {
+        Self {
+            ip: RateLimiter::from_env("ip", "RATE_LIMIT_IP_PER_MIN", 120),
+            user: RateLimiter::from_env("user", "RATE_LIMIT_USER_PER_MIN", 60),
+            auth: RateLimiter::from_env("auth", "RATE_LIMIT_AUTH_PER_MIN", 10),
+        }
+    }
+}
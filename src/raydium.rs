@@ -5,7 +5,6 @@ use solana_sdk::{
     transaction::Transaction,
     system_instruction,
     commitment_config::CommitmentConfig,
-    compute_budget::ComputeBudgetInstruction, 
 };
 use solana_client::{
     rpc_config::{RpcProgramAccountsConfig, RpcAccountInfoConfig},
@@ -15,12 +14,13 @@ use solana_account_decoder::UiAccountEncoding;
 use borsh::{BorshSerialize, BorshDeserialize};
 use std::sync::Arc;
 use std::str::FromStr;
-use crate::network::NetworkClient;
-use log::{info, warn};
+use crate::network::{NetworkClient, TxProfile};
 
 // Program ID Ufficiali
 pub const RAYDIUM_V4_PROGRAM_ID: &str = "675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8";
 pub const SERUM_PROGRAM_ID: &str = "srmqPvymJeFKQ4zGQed1GFppgkRHL9kaELCbyksJtPX"; 
+// Fee di swap delle pool AMM V4 (0,25%)
+const SWAP_FEE_BPS: u128 = 25;
 
 // Struttura Dati Istruzione Swap (Borsh)
 #[derive(BorshSerialize, BorshDeserialize, Debug)]
@@ -47,6 +47,8 @@ pub struct RaydiumPoolKeys {
     pub market_coin_vault: Pubkey,
     pub market_pc_vault: Pubkey,
     pub market_vault_signer: Pubkey,
+    // Mint dei token LP della pool (verifica burn/lock della liquidità)
+    pub lp_mint: Pubkey,
 }
 
 // Struttura Dati on-chain AMM (Layout di memoria)
@@ -163,10 +165,29 @@ pub async fn fetch_pool_keys_by_mint(
     let amm_authority = Pubkey::from_str("5Q544fKrFoe6tsEbD7S8EmxGTJYAKtTVhAW5Q5pge4j1")?;
 
     Ok(RaydiumPoolKeys {
-        amm_id: amm_id.clone(), amm_authority, amm_open_orders: amm_info.amm_open_orders, amm_target_orders: amm_info.amm_target_orders, amm_coin_vault: amm_info.pool_coin_token_account, amm_pc_vault: amm_info.pool_pc_token_account, market_program_id: Pubkey::from_str(SERUM_PROGRAM_ID)?, market_id, market_bids, market_asks, market_event_queue, market_coin_vault, market_pc_vault, market_vault_signer,
+        amm_id: *amm_id, amm_authority, amm_open_orders: amm_info.amm_open_orders, amm_target_orders: amm_info.amm_target_orders, amm_coin_vault: amm_info.pool_coin_token_account, amm_pc_vault: amm_info.pool_pc_token_account, market_program_id: Pubkey::from_str(SERUM_PROGRAM_ID)?, market_id, market_bids, market_asks, market_event_queue, market_coin_vault, market_pc_vault, market_vault_signer,
+        lp_mint: amm_info.lp_mint_address,
     })
 }
 
+/// Quote di acquisto (SOL -> Token) dalle riserve dei vault della pool: prodotto costante al netto della fee.
+/// Serve al router per il minimo in uscita quando Jupiter e Orca non hanno una quote.
+pub async fn quote_buy(
+    network: &Arc<NetworkClient>,
+    pool_keys: &RaydiumPoolKeys,
+    amount_in: u64,
+) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+    let (coin, pc) = tokio::try_join!(
+        network.rpc.get_token_account_balance(&pool_keys.amm_coin_vault),
+        network.rpc.get_token_account_balance(&pool_keys.amm_pc_vault)
+    )?;
+    // Coin = token, PC = WSOL (la pool è cercata per coppia token/SOL)
+    let (token_reserve, sol_reserve) = (coin.amount.parse::<u128>()?, pc.amount.parse::<u128>()?);
+    if token_reserve == 0 || sol_reserve == 0 { return Err("Pool Raydium senza liquidità".into()); }
+    let in_after_fee = amount_in as u128 * (10_000 - SWAP_FEE_BPS) / 10_000;
+    Ok((token_reserve * in_after_fee / (sol_reserve + in_after_fee)) as u64)
+}
+
 /// Esegue lo Swap su Raydium V4
 pub async fn execute_swap(
     network: &Arc<NetworkClient>,
@@ -174,38 +195,30 @@ pub async fn execute_swap(
     pool_keys: &RaydiumPoolKeys,
     token_mint_address: Pubkey, 
     amount_in: u64, 
-    slippage_bps: u64 
+    min_amount_out: u64 
 ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
 
     let user = payer.pubkey();
     let wsol_mint = spl_token::native_mint::id();
     let program_id = Pubkey::from_str(RAYDIUM_V4_PROGRAM_ID)?;
 
-    // CALCOLO MINIMO OUT
-    // Per lo sniping veloce su Raydium diretto, impostiamo min_out a 0 per evitare fallimenti dovuti a volatilità estrema.
-    // La protezione reale viene fatta a monte dall'analisi di sicurezza e liquidità.
-    // Se stiamo usando Jupiter (in jupiter.rs), lì lo slippage è gestito precisamente.
-    // Qui, se siamo su Raydium diretto, stiamo cercando velocità pura.
-    let min_amount_out = 0; 
+    // MINIMO OUT
+    // Calcolato dal router (swap_router.rs) sulla quote migliore con lo stesso slippage delle altre route
+    // (quote_buy sulle riserve della pool se Jupiter e Orca non ne hanno una).
 
     let mut instructions = Vec::new();
 
-    // 1. PRIORITY FEES (Massima Velocità)
-    // 1M microlamports = 0.001 SOL. Abbastanza per battere la congestione media.
-    instructions.push(ComputeBudgetInstruction::set_compute_unit_price(1_000_000));
-    instructions.push(ComputeBudgetInstruction::set_compute_unit_limit(200_000));
-
-    // 2. GESTIONE WSOL (Wrap SOL)
+    // 1. GESTIONE WSOL (Wrap SOL)
     let wsol_ata = spl_associated_token_account::get_associated_token_address(&user, &wsol_mint);
     instructions.push(spl_associated_token_account::instruction::create_associated_token_account_idempotent(&user, &user, &wsol_mint, &spl_token::id()));
     instructions.push(system_instruction::transfer(&user, &wsol_ata, amount_in));
     instructions.push(spl_token::instruction::sync_native(&spl_token::id(), &wsol_ata)?);
 
-    // 3. GESTIONE TOKEN DESTINAZIONE (Create ATA)
+    // 2. GESTIONE TOKEN DESTINAZIONE (Create ATA)
     let token_ata = spl_associated_token_account::get_associated_token_address(&user, &token_mint_address);
     instructions.push(spl_associated_token_account::instruction::create_associated_token_account_idempotent(&user, &user, &token_mint_address, &spl_token::id()));
 
-    // 4. SWAP INSTRUCTION
+    // 3. SWAP INSTRUCTION
     let data = SwapInstructionData {
         instruction: 9, // swapBaseIn
         amount_in,
@@ -235,9 +248,12 @@ pub async fn execute_swap(
 
     instructions.push(Instruction { program_id, accounts, data: data.try_to_vec()? });
 
-    // 5. CLOSE WSOL (Recupero Rent)
+    // 4. CLOSE WSOL (Recupero Rent)
     instructions.push(spl_token::instruction::close_account(&spl_token::id(), &wsol_ata, &user, &user, &[])?);
 
+    // 5. COMPUTE BUDGET (Profilo Swap: p90 delle fee sulla pool, limite CU stimato)
+    let instructions = network.with_compute_budget(TxProfile::Swap, &user, instructions, &[pool_keys.amm_id]).await;
+
     // 6. FIRMA E INVIO
     let recent_blockhash = network.rpc.get_latest_blockhash().await?;
     let transaction = Transaction::new_signed_with_payer(&instructions, Some(&user), &[payer], recent_blockhash);
@@ -0,0 +1,103 @@
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use std::sync::Arc;
+use chrono::{Duration as ChronoDuration, Utc};
+use serde::Serialize;
+use solana_sdk::pubkey::Pubkey;
+use tokio::time::Duration;
+use log::{info, warn};
+use crate::{db, dust, i18n, metadata, network, telegram_bot, AppState};
+
+// --- RICONCILIAZIONE (Trade nel DB vs Token nel wallet on-chain) ---
+// 1. Ogni RECONCILE_SECS, per gli utenti attivi o con trade OPEN, si leggono i token account del wallet
+// 2. Trade OPEN su un token a saldo zero (venduto fuori dal bot, es. da Phantom) = fantasma:
+//    status RECONCILED (PnL sconosciuto, fuori dalle statistiche), posizione tolta dal Position Manager, avviso Telegram.
+//    I trade aperti da meno di GRACE_SECS restano fuori: l'acquisto può non essere ancora confermato.
+// 3. Token con saldo ma senza trade OPEN (esclusi SOL, stablecoin e griglia attiva) = non tracciati:
+//    nessuna azione, vengono segnalati in /status (AppState.untracked_holdings)
+
+const RECONCILE_SECS: u64 = 15 * 60;
+const GRACE_SECS: i64 = 10 * 60;
+const SOL_MINT: &str = "So11111111111111111111111111111111111111112";
+const STABLECOINS: [&str; 2] = ["EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v", "Es9vMFrzaCERmJfrF4H2FYD4KCoNkY11McCe8BenwNYB"];
+
+// Token nel wallet senza trade registrato
+#[derive(Serialize, Clone, Debug)]
+pub struct UntrackedHolding {
+    pub mint: String,
+    pub symbol: String,
+    pub amount: u64,
+    pub ui_amount: f64,
+}
+
+pub async fn run_reconciler(pool: db::DbPool, net: Arc<network::NetworkClient>, state: Arc<AppState>) {
+    info!("🔎 Reconciler: ONLINE");
+
+    loop {
+        // Utenti da controllare: trade OPEN (raggruppati per utente) + auto-trading attivo
+        let mut open: HashMap<String, HashSet<String>> = HashMap::new();
+        for (_, user_id, token, _, _, _) in db::get_open_trades(&pool).await.unwrap_or_default() {
+            open.entry(user_id).or_default().insert(token);
+        }
+        for user_id in db::get_active_users(&pool).await.unwrap_or_default() {
+            open.entry(user_id).or_default();
+        }
+        // Chi non è più tra questi non ha nulla da segnalare
+        state.untracked_holdings.retain(|u, _| open.contains_key(u));
+
+        for (user_id, tokens) in open {
+            if state.is_shutting_down() { break; }
+            if let Err(e) = reconcile_user(&pool, &net, &state, &user_id, &tokens).await {
+                warn!("⚠️ Riconciliazione {} non riuscita: {}", user_id, e);
+            }
+        }
+        if state.sleep_or_shutdown(Duration::from_secs(RECONCILE_SECS)).await { break; }
+    }
+    info!("🛑 Reconciler fermato.");
+}
+
+async fn reconcile_user(pool: &db::DbPool, net: &Arc<network::NetworkClient>, state: &Arc<AppState>, user_id: &str, open_tokens: &HashSet<String>) -> Result<(), String> {
+    let owner = crate::wallet_manager::create_user_wallet(pool, user_id).await.ok()
+        .and_then(|s| Pubkey::from_str(&s).ok())
+        .ok_or("Wallet Error")?;
+    // Errore RPC: meglio non toccare nulla che chiudere trade veri
+    let accounts = dust::token_accounts(net, &owner).await.map_err(|e| e.to_string())?;
+    let held: HashMap<&str, &dust::TokenAccount> = accounts.iter().filter(|a| a.amount > 0).map(|a| (a.mint.as_str(), a)).collect();
+
+    // 1. Trade fantasma
+    let before = Utc::now() - ChronoDuration::seconds(GRACE_SECS);
+    for token in open_tokens.iter().filter(|t| !held.contains_key(t.as_str())) {
+        let ids = db::reconcile_ghost_trades(pool, user_id, token, before).await.map_err(|e| e.to_string())?;
+        if ids.is_empty() { continue; }
+        for id in &ids { state.open_positions.remove(id); }
+        let _ = db::clear_sell_retry(pool, user_id, token).await;
+
+        let symbol = metadata::symbol(pool, net, token).await;
+        let list = ids.iter().map(|id| format!("#{}", id)).collect::<Vec<_>>().join(", ");
+        warn!("🔎 Trade fantasma {} ({}) su {}: token non più nel wallet", list, user_id, token);
+        let lang = i18n::user_lang(pool, user_id).await;
+        telegram_bot::notify_user(user_id, i18n::tr(lang, "notify.reconciled", &[&list, &symbol])).await;
+    }
+
+    // 2. Token non tracciati
+    let mut ignored: HashSet<String> = STABLECOINS.iter().map(|m| m.to_string()).collect();
+    ignored.insert(SOL_MINT.to_string());
+    if let Ok(Some(grid)) = db::get_user_grid(pool, user_id).await {
+        if grid.status == "ACTIVE" { ignored.extend([grid.base_mint, grid.quote_mint]); }
+    }
+    let mut untracked = Vec::new();
+    for acc in held.values().filter(|a| !open_tokens.contains(&a.mint) && !ignored.contains(&a.mint)) {
+        untracked.push(UntrackedHolding {
+            mint: acc.mint.clone(),
+            symbol: metadata::symbol(pool, net, &acc.mint).await,
+            amount: acc.amount,
+            ui_amount: acc.amount as f64 / 10f64.powi(acc.decimals as i32),
+        });
+    }
+    if untracked.is_empty() {
+        state.untracked_holdings.remove(user_id);
+    } else {
+        state.untracked_holdings.insert(user_id.to_string(), untracked);
+    }
+    Ok(())
+}
@@ -0,0 +1,158 @@
+use std::str::FromStr;
+use std::sync::Arc;
+use rand::Rng;
+use serde::Serialize;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signer;
+use solana_sdk::system_instruction;
+use solana_sdk::transaction::Transaction;
+use log::{info, warn};
+use crate::{db, i18n, network, wallet_manager};
+
+// --- PROGRAMMA REFERRAL (Codice invito + quota sul volume degli invitati) ---
+// 1. Ogni utente ha un codice (8 caratteri) da condividere: t.me/<bot>?start=CODICE oppure POST /referrals
+// 2. Su ogni swap riuscito dell'invitato, REFERRAL_FEE_BPS del volume in SOL matura al referrer
+// 3. Il maturato si preleva dal wallet tesoreria (REFERRAL_TREASURY_KEY) sopra la soglia minima
+
+const DEFAULT_FEE_BPS: u64 = 10; // 0.10% del volume
+pub const MIN_PAYOUT_LAMPORTS: u64 = 10_000_000; // 0.01 SOL
+const CODE_LEN: usize = 8;
+// Niente 0/O/1/I: codici da scrivere a mano
+const CODE_ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ReferralSummary {
+    pub code: String,
+    pub link: Option<String>,
+    pub fee_bps: u64,
+    pub invitees: i64,
+    pub volume_sol: f64,
+    pub earned_sol: f64,
+    pub paid_sol: f64,
+    pub available_sol: f64,
+}
+
+fn fee_bps() -> u64 {
+    std::env::var("REFERRAL_FEE_BPS").ok().and_then(|v| v.parse().ok()).unwrap_or(DEFAULT_FEE_BPS).min(10_000)
+}
+
+// Errore DB: dettaglio nel log, all'utente il messaggio tradotto
+fn db_error(lang: i18n::Lang, e: sqlx::Error) -> String {
+    warn!("⚠️ Referral: errore DB: {}", e);
+    i18n::t(lang, "api.db_error").into()
+}
+
+fn random_code() -> String {
+    let mut rng = rand::thread_rng();
+    (0..CODE_LEN).map(|_| CODE_ALPHABET[rng.gen_range(0..CODE_ALPHABET.len())] as char).collect()
+}
+
+/// Codice invito dell'utente (generato al primo uso; si ritenta in caso di collisione)
+pub async fn code_for(pool: &db::DbPool, user_id: &str, lang: i18n::Lang) -> Result<String, String> {
+    for _ in 0..3 {
+        match db::ensure_referral_code(pool, user_id, &random_code()).await {
+            Ok(Some(code)) => return Ok(code),
+            Ok(None) => return Err(i18n::t(lang, "referral.not_registered").into()),
+            Err(e) => warn!("⚠️ Codice referral per {}: {}", user_id, e),
+        }
+    }
+    Err(i18n::t(lang, "referral.code_failed").into())
+}
+
+/// Link Telegram con il codice (solo se BOT_USERNAME è configurato)
+pub fn invite_link(code: &str) -> Option<String> {
+    std::env::var("BOT_USERNAME").ok()
+        .filter(|u| !u.is_empty())
+        .map(|u| format!("https://t.me/{}?start={}", u.trim_start_matches('@'), code))
+}
+
+/// Collega l'invitato al proprietario del codice: messaggio per l'utente oppure errore
+pub async fn link(pool: &db::DbPool, user_id: &str, code: &str) -> Result<String, String> {
+    let lang = i18n::user_lang(pool, user_id).await;
+    let code = code.trim().to_uppercase();
+    let referrer = db::find_user_by_referral_code(pool, &code).await
+        .map_err(|e| db_error(lang, e))?
+        .ok_or(i18n::t(lang, "referral.invalid_code"))?;
+    if referrer == user_id { return Err(i18n::t(lang, "referral.own_code").into()); }
+
+    match db::set_referrer(pool, user_id, &referrer).await {
+        Ok(true) => {
+            info!("🤝 Referral: {} invitato da {}", user_id, referrer);
+            Ok(i18n::t(lang, "referral.linked").into())
+        },
+        Ok(false) => Err(i18n::t(lang, "referral.already_linked").into()),
+        Err(e) => Err(db_error(lang, e)),
+    }
+}
+
+/// Matura la quota del referrer su uno swap riuscito (non blocca il chiamante)
+pub fn accrue(pool: &db::DbPool, user_id: &str, volume_lamports: u64, signature: &str) {
+    let reward = volume_lamports * fee_bps() / 10_000;
+    if reward == 0 { return; }
+    let (pool, user_id, signature) = (pool.clone(), user_id.to_string(), signature.to_string());
+    tokio::spawn(async move {
+        let referrer = match db::get_referrer(&pool, &user_id).await {
+            Ok(Some(r)) => r,
+            _ => return,
+        };
+        if let Err(e) = db::record_referral_reward(&pool, &referrer, &user_id, volume_lamports, reward, &signature).await {
+            warn!("⚠️ Quota referral non salvata ({} -> {}): {}", user_id, referrer, e);
+        }
+    });
+}
+
+pub async fn summary(pool: &db::DbPool, user_id: &str) -> Result<ReferralSummary, String> {
+    let lang = i18n::user_lang(pool, user_id).await;
+    let code = code_for(pool, user_id, lang).await?;
+    let (invitees, volume, earned, paid) = db::get_referral_stats(pool, user_id).await.map_err(|e| db_error(lang, e))?;
+    let sol = |l: u64| l as f64 / 1_000_000_000.0;
+    Ok(ReferralSummary {
+        link: invite_link(&code),
+        code,
+        fee_bps: fee_bps(),
+        invitees,
+        volume_sol: sol(volume),
+        earned_sol: sol(earned),
+        paid_sol: sol(paid),
+        available_sol: sol(earned.saturating_sub(paid)),
+    })
+}
+
+/// Paga tutto il maturato sul wallet dell'utente. Ritorna la firma della TX.
+pub async fn withdraw(pool: &db::DbPool, net: &Arc<network::NetworkClient>, user_id: &str) -> Result<String, String> {
+    let lang = i18n::user_lang(pool, user_id).await;
+    let treasury = std::env::var("REFERRAL_TREASURY_KEY").ok()
+        .and_then(|k| wallet_manager::keypair_from_base58(&k).ok())
+        .ok_or(i18n::t(lang, "referral.payouts_disabled"))?;
+    let dest = wallet_manager::create_user_wallet(pool, user_id).await.ok()
+        .and_then(|s| Pubkey::from_str(&s).ok())
+        .ok_or(i18n::t(lang, "withdraw.wallet_error"))?;
+
+    // Prenotazione atomica: due richieste parallele non possono pagare lo stesso maturato
+    let (payout_id, amount) = db::reserve_referral_payout(pool, user_id, MIN_PAYOUT_LAMPORTS).await
+        .map_err(|e| db_error(lang, e))?
+        .ok_or_else(|| i18n::tr(lang, "referral.min_payout", &[&(MIN_PAYOUT_LAMPORTS as f64 / 1_000_000_000.0)]))?;
+
+    let ix = system_instruction::transfer(&treasury.pubkey(), &dest, amount);
+    let ixs = net.with_compute_budget(network::TxProfile::Transfer, &treasury.pubkey(), vec![ix], &[]).await;
+    let res = match net.rpc.get_latest_blockhash().await {
+        Ok(bh) => {
+            let tx = Transaction::new_signed_with_payer(&ixs, Some(&treasury.pubkey()), &[&treasury], bh);
+            net.rpc.send_transaction(&tx).await.map(|s| s.to_string()).map_err(|e| e.to_string())
+        },
+        Err(e) => Err(e.to_string()),
+    };
+
+    match res {
+        Ok(sig) => {
+            let _ = db::finish_referral_payout(pool, payout_id, "COMPLETED", Some(&sig)).await;
+            info!("🤝 Referral payout {} -> {}: {} lamports ({})", payout_id, user_id, amount, sig);
+            Ok(sig)
+        },
+        Err(e) => {
+            warn!("⚠️ Referral payout {} fallito: {}", payout_id, e);
+            let _ = db::finish_referral_payout(pool, payout_id, "FAILED", None).await;
+            Err(i18n::t(lang, "referral.network_error").into())
+        }
+    }
+}
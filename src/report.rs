@@ -0,0 +1,176 @@
+use std::str::FromStr;
+use std::sync::Arc;
+use chrono::{Timelike, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+use tokio::time::Duration;
+use log::{info, warn};
+use crate::{db, i18n, network, notifications, telegram_bot, AppState};
+
+// --- REPORT GIORNALIERO (Orario e fuso per utente, salvati in users.settings["report"]) ---
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct ReportSettings {
+    pub enabled: bool,
+    pub hour: u32,
+    pub minute: u32,
+    pub timezone: String,
+    // Data locale (YYYY-MM-DD) dell'ultimo invio: un solo report al giorno
+    pub last_sent: Option<String>,
+}
+
+impl Default for ReportSettings {
+    fn default() -> Self {
+        Self { enabled: true, hour: 21, minute: 0, timezone: "UTC".into(), last_sent: None }
+    }
+}
+
+impl ReportSettings {
+    pub fn from_settings(settings: &serde_json::Value) -> Self {
+        serde_json::from_value(settings["report"].clone()).unwrap_or_default()
+    }
+
+    pub fn tz(&self) -> Tz {
+        Tz::from_str(&self.timezone).unwrap_or(Tz::UTC)
+    }
+
+    /// Data locale se il report è dovuto adesso (orario passato e non ancora inviato oggi)
+    fn due_date(&self, now: chrono::DateTime<Utc>) -> Option<String> {
+        if !self.enabled { return None; }
+        let local = now.with_timezone(&self.tz());
+        let today = local.format("%Y-%m-%d").to_string();
+        let passed = (local.hour(), local.minute()) >= (self.hour, self.minute);
+        (passed && self.last_sent.as_deref() != Some(today.as_str())).then_some(today)
+    }
+}
+
+// Numeri del report (ultime 24h), inviati su Telegram e ai webhook
+#[derive(Serialize, Clone, Debug)]
+pub struct DailyStats {
+    pub balance_sol: f64,
+    pub pnl_sol: f64,
+    pub trades_opened: i64,
+    pub trades_closed: i64,
+    pub open_positions: usize,
+    // SOL investiti nelle posizioni ancora aperte
+    pub open_exposure_sol: f64,
+    // % di trade chiusi in profitto (None = nessun trade chiuso)
+    pub win_rate_pct: Option<f64>,
+    pub by_strategy: Vec<db::StrategyBreakdown>,
+    pub best_trade: Option<db::TradeOutcome>,
+    pub worst_trade: Option<db::TradeOutcome>,
+    // PnL realizzato cumulato a fine di ogni ora (dalla più vecchia)
+    pub equity_curve: Vec<f64>,
+}
+
+const SPARK_BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// "08:00" -> (8, 0)
+pub fn parse_report_time(s: &str) -> Option<(u32, u32)> {
+    let (h, m) = s.split_once(':')?;
+    let (h, m) = (h.parse::<u32>().ok()?, m.parse::<u32>().ok()?);
+    (h < 24 && m < 60).then_some((h, m))
+}
+
+/// Controlla ogni minuto quali utenti devono ricevere il report (ognuno col suo fuso)
+pub async fn run_daily_report_task(pool: db::DbPool, net: Arc<network::NetworkClient>, state: Arc<AppState>) {
+    info!("📰 Daily Report: ONLINE");
+
+    loop {
+        let now = Utc::now();
+        for (user_id, settings) in db::get_all_user_settings(&pool).await.unwrap_or_default() {
+            let mut report = ReportSettings::from_settings(&settings);
+            let today = match report.due_date(now) { Some(d) => d, None => continue };
+
+            let stats = daily_stats(&pool, &net, &state, &user_id).await;
+            telegram_bot::notify_user(&user_id, format_report(i18n::lang_from_settings(&settings), &stats)).await;
+            notifications::notify(&pool, &user_id, notifications::Notification::DailyReport(stats));
+
+            report.last_sent = Some(today);
+            if let Err(e) = db::update_setting(&pool, &user_id, "report", serde_json::json!(report)).await {
+                warn!("⚠️ Report {}: salvataggio stato fallito: {}", user_id, e);
+            }
+        }
+        if state.sleep_or_shutdown(Duration::from_secs(60)).await { break; }
+    }
+    info!("🛑 Daily Report fermato.");
+}
+
+async fn daily_stats(pool: &db::DbPool, net: &Arc<network::NetworkClient>, state: &Arc<AppState>, user_id: &str) -> DailyStats {
+    let now = Utc::now();
+    let since = now - chrono::Duration::hours(24);
+    let (opened, closed, pnl) = db::get_stats_since(pool, user_id, since).await.unwrap_or((0, 0, 0.0));
+    let by_strategy = db::get_strategy_breakdown_since(pool, user_id, since).await.unwrap_or_default();
+    let (best_trade, worst_trade) = db::get_best_worst_trades_since(pool, user_id, since).await.unwrap_or((None, None));
+    let hourly = db::get_hourly_pnl_since(pool, user_id, since).await.unwrap_or_default();
+
+    let positions = state.positions_snapshot(Some(user_id));
+    let open_exposure_sol = positions.iter().map(|p| p.amount_in_lamports).sum::<u64>() as f64 / 1_000_000_000.0;
+    let wins: i64 = by_strategy.iter().map(|b| b.wins).sum();
+    let win_rate_pct = (closed > 0).then(|| wins as f64 / closed as f64 * 100.0);
+
+    let mut balance = 0.0;
+    if let Ok(pk_str) = crate::wallet_manager::create_user_wallet(pool, user_id).await {
+        if let Ok(pk) = Pubkey::from_str(&pk_str) {
+            balance = net.get_balance_fast(&pk).await as f64 / 1_000_000_000.0;
+        }
+    }
+
+    DailyStats {
+        balance_sol: balance, pnl_sol: pnl, trades_opened: opened, trades_closed: closed, open_positions: positions.len(),
+        open_exposure_sol, win_rate_pct, by_strategy, best_trade, worst_trade,
+        equity_curve: equity_curve(&hourly, since, now),
+    }
+}
+
+/// PnL cumulato ora per ora da `since` a `now` (le ore senza chiusure ripetono il valore precedente)
+fn equity_curve(hourly: &[(String, f64)], since: chrono::DateTime<Utc>, now: chrono::DateTime<Utc>) -> Vec<f64> {
+    let mut cumulated = 0.0;
+    let mut curve = Vec::new();
+    for i in 0..=(now - since).num_hours() {
+        let key = (since + chrono::Duration::hours(i)).format("%Y-%m-%d %H").to_string();
+        cumulated += hourly.iter().filter(|(h, _)| *h == key).map(|(_, pnl)| pnl).sum::<f64>();
+        curve.push(cumulated);
+    }
+    curve
+}
+
+/// Grafico a barre testuale (▁..█) scalato tra minimo e massimo
+fn sparkline(values: &[f64]) -> String {
+    let min = values.iter().cloned().fold(f64::INFINITY, f64::min);
+    let max = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    values.iter().map(|v| {
+        if max - min < 1e-9 { return SPARK_BARS[3]; }
+        SPARK_BARS[(((v - min) / (max - min)) * (SPARK_BARS.len() - 1) as f64).round() as usize]
+    }).collect()
+}
+
+fn format_report(lang: i18n::Lang, s: &DailyStats) -> String {
+    let icon = if s.pnl_sol >= 0.0 { "🟢" } else { "🔴" };
+    let win_rate = s.win_rate_pct.map(|w| format!("{:.0}%", w)).unwrap_or_else(|| "-".into());
+
+    let mut details = String::new();
+    if !s.by_strategy.is_empty() {
+        details.push_str(i18n::t(lang, "report.by_strategy"));
+        for b in &s.by_strategy {
+            let name = if b.strategy.is_empty() { "-" } else { b.strategy.as_str() };
+            details.push_str(&i18n::tr(lang, "report.strategy_line", &[&name, &b.closed, &b.wins, &format!("{:+.4}", b.pnl_sol)]));
+        }
+    }
+    for (key, trade) in [("report.best", &s.best_trade), ("report.worst", &s.worst_trade)] {
+        if let Some(t) = trade {
+            let name = t.symbol.clone().unwrap_or_else(|| t.token.chars().take(6).collect());
+            details.push_str(&i18n::tr(lang, key, &[&name, &format!("{:+.4}", t.pnl_sol), &format!("{:+.1}", t.pnl_pct)]));
+        }
+    }
+    if s.equity_curve.len() > 1 {
+        details.push_str(&i18n::tr(lang, "report.equity", &[&sparkline(&s.equity_curve)]));
+    }
+
+    i18n::tr(lang, "report.daily", &[
+        &format!("{:.4}", s.balance_sol), &icon, &format!("{:+.4}", s.pnl_sol), &s.trades_opened, &s.trades_closed, &win_rate,
+        &s.open_positions, &format!("{:.4}", s.open_exposure_sol), &details,
+    ])
+}
@@ -0,0 +1,242 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+use chrono::{Duration as ChronoDuration, TimeZone, Utc};
+use serde::Serialize;
+use solana_sdk::pubkey::Pubkey;
+use tokio::time::Duration;
+use log::{info, warn};
+use crate::{db, i18n, jupiter, network, telegram_bot, AppState};
+use crate::strategy::StrategyParams;
+
+// --- RISK ENGINE (Circuit Breaker sulla perdita giornaliera) ---
+// 1. Al primo controllo del giorno (UTC) si fissa il saldo SOL di partenza dell'utente
+// 2. Se il PnL realizzato del giorno scende sotto -max_daily_loss_pct% del saldo iniziale:
+//    auto-trading spento (is_active = 0), notifica Telegram, Auto-Buy bloccato fino a mezzanotte
+// 3. A mezzanotte UTC il blocco decade e l'auto-trading viene riacceso
+
+fn today() -> String {
+    Utc::now().format("%Y-%m-%d").to_string()
+}
+
+/// true se l'Auto-Buy dell'utente è bloccato dal Circuit Breaker (in caso di errore DB si blocca)
+pub async fn auto_buy_blocked(pool: &db::DbPool, user_id: &str) -> bool {
+    db::is_risk_tripped(pool, user_id, &today()).await.unwrap_or(true)
+}
+
+// --- LIMITI D'INVESTIMENTO (Validi per ogni acquisto: auto, sniper, manuale, DCA) ---
+// max_per_trade_sol per singolo acquisto, max_total_exposure_sol sul valore bloccato nei trade aperti,
+// reserve_sol sempre lasciati nel wallet. Gli acquisti automatici vengono ridotti al tetto,
+// quelli chiesti dall'utente (manuali, DCA, ordini limite) rifiutati se lo superano.
+// Valore bloccato = trade OPEN ai prezzi live (investito x prezzo / prezzo d'ingresso): una posizione
+// salita occupa più esposizione, una scesa ne libera. Senza prezzo live o d'ingresso vale quanto è costata.
+
+// Margine per le fee della TX di swap, oltre alla riserva
+const SWAP_FEE_LAMPORTS: u64 = 5_000;
+
+#[derive(Debug, Clone, Copy)]
+pub struct BuyBudget {
+    pub per_trade: u64,
+    pub exposure_left: Option<u64>,
+    pub spendable: u64,
+}
+
+// Fondi dell'utente per la Dashboard (/status), in SOL
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct Funds {
+    pub balance_sol: f64,
+    // SOL investiti nei trade aperti (costo)
+    pub invested_sol: f64,
+    // Valore live degli stessi trade
+    pub locked_sol: f64,
+    pub reserve_sol: f64,
+    // None = nessun tetto di esposizione (max_total_exposure_sol = 0)
+    pub exposure_left_sol: Option<f64>,
+    // Acquistabili ora: saldo oltre riserva e fee, entro l'esposizione residua
+    pub available_sol: f64,
+}
+
+impl BuyBudget {
+    /// Importo massimo acquistabile ora (lamports)
+    pub fn max(&self) -> u64 {
+        self.per_trade.min(self.available())
+    }
+
+    /// SOL liberi (lamports): spendibili oltre la riserva, entro l'esposizione residua
+    pub fn available(&self) -> u64 {
+        self.exposure_left.unwrap_or(u64::MAX).min(self.spendable)
+    }
+
+    /// Verifica un importo richiesto dall'utente, con il limite che lo blocca (nella lingua dell'utente)
+    pub fn check(&self, amount: u64, lang: i18n::Lang) -> Result<(), String> {
+        let sol = |l: u64| format!("{:.4}", l as f64 / 1_000_000_000.0);
+        if amount > self.per_trade {
+            return Err(i18n::tr(lang, "risk.over_per_trade", &[&sol(self.per_trade)]));
+        }
+        if let Some(left) = self.exposure_left.filter(|left| amount > *left) {
+            return Err(i18n::tr(lang, "risk.exposure_reached", &[&sol(left)]));
+        }
+        if amount > self.spendable {
+            return Err(i18n::tr(lang, "risk.insufficient_funds", &[&sol(self.spendable)]));
+        }
+        Ok(())
+    }
+}
+
+/// Budget di acquisto dell'utente dato il saldo SOL attuale (lamports)
+pub async fn buy_budget(pool: &db::DbPool, user_id: &str, params: &StrategyParams, balance: u64) -> Result<BuyBudget, sqlx::Error> {
+    // Senza tetto di esposizione non serve valutare le posizioni
+    let locked = if params.max_total_exposure_sol > 0.0 { locked_value(pool, user_id).await?.1 } else { 0 };
+    Ok(budget_from(params, balance, locked))
+}
+
+fn budget_from(params: &StrategyParams, balance: u64, locked: u64) -> BuyBudget {
+    let to_lamports = |sol: f64| (sol * 1_000_000_000.0) as u64;
+    BuyBudget {
+        per_trade: to_lamports(params.max_per_trade_sol),
+        exposure_left: (params.max_total_exposure_sol > 0.0).then(|| to_lamports(params.max_total_exposure_sol).saturating_sub(locked)),
+        spendable: balance.saturating_sub(to_lamports(params.reserve_sol) + SWAP_FEE_LAMPORTS),
+    }
+}
+
+/// Valore dei trade OPEN dell'utente: (investito, valore live) in lamports. Un prezzo per token (cache condivisa).
+pub async fn locked_value(pool: &db::DbPool, user_id: &str) -> Result<(u64, u64), sqlx::Error> {
+    let entries = db::get_open_trade_entries(pool, user_id).await?;
+    let mut prices: HashMap<String, f64> = HashMap::new();
+    let (mut invested, mut locked) = (0u64, 0u64);
+    for (token, amount_in, entry) in entries {
+        if !prices.contains_key(&token) {
+            let p = jupiter::get_token_market_data(&token).await.map(|m| m.price).unwrap_or(0.0);
+            prices.insert(token.clone(), p);
+        }
+        let price = prices[&token];
+        invested += amount_in;
+        locked += match entry {
+            Some(e) if e > 0.0 && price > 0.0 => (amount_in as f64 * price / e) as u64,
+            _ => amount_in,
+        };
+    }
+    Ok((invested, locked))
+}
+
+/// Fondi correnti dell'utente dato il saldo SOL (lamports)
+pub async fn funds(pool: &db::DbPool, user_id: &str, params: &StrategyParams, balance: u64) -> Result<Funds, sqlx::Error> {
+    let sol = |l: u64| l as f64 / 1_000_000_000.0;
+    let (invested, locked) = locked_value(pool, user_id).await?;
+    let budget = budget_from(params, balance, locked);
+    Ok(Funds {
+        balance_sol: sol(balance),
+        invested_sol: sol(invested),
+        locked_sol: sol(locked),
+        reserve_sol: params.reserve_sol,
+        exposure_left_sol: budget.exposure_left.map(sol),
+        available_sol: sol(budget.available()),
+    })
+}
+
+// --- FREQUENZA ACQUISTI AUTOMATICI (Cooldown per token e tetto orario) ---
+// buy_cooldown_secs: dopo un acquisto lo stesso token non si ricompra per quel tempo (0 = nessun cooldown)
+// max_trades_per_hour: acquisti automatici massimi nell'ultima ora su tutti i token (0 = nessun limite)
+// Gli istanti d'acquisto sono quelli della tabella trades: sopravvivono ai riavvii.
+
+#[derive(Serialize, Debug, Clone)]
+pub struct TokenCooldown {
+    pub token: String,
+    pub remaining_secs: i64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct TradeFrequency {
+    pub cooldowns: Vec<TokenCooldown>,
+    pub buys_last_hour: i64,
+    // 0 = nessun limite
+    pub max_trades_per_hour: u32,
+}
+
+impl TradeFrequency {
+    /// Motivo per cui l'Auto-Buy sul token va saltato, se c'è
+    pub fn blocks(&self, token: &str) -> Option<String> {
+        if let Some(c) = self.cooldowns.iter().find(|c| c.token == token) {
+            return Some(format!("Cooldown attivo ({}s)", c.remaining_secs));
+        }
+        if self.max_trades_per_hour > 0 && self.buys_last_hour >= self.max_trades_per_hour as i64 {
+            return Some(format!("{} acquisti nell'ultima ora (max_trades_per_hour {})", self.buys_last_hour, self.max_trades_per_hour));
+        }
+        None
+    }
+}
+
+/// Cooldown attivi e acquisti dell'ultima ora dell'utente
+pub async fn trade_frequency(pool: &db::DbPool, user_id: &str, params: &StrategyParams) -> Result<TradeFrequency, sqlx::Error> {
+    let cooldowns = match params.buy_cooldown_secs {
+        0 => Vec::new(),
+        secs => db::active_cooldowns(pool, user_id, secs as i64).await?
+            .into_iter()
+            .map(|(token, remaining_secs)| TokenCooldown { token, remaining_secs })
+            .collect(),
+    };
+    Ok(TradeFrequency {
+        cooldowns,
+        buys_last_hour: db::count_buys_since(pool, user_id, Utc::now() - ChronoDuration::hours(1)).await?,
+        max_trades_per_hour: params.max_trades_per_hour,
+    })
+}
+
+/// Controlla ogni 30 secondi le perdite realizzate degli utenti attivi
+pub async fn run_risk_monitor(pool: db::DbPool, net: Arc<network::NetworkClient>, state: Arc<AppState>) {
+    info!("🧯 Risk Engine: ONLINE");
+
+    loop {
+        let day = today();
+
+        // RESET DI MEZZANOTTE
+        match db::reset_tripped_before(&pool, &day).await {
+            Ok(users) => for user_id in users {
+                info!("🧯 Circuit Breaker resettato per {}", user_id);
+                let lang = i18n::user_lang(&pool, &user_id).await;
+                telegram_bot::notify_user(&user_id, i18n::t(lang, "notify.risk_reset").to_string()).await;
+            },
+            Err(e) => warn!("⚠️ Reset Circuit Breaker fallito: {}", e),
+        }
+
+        for user_id in db::get_active_users(&pool).await.unwrap_or_default() {
+            check_user(&pool, &net, &user_id, &day).await;
+        }
+        if state.sleep_or_shutdown(Duration::from_secs(30)).await { break; }
+    }
+    info!("🛑 Risk Engine fermato.");
+}
+
+async fn check_user(pool: &db::DbPool, net: &Arc<network::NetworkClient>, user_id: &str, day: &str) {
+    let pk = match crate::wallet_manager::create_user_wallet(pool, user_id).await.ok().and_then(|s| Pubkey::from_str(&s).ok()) {
+        Some(pk) => pk,
+        None => return,
+    };
+
+    // Il saldo si legge comunque: serve solo la prima volta del giorno, ma è una chiamata economica
+    let balance = net.get_balance_fast(&pk).await;
+    let (start_balance, tripped) = match db::ensure_risk_day(pool, user_id, day, balance).await {
+        Ok(r) => r,
+        Err(e) => { warn!("⚠️ Risk {}: {}", user_id, e); return; }
+    };
+    if tripped || start_balance == 0 { return; }
+
+    let midnight = Utc.from_utc_datetime(&Utc::now().date_naive().and_hms_opt(0, 0, 0).unwrap());
+    let (_, _, pnl_sol) = match db::get_stats_since(pool, user_id, midnight).await {
+        Ok(s) => s,
+        Err(e) => { warn!("⚠️ Risk {}: {}", user_id, e); return; }
+    };
+
+    let params = db::get_strategy_params(pool, user_id).await;
+    let start_sol = start_balance as f64 / 1_000_000_000.0;
+    let max_loss_sol = start_sol * params.max_daily_loss_pct / 100.0;
+    if pnl_sol > -max_loss_sol { return; }
+
+    if let Ok(true) = db::trip_risk_day(pool, user_id, day).await {
+        warn!("🧯 CIRCUIT BREAKER {}: PnL {:.4} SOL (limite -{:.4})", user_id, pnl_sol, max_loss_sol);
+        let lang = i18n::user_lang(pool, user_id).await;
+        telegram_bot::notify_user(user_id, i18n::tr(lang, "notify.circuit_breaker", &[
+            &format!("{:.4}", pnl_sol), &format!("{:.1}", params.max_daily_loss_pct), &format!("{:.4}", start_sol),
+        ])).await;
+    }
+}
@@ -0,0 +1,93 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+use tokio::time::Duration;
+use log::{debug, info, warn};
+use crate::{db, i18n, jupiter, network, strategy, telegram_bot, AppState};
+
+// --- RUG DETECTOR (Ritiro di liquidità sui token in posizione) ---
+// 1. Ogni 15s si legge la liquidità (USD) della pool di ogni token in posizione
+// 2. Per ogni utente: se la liquidità scende oltre rug_liquidity_drop_pct% dal massimo degli ultimi
+//    rug_window_mins minuti è la firma classica del rug
+// 3. Vendita immediata con lo slippage massimo, senza passare da trailing stop e coda dei retry
+
+const CHECK_SECS: u64 = 15;
+// Slippage massimo consentito dalla strategia: meglio uscire male che restare con token senza pool
+const RUG_SLIPPAGE_BPS: u16 = 5_000;
+// Storico conservato (la finestra più lunga configurabile)
+const MAX_WINDOW_SECS: i64 = 3600;
+
+// Campioni (timestamp, liquidità USD) per token
+type History = HashMap<String, VecDeque<(i64, f64)>>;
+
+/// Calo % della liquidità attuale rispetto al massimo degli ultimi `window_secs`. None se i campioni non bastano.
+fn liquidity_drop_pct(samples: &VecDeque<(i64, f64)>, now: i64, window_secs: i64) -> Option<f64> {
+    let (_, current) = *samples.back()?;
+    let peak = samples.iter().filter(|(t, _)| now - t <= window_secs).map(|(_, l)| *l).fold(0.0, f64::max);
+    if samples.len() < 2 || peak <= 0.0 { return None; }
+    Some((peak - current) / peak * 100.0)
+}
+
+pub async fn run_rug_monitor(pool: db::DbPool, net: Arc<network::NetworkClient>, state: Arc<AppState>) {
+    info!("🚨 Rug Detector: ONLINE");
+    let mut history: History = HashMap::new();
+    // Vendite già scattate (utente, token): una sola per episodio
+    let mut fired: HashSet<(String, String)> = HashSet::new();
+
+    loop {
+        let positions = state.positions_snapshot(None);
+        let mut holders: HashMap<String, HashSet<String>> = HashMap::new();
+        for p in &positions { holders.entry(p.token.clone()).or_default().insert(p.user_id.clone()); }
+        history.retain(|t, _| holders.contains_key(t));
+        fired.retain(|(u, t)| holders.get(t).is_some_and(|users| users.contains(u)));
+
+        let now = chrono::Utc::now().timestamp();
+        let mut user_params: HashMap<String, strategy::StrategyParams> = HashMap::new();
+        for (token, users) in holders {
+            if state.is_shutting_down() { break; }
+            // Prezzo 0 = pair non trovata: nessun dato, non liquidità zero
+            let liquidity = match jupiter::get_token_market_data(&token).await {
+                Ok(m) if m.price > 0.0 => m.liquidity_usd,
+                Ok(_) => continue,
+                Err(e) => { debug!("🚨 Liquidità {} non disponibile: {}", token, e); continue; }
+            };
+            let samples = history.entry(token.clone()).or_default();
+            samples.push_back((now, liquidity));
+            while samples.front().is_some_and(|(t, _)| now - t > MAX_WINDOW_SECS) { samples.pop_front(); }
+
+            for user_id in users {
+                if fired.contains(&(user_id.clone(), token.clone())) { continue; }
+                if !user_params.contains_key(&user_id) {
+                    let p = db::get_strategy_params(&pool, &user_id).await;
+                    user_params.insert(user_id.clone(), p);
+                }
+                let params = &user_params[&user_id];
+                if params.rug_liquidity_drop_pct <= 0.0 { continue; }
+
+                let drop = match liquidity_drop_pct(samples, now, params.rug_window_mins as i64 * 60) {
+                    Some(d) if d >= params.rug_liquidity_drop_pct => d,
+                    _ => continue,
+                };
+                warn!("🚨 RUG {} ({}): liquidità -{:.1}% in {} min", token, user_id, drop, params.rug_window_mins);
+                fired.insert((user_id.clone(), token.clone()));
+                let _ = db::mark_token_rugged(&pool, &token).await;
+
+                let reason = format!("🚨 Rug: liquidità -{:.0}%", drop);
+                match crate::trading::emergency_sell(&pool, &net, &state, &user_id, &token, RUG_SLIPPAGE_BPS, &reason).await {
+                    Ok((sig, pnl)) => {
+                        let lang = i18n::user_lang(&pool, &user_id).await;
+                        telegram_bot::notify_user(&user_id, i18n::tr(lang, "notify.rug", &[
+                            &token, &format!("{:.1}", drop), &params.rug_window_mins, &format!("{:.0}", liquidity), &format!("{:+.4}", pnl), &sig,
+                        ])).await;
+                    },
+                    Err(e) => {
+                        // Si riprova al prossimo giro finché la liquidità resta giù
+                        warn!("⚠️ Vendita anti-rug fallita ({}) {}: {}", user_id, token, e);
+                        fired.remove(&(user_id.clone(), token.clone()));
+                    }
+                }
+            }
+        }
+        if state.sleep_or_shutdown(Duration::from_secs(CHECK_SECS)).await { break; }
+    }
+    info!("🛑 Rug Detector fermato.");
+}
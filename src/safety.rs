@@ -1,11 +1,21 @@
-use solana_sdk::{
-    pubkey::Pubkey,
-    program_pack::Pack, 
-};
-use spl_token::state::Mint; 
-use std::sync::Arc;
+use solana_sdk::pubkey::Pubkey;
+// StateWithExtensions legge sia i mint/account del Token classico che quelli Token-2022
+use spl_token_2022::extension::{BaseStateWithExtensions, ExtensionType, StateWithExtensions};
+use spl_token_2022::state::{Account as TokenAccount, Mint};
+use solana_client::rpc_config::RpcSimulateTransactionConfig;
+use solana_sdk::commitment_config::CommitmentConfig;
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use std::sync::{Arc, LazyLock};
+use solana_client::rpc_config::RpcTransactionConfig;
+use solana_transaction_status::UiTransactionEncoding;
 use crate::network::NetworkClient;
+use crate::strategy::StrategyParams;
+use crate::{db, jupiter, raydium};
 
+const WSOL_MINT: &str = "So11111111111111111111111111111111111111112";
+
+#[derive(serde::Serialize)]
 pub struct TokenSafetyReport {
     pub is_safe: bool,
     pub mint_authority_disabled: bool,
@@ -25,8 +35,14 @@ pub async fn check_token_safety(
     let account = network.rpc.get_account(token_mint).await?;
 
     // 2. Decodifica i dati grezzi nella struttura Mint di SPL Token
-    let mint_data = Mint::unpack(&account.data)
+    let mint_state = StateWithExtensions::<Mint>::unpack(&account.data)
         .map_err(|_| "Impossibile decodificare i dati del Token")?;
+    let mint_data = mint_state.base;
+
+    // Token-2022: il Permanent Delegate può spostare/bruciare i token di chiunque
+    let permanent_delegate = mint_state.get_extension_types()
+        .map(|exts| exts.contains(&ExtensionType::PermanentDelegate))
+        .unwrap_or(false);
 
     // 3. ANALISI ANTI-RUG (Mint Authority)
     // Se è None, la supply è fissa (SAFE).
@@ -50,6 +66,11 @@ pub async fn check_token_safety(
         reasons.push("❄️ Freeze Auth Attiva");
     }
 
+    if permanent_delegate {
+        is_safe = false;
+        reasons.push("🕵️ Permanent Delegate (Token-2022)");
+    }
+
     let report_string = if is_safe {
         "✅ Token Sicuro".to_string()
     } else {
@@ -64,4 +85,216 @@ pub async fn check_token_safety(
         decimals: mint_data.decimals,
         reason: report_string,
     })
-}
\ No newline at end of file
+}
+
+pub struct HoneypotReport {
+    pub can_sell: bool,
+    pub round_trip_loss_pct: f64,
+    pub reason: String,
+}
+
+/// ANTI-HONEYPOT: simula un giro completo SOL -> Token -> SOL prima di comprare.
+/// 1. Quote di acquisto e quote inversa (se non esiste una rotta di vendita, è una trappola)
+/// 2. Vendita simulata on-chain dal wallet di un holder reale (blocca transfer hook / freeze / tasse nascoste)
+pub async fn check_honeypot(
+    network: &Arc<NetworkClient>,
+    token_mint: &Pubkey,
+    probe_lamports: u64,
+) -> Result<HoneypotReport, Box<dyn std::error::Error + Send + Sync>> {
+    let mint_str = token_mint.to_string();
+
+    // 1. Round-Trip tramite Quote
+    let buy_quote = jupiter::get_quote(WSOL_MINT, &mint_str, probe_lamports, 100).await?;
+    let tokens_out = jupiter::quote_out_amount(&buy_quote);
+    if tokens_out == 0 {
+        return Ok(HoneypotReport { can_sell: false, round_trip_loss_pct: 100.0, reason: "Quote di acquisto vuota".into() });
+    }
+
+    let sell_quote = match jupiter::get_quote(&mint_str, WSOL_MINT, tokens_out, 100).await {
+        Ok(q) => q,
+        Err(_) => return Ok(HoneypotReport { can_sell: false, round_trip_loss_pct: 100.0, reason: "🍯 Nessuna rotta di vendita".into() }),
+    };
+    let sol_back = jupiter::quote_out_amount(&sell_quote);
+    let loss_pct = (1.0 - sol_back as f64 / probe_lamports as f64) * 100.0;
+
+    // 2. Simulazione della vendita da un holder esistente (con saldo sufficiente)
+    if let Some(holder) = find_token_holder(network, token_mint, tokens_out).await {
+        let tx = jupiter::get_unsigned_swap_tx(&holder.to_string(), sell_quote, None).await?;
+        let config = RpcSimulateTransactionConfig {
+            sig_verify: false,
+            replace_recent_blockhash: true,
+            commitment: Some(CommitmentConfig::processed()),
+            ..Default::default()
+        };
+        let sim = network.rpc.simulate_transaction_with_config(&tx, config).await?;
+        if let Some(err) = sim.value.err {
+            return Ok(HoneypotReport { can_sell: false, round_trip_loss_pct: loss_pct, reason: format!("🍯 Vendita simulata fallita: {}", err) });
+        }
+    }
+
+    Ok(HoneypotReport { can_sell: true, round_trip_loss_pct: loss_pct, reason: format!("✅ Round-Trip -{:.1}%", loss_pct) })
+}
+
+/// Trova un wallet (non un vault/PDA) che detiene almeno `min_amount` token nel proprio ATA
+async fn find_token_holder(network: &Arc<NetworkClient>, token_mint: &Pubkey, min_amount: u64) -> Option<Pubkey> {
+    let largest = network.rpc.get_token_largest_accounts(token_mint).await.ok()?;
+    for acc in largest.iter().take(5) {
+        let address = match acc.address.parse::<Pubkey>() { Ok(a) => a, Err(_) => continue };
+        let account = match network.rpc.get_account(&address).await { Ok(a) => a, Err(_) => continue };
+        let token_acc = match StateWithExtensions::<TokenAccount>::unpack(&account.data) { Ok(t) => t.base, Err(_) => continue };
+        let ata = spl_associated_token_account::get_associated_token_address_with_program_id(&token_acc.owner, token_mint, &account.owner);
+        if token_acc.amount >= min_amount && ata == address {
+            return Some(token_acc.owner);
+        }
+    }
+    None
+}
+
+// Concentrazione degli holder (quota della supply, 0-100)
+#[derive(serde::Serialize)]
+pub struct HolderConcentration {
+    pub top1_pct: f64,
+    pub top10_pct: f64,
+}
+
+/// Quota della supply nei 10 account più grandi (include pool e vault: è un indicatore, non una sentenza)
+pub async fn holder_concentration(network: &Arc<NetworkClient>, token_mint: &Pubkey) -> Result<HolderConcentration, Box<dyn std::error::Error + Send + Sync>> {
+    let supply = network.rpc.get_token_supply(token_mint).await?.amount.parse::<u64>()?;
+    if supply == 0 { return Err("Supply nulla".into()); }
+    let largest = network.rpc.get_token_largest_accounts(token_mint).await?;
+    let amounts: Vec<u64> = largest.iter().take(10).map(|a| a.amount.amount.parse::<u64>().unwrap_or(0)).collect();
+    let pct = |amount: u64| amount as f64 / supply as f64 * 100.0;
+    Ok(HolderConcentration {
+        top1_pct: pct(amounts.first().copied().unwrap_or(0)),
+        top10_pct: pct(amounts.iter().sum()),
+    })
+}
+
+// --- ANALISI DEL LANCIO (Sniper: deployer, LP, dimensione pool) ---
+// 1. Deployer = chi paga la TX initialize2. Si guarda la sua storia: età del wallet (prima TX nota),
+//    chi lo ha finanziato (hot wallet di un exchange = wallet usa e getta) e i rug passati (tabella launches)
+// 2. LP: quota dei token LP minted all'initialize2 che NON è più nel wallet del deployer (bruciata o in locker)
+// 3. Pool: SOL nel vault WSOL della pool Raydium
+// Le soglie sono per utente (StrategyParams.sniper_*): il report si calcola una volta sola per token.
+
+// Firme lette per stimare l'età del wallet: oltre questa storia il wallet è considerato vecchio
+const DEPLOYER_HISTORY_SIGS: usize = 1000;
+// Un wallet finanziato da un exchange da meno di così è un wallet usa e getta
+const FRESH_CEX_HOURS: f64 = 24.0;
+
+// Hot wallet noti degli exchange (sovrascrivibili con CEX_WALLETS=addr1,addr2)
+static CEX_WALLETS: LazyLock<HashSet<String>> = LazyLock::new(|| {
+    match std::env::var("CEX_WALLETS") {
+        Ok(list) => list.split(',').map(|a| a.trim().to_string()).filter(|a| !a.is_empty()).collect(),
+        Err(_) => [
+            "5tzFkiKscXHK5ZXCGbXZxdw7gTjjD1mBwuoFbhUvuAi9", // Binance
+            "9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM", // Binance
+            "H8sMJSCQxfKiFTCfDR3DUMLPwcRbM61LGFJ8N4dK3WjS", // Coinbase
+            "5VCwKtCXgCJ6kit5FybXjvriW3xELsFDhYrPSqtJNmcD", // OKX
+            "AC5RDfQFmDS1deWZos921JfqscXdByf8BKHs5ACWjtW2", // Bybit
+            "FWznbcNXWQuHTawe9RxvQ2LdCENssh12dsznf4RiouN5", // Kraken
+        ].iter().map(|a| a.to_string()).collect(),
+    }
+});
+
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct LaunchReport {
+    pub deployer: String,
+    // None = storia più lunga di DEPLOYER_HISTORY_SIGS firme (wallet vecchio)
+    pub deployer_age_hours: Option<f64>,
+    pub funded_by: Option<String>,
+    pub funded_by_cex: bool,
+    pub deployer_rugs: i64,
+    pub pool_sol: f64,
+    // None = LP iniziale non nota
+    pub lp_secured_pct: Option<f64>,
+}
+
+impl LaunchReport {
+    /// Verifica il lancio con le soglie dell'utente: Err(motivo) se l'acquisto non è consentito
+    pub fn check(&self, params: &StrategyParams) -> Result<(), String> {
+        if self.pool_sol < params.sniper_min_pool_sol {
+            return Err(format!("pool {:.2} SOL (min {})", self.pool_sol, params.sniper_min_pool_sol));
+        }
+        if self.deployer_rugs > params.sniper_max_deployer_rugs as i64 {
+            return Err(format!("deployer con {} rug precedenti", self.deployer_rugs));
+        }
+        if let Some(age) = self.deployer_age_hours {
+            if age < params.sniper_min_deployer_age_hours {
+                return Err(format!("deployer creato da {:.1}h (min {}h)", age, params.sniper_min_deployer_age_hours));
+            }
+            if params.sniper_block_fresh_cex_wallets && self.funded_by_cex && age < FRESH_CEX_HOURS {
+                return Err("deployer finanziato da un exchange nelle ultime 24h".into());
+            }
+        }
+        if params.sniper_min_lp_secured_pct > 0.0 {
+            match self.lp_secured_pct {
+                Some(p) if p >= params.sniper_min_lp_secured_pct => {},
+                Some(p) => return Err(format!("LP bruciata/bloccata {:.0}% (min {}%)", p, params.sniper_min_lp_secured_pct)),
+                None => return Err("LP non verificabile".into()),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Età (ore) e finanziatore del wallet: dalla TX più vecchia tra le ultime DEPLOYER_HISTORY_SIGS
+async fn deployer_history(network: &Arc<NetworkClient>, deployer: &Pubkey) -> Result<(Option<f64>, Option<String>), Box<dyn std::error::Error + Send + Sync>> {
+    let sigs = network.rpc.get_signatures_for_address(deployer).await?;
+    if sigs.len() >= DEPLOYER_HISTORY_SIGS { return Ok((None, None)); }
+    let oldest = match sigs.last() {
+        Some(s) => s,
+        None => return Ok((Some(0.0), None)),
+    };
+    let age = oldest.block_time.map(|t| (chrono::Utc::now().timestamp() - t).max(0) as f64 / 3600.0);
+
+    // Chi ha pagato la prima TX (tipicamente il trasferimento che ha finanziato il wallet)
+    let config = RpcTransactionConfig {
+        encoding: Some(UiTransactionEncoding::Base64),
+        commitment: Some(CommitmentConfig::confirmed()),
+        max_supported_transaction_version: Some(0),
+    };
+    let funder = match solana_sdk::signature::Signature::from_str(&oldest.signature) {
+        Ok(sig) => network.rpc.get_transaction_with_config(&sig, config).await.ok()
+            .and_then(|tx| tx.transaction.transaction.decode())
+            .and_then(|tx| tx.message.static_account_keys().first().copied())
+            .filter(|payer| payer != deployer)
+            .map(|payer| payer.to_string()),
+        Err(_) => None,
+    };
+    Ok((Some(age.unwrap_or(0.0)), funder))
+}
+
+/// Report del lancio. `deployer_post` = saldi token del deployer dopo l'initialize2 (mint -> unità base), per l'LP iniziale.
+pub async fn check_launch(
+    pool: &db::DbPool,
+    network: &Arc<NetworkClient>,
+    token_mint: &Pubkey,
+    deployer: &Pubkey,
+    deployer_post: &HashMap<String, u64>
+) -> Result<LaunchReport, Box<dyn std::error::Error + Send + Sync>> {
+    let keys = raydium::fetch_pool_keys_by_mint(network, token_mint).await?;
+    let pool_sol = network.rpc.get_token_account_balance(&keys.amm_pc_vault).await?.ui_amount.unwrap_or(0.0);
+
+    // LP ancora nel wallet del deployer rispetto a quella ricevuta all'initialize2
+    let lp_secured_pct = match deployer_post.get(&keys.lp_mint.to_string()) {
+        Some(&initial) if initial > 0 => {
+            let lp_ata = spl_associated_token_account::get_associated_token_address(deployer, &keys.lp_mint);
+            let held = network.rpc.get_token_account_balance(&lp_ata).await.ok().and_then(|b| b.amount.parse::<u64>().ok()).unwrap_or(0);
+            Some((1.0 - held.min(initial) as f64 / initial as f64) * 100.0)
+        },
+        _ => None,
+    };
+
+    let (deployer_age_hours, funded_by) = deployer_history(network, deployer).await?;
+    let deployer_str = deployer.to_string();
+    Ok(LaunchReport {
+        deployer_rugs: db::count_deployer_rugs(pool, &deployer_str).await.unwrap_or(0),
+        deployer: deployer_str,
+        deployer_age_hours,
+        funded_by_cex: funded_by.as_ref().is_some_and(|f| CEX_WALLETS.contains(f)),
+        funded_by,
+        pool_sol,
+        lp_secured_pct,
+    })
+}
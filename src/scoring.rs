@@ -0,0 +1,106 @@
+use std::sync::LazyLock;
+use dashmap::DashMap;
+use serde::Serialize;
+use crate::{birdeye, jupiter};
+
+// --- PUNTEGGIO TOKEN (0-100, somma di componenti) ---
+// 1. Mercato (DexScreener, sempre disponibile): liquidità, rotazione volume/liquidità, momentum 1h
+// 2. Crescita (Birdeye): holder rispetto all'ultima osservazione, variazione dei wallet unici 24h
+// 3. Social (profilo DexScreener): sito, Twitter, Telegram
+// Le componenti senza dato non contano: il totale è riscalato su quelle disponibili.
+
+const LIQUIDITY_MAX: f64 = 25.0;
+const VOLUME_MAX: f64 = 20.0;
+const MOMENTUM_MAX: f64 = 20.0;
+const HOLDER_GROWTH_MAX: f64 = 15.0;
+const WALLET_GROWTH_MAX: f64 = 10.0;
+const SOCIALS_MAX: f64 = 10.0;
+
+// Crescita holder: misurata su almeno 5 minuti, base rinnovata ogni ora
+const HOLDER_MIN_WINDOW_SECS: i64 = 300;
+const HOLDER_BASE_MAX_AGE_SECS: i64 = 3_600;
+const HOLDER_SNAPSHOTS_MAX: usize = 5_000;
+
+// Mint -> (holder, momento dell'osservazione)
+static HOLDER_SNAPSHOTS: LazyLock<DashMap<String, (u64, i64)>> = LazyLock::new(DashMap::new);
+
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct TokenScore {
+    pub total: u8,
+    // Punti per componente (None = dato non disponibile)
+    pub liquidity: f64,
+    pub volume: f64,
+    pub momentum: f64,
+    pub holder_growth: Option<f64>,
+    pub wallet_growth: Option<f64>,
+    pub socials: Option<f64>,
+    // Dati grezzi usati per le componenti di crescita
+    pub holders: Option<u64>,
+    pub holder_growth_pct_per_hour: Option<f64>,
+    pub unique_wallets_24h: Option<u64>,
+    pub unique_wallets_24h_change_pct: Option<f64>,
+    pub token_socials: Option<jupiter::TokenSocials>,
+}
+
+impl TokenScore {
+    fn finalize(mut self) -> Self {
+        let mut points = self.liquidity + self.volume + self.momentum;
+        let mut max = LIQUIDITY_MAX + VOLUME_MAX + MOMENTUM_MAX;
+        for (value, cap) in [(self.holder_growth, HOLDER_GROWTH_MAX), (self.wallet_growth, WALLET_GROWTH_MAX), (self.socials, SOCIALS_MAX)] {
+            if let Some(v) = value { points += v; max += cap; }
+        }
+        self.total = (points / max * 100.0).round().clamp(0.0, 100.0) as u8;
+        self
+    }
+}
+
+/// Punteggio dai soli dati di mercato (nessuna chiamata esterna)
+pub fn calculate_token_score(mkt: &jupiter::TokenMarketData) -> TokenScore {
+    // $1M di liquidità = massimo
+    let liquidity = (mkt.liquidity_usd.max(1.0).log10() / 6.0).clamp(0.0, 1.0) * LIQUIDITY_MAX;
+    // Volume 24h pari a 5 volte la liquidità = massimo
+    let volume = if mkt.liquidity_usd > 0.0 { (mkt.volume_24h / mkt.liquidity_usd / 5.0).clamp(0.0, 1.0) * VOLUME_MAX } else { 0.0 };
+    // -10% in un'ora = 0, +20% = massimo
+    let momentum = ((mkt.change_1h + 10.0) / 30.0).clamp(0.0, 1.0) * MOMENTUM_MAX;
+    TokenScore { liquidity, volume, momentum, ..Default::default() }.finalize()
+}
+
+/// Punteggio completo: mercato + crescita holder/wallet (Birdeye) + social (DexScreener)
+pub async fn analyze_token_potential(mint: &str, mkt: &jupiter::TokenMarketData) -> TokenScore {
+    let (overview, socials) = tokio::join!(birdeye::get_token_overview(mint), jupiter::get_token_socials(mint));
+    let mut score = calculate_token_score(mkt);
+
+    if let Ok(ov) = overview {
+        score.holders = ov.holder;
+        score.holder_growth_pct_per_hour = ov.holder.and_then(|h| holder_growth(mint, h, chrono::Utc::now().timestamp()));
+        // 0%/h = 0, +10%/h = massimo
+        score.holder_growth = score.holder_growth_pct_per_hour.map(|g| (g / 10.0).clamp(0.0, 1.0) * HOLDER_GROWTH_MAX);
+        score.unique_wallets_24h = ov.unique_wallet_24h;
+        score.unique_wallets_24h_change_pct = ov.unique_wallet_24h_change_percent;
+        // -50% = 0, +100% = massimo
+        score.wallet_growth = ov.unique_wallet_24h_change_percent.map(|c| ((c + 50.0) / 150.0).clamp(0.0, 1.0) * WALLET_GROWTH_MAX);
+    }
+    if let Ok(s) = socials {
+        let points = if s.websites.is_empty() { 0.0 } else { 4.0 }
+            + if s.twitter.is_some() { 3.0 } else { 0.0 }
+            + if s.telegram.is_some() { 3.0 } else { 0.0 };
+        score.socials = Some(points / 10.0 * SOCIALS_MAX);
+        score.token_socials = Some(s);
+    }
+    score.finalize()
+}
+
+/// Crescita % oraria degli holder rispetto all'osservazione precedente (None alla prima o se troppo recente)
+fn holder_growth(mint: &str, holders: u64, now: i64) -> Option<f64> {
+    let prev = HOLDER_SNAPSHOTS.get(mint).map(|s| *s);
+    let renew = prev.is_none_or(|(_, at)| now - at >= HOLDER_BASE_MAX_AGE_SECS);
+    if renew {
+        if HOLDER_SNAPSHOTS.len() >= HOLDER_SNAPSHOTS_MAX { HOLDER_SNAPSHOTS.clear(); }
+        HOLDER_SNAPSHOTS.insert(mint.to_string(), (holders, now));
+    }
+
+    let (base, at) = prev?;
+    let elapsed = now - at;
+    if base == 0 || elapsed < HOLDER_MIN_WINDOW_SECS { return None; }
+    Some((holders as f64 - base as f64) / base as f64 * 100.0 * 3_600.0 / elapsed as f64)
+}
@@ -0,0 +1,78 @@
+use dashmap::DashMap;
+use dashmap::mapref::entry::Entry;
+
+// --- CACHE FIRME PROCESSATE (Sniper e Copy-Trading) ---
+// 1. Filtro veloce in RAM davanti alla tabella processed_signatures, che resta la fonte di verità tra i riavvii
+// 2. Ogni firma è associata alla finestra di BUCKET_SECS in cui è stata vista per la prima volta
+// 3. Oltre il limite si scartano le finestre più vecchie, una alla volta: le firme recenti restano sempre
+//    (prima la cache si svuotava tutta a 10k elementi e le firme appena viste potevano ripassare)
+
+const BUCKET_SECS: i64 = 60;
+const MAX_ENTRIES: usize = 10_000;
+
+pub struct SignatureCache {
+    // Firma -> finestra (unix / BUCKET_SECS)
+    seen: DashMap<String, i64>,
+    max_entries: usize,
+}
+
+impl Default for SignatureCache {
+    fn default() -> Self {
+        Self::with_capacity(MAX_ENTRIES)
+    }
+}
+
+impl SignatureCache {
+    pub fn with_capacity(max_entries: usize) -> Self {
+        Self { seen: DashMap::new(), max_entries }
+    }
+
+    /// true se la firma non era in cache (da ora c'è)
+    pub fn insert(&self, sig: &str, now: i64) -> bool {
+        // Il riferimento dell'entry va rilasciato prima dello sfoltimento (stessa shard)
+        let inserted = match self.seen.entry(sig.to_string()) {
+            Entry::Occupied(_) => false,
+            Entry::Vacant(v) => { v.insert(now / BUCKET_SECS); true },
+        };
+        if inserted && self.seen.len() > self.max_entries { self.evict_oldest(); }
+        inserted
+    }
+
+    pub fn contains(&self, sig: &str) -> bool {
+        self.seen.contains_key(sig)
+    }
+
+    // Scarta le finestre più vecchie finché si torna sotto il limite (la più recente non si tocca)
+    fn evict_oldest(&self) {
+        while self.seen.len() > self.max_entries {
+            let (oldest, newest) = self.seen.iter().fold((i64::MAX, i64::MIN), |(lo, hi), e| (lo.min(*e.value()), hi.max(*e.value())));
+            if oldest >= newest { return; }
+            self.seen.retain(|_, bucket| *bucket > oldest);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn duplicates_are_rejected() {
+        let cache = SignatureCache::default();
+        assert!(cache.insert("sig1", 1_000));
+        assert!(!cache.insert("sig1", 5_000));
+        assert!(cache.insert("sig2", 5_000));
+    }
+
+    #[test]
+    fn rollover_drops_only_the_oldest_window() {
+        let cache = SignatureCache::with_capacity(4);
+        for (i, sig) in ["a", "b", "c"].iter().enumerate() { assert!(cache.insert(sig, i as i64)); }
+        assert!(cache.insert("d", BUCKET_SECS));
+        assert!(cache.insert("e", BUCKET_SECS * 2));
+        // Oltre il limite sparisce solo la prima finestra (a, b, c): le firme recenti restano
+        assert!(!cache.contains("a") && !cache.contains("c"));
+        assert!(!cache.insert("d", BUCKET_SECS * 2));
+        assert!(!cache.insert("e", BUCKET_SECS * 2));
+    }
+}
@@ -0,0 +1,117 @@
+use std::sync::Arc;
+use log::{info, warn};
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup};
+use tokio::time::Duration;
+use crate::{db, i18n, jupiter, network, telegram_bot, AppState};
+
+// --- SIGNALS-ONLY (Segnali senza auto-trading) ---
+// 1. Con signals_only l'ingresso trovato dalla strategia NON viene eseguito: diventa un trade
+//    in attesa (tabella pending_trades) con la quote del momento, e l'utente riceve un alert
+//    Telegram (token, strategia, score, importo suggerito, grafico)
+// 2. "Approva" (Telegram o POST /pending/{id}/approve) entro PENDING_TTL_MINUTES lo esegue con il
+//    percorso manuale (router + limiti d'investimento); senza risposta il trade scade
+// 3. Quote bloccata: se all'approvazione i token in uscita sono peggiorati oltre lo slippage si rifiuta
+// 4. Un solo trade in attesa per utente e token: i segnali ripetuti non mandano altri alert
+
+pub const PENDING_TTL_MINUTES: i64 = 5;
+const SOL_MINT: &str = "So11111111111111111111111111111111111111112";
+const CHART_URL: &str = "https://dexscreener.com/solana/";
+
+/// Segnale d'ingresso passato all'Auto-Buy (Market Strategy, Sniper, Copy-Trade)
+#[derive(Debug, Clone)]
+pub struct EntrySignal {
+    pub symbol: String,
+    pub source: &'static str,
+    pub score: u8,
+    // Quota del saldo da investire (Copy-Trading), None = dimensionamento standard
+    pub balance_fraction: Option<f64>,
+}
+
+/// Registra il trade suggerito con la quote attuale e manda l'alert.
+/// false se l'utente ne ha già uno in attesa sullo stesso token.
+pub async fn suggest(pool: &db::DbPool, mut trade: db::PendingTrade) -> Result<bool, sqlx::Error> {
+    // Senza quote Jupiter (es. pool appena nata, solo Raydium) il prezzo non si blocca
+    trade.quoted_out = jupiter::get_quote(SOL_MINT, &trade.token, trade.amount_lamports, trade.slippage_bps).await
+        .map(|q| jupiter::quote_out_amount(&q))
+        .unwrap_or(0);
+    let id = match db::create_pending_trade(pool, &trade).await? {
+        Some(id) => id,
+        None => return Ok(false),
+    };
+
+    let amount_sol = trade.amount_lamports as f64 / 1_000_000_000.0;
+    info!("📡 SEGNALE #{} {} -> {} ({} {:.4} SOL)", id, trade.token, trade.user_id, trade.engine, amount_sol);
+    let lang = i18n::user_lang(pool, &trade.user_id).await;
+    let text = i18n::tr(lang, "notify.signal", &[
+        &trade.symbol, &trade.token, &trade.engine, &trade.source,
+        &trade.score, &format!("{:.4}", amount_sol), &PENDING_TTL_MINUTES,
+    ]);
+
+    let mut rows = vec![vec![
+        InlineKeyboardButton::callback(i18n::tr(lang, "tg.signal_accept", &[&format!("{:.4}", amount_sol)]), format!("pt_approve:{}", id)),
+        InlineKeyboardButton::callback(i18n::t(lang, "tg.signal_ignore"), format!("pt_reject:{}", id)),
+    ]];
+    if let Ok(url) = format!("{}{}", CHART_URL, trade.token).parse() {
+        rows.push(vec![InlineKeyboardButton::url(i18n::t(lang, "tg.signal_chart"), url)]);
+    }
+    telegram_bot::notify_user_with_buttons(&trade.user_id, text, InlineKeyboardMarkup::new(rows)).await;
+    Ok(true)
+}
+
+/// Esegue un trade approvato: (firma, trade) oppure messaggio di errore
+pub async fn approve(pool: &db::DbPool, net: &Arc<network::NetworkClient>, user_id: &str, id: i64) -> Result<(String, db::PendingTrade), String> {
+    let lang = i18n::user_lang(pool, user_id).await;
+    let trade = db::claim_pending_trade(pool, user_id, id, PENDING_TTL_MINUTES).await
+        .map_err(|e| i18n::tr(lang, "tg.db_error", &[&e]))?
+        .ok_or_else(|| i18n::t(lang, "tg.signal_expired").to_string())?;
+
+    // Quote bloccata: stessa tolleranza dello swap
+    if trade.quoted_out > 0 {
+        let now_out = jupiter::get_quote(SOL_MINT, &trade.token, trade.amount_lamports, trade.slippage_bps).await
+            .map(|q| jupiter::quote_out_amount(&q))
+            .unwrap_or(0);
+        if now_out < crate::swap_router::min_out_with_slippage(trade.quoted_out, trade.slippage_bps) {
+            db::finish_pending_trade(pool, id, None).await;
+            warn!("📡 Trade #{} ({}) rifiutato: quote {} < bloccata {}", id, user_id, now_out, trade.quoted_out);
+            return Err(i18n::t(lang, "tg.signal_quote_moved").to_string());
+        }
+    }
+
+    match crate::trading::execute_buy(pool, net, user_id, &trade.token, trade.amount_lamports, trade.slippage_bps).await {
+        Ok((sig, _)) => {
+            db::finish_pending_trade(pool, id, Some(&sig)).await;
+            info!("📡 Trade #{} ({}) approvato -> TX: {}", id, user_id, sig);
+            Ok((sig, trade))
+        },
+        Err(e) => {
+            db::finish_pending_trade(pool, id, None).await;
+            Err(i18n::tr(lang, "tg.signal_swap_failed", &[&e]))
+        }
+    }
+}
+
+/// Rifiuto dell'utente: messaggio per l'utente oppure errore
+pub async fn reject(pool: &db::DbPool, user_id: &str, id: i64) -> Result<String, String> {
+    let lang = i18n::user_lang(pool, user_id).await;
+    match db::reject_pending_trade(pool, user_id, id).await {
+        Ok(true) => Ok(i18n::tr(lang, "tg.signal_rejected", &[&id])),
+        Ok(false) => Err(i18n::t(lang, "tg.signal_expired").to_string()),
+        Err(e) => Err(i18n::tr(lang, "tg.db_error", &[&e])),
+    }
+}
+
+/// Fa scadere i trade non approvati e avvisa l'utente
+pub async fn run_pending_expiry(pool: db::DbPool, state: Arc<AppState>) {
+    loop {
+        match db::expire_pending_trades(&pool, PENDING_TTL_MINUTES).await {
+            Ok(expired) => for (id, user_id, symbol) in expired {
+                info!("⌛ Trade suggerito #{} ({}) scaduto", id, user_id);
+                let lang = i18n::user_lang(&pool, &user_id).await;
+                telegram_bot::notify_user(&user_id, i18n::tr(lang, "notify.signal_expired", &[&id, &symbol])).await;
+            },
+            Err(e) => warn!("⚠️ Scadenza trade suggeriti: {}", e),
+        }
+        if state.sleep_or_shutdown(Duration::from_secs(60)).await { break; }
+    }
+    info!("🛑 Scadenza Trade Suggeriti fermata.");
+}
@@ -0,0 +1,150 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use log::{info, warn};
+use serde::Serialize;
+use solana_sdk::pubkey::Pubkey;
+use tokio::time::Duration;
+use crate::{db, jupiter, metadata, network, safety, AppState};
+
+// --- SNIPER DRY-RUN (Scoreboard prima dello sniping reale) ---
+// 1. Con SNIPER_DRY_RUN=1 lo Sniper non compra: ogni lancio rilevato (anche scartato dalla safety) viene registrato
+//    in sniper_hits con report di sicurezza e dati di mercato al momento della scoperta
+// 2. run_followup completa il prezzo a +5m, +30m e +2h (0 = nessun prezzo: token morto o senza liquidità = -100%)
+// 3. GET /sniper/stats (admin): PnL ipotetico per orizzonte, lanci passati vs scartati dai filtri e motivi di scarto
+
+// (colonna, etichetta, secondi dalla scoperta)
+const HORIZONS: [(&str, &str, i64); 3] = [("price_5m", "5m", 300), ("price_30m", "30m", 1_800), ("price_2h", "2h", 7_200)];
+const FOLLOWUP_SECS: u64 = 60;
+// Ultimi lanci restituiti dallo scoreboard
+const RECENT_HITS: usize = 50;
+
+pub fn dry_run() -> bool {
+    std::env::var("SNIPER_DRY_RUN").map(|v| v == "1").unwrap_or(false)
+}
+
+/// Registra un lancio rilevato (nessun acquisto)
+pub async fn record_hit(pool: &db::DbPool, net: &Arc<network::NetworkClient>, mint: &Pubkey, deployer: &Pubkey, report: &safety::TokenSafetyReport) {
+    let token = mint.to_string();
+    // Stessa attesa del percorso reale: DexScreener indicizza la pool dopo qualche secondo
+    tokio::time::sleep(Duration::from_secs(2)).await;
+    let market = jupiter::get_token_market_data(&token).await.ok();
+    let hit = db::SniperHit {
+        id: 0,
+        symbol: metadata::symbol(pool, net, &token).await,
+        token,
+        deployer: deployer.to_string(),
+        detected_at: chrono::Utc::now().timestamp(),
+        is_safe: report.is_safe,
+        reason: report.reason.clone(),
+        safety_report: serde_json::json!(report),
+        price: market.as_ref().map(|m| m.price).unwrap_or(0.0),
+        liquidity_usd: market.as_ref().map(|m| m.liquidity_usd).unwrap_or(0.0),
+        market_cap: market.as_ref().map(|m| m.market_cap).unwrap_or(0.0),
+        volume_24h: market.as_ref().map(|m| m.volume_24h).unwrap_or(0.0),
+        price_5m: None,
+        price_30m: None,
+        price_2h: None,
+    };
+    match db::record_sniper_hit(pool, &hit).await {
+        Ok(id) => info!("🧪 DRY-RUN Sniper #{}: {} ({}) safe={} ${:.8}", id, hit.symbol, hit.token, hit.is_safe, hit.price),
+        Err(e) => warn!("⚠️ Lancio {} non registrato: {}", hit.token, e),
+    }
+}
+
+/// Completa i prezzi dei lanci registrati a +5m/+30m/+2h
+pub async fn run_followup(pool: db::DbPool, state: Arc<AppState>) {
+    loop {
+        let now = chrono::Utc::now().timestamp();
+        for (column, _, secs) in HORIZONS {
+            for (id, token) in db::get_sniper_hits_due(&pool, column, now - secs).await.unwrap_or_default() {
+                let price = jupiter::get_token_market_data(&token).await.map(|m| m.price).unwrap_or(0.0);
+                if let Err(e) = db::set_sniper_hit_price(&pool, id, column, price).await {
+                    warn!("⚠️ Prezzo {} del lancio #{} non salvato: {}", column, id, e);
+                }
+            }
+        }
+        if state.sleep_or_shutdown(Duration::from_secs(FOLLOWUP_SECS)).await { break; }
+    }
+    info!("🛑 Follow-up Sniper fermato.");
+}
+
+/// Rendimenti ipotetici di un gruppo di lanci a un orizzonte
+#[derive(Serialize, Default)]
+pub struct Bucket {
+    pub samples: usize,
+    pub win_rate_pct: f64,
+    pub avg_return_pct: f64,
+    pub median_return_pct: f64,
+    pub best_return_pct: f64,
+    pub worst_return_pct: f64,
+    // PnL ipotetico comprando 1 SOL per lancio
+    pub pnl_per_sol: f64,
+}
+
+impl Bucket {
+    fn from_returns(mut returns: Vec<f64>) -> Self {
+        if returns.is_empty() { return Self::default(); }
+        returns.sort_by(|a, b| a.total_cmp(b));
+        let n = returns.len();
+        let sum: f64 = returns.iter().sum();
+        Self {
+            samples: n,
+            win_rate_pct: returns.iter().filter(|r| **r > 0.0).count() as f64 / n as f64 * 100.0,
+            avg_return_pct: sum / n as f64,
+            median_return_pct: if n % 2 == 1 { returns[n / 2] } else { (returns[n / 2 - 1] + returns[n / 2]) / 2.0 },
+            best_return_pct: returns[n - 1],
+            worst_return_pct: returns[0],
+            pnl_per_sol: sum / 100.0,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct HorizonStats {
+    pub horizon: &'static str,
+    // Lanci che avrebbero passato la safety (quelli che lo Sniper reale valuterebbe)
+    pub safe: Bucket,
+    // Lanci scartati: se rendono meglio dei safe i filtri sono troppo stretti
+    pub rejected: Bucket,
+}
+
+#[derive(Serialize)]
+pub struct Scoreboard {
+    pub dry_run: bool,
+    pub hits: usize,
+    pub safe_hits: usize,
+    // Senza prezzo alla scoperta: fuori dai rendimenti
+    pub unpriced_hits: usize,
+    pub horizons: Vec<HorizonStats>,
+    // Motivi di scarto della safety con il numero di lanci
+    pub rejection_reasons: BTreeMap<String, usize>,
+    pub recent: Vec<db::SniperHit>,
+}
+
+/// Scoreboard dei lanci registrati (più recenti prima)
+pub fn scoreboard(mut hits: Vec<db::SniperHit>) -> Scoreboard {
+    let priced: Vec<&db::SniperHit> = hits.iter().filter(|h| h.price > 0.0).collect();
+    let horizons = HORIZONS.iter().map(|(_, label, _)| {
+        let returns = |safe: bool| priced.iter()
+            .filter(|h| h.is_safe == safe)
+            .filter_map(|h| horizon_price(h, label).map(|p| (p / h.price - 1.0) * 100.0))
+            .collect::<Vec<_>>();
+        HorizonStats { horizon: label, safe: Bucket::from_returns(returns(true)), rejected: Bucket::from_returns(returns(false)) }
+    }).collect();
+
+    let mut rejection_reasons = BTreeMap::new();
+    for h in hits.iter().filter(|h| !h.is_safe) {
+        *rejection_reasons.entry(h.reason.clone()).or_insert(0) += 1;
+    }
+    let (total, safe_hits, unpriced_hits) = (hits.len(), hits.iter().filter(|h| h.is_safe).count(), hits.len() - priced.len());
+    hits.truncate(RECENT_HITS);
+    Scoreboard { dry_run: dry_run(), hits: total, safe_hits, unpriced_hits, horizons, rejection_reasons, recent: hits }
+}
+
+fn horizon_price(hit: &db::SniperHit, label: &str) -> Option<f64> {
+    match label {
+        "5m" => hit.price_5m,
+        "30m" => hit.price_30m,
+        _ => hit.price_2h,
+    }
+}
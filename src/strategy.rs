@@ -1,26 +1,217 @@
-use log::{info, debug};
-use std::collections::VecDeque;
-use serde::Deserialize;
+use std::collections::{BTreeMap, VecDeque};
+use serde::{Serialize, Deserialize};
+use crate::indicators;
+pub use crate::indicators::Candle;
 
 // --- CONFIGURAZIONE INDICATORI ---
 const RSI_PERIOD: usize = 14;
 const BOLLINGER_PERIOD: usize = 20;
 const BOLLINGER_MULT: f64 = 2.0;
-const ATR_PERIOD: usize = 14;
 const VOLUME_MA_PERIOD: usize = 10; // Media mobile del volume
+const ATR_PERIOD: usize = 14;
+const EMA_FAST: usize = 20;
+const EMA_SLOW: usize = 50;
+const WHALE_PRESSURE_CONFIRM: f64 = 0.5; // Acquisti whale netti: valgono come conferma del volume
+const WHALE_PRESSURE_BLOCK: f64 = -0.5; // Vendite whale nette: niente ingressi
 
-// Struttura Candela Completa
-#[derive(Clone, Copy, Debug)]
-pub struct Candle {
-    pub high: f64,
-    pub low: f64,
-    pub close: f64,
-    pub volume: f64, // Aggiunto Volume
+// --- PARAMETRI STRATEGIA (Personalizzabili per utente, salvati in users.settings) ---
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct StrategyParams {
+    pub rsi_oversold: f64,       // Sotto questa soglia il prezzo è "a sconto"
+    pub rsi_overbought: f64,     // Sopra questa soglia si vende
+    pub volume_spike_mult: f64,  // Volume attuale vs media per confermare l'ingresso
+    pub min_liquidity_usd: f64,  // Filtro Anti-Rumore
+    pub min_volume_24h: f64,
+    #[serde(alias = "max_trade_sol")]
+    pub max_per_trade_sol: f64,  // Tetto massimo per singolo acquisto (auto, sniper, manuale, DCA)
+    pub max_total_exposure_sol: f64, // SOL massimi investiti nei trade aperti (0 = nessun limite)
+    pub reserve_sol: f64,        // SOL che restano sempre nel wallet (fee, rent): mai investiti
+    pub slippage_bps: u16,
+    pub trailing_stop_pct: f64,  // Smart Stop standard (% dal massimo)
+    pub tight_stop_pct: f64,     // Smart Stop stretto (forte ritracciamento dal massimo)
+    pub max_round_trip_loss_pct: f64, // Anti-Honeypot: perdita massima accettata su SOL -> Token -> SOL
+    pub max_open_positions: usize, // Auto-Buy sospeso oltre questo numero di trade OPEN
+    pub max_daily_loss_pct: f64, // Circuit Breaker: perdita realizzata max del giorno (% saldo iniziale)
+    pub buy_cooldown_secs: u64,  // Auto-Buy: attesa prima di ricomprare lo stesso token (0 = nessun cooldown)
+    pub max_trades_per_hour: u32, // Auto-Buy: acquisti massimi nell'ultima ora su tutti i token (0 = nessun limite)
+    pub signals_only: bool,      // Solo segnali: gli ingressi arrivano come alert Telegram da confermare, nessun Auto-Buy
+    pub engine: String,          // Strategia di ingresso/uscita (smart, momentum, mean_reversion)
+    pub sizing: String,          // Dimensione dei trade: tiered (a scaglioni sul saldo) | kelly (dallo storico della strategia)
+    pub kelly_fraction: f64,     // Quota della puntata di Kelly usata (0.25 = Kelly frazionario a 1/4)
+    pub max_hold_hours: BTreeMap<String, f64>, // Durata massima per strategia d'ingresso (ore, 0 = nessun limite)
+    pub exit_mode: String,       // Distanza del trailing: percent (trailing/tight_stop_pct) | atr (atr_stop_mult x ATR) | hybrid (ATR entro tight..trailing)
+    pub atr_stop_mult: f64,      // Multipli di ATR tra il massimo e lo stop (exit_mode atr/hybrid)
+    pub take_profit_pct: f64,    // Take profit fisso sul PnL (0 = disattivato, il TP manuale della posizione ha la precedenza)
+    pub break_even_after_pct: f64, // Oltre questo guadagno lo stop sale a break-even (0 = disattivato)
+    pub rug_liquidity_drop_pct: f64, // Rug Detector: calo di liquidità della pool che fa vendere subito (0 = disattivato)
+    pub rug_window_mins: u64,    // Finestra (minuti) in cui si misura il calo di liquidità
+    pub sniper_min_pool_sol: f64, // Sniper: SOL minimi nella pool al lancio
+    pub sniper_min_deployer_age_hours: f64, // Sniper: età minima del wallet deployer (0 = nessun limite)
+    pub sniper_block_fresh_cex_wallets: bool, // Sniper: scarta i deployer finanziati da un exchange nelle ultime 24h
+    pub sniper_max_deployer_rugs: u32, // Sniper: rug precedenti tollerati per il deployer
+    pub sniper_min_lp_secured_pct: f64, // Sniper: quota minima di LP bruciata o bloccata (0 = nessun controllo)
+    pub universe: Vec<String>,   // Token su cui entra l'Auto-Bot: watchlist (curata), gems (discovery), sniper (nuovi lanci)
+}
+
+const EXIT_MODES: [&str; 3] = ["percent", "atr", "hybrid"];
+// Universi di token dell'Auto-Bot: watchlist curata (TOP), gemme dalle watchlist utenti (DISCOVERY), lanci Raydium (SNIPER)
+pub const UNIVERSES: [&str; 3] = ["watchlist", "gems", "sniper"];
+// Lo stop ATR non può stare più lontano di così dal massimo
+const MAX_ATR_STOP_PCT: f64 = 90.0;
+
+// Origini dei trade oltre alle strategie (trades.strategy)
+const TRADE_SOURCES: [&str; 2] = ["copy", "manual"];
+// Oltre 30 giorni non è più un limite
+const MAX_HOLD_HOURS_CAP: f64 = 720.0;
+// Cooldown massimo per token: una settimana
+const MAX_BUY_COOLDOWN_SECS: u64 = 7 * 24 * 3600;
+
+impl Default for StrategyParams {
+    fn default() -> Self {
+        Self {
+            rsi_oversold: 40.0,
+            rsi_overbought: 75.0,
+            volume_spike_mult: 2.0,
+            min_liquidity_usd: 10_000.0,
+            min_volume_24h: 50_000.0,
+            max_per_trade_sol: 0.5,
+            max_total_exposure_sol: 0.0,
+            reserve_sol: 0.05,
+            slippage_bps: 100,
+            trailing_stop_pct: 10.0,
+            tight_stop_pct: 3.0,
+            max_round_trip_loss_pct: 15.0,
+            max_open_positions: 5,
+            max_daily_loss_pct: 10.0,
+            buy_cooldown_secs: 600,
+            max_trades_per_hour: 0,
+            signals_only: false,
+            engine: "smart".to_string(),
+            sizing: "tiered".to_string(),
+            kelly_fraction: 0.25,
+            // Breakout: se non parte subito non parte più. Dip: il rimbalzo può richiedere mezza giornata.
+            max_hold_hours: BTreeMap::from([
+                ("momentum".to_string(), 2.0),
+                ("smart".to_string(), 12.0),
+                ("mean_reversion".to_string(), 12.0),
+            ]),
+            exit_mode: "percent".to_string(),
+            atr_stop_mult: 2.5,
+            take_profit_pct: 0.0,
+            break_even_after_pct: 0.0,
+            rug_liquidity_drop_pct: 40.0,
+            rug_window_mins: 5,
+            sniper_min_pool_sol: 5.0,
+            sniper_min_deployer_age_hours: 0.0,
+            sniper_block_fresh_cex_wallets: true,
+            sniper_max_deployer_rugs: 0,
+            sniper_min_lp_secured_pct: 0.0,
+            universe: UNIVERSES.iter().map(|u| u.to_string()).collect(),
+        }
+    }
+}
+
+impl StrategyParams {
+    /// Controlla che i valori siano sensati prima di salvarli
+    pub fn validate(&self) -> Result<(), String> {
+        if !(0.0..100.0).contains(&self.rsi_oversold) || !(0.0..=100.0).contains(&self.rsi_overbought) || self.rsi_oversold >= self.rsi_overbought {
+            return Err("RSI non valido (0 < oversold < overbought <= 100)".into());
+        }
+        if self.volume_spike_mult < 1.0 { return Err("volume_spike_mult deve essere >= 1".into()); }
+        if self.min_liquidity_usd < 0.0 || self.min_volume_24h < 0.0 { return Err("Filtri liquidità/volume negativi".into()); }
+        if self.max_per_trade_sol <= 0.0 || self.max_per_trade_sol > 50.0 { return Err("max_per_trade_sol deve essere tra 0 e 50 SOL".into()); }
+        if self.max_total_exposure_sol < 0.0 || (self.max_total_exposure_sol > 0.0 && self.max_total_exposure_sol < self.max_per_trade_sol) {
+            return Err("max_total_exposure_sol deve essere 0 (nessun limite) oppure >= max_per_trade_sol".into());
+        }
+        if !(0.01..=100.0).contains(&self.reserve_sol) { return Err("reserve_sol deve essere tra 0.01 e 100 SOL".into()); }
+        if self.slippage_bps == 0 || self.slippage_bps > 5_000 { return Err("slippage_bps deve essere tra 1 e 5000".into()); }
+        if self.tight_stop_pct <= 0.0 || self.trailing_stop_pct <= 0.0 || self.trailing_stop_pct > 90.0 || self.tight_stop_pct > self.trailing_stop_pct {
+            return Err("Stop non validi (0 < tight_stop_pct <= trailing_stop_pct <= 90)".into());
+        }
+        if self.max_round_trip_loss_pct <= 0.0 || self.max_round_trip_loss_pct > 100.0 {
+            return Err("max_round_trip_loss_pct deve essere tra 0 e 100".into());
+        }
+        if self.max_open_positions == 0 || self.max_open_positions > 50 {
+            return Err("max_open_positions deve essere tra 1 e 50".into());
+        }
+        if self.max_daily_loss_pct <= 0.0 || self.max_daily_loss_pct > 100.0 {
+            return Err("max_daily_loss_pct deve essere tra 0 e 100".into());
+        }
+        if self.buy_cooldown_secs > MAX_BUY_COOLDOWN_SECS {
+            return Err(format!("buy_cooldown_secs deve essere tra 0 e {}", MAX_BUY_COOLDOWN_SECS));
+        }
+        if self.max_trades_per_hour > 1_000 { return Err("max_trades_per_hour deve essere tra 0 e 1000".into()); }
+        if by_name(&self.engine).is_none() {
+            return Err(format!("engine sconosciuto (disponibili: {})", names().join(", ")));
+        }
+        if self.sizing != "tiered" && self.sizing != "kelly" { return Err("sizing deve essere tiered o kelly".into()); }
+        if self.kelly_fraction <= 0.0 || self.kelly_fraction > 1.0 { return Err("kelly_fraction deve essere tra 0 e 1".into()); }
+        if !EXIT_MODES.contains(&self.exit_mode.as_str()) {
+            return Err(format!("exit_mode deve essere uno tra: {}", EXIT_MODES.join(", ")));
+        }
+        if self.atr_stop_mult <= 0.0 || self.atr_stop_mult > 20.0 { return Err("atr_stop_mult deve essere tra 0 e 20".into()); }
+        if !(0.0..=10_000.0).contains(&self.take_profit_pct) { return Err("take_profit_pct deve essere tra 0 e 10000".into()); }
+        if !(0.0..=1_000.0).contains(&self.break_even_after_pct) { return Err("break_even_after_pct deve essere tra 0 e 1000".into()); }
+        if !(0.0..=100.0).contains(&self.rug_liquidity_drop_pct) { return Err("rug_liquidity_drop_pct deve essere tra 0 e 100".into()); }
+        if self.rug_window_mins == 0 || self.rug_window_mins > 60 { return Err("rug_window_mins deve essere tra 1 e 60".into()); }
+        if self.sniper_min_pool_sol < 0.0 || self.sniper_min_deployer_age_hours < 0.0 {
+            return Err("Soglie sniper negative".into());
+        }
+        if !(0.0..=100.0).contains(&self.sniper_min_lp_secured_pct) { return Err("sniper_min_lp_secured_pct deve essere tra 0 e 100".into()); }
+        if self.universe.is_empty() || self.universe.iter().any(|u| !UNIVERSES.contains(&u.as_str())) {
+            return Err(format!("universe deve contenere almeno uno tra: {}", UNIVERSES.join(", ")));
+        }
+        for (mode, hours) in &self.max_hold_hours {
+            if by_name(mode).is_none() && !TRADE_SOURCES.contains(&mode.as_str()) {
+                return Err(format!("max_hold_hours: strategia sconosciuta {} (disponibili: {}, {})", mode, names().join(", "), TRADE_SOURCES.join(", ")));
+            }
+            if !hours.is_finite() || *hours < 0.0 || *hours > MAX_HOLD_HOURS_CAP {
+                return Err(format!("max_hold_hours.{} deve essere tra 0 e {}", mode, MAX_HOLD_HOURS_CAP));
+            }
+        }
+        Ok(())
+    }
+
+    /// Durata massima (secondi) delle posizioni aperte dalla strategia. None = nessun limite.
+    pub fn max_hold_secs(&self, strategy: &str) -> Option<i64> {
+        self.max_hold_hours.get(strategy).filter(|h| **h > 0.0).map(|h| (h * 3600.0) as i64)
+    }
+
+    /// L'Auto-Bot può entrare sui token di questo universo (watchlist, gems, sniper)
+    pub fn trades_universe(&self, universe: &str) -> bool {
+        self.universe.iter().any(|u| u == universe)
+    }
+
+    /// Applica modifiche parziali (es. {"rsi_oversold": 35}) e valida il risultato.
+    /// Le mappe si modificano una voce alla volta con la chiave puntata (es. {"max_hold_hours.momentum": 4}).
+    pub fn with_overrides(&self, patch: &serde_json::Value) -> Result<Self, String> {
+        let patch = patch.as_object().ok_or("Formato parametri non valido")?;
+        let mut merged = serde_json::json!(self);
+        for (key, value) in patch {
+            match key.split_once('.') {
+                Some((map, entry)) => match merged.get_mut(map).and_then(|m| m.as_object_mut()) {
+                    Some(m) => { m.insert(entry.to_string(), value.clone()); },
+                    None => return Err(format!("Parametro sconosciuto: {}", key)),
+                },
+                None => {
+                    if merged.get(key).is_none() { return Err(format!("Parametro sconosciuto: {}", key)); }
+                    merged[key] = value.clone();
+                }
+            }
+        }
+        let params: StrategyParams = serde_json::from_value(merged).map_err(|e| format!("Valore non valido: {}", e))?;
+        params.validate()?;
+        Ok(params)
+    }
 }
 
+#[derive(Clone)]
 pub struct MarketData {
     pub candles: VecDeque<Candle>,
     pub symbol: String,
+    // Flusso netto whale recente (-1 solo vendite .. +1 solo acquisti), 0 se non noto
+    pub whale_pressure: f64,
     
     // Buffer Tick
     current_high: f64,
@@ -28,6 +219,8 @@ pub struct MarketData {
     current_vol: f64, // Accumulatore volume tick
     last_price: f64,
     tick_count: u32,
+    // Le candele vengono da OHLCV reale (replace_candles): da qui in poi niente tick
+    real_candles: bool,
 }
 
 impl MarketData {
@@ -35,11 +228,13 @@ impl MarketData {
         Self { 
             candles: VecDeque::new(), 
             symbol: symbol.to_string(), 
+            whale_pressure: 0.0,
             current_high: 0.0,
             current_low: f64::MAX,
             current_vol: 0.0,
             last_price: 0.0,
             tick_count: 0,
+            real_candles: false,
         }
     }
 
@@ -73,6 +268,33 @@ impl MarketData {
             self.current_vol = 0.0;
         }
     }
+
+    // Sostituisce lo storico con candele vere (OHLCV live): il buffer tick riparte da zero
+    pub fn replace_candles(&mut self, candles: impl IntoIterator<Item = Candle>) {
+        self.candles = candles.into_iter().collect();
+        while self.candles.len() > 100 { self.candles.pop_front(); }
+        self.real_candles = true;
+        self.tick_count = 0;
+        self.current_high = 0.0;
+        self.current_low = f64::MAX;
+        self.current_vol = 0.0;
+    }
+
+    // Un ciclo live: barre OHLCV se arrivate, altrimenti tick (prezzo + volume 24h).
+    // Con barre reali già caricate un errore OHLCV salta il ciclo (false): volumi 24h e barre 1m non si mescolano
+    pub fn refresh(&mut self, ohlcv: Option<Vec<Candle>>, price: f64, volume_24h: f64) -> bool {
+        match ohlcv {
+            Some(bars) if !bars.is_empty() => { self.replace_candles(bars); true },
+            _ if self.real_candles => false,
+            _ => { self.add_tick(price, volume_24h); true },
+        }
+    }
+
+    // Aggiunge una candela già chiusa (Dati storici OHLCV / Backtest)
+    pub fn add_candle(&mut self, candle: Candle) {
+        self.candles.push_back(candle);
+        if self.candles.len() > 100 { self.candles.pop_front(); }
+    }
 }
 
 #[derive(Debug, PartialEq)]
@@ -83,41 +305,31 @@ pub enum TradeAction {
     UpdateHigh(u64)
 }
 
-// --- 1. MATEMATICA FINANZIARIA ---
+// --- 1. MATEMATICA FINANZIARIA (Funzioni pure e testate in indicators.rs) ---
 
-fn calculate_rsi(candles: &VecDeque<Candle>) -> Option<f64> {
-    if candles.len() < RSI_PERIOD + 1 { return None; }
-    let mut gains = 0.0; let mut losses = 0.0;
-    for i in (candles.len() - RSI_PERIOD)..candles.len() {
-        let diff = candles[i].close - candles[i - 1].close;
-        if diff >= 0.0 { gains += diff; } else { losses += diff.abs(); }
-    }
-    if losses == 0.0 { return Some(100.0); }
-    let rs = (gains / RSI_PERIOD as f64) / (losses / RSI_PERIOD as f64);
-    Some(100.0 - (100.0 / (1.0 + rs)))
+fn closes(candles: &VecDeque<Candle>) -> Vec<f64> {
+    candles.iter().map(|c| c.close).collect()
 }
 
-fn calculate_bollinger(candles: &VecDeque<Candle>) -> Option<(f64, f64)> { 
-    if candles.len() < BOLLINGER_PERIOD { return None; }
-    let sum: f64 = candles.iter().rev().take(BOLLINGER_PERIOD).map(|c| c.close).sum();
-    let ma = sum / BOLLINGER_PERIOD as f64;
-    let variance = candles.iter().rev().take(BOLLINGER_PERIOD)
-        .map(|c| (ma - c.close).powi(2)).sum::<f64>() / BOLLINGER_PERIOD as f64;
-    let std_dev = variance.sqrt();
-    Some((ma - std_dev * BOLLINGER_MULT, ma + std_dev * BOLLINGER_MULT))
+fn calculate_atr(candles: &VecDeque<Candle>) -> Option<f64> {
+    let bars: Vec<Candle> = candles.iter().copied().collect();
+    indicators::atr(&bars, ATR_PERIOD)
 }
 
 // --- 2. VOLUME ANALYSIS (Whale Detector) ---
 // Ritorna true se il volume attuale è molto superiore alla media (Smart Money in entrata)
-fn check_volume_spike(candles: &VecDeque<Candle>) -> bool {
-    if candles.len() < VOLUME_MA_PERIOD + 1 { return false; }
-    
-    let current_vol = candles.back().unwrap().volume;
-    let sum_vol: f64 = candles.iter().rev().skip(1).take(VOLUME_MA_PERIOD).map(|c| c.volume).sum();
-    let avg_vol = sum_vol / VOLUME_MA_PERIOD as f64;
+fn check_volume_spike(candles: &VecDeque<Candle>, mult: f64) -> bool {
+    let volumes: Vec<f64> = candles.iter().map(|c| c.volume).collect();
+    let Some((&current_vol, previous)) = volumes.split_last() else { return false };
 
-    // Se il volume è doppio rispetto alla media, c'è interesse forte
-    current_vol > (avg_vol * 2.0)
+    // Se il volume è multiplo della media (default 2x), c'è interesse forte
+    indicators::sma(previous, VOLUME_MA_PERIOD).is_some_and(|avg_vol| current_vol > avg_vol * mult)
+}
+
+// Volume dell'ultima candela rispetto alla media delle precedenti (None se la media è zero)
+fn volume_ratio(candles: &VecDeque<Candle>) -> Option<f64> {
+    let volumes: Vec<f64> = candles.iter().map(|c| c.volume).collect();
+    indicators::volume_ratio(&volumes, VOLUME_MA_PERIOD)
 }
 
 // --- 3. MONEY MANAGEMENT ---
@@ -129,54 +341,303 @@ pub fn calculate_investment_amount(wallet_balance_sol: f64) -> f64 {
     if amount > 5.0 { 5.0 } else { amount }
 }
 
-// --- 4. ENGINE DECISIONALE (Volume + Prezzo) ---
+// --- 3b. KELLY SIZING (Dallo storico dei trade chiusi della stessa strategia) ---
+// f* = W - (1 - W) / R, con W = win rate e R = rendimento medio vincite / perdita media.
+// Si punta kelly_fraction * f* del saldo; con pochi trade si torna agli scaglioni, con edge negativo non si compra.
 
-pub fn analyze_market(data: &MarketData, wallet_balance: f64) -> TradeAction {
-    if data.candles.len() < BOLLINGER_PERIOD { return TradeAction::Hold; }
-    
-    let current_close = data.candles.back().unwrap().close;
-    let rsi = calculate_rsi(&data.candles);
-    let bb = calculate_bollinger(&data.candles);
-    let volume_spike = check_volume_spike(&data.candles);
+const KELLY_MIN_TRADES: usize = 20;
 
-    if rsi.is_none() || bb.is_none() { return TradeAction::Hold; }
-    
-    let rsi_val = rsi.unwrap();
-    let (lower_band, upper_band) = bb.unwrap();
+// Esiti dei trade chiusi: rendimenti (PnL / investito), es. +0.35 = +35%
+#[derive(Clone, Debug, Default)]
+pub struct EdgeStats {
+    pub returns: Vec<f64>,
+}
 
-    // VENDITA
-    if rsi_val > 75.0 || current_close > upper_band {
-        return TradeAction::Sell(format!("Overbought: RSI {:.1}", rsi_val));
+impl EdgeStats {
+    /// Frazione di Kelly piena (None se lo storico non basta)
+    pub fn kelly(&self) -> Option<f64> {
+        if self.returns.len() < KELLY_MIN_TRADES { return None; }
+        let wins: Vec<f64> = self.returns.iter().copied().filter(|r| *r > 0.0).collect();
+        let losses: Vec<f64> = self.returns.iter().copied().filter(|r| *r < 0.0).map(f64::abs).collect();
+        let win_rate = wins.len() as f64 / self.returns.len() as f64;
+        if losses.is_empty() { return Some(1.0); }
+        if wins.is_empty() { return Some(0.0); }
+        let avg_win = wins.iter().sum::<f64>() / wins.len() as f64;
+        let avg_loss = losses.iter().sum::<f64>() / losses.len() as f64;
+        Some((win_rate - (1.0 - win_rate) / (avg_win / avg_loss)).clamp(0.0, 1.0))
     }
+}
 
-    // ACQUISTO (Setup Whale)
-    // 1. Prezzo basso (Sconto BB o RSI < 40)
-    // 2. VOLUME ALTO (Qualcuno sta comprando pesantemente il dip!)
-    
-    let is_cheap = current_close <= lower_band * 1.02 || rsi_val < 40.0;
+/// Importo da investire secondo il sizing scelto dall'utente, sempre entro max_per_trade_sol
+pub fn sized_investment_amount(wallet_balance_sol: f64, params: &StrategyParams, stats: Option<&EdgeStats>) -> f64 {
+    let amount = match stats.and_then(EdgeStats::kelly) {
+        Some(f) if params.sizing == "kelly" => (wallet_balance_sol - 0.02).max(0.0) * f * params.kelly_fraction,
+        _ => calculate_investment_amount(wallet_balance_sol),
+    };
+    amount.min(params.max_per_trade_sol)
+}
+
+// --- 4. ENGINE DECISIONALE (Strategie intercambiabili) ---
+// Ogni strategia decide ingresso/uscita sugli stessi indicatori; l'utente sceglie quale usare
+// con `engine` nei parametri (default "smart") e analyze_market smista alla strategia scelta.
+
+/// ATR in percentuale dell'ultima chiusura (per stop indipendenti dal prezzo del token)
+pub fn atr_pct(candles: &VecDeque<Candle>) -> Option<f64> {
+    let close = candles.back()?.close;
+    if close <= 0.0 { return None; }
+    calculate_atr(candles).map(|atr| atr / close * 100.0)
+}
+
+// Indicatori dell'ultima candela, calcolati una volta per analisi
+#[derive(Serialize, Clone, Debug)]
+pub struct Indicators {
+    pub close: f64,
+    pub rsi: f64,
+    pub lower_band: f64,
+    pub middle_band: f64,
+    pub upper_band: f64,
+    pub atr: Option<f64>,
+    pub volume_spike: bool,
+}
+
+impl Indicators {
+    pub fn compute(data: &MarketData, params: &StrategyParams) -> Option<Self> {
+        if data.candles.len() < BOLLINGER_PERIOD { return None; }
+        let closes = closes(&data.candles);
+        let rsi = indicators::rsi(&closes, RSI_PERIOD)?;
+        let (lower_band, middle_band, upper_band) = indicators::bollinger(&closes, BOLLINGER_PERIOD, BOLLINGER_MULT)?;
+        Some(Self {
+            close: data.candles.back()?.close,
+            rsi,
+            lower_band,
+            middle_band,
+            upper_band,
+            atr: calculate_atr(&data.candles),
+            volume_spike: check_volume_spike(&data.candles, params.volume_spike_mult),
+        })
+    }
+}
 
-    if is_cheap && volume_spike {
-        let invest_amount = calculate_investment_amount(wallet_balance);
-        if invest_amount > 0.001 {
-            return TradeAction::Buy {
-                amount_sol: invest_amount,
-                reason: format!("WHALE ALERT: Volume Spike + Prezzo Basso (RSI {:.1})", rsi_val)
-            };
+// Singola condizione d'ingresso di una strategia (per capire perché il bot è entrato o no)
+#[derive(Serialize, Clone, Debug)]
+pub struct EntryCheck {
+    pub name: &'static str,
+    pub passed: bool,
+    pub detail: String,
+}
+
+impl EntryCheck {
+    pub fn new(name: &'static str, passed: bool, detail: String) -> Self {
+        Self { name, passed, detail }
+    }
+}
+
+fn all_passed(checks: &[EntryCheck]) -> bool {
+    checks.iter().all(|c| c.passed)
+}
+
+pub trait Strategy: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Tipo di ingresso cercato (DIP, BREAKOUT, REVERSION)
+    fn mode(&self) -> &'static str;
+
+    /// Condizioni d'ingresso valutate una per una: si entra solo se passano tutte
+    fn entry_checks(&self, data: &MarketData, ind: &Indicators, params: &StrategyParams) -> Vec<EntryCheck>;
+
+    /// Motivo dell'ingresso, None = nessun segnale
+    fn entry_signal(&self, data: &MarketData, ind: &Indicators, params: &StrategyParams) -> Option<String>;
+
+    /// Motivo dell'uscita, None = tieni
+    fn exit_signal(&self, data: &MarketData, ind: &Indicators, params: &StrategyParams) -> Option<String>;
+
+    /// SOL da investire dato il saldo
+    fn position_sizing(&self, wallet_balance: f64, _params: &StrategyParams) -> f64 {
+        calculate_investment_amount(wallet_balance)
+    }
+
+    /// Prima l'uscita, poi l'ingresso (solo se l'importo ha senso)
+    fn analyze(&self, data: &MarketData, wallet_balance: f64, params: &StrategyParams) -> TradeAction {
+        let ind = match Indicators::compute(data, params) {
+            Some(i) => i,
+            None => return TradeAction::Hold,
+        };
+        if let Some(reason) = self.exit_signal(data, &ind, params) {
+            return TradeAction::Sell(reason);
+        }
+        if let Some(reason) = self.entry_signal(data, &ind, params) {
+            let invest_amount = self.position_sizing(wallet_balance, params);
+            if invest_amount > 0.001 {
+                return TradeAction::Buy { amount_sol: invest_amount, reason };
+            }
         }
+        TradeAction::Hold
     }
+}
 
-    TradeAction::Hold
+// SMART (default): compra il dip confermato da volume o whale, vende in ipercomprato
+pub struct SmartDip;
+
+impl Strategy for SmartDip {
+    fn name(&self) -> &'static str { "smart" }
+    fn mode(&self) -> &'static str { "DIP" }
+
+    // ACQUISTO (Setup Whale)
+    // 1. Prezzo basso (Sconto BB o RSI < soglia oversold)
+    // 2. VOLUME ALTO (Qualcuno sta comprando pesantemente il dip!) o acquisti whale netti
+    // 3. Nessuna whale in uscita
+    fn entry_checks(&self, data: &MarketData, ind: &Indicators, params: &StrategyParams) -> Vec<EntryCheck> {
+        let is_cheap = ind.close <= ind.lower_band * 1.02 || ind.rsi < params.rsi_oversold;
+        let whale_buying = data.whale_pressure >= WHALE_PRESSURE_CONFIRM;
+        let whale_dumping = data.whale_pressure <= WHALE_PRESSURE_BLOCK;
+        vec![
+            EntryCheck::new("cheap", is_cheap, format!("close {:.8} vs banda inf. {:.8} (+2%), RSI {:.1} < {:.1}", ind.close, ind.lower_band, ind.rsi, params.rsi_oversold)),
+            EntryCheck::new("volume_or_whale", ind.volume_spike || whale_buying, format!("volume spike {}, pressione whale {:.2} >= {:.2}", ind.volume_spike, data.whale_pressure, WHALE_PRESSURE_CONFIRM)),
+            EntryCheck::new("no_whale_dump", !whale_dumping, format!("pressione whale {:.2} > {:.2}", data.whale_pressure, WHALE_PRESSURE_BLOCK)),
+        ]
+    }
+
+    fn entry_signal(&self, data: &MarketData, ind: &Indicators, params: &StrategyParams) -> Option<String> {
+        all_passed(&self.entry_checks(data, ind, params))
+            .then(|| format!("WHALE ALERT: Volume Spike + Prezzo Basso (RSI {:.1})", ind.rsi))
+    }
+
+    fn exit_signal(&self, _data: &MarketData, ind: &Indicators, params: &StrategyParams) -> Option<String> {
+        (ind.rsi > params.rsi_overbought || ind.close > ind.upper_band)
+            .then(|| format!("Overbought: RSI {:.1}", ind.rsi))
+    }
+}
+
+// MOMENTUM: entra sulla forza (sopra la media BB, RSI in salita ma non ipercomprato, volume in aumento)
+pub struct Momentum;
+
+impl Strategy for Momentum {
+    fn name(&self) -> &'static str { "momentum" }
+    fn mode(&self) -> &'static str { "BREAKOUT" }
+
+    fn entry_checks(&self, data: &MarketData, ind: &Indicators, params: &StrategyParams) -> Vec<EntryCheck> {
+        let trending = ind.close > ind.middle_band && ind.rsi >= 50.0 && ind.rsi < params.rsi_overbought;
+        vec![
+            EntryCheck::new("trending", trending, format!("close {:.8} > media {:.8}, RSI {:.1} in [50, {:.1})", ind.close, ind.middle_band, ind.rsi, params.rsi_overbought)),
+            EntryCheck::new("volume_spike", ind.volume_spike, format!("volume spike {} (x{:.1})", ind.volume_spike, params.volume_spike_mult)),
+            EntryCheck::new("no_whale_dump", data.whale_pressure > WHALE_PRESSURE_BLOCK, format!("pressione whale {:.2} > {:.2}", data.whale_pressure, WHALE_PRESSURE_BLOCK)),
+        ]
+    }
+
+    fn entry_signal(&self, data: &MarketData, ind: &Indicators, params: &StrategyParams) -> Option<String> {
+        all_passed(&self.entry_checks(data, ind, params))
+            .then(|| format!("MOMENTUM: Breakout con volume (RSI {:.1})", ind.rsi))
+    }
+
+    // Il momentum è finito quando si torna sotto la media o si va in ipercomprato
+    fn exit_signal(&self, _data: &MarketData, ind: &Indicators, params: &StrategyParams) -> Option<String> {
+        if ind.rsi > params.rsi_overbought { return Some(format!("Overbought: RSI {:.1}", ind.rsi)); }
+        (ind.close < ind.middle_band).then(|| format!("Momentum perso: sotto la media (RSI {:.1})", ind.rsi))
+    }
+}
+
+// MEAN REVERSION: compra sotto la banda inferiore in ipervenduto, vende al ritorno sulla media
+pub struct MeanReversion;
+
+impl Strategy for MeanReversion {
+    fn name(&self) -> &'static str { "mean_reversion" }
+    fn mode(&self) -> &'static str { "REVERSION" }
+
+    fn entry_checks(&self, data: &MarketData, ind: &Indicators, params: &StrategyParams) -> Vec<EntryCheck> {
+        vec![
+            EntryCheck::new("below_lower_band", ind.close <= ind.lower_band, format!("close {:.8} <= banda inf. {:.8}", ind.close, ind.lower_band)),
+            EntryCheck::new("oversold", ind.rsi < params.rsi_oversold, format!("RSI {:.1} < {:.1}", ind.rsi, params.rsi_oversold)),
+            EntryCheck::new("no_whale_dump", data.whale_pressure > WHALE_PRESSURE_BLOCK, format!("pressione whale {:.2} > {:.2}", data.whale_pressure, WHALE_PRESSURE_BLOCK)),
+        ]
+    }
+
+    fn entry_signal(&self, data: &MarketData, ind: &Indicators, params: &StrategyParams) -> Option<String> {
+        all_passed(&self.entry_checks(data, ind, params))
+            .then(|| format!("MEAN REVERSION: Sotto banda inferiore (RSI {:.1})", ind.rsi))
+    }
+
+    fn exit_signal(&self, _data: &MarketData, ind: &Indicators, _params: &StrategyParams) -> Option<String> {
+        (ind.close >= ind.middle_band).then(|| format!("Ritorno alla media (RSI {:.1})", ind.rsi))
+    }
+}
+
+static STRATEGIES: [&dyn Strategy; 3] = [&SmartDip, &Momentum, &MeanReversion];
+
+/// Strategia registrata con questo nome
+pub fn by_name(name: &str) -> Option<&'static dyn Strategy> {
+    STRATEGIES.iter().copied().find(|s| s.name() == name)
+}
+
+/// Nomi disponibili (per messaggi e validazione)
+pub fn names() -> Vec<&'static str> {
+    STRATEGIES.iter().map(|s| s.name()).collect()
+}
+
+/// Analisi con la strategia scelta dall'utente (`engine`), "smart" se sconosciuta
+pub fn analyze_market(data: &MarketData, wallet_balance: f64, params: &StrategyParams) -> TradeAction {
+    by_name(&params.engine).unwrap_or(&SmartDip).analyze(data, wallet_balance, params)
+}
+
+// Analisi completa con la strategia dell'utente: indicatori, condizioni d'ingresso e decisione (GET /debug/analysis)
+#[derive(Serialize, Clone, Debug)]
+pub struct MarketAnalysis {
+    pub engine: &'static str,
+    pub mode: &'static str,
+    pub candles: usize,
+    pub indicators: Option<Indicators>, // None = candele insufficienti
+    pub ema20: Option<f64>,
+    pub ema50: Option<f64>,
+    pub atr_pct: Option<f64>,
+    pub volume_ratio: Option<f64>,
+    pub whale_pressure: f64,
+    pub entry_checks: Vec<EntryCheck>,
+    pub exit_signal: Option<String>,
+    pub action: String,
+}
+
+impl MarketAnalysis {
+    pub fn compute(data: &MarketData, params: &StrategyParams) -> Self {
+        let strategy = by_name(&params.engine).unwrap_or(&SmartDip);
+        let indicators = Indicators::compute(data, params);
+        let closes = closes(&data.candles);
+        let action = match strategy.analyze(data, 1.0, params) {
+            TradeAction::Buy { reason, .. } => format!("BUY: {}", reason),
+            TradeAction::Sell(reason) => format!("SELL: {}", reason),
+            _ => "HOLD".to_string(),
+        };
+        Self {
+            engine: strategy.name(),
+            mode: strategy.mode(),
+            candles: data.candles.len(),
+            entry_checks: indicators.as_ref().map(|ind| strategy.entry_checks(data, ind, params)).unwrap_or_default(),
+            exit_signal: indicators.as_ref().and_then(|ind| strategy.exit_signal(data, ind, params)),
+            indicators,
+            ema20: indicators::ema(&closes, EMA_FAST),
+            ema50: indicators::ema(&closes, EMA_SLOW),
+            atr_pct: atr_pct(&data.candles),
+            volume_ratio: volume_ratio(&data.candles),
+            whale_pressure: data.whale_pressure,
+            action,
+        }
+    }
 }
 
 // --- 5. TRAILING STOP ---
-pub fn check_position(current_val: u64, high_val: u64) -> TradeAction {
+// `atr_pct` = ATR del token in % del prezzo (None = non noto: si usa lo stop percentuale)
+pub fn check_position(current_val: u64, high_val: u64, params: &StrategyParams, atr_pct: Option<f64>) -> TradeAction {
     if current_val > high_val { return TradeAction::UpdateHigh(current_val); }
 
     let drop_pct = (high_val.saturating_sub(current_val) as f64 / high_val as f64) * 100.0;
-    let dynamic_stop = if high_val > (current_val * 12 / 10) { 3.0 } else { 10.0 };
+    let dynamic_stop = if high_val > (current_val * 12 / 10) { params.tight_stop_pct } else { params.trailing_stop_pct };
+
+    let (stop, label) = match (params.exit_mode.as_str(), atr_pct) {
+        ("atr", Some(atr)) => ((atr * params.atr_stop_mult).min(MAX_ATR_STOP_PCT), "ATR Stop"),
+        ("hybrid", Some(atr)) => ((atr * params.atr_stop_mult).clamp(params.tight_stop_pct, params.trailing_stop_pct), "Hybrid Stop"),
+        _ => (dynamic_stop, "Smart Stop"),
+    };
 
-    if drop_pct >= dynamic_stop {
-        return TradeAction::Sell(format!("Smart Stop: -{:.1}%", drop_pct));
+    if drop_pct >= stop {
+        return TradeAction::Sell(format!("{}: -{:.1}%", label, drop_pct));
     }
     
     TradeAction::Hold
@@ -0,0 +1,391 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use async_trait::async_trait;
+use solana_sdk::signature::Keypair;
+use sqlx::Row;
+use tokio::sync::mpsc;
+use crate::chain::{Chain, ChainClient, ChainLog, ChainResult, Quote, Swap, Wallet};
+use crate::db::{self, DbPool};
+use crate::strategy::{Candle, MarketData, StrategyParams};
+use crate::tasks::positions::{self, PositionTick};
+use crate::{signals, tasks, trading, AppState};
+
+// --- STRESS TEST DEL CICLO DI TRADING (feature "stress": cargo test --features stress) ---
+// 1. MockChain implementa ChainClient: saldi in memoria, quote e swap al prezzo dello script, nessuna RPC né Jupiter
+// 2. Il Harness fa girare su un percorso di prezzi scriptato le funzioni dei task (tasks/), non una loro copia:
+//    Market Strategy (tasks::strategy::entry_signal -> trading::auto_buy_on_chain, con i controlli di rischio) e
+//    Position Manager (positions::track_open_trades -> positions::position_tick) su un DB SQLite vero con le migrazioni
+// 3. Gli scenari verificano ingressi, uscite e righe scritte nel DB: vanno verdi prima di toccare il percorso d'esecuzione
+// Fuori dal harness: coda acquisti, prezzi Birdeye, ATR e notifiche (servono la rete).
+
+const LAMPORTS_PER_SOL: f64 = 1_000_000_000.0;
+// Liquidità e volume del mercato simulato: i filtri della Market Strategy passano sempre
+const DEEP_MARKET_USD: f64 = 1e12;
+
+// Candela dello script: prezzo in SOL per unità base del token e volume della candela
+#[derive(Clone, Copy, Debug)]
+pub struct Tick {
+    pub price: f64,
+    pub volume: f64,
+}
+
+// Swap eseguito dal MockChain
+#[derive(Clone, Debug)]
+pub struct MockSwap {
+    pub side: &'static str,
+    pub token: String,
+    pub amount_in: u64,
+    pub amount_out: u64,
+}
+
+#[derive(Default)]
+struct MockState {
+    native: u64,
+    tokens: HashMap<String, u64>,
+    prices: HashMap<String, f64>,
+    swaps: Vec<MockSwap>,
+    fail_sells: bool,
+    logs: Vec<ChainLog>,
+}
+
+// Catena simulata con un solo wallet: i saldi non dipendono dall'indirizzo
+#[derive(Default)]
+pub struct MockChain {
+    state: Mutex<MockState>,
+}
+
+impl MockChain {
+    pub fn new(balance_sol: f64) -> Self {
+        let chain = Self::default();
+        chain.state.lock().unwrap().native = (balance_sol * LAMPORTS_PER_SOL) as u64;
+        chain
+    }
+
+    pub fn set_price(&self, token: &str, price: f64) {
+        self.state.lock().unwrap().prices.insert(token.to_string(), price);
+    }
+
+    /// Le vendite falliscono (RPC giù, pool senza liquidità) finché non si rimette false
+    pub fn fail_sells(&self, fail: bool) {
+        self.state.lock().unwrap().fail_sells = fail;
+    }
+
+    /// Log consegnati alla prossima subscribe_logs (es. lanci Raydium per lo Sniper)
+    pub fn push_log(&self, log: ChainLog) {
+        self.state.lock().unwrap().logs.push(log);
+    }
+
+    pub fn native(&self) -> u64 {
+        self.state.lock().unwrap().native
+    }
+
+    pub fn swaps(&self) -> Vec<MockSwap> {
+        self.state.lock().unwrap().swaps.clone()
+    }
+
+    fn price(state: &MockState, token: &str) -> ChainResult<f64> {
+        state.prices.get(token).copied().filter(|p| *p > 0.0).ok_or_else(|| format!("Nessun prezzo per {}", token).into())
+    }
+
+    fn record(state: &mut MockState, side: &'static str, token: &str, amount_in: u64, amount_out: u64) -> Swap {
+        state.swaps.push(MockSwap { side, token: token.to_string(), amount_in, amount_out });
+        Swap { signature: format!("mock-{}-{}", side, state.swaps.len()), route: "mock", expected_out: amount_out }
+    }
+}
+
+#[async_trait]
+impl ChainClient for MockChain {
+    fn chain(&self) -> Chain { Chain::Solana }
+
+    fn native_token(&self) -> &'static str { "So11111111111111111111111111111111111111112" }
+
+    fn is_valid_address(&self, address: &str) -> bool { !address.is_empty() }
+
+    async fn load_wallet(&self, _pool: &DbPool, _user_id: &str) -> ChainResult<Wallet> {
+        Ok(Wallet::Solana(Keypair::new()))
+    }
+
+    async fn native_balance(&self, _owner: &str) -> ChainResult<u64> {
+        Ok(self.native())
+    }
+
+    async fn token_balance(&self, _owner: &str, token: &str) -> ChainResult<u64> {
+        Ok(self.state.lock().unwrap().tokens.get(token).copied().unwrap_or(0))
+    }
+
+    async fn quote(&self, input: &str, output: &str, amount: u64, _slippage_bps: u16) -> ChainResult<Quote> {
+        let state = self.state.lock().unwrap();
+        let out_amount = if input == self.native_token() {
+            (amount as f64 / Self::price(&state, output)?) as u64
+        } else {
+            (amount as f64 * Self::price(&state, input)?) as u64
+        };
+        Ok(Quote { route: "mock", in_amount: amount, out_amount })
+    }
+
+    async fn buy(&self, _pool: &DbPool, _user_id: &str, _wallet: &Wallet, token: &str, amount: u64, _slippage_bps: u16) -> ChainResult<Swap> {
+        let mut state = self.state.lock().unwrap();
+        if state.native < amount { return Err("Saldo insufficiente".into()); }
+        let out = (amount as f64 / Self::price(&state, token)?) as u64;
+        state.native -= amount;
+        *state.tokens.entry(token.to_string()).or_default() += out;
+        Ok(Self::record(&mut state, "buy", token, amount, out))
+    }
+
+    async fn sell(&self, _pool: &DbPool, _user_id: &str, _wallet: &Wallet, token: &str, amount: u64, _slippage_bps: u16) -> ChainResult<Swap> {
+        let mut state = self.state.lock().unwrap();
+        if state.fail_sells { return Err("Vendita simulata fallita".into()); }
+        let held = state.tokens.get(token).copied().unwrap_or(0);
+        if held < amount { return Err("Token insufficienti".into()); }
+        let out = (amount as f64 * Self::price(&state, token)?) as u64;
+        state.tokens.insert(token.to_string(), held - amount);
+        state.native += out;
+        Ok(Self::record(&mut state, "sell", token, amount, out))
+    }
+
+    async fn subscribe_logs(&self, _address: &str) -> ChainResult<mpsc::UnboundedReceiver<ChainLog>> {
+        // Consegna i log in coda e chiude lo stream (come una caduta del WebSocket)
+        let (tx, rx) = mpsc::unbounded_channel();
+        for log in self.state.lock().unwrap().logs.drain(..) {
+            let _ = tx.send(log);
+        }
+        Ok(rx)
+    }
+}
+
+// Ingresso o uscita eseguiti dal harness
+#[derive(Clone, Debug)]
+pub struct Fill {
+    pub tick: usize,
+    pub price: f64,
+    pub trade_id: i64,
+    pub reason: String,
+    pub signature: String,
+}
+
+#[derive(Default, Debug)]
+pub struct Report {
+    pub entries: Vec<Fill>,
+    pub exits: Vec<Fill>,
+    // Vendite fallite (finiscono nel Sell Retry Queue: i giri successivi non rivendono il token)
+    pub failed_sells: usize,
+}
+
+pub struct Harness {
+    pub pool: DbPool,
+    pub chain: MockChain,
+    pub state: Arc<AppState>,
+    pub params: StrategyParams,
+    pub user_id: String,
+}
+
+impl Harness {
+    /// DB SQLite nuovo nella cartella temporanea (un file per scenario) e wallet simulato con `balance_sol`
+    pub async fn new(scenario: &str, params: StrategyParams, balance_sol: f64) -> Self {
+        let path = std::env::temp_dir().join(format!("god_sniper_stress_{}_{}.db", scenario, std::process::id()));
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+        }
+        let pool = db::connect_url(&format!("sqlite://{}", path.display())).await;
+        Self { pool, chain: MockChain::new(balance_sol), state: Arc::new(AppState::default()), params, user_id: "stress-user".into() }
+    }
+
+    /// Una candela per tick: la Market Strategy valuta l'ingresso, poi un giro del Position Manager
+    pub async fn run(&self, token: &str, path: &[Tick]) -> ChainResult<Report> {
+        let mut report = Report::default();
+        let mut data = MarketData::new("STRESS");
+        let signal = signals::EntrySignal { symbol: "STRESS".into(), source: "MARKET", score: 90, balance_fraction: None };
+
+        for (i, tick) in path.iter().enumerate() {
+            self.chain.set_price(token, tick.price);
+            data.add_candle(Candle { high: tick.price, low: tick.price, close: tick.price, volume: tick.volume });
+
+            // Market Strategy: segnale dell'utente e acquisto come nei worker della coda
+            if let Some(reason) = tasks::strategy::entry_signal(&data, DEEP_MARKET_USD, DEEP_MARKET_USD, &self.params) {
+                if let Some((swap, _)) = trading::auto_buy_on_chain(&self.pool, &self.chain, &self.state, &self.user_id, token, &self.params, &signal).await {
+                    let trade_id = self.trade_id(&swap.signature).await?;
+                    report.entries.push(Fill { tick: i, price: tick.price, trade_id, reason, signature: swap.signature });
+                }
+            }
+
+            // Position Manager: aggancio dei trade OPEN al prezzo del giro, poi un giro su ogni posizione
+            let prices = HashMap::from([(token.to_string(), tick.price)]);
+            positions::track_open_trades(&self.pool, &self.state, db::get_open_trades(&self.pool).await?, &prices).await;
+            let queued: HashSet<(String, String)> = db::get_queued_sells(&self.pool).await?.into_iter().collect();
+            for pos in self.state.positions_snapshot(Some(&self.user_id)) {
+                let trade_id = pos.trade_id;
+                match positions::position_tick(&self.pool, &self.chain, &self.state, pos, &self.params, tick.price, None, &queued).await {
+                    PositionTick::Sold { reason, signature, .. } => report.exits.push(Fill { tick: i, price: tick.price, trade_id, reason, signature }),
+                    PositionTick::SellFailed => report.failed_sells += 1,
+                    _ => {},
+                }
+            }
+        }
+        Ok(report)
+    }
+
+    async fn trade_id(&self, signature: &str) -> ChainResult<i64> {
+        let row = sqlx::query("SELECT id FROM trades WHERE tx_signature = $1")
+            .bind(signature)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(row.get("id"))
+    }
+
+    /// Trade dell'utente nel DB: (id, stato, PnL SOL, firma d'uscita)
+    pub async fn trades(&self) -> Vec<(i64, String, f64, Option<String>)> {
+        let rows = sqlx::query("SELECT id, status, profit_loss_sol, exit_signature FROM trades WHERE user_id = $1 ORDER BY id")
+            .bind(&self.user_id)
+            .fetch_all(&self.pool)
+            .await
+            .unwrap_or_default();
+        rows.iter().map(|r| (r.get("id"), r.get("status"), r.get::<Option<f64>, _>("profit_loss_sol").unwrap_or(0.0), r.get("exit_signature"))).collect()
+    }
+}
+
+// --- PERCORSI DI PREZZO ---
+
+/// `len` candele piatte al prezzo `price` con volume `volume`
+pub fn flat(price: f64, volume: f64, len: usize) -> Vec<Tick> {
+    vec![Tick { price, volume }; len]
+}
+
+/// Da `from` a `to` in `steps` candele (esclusa la partenza)
+pub fn ramp(from: f64, to: f64, steps: usize, volume: f64) -> Vec<Tick> {
+    (1..=steps).map(|i| Tick { price: from + (to - from) * i as f64 / steps as f64, volume }).collect()
+}
+
+/// Passeggiata casuale riproducibile (xorshift): variazioni fino a ±`max_move_pct`% e picchi di volume occasionali
+pub fn random_walk(seed: u64, start: f64, len: usize, max_move_pct: f64) -> Vec<Tick> {
+    let mut state = seed.max(1);
+    let mut next = move || { state ^= state << 13; state ^= state >> 7; state ^= state << 17; (state % 10_000) as f64 / 10_000.0 };
+    let mut price = start;
+    (0..len).map(|_| {
+        price = (price * (1.0 + (next() * 2.0 - 1.0) * max_move_pct / 100.0)).max(start * 1e-6);
+        let volume = if next() > 0.9 { 1_000.0 } else { 100.0 };
+        Tick { price, volume }
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOKEN: &str = "StressToken1111111111111111111111111111111";
+
+    // Una posizione per volta e nessun cooldown: i percorsi durano pochi secondi di orologio reale
+    fn params() -> StrategyParams {
+        StrategyParams { max_per_trade_sol: 0.5, max_open_positions: 1, buy_cooldown_secs: 0, ..StrategyParams::default() }
+    }
+
+    // Mercato piatto, poi un dip con volume 5x: ingresso SmartDip sulla candela del dip
+    fn dip() -> Vec<Tick> {
+        let mut path = flat(1.0, 100.0, 30);
+        path.push(Tick { price: 0.9, volume: 500.0 });
+        path
+    }
+
+    #[tokio::test]
+    async fn dip_entry_then_trailing_stop_exit() {
+        let h = Harness::new("trailing", params(), 2.0).await;
+        let mut path = dip();
+        path.extend(ramp(0.9, 1.2, 10, 100.0));
+        path.extend(ramp(1.2, 1.0, 4, 100.0));
+        let report = h.run(TOKEN, &path).await.unwrap();
+
+        assert_eq!(report.entries.len(), 1, "{:?}", report);
+        assert_eq!(report.entries[0].tick, 30);
+        assert_eq!(report.exits.len(), 1, "{:?}", report);
+        let exit = &report.exits[0];
+        assert!(exit.price < 1.2 * 0.9 + 1e-9, "uscita oltre lo stop del 10%: {}", exit.price);
+        assert!(exit.price > 0.9, "uscita in perdita nonostante il massimo a 1.2");
+
+        // DB: trade chiuso con la firma della vendita e PnL positivo, posizione rimossa
+        let trades = h.trades().await;
+        assert_eq!(trades.len(), 1);
+        let (id, status, pnl, exit_sig) = &trades[0];
+        assert_eq!(*id, report.entries[0].trade_id);
+        assert_eq!(status, "SOLD");
+        assert!(*pnl > 0.0, "PnL {}", pnl);
+        assert_eq!(exit_sig.as_deref(), Some(exit.signature.as_str()));
+        assert!(db::load_positions(&h.pool).await.unwrap().is_empty());
+
+        // Catena: un acquisto e una vendita di tutti i token comprati
+        let swaps = h.chain.swaps();
+        assert_eq!(swaps.iter().map(|s| s.side).collect::<Vec<_>>(), ["buy", "sell"]);
+        assert_eq!(swaps[0].amount_out, swaps[1].amount_in);
+        assert!(h.chain.native() > 2_000_000_000);
+    }
+
+    #[tokio::test]
+    async fn take_profit_exit() {
+        let h = Harness::new("take_profit", StrategyParams { take_profit_pct: 20.0, ..params() }, 2.0).await;
+        let mut path = dip();
+        path.extend(ramp(0.9, 1.2, 6, 100.0));
+        let report = h.run(TOKEN, &path).await.unwrap();
+
+        assert_eq!(report.exits.len(), 1, "{:?}", report);
+        assert_eq!(report.exits[0].reason, "🎯 Take Profit");
+        assert!(report.exits[0].price >= 0.9 * 1.2 - 1e-9);
+    }
+
+    #[tokio::test]
+    async fn no_entry_without_volume() {
+        let h = Harness::new("no_volume", params(), 2.0).await;
+        let mut path = flat(1.0, 100.0, 30);
+        path.extend(ramp(1.0, 0.5, 20, 100.0));
+        let report = h.run(TOKEN, &path).await.unwrap();
+
+        assert!(report.entries.is_empty(), "{:?}", report);
+        assert!(h.trades().await.is_empty());
+        assert!(h.chain.swaps().is_empty());
+    }
+
+    #[tokio::test]
+    async fn failed_sell_keeps_position_open() {
+        let h = Harness::new("failed_sell", params(), 2.0).await;
+        h.chain.fail_sells(true);
+        let mut path = dip();
+        path.extend(ramp(0.9, 0.5, 5, 100.0));
+        let report = h.run(TOKEN, &path).await.unwrap();
+
+        assert_eq!(report.entries.len(), 1);
+        assert!(report.exits.is_empty());
+        assert!(report.failed_sells > 0);
+        let trades = h.trades().await;
+        assert_eq!(trades[0].1, "OPEN");
+        assert_eq!(db::load_positions(&h.pool).await.unwrap().len(), 1);
+    }
+
+    // Stress: molti percorsi casuali, invarianti tra report, DB e saldi simulati
+    #[tokio::test]
+    async fn random_walks_keep_db_and_chain_consistent() {
+        let mut total_entries = 0;
+        for seed in 1..=40 {
+            let h = Harness::new(&format!("walk_{}", seed), params(), 5.0).await;
+            let mut path = flat(1.0, 100.0, 25);
+            path.extend(random_walk(seed, 1.0, 300, 4.0));
+            let report = h.run(TOKEN, &path).await.unwrap();
+            total_entries += report.entries.len();
+
+            let trades = h.trades().await;
+            assert_eq!(trades.len(), report.entries.len(), "seed {}", seed);
+            let sold = trades.iter().filter(|t| t.1 == "SOLD").count();
+            assert_eq!(sold, report.exits.len(), "seed {}", seed);
+            // Al più una posizione aperta per volta: solo l'ultimo trade può restare OPEN
+            assert!(report.entries.len() - report.exits.len() <= 1, "seed {}", seed);
+            assert_eq!(db::load_positions(&h.pool).await.unwrap().len(), report.entries.len() - report.exits.len(), "seed {}", seed);
+
+            // PnL registrato nel DB = SOL guadagnati sulla catena simulata (a meno degli arrotondamenti)
+            let held: u64 = h.chain.token_balance("", TOKEN).await.unwrap();
+            if held == 0 {
+                let pnl: f64 = trades.iter().map(|t| t.2).sum();
+                let chain_pnl = (h.chain.native() as f64 - 5.0 * LAMPORTS_PER_SOL) / LAMPORTS_PER_SOL;
+                assert!((pnl - chain_pnl).abs() < 1e-6, "seed {}: DB {} vs catena {}", seed, pnl, chain_pnl);
+            }
+            h.pool.close().await;
+        }
+        // Lo scenario deve davvero aprire e chiudere trade, altrimenti le invarianti sono vuote
+        assert!(total_entries > 0);
+    }
+}
@@ -0,0 +1,481 @@
+use std::cmp::Reverse;
+use std::error::Error;
+use std::str::FromStr;
+use std::sync::Arc;
+use base64::{Engine as _, engine::general_purpose};
+use log::{info, warn};
+use solana_client::rpc_config::RpcTransactionConfig;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signature, Signer};
+use solana_sdk::transaction::VersionedTransaction;
+use solana_transaction_status::option_serializer::OptionSerializer;
+use solana_transaction_status::{UiTransactionEncoding, UiTransactionTokenBalance};
+use crate::db::DbPool;
+use tokio::time::{sleep, Duration};
+use crate::{db, i18n, jupiter, raydium, referral, telegram_bot, network::{FeePercentile, NetworkClient, OutCheck}};
+use crate::metrics::METRICS;
+
+// --- ROUTER UNICO PER GLI SWAP (Auto-Buy, Trade Manuali, Vendite) ---
+// 1. Quote in parallelo: Jupiter (aggregato) e Orca Whirlpool (solo pool Orca)
+// 2. Si invia la quote migliore, poi le altre in ordine se l'invio fallisce
+// 3. Raydium V4 diretto come ultima spiaggia (solo SOL -> Token)
+// Ogni tentativo finisce nella tabella executions; il fill reale viene letto dalla TX confermata.
+// Ogni swap ha un minimo in uscita dalla quote; a conferma avvenuta lo slippage realizzato viene registrato
+// e, se supera la tolleranza, l'utente riceve un avviso.
+
+const SOL_MINT: &str = "So11111111111111111111111111111111111111112";
+// Nome DEX usato dall'API Jupiter per le pool concentrate di Orca
+const ORCA_DEXES: &str = "Whirlpool";
+// Fee base per firma: il resto della fee è priority fee (TX firmate solo dal payer)
+const BASE_FEE_LAMPORTS: u64 = 5_000;
+// Percentile delle priority fee per gli swap Jupiter/Orca (il fallback Raydium usa p90: velocità pura)
+const ROUTER_FEE_PERCENTILE: FeePercentile = FeePercentile::P75;
+const CONFIRM_ATTEMPTS: u32 = 10;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Route { Jupiter, Orca, Raydium }
+
+impl Route {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Route::Jupiter => "Jupiter",
+            Route::Orca => "Orca",
+            Route::Raydium => "Raydium",
+        }
+    }
+}
+
+pub struct SwapOutcome {
+    pub signature: String,
+    pub route: Route,
+    // Quantità attesa in uscita (unità base), 0 se la route non ha quote
+    pub expected_out: u64,
+}
+
+/// Minimo accettato in uscita: stessa tolleranza per tutte le route
+pub fn min_out_with_slippage(expected_out: u64, slippage_bps: u16) -> u64 {
+    (expected_out as u128 * (10_000 - slippage_bps.min(10_000)) as u128 / 10_000) as u64
+}
+
+/// Slippage realizzato in bps rispetto alla quote (negativo = ricevuto più del previsto), None senza quote
+pub fn realized_slippage_bps(quoted_out: u64, actual_out: u64) -> Option<i64> {
+    (quoted_out > 0).then(|| ((quoted_out as i128 - actual_out as i128) * 10_000 / quoted_out as i128) as i64)
+}
+
+// Chi sta facendo lo swap (per l'Audit Log)
+struct Audit<'a> {
+    pool: &'a DbPool,
+    user_id: &'a str,
+}
+
+/// Compra `token` spendendo `amount_lamports` SOL
+pub async fn buy(net: &Arc<NetworkClient>, pool: &DbPool, user_id: &str, payer: &Keypair, token: &str, amount_lamports: u64, slippage_bps: u16) -> Result<SwapOutcome, Box<dyn Error + Send + Sync>> {
+    swap(net, &Audit { pool, user_id }, payer, SOL_MINT, token, amount_lamports, slippage_bps).await
+}
+
+/// Vende `amount` (unità base) di `token` per SOL
+pub async fn sell(net: &Arc<NetworkClient>, pool: &DbPool, user_id: &str, payer: &Keypair, token: &str, amount: u64, slippage_bps: u16) -> Result<SwapOutcome, Box<dyn Error + Send + Sync>> {
+    swap(net, &Audit { pool, user_id }, payer, token, SOL_MINT, amount, slippage_bps).await
+}
+
+/// Scambia `amount` (unità base) di `input` in `output` per coppie qualsiasi (es. USDC -> SOL nel Grid Trading)
+pub async fn swap_pair(net: &Arc<NetworkClient>, pool: &DbPool, user_id: &str, payer: &Keypair, (input, output): (&str, &str), amount: u64, slippage_bps: u16) -> Result<SwapOutcome, Box<dyn Error + Send + Sync>> {
+    swap(net, &Audit { pool, user_id }, payer, input, output, amount, slippage_bps).await
+}
+
+// --- EXACT OUT (Conversioni a importo preciso, es. "esattamente 50 USDC") ---
+// Solo Jupiter (swapMode=ExactOut): l'output è fisso, lo slippage ricade sull'input.
+// Il chiamante valida l'input massimo della quote (max_in) prima di eseguire.
+
+pub struct ExactOutQuote {
+    pub quote: serde_json::Value,
+    pub out_amount: u64,
+    // Input stimato e massimo con lo slippage (unità base del token di input)
+    pub in_amount: u64,
+    pub max_in: u64,
+}
+
+pub async fn quote_exact_out((input, output): (&str, &str), out_amount: u64, slippage_bps: u16) -> Result<ExactOutQuote, Box<dyn Error + Send + Sync>> {
+    let quote = jupiter::get_quote_exact_out(input, output, out_amount, slippage_bps).await?;
+    let in_amount = jupiter::quote_in_amount(&quote);
+    if in_amount == 0 { return Err("Quote ExactOut senza input".into()); }
+    // Senza soglia esplicita si applica lo slippage all'input stimato
+    let max_in = match jupiter::quote_threshold(&quote) {
+        0 => (in_amount as u128 * (10_000 + slippage_bps as u128) / 10_000) as u64,
+        t => t,
+    };
+    Ok(ExactOutQuote { quote, out_amount, in_amount, max_in })
+}
+
+/// Esegue una quote ExactOut già validata
+pub async fn swap_exact_out(net: &Arc<NetworkClient>, pool: &DbPool, user_id: &str, payer: &Keypair, (input, output): (&str, &str), quoted: ExactOutQuote, slippage_bps: u16) -> Result<SwapOutcome, Box<dyn Error + Send + Sync>> {
+    let side = if input == SOL_MINT { "buy" } else { "sell" };
+    let attempt = db::ExecutionAttempt {
+        user_id, side: if input == SOL_MINT { "BUY" } else { "SELL" },
+        input_mint: input, output_mint: output, amount_in: quoted.in_amount,
+        route: Route::Jupiter.label(), quoted_out: quoted.out_amount, slippage_bps,
+    };
+    let res = send_quote(net, payer, quoted.quote, output, slippage_bps).await;
+    log_attempt(net, pool, &attempt, &payer.pubkey(), &res).await;
+    METRICS.swaps.with_label_values(&[Route::Jupiter.label(), side, if res.is_ok() { "ok" } else { "error" }]).inc();
+    let signature = res?;
+    info!("🔀 Swap ExactOut via Jupiter ({} -> {}, {} out): {}", input, output, quoted.out_amount, signature);
+    // Volume in SOL: solo se SOL è da un lato della coppia
+    if input == SOL_MINT || output == SOL_MINT {
+        referral::accrue(pool, user_id, if input == SOL_MINT { quoted.in_amount } else { quoted.out_amount }, &signature);
+    }
+    Ok(SwapOutcome { signature, route: Route::Jupiter, expected_out: quoted.out_amount })
+}
+
+// Quote Jupiter e Orca in parallelo, dalla migliore alla peggiore
+async fn ranked_quotes(input: &str, output: &str, amount: u64, slippage_bps: u16) -> Vec<(Route, serde_json::Value)> {
+    let (jup, orca) = tokio::join!(
+        jupiter::get_quote(input, output, amount, slippage_bps),
+        jupiter::get_quote_on_dexes(input, output, amount, slippage_bps, Some(ORCA_DEXES))
+    );
+
+    let mut quotes: Vec<(Route, serde_json::Value)> = Vec::new();
+    if let Ok(q) = jup { quotes.push((Route::Jupiter, q)); }
+    if let Ok(q) = orca { quotes.push((Route::Orca, q)); }
+    quotes.sort_by_key(|(_, q)| Reverse(jupiter::quote_out_amount(q)));
+    quotes
+}
+
+/// Route e importo in uscita della quote migliore (Jupiter / Orca)
+pub async fn best_quote(input: &str, output: &str, amount: u64, slippage_bps: u16) -> Option<(Route, u64)> {
+    ranked_quotes(input, output, amount, slippage_bps).await
+        .first()
+        .map(|(route, q)| (*route, jupiter::quote_out_amount(q)))
+}
+
+async fn swap(net: &Arc<NetworkClient>, audit: &Audit<'_>, payer: &Keypair, input: &str, output: &str, amount: u64, slippage_bps: u16) -> Result<SwapOutcome, Box<dyn Error + Send + Sync>> {
+    let quotes = ranked_quotes(input, output, amount, slippage_bps).await;
+    let best_out = quotes.first().map(|(_, q)| jupiter::quote_out_amount(q)).unwrap_or(0);
+    let side = if input == SOL_MINT { "buy" } else { "sell" };
+    let attempt = |route: Route, quoted_out: u64| db::ExecutionAttempt {
+        user_id: audit.user_id, side: if input == SOL_MINT { "BUY" } else { "SELL" },
+        input_mint: input, output_mint: output, amount_in: amount,
+        route: route.label(), quoted_out, slippage_bps,
+    };
+
+    for (route, quote) in quotes {
+        let expected_out = jupiter::quote_out_amount(&quote);
+        let res = send_quote(net, payer, quote, output, slippage_bps).await;
+        log_attempt(net, audit.pool, &attempt(route, expected_out), &payer.pubkey(), &res).await;
+        match res {
+            Ok(signature) => {
+                METRICS.swaps.with_label_values(&[route.label(), side, "ok"]).inc();
+                info!("🔀 Swap via {} ({} -> {}): {}", route.label(), input, output, signature);
+                // Volume in SOL: speso in acquisto, atteso in vendita
+                referral::accrue(audit.pool, audit.user_id, if input == SOL_MINT { amount } else { expected_out }, &signature);
+                return Ok(SwapOutcome { signature, route, expected_out });
+            },
+            Err(e) => {
+                METRICS.swaps.with_label_values(&[route.label(), side, "error"]).inc();
+                warn!("⚠️ Route {} fallita: {}", route.label(), e);
+            }
+        }
+    }
+
+    // RAYDIUM DIRETTO: wrap nativo di SOL, quindi solo in acquisto.
+    // Il minimo in uscita deriva dalla quote migliore o, se manca, dalle riserve della pool: mai swap senza minimo.
+    if input != SOL_MINT { return Err("Nessuna route disponibile per la vendita".into()); }
+    let mint = Pubkey::from_str(output)?;
+    // Le pool AMM V4 supportano solo il programma Token classico
+    if net.get_token_program(&mint).await? != spl_token::id() {
+        return Err("Nessuna route disponibile (Token-2022 non supportato da Raydium V4)".into());
+    }
+    let (res, expected_out) = match raydium::fetch_pool_keys_by_mint(net, &mint).await {
+        Ok(keys) => {
+            let expected_out = match best_out {
+                0 => raydium::quote_buy(net, &keys, amount).await.unwrap_or(0),
+                q => q,
+            };
+            let res = match expected_out {
+                0 => Err("Nessuna quote Raydium: minimo in uscita non calcolabile, swap non inviato".into()),
+                q => raydium::execute_swap(net, payer, &keys, mint, amount, min_out_with_slippage(q, slippage_bps)).await,
+            };
+            (res, expected_out)
+        },
+        Err(e) => (Err(e), best_out),
+    };
+    log_attempt(net, audit.pool, &attempt(Route::Raydium, expected_out), &payer.pubkey(), &res).await;
+    METRICS.swaps.with_label_values(&[Route::Raydium.label(), side, if res.is_ok() { "ok" } else { "error" }]).inc();
+    let signature = res?;
+    info!("🔀 Swap via Raydium ({} -> {}): {}", input, output, signature);
+    referral::accrue(audit.pool, audit.user_id, amount, &signature);
+    Ok(SwapOutcome { signature, route: Route::Raydium, expected_out })
+}
+
+// --- MODALITÀ NON-CUSTODIAL (Wallet esterno: Phantom, Solflare...) ---
+
+// Swap da far firmare al wallet dell'utente
+pub struct UnsignedSwap {
+    pub transaction: String, // VersionedTransaction serializzata (base64)
+    pub route: Route,
+    pub expected_out: u64,
+}
+
+/// Swap NON firmato con `owner` come payer (miglior quote Jupiter / Orca, niente fallback Raydium)
+pub async fn build_unsigned(net: &Arc<NetworkClient>, owner: &Pubkey, buy: bool, token: &str, amount: u64, slippage_bps: u16) -> Result<UnsignedSwap, Box<dyn Error + Send + Sync>> {
+    let (input, output) = if buy { (SOL_MINT, token) } else { (token, SOL_MINT) };
+    let (route, quote) = ranked_quotes(input, output, amount, slippage_bps).await
+        .into_iter().next()
+        .ok_or("Nessuna quote disponibile")?;
+    let expected_out = jupiter::quote_out_amount(&quote);
+    let cu_price = net.priority_fee(ROUTER_FEE_PERCENTILE, &[]).await;
+    let tx = jupiter::get_unsigned_swap_tx(&owner.to_string(), quote, cu_price).await?;
+    Ok(UnsignedSwap { transaction: general_purpose::STANDARD.encode(bincode::serialize(&tx)?), route, expected_out })
+}
+
+/// Invia una TX già firmata dal wallet esterno: payer e firme devono essere validi
+pub async fn submit_signed(net: &Arc<NetworkClient>, owner: &Pubkey, signed_b64: &str) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let bytes = general_purpose::STANDARD.decode(signed_b64.trim())?;
+    let tx: VersionedTransaction = bincode::deserialize(&bytes)?;
+    if tx.message.static_account_keys().first() != Some(owner) { return Err("La TX non è pagata dal wallet collegato".into()); }
+    let checks = tx.verify_with_results();
+    if checks.is_empty() || !checks.iter().all(|ok| *ok) { return Err("Firma non valida".into()); }
+
+    let _t = METRICS.rpc_timer("sendTransaction");
+    let sig = net.rpc.send_transaction(&tx).await?;
+    info!("🔀 TX esterna inviata ({}): {}", owner, sig);
+    Ok(sig.to_string())
+}
+
+/// Firma e invia lo swap della quote, dopo la simulazione: `output` deve ricevere almeno il minimo con slippage
+async fn send_quote(net: &Arc<NetworkClient>, payer: &Keypair, quote: serde_json::Value, output: &str, slippage_bps: u16) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let min_out = min_out_with_slippage(jupiter::quote_out_amount(&quote), slippage_bps);
+    let cu_price = net.priority_fee(ROUTER_FEE_PERCENTILE, &[]).await;
+    let unsigned = jupiter::get_unsigned_swap_tx(&payer.pubkey().to_string(), quote, cu_price).await?;
+    let tx = sign_swap_tx(unsigned, payer)?;
+
+    let owner = payer.pubkey();
+    let check = if output == SOL_MINT {
+        OutCheck { account: owner, is_token: false, pre_amount: net.get_balance_fast(&owner).await, min_out }
+    } else {
+        let mint = Pubkey::from_str(output)?;
+        OutCheck { account: net.get_token_ata(&owner, &mint).await?, is_token: true, pre_amount: net.get_token_balance(&owner, &mint).await?, min_out }
+    };
+    let sig = net.simulate_and_send(&tx, Some(&check)).await?;
+    Ok(sig.to_string())
+}
+
+/// Registra lo slippage realizzato; oltre la tolleranza dello swap segna l'esecuzione e avvisa l'utente
+async fn check_slippage(pool: &DbPool, id: i64, sig: &str, quoted: &QuotedFill, actual_out: u64) {
+    let Some(bps) = realized_slippage_bps(quoted.quoted_out, actual_out) else { return };
+    METRICS.swap_slippage.with_label_values(&[&quoted.side.to_lowercase()]).observe(bps as f64);
+    let exceeded = bps > quoted.slippage_bps as i64;
+    if let Err(e) = db::record_execution_slippage(pool, id, bps, exceeded).await {
+        warn!("⚠️ Slippage non salvato ({}): {}", sig, e);
+    }
+    if !exceeded { return; }
+
+    METRICS.swap_slippage_exceeded.with_label_values(&[&quoted.route]).inc();
+    warn!("⚠️ Slippage {} bps oltre la tolleranza ({} bps) per {} via {}: {}", bps, quoted.slippage_bps, quoted.user_id, quoted.route, sig);
+    let lang = i18n::user_lang(pool, &quoted.user_id).await;
+    telegram_bot::notify_user(&quoted.user_id, i18n::tr(lang, "alert.slippage", &[
+        &quoted.side, &quoted.route, &format!("{:.2}", bps as f64 / 100.0), &format!("{:.2}", quoted.slippage_bps as f64 / 100.0), &sig,
+    ])).await;
+}
+
+// --- FIRMA DELLE TX JUPITER (V0 con Address Lookup Table) ---
+// 1. Messaggio, blockhash e lookup table arrivano da Jupiter e non si toccano: cambiarli invalida le firme già presenti
+// 2. Il payer firma solo il proprio slot tra i firmatari richiesti (header), le altre firme restano come sono
+// 3. Payer diverso dal fee payer o firme richieste ancora vuote (TX multi-firmatario incompleta): errore, nessun invio
+
+fn sign_swap_tx(unsigned: VersionedTransaction, payer: &Keypair) -> Result<VersionedTransaction, Box<dyn Error + Send + Sync>> {
+    let owner = payer.pubkey();
+    let required = unsigned.message.header().num_required_signatures as usize;
+    let signers: Vec<Pubkey> = unsigned.message.static_account_keys().iter().take(required).copied().collect();
+    if signers.first() != Some(&owner) { return Err("La TX di Jupiter non è pagata dal wallet".into()); }
+
+    let mut signatures = unsigned.signatures;
+    signatures.resize(required, Signature::default());
+    let own = payer.sign_message(&unsigned.message.serialize());
+    for (slot, key) in signatures.iter_mut().zip(&signers) {
+        if *key == owner {
+            *slot = own;
+        } else if *slot == Signature::default() {
+            return Err(format!("Firma mancante per {}: TX multi-firmatario non supportata", key).into());
+        }
+    }
+
+    let tx = VersionedTransaction { signatures, message: unsigned.message };
+    if !tx.verify_with_results().iter().all(|ok| *ok) { return Err("Firme della TX di swap non valide".into()); }
+    Ok(tx)
+}
+
+// --- AUDIT LOG ---
+
+/// Registra il tentativo; se la TX è partita, il fill reale viene verificato in background
+async fn log_attempt(net: &Arc<NetworkClient>, pool: &DbPool, attempt: &db::ExecutionAttempt<'_>, payer: &Pubkey, res: &Result<String, Box<dyn Error + Send + Sync>>) {
+    let (signature, error) = match res {
+        Ok(sig) => (Some(sig.as_str()), None),
+        Err(e) => (None, Some(e.to_string())),
+    };
+    let id = match db::record_execution(pool, attempt, signature, error.as_deref()).await {
+        Ok(id) => id,
+        Err(e) => { warn!("⚠️ Audit swap non salvato: {}", e); return; }
+    };
+
+    if let Some(sig) = signature {
+        let (net, pool, payer) = (net.clone(), pool.clone(), *payer);
+        let (sig, output) = (sig.to_string(), attempt.output_mint.to_string());
+        let quoted = QuotedFill {
+            user_id: attempt.user_id.to_string(), side: attempt.side.to_string(), route: attempt.route.to_string(),
+            quoted_out: attempt.quoted_out, slippage_bps: attempt.slippage_bps,
+        };
+        tokio::spawn(async move { reconcile_execution(&net, &pool, id, &sig, &payer, &output, &quoted).await; });
+    }
+}
+
+// Quote dello swap inviato, confrontata col fill reale dopo la conferma
+struct QuotedFill {
+    user_id: String,
+    side: String,
+    route: String,
+    quoted_out: u64,
+    slippage_bps: u16,
+}
+
+// Saldo di un token account del payer nella TX confermata
+struct OwnedTokenBalance {
+    account_index: u8,
+    mint: String,
+    amount: u64,
+    decimals: u8,
+}
+
+fn owned_token_balances(balances: &OptionSerializer<Vec<UiTransactionTokenBalance>>, owner: &str) -> Vec<OwnedTokenBalance> {
+    match balances {
+        OptionSerializer::Some(list) => list.iter()
+            .filter(|b| matches!(&b.owner, OptionSerializer::Some(o) if o == owner))
+            .map(|b| OwnedTokenBalance {
+                account_index: b.account_index,
+                mint: b.mint.clone(),
+                amount: b.ui_token_amount.amount.parse().unwrap_or(0),
+                decimals: b.ui_token_amount.decimals,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Legge la TX confermata: quantità ricevuta (post - pre balances), fee ed eventuale errore on-chain;
+/// per gli acquisti anche i SOL spesi, da cui settle_buy ricava il prezzo d'ingresso reale
+async fn reconcile_execution(net: &Arc<NetworkClient>, pool: &DbPool, id: i64, sig: &str, payer: &Pubkey, output: &str, quoted: &QuotedFill) {
+    let signature = match Signature::from_str(sig) { Ok(s) => s, Err(_) => return };
+    let config = RpcTransactionConfig {
+        encoding: Some(UiTransactionEncoding::Json),
+        commitment: Some(CommitmentConfig::confirmed()),
+        max_supported_transaction_version: Some(0),
+    };
+
+    for _ in 0..CONFIRM_ATTEMPTS {
+        sleep(Duration::from_secs(3)).await;
+        let meta = match net.rpc.get_transaction_with_config(&signature, config).await {
+            Ok(tx) => match tx.transaction.meta { Some(m) => m, None => continue },
+            Err(_) => continue,
+        };
+
+        let fee = meta.fee;
+        let priority_fee = fee.saturating_sub(BASE_FEE_LAMPORTS);
+        if let Some(err) = meta.err {
+            let _ = db::finish_execution(pool, id, "FAILED", None, Some(fee), Some(priority_fee), Some(&err.to_string())).await;
+            return;
+        }
+
+        // SOL nativo del payer (indice 0)
+        let native_pre = meta.pre_balances.first().copied().unwrap_or(0) as i128;
+        let native_post = meta.post_balances.first().copied().unwrap_or(0) as i128;
+        let (actual_out, fill) = if output == SOL_MINT {
+            // Vendita: la fee va riaggiunta
+            ((native_post - native_pre + fee as i128).max(0) as u64, None)
+        } else {
+            let owner = payer.to_string();
+            let pre_tokens = owned_token_balances(&meta.pre_token_balances, &owner);
+            let post_tokens = owned_token_balances(&meta.post_token_balances, &owner);
+            let amount = |list: &[OwnedTokenBalance]| -> u64 { list.iter().filter(|b| b.mint == output).map(|b| b.amount).sum() };
+            let received = amount(&post_tokens).saturating_sub(amount(&pre_tokens));
+            // Rent dei token account creati dalla TX (ATA del token): resta nel wallet come deposito, non è prezzo pagato
+            let rent: u64 = post_tokens.iter()
+                .filter(|b| !pre_tokens.iter().any(|p| p.account_index == b.account_index))
+                .map(|b| meta.post_balances.get(b.account_index as usize).copied().unwrap_or(0))
+                .sum();
+            let spent = (native_pre - native_post - fee as i128 - rent as i128).max(0) as u64;
+            let decimals = post_tokens.iter().find(|b| b.mint == output).map(|b| b.decimals);
+            (received, decimals.map(|d| (spent, d)))
+        };
+
+        let _ = db::finish_execution(pool, id, "CONFIRMED", Some(actual_out), Some(fee), Some(priority_fee), None).await;
+        check_slippage(pool, id, sig, quoted, actual_out).await;
+        if output == SOL_MINT {
+            // Vendita: PnL realizzato dei trade chiusi con i SOL effettivamente ricevuti
+            if let Err(e) = db::settle_sell(pool, sig).await { warn!("⚠️ PnL realizzato non aggiornato ({}): {}", sig, e); }
+        } else if let Some((spent, decimals)) = fill {
+            // Acquisto: prezzo d'ingresso reale dei trade aperti da questa TX
+            if let Err(e) = db::record_buy_fill(pool, id, spent, decimals).await { warn!("⚠️ Fill d'acquisto non salvato ({}): {}", sig, e); }
+            if let Err(e) = db::settle_buy(pool, sig).await { warn!("⚠️ Prezzo d'ingresso non aggiornato ({}): {}", sig, e); }
+        }
+        return;
+    }
+    let _ = db::finish_execution(pool, id, "UNCONFIRMED", None, None, None, Some("TX non trovata dopo 30s")).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::hash::Hash;
+    use solana_sdk::message::{v0, VersionedMessage};
+    use solana_sdk::system_instruction;
+
+    // TX V0 come arriva da Jupiter: messaggio compilato, slot delle firme vuoti
+    fn unsigned_v0(payer: &Pubkey, ixs: &[solana_sdk::instruction::Instruction]) -> VersionedTransaction {
+        let message = VersionedMessage::V0(v0::Message::try_compile(payer, ixs, &[], Hash::new_unique()).unwrap());
+        let required = message.header().num_required_signatures as usize;
+        VersionedTransaction { signatures: vec![Signature::default(); required], message }
+    }
+
+    #[test]
+    fn signs_v0_with_single_signer() {
+        let payer = Keypair::new();
+        let tx = unsigned_v0(&payer.pubkey(), &[system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 1)]);
+        let signed = sign_swap_tx(tx, &payer).unwrap();
+        assert_eq!(signed.signatures.len(), 1);
+        assert_ne!(signed.signatures[0], Signature::default());
+        assert!(signed.verify_with_results().iter().all(|ok| *ok));
+    }
+
+    #[test]
+    fn rejects_payer_that_is_not_fee_payer() {
+        let (fee_payer, other) = (Keypair::new(), Keypair::new());
+        let tx = unsigned_v0(&fee_payer.pubkey(), &[system_instruction::transfer(&fee_payer.pubkey(), &Pubkey::new_unique(), 1)]);
+        assert!(sign_swap_tx(tx, &other).is_err());
+    }
+
+    #[test]
+    fn preserves_signature_slot_count() {
+        let (payer, cosigner) = (Keypair::new(), Keypair::new());
+        let ixs = [
+            system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 1),
+            system_instruction::transfer(&cosigner.pubkey(), &Pubkey::new_unique(), 1),
+        ];
+        // Firma del secondo firmatario mancante: errore, nessuna TX incompleta
+        assert!(sign_swap_tx(unsigned_v0(&payer.pubkey(), &ixs), &payer).is_err());
+
+        // Già firmata dal secondo firmatario: il payer riempie solo il proprio slot
+        let mut tx = unsigned_v0(&payer.pubkey(), &ixs);
+        let cosigned = cosigner.sign_message(&tx.message.serialize());
+        tx.signatures[1] = cosigned;
+        let signed = sign_swap_tx(tx, &payer).unwrap();
+        assert_eq!(signed.signatures.len(), 2);
+        assert_eq!(signed.signatures[1], cosigned);
+        assert!(signed.verify_with_results().iter().all(|ok| *ok));
+
+        // Slot assenti nella TX ricevuta: riportati al numero di firmatari richiesti
+        let mut tx = unsigned_v0(&payer.pubkey(), &ixs[..1]);
+        tx.signatures.clear();
+        assert_eq!(sign_swap_tx(tx, &payer).unwrap().signatures.len(), 1);
+    }
+}
@@ -0,0 +1,92 @@
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Instant;
+use log::{error, info, warn};
+use tokio::task::JoinHandle;
+use tokio::time::Duration;
+use crate::{db, metrics, network, telegram_bot, AppState};
+
+// --- TASK DEL MOTORE (Loop in background avviati da main) ---
+// 1. Ogni loop vive nel proprio modulo ed espone `run(ctx)`: Context porta DB, rete e stato condiviso
+// 2. I loop degli altri moduli (API, Telegram, rischio...) partono con lo stesso Context tramite closure
+// 3. `supervise` avvia il task e lo riavvia se va in panic (log, metrica task_panics_total, avviso all'admin),
+//    con attesa esponenziale tra i riavvii; un'uscita normale lo chiude (shutdown o modulo non configurato)
+// Le operazioni di trading usate dai task (acquisti, vendite, chiusure) sono in trading.rs.
+
+pub mod strategy;
+pub mod sniper;
+pub mod positions;
+pub mod sell_retry;
+pub mod dca;
+pub mod limit_orders;
+pub mod reports;
+
+// Attesa prima del riavvio dopo un panic: raddoppia a ogni panic di fila fino al massimo
+const RESTART_BACKOFF_BASE_SECS: u64 = 2;
+const RESTART_BACKOFF_MAX_SECS: u64 = 300;
+// Un task rimasto in piedi almeno così a lungo riparte dall'attesa minima
+const RESTART_STABLE_SECS: u64 = 600;
+
+// Dipendenze condivise da tutti i task (clonare costa solo i contatori degli Arc)
+#[derive(Clone)]
+pub struct Context {
+    pub pool: db::DbPool,
+    pub net: Arc<network::NetworkClient>,
+    pub state: Arc<AppState>,
+}
+
+impl Context {
+    pub fn new(pool: db::DbPool, net: Arc<network::NetworkClient>, state: Arc<AppState>) -> Self {
+        Self { pool, net, state }
+    }
+}
+
+/// Avvia `task` e lo riavvia dopo un panic finché non parte lo shutdown.
+/// L'handle si risolve quando il task termina normalmente (o allo shutdown).
+pub fn supervise<F, Fut>(name: &'static str, ctx: Context, task: F) -> JoinHandle<()>
+where
+    F: Fn(Context) -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    tokio::spawn(async move {
+        // Panic di fila (backoff)
+        let mut attempt: u32 = 0;
+        loop {
+            let started = Instant::now();
+            match tokio::spawn(task(ctx.clone())).await {
+                Ok(()) => {
+                    if !ctx.state.is_shutting_down() { warn!("⚠️ Task {} terminato senza shutdown: non viene riavviato", name); }
+                    break;
+                },
+                Err(e) if e.is_panic() => {
+                    let reason = panic_message(e.into_panic());
+                    metrics::METRICS.task_panics.with_label_values(&[name]).inc();
+                    if started.elapsed() >= Duration::from_secs(RESTART_STABLE_SECS) { attempt = 0; }
+                    let delay = restart_backoff(attempt);
+                    attempt = attempt.saturating_add(1);
+                    error!("💥 Task {} andato in panic (#{} di fila): {} -> riavvio tra {}s", name, attempt, reason, delay.as_secs());
+                    // In background: una chat Telegram lenta non deve ritardare il riavvio
+                    tokio::spawn(telegram_bot::notify_admin(format!(
+                        "💥 <b>Task {} andato in panic</b> (#{} di fila)\n<code>{}</code>\nRiavvio tra {}s",
+                        name, attempt, teloxide::utils::html::escape(&reason), delay.as_secs(),
+                    )));
+                    if ctx.state.sleep_or_shutdown(delay).await { break; }
+                    info!("♻️ Riavvio del task {}", name);
+                },
+                Err(_) => break,
+            }
+        }
+    })
+}
+
+/// Attesa prima del riavvio numero `attempt` (0 = primo panic)
+fn restart_backoff(attempt: u32) -> Duration {
+    Duration::from_secs(RESTART_BACKOFF_BASE_SECS.saturating_mul(1u64 << attempt.min(16)).min(RESTART_BACKOFF_MAX_SECS))
+}
+
+// Testo del panic (&str o String), altrimenti un segnaposto
+fn panic_message(payload: Box<dyn std::any::Any + Send>) -> String {
+    payload.downcast_ref::<&str>().map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "panic senza messaggio".into())
+}
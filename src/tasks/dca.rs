@@ -0,0 +1,54 @@
+use log::{info, warn};
+use tokio::time::Duration;
+use crate::{activity, db, notifications, trade_alerts, trading, LiveEvent};
+use super::Context;
+
+// --- DCA SCHEDULER (Acquisti ricorrenti) ---
+// I fill DCA non aprono trade: l'accumulo non deve finire sotto il Trailing Stop.
+pub async fn run(ctx: Context) {
+    let Context { pool, net, state } = ctx;
+    info!("🔁 DCA Scheduler: ONLINE");
+
+    loop {
+        let now = chrono::Utc::now().timestamp();
+        let due = match db::get_due_dca_orders(&pool, now).await {
+            Ok(orders) => orders,
+            Err(e) => {
+                warn!("⚠️ DCA: lettura ordini fallita: {}", e);
+                if state.sleep_or_shutdown(Duration::from_secs(30)).await { break; }
+                continue;
+            }
+        };
+
+        for order in due {
+            if state.is_shutting_down() { break; }
+            let next_run = now + order.interval_secs;
+            let params = db::get_strategy_params(&pool, &order.user_id).await;
+
+            match trading::swap_sol_for_token(&pool, &net, &order.user_id, &order.token, order.amount_lamports, params.slippage_bps).await {
+                Ok((sig, route)) => {
+                    info!("🔁 DCA #{} ({}) -> {} via {} TX: {}", order.id, order.user_id, order.token, route, sig);
+                    let _ = db::record_dca_fill(&pool, order.id, order.amount_lamports, next_run).await;
+                    let amount_sol = order.amount_lamports as f64 / 1_000_000_000.0;
+                    activity::record(&pool, &order.user_id, activity::Event::dca_fill(order.id, &order.token, amount_sol, &sig)).await;
+                    trade_alerts::notify(&pool, &net, &order.user_id, notifications::Notification::Buy {
+                        token: order.token.clone(), amount_sol, route: format!("DCA #{} ({})", order.id, route), tx_signature: sig.clone(),
+                    });
+                    state.publish(LiveEvent::DcaFill {
+                        user_id: order.user_id.clone(), order_id: order.id, token: order.token.clone(),
+                        amount_sol, tx_signature: sig,
+                    });
+                },
+                Err(e) => {
+                    // Niente retry a raffica: si riprova al prossimo intervallo
+                    warn!("⚠️ DCA #{} ({}) fallito: {}", order.id, order.user_id, e);
+                    let _ = db::reschedule_dca_order(&pool, order.id, next_run).await;
+                    activity::record(&pool, &order.user_id, activity::Event::error(Some(&order.token), format!("DCA #{} fallito: {}", order.id, e))).await;
+                }
+            }
+        }
+        if state.sleep_or_shutdown(Duration::from_secs(30)).await { break; }
+    }
+    info!("🛑 DCA Scheduler fermato.");
+}
+
@@ -0,0 +1,60 @@
+use log::{info, warn};
+use std::collections::HashMap;
+use tokio::time::Duration;
+use crate::{activity, db, i18n, jupiter, telegram_bot, trading};
+use super::Context;
+
+// --- LIMIT ORDER WATCHER (Buy/Sell al prezzo target) ---
+pub async fn run(ctx: Context) {
+    let Context { pool, net, state } = ctx;
+    info!("🎯 Limit Order Watcher: ONLINE");
+
+    loop {
+        let orders = db::get_active_limit_orders(&pool).await.unwrap_or_default();
+
+        // Un solo prezzo per token a giro
+        let mut prices: HashMap<String, f64> = HashMap::new();
+        for order in orders {
+            if state.is_shutting_down() { break; }
+            let price = match prices.get(&order.token) {
+                Some(p) => *p,
+                None => {
+                    let p = jupiter::get_token_market_data(&order.token).await.map(|m| m.price).unwrap_or(0.0);
+                    prices.insert(order.token.clone(), p);
+                    p
+                }
+            };
+            if price <= 0.0 { continue; }
+
+            let crossed = if order.trigger_above { price >= order.trigger_price } else { price <= order.trigger_price };
+            if !crossed { continue; }
+            if !db::claim_limit_order(&pool, order.id).await.unwrap_or(false) { continue; }
+
+            info!("🎯 LIMIT {} #{} ({}) {} @ ${} (target ${})", order.side, order.id, order.user_id, order.token, price, order.trigger_price);
+            let res = if order.side == "BUY" {
+                let params = db::get_strategy_params(&pool, &order.user_id).await;
+                trading::execute_buy(&pool, &net, &order.user_id, &order.token, order.amount_lamports, params.slippage_bps).await.map(|(sig, _)| sig)
+            } else {
+                trading::sell_position_now(&pool, &net, &state, &order.user_id, &order.token).await.map(|(sig, _)| sig)
+            };
+
+            let lang = i18n::user_lang(&pool, &order.user_id).await;
+            let text = match res {
+                Ok(sig) => {
+                    let _ = db::finish_limit_order(&pool, order.id, "FILLED", Some(&sig), price).await;
+                    i18n::tr(lang, "notify.limit_filled", &[&order.id, &order.side, &order.token, &price, &sig])
+                },
+                Err(e) => {
+                    warn!("⚠️ LIMIT #{} fallito: {}", order.id, e);
+                    let _ = db::finish_limit_order(&pool, order.id, "FAILED", None, price).await;
+                    activity::record(&pool, &order.user_id, activity::Event::error(Some(&order.token), format!("Limit {} #{} fallito: {}", order.side, order.id, e))).await;
+                    i18n::tr(lang, "notify.limit_failed", &[&order.id, &order.side, &order.token, &e])
+                }
+            };
+            telegram_bot::notify_user(&order.user_id, text).await;
+        }
+        if state.sleep_or_shutdown(Duration::from_secs(10)).await { break; }
+    }
+    info!("🛑 Limit Order Watcher fermato.");
+}
+
@@ -0,0 +1,279 @@
+use log::{info, warn, debug};
+use tracing::Instrument;
+use futures::StreamExt;
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::sync::Arc;
+use tokio::time::{sleep, Duration};
+use crate::chain::ChainClient;
+use crate::{activity, auto_park, birdeye, db, jupiter, metrics, network, notifications, strategy, trade_alerts, trading, AppState, LiveEvent, OpenPosition, DEFAULT_WATCHLIST};
+use super::sell_retry::SELL_RETRY_BASE_SECS;
+use super::Context;
+
+// --- PREZZI DEL GIRO (Birdeye multi_price, DexScreener solo per i mancanti) ---
+async fn refresh_cycle_prices(pool: &db::DbPool, state: &Arc<AppState>, open_trades: &[db::OpenTrade]) -> HashMap<String, f64> {
+    let mut held: Vec<String> = open_trades.iter().map(|t| t.2.clone()).collect();
+    held.extend(state.positions_snapshot(None).into_iter().map(|p| p.token));
+    held.sort();
+    held.dedup();
+    let mut tokens = held.clone();
+    tokens.extend(DEFAULT_WATCHLIST.iter().map(|t| t.to_string()));
+    tokens.extend(db::get_watched_tokens(pool).await.unwrap_or_default());
+    tokens.sort();
+    tokens.dedup();
+
+    let mut prices = match birdeye::get_multi_price(&tokens).await {
+        Ok(p) => p,
+        Err(e) => { debug!("Birdeye multi_price non disponibile: {}", e); HashMap::new() }
+    };
+    // Fallback solo per i token in posizione: la watchlist senza Birdeye resta alla Market Strategy
+    let mut fallbacks = 0;
+    for token in &held {
+        if prices.contains_key(token) { continue; }
+        if let Ok(m) = jupiter::get_token_market_data(token).await {
+            if m.price > 0.0 { prices.insert(token.clone(), m.price); }
+        }
+        fallbacks += 1;
+    }
+    if fallbacks > 0 { debug!("💲 Prezzi: {} mancanti su Birdeye, recuperati via DexScreener", fallbacks); }
+
+    state.spot_prices.retain(|t, _| prices.contains_key(t));
+    for (token, price) in &prices { state.spot_prices.insert(token.clone(), *price); }
+    prices
+}
+
+// --- ATR DELLE POSIZIONI (Stop atr/hybrid, candele Birdeye ricalcolate ogni ATR_TTL_SECS) ---
+const ATR_INTERVAL: &str = "5m";
+const ATR_TTL_SECS: i64 = 300;
+
+async fn position_atr_pct(state: &Arc<AppState>, token: &str) -> Option<f64> {
+    let now = chrono::Utc::now().timestamp();
+    if let Some((atr, at)) = state.token_atr.get(token).map(|e| *e.value()) {
+        if now - at < ATR_TTL_SECS { return atr; }
+    }
+    let secs = birdeye::interval_secs(ATR_INTERVAL).unwrap_or(300);
+    let atr = match birdeye::get_ohlcv(token, ATR_INTERVAL, now - secs * 30, now).await {
+        Ok(bars) => strategy::atr_pct(&bars.iter().map(|c| strategy::Candle { high: c.high, low: c.low, close: c.close, volume: c.volume }).collect()),
+        Err(e) => { debug!("ATR {} non disponibile: {}", token, e); None }
+    };
+    state.token_atr.insert(token.to_string(), (atr, now));
+    atr
+}
+
+// --- POSITION MANAGER (Trailing Stop persistente) ---
+
+/// Uscita della posizione al valore attuale: stop manuale o break-even, take profit, durata massima, poi trailing stop
+pub fn exit_action(pos: &OpenPosition, params: &strategy::StrategyParams, current_val: u64, expired: bool, atr: Option<f64>) -> strategy::TradeAction {
+    // TP manuale della posizione, altrimenti quello fisso della strategia
+    let take_profit = if pos.take_profit_lamports > 0 {
+        pos.take_profit_lamports
+    } else if params.take_profit_pct > 0.0 {
+        (pos.amount_in_lamports as f64 * (1.0 + params.take_profit_pct / 100.0)) as u64
+    } else { 0 };
+
+    if pos.stop_floor_lamports > 0 && current_val <= pos.stop_floor_lamports {
+        let label = if pos.stop_floor_lamports == pos.amount_in_lamports { "🛡️ Stop Break-even" } else { "🛑 Stop Loss Manuale" };
+        strategy::TradeAction::Sell(label.into())
+    } else if take_profit > 0 && current_val >= take_profit {
+        strategy::TradeAction::Sell("🎯 Take Profit".into())
+    } else if expired {
+        let hours = params.max_hold_hours.get(&pos.strategy).copied().unwrap_or(0.0);
+        strategy::TradeAction::Sell(format!("⏰ Durata massima ({}h)", hours))
+    } else {
+        // Trailing spento: il massimo si aggiorna comunque (serve se viene riacceso)
+        match strategy::check_position(current_val, pos.highest_value_lamports, params, atr) {
+            strategy::TradeAction::Sell(_) if pos.trailing_disabled => strategy::TradeAction::Hold,
+            a => a,
+        }
+    }
+}
+
+// Utenti gestiti in parallelo dal Position Manager (POSITION_WORKERS)
+const DEFAULT_POSITION_WORKERS: usize = 8;
+
+pub async fn run(ctx: Context) {
+    let Context { pool, net, state } = ctx;
+    let workers = env::var("POSITION_WORKERS").ok().and_then(|v| v.parse::<usize>().ok()).filter(|w| *w > 0).unwrap_or(DEFAULT_POSITION_WORKERS);
+    info!("🛡️ Position Manager: {} utenti in parallelo", workers);
+    loop {
+        // 0. Tutti i prezzi del giro in una chiamata
+        let open_trades = db::get_open_trades(&pool).await.unwrap_or_default();
+        let prices = refresh_cycle_prices(&pool, &state, &open_trades).await;
+
+        // 1. Aggancia i trade OPEN non ancora tracciati (Auto-Buy, API, Telegram)
+        track_open_trades(&pool, &state, open_trades, &prices).await;
+
+        // 2. Trailing Stop su ogni posizione (con i parametri dell'utente)
+        // I token con una vendita in coda sono gestiti dal Sell Retry Queue
+        let queued: HashSet<(String, String)> = db::get_queued_sells(&pool).await.unwrap_or_default().into_iter().collect();
+        let positions = state.positions_snapshot(None);
+        state.token_atr.retain(|t, _| positions.iter().any(|p| &p.token == t));
+        let cycle = metrics::METRICS.position_cycle.start_timer();
+        let cycle_start = std::time::Instant::now();
+
+        // Un gruppo per utente: le posizioni dello stesso utente restano in sequenza (parametri e vendite),
+        // utenti diversi procedono in parallelo fino a POSITION_WORKERS
+        let mut by_user: HashMap<String, Vec<OpenPosition>> = HashMap::new();
+        for pos in positions {
+            by_user.entry(pos.user_id.clone()).or_default().push(pos);
+        }
+        let (pool_ref, net_ref, state_ref, prices_ref, queued_ref) = (&pool, &net, &state, &prices, &queued);
+        futures::stream::iter(by_user).for_each_concurrent(workers, |(user_id, positions)| async move {
+            let params = db::get_strategy_params(pool_ref, &user_id).await;
+            for pos in positions {
+                if state_ref.is_shutting_down() { break; }
+                let price = match prices_ref.get(&pos.token) {
+                    Some(&p) => p,
+                    None => continue,
+                };
+                let span = tracing::info_span!("position", user_id = %pos.user_id, token = %pos.token, trade_id = pos.trade_id);
+                manage_position(pool_ref, net_ref, state_ref, pos, &params, price, queued_ref).instrument(span).await;
+                metrics::METRICS.position_check.observe(cycle_start.elapsed().as_secs_f64());
+                sleep(Duration::from_millis(200)).await;
+            }
+        }).await;
+        cycle.observe_duration();
+
+        if state.sleep_or_shutdown(Duration::from_secs(5)).await { break; }
+    }
+    info!("🛑 Position Manager fermato.");
+}
+
+/// Aggancia i trade OPEN non ancora tracciati come posizioni (DB e RAM).
+/// Prezzo d'ingresso: fill reale della TX confermata; finché non arriva, il prezzo di mercato del giro
+pub async fn track_open_trades(pool: &db::DbPool, state: &Arc<AppState>, open_trades: Vec<db::OpenTrade>, prices: &HashMap<String, f64>) {
+    for (trade_id, user_id, token, amount_in, strategy, fill_price) in open_trades {
+        if let Some(entry) = state.open_positions.get(&trade_id).map(|p| p.entry_price) {
+            if let Some(fill) = fill_price.filter(|f| *f != entry) { apply_entry_fill(pool, state, trade_id, entry, fill).await; }
+            continue;
+        }
+
+        if let Some(price) = fill_price.or_else(|| prices.get(&token).copied()) {
+            let pos = OpenPosition {
+                trade_id, user_id, token, amount_in_lamports: amount_in,
+                entry_price: price, highest_value_lamports: amount_in,
+                opened_at: chrono::Utc::now().timestamp(), stop_floor_lamports: 0,
+                take_profit_lamports: 0, trailing_disabled: false, strategy,
+            };
+            // Write-Through: prima il DB, poi la RAM
+            if db::save_position(pool, &pos).await.is_ok() {
+                state.open_positions.insert(trade_id, pos.clone());
+                state.publish(LiveEvent::Position(pos));
+            }
+        }
+    }
+}
+
+// Posizione agganciata al prezzo di mercato, fill on-chain arrivato dopo: si corregge l'ingresso.
+// Il massimo del trailing era calcolato sul vecchio ingresso: si riscala (mai sotto l'investito)
+async fn apply_entry_fill(pool: &db::DbPool, state: &Arc<AppState>, trade_id: i64, market_entry: f64, fill: f64) {
+    let pos = state.open_positions.get_mut(&trade_id).map(|mut p| {
+        p.entry_price = fill;
+        p.highest_value_lamports = ((p.highest_value_lamports as f64 * market_entry / fill) as u64).max(p.amount_in_lamports);
+        p.clone()
+    });
+    let Some(pos) = pos else { return };
+    info!("🧾 Trade #{}: ingresso dal fill on-chain {:.10} (stima di mercato {:.10})", trade_id, fill, market_entry);
+    if let Err(e) = db::save_position(pool, &pos).await { warn!("⚠️ Posizione #{} non salvata: {}", trade_id, e); }
+    state.publish(LiveEvent::Position(pos));
+}
+
+// Un giro del Position Manager su una posizione: ATR (rete), position_tick, poi auto-park e notifica della vendita
+async fn manage_position(
+    pool: &db::DbPool,
+    net: &Arc<network::NetworkClient>,
+    state: &Arc<AppState>,
+    pos: OpenPosition,
+    params: &strategy::StrategyParams,
+    price: f64,
+    queued: &HashSet<(String, String)>
+) {
+    let atr = if params.exit_mode == "percent" { None } else { position_atr_pct(state, &pos.token).await };
+    let (user_id, token) = (pos.user_id.clone(), pos.token.clone());
+    if let PositionTick::Sold { reason, signature, pnl_sol, entry_price, hold_secs, invested } = position_tick(pool, net, state, pos, params, price, atr, queued).await {
+        auto_park::park_profit(pool, net, &user_id, invested, pnl_sol);
+        trade_alerts::notify(pool, net, &user_id, notifications::Notification::StopOut {
+            token, pnl_sol, reason, tx_signature: signature,
+            entry_price, exit_price: price, hold_secs,
+        });
+    }
+}
+
+/// Esito di un giro su una posizione
+pub enum PositionTick {
+    Hold,
+    // Nuovo massimo del trailing (DB e RAM)
+    UpdatedHigh,
+    // Venduta: i trade aperti sul token sono chiusi nel DB e tolti dalla RAM
+    Sold { reason: String, signature: String, pnl_sol: f64, entry_price: f64, hold_secs: i64, invested: u64 },
+    // Vendita fallita: il token passa al Sell Retry Queue
+    SellFailed,
+}
+
+/// Un giro su una posizione al prezzo `price`: break-even, uscite (stop, TP, durata, trailing) e vendita sul ChainClient.
+/// Usato dal Position Manager e dallo stress test (catena simulata); le notifiche restano al chiamante.
+#[allow(clippy::too_many_arguments)]
+pub async fn position_tick(
+    pool: &db::DbPool,
+    chain: &dyn ChainClient,
+    state: &Arc<AppState>,
+    mut pos: OpenPosition,
+    params: &strategy::StrategyParams,
+    price: f64,
+    atr: Option<f64>,
+    queued: &HashSet<(String, String)>
+) -> PositionTick {
+    let current_val = (pos.amount_in_lamports as f64 * price / pos.entry_price) as u64;
+    let expired = pos.hold_remaining_secs(params, chrono::Utc::now().timestamp()) == Some(0);
+    let invested = pos.amount_in_lamports as f64;
+
+    // Break-even automatico: oltre break_even_after_pct lo stop sale all'investito (come il bottone 🛡️)
+    if params.break_even_after_pct > 0.0 && pos.stop_floor_lamports < pos.amount_in_lamports
+        && current_val as f64 >= invested * (1.0 + params.break_even_after_pct / 100.0)
+        && db::update_position_exits(pool, pos.trade_id, pos.amount_in_lamports, pos.take_profit_lamports, pos.trailing_disabled).await.is_ok() {
+        info!("🛡️ Break-even automatico ({}) {} oltre +{}%", pos.user_id, pos.token, params.break_even_after_pct);
+        pos.stop_floor_lamports = pos.amount_in_lamports;
+        let updated = state.open_positions.get_mut(&pos.trade_id).map(|mut p| {
+            p.stop_floor_lamports = pos.amount_in_lamports;
+            p.value().clone()
+        });
+        if let Some(p) = updated { state.publish(LiveEvent::Position(p)); }
+    }
+    let action = exit_action(&pos, params, current_val, expired, atr);
+
+    match action {
+        strategy::TradeAction::UpdateHigh(new_high) => {
+            if db::update_position_high(pool, pos.trade_id, new_high).await.is_err() { return PositionTick::Hold; }
+            let updated = state.open_positions.get_mut(&pos.trade_id).map(|mut p| {
+                p.highest_value_lamports = new_high;
+                p.value().clone()
+            });
+            if let Some(p) = updated { state.publish(LiveEvent::Position(p)); }
+            PositionTick::UpdatedHigh
+        },
+        strategy::TradeAction::Sell(_) if queued.contains(&(pos.user_id.clone(), pos.token.clone())) => PositionTick::Hold,
+        strategy::TradeAction::Sell(reason) => {
+            info!("📉 USCITA ({}) {}: {}", pos.user_id, pos.token, reason);
+            let wallet = match chain.load_wallet(pool, &pos.user_id).await {
+                Ok(w) => w,
+                Err(_) => return PositionTick::Hold,
+            };
+            match trading::execute_sell(pool, chain, &pos.user_id, &wallet, &pos.token, params.slippage_bps * 2).await {
+                Ok(signature) => {
+                    info!("✅ SELL ({}) -> TX: {}", pos.user_id, signature);
+                    let (entry_price, hold_secs, invested) = trading::position_entry(state, &pos.user_id, &pos.token);
+                    let pnl_sol = trading::close_token_positions(pool, state, &pos.user_id, &pos.token, price, &signature, &reason).await;
+                    PositionTick::Sold { reason, signature, pnl_sol, entry_price, hold_secs, invested }
+                },
+                Err(e) => {
+                    warn!("⚠️ Vendita fallita ({}) {}: {} -> in coda per il retry", pos.user_id, pos.token, e);
+                    let next = chrono::Utc::now().timestamp() + SELL_RETRY_BASE_SECS;
+                    let _ = db::enqueue_sell_retry(pool, &pos.user_id, &pos.token, &reason, &e.to_string(), next).await;
+                    activity::record(pool, &pos.user_id, activity::Event::error(Some(&pos.token), format!("Vendita fallita ({}): {} -> in coda per il retry", reason, e))).await;
+                    PositionTick::SellFailed
+                },
+            }
+        },
+        _ => PositionTick::Hold,
+    }
+}
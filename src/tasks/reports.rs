@@ -0,0 +1,8 @@
+use crate::report;
+use super::Context;
+
+// --- DAILY REPORT (Riepilogo giornaliero all'orario scelto dall'utente) ---
+// Pianificazione e contenuto del report restano in report.rs (condivisi con /report e l'API)
+pub async fn run(ctx: Context) {
+    report::run_daily_report_task(ctx.pool, ctx.net, ctx.state).await;
+}
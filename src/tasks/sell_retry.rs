@@ -0,0 +1,81 @@
+use log::{info, warn};
+use tokio::time::Duration;
+use crate::chain::ChainClient;
+use crate::{activity, auto_park, db, i18n, jupiter, notifications, telegram_bot, trade_alerts, trading};
+use super::Context;
+
+// --- SELL RETRY QUEUE (Vendite fallite del Position Manager) ---
+// 1. Ogni vendita fallita entra in coda (tabella sell_retries, sopravvive ai riavvii)
+// 2. Ogni tentativo allarga lo slippage (base x2, x3, x4...) fino a SELL_RETRY_MAX_SLIPPAGE_BPS
+// 3. L'attesa tra i tentativi raddoppia (10s, 20s, 40s...) fino a SELL_RETRY_MAX_BACKOFF_SECS
+// 4. Dopo SELL_RETRY_MAX_ATTEMPTS l'utente viene avvisato su Telegram e la vendita resta manuale
+
+// Coda vendite fallite: tentativi prima dell'avviso, backoff (raddoppia a ogni tentativo) e tetto allo slippage
+const SELL_RETRY_MAX_ATTEMPTS: i64 = 6;
+pub const SELL_RETRY_BASE_SECS: i64 = 10;
+const SELL_RETRY_MAX_BACKOFF_SECS: i64 = 600;
+const SELL_RETRY_MAX_SLIPPAGE_BPS: u16 = 3000;
+
+fn retry_slippage_bps(base_bps: u16, attempt: i64) -> u16 {
+    (base_bps as i64 * (attempt + 2)).min(SELL_RETRY_MAX_SLIPPAGE_BPS as i64) as u16
+}
+
+fn retry_backoff_secs(attempt: i64) -> i64 {
+    (SELL_RETRY_BASE_SECS << attempt.clamp(0, 16)).min(SELL_RETRY_MAX_BACKOFF_SECS)
+}
+
+pub async fn run(ctx: Context) {
+    let Context { pool, net, state } = ctx;
+    info!("🔁 Sell Retry Queue: ONLINE");
+
+    loop {
+        let now = chrono::Utc::now().timestamp();
+        for retry in db::get_due_sell_retries(&pool, now).await.unwrap_or_default() {
+            if state.is_shutting_down() { break; }
+            // Posizioni chiuse nel frattempo (vendita manuale, liquidazione): niente da fare
+            if db::get_user_token_open_trades(&pool, &retry.user_id, &retry.token).await.map(|t| t.is_empty()).unwrap_or(false) {
+                let _ = db::clear_sell_retry(&pool, &retry.user_id, &retry.token).await;
+                continue;
+            }
+
+            let params = db::get_strategy_params(&pool, &retry.user_id).await;
+            let slippage = retry_slippage_bps(params.slippage_bps * 2, retry.attempts);
+            let res = match net.load_wallet(&pool, &retry.user_id).await {
+                Ok(wallet) => trading::execute_sell(&pool, &net, &retry.user_id, &wallet, &retry.token, slippage).await,
+                Err(e) => Err(e),
+            };
+
+            match res {
+                Ok(sig) => {
+                    info!("✅ SELL RETRY #{} ({}) {} -> TX: {}", retry.attempts + 1, retry.user_id, retry.token, sig);
+                    let price = jupiter::get_token_market_data(&retry.token).await.map(|m| m.price).unwrap_or(0.0);
+                    let reason = format!("{} (tentativo {}, slippage {:.1}%)", retry.reason, retry.attempts + 1, slippage as f64 / 100.0);
+                    let (entry_price, hold_secs, invested) = trading::position_entry(&state, &retry.user_id, &retry.token);
+                    let pnl = trading::close_token_positions(&pool, &state, &retry.user_id, &retry.token, price, &sig, &reason).await;
+                    auto_park::park_profit(&pool, &net, &retry.user_id, invested, pnl);
+                    trade_alerts::notify(&pool, &net, &retry.user_id, notifications::Notification::StopOut {
+                        token: retry.token.clone(), pnl_sol: pnl, reason, tx_signature: sig,
+                        entry_price, exit_price: price, hold_secs,
+                    });
+                },
+                Err(e) => {
+                    let attempts = retry.attempts + 1;
+                    let exhausted = attempts >= SELL_RETRY_MAX_ATTEMPTS;
+                    let next = now + retry_backoff_secs(attempts);
+                    warn!("⚠️ SELL RETRY #{} ({}) {} fallito: {}", attempts, retry.user_id, retry.token, e);
+                    let _ = db::record_sell_retry_failure(&pool, retry.id, &e.to_string(), next, if exhausted { "FAILED" } else { "PENDING" }).await;
+                    if exhausted {
+                        activity::record(&pool, &retry.user_id, activity::Event::error(Some(&retry.token), format!("Vendita non riuscita dopo {} tentativi ({}): {}", attempts, retry.reason, e))).await;
+                        let lang = i18n::user_lang(&pool, &retry.user_id).await;
+                        telegram_bot::notify_user(&retry.user_id, i18n::tr(lang, "notify.sell_failed", &[
+                            &retry.token, &retry.reason, &attempts, &format!("{:.1}", slippage as f64 / 100.0), &e,
+                        ])).await;
+                    }
+                }
+            }
+        }
+        if state.sleep_or_shutdown(Duration::from_secs(5)).await { break; }
+    }
+    info!("🛑 Sell Retry Queue fermato.");
+}
+
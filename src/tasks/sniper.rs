@@ -0,0 +1,225 @@
+use log::{info, warn, debug};
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
+use rand::Rng;
+use solana_client::rpc_client::GetConfirmedSignaturesForAddress2Config;
+use solana_client::rpc_config::RpcTransactionConfig;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::pubkey::Pubkey;
+use solana_transaction_status::UiTransactionEncoding;
+use solana_transaction_status::option_serializer::OptionSerializer;
+use tokio::time::{sleep, Duration};
+use crate::chain;
+use crate::{db, gem_filters, jupiter, metadata, metrics, network, safety, scoring, signals, sniper_stats, trading, AppState, GemData, LiveEvent};
+use super::Context;
+
+// --- HELPER: CONTROLLO DUPLICATI (Sniper e Copy-Trading) ---
+// Ore di storico delle firme processate (PROCESSED_SIG_RETENTION_HOURS): oltre, nessun replay le ripropone
+const DEFAULT_PROCESSED_SIG_RETENTION_HOURS: i64 = 48;
+const PROCESSED_SIG_PRUNE_SECS: u64 = 3600;
+
+/// true solo la prima volta che la firma viene vista, anche tra riavvii (cache in RAM + tabella processed_signatures)
+pub async fn is_new_signature(pool: &db::DbPool, state: &Arc<AppState>, sig: &str, source: &str) -> bool {
+    let now = chrono::Utc::now().timestamp();
+    if !state.processed_sigs.insert(sig, now) { return false; }
+    match db::claim_signature(pool, sig, source, now).await {
+        Ok(claimed) => claimed,
+        // DB non raggiungibile: resta il filtro in RAM
+        Err(e) => { warn!("⚠️ Firma {} non registrata: {}", sig, e); true }
+    }
+}
+
+// Cancella le firme oltre la retention (in background: non blocca lo stream)
+fn spawn_signature_prune(pool: &db::DbPool, retention_hours: i64) {
+    let pool = pool.clone();
+    tokio::spawn(async move {
+        let cutoff = chrono::Utc::now().timestamp() - retention_hours * 3600;
+        match db::prune_processed_signatures(&pool, cutoff).await {
+            Ok(n) if n > 0 => debug!("🧹 Sniper: {} firme processate scadute", n),
+            Ok(_) => {},
+            Err(e) => warn!("⚠️ Pulizia firme processate fallita: {}", e),
+        }
+    });
+}
+
+// --- SNIPER LISTENER (Anti-Rug e Anti-Doppioni) ---
+pub async fn run(ctx: Context) {
+    let Context { pool, net, state } = ctx;
+    let chain: &dyn chain::ChainClient = &net;
+    // Tentativi falliti di fila (backoff), ultimo slot ricevuto dallo stream e inizio della disconnessione
+    let mut attempt: u32 = 0;
+    let mut last_slot: Option<u64> = None;
+    let mut disconnected_at: Option<std::time::Instant> = None;
+    let retention_hours = std::env::var("PROCESSED_SIG_RETENTION_HOURS").ok().and_then(|v| v.parse::<i64>().ok()).filter(|h| *h > 0).unwrap_or(DEFAULT_PROCESSED_SIG_RETENTION_HOURS);
+    spawn_signature_prune(&pool, retention_hours);
+    let mut last_prune = std::time::Instant::now();
+
+    loop {
+        let subscription = chain.subscribe_logs(crate::raydium::RAYDIUM_V4_PROGRAM_ID).await
+            .map_err(|e| warn!("⚠️ Sottoscrizione Sniper fallita: {}", e)).ok();
+
+        if let Some(mut stream) = subscription {
+            info!("✅ Sniper Attivo.");
+            state.sniper_ws_connected.store(true, Ordering::Relaxed);
+            attempt = 0;
+            if let Some(since) = disconnected_at.take() {
+                metrics::METRICS.sniper_ws_downtime.observe(since.elapsed().as_secs_f64());
+            }
+            // Lanci avvenuti mentre lo stream era giù
+            if let Some(slot) = last_slot {
+                let (n, s, p) = (net.clone(), state.clone(), pool.clone());
+                tokio::spawn(async move { replay_missed_launches(&n, &s, &p, slot).await; });
+            }
+
+            while let Some(log) = tokio::select! { l = stream.recv() => l, _ = state.shutdown_signal() => None } {
+                last_slot = Some(log.slot);
+                if last_prune.elapsed() >= Duration::from_secs(PROCESSED_SIG_PRUNE_SECS) {
+                    spawn_signature_prune(&pool, retention_hours);
+                    last_prune = std::time::Instant::now();
+                }
+                if log.logs.iter().any(|l| l.contains("initialize2")) {
+                    spawn_launch_analysis(&net, &state, &pool, log.signature);
+                }
+            }
+            state.sniper_ws_connected.store(false, Ordering::Relaxed);
+            if state.is_shutting_down() { break; }
+            warn!("⚠️ Stream Sniper chiuso, riconnessione...");
+            disconnected_at = Some(std::time::Instant::now());
+        } else {
+            disconnected_at.get_or_insert_with(std::time::Instant::now);
+        }
+
+        metrics::METRICS.sniper_ws_reconnects.inc();
+        let delay = ws_backoff(attempt);
+        attempt = attempt.saturating_add(1);
+        debug!("🔌 Sniper: nuovo tentativo tra {}ms (#{})", delay.as_millis(), attempt);
+        if state.sleep_or_shutdown(delay).await { break; }
+    }
+    info!("🛑 Sniper fermato.");
+}
+
+// --- SNIPER: RICONNESSIONE E RECUPERO LANCI ---
+// 1. Backoff esponenziale con jitter tra i tentativi (niente loop stretto se il nodo WSS è giù)
+// 2. Alla riconnessione si rileggono le TX dell'account fee di Raydium successive all'ultimo slot visto:
+//    ogni initialize2 paga la fee di creazione pool, quindi ogni sua firma è un lancio
+// 3. I lanci recuperati passano dall'analisi normale (is_new_signature evita i doppioni)
+
+const WS_BACKOFF_BASE_MS: u64 = 500;
+const WS_BACKOFF_MAX_MS: u64 = 60_000;
+const RAYDIUM_POOL_FEE_ACCOUNT: &str = "7YttLkHDoNj9wyDur5pM1ejNaAvT9X4eqaYcHQqtj2G5";
+const GAP_REPLAY_LIMIT: usize = 200;
+
+/// Attesa prima del tentativo `attempt` (0 = primo): raddoppia fino al massimo, jitter tra metà e intero
+fn ws_backoff(attempt: u32) -> Duration {
+    let cap = WS_BACKOFF_BASE_MS.saturating_mul(1u64 << attempt.min(16)).min(WS_BACKOFF_MAX_MS);
+    Duration::from_millis(cap / 2 + rand::thread_rng().gen_range(0..=cap / 2))
+}
+
+async fn replay_missed_launches(net: &Arc<network::NetworkClient>, state: &Arc<AppState>, pool: &db::DbPool, after_slot: u64) {
+    let Ok(fee_account) = Pubkey::from_str(RAYDIUM_POOL_FEE_ACCOUNT) else { return };
+    let config = GetConfirmedSignaturesForAddress2Config {
+        limit: Some(GAP_REPLAY_LIMIT),
+        commitment: Some(CommitmentConfig::confirmed()),
+        ..Default::default()
+    };
+    let sigs = {
+        let _t = metrics::METRICS.rpc_timer("getSignaturesForAddress");
+        match net.rpc.get_signatures_for_address_with_config(&fee_account, config).await {
+            Ok(s) => s,
+            Err(e) => { warn!("⚠️ Recupero lanci persi non riuscito: {}", e); return; }
+        }
+    };
+    if sigs.len() == GAP_REPLAY_LIMIT && sigs.last().is_some_and(|s| s.slot > after_slot) {
+        warn!("⚠️ Sniper: buco oltre {} lanci, i più vecchi non vengono recuperati", GAP_REPLAY_LIMIT);
+    }
+
+    // Dal più vecchio al più recente
+    let missed: Vec<String> = sigs.into_iter().rev()
+        .filter(|s| s.slot > after_slot && s.err.is_none())
+        .map(|s| s.signature)
+        .collect();
+    if missed.is_empty() { return; }
+    info!("🔁 Sniper: {} lanci recuperati dopo lo slot {}", missed.len(), after_slot);
+    metrics::METRICS.sniper_replayed_launches.inc_by(missed.len() as u64);
+    for sig in missed {
+        spawn_launch_analysis(net, state, pool, sig);
+    }
+}
+
+// Analisi di un initialize2 (safety, filtri, acquisti) in un task dedicato
+fn spawn_launch_analysis(net: &Arc<network::NetworkClient>, state: &Arc<AppState>, pool: &db::DbPool, sig_str: String) {
+    let n_an = net.clone(); let s_an = state.clone(); let p_an = pool.clone();
+    tokio::spawn(async move {
+        // 1. CHECK DUPLICATI (anche dopo un riavvio o il recupero dei lanci persi)
+        if !is_new_signature(&p_an, &s_an, &sig_str, "SNIPER").await { return; }
+        if let Ok(sig) = solana_sdk::signature::Signature::from_str(&sig_str) {
+            if let Ok(tx) = n_an.rpc.get_transaction_with_config(&sig, RpcTransactionConfig { encoding: Some(UiTransactionEncoding::Base64), commitment: Some(CommitmentConfig::confirmed()), max_supported_transaction_version: Some(0) }).await {
+                // Deployer = chi paga l'initialize2
+                let deployer = tx.transaction.transaction.decode().and_then(|t| t.message.static_account_keys().first().copied());
+                if let (Some(meta), Some(deployer)) = (tx.transaction.meta, deployer) {
+                    let balances = match meta.post_token_balances { OptionSerializer::Some(b) => b, _ => vec![] };
+                    let wsol = "So11111111111111111111111111111111111111112";
+                    // Saldi del deployer dopo il lancio (tra questi l'LP appena creata)
+                    let deployer_post: HashMap<String, u64> = balances.iter()
+                        .filter(|b| matches!(&b.owner, OptionSerializer::Some(o) if *o == deployer.to_string()))
+                        .map(|b| (b.mint.clone(), b.ui_token_amount.amount.parse().unwrap_or(0)))
+                        .collect();
+                    
+                    for b in balances {
+                        let mint = b.mint;
+                        if mint != wsol && b.ui_token_amount.decimals > 0 {
+                            if let Ok(pk) = Pubkey::from_str(&mint) {
+                                // 2. CHECK SAFETY + ANTI-HONEYPOT (Simulazione)
+                                // Qui chiameremo la nuova safety::full_check
+                                if let Ok(rep) = safety::check_token_safety(&n_an, &pk).await {
+                                    // DRY-RUN: si registra ogni lancio (anche scartato) per lo scoreboard, nessun acquisto
+                                    if sniper_stats::dry_run() {
+                                        sniper_stats::record_hit(&p_an, &n_an, &pk, &deployer, &rep).await;
+                                    } else if rep.is_safe {
+                                        sleep(Duration::from_secs(2)).await;
+                                        if let Ok(mkt) = jupiter::get_token_market_data(&mint).await {
+                                            // 3. FILTRO QUALITÀ (soglie configurabili, ricaricabili a caldo)
+                                            if gem_filters::passes(&mint, &mkt) {
+                                                // Simbolo e logo on-chain: DexScreener spesso non li ha ancora per i token appena nati
+                                                let meta = metadata::resolve(&p_an, &n_an, &mint).await;
+                                                info!("💎 GEMMA NUOVA: {} (${:.6}) Liq: ${:.0}", meta.symbol, mkt.price, mkt.liquidity_usd);
+                                                
+                                                let potential = scoring::analyze_token_potential(&mint, &mkt).await;
+                                                let gem = GemData::from_market(&mint, &meta.symbol, meta.logo_uri, &mkt, potential.total, 90, "SNIPER");
+                                                s_an.gems.record(gem.clone());
+                                                let score = gem.safety_score;
+                                                s_an.publish(LiveEvent::Gem(gem));
+                                                
+                                                // 4. DEPLOYER, LP E POOL (soglie per utente)
+                                                let launch = match safety::check_launch(&p_an, &n_an, &pk, &deployer, &deployer_post).await {
+                                                    Ok(l) => l,
+                                                    Err(e) => { warn!("🔬 Analisi lancio {} non riuscita: {}", mint, e); return; }
+                                                };
+                                                let _ = db::record_launch(&p_an, &mint, &launch.deployer, launch.pool_sol).await;
+                                                let mut users = Vec::new();
+                                                for uid in db::get_active_users(&p_an).await.unwrap_or_default() {
+                                                    let params = db::get_strategy_params(&p_an, &uid).await;
+                                                    if !params.trades_universe("sniper") { continue; }
+                                                    match launch.check(&params) {
+                                                        Ok(()) => users.push(uid),
+                                                        Err(why) => debug!("🔬 Sniper saltato per {} su {}: {}", uid, mint, why),
+                                                    }
+                                                }
+                                                let signal = signals::EntrySignal { symbol: meta.symbol, source: "SNIPER", score, balance_fraction: None };
+                                                trading::execute_smart_auto_buy(&p_an, &n_an, &s_an, &pk, users, signal).await;
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+    });
+}
+
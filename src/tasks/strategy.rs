@@ -0,0 +1,152 @@
+use log::{info, warn};
+use std::collections::HashMap;
+use std::env;
+use std::str::FromStr;
+use solana_sdk::pubkey::Pubkey;
+use tokio::time::Duration;
+use crate::{api, birdeye, db, jupiter, scoring, signals, strategy, trading, whale, GemData, LiveEvent, DEFAULT_WATCHLIST};
+use super::Context;
+
+// --- MARKET STRATEGY (Filtrato) ---
+// Segnali tenuti in RAM per la Dashboard (lo storico completo è nella tabella signals)
+pub const MAX_LIVE_SIGNALS: usize = 20;
+// Giorni di storico segnali (SIGNAL_RETENTION_DAYS)
+const DEFAULT_SIGNAL_RETENTION_DAYS: i64 = 30;
+
+/// Ingresso della Market Strategy con i parametri di un utente: filtri di liquidità e volume, poi analyze_market.
+/// Ritorna il motivo del segnale (None = nessun acquisto).
+pub fn entry_signal(data: &strategy::MarketData, liquidity_usd: f64, volume_24h: f64, params: &strategy::StrategyParams) -> Option<String> {
+    // FILTRO LIQUIDITÀ E VOLUME (Anti-Rumore, soglie per utente)
+    if liquidity_usd < params.min_liquidity_usd || volume_24h < params.min_volume_24h { return None; }
+    match strategy::analyze_market(data, 1.0, params) {
+        strategy::TradeAction::Buy { reason, .. } => Some(reason),
+        _ => None,
+    }
+}
+
+pub async fn run(ctx: Context) {
+    let Context { pool, net, state } = ctx;
+    let mut history: HashMap<String, strategy::MarketData> = HashMap::new();
+    // Candele reali Birdeye (MARKET_CANDLE_INTERVAL, default 1m); tick DexScreener solo per token mai coperti da Birdeye
+    let interval = env::var("MARKET_CANDLE_INTERVAL").ok().filter(|i| birdeye::interval_secs(i).is_some()).unwrap_or_else(|| "1m".into());
+    let interval_secs = birdeye::interval_secs(&interval).unwrap_or(60);
+    let retention_days = env::var("SIGNAL_RETENTION_DAYS").ok().and_then(|v| v.parse::<i64>().ok()).filter(|d| *d > 0).unwrap_or(DEFAULT_SIGNAL_RETENTION_DAYS);
+    
+    loop {
+        // Universo: watchlist di default + token scelti dagli utenti attivi
+        let mut tokens: Vec<String> = DEFAULT_WATCHLIST.iter().map(|t| t.to_string()).collect();
+        if let Ok(user_tokens) = db::get_watched_tokens(&pool).await {
+            for t in user_tokens {
+                if !tokens.contains(&t) { tokens.push(t); }
+            }
+        }
+
+        for token in &tokens {
+            // 1. Check Dati Mercato Completi
+            if let Ok(mkt) = jupiter::get_token_market_data(token).await {
+                 if mkt.price <= 0.0 { continue; }
+                 let source = if DEFAULT_WATCHLIST.contains(&token.as_str()) { "TOP" } else { "DISCOVERY" };
+                 state.gems.record(GemData::from_market(token, &mkt.symbol, None, &mkt, scoring::calculate_token_score(&mkt).total, 0, source));
+
+                 let data = history.entry(token.to_string()).or_insert_with(|| strategy::MarketData::new(&mkt.symbol));
+                 let now = chrono::Utc::now().timestamp();
+                 let ohlcv = birdeye::get_ohlcv(token, &interval, now - interval_secs * 100, now).await.ok()
+                     .map(|bars| bars.iter().map(|c| strategy::Candle { high: c.high, low: c.low, close: c.close, volume: c.volume }).collect());
+                 if !data.refresh(ohlcv, mkt.price, mkt.volume_24h) {
+                     warn!("⚠️ OHLCV {} non disponibile: ciclo saltato (ultime candele reali tenute)", mkt.symbol);
+                     continue;
+                 }
+                 data.whale_pressure = whale::pressure(&state, token);
+                 state.market_data.insert(token.to_string(), data.clone());
+
+                 // 2. Segnale pubblico (Parametri Default)
+                 let defaults = strategy::StrategyParams::default();
+                 if let Some(reason) = entry_signal(data, mkt.liquidity_usd, mkt.volume_24h, &defaults) {
+                     info!("📈 SEGNALE VALIDO: {} - {}", mkt.symbol, reason);
+
+                     // Deduplica nel DB (token + strategia, finestra di 5 minuti): sopravvive ai riavvii
+                     let signal = api::SignalData { token: token.to_string(), mode: defaults.engine.clone(), price: mkt.price, score: 90, reason: reason.clone(), timestamp: chrono::Utc::now().timestamp() };
+                     match db::record_signal(&pool, &signal.token, &signal.mode, signal.price, signal.score, &signal.reason, signal.timestamp).await {
+                         Ok(Some(_)) => {
+                             let mut s = state.math_signals.write().await;
+                             s.insert(0, signal.clone());
+                             s.truncate(MAX_LIVE_SIGNALS);
+                             state.publish(LiveEvent::Signal(signal));
+                         },
+                         Ok(None) => {},
+                         Err(e) => warn!("⚠️ Segnale {} non salvato: {}", token, e),
+                     }
+                 }
+
+                 // 3. Auto-Buy: ogni utente che osserva il token valuta con i PROPRI parametri e la PROPRIA strategia
+                 let watchers = db::get_active_watchers(&pool, token, DEFAULT_WATCHLIST.contains(&token.as_str())).await.unwrap_or_default();
+                 let universe = if source == "TOP" { "watchlist" } else { "gems" };
+                 let mut buyers = Vec::new();
+                 for uid in watchers {
+                     let params = db::get_strategy_params(&pool, &uid).await;
+                     if !params.trades_universe(universe) { continue; }
+                     if entry_signal(data, mkt.liquidity_usd, mkt.volume_24h, &params).is_some() {
+                         buyers.push(uid);
+                     }
+                 }
+
+                 if !buyers.is_empty() {
+                     if let Ok(m) = Pubkey::from_str(token) {
+                         let p = pool.clone(); let n = net.clone(); let s = state.clone(); let sym = mkt.symbol.clone();
+                         let signal = signals::EntrySignal { symbol: sym, source: "MARKET", score: 90, balance_fraction: None };
+                         tokio::spawn(async move { trading::execute_smart_auto_buy(&p, &n, &s, &m, buyers, signal).await; });
+                     }
+                 }
+            }
+            if state.sleep_or_shutdown(Duration::from_millis(500)).await { break; }
+        }
+        
+        if history.len() > 50 { history.retain(|k, _| tokens.contains(k)); }
+        let cutoff = chrono::Utc::now().timestamp() - retention_days * 86_400;
+        if let Err(e) = db::prune_signals(&pool, cutoff).await { warn!("⚠️ Pulizia storico segnali fallita: {}", e); }
+        if state.sleep_or_shutdown(Duration::from_secs(30)).await { break; }
+    }
+    info!("🛑 Market Strategy fermato.");
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strategy::{Candle, MarketData, StrategyParams};
+
+    fn bar(price: f64, volume: f64) -> Candle {
+        Candle { high: price, low: price, close: price, volume }
+    }
+
+    #[test]
+    fn ohlcv_errors_after_real_bars_never_open_a_trade() {
+        let params = StrategyParams::default();
+        let mut data = MarketData::new("TEST");
+        assert!(data.refresh(Some(vec![bar(1.0, 100.0); 30]), 1.0, 1_000_000.0));
+        assert!(entry_signal(&data, 1e9, 1e9, &params).is_none());
+
+        // Birdeye giù: il dip e i volumi 24h dei tick non devono diventare una candela con volume enorme
+        for _ in 0..5 {
+            assert!(!data.refresh(None, 0.9, 1_000_000.0));
+        }
+        assert_eq!(data.candles.len(), 30);
+        assert!(data.candles.iter().all(|c| c.volume == 100.0 && c.close == 1.0));
+        assert!(entry_signal(&data, 1e9, 1e9, &params).is_none());
+
+        // Le stesse barre del dip, ma reali: l'ingresso scatta (il test sopra non passa per caso)
+        let mut bars = vec![bar(1.0, 100.0); 30];
+        bars.push(bar(0.9, 500.0));
+        assert!(data.refresh(Some(bars), 0.9, 1_000_000.0));
+        assert!(entry_signal(&data, 1e9, 1e9, &params).is_some());
+    }
+
+    #[test]
+    fn tick_mode_without_ohlcv_history() {
+        let mut data = MarketData::new("TEST");
+        for _ in 0..5 {
+            assert!(data.refresh(None, 1.0, 10.0));
+        }
+        assert_eq!(data.candles.len(), 1);
+    }
+}
@@ -1,14 +1,16 @@
 use teloxide::{
     prelude::*,
-    types::{InlineKeyboardButton, InlineKeyboardMarkup, WebAppInfo, ParseMode},
+    types::{InlineKeyboardButton, InlineKeyboardMarkup, InputFile, WebAppInfo, ParseMode},
     utils::command::BotCommands,
 };
-use sqlx::SqlitePool;
-use std::sync::Arc;
+use crate::db::DbPool;
+use std::sync::{Arc, Mutex};
+use std::collections::HashMap;
 use solana_sdk::pubkey::Pubkey;
 use std::str::FromStr;
 use solana_sdk::native_token::LAMPORTS_PER_SOL;
 use crate::network::NetworkClient;
+use crate::i18n::{self, Lang};
 
 // ⚠️ IMPORTANTE: SOSTITUISCI QUESTO CON IL TUO LINK NETLIFY
 // Esempio: "https://tuo-sito-fantastico.netlify.app"
@@ -16,18 +18,123 @@ const WEB_APP_URL: &str = "https://cryptostarstudiobot.netlify.app";
 
 // Stato Condiviso
 pub struct BotState {
-    pub pool: SqlitePool,
+    pub pool: DbPool,
     pub network: Arc<NetworkClient>,
+    pub app: Arc<crate::AppState>,
+    // Conferme export chiave (User -> Timestamp), valide EXPORT_CONFIRM_SECS
+    pub export_confirmed: Mutex<HashMap<String, i64>>,
 }
 
+const EXPORT_CONFIRM_SECS: i64 = 300;
+
 // Comandi Base
+#[allow(dead_code)]
 #[derive(BotCommands, Clone)]
 #[command(rename_rule = "lowercase", description = "Comandi Disponibili:")]
 enum Command {
     #[command(description = "Avvia il Pannello di Controllo")]
-    Start,
-    #[command(description = "Compra manuale: /buy INDIRIZZO IMPORTO")]
+    Start(String),
+    #[command(description = "Compra: /buy MINT_O_LINK [IMPORTO] (link DexScreener, Birdeye, Pump.fun, Solscan)")]
     Buy(String),
+    #[command(description = "Watchlist: /watch add|remove INDIRIZZO oppure /watch list")]
+    Watch(String),
+    #[command(description = "Blacklist: /blacklist add|remove MINT_O_SIMBOLO oppure /blacklist list")]
+    Blacklist(String),
+    #[command(description = "Whitelist: /whitelist add|remove MINT_O_SIMBOLO | on | off | list")]
+    Whitelist(String),
+    #[command(description = "Copy-Trading: /follow add|remove WALLET oppure /follow list")]
+    Follow(String),
+    #[command(description = "Parametri strategia: /strategy oppure /strategy PARAMETRO VALORE")]
+    Strategy(String),
+    #[command(description = "Posizioni aperte con vendita e stop rapidi")]
+    Positions,
+    #[command(description = "Uscite di una posizione: /position ID sl -20|off | tp 50|off | trailing on|off")]
+    Position(String),
+    #[command(description = "Nota sul trade: /note ID TESTO | /note ID off")]
+    Note(String),
+    #[command(description = "Tag del trade: /tag ID tag1, tag2 | /tag ID off (filtrabili nell'export)")]
+    Tag(String),
+    #[command(description = "DCA: /dca INDIRIZZO SOL ORE | /dca list | /dca pause|resume|cancel ID")]
+    Dca(String),
+    #[command(description = "Esporta la chiave privata criptata: /export poi /export PASSPHRASE")]
+    Export(String),
+    #[command(description = "Importa un wallet: /import EXPORT PASSPHRASE oppure /import CHIAVE_BASE58")]
+    Import(String),
+    #[command(description = "Report giornaliero: /report HH:MM FUSO (es. 08:00 Europe/Rome) | /report off")]
+    Report(String),
+    #[command(description = "Whitelist prelievi: /address add INDIRIZZO [ETICHETTA] | remove INDIRIZZO | list")]
+    Address(String),
+    #[command(description = "Programma referral: codice invito e ricompense | /referrals CODICE per usare un invito")]
+    Referrals(String),
+    #[command(description = "Pulizia polvere: /dust on [MAX_SOL] | /dust off | /dust rent on|off (chiude gli ATA vuoti allo stop)")]
+    Dust(String),
+    #[command(description = "Lingua dei messaggi: /language it|en")]
+    Language(String),
+    #[command(description = "Notifiche dei trade: /alerts off|compact|full")]
+    Alerts(String),
+    #[command(description = "Profitti in USDC: /park PERCENTUALE | /park off | /park release")]
+    Park(String),
+    #[command(description = "Prelievo ricorrente dei profitti: /skim weekly|monthly SALDO_MINIMO INDIRIZZO | /skim off")]
+    Skim(String),
+}
+
+// --- ONBOARDING (Wizard di /start) ---
+// 1. Wallet creato, indirizzo di deposito con QR code
+// 2. Disclaimer sui rischi da accettare: senza, l'Auto-Bot non parte (db::start_daily_cycle)
+// 3. Preferenze iniziali (strategia, tetto per trade), poi il pannello di controllo
+
+// QR generato dal servizio esterno: Telegram scarica l'immagine dall'URL
+const QR_CODE_URL: &str = "https://api.qrserver.com/v1/create-qr-code/?size=400x400&data=solana:";
+const ONBOARDING_MAX_PER_TRADE: [f64; 4] = [0.1, 0.25, 0.5, 1.0];
+
+fn welcome_text(lang: Lang, pubkey: &str) -> String {
+    i18n::tr(lang, "tg.welcome", &[&pubkey])
+}
+
+/// Prossimo passo dell'onboarding per l'utente. false se è già completato.
+async fn send_onboarding_step(bot: &Bot, chat_id: ChatId, state: &BotState, user_id: &str, pubkey: &str, lang: Lang) -> ResponseResult<bool> {
+    let (disclaimer, onboarded) = crate::db::get_onboarding(&state.pool, user_id).await.unwrap_or((false, false));
+    if onboarded { return Ok(false); }
+
+    if !disclaimer {
+        let caption = i18n::tr(lang, "tg.deposit_address", &[&pubkey]);
+        let qr = InputFile::url(format!("{}{}", QR_CODE_URL, pubkey).parse().unwrap());
+        if bot.send_photo(chat_id, qr).caption(caption.clone()).parse_mode(ParseMode::Html).await.is_err() {
+            // QR non disponibile: l'indirizzo serve comunque
+            bot.send_message(chat_id, caption).parse_mode(ParseMode::Html).await?;
+        }
+        let kb = InlineKeyboardMarkup::new(vec![vec![InlineKeyboardButton::callback(i18n::t(lang, "tg.disclaimer_accept"), "onb_accept")]]);
+        bot.send_message(chat_id, i18n::t(lang, "tg.disclaimer")).reply_markup(kb).parse_mode(ParseMode::Html).await?;
+    } else {
+        send_onboarding_strategy(bot, chat_id, lang).await?;
+    }
+    Ok(true)
+}
+
+async fn send_onboarding_strategy(bot: &Bot, chat_id: ChatId, lang: Lang) -> ResponseResult<()> {
+    let row: Vec<InlineKeyboardButton> = crate::strategy::names().into_iter()
+        .map(|name| InlineKeyboardButton::callback(name, format!("onb_engine:{}", name)))
+        .collect();
+    bot.send_message(chat_id, i18n::t(lang, "tg.onboarding_strategy"))
+        .reply_markup(InlineKeyboardMarkup::new(vec![row]))
+        .parse_mode(ParseMode::Html).await?;
+    Ok(())
+}
+
+async fn send_onboarding_max_per_trade(bot: &Bot, chat_id: ChatId, lang: Lang) -> ResponseResult<()> {
+    let row: Vec<InlineKeyboardButton> = ONBOARDING_MAX_PER_TRADE.iter()
+        .map(|v| InlineKeyboardButton::callback(format!("{} SOL", v), format!("onb_max:{}", v)))
+        .collect();
+    bot.send_message(chat_id, i18n::t(lang, "tg.onboarding_max"))
+        .reply_markup(InlineKeyboardMarkup::new(vec![row]))
+        .parse_mode(ParseMode::Html).await?;
+    Ok(())
+}
+
+fn dust_status(dust: &crate::dust::DustSettings) -> String {
+    let sweep = if dust.enabled { format!("<b>ATTIVA</b> (token sotto {} SOL)", dust.max_sol) } else { "<b>DISATTIVATA</b>".to_string() };
+    let rent = if dust.close_on_stop { "<b>ON</b>" } else { "<b>OFF</b>" };
+    format!("🧹 Pulizia polvere: {}\n♻️ Chiusura ATA vuoti allo stop: {}", sweep, rent)
 }
 
 // --- 1. TASTIERA IBRIDA (WEB APP + AZIONI RAPIDE) ---
@@ -86,9 +193,11 @@ pub async fn send_opportunity_alert(
     // Calcolo Strategico dell'Importo
     // "Small" = Cippino di prova
     // "Medium" = Posizione seria (o metà wallet se povero)
-    let safe_balance = (balance_sol - 0.02).max(0.0);
-    let amount_small = if safe_balance > 0.2 { 0.1 } else { 0.01 };
-    let amount_medium = if safe_balance > 1.0 { 0.5 } else { safe_balance * 0.5 };
+    // Entrambi entro i limiti dell'utente (riserva nel wallet e max_per_trade_sol), altrimenti il tasto fallirebbe
+    let params = crate::db::get_strategy_params(&state.pool, &user_id).await;
+    let safe_balance = (balance_sol - params.reserve_sol).max(0.0);
+    let amount_small = (if safe_balance > 0.2 { 0.1_f64 } else { 0.01 }).min(params.max_per_trade_sol);
+    let amount_medium = (if safe_balance > 1.0 { 0.5 } else { safe_balance * 0.5 }).min(params.max_per_trade_sol);
 
     let safety_icon = if safety_score > 85 { "🟢" } else if safety_score > 50 { "🟡" } else { "🔴" };
 
@@ -134,10 +243,137 @@ pub async fn send_opportunity_alert(
     Ok(())
 }
 
+// --- 2a. /buy: MINT O LINK INCOLLATO ---
+// Importi rapidi dei pulsanti (entro i limiti dell'utente, verificati da execute_buy)
+const BUY_PRESETS_SOL: [f64; 3] = [0.05, 0.1, 0.25];
+
+/// Mint da un indirizzo o da un link (dexscreener.com/solana/POOL_O_MINT, birdeye.so/token/MINT, pump.fun/MINT, solscan.io/token/MINT)
+async fn resolve_token_input(input: &str) -> Option<String> {
+    let input = input.trim();
+    if Pubkey::from_str(input).is_ok() { return Some(input.to_string()); }
+
+    let path = input.split("://").last().unwrap_or(input);
+    let path = path.split(['?', '#']).next().unwrap_or(path);
+    let host = path.split('/').next().unwrap_or_default().to_lowercase();
+    let address = path.split('/').rev().find(|s| !s.is_empty()).filter(|s| Pubkey::from_str(s).is_ok())?;
+
+    // DexScreener mostra la pool: si risale al token base (se non è una pool è già il mint)
+    if host.ends_with("dexscreener.com") {
+        if let Ok(Some(base)) = crate::jupiter::get_pair_base_token(address).await {
+            return Some(base);
+        }
+    }
+    Some(address.to_string())
+}
+
+/// Scheda sicurezza + mercato con i pulsanti di acquisto (importo scelto o preset + personalizzato)
+async fn send_buy_preview(bot: &Bot, chat_id: ChatId, state: &BotState, user_id: &str, mint: &str, amount: Option<f64>, lang: Lang) -> ResponseResult<()> {
+    let pubkey = match Pubkey::from_str(mint) {
+        Ok(p) => p,
+        Err(_) => {
+            bot.send_message(chat_id, i18n::t(lang, "tg.buy_unresolved")).parse_mode(ParseMode::Html).await?;
+            return Ok(());
+        }
+    };
+    let (meta, market, safety) = tokio::join!(
+        crate::metadata::resolve(&state.pool, &state.network, mint),
+        crate::jupiter::get_token_market_data(mint),
+        crate::safety::check_token_safety(&state.network, &pubkey)
+    );
+    let market = match market {
+        Ok(m) if m.price > 0.0 => m,
+        _ => {
+            bot.send_message(chat_id, i18n::tr(lang, "tg.buy_no_market", &[&mint])).parse_mode(ParseMode::Html).await?;
+            return Ok(());
+        }
+    };
+    let score = crate::scoring::analyze_token_potential(mint, &market).await;
+    let safety_line = match safety {
+        Ok(r) if r.is_safe => format!("🟢 {}", r.reason),
+        Ok(r) => format!("🔴 {}", r.reason),
+        Err(_) => "⚪ -".to_string(),
+    };
+    let wallet = crate::wallet_manager::create_user_wallet(&state.pool, user_id).await.ok().and_then(|s| Pubkey::from_str(&s).ok());
+    let balance = match wallet {
+        Some(pk) => state.network.get_balance_fast(&pk).await,
+        None => 0,
+    };
+    let params = crate::db::get_strategy_params(&state.pool, user_id).await;
+    let funds = crate::risk::funds(&state.pool, user_id, &params, balance).await
+        .unwrap_or(crate::risk::Funds { balance_sol: balance as f64 / LAMPORTS_PER_SOL as f64, ..Default::default() });
+
+    let text = i18n::tr(lang, "tg.buy_preview", &[
+        &meta.symbol, &mint, &format!("{:.8}", market.price), &format!("{:.0}", market.liquidity_usd), &format!("{:.0}", market.market_cap),
+        &format!("{:+.1}", market.change_1h), &format!("{:+.1}", market.change_24h), &score.total, &safety_line,
+        &format!("{:.3}", funds.balance_sol), &format!("{:.3}", funds.available_sol), &format!("{:.3}", funds.locked_sol),
+    ]);
+
+    let buy_button = |sol: f64| InlineKeyboardButton::callback(i18n::tr(lang, "tg.signal_accept", &[&sol]), format!("buy:{}:{}", mint, sol));
+    let mut rows = match amount {
+        Some(sol) => vec![vec![buy_button(sol)]],
+        None => vec![
+            BUY_PRESETS_SOL.iter().map(|sol| buy_button(*sol)).collect(),
+            vec![InlineKeyboardButton::callback(i18n::t(lang, "tg.buy_custom_button"), format!("buy_custom:{}", mint))],
+        ],
+    };
+    rows.push(vec![InlineKeyboardButton::callback(i18n::t(lang, "tg.signal_ignore"), "ignore")]);
+
+    bot.send_message(chat_id, text)
+        .reply_markup(InlineKeyboardMarkup::new(rows))
+        .parse_mode(ParseMode::Html)
+        .await?;
+    Ok(())
+}
+
+// --- 2b. NOTIFICA DIRETTA (Eventi Backend: fill ordini, ecc.) ---
+pub async fn notify_user(user_id: &str, text: String) {
+    let chat_id = match user_id.parse::<i64>() {
+        Ok(id) => ChatId(id),
+        Err(_) => return, // Utente solo Web (nessuna chat Telegram)
+    };
+    if let Err(e) = Bot::from_env().send_message(chat_id, text).parse_mode(ParseMode::Html).await {
+        log::warn!("⚠️ Notifica Telegram fallita ({}): {}", user_id, e);
+    }
+}
+
+/// Avviso all'amministratore (chat ADMIN_CHAT_ID): ignorato se non configurata
+pub async fn notify_admin(text: String) {
+    if let Ok(chat_id) = std::env::var("ADMIN_CHAT_ID") {
+        notify_user(chat_id.trim(), text).await;
+    }
+}
+
+/// Notifica con pulsanti (conferme di sicurezza). false se l'utente non ha una chat Telegram o l'invio fallisce.
+pub async fn notify_user_with_buttons(user_id: &str, text: String, keyboard: InlineKeyboardMarkup) -> bool {
+    let chat_id = match user_id.parse::<i64>() {
+        Ok(id) => ChatId(id),
+        Err(_) => return false,
+    };
+    match Bot::from_env().send_message(chat_id, text).reply_markup(keyboard).parse_mode(ParseMode::Html).await {
+        Ok(_) => true,
+        Err(e) => {
+            log::warn!("⚠️ Notifica Telegram fallita ({}): {}", user_id, e);
+            false
+        }
+    }
+}
+
+/// Notifica con immagine (URL scaricato da Telegram) e testo in didascalia. false se l'invio fallisce.
+pub async fn notify_user_with_photo(user_id: &str, photo_url: &str, caption: String) -> bool {
+    let (Ok(id), Ok(url)) = (user_id.parse::<i64>(), photo_url.parse()) else { return false };
+    match Bot::from_env().send_photo(ChatId(id), InputFile::url(url)).caption(caption).parse_mode(ParseMode::Html).await {
+        Ok(_) => true,
+        Err(e) => {
+            log::warn!("⚠️ Notifica con immagine fallita ({}): {}", user_id, e);
+            false
+        }
+    }
+}
+
 // --- 3. AVVIO BOT (Entry Point) ---
-pub async fn start_bot(pool: SqlitePool, network: Arc<NetworkClient>) {
+pub async fn start_bot(pool: DbPool, network: Arc<NetworkClient>, app: Arc<crate::AppState>) {
     let bot = Bot::from_env();
-    let state = Arc::new(BotState { pool, network });
+    let state = Arc::new(BotState { pool, network, app, export_confirmed: Mutex::new(HashMap::new()) });
 
     let handler = Update::filter_message()
         .filter_command::<Command>()
@@ -148,34 +384,95 @@ pub async fn start_bot(pool: SqlitePool, network: Arc<NetworkClient>) {
 
     log::info!("🤖 TELEGRAM UI AVVIATA! (Web App Link: {})", WEB_APP_URL);
 
-    Dispatcher::builder(bot, dptree::entry().branch(handler).branch(callback_handler))
+    let app = state.app.clone();
+    let mut dispatcher = Dispatcher::builder(bot, dptree::entry().branch(handler).branch(callback_handler))
         .dependencies(dptree::deps![state])
-        .enable_ctrlc_handler()
-        .build()
-        .dispatch()
-        .await;
+        .build();
+
+    // Lo shutdown arriva da main (segnale condiviso), non dal Ctrl+C del Dispatcher
+    let token = dispatcher.shutdown_token();
+    tokio::spawn(async move {
+        app.shutdown_signal().await;
+        if let Ok(done) = token.shutdown() { done.await; }
+    });
+
+    dispatcher.dispatch().await;
+    log::info!("🛑 Telegram UI fermata.");
 }
 
 // --- 4. GESTIONE COMANDI TESTUALI ---
+// Gestione comune di /blacklist e /whitelist
+async fn filter_command(state: &BotState, user_id: &str, list: &str, args: &str) -> String {
+    let parts: Vec<&str> = args.split_whitespace().collect();
+    let is_whitelist = list == crate::token_filter::WHITELIST;
+
+    match parts.as_slice() {
+        [action @ ("add" | "remove"), value] => {
+            match crate::token_filter::update_entry(&state.pool, user_id, list, value, *action == "add").await {
+                Ok(m) => format!("✅ {}", m),
+                Err(e) => format!("❌ {}", e),
+            }
+        },
+        [mode @ ("on" | "off")] if is_whitelist => {
+            let enabled = *mode == "on";
+            match crate::db::update_setting(&state.pool, user_id, "whitelist_only", serde_json::json!(enabled)).await {
+                Ok(_) if enabled => "✅ Modalità <b>Solo-Whitelist</b> ATTIVA: l'Auto-Buy compra solo i token in Whitelist.".to_string(),
+                Ok(_) => "✅ Modalità Solo-Whitelist <b>DISATTIVATA</b>.".to_string(),
+                Err(e) => format!("Errore Database: {}", e),
+            }
+        },
+        ["list"] | [] => {
+            let filter = crate::token_filter::TokenFilter::load(&state.pool, user_id).await;
+            let entries = if is_whitelist { &filter.whitelist } else { &filter.blacklist };
+            let list_text = if entries.is_empty() {
+                "<i>Vuota</i>".to_string()
+            } else {
+                entries.iter().map(|v| format!("• <code>{}</code>", v)).collect::<Vec<_>>().join("\n")
+            };
+            if is_whitelist {
+                format!("✅ <b>Whitelist</b> (Solo-Whitelist: {})\n\n{}", if filter.whitelist_only { "ON" } else { "OFF" }, list_text)
+            } else {
+                format!("⛔ <b>Blacklist</b>\n\n{}\n\n<i>Un simbolo blocca ogni token che lo contiene nel nome.</i>", list_text)
+            }
+        },
+        _ if is_whitelist => "⚠️ Uso: /whitelist add|remove MINT_O_SIMBOLO | /whitelist on|off | /whitelist list".to_string(),
+        _ => "⚠️ Uso: /blacklist add|remove MINT_O_SIMBOLO | /blacklist list".to_string(),
+    }
+}
+
 async fn answer_command(bot: Bot, msg: Message, cmd: Command, state: Arc<BotState>) -> ResponseResult<()> {
+    // Account sospeso dall'Admin: nessun comando
+    if crate::db::is_user_banned(&state.pool, &msg.chat.id.to_string()).await.unwrap_or(false) {
+        let lang = i18n::user_lang(&state.pool, &msg.chat.id.to_string()).await;
+        bot.send_message(msg.chat.id, i18n::t(lang, "tg.banned")).await?;
+        return Ok(());
+    }
     match cmd {
-        Command::Start => {
+        Command::Start(code) => {
             let user_id = msg.chat.id.to_string();
             
             // Crea o Recupera il Wallet
             let wallet_res = crate::wallet_manager::create_user_wallet(&state.pool, &user_id).await;
+            // Lingua iniziale dal client Telegram (se l'utente non ne ha già scelta una)
+            if wallet_res.is_ok() {
+                i18n::init_user_lang(&state.pool, &user_id, msg.from().and_then(|u| u.language_code.as_deref())).await;
+            }
+            let lang = i18n::user_lang(&state.pool, &user_id).await;
+
+            // Deep link di invito: /start CODICE (errori silenziosi, è solo un link)
+            if wallet_res.is_ok() && !code.trim().is_empty() {
+                if let Ok(msg_ok) = crate::referral::link(&state.pool, &user_id, &code).await {
+                    bot.send_message(msg.chat.id, format!("🤝 {}", msg_ok)).await?;
+                }
+            }
 
             let text = match wallet_res {
-                Ok(pubkey) => format!(
-                    "💎 <b>GOD SNIPER WALLET</b>\n\n\
-                    Il tuo terminale di trading istituzionale è pronto.\n\n\
-                    🔑 <b>Address:</b> <code>{}</code>\n\
-                    🟢 <b>Stato Sistema:</b> ONLINE\n\
-                    🤖 <b>Modalità:</b> Ibrida (App + Bot Automatico)\n\n\
-                    Clicca sotto per iniziare.",
-                    pubkey
-                ),
-                Err(e) => format!("❌ Errore Critico Creazione Wallet: {}", e),
+                Ok(pubkey) => {
+                    // Primo avvio (o wizard interrotto): deposito, disclaimer e preferenze prima del pannello
+                    if send_onboarding_step(&bot, msg.chat.id, &state, &user_id, &pubkey, lang).await? { return Ok(()); }
+                    welcome_text(lang, &pubkey)
+                },
+                Err(e) => i18n::tr(lang, "tg.wallet_error", &[&e]),
             };
 
             bot.send_message(msg.chat.id, text)
@@ -183,8 +480,616 @@ async fn answer_command(bot: Bot, msg: Message, cmd: Command, state: Arc<BotStat
                 .parse_mode(ParseMode::Html)
                 .await?;
         }
-        Command::Buy(_) => {
-            bot.send_message(msg.chat.id, "⚠️ Per comprare usa i pulsanti rapidi o la Web App per maggiore sicurezza.").await?;
+        Command::Language(args) => {
+            let user_id = msg.chat.id.to_string();
+            let text = match args.trim() {
+                "" => {
+                    let lang = i18n::user_lang(&state.pool, &user_id).await;
+                    i18n::tr(lang, "tg.language_current", &[&lang.code(), &i18n::LANGUAGES.join("|")])
+                },
+                code => match i18n::Lang::from_code(code) {
+                    Some(lang) => match i18n::set_user_lang(&state.pool, &user_id, lang).await {
+                        Ok(_) => i18n::tr(lang, "tg.language_set", &[&lang.code()]),
+                        Err(e) => i18n::tr(lang, "tg.db_error", &[&e]),
+                    },
+                    None => i18n::tr(i18n::user_lang(&state.pool, &user_id).await, "tg.language_invalid", &[&i18n::LANGUAGES.join(", ")]),
+                },
+            };
+            bot.send_message(msg.chat.id, text).parse_mode(ParseMode::Html).await?;
+        }
+        Command::Alerts(args) => {
+            let user_id = msg.chat.id.to_string();
+            let lang = i18n::user_lang(&state.pool, &user_id).await;
+            let usage = crate::trade_alerts::Verbosity::ALL.join("|");
+            let text = match args.trim() {
+                "" => {
+                    let settings = crate::db::get_settings(&state.pool, &user_id).await.unwrap_or_default();
+                    i18n::tr(lang, "tg.alerts_current", &[&crate::trade_alerts::Verbosity::from_settings(&settings).code(), &usage])
+                },
+                code => match crate::trade_alerts::Verbosity::from_code(code) {
+                    Some(level) => match crate::trade_alerts::set_verbosity(&state.pool, &user_id, level).await {
+                        Ok(_) => i18n::tr(lang, "tg.alerts_set", &[&level.code()]),
+                        Err(e) => i18n::tr(lang, "tg.db_error", &[&e]),
+                    },
+                    None => i18n::tr(lang, "tg.alerts_invalid", &[&usage]),
+                },
+            };
+            bot.send_message(msg.chat.id, text).parse_mode(ParseMode::Html).await?;
+        }
+        Command::Park(args) => {
+            let user_id = msg.chat.id.to_string();
+            let lang = i18n::user_lang(&state.pool, &user_id).await;
+            let settings = crate::db::get_settings(&state.pool, &user_id).await.unwrap_or_default();
+            let mut park = crate::auto_park::AutoParkSettings::from_settings(&settings);
+
+            let updated = match args.trim() {
+                "" => None,
+                "off" => { park.enabled = false; Some(Ok(())) },
+                "on" => { park.enabled = true; Some(Ok(())) },
+                // La quota torna spendibile (i totali convertiti restano nello storico)
+                "release" => { park.parked_usdc = 0; Some(Ok(())) },
+                pct => match pct.trim_end_matches('%').replace(',', ".").parse::<f64>() {
+                    Ok(p) if crate::auto_park::AutoParkSettings::valid_percent(p) => { park.enabled = true; park.percent = p; Some(Ok(())) },
+                    _ => Some(Err(i18n::t(lang, "tg.park_invalid").to_string())),
+                },
+            };
+            let status = i18n::tr(lang, "tg.park_status", &[
+                &if park.enabled { "ON" } else { "OFF" }, &park.percent, &format!("{:.4}", park.parked_sol), &format!("{:.2}", park.parked_usdc as f64 / 1_000_000.0),
+            ]);
+            let text = match updated {
+                Some(Err(e)) => e,
+                Some(Ok(())) => match crate::auto_park::save(&state.pool, &user_id, &park).await {
+                    Ok(_) => status,
+                    Err(e) => i18n::tr(lang, "tg.db_error", &[&e]),
+                },
+                None => format!("{}\n\n{}", status, i18n::t(lang, "tg.park_usage")),
+            };
+            bot.send_message(msg.chat.id, text).parse_mode(ParseMode::Html).await?;
+        }
+        Command::Skim(args) => {
+            let user_id = msg.chat.id.to_string();
+            let lang = i18n::user_lang(&state.pool, &user_id).await;
+            let parts: Vec<&str> = args.split_whitespace().collect();
+
+            let updated = match parts.as_slice() {
+                [] => None,
+                ["off"] => {
+                    let settings = crate::db::get_settings(&state.pool, &user_id).await.unwrap_or_default();
+                    let mut skim = crate::auto_skim::AutoSkimSettings::from_settings(&settings);
+                    skim.enabled = false;
+                    Some(crate::auto_skim::save(&state.pool, &user_id, &skim).await.map_err(|e| i18n::tr(lang, "tg.db_error", &[&e])))
+                },
+                [period, floor, address] => match (crate::auto_skim::SkimPeriod::from_code(period), floor.replace(',', ".").parse::<f64>()) {
+                    (Some(period), Ok(floor)) => Some(crate::auto_skim::enable(&state.pool, &user_id, period, floor, address, lang).await.map(|_| ()).map_err(|e| format!("❌ {}", e))),
+                    _ => Some(Err(i18n::t(lang, "tg.skim_usage").to_string())),
+                },
+                _ => Some(Err(i18n::t(lang, "tg.skim_usage").to_string())),
+            };
+            let settings = crate::db::get_settings(&state.pool, &user_id).await.unwrap_or_default();
+            let skim = crate::auto_skim::AutoSkimSettings::from_settings(&settings);
+            let status = i18n::tr(lang, "tg.skim_status", &[
+                &if skim.enabled { "ON" } else { "OFF" }, &skim.period.code(), &skim.floor_sol,
+                &skim.destination.as_deref().unwrap_or("-"), &format!("{:.4}", skim.skimmed_sol),
+            ]);
+            let text = match updated {
+                Some(Err(e)) => e,
+                Some(Ok(())) => status,
+                None => format!("{}\n\n{}", status, i18n::t(lang, "tg.skim_usage")),
+            };
+            bot.send_message(msg.chat.id, text).parse_mode(ParseMode::Html).await?;
+        }
+        Command::Buy(args) => {
+            let user_id = msg.chat.id.to_string();
+            let lang = i18n::user_lang(&state.pool, &user_id).await;
+            let parts: Vec<&str> = args.split_whitespace().collect();
+            let (input, amount) = match parts.as_slice() {
+                [input] => (*input, None),
+                [input, amount] => match amount.replace(',', ".").parse::<f64>() {
+                    Ok(a) if a > 0.0 && a.is_finite() => (*input, Some(a)),
+                    _ => {
+                        bot.send_message(msg.chat.id, i18n::t(lang, "tg.buy_usage")).parse_mode(ParseMode::Html).await?;
+                        return Ok(());
+                    }
+                },
+                _ => {
+                    bot.send_message(msg.chat.id, i18n::t(lang, "tg.buy_usage")).parse_mode(ParseMode::Html).await?;
+                    return Ok(());
+                }
+            };
+            let Some(mint) = resolve_token_input(input).await else {
+                bot.send_message(msg.chat.id, i18n::t(lang, "tg.buy_unresolved")).parse_mode(ParseMode::Html).await?;
+                return Ok(());
+            };
+            send_buy_preview(&bot, msg.chat.id, &state, &user_id, &mint, amount, lang).await?;
+        }
+        Command::Watch(args) => {
+            let user_id = msg.chat.id.to_string();
+            let parts: Vec<&str> = args.split_whitespace().collect();
+
+            let text = match parts.as_slice() {
+                ["add", mint] | ["remove", mint] if Pubkey::from_str(mint).is_err() => {
+                    "❌ Indirizzo token non valido.".to_string()
+                },
+                ["add", mint] => match crate::db::add_to_watchlist(&state.pool, &user_id, mint).await {
+                    Ok(true) => format!("👀 Aggiunto alla Watchlist:\n<code>{}</code>", mint),
+                    Ok(false) => "ℹ️ Token già presente nella Watchlist.".to_string(),
+                    Err(e) => format!("Errore Database: {}", e),
+                },
+                ["remove", mint] => match crate::db::remove_from_watchlist(&state.pool, &user_id, mint).await {
+                    Ok(true) => format!("🗑️ Rimosso dalla Watchlist:\n<code>{}</code>", mint),
+                    Ok(false) => "ℹ️ Token non presente nella Watchlist.".to_string(),
+                    Err(e) => format!("Errore Database: {}", e),
+                },
+                ["list"] | [] => {
+                    let tokens = crate::db::get_watchlist(&state.pool, &user_id).await.unwrap_or_default();
+                    if tokens.is_empty() {
+                        let list: Vec<String> = crate::DEFAULT_WATCHLIST.iter().map(|t| format!("• <code>{}</code>", t)).collect();
+                        format!("👀 <b>Watchlist (Default)</b>\n\n{}\n\n<i>Aggiungi un token con /watch add INDIRIZZO</i>", list.join("\n"))
+                    } else {
+                        let list: Vec<String> = tokens.iter().map(|t| format!("• <code>{}</code>", t)).collect();
+                        format!("👀 <b>La tua Watchlist</b>\n\n{}", list.join("\n"))
+                    }
+                },
+                _ => "⚠️ Uso: /watch add INDIRIZZO | /watch remove INDIRIZZO | /watch list".to_string(),
+            };
+
+            bot.send_message(msg.chat.id, text).parse_mode(ParseMode::Html).await?;
+        }
+        Command::Blacklist(args) => {
+            let text = filter_command(&state, &msg.chat.id.to_string(), crate::token_filter::BLACKLIST, &args).await;
+            bot.send_message(msg.chat.id, text).parse_mode(ParseMode::Html).await?;
+        }
+        Command::Whitelist(args) => {
+            let text = filter_command(&state, &msg.chat.id.to_string(), crate::token_filter::WHITELIST, &args).await;
+            bot.send_message(msg.chat.id, text).parse_mode(ParseMode::Html).await?;
+        }
+        Command::Strategy(args) => {
+            let user_id = msg.chat.id.to_string();
+            let current = crate::db::get_strategy_params(&state.pool, &user_id).await;
+            let parts: Vec<&str> = args.split_whitespace().collect();
+
+            let text = match parts.as_slice() {
+                [] => {
+                    let fields = serde_json::json!(current);
+                    let list: Vec<String> = fields.as_object().map(|o| o.iter().map(|(k, v)| format!("• <code>{}</code>: <b>{}</b>", k, v)).collect()).unwrap_or_default();
+                    format!("⚙️ <b>Parametri Strategia</b>\n\n{}\n\n<i>Modifica con /strategy PARAMETRO VALORE (mappe: /strategy max_hold_hours.momentum 4)</i>", list.join("\n"))
+                },
+                [key, value] => {
+                    // Numeri come numeri, true/false come booleani, il resto come testo (es. engine momentum)
+                    let parsed: serde_json::Value = value.parse::<f64>().map(|v| serde_json::json!(v))
+                        .or_else(|_| value.parse::<bool>().map(|v| serde_json::json!(v)))
+                        .unwrap_or_else(|_| serde_json::json!(value));
+                    // I campi interi (es. slippage_bps) non accettano decimali
+                    let parsed = match value.parse::<u64>() { Ok(v) => serde_json::json!(v), Err(_) => parsed };
+                    // Liste separate da virgole (es. /strategy universe watchlist,sniper)
+                    let parsed = if *key == "universe" {
+                        serde_json::json!(value.split(',').map(|u| u.trim().to_lowercase()).filter(|u| !u.is_empty()).collect::<Vec<_>>())
+                    } else { parsed };
+                    match current.with_overrides(&serde_json::json!({ *key: parsed })) {
+                        Ok(params) => match crate::db::save_strategy_params(&state.pool, &user_id, &params).await {
+                            Ok(_) => format!("✅ <code>{}</code> impostato a <b>{}</b>", key, value),
+                            Err(e) => format!("Errore Database: {}", e),
+                        },
+                        Err(msg) => format!("❌ {}", msg),
+                    }
+                },
+                _ => "⚠️ Uso: /strategy oppure /strategy PARAMETRO VALORE".to_string(),
+            };
+
+            bot.send_message(msg.chat.id, text).parse_mode(ParseMode::Html).await?;
+        }
+        Command::Positions => {
+            let user_id = msg.chat.id.to_string();
+            let mut positions = state.app.positions_snapshot(Some(&user_id));
+            positions.sort_by_key(|p| p.trade_id);
+            let params = crate::db::get_strategy_params(&state.pool, &user_id).await;
+            let now = chrono::Utc::now().timestamp();
+            let lang = i18n::user_lang(&state.pool, &user_id).await;
+
+            if positions.is_empty() {
+                bot.send_message(msg.chat.id, i18n::t(lang, "tg.no_positions")).await?;
+                return Ok(());
+            }
+
+            let mut lines = Vec::new();
+            let mut rows = Vec::new();
+            for pos in &positions {
+                let invested = pos.amount_in_lamports as f64 / LAMPORTS_PER_SOL as f64;
+                let value = match crate::jupiter::get_token_market_data(&pos.token).await {
+                    Ok(mkt) if pos.entry_price > 0.0 => invested * mkt.price / pos.entry_price,
+                    _ => invested,
+                };
+                let pnl_pct = if invested > 0.0 { (value / invested - 1.0) * 100.0 } else { 0.0 };
+                let pct_of = |lamports: u64| (lamports as f64 / pos.amount_in_lamports.max(1) as f64 - 1.0) * 100.0;
+                let mut exits = Vec::new();
+                if !pos.trailing_disabled {
+                    exits.push(if params.exit_mode == "percent" { "📉 Trailing".to_string() } else { format!("📉 Trailing {}", params.exit_mode.to_uppercase()) });
+                }
+                if pos.stop_floor_lamports == pos.amount_in_lamports {
+                    exits.push("🛡️ Break-even".to_string());
+                } else if pos.stop_floor_lamports > 0 {
+                    exits.push(format!("🛑 SL {:+.0}%", pct_of(pos.stop_floor_lamports)));
+                }
+                if pos.take_profit_lamports > 0 {
+                    exits.push(format!("🎯 TP {:+.0}%", pct_of(pos.take_profit_lamports)));
+                } else if params.take_profit_pct > 0.0 {
+                    exits.push(i18n::tr(lang, "tg.positions_tp_strategy", &[&format!("{:+.0}", params.take_profit_pct)]));
+                }
+                if let Some(left) = pos.hold_remaining_secs(&params, now) {
+                    exits.push(i18n::tr(lang, "tg.positions_hold_left", &[&(left / 3600), &format!("{:02}", left % 3600 / 60)]));
+                }
+                let stop = if exits.is_empty() { i18n::t(lang, "tg.positions_no_exit").to_string() } else { exits.join(" | ") };
+
+                lines.push(format!(
+                    "<b>#{}</b> <code>{}</code>\n💰 {:.4} SOL → {:.4} SOL ({:+.1}%)\n{}",
+                    pos.trade_id, pos.token, invested, value, pnl_pct, stop
+                ));
+                rows.push(vec![
+                    InlineKeyboardButton::callback(i18n::tr(lang, "tg.positions_sell_button", &[&pos.trade_id]), format!("pos_sell:{}", pos.trade_id)),
+                    InlineKeyboardButton::callback("🛡️ Break-even", format!("pos_be:{}", pos.trade_id)),
+                    InlineKeyboardButton::callback("➕ 50%", format!("pos_add:{}", pos.trade_id)),
+                ]);
+            }
+
+            let text = i18n::tr(lang, "tg.positions", &[&lines.join("\n\n")]);
+            bot.send_message(msg.chat.id, text)
+                .reply_markup(InlineKeyboardMarkup::new(rows))
+                .parse_mode(ParseMode::Html)
+                .await?;
+        }
+        Command::Follow(args) => {
+            let user_id = msg.chat.id.to_string();
+            let parts: Vec<&str> = args.split_whitespace().collect();
+
+            let text = match parts.as_slice() {
+                ["add", wallet] | ["remove", wallet] => match crate::copy_trade::update_follow(&state.pool, &user_id, wallet, parts[0] == "add").await {
+                    Ok(m) => format!("👥 {}", m),
+                    Err(e) => format!("❌ {}", e),
+                },
+                ["list"] | [] => {
+                    let wallets = crate::db::get_followed_wallets(&state.pool, &user_id).await.unwrap_or_default();
+                    if wallets.is_empty() {
+                        "👥 Non segui nessun wallet.\n\n<i>Aggiungi un leader con /follow add WALLET</i>".to_string()
+                    } else {
+                        let list: Vec<String> = wallets.iter().map(|w| format!("• <code>{}</code>", w)).collect();
+                        format!(
+                            "👥 <b>Wallet Seguiti</b>\n\n{}\n\n<i>I loro acquisti vengono replicati con la stessa quota del tuo saldo (Auto-Bot attivo).</i>",
+                            list.join("\n")
+                        )
+                    }
+                },
+                _ => "⚠️ Uso: /follow add WALLET | /follow remove WALLET | /follow list".to_string(),
+            };
+
+            bot.send_message(msg.chat.id, text).parse_mode(ParseMode::Html).await?;
+        }
+        Command::Address(args) => {
+            let user_id = msg.chat.id.to_string();
+            let parts: Vec<&str> = args.split_whitespace().collect();
+
+            let text = match parts.as_slice() {
+                ["add", address, label @ ..] => {
+                    let label = label.join(" ");
+                    match crate::withdrawals::request_address(&state.pool, &user_id, address, Some(&label)).await {
+                        // La conferma arriva come messaggio separato con i pulsanti
+                        Ok(_) => return Ok(()),
+                        Err(e) => format!("❌ {}", e),
+                    }
+                },
+                ["remove", address] => match crate::db::remove_withdrawal_address(&state.pool, &user_id, address).await {
+                    Ok(true) => "🗑 Indirizzo rimosso dalla whitelist".to_string(),
+                    Ok(false) => "⚠️ Indirizzo non in whitelist".to_string(),
+                    Err(e) => format!("Errore Database: {}", e),
+                },
+                ["list"] | [] => {
+                    let list = crate::db::get_withdrawal_addresses(&state.pool, &user_id).await.unwrap_or_default();
+                    if list.is_empty() {
+                        "🔐 Nessun indirizzo di prelievo.\n\n<i>Aggiungine uno con /address add INDIRIZZO</i>".to_string()
+                    } else {
+                        let now = chrono::Utc::now().timestamp();
+                        let lines: Vec<String> = list.iter().map(|a| {
+                            let status = match crate::withdrawals::usable_from(a) {
+                                None => "⏳ da confermare".to_string(),
+                                Some(t) if t > now => format!("🕒 attivo tra {}h", (t - now + 3599) / 3600),
+                                Some(_) => "✅ attivo".to_string(),
+                            };
+                            format!("• <code>{}</code>{} — {}", a.address, a.label.as_deref().map(|l| format!(" ({})", l)).unwrap_or_default(), status)
+                        }).collect();
+                        format!("🔐 <b>Indirizzi di Prelievo</b>\n\n{}", lines.join("\n"))
+                    }
+                },
+                _ => "⚠️ Uso: /address add INDIRIZZO [ETICHETTA] | /address remove INDIRIZZO | /address list".to_string(),
+            };
+
+            bot.send_message(msg.chat.id, text).parse_mode(ParseMode::Html).await?;
+        }
+        Command::Position(args) => {
+            let user_id = msg.chat.id.to_string();
+            let parts: Vec<&str> = args.split_whitespace().collect();
+            let pct = |v: &str| v.trim_end_matches('%').parse::<f64>().ok();
+
+            let update = match parts.as_slice() {
+                [_, "sl", "off"] => Some(crate::trading::ExitUpdate { stop_loss_pct: Some(None), ..Default::default() }),
+                [_, "tp", "off"] => Some(crate::trading::ExitUpdate { take_profit_pct: Some(None), ..Default::default() }),
+                [_, "sl", v] => pct(v).map(|p| crate::trading::ExitUpdate { stop_loss_pct: Some(Some(p)), ..Default::default() }),
+                [_, "tp", v] => pct(v).map(|p| crate::trading::ExitUpdate { take_profit_pct: Some(Some(p)), ..Default::default() }),
+                [_, "trailing", "on"] => Some(crate::trading::ExitUpdate { trailing: Some(true), ..Default::default() }),
+                [_, "trailing", "off"] => Some(crate::trading::ExitUpdate { trailing: Some(false), ..Default::default() }),
+                _ => None,
+            };
+            let trade_id = parts.first().and_then(|id| id.trim_start_matches('#').parse::<i64>().ok());
+
+            let text = match (trade_id, update) {
+                (Some(id), Some(update)) => match crate::trading::update_position_exits(&state.pool, &state.app, &user_id, id, update).await {
+                    Ok(pos) => {
+                        let sol = |l: u64| l as f64 / LAMPORTS_PER_SOL as f64;
+                        let fmt = |l: u64| if l > 0 { format!("{:.4} SOL", sol(l)) } else { "off".to_string() };
+                        format!(
+                            "✅ <b>Posizione #{} aggiornata</b>\n\n🛑 Stop: {}\n🎯 Take Profit: {}\n📉 Trailing: {}",
+                            id, fmt(pos.stop_floor_lamports), fmt(pos.take_profit_lamports), if pos.trailing_disabled { "off" } else { "on" }
+                        )
+                    },
+                    Err(e) => format!("❌ {}", e),
+                },
+                _ => "⚠️ Uso: /position ID sl -20 | /position ID tp 50 | /position ID sl off | /position ID trailing off".to_string(),
+            };
+
+            bot.send_message(msg.chat.id, text).parse_mode(ParseMode::Html).await?;
+        }
+        Command::Note(args) => {
+            let user_id = msg.chat.id.to_string();
+            let lang = i18n::user_lang(&state.pool, &user_id).await;
+            let (id, text) = args.trim().split_once(char::is_whitespace).unwrap_or((args.trim(), ""));
+            let trade_id = id.trim_start_matches('#').parse::<i64>().ok();
+
+            let reply = match (trade_id, text.trim()) {
+                (Some(_), "") | (None, _) => i18n::t(lang, "tg.note_usage").to_string(),
+                (Some(id), note) => {
+                    let clear = note == "off";
+                    let update = crate::trade_notes::TradeNotes { note: Some(if clear { String::new() } else { note.to_string() }), tags: None };
+                    match crate::trade_notes::annotate(&state.pool, &user_id, id, update).await {
+                        Ok(()) => i18n::tr(lang, if clear { "tg.note_cleared" } else { "tg.note_saved" }, &[&id]),
+                        Err(e) => format!("❌ {}", e),
+                    }
+                },
+            };
+            bot.send_message(msg.chat.id, reply).parse_mode(ParseMode::Html).await?;
+        }
+        Command::Tag(args) => {
+            let user_id = msg.chat.id.to_string();
+            let lang = i18n::user_lang(&state.pool, &user_id).await;
+            let (id, text) = args.trim().split_once(char::is_whitespace).unwrap_or((args.trim(), ""));
+            let trade_id = id.trim_start_matches('#').parse::<i64>().ok();
+
+            let reply = match (trade_id, text.trim()) {
+                (Some(_), "") | (None, _) => i18n::t(lang, "tg.tags_usage").to_string(),
+                (Some(id), "off") => {
+                    let update = crate::trade_notes::TradeNotes { note: None, tags: Some(Vec::new()) };
+                    match crate::trade_notes::annotate(&state.pool, &user_id, id, update).await {
+                        Ok(()) => i18n::tr(lang, "tg.tags_cleared", &[&id]),
+                        Err(e) => format!("❌ {}", e),
+                    }
+                },
+                (Some(id), list) => match crate::trade_notes::parse_tags(list) {
+                    Ok(tags) => {
+                        let shown = tags.join(", ");
+                        let update = crate::trade_notes::TradeNotes { note: None, tags: Some(tags) };
+                        match crate::trade_notes::annotate(&state.pool, &user_id, id, update).await {
+                            Ok(()) => i18n::tr(lang, "tg.tags_saved", &[&id, &shown]),
+                            Err(e) => format!("❌ {}", e),
+                        }
+                    },
+                    Err(e) => format!("❌ {}", e),
+                },
+            };
+            bot.send_message(msg.chat.id, reply).parse_mode(ParseMode::Html).await?;
+        }
+        Command::Dca(args) => {
+            let user_id = msg.chat.id.to_string();
+            let parts: Vec<&str> = args.split_whitespace().collect();
+
+            let text = match parts.as_slice() {
+                ["list"] | [] => {
+                    let orders = crate::db::get_dca_orders(&state.pool, &user_id).await.unwrap_or_default();
+                    if orders.is_empty() {
+                        "🔁 Nessun ordine DCA.\n\n<i>Crea con /dca INDIRIZZO SOL ORE (es. /dca JUP... 0.05 6)</i>".to_string()
+                    } else {
+                        let list: Vec<String> = orders.iter().map(|o| format!(
+                            "<b>#{}</b> {} <code>{}</code>\n💰 {:.3} SOL ogni {}h | Fill: {} ({:.3} SOL)",
+                            o.id, if o.status == "ACTIVE" { "🟢" } else { "⏸️" }, o.token,
+                            o.amount_lamports as f64 / LAMPORTS_PER_SOL as f64, o.interval_secs / 3600,
+                            o.fills, o.total_spent_lamports as f64 / LAMPORTS_PER_SOL as f64
+                        )).collect();
+                        format!("🔁 <b>Ordini DCA</b>\n\n{}", list.join("\n\n"))
+                    }
+                },
+                [action @ ("pause" | "resume" | "cancel"), id] => {
+                    let status = match *action { "pause" => "PAUSED", "resume" => "ACTIVE", _ => "CANCELLED" };
+                    match id.trim_start_matches('#').parse::<i64>() {
+                        Ok(order_id) => match crate::db::set_dca_status(&state.pool, &user_id, order_id, status).await {
+                            Ok(true) => format!("✅ Ordine DCA #{} -> <b>{}</b>", order_id, status),
+                            Ok(false) => "ℹ️ Ordine DCA non trovato.".to_string(),
+                            Err(e) => format!("Errore Database: {}", e),
+                        },
+                        Err(_) => "❌ ID ordine non valido.".to_string(),
+                    }
+                },
+                [mint, amount, hours] => {
+                    match (Pubkey::from_str(mint), amount.parse::<f64>(), hours.parse::<i64>()) {
+                        _ if crate::db::count_dca_orders(&state.pool, &user_id).await.unwrap_or(0) >= crate::db::MAX_DCA_ORDERS => {
+                            format!("❌ Massimo {} ordini DCA per utente.", crate::db::MAX_DCA_ORDERS)
+                        },
+                        (Ok(_), Ok(sol), Ok(h)) if sol >= 0.01 && (1..=24 * 30).contains(&h) => {
+                            let amount_lamports = (sol * LAMPORTS_PER_SOL as f64) as u64;
+                            match crate::db::create_dca_order(&state.pool, &user_id, mint, amount_lamports, h * 3600).await {
+                                Ok(id) => format!("🔁 <b>DCA #{} creato</b>\n{} SOL di <code>{}</code> ogni {}h", id, sol, mint, h),
+                                Err(e) => format!("Errore Database: {}", e),
+                            }
+                        },
+                        _ => "❌ Parametri DCA non validi (min 0.01 SOL, intervallo 1h-30g).".to_string(),
+                    }
+                },
+                _ => "⚠️ Uso: /dca INDIRIZZO SOL ORE | /dca list | /dca pause|resume|cancel ID".to_string(),
+            };
+
+            bot.send_message(msg.chat.id, text).parse_mode(ParseMode::Html).await?;
+        }
+        Command::Report(args) => {
+            let user_id = msg.chat.id.to_string();
+            let parts: Vec<&str> = args.split_whitespace().collect();
+            let settings = crate::db::get_settings(&state.pool, &user_id).await.unwrap_or_default();
+            let mut report = crate::report::ReportSettings::from_settings(&settings);
+
+            let updated = match parts.as_slice() {
+                [] => None,
+                ["off"] => { report.enabled = false; Some(Ok(())) },
+                ["on"] => { report.enabled = true; Some(Ok(())) },
+                [time, tz @ ..] if tz.len() <= 1 => {
+                    let tz_name = tz.first().copied().unwrap_or(report.timezone.as_str()).to_string();
+                    match (crate::report::parse_report_time(time), chrono_tz::Tz::from_str(&tz_name)) {
+                        (Some((h, m)), Ok(tz)) => {
+                            report.enabled = true;
+                            report.hour = h;
+                            report.minute = m;
+                            report.timezone = tz.name().to_string();
+                            // Nuovo orario: il report di oggi può ripartire
+                            report.last_sent = None;
+                            Some(Ok(()))
+                        },
+                        (None, _) => Some(Err("❌ Orario non valido (formato HH:MM).")),
+                        (_, Err(_)) => Some(Err("❌ Fuso orario non valido (es. Europe/Rome, UTC).")),
+                    }
+                },
+                _ => Some(Err("⚠️ Uso: /report HH:MM FUSO | /report off | /report on")),
+            };
+
+            let text = match updated {
+                Some(Err(e)) => e.to_string(),
+                Some(Ok(())) => match crate::db::update_setting(&state.pool, &user_id, "report", serde_json::json!(report)).await {
+                    Ok(_) if report.enabled => format!("📰 Report giornaliero alle <b>{:02}:{:02}</b> ({})", report.hour, report.minute, report.timezone),
+                    Ok(_) => "📰 Report giornaliero <b>DISATTIVATO</b>.".to_string(),
+                    Err(e) => format!("Errore Database: {}", e),
+                },
+                None if report.enabled => format!(
+                    "📰 Report giornaliero alle <b>{:02}:{:02}</b> ({})\n\n<i>Cambia con /report HH:MM FUSO oppure /report off</i>",
+                    report.hour, report.minute, report.timezone
+                ),
+                None => "📰 Report giornaliero <b>DISATTIVATO</b>.\n\n<i>Riattiva con /report on</i>".to_string(),
+            };
+
+            bot.send_message(msg.chat.id, text).parse_mode(ParseMode::Html).await?;
+        }
+        Command::Dust(args) => {
+            let user_id = msg.chat.id.to_string();
+            let parts: Vec<&str> = args.split_whitespace().collect();
+            let settings = crate::db::get_settings(&state.pool, &user_id).await.unwrap_or_default();
+            let mut dust = crate::dust::DustSettings::from_settings(&settings);
+
+            let updated = match parts.as_slice() {
+                [] => None,
+                ["off"] => { dust.enabled = false; Some(Ok(())) },
+                ["on"] => { dust.enabled = true; Some(Ok(())) },
+                ["on", max] => match max.replace(',', ".").parse::<f64>() {
+                    Ok(m) if m > 0.0 && m <= 1.0 => { dust.enabled = true; dust.max_sol = m; Some(Ok(())) },
+                    _ => Some(Err("❌ Soglia non valida (tra 0 e 1 SOL).")),
+                },
+                ["rent", "on"] => { dust.close_on_stop = true; Some(Ok(())) },
+                ["rent", "off"] => { dust.close_on_stop = false; Some(Ok(())) },
+                _ => Some(Err("⚠️ Uso: /dust on [MAX_SOL] | /dust off | /dust rent on|off")),
+            };
+
+            let text = match updated {
+                Some(Err(e)) => e.to_string(),
+                Some(Ok(())) => match crate::db::update_setting(&state.pool, &user_id, "dust", serde_json::json!(dust)).await {
+                    Ok(_) => dust_status(&dust),
+                    Err(e) => format!("Errore Database: {}", e),
+                },
+                None => format!("{}\n\n<i>Cambia con /dust on [MAX_SOL] | /dust off | /dust rent on|off</i>", dust_status(&dust)),
+            };
+
+            bot.send_message(msg.chat.id, text).parse_mode(ParseMode::Html).await?;
+        }
+        Command::Referrals(args) => {
+            let user_id = msg.chat.id.to_string();
+            let parts: Vec<&str> = args.split_whitespace().collect();
+            let lang = i18n::user_lang(&state.pool, &user_id).await;
+
+            let text = match parts.as_slice() {
+                [] => match crate::referral::summary(&state.pool, &user_id).await {
+                    Ok(s) => i18n::tr(lang, "tg.referrals", &[
+                        &s.code,
+                        &s.link.as_deref().map(|l| format!("🔗 {}\n", l)).unwrap_or_default(),
+                        &format!("{:.2}", s.fee_bps as f64 / 100.0), &s.invitees, &format!("{:.4}", s.volume_sol),
+                        &format!("{:.6}", s.earned_sol), &format!("{:.6}", s.paid_sol), &format!("{:.6}", s.available_sol),
+                        &(crate::referral::MIN_PAYOUT_LAMPORTS as f64 / 1_000_000_000.0),
+                    ]),
+                    Err(e) => format!("❌ {}", e),
+                },
+                [code] => match crate::referral::link(&state.pool, &user_id, code).await {
+                    Ok(m) => format!("🤝 {}", m),
+                    Err(e) => format!("❌ {}", e),
+                },
+                _ => i18n::t(lang, "tg.referrals_usage").to_string(),
+            };
+
+            bot.send_message(msg.chat.id, text).parse_mode(ParseMode::Html).await?;
+        }
+        Command::Export(args) => {
+            let user_id = msg.chat.id.to_string();
+            let passphrase = args.trim();
+            let lang = i18n::user_lang(&state.pool, &user_id).await;
+
+            if passphrase.is_empty() {
+                let kb = InlineKeyboardMarkup::new(vec![vec![
+                    InlineKeyboardButton::callback(i18n::t(lang, "tg.export_confirm_button"), "export_confirm"),
+                    InlineKeyboardButton::callback(i18n::t(lang, "tg.cancel_button"), "ignore"),
+                ]]);
+                bot.send_message(msg.chat.id, i18n::t(lang, "tg.export_warning"))
+                    .reply_markup(kb)
+                    .parse_mode(ParseMode::Html)
+                    .await?;
+                return Ok(());
+            }
+
+            // La passphrase non deve restare nella chat
+            let _ = bot.delete_message(msg.chat.id, msg.id).await;
+
+            let confirmed_at = state.export_confirmed.lock().unwrap().remove(&user_id);
+            let now = chrono::Utc::now().timestamp();
+            let text = match confirmed_at {
+                Some(t) if now - t <= EXPORT_CONFIRM_SECS => {
+                    if passphrase.len() < crate::wallet_manager::MIN_PASSPHRASE_LEN {
+                        i18n::tr(lang, "tg.export_short_passphrase", &[&crate::wallet_manager::MIN_PASSPHRASE_LEN])
+                    } else {
+                        match crate::wallet_manager::export_wallet_encrypted(&state.pool, &user_id, passphrase).await {
+                            Ok(blob) => i18n::tr(lang, "tg.export_done", &[&blob]),
+                            Err(e) => i18n::tr(lang, "tg.export_failed", &[&e]),
+                        }
+                    }
+                },
+                _ => i18n::t(lang, "tg.export_expired").to_string(),
+            };
+            bot.send_message(msg.chat.id, text).parse_mode(ParseMode::Html).await?;
+        }
+        Command::Import(args) => {
+            let user_id = msg.chat.id.to_string();
+            // Il messaggio contiene chiave/passphrase: rimuoverlo subito
+            let _ = bot.delete_message(msg.chat.id, msg.id).await;
+
+            let parsed = match args.trim().split_once(char::is_whitespace) {
+                Some((blob, passphrase)) => crate::wallet_manager::decrypt_export(blob, passphrase.trim()),
+                None if !args.trim().is_empty() => crate::wallet_manager::keypair_from_base58(&args),
+                None => Err("Uso: /import EXPORT PASSPHRASE oppure /import CHIAVE_BASE58".into()),
+            };
+
+            let text = match parsed {
+                Ok(kp) => match crate::wallet_manager::import_user_wallet(&state.pool, &user_id, &kp).await {
+                    Ok(pubkey) => format!("📥 <b>Wallet Importato!</b>\n\n🔑 <b>Address:</b> <code>{}</code>", pubkey),
+                    Err(e) => format!("❌ {}", e),
+                },
+                Err(e) => format!("❌ {}", e),
+            };
+            bot.send_message(msg.chat.id, text).parse_mode(ParseMode::Html).await?;
         }
     }
     Ok(())
@@ -202,77 +1107,245 @@ async fn answer_callback(bot: Bot, q: CallbackQuery, state: Arc<BotState>) -> Re
             return Ok(());
         };
 
+        let lang = i18n::user_lang(&state.pool, &user_id).await;
+        if crate::db::is_user_banned(&state.pool, &user_id).await.unwrap_or(false) {
+            bot.answer_callback_query(q.id).text(format!("⛔ {}", i18n::t(lang, "api.banned"))).await?;
+            return Ok(());
+        }
+
         let parts: Vec<&str> = data.split(':').collect();
         let action = parts[0];
 
         match action {
+            // --- 0. ONBOARDING (Disclaimer -> Strategia -> Tetto per trade) ---
+            "onb_accept" => {
+                if let Err(e) = crate::db::accept_disclaimer(&state.pool, &user_id).await {
+                    bot.send_message(chat_id, i18n::tr(lang, "tg.db_error", &[&e])).await?;
+                    return Ok(());
+                }
+                bot.answer_callback_query(q.id).text(i18n::t(lang, "tg.disclaimer_accepted")).await?;
+                send_onboarding_strategy(&bot, chat_id, lang).await?;
+            },
+            "onb_engine" => {
+                let engine = parts.get(1).copied().unwrap_or_default();
+                let current = crate::db::get_strategy_params(&state.pool, &user_id).await;
+                let res = match current.with_overrides(&serde_json::json!({ "engine": engine })) {
+                    Ok(params) => crate::db::save_strategy_params(&state.pool, &user_id, &params).await.map_err(|e| i18n::tr(lang, "tg.db_error", &[&e])),
+                    Err(e) => Err(e),
+                };
+                match res {
+                    Ok(_) => send_onboarding_max_per_trade(&bot, chat_id, lang).await?,
+                    Err(e) => { bot.send_message(chat_id, format!("❌ {}", e)).await?; }
+                }
+            },
+            "onb_max" => {
+                let max: f64 = parts.get(1).and_then(|v| v.parse().ok()).unwrap_or(0.0);
+                let current = crate::db::get_strategy_params(&state.pool, &user_id).await;
+                let res = match current.with_overrides(&serde_json::json!({ "max_per_trade_sol": max })) {
+                    Ok(params) => match crate::db::save_strategy_params(&state.pool, &user_id, &params).await {
+                        Ok(_) => crate::db::complete_onboarding(&state.pool, &user_id).await.map_err(|e| i18n::tr(lang, "tg.db_error", &[&e])),
+                        Err(e) => Err(i18n::tr(lang, "tg.db_error", &[&e])),
+                    },
+                    Err(e) => Err(e),
+                };
+                match res {
+                    Ok(true) => {
+                        let pubkey = crate::wallet_manager::create_user_wallet(&state.pool, &user_id).await.unwrap_or_default();
+                        let text = i18n::tr(lang, "tg.onboarding_done", &[&current.engine, &max, &welcome_text(lang, &pubkey)]);
+                        bot.send_message(chat_id, text).reply_markup(make_main_keyboard()).parse_mode(ParseMode::Html).await?;
+                    },
+                    Ok(false) => { bot.send_message(chat_id, i18n::t(lang, "tg.onboarding_disclaimer_first")).await?; },
+                    Err(e) => { bot.send_message(chat_id, format!("❌ {}", e)).await?; }
+                }
+            },
+
             // --- A. CONTROLLO AUTO-BOT (DB + Logica) ---
             "start_auto_bot" => {
-                match crate::db::start_daily_cycle(&state.pool, &user_id).await {
-                    Ok(_) => {
-                        bot.send_message(chat_id, "🤖 <b>AUTO-TRADING AVVIATO (24h)</b> 🟢\n\nIl bot cercherà gemme e reinvestirà i profitti.\n⚠️ Prelievi bloccati fino a fine ciclo per compounding.\nPuoi sempre fare trading manuale!").parse_mode(ParseMode::Html).await?;
+                // Primo click: scelta della strategia (quella attuale evidenziata)
+                if parts.len() < 2 {
+                    let current = crate::db::get_strategy_params(&state.pool, &user_id).await.engine;
+                    let row: Vec<InlineKeyboardButton> = crate::strategy::names().into_iter()
+                        .map(|name| {
+                            let label = if name == current { format!("✅ {}", name) } else { name.to_string() };
+                            InlineKeyboardButton::callback(label, format!("start_auto_bot:{}", name))
+                        })
+                        .collect();
+                    bot.send_message(chat_id, i18n::t(lang, "tg.strategy_choose"))
+                        .reply_markup(InlineKeyboardMarkup::new(vec![row]))
+                        .parse_mode(ParseMode::Html).await?;
+                    return Ok(());
+                }
+                match crate::trading::start_auto_bot(&state.pool, &user_id, Some(parts[1]), None).await {
+                    Ok(engine) => {
+                        bot.send_message(chat_id, i18n::tr(lang, "tg.autobot_started", &[&engine])).parse_mode(ParseMode::Html).await?;
                     },
-                    Err(e) => { bot.send_message(chat_id, format!("Errore Database: {}", e)).await?; }
+                    Err(e) => { bot.send_message(chat_id, format!("❌ {}", e)).await?; }
                 }
             },
             "stop_auto_bot" => {
-                // Query diretta per spegnere il flag
-                sqlx::query("UPDATE users SET is_active = 0 WHERE tg_id = ?")
-                    .bind(&user_id)
-                    .execute(&state.pool).await.ok();
-                bot.send_message(chat_id, "🛑 <b>Auto-Trading Fermato.</b>\nIl bot non comprerà più autonomamente.\nPrelievi sbloccati.").parse_mode(ParseMode::Html).await?;
+                if let Err(e) = crate::db::stop_daily_cycle(&state.pool, &user_id).await {
+                    log::error!("❌ Stop auto-bot fallito ({}): {}", user_id, e);
+                    bot.send_message(chat_id, i18n::t(lang, "api.db_error")).await?;
+                    return Ok(());
+                }
+                crate::activity::record(&state.pool, &user_id, crate::activity::Event::bot_stop("Telegram")).await;
+                let mut text = i18n::t(lang, "tg.autobot_stopped").to_string();
+                if let Some(lamports) = crate::dust::reclaim_on_stop(&state.pool, &state.network, &user_id).await {
+                    text.push_str(&i18n::tr(lang, "tg.rent_reclaimed", &[&format!("{:.4}", lamports as f64 / 1_000_000_000.0)]));
+                }
+                bot.send_message(chat_id, text).parse_mode(ParseMode::Html).await?;
             },
 
-            // --- B. TRADING MANUALE (Raydium Swap) ---
+            // --- B. TRADING MANUALE (Stesso percorso della API: Jupiter -> Raydium) ---
             "buy" => {
                 if parts.len() < 3 { return Ok(()); }
                 let token_address = parts[1];
                 let amount_sol: f64 = parts[2].parse().unwrap_or(0.01);
 
-                bot.send_message(chat_id, format!("⏳ <b>Esecuzione Swap...</b>\nTarget: <code>{}</code>\nImporto: {} SOL", token_address, amount_sol))
+                bot.send_message(chat_id, i18n::tr(lang, "tg.swap_running", &[&token_address, &amount_sol]))
                    .parse_mode(ParseMode::Html).await?;
 
-                // Recupera chiave privata (Decriptata al volo)
-                let payer = crate::wallet_manager::get_decrypted_wallet(&state.pool, &user_id).await.unwrap();
-                let token_mint = Pubkey::from_str(token_address).unwrap();
                 let amount_lamports = (amount_sol * LAMPORTS_PER_SOL as f64) as u64;
+                let params = crate::db::get_strategy_params(&state.pool, &user_id).await;
 
-                // 1. Trova Pool Keys
-                let pool_keys = match crate::raydium::fetch_pool_keys_by_mint(&state.network, &token_mint).await {
-                    Ok(k) => k,
-                    Err(_) => { bot.send_message(chat_id, "❌ Liquidità non trovata o pool inesistente.").await?; return Ok(()); }
-                };
-
-                // 2. Esegui Swap
-                // Min Amount 0 per slippage dinamico (massima velocità)
-                match crate::raydium::execute_swap(&state.network, &payer, &pool_keys, token_mint, amount_lamports, 0).await {
-                    Ok(sig) => {
-                         // Salva il Trade nel DB per il P&L
-                         let _ = crate::db::record_buy(&state.pool, &user_id, token_address, &sig, amount_lamports).await;
-                         
-                         let text = format!("✅ <b>ACQUISTO COMPLETATO!</b>\n💎 Token in wallet.\n🔗 <a href=\"https://solscan.io/tx/{}\">Vedi su Solscan</a>", sig);
+                match crate::trading::execute_buy(&state.pool, &state.network, &user_id, token_address, amount_lamports, params.slippage_bps).await {
+                    Ok((sig, _)) => {
+                         let text = i18n::tr(lang, "tg.buy_done", &[&sig]);
                          
                          // Tasto per vendere subito
                          let kb = InlineKeyboardMarkup::new(vec![vec![
-                             InlineKeyboardButton::callback("🔴 VENDI TUTTO (Panic)", format!("sell:{}:100", token_address))
+                             InlineKeyboardButton::callback(i18n::t(lang, "tg.panic_sell_button"), format!("sell:{}:100", token_address))
                          ]]);
                          bot.send_message(chat_id, text).reply_markup(kb).parse_mode(ParseMode::Html).await?;
                     },
-                    Err(e) => { bot.send_message(chat_id, format!("❌ Errore Swap: {}", e)).await?; }
+                    Err(e) => { bot.send_message(chat_id, i18n::tr(lang, "tg.signal_swap_failed", &[&e])).await?; }
+                }
+            },
+
+            // Importo personalizzato: si ripete /buy con l'importo (passa di nuovo dalla scheda di conferma)
+            "buy_custom" => {
+                let Some(mint) = parts.get(1) else { return Ok(()) };
+                bot.answer_callback_query(q.id).await?;
+                bot.send_message(chat_id, i18n::tr(lang, "tg.buy_custom", &[mint])).parse_mode(ParseMode::Html).await?;
+            },
+
+            // --- B1. TRADE SUGGERITO (Modalità solo segnali) ---
+            "pt_approve" | "pt_reject" => {
+                let id: i64 = match parts.get(1).and_then(|p| p.parse().ok()) { Some(id) => id, None => return Ok(()) };
+                if action == "pt_reject" {
+                    let text = crate::signals::reject(&state.pool, &user_id, id).await.unwrap_or_else(|e| e);
+                    if let Some(msg) = q.message {
+                        bot.edit_message_text(msg.chat.id, msg.id, text).await?;
+                    }
+                    return Ok(());
+                }
+                bot.send_message(chat_id, i18n::tr(lang, "tg.signal_swap_running", &[&id])).parse_mode(ParseMode::Html).await?;
+                match crate::signals::approve(&state.pool, &state.network, &user_id, id).await {
+                    Ok((sig, trade)) => {
+                        let text = i18n::tr(lang, "tg.signal_buy_done", &[&trade.symbol, &sig]);
+                        let kb = InlineKeyboardMarkup::new(vec![vec![
+                            InlineKeyboardButton::callback(i18n::t(lang, "tg.panic_sell_button"), format!("sell:{}:100", trade.token))
+                        ]]);
+                        bot.send_message(chat_id, text).reply_markup(kb).parse_mode(ParseMode::Html).await?;
+                    },
+                    Err(e) => { bot.send_message(chat_id, e).await?; }
                 }
             },
 
             "sell" => {
+                if parts.len() < 2 { return Ok(()); }
                 let token = parts[1];
-                bot.send_message(chat_id, format!("⚠️ Funzione Vendita Manuale per {} in arrivo nel prossimo update...", token)).await?;
-                // Qui andrà la chiamata a execute_sell
+                bot.send_message(chat_id, i18n::tr(lang, "tg.selling", &[&token])).parse_mode(ParseMode::Html).await?;
+                let text = match crate::trading::sell_position_now(&state.pool, &state.network, &state.app, &user_id, token).await {
+                    Ok((sig, pnl)) => i18n::tr(lang, "tg.sell_done", &[&format!("{:+.4}", pnl), &sig]),
+                    Err(e) => i18n::tr(lang, "tg.sell_failed", &[&e]),
+                };
+                bot.send_message(chat_id, text).parse_mode(ParseMode::Html).await?;
+            },
+
+            // --- B2. GESTIONE POSIZIONI (/positions) ---
+            "pos_sell" | "pos_be" | "pos_add" => {
+                let trade_id: i64 = match parts.get(1).and_then(|p| p.parse().ok()) { Some(id) => id, None => return Ok(()) };
+                let pos = state.app.open_positions.get(&trade_id).map(|p| p.value().clone());
+                let pos = match pos {
+                    Some(p) if p.user_id == user_id => p,
+                    _ => {
+                        bot.answer_callback_query(q.id).text(i18n::t(lang, "tg.position_not_open")).show_alert(true).await?;
+                        return Ok(());
+                    }
+                };
+
+                let text = match action {
+                    "pos_sell" => match crate::trading::sell_position_now(&state.pool, &state.network, &state.app, &user_id, &pos.token).await {
+                        Ok((sig, pnl)) => i18n::tr(lang, "tg.position_sold", &[&trade_id, &format!("{:+.4}", pnl), &sig]),
+                        Err(e) => i18n::tr(lang, "tg.sell_failed", &[&e]),
+                    },
+                    "pos_be" => match crate::db::set_position_stop_floor(&state.pool, trade_id, pos.amount_in_lamports).await {
+                        Ok(_) => {
+                            if let Some(mut p) = state.app.open_positions.get_mut(&trade_id) {
+                                p.stop_floor_lamports = pos.amount_in_lamports;
+                            }
+                            i18n::tr(lang, "tg.position_breakeven", &[&trade_id, &format!("{:.4}", pos.amount_in_lamports as f64 / LAMPORTS_PER_SOL as f64)])
+                        },
+                        Err(e) => {
+                            log::error!("❌ Break-even fallito (#{}): {}", trade_id, e);
+                            i18n::t(lang, "api.db_error").to_string()
+                        },
+                    },
+                    _ => {
+                        let params = crate::db::get_strategy_params(&state.pool, &user_id).await;
+                        let amount = pos.amount_in_lamports / 2;
+                        match crate::trading::execute_buy(&state.pool, &state.network, &user_id, &pos.token, amount, params.slippage_bps).await {
+                            Ok((sig, route)) => i18n::tr(lang, "tg.position_added", &[&format!("{:.4}", amount as f64 / LAMPORTS_PER_SOL as f64), &pos.token, &route, &sig]),
+                            Err(e) => i18n::tr(lang, "tg.signal_swap_failed", &[&e]),
+                        }
+                    },
+                };
+                bot.send_message(chat_id, text).parse_mode(ParseMode::Html).await?;
+            },
+
+            // --- B3. EXPORT CHIAVE (Conferma) ---
+            "export_confirm" => {
+                state.export_confirmed.lock().unwrap().insert(user_id.clone(), chrono::Utc::now().timestamp());
+                bot.send_message(chat_id, i18n::tr(lang, "tg.export_confirmed", &[&crate::wallet_manager::MIN_PASSPHRASE_LEN]))
+                    .parse_mode(ParseMode::Html).await?;
             },
 
             // --- C. GESTIONE FONDI (Prelievo con Blocco) ---
+            "wd_approve" | "wd_reject" => {
+                let id = match parts.get(1).and_then(|v| v.parse::<i64>().ok()) { Some(i) => i, None => return Ok(()) };
+                let text = match crate::withdrawals::resolve_withdrawal(&state.pool, &state.network, &user_id, id, action == "wd_approve").await {
+                    Ok(m) => m,
+                    Err(e) => format!("⚠️ {}", e),
+                };
+                if let Some(msg) = q.message {
+                    bot.edit_message_text(msg.chat.id, msg.id, text).await?;
+                }
+            },
+            "of_approve" | "of_reject" => {
+                let id = match parts.get(1).and_then(|v| v.parse::<i64>().ok()) { Some(i) => i, None => return Ok(()) };
+                let text = match crate::offramp::resolve(&state.pool, &state.network, &user_id, id, action == "of_approve").await {
+                    Ok(m) => m,
+                    Err(e) => format!("⚠️ {}", e),
+                };
+                if let Some(msg) = q.message {
+                    bot.edit_message_text(msg.chat.id, msg.id, text).await?;
+                }
+            },
+            "wl_confirm" | "wl_reject" => {
+                let id = match parts.get(1).and_then(|v| v.parse::<i64>().ok()) { Some(i) => i, None => return Ok(()) };
+                let text = match crate::withdrawals::resolve_address(&state.pool, &user_id, id, action == "wl_confirm").await {
+                    Ok(m) => m,
+                    Err(e) => format!("⚠️ {}", e),
+                };
+                if let Some(msg) = q.message {
+                    bot.edit_message_text(msg.chat.id, msg.id, text).await?;
+                }
+            },
             "withdraw_all" => {
                 match crate::db::can_withdraw(&state.pool, &user_id).await {
                     Ok((true, _)) => {
-                        bot.send_message(chat_id, "💸 <b>Prelievo Sbloccato</b>\n\nPer sicurezza, inserisci l'indirizzo di destinazione nel prossimo messaggio (Funzione in arrivo).").parse_mode(ParseMode::Html).await?;
+                        bot.send_message(chat_id, i18n::t(lang, "tg.withdraw_unlocked")).parse_mode(ParseMode::Html).await?;
                     },
                     Ok((false, msg)) => {
                         // Se bloccato, mostra popup alert invece di messaggio
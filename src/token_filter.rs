@@ -0,0 +1,92 @@
+use std::str::FromStr;
+use serde::Serialize;
+use solana_sdk::pubkey::Pubkey;
+use crate::db;
+
+// --- FILTRI TOKEN PER UTENTE (Blacklist / Whitelist) ---
+// Voci: mint esatti oppure simboli (es. "ELON").
+// Blacklist: un simbolo blocca ogni token il cui nome lo CONTIENE ("ELON" blocca anche "BABYELON").
+// Whitelist: il simbolo deve coincidere. In modalità whitelist_only passa SOLO ciò che è in whitelist.
+
+pub const BLACKLIST: &str = "BLACKLIST";
+pub const WHITELIST: &str = "WHITELIST";
+pub const MAX_FILTER_ENTRIES: usize = 100;
+
+#[derive(Serialize, Default, Clone, Debug)]
+pub struct TokenFilter {
+    pub whitelist_only: bool,
+    pub blacklist: Vec<String>,
+    pub whitelist: Vec<String>,
+}
+
+impl TokenFilter {
+    pub async fn load(pool: &db::DbPool, user_id: &str) -> Self {
+        let settings = db::get_settings(pool, user_id).await.unwrap_or_default();
+        let mut filter = TokenFilter { whitelist_only: settings["whitelist_only"].as_bool().unwrap_or(false), ..Default::default() };
+        for (list, value) in db::get_token_filters(pool, user_id).await.unwrap_or_default() {
+            match list.as_str() {
+                BLACKLIST => filter.blacklist.push(value),
+                WHITELIST => filter.whitelist.push(value),
+                _ => {}
+            }
+        }
+        filter
+    }
+
+    /// true se l'utente accetta il token (`symbol` può essere vuoto se non noto)
+    pub fn allows(&self, mint: &str, symbol: &str) -> bool {
+        let symbol = symbol.to_uppercase();
+        let blocked = self.blacklist.iter().any(|v| v == mint || (!symbol.is_empty() && !is_mint(v) && symbol.contains(v.as_str())));
+        if blocked { return false; }
+        if !self.whitelist_only { return true; }
+        self.whitelist.iter().any(|v| v == mint || (!symbol.is_empty() && *v == symbol))
+    }
+}
+
+fn is_mint(value: &str) -> bool {
+    Pubkey::from_str(value).is_ok()
+}
+
+/// Normalizza l'input utente: mint valido così com'è, simbolo in MAIUSCOLO (senza "$", max 20 caratteri)
+pub fn normalize_entry(raw: &str) -> Option<String> {
+    let raw = raw.trim();
+    if is_mint(raw) { return Some(raw.to_string()); }
+    let symbol = raw.trim_start_matches('$').to_uppercase();
+    let valid = !symbol.is_empty() && symbol.len() <= 20 && symbol.chars().all(|c| c.is_ascii_alphanumeric());
+    valid.then_some(symbol)
+}
+
+/// Lista da nome (case-insensitive): "blacklist" -> BLACKLIST
+pub fn parse_list(name: &str) -> Option<&'static str> {
+    match name.to_uppercase().as_str() {
+        BLACKLIST => Some(BLACKLIST),
+        WHITELIST => Some(WHITELIST),
+        _ => None,
+    }
+}
+
+/// Aggiunge o rimuove una voce: messaggio per l'utente oppure errore
+pub async fn update_entry(pool: &db::DbPool, user_id: &str, list: &str, raw: &str, add: bool) -> Result<String, String> {
+    let value = normalize_entry(raw).ok_or("Voce non valida (mint o simbolo alfanumerico)")?;
+    let name = if list == BLACKLIST { "Blacklist" } else { "Whitelist" };
+
+    let res = if add {
+        let count = db::get_token_filters(pool, user_id).await.map_err(|e| e.to_string())?.len();
+        if count >= MAX_FILTER_ENTRIES { return Err(format!("Massimo {} voci tra Blacklist e Whitelist", MAX_FILTER_ENTRIES)); }
+        db::add_token_filter(pool, user_id, list, &value).await
+            .map(|added| if added { format!("{} aggiunto alla {}", value, name) } else { format!("{} già presente in {}", value, name) })
+    } else {
+        db::remove_token_filter(pool, user_id, list, &value).await
+            .map(|removed| if removed { format!("{} rimosso dalla {}", value, name) } else { format!("{} non presente in {}", value, name) })
+    };
+    res.map_err(|e| format!("Errore Database: {}", e))
+}
+
+/// Utenti (tra i candidati) che accettano il token
+pub async fn retain_allowed(pool: &db::DbPool, users: Vec<String>, mint: &str, symbol: &str) -> Vec<String> {
+    let mut allowed = Vec::with_capacity(users.len());
+    for uid in users {
+        if TokenFilter::load(pool, &uid).await.allows(mint, symbol) { allowed.push(uid); }
+    }
+    allowed
+}
@@ -0,0 +1,186 @@
+use std::io::Cursor;
+use std::sync::Arc;
+use dashmap::DashMap;
+use image::imageops::FilterType;
+use image::{ImageBuffer, ImageFormat, Rgba};
+use log::debug;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use crate::{db, metadata, network::NetworkClient, AppState};
+
+// --- ICONE TOKEN (Proxy con cache, GET /token/{mint}/icon) ---
+// 1. Il logo dei metadati (Metaplex/DexScreener) si scarica una volta, si ridimensiona e si serve come PNG dal nostro dominio:
+//    la Dashboard non fa più hotlink a DexScreener/Arweave (404, blocchi CORS)
+// 2. Cache in RAM per mint + dimensione; oltre MAX_ENTRIES si scarta l'icona richiesta meno di recente
+// 3. Logo assente, non scaricabile o in un formato non supportato (es. SVG): identicon generato dal mint,
+//    tenuto in cache per meno tempo così il logo vero compare appena disponibile
+
+const DEFAULT_SIZE: u32 = 64;
+const MIN_SIZE: u32 = 16;
+const MAX_SIZE: u32 = 256;
+const MAX_ENTRIES: usize = 2000;
+const ICON_TTL_SECS: i64 = 86_400;
+const FALLBACK_TTL_SECS: i64 = 900;
+const FETCH_TIMEOUT_SECS: u64 = 5;
+const MAX_DOWNLOAD_BYTES: usize = 2 * 1024 * 1024;
+// Oltre queste dimensioni il logo non si decodifica (immagini enormi o malformate)
+const MAX_SOURCE_PIXELS: u32 = 4096;
+// Gateway per i logo ipfs:// (IPFS_GATEWAY)
+const DEFAULT_IPFS_GATEWAY: &str = "https://ipfs.io/ipfs/";
+
+// GET /token/{mint}/icon?size=64
+#[derive(Deserialize)]
+pub struct IconQuery {
+    pub size: Option<u32>,
+}
+
+impl IconQuery {
+    /// Lato in pixel, limitato a MIN_SIZE..=MAX_SIZE
+    pub fn size(&self) -> u32 {
+        self.size.unwrap_or(DEFAULT_SIZE).clamp(MIN_SIZE, MAX_SIZE)
+    }
+}
+
+#[derive(Clone)]
+pub struct Icon {
+    pub png: Vec<u8>,
+    pub etag: String,
+    // true = identicon (logo non disponibile)
+    pub generated: bool,
+}
+
+impl Icon {
+    fn new(png: Vec<u8>, generated: bool) -> Self {
+        let etag = format!("\"{}\"", hex::encode(&Sha256::digest(&png)[..8]));
+        Self { png, etag, generated }
+    }
+
+    /// max-age per l'header Cache-Control (l'identicon scade prima)
+    pub fn max_age(&self) -> i64 {
+        if self.generated { FALLBACK_TTL_SECS } else { ICON_TTL_SECS }
+    }
+}
+
+struct CachedIcon {
+    icon: Icon,
+    expires_at: i64,
+    last_hit: i64,
+}
+
+#[derive(Default)]
+pub struct IconCache {
+    icons: DashMap<(String, u32), CachedIcon>,
+}
+
+impl IconCache {
+    fn get(&self, mint: &str, size: u32, now: i64) -> Option<Icon> {
+        let mut entry = self.icons.get_mut(&(mint.to_string(), size))?;
+        if entry.expires_at <= now { return None; }
+        entry.last_hit = now;
+        Some(entry.icon.clone())
+    }
+
+    fn insert(&self, mint: &str, size: u32, icon: Icon, now: i64) {
+        let expires_at = now + icon.max_age();
+        self.icons.insert((mint.to_string(), size), CachedIcon { icon, expires_at, last_hit: now });
+        if self.icons.len() > MAX_ENTRIES {
+            let stalest = self.icons.iter().min_by_key(|e| e.last_hit).map(|e| e.key().clone());
+            if let Some(key) = stalest { self.icons.remove(&key); }
+        }
+    }
+}
+
+/// Icona del token in PNG size x size: cache, poi logo dei metadati, poi identicon
+pub async fn icon(pool: &db::DbPool, net: &Arc<NetworkClient>, state: &Arc<AppState>, mint: &str, size: u32) -> Icon {
+    let now = chrono::Utc::now().timestamp();
+    if let Some(icon) = state.token_icons.get(mint, size, now) { return icon; }
+
+    let icon = match fetch_logo(pool, net, mint, size).await {
+        Ok(png) => Icon::new(png, false),
+        Err(e) => {
+            debug!("🖼️ Logo {} non disponibile ({}): identicon", mint, e);
+            Icon::new(identicon(mint, size), true)
+        },
+    };
+    state.token_icons.insert(mint, size, icon.clone(), now);
+    icon
+}
+
+async fn fetch_logo(pool: &db::DbPool, net: &Arc<NetworkClient>, mint: &str, size: u32) -> Result<Vec<u8>, String> {
+    let uri = metadata::resolve(pool, net, mint).await.logo_uri.ok_or("nessun logo nei metadati")?;
+    let url = match uri.strip_prefix("ipfs://") {
+        Some(cid) => format!("{}{}", std::env::var("IPFS_GATEWAY").unwrap_or_else(|_| DEFAULT_IPFS_GATEWAY.into()), cid),
+        None if uri.starts_with("https://") || uri.starts_with("http://") => uri,
+        None => return Err(format!("URI non supportato: {}", uri)),
+    };
+
+    let client = reqwest::Client::builder().timeout(std::time::Duration::from_secs(FETCH_TIMEOUT_SECS)).build().map_err(|e| e.to_string())?;
+    let resp = client.get(&url).send().await.map_err(|e| e.to_string())?.error_for_status().map_err(|e| e.to_string())?;
+    if resp.content_length().is_some_and(|len| len as usize > MAX_DOWNLOAD_BYTES) { return Err("logo troppo grande".into()); }
+    let bytes = resp.bytes().await.map_err(|e| e.to_string())?;
+    if bytes.len() > MAX_DOWNLOAD_BYTES { return Err("logo troppo grande".into()); }
+
+    // Decodifica e ridimensionamento fuori dal runtime async
+    tokio::task::spawn_blocking(move || resize_png(&bytes, size)).await.map_err(|e| e.to_string())?
+}
+
+// Qualsiasi formato supportato (PNG, JPEG, GIF, WebP) -> PNG quadrato size x size (ritaglio centrale)
+fn resize_png(bytes: &[u8], size: u32) -> Result<Vec<u8>, String> {
+    let mut reader = image::io::Reader::new(Cursor::new(bytes)).with_guessed_format().map_err(|e| e.to_string())?;
+    let mut limits = image::io::Limits::default();
+    limits.max_image_width = Some(MAX_SOURCE_PIXELS);
+    limits.max_image_height = Some(MAX_SOURCE_PIXELS);
+    reader.limits(limits);
+    let img = reader.decode().map_err(|e| e.to_string())?;
+    encode_png(&img.resize_to_fill(size, size, FilterType::Lanczos3))
+}
+
+fn encode_png(img: &image::DynamicImage) -> Result<Vec<u8>, String> {
+    let mut out = Cursor::new(Vec::new());
+    img.write_to(&mut out, ImageFormat::Png).map_err(|e| e.to_string())?;
+    Ok(out.into_inner())
+}
+
+/// Identicon 5x5 simmetrico (stile GitHub): colore e disegno dall'hash del mint, sempre uguali per lo stesso token
+pub fn identicon(mint: &str, size: u32) -> Vec<u8> {
+    let hash = Sha256::digest(mint.as_bytes());
+    // Colore né troppo chiaro né troppo scuro sullo sfondo
+    let color = Rgba([hash[0] / 2 + 48, hash[1] / 2 + 48, hash[2] / 2 + 48, 255]);
+    let background = Rgba([240, 240, 240, 255]);
+    // 15 celle (3 colonne x 5 righe), le ultime 2 colonne sono lo specchio delle prime
+    let bits = u32::from_le_bytes([hash[3], hash[4], hash[5], 0]);
+    // Margine di mezza cella: griglia 5x5 su 6 celle di lato
+    let cell = size as f32 / 6.0;
+
+    let img = ImageBuffer::from_fn(size, size, |x, y| {
+        let col = ((x as f32 - cell / 2.0) / cell).floor();
+        let row = ((y as f32 - cell / 2.0) / cell).floor();
+        if !(0.0..5.0).contains(&col) || !(0.0..5.0).contains(&row) { return background; }
+        let col = (col as u32).min(4 - col as u32);
+        if bits >> (row as u32 * 3 + col) & 1 == 1 { color } else { background }
+    });
+    // Un PNG in memoria appena generato non può fallire la codifica
+    encode_png(&image::DynamicImage::ImageRgba8(img)).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identicon_is_stable_per_mint() {
+        let a = identicon("So11111111111111111111111111111111111111112", 64);
+        assert_eq!(a, identicon("So11111111111111111111111111111111111111112", 64));
+        assert_ne!(a, identicon("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v", 64));
+        let img = image::load_from_memory(&a).unwrap();
+        assert_eq!((img.width(), img.height()), (64, 64));
+    }
+
+    #[test]
+    fn logos_are_resized_to_a_square() {
+        let logo = encode_png(&image::DynamicImage::new_rgba8(300, 120)).unwrap();
+        let img = image::load_from_memory(&resize_png(&logo, 48).unwrap()).unwrap();
+        assert_eq!((img.width(), img.height()), (48, 48));
+        assert!(resize_png(b"<svg></svg>", 48).is_err());
+    }
+}
@@ -0,0 +1,157 @@
+use std::str::FromStr;
+use std::sync::Arc;
+use chrono::{TimeZone, Utc};
+use serde_json::json;
+use solana_sdk::pubkey::Pubkey;
+use crate::{birdeye, db, i18n, jupiter, metadata, network, notifications, report, telegram_bot};
+use crate::notifications::Notification;
+
+// --- NOTIFICHE TRADE (Telegram con contesto: prezzi, durata, saldo, PnL del giorno e grafico) ---
+// 1. Acquisti, vendite e stop-out passano da notify: webhook come prima + messaggio Telegram dell'utente
+// 2. Livello per utente in users.settings["trade_alerts"]: off | compact (titolo, PnL e link) | full (tutto + grafico)
+// 3. Il grafico è un'immagine QuickChart costruita dalle candele Birdeye recenti (come il QR del deposito:
+//    Telegram scarica l'URL). Senza candele o se l'invio della foto fallisce si manda solo il testo.
+// 4. PnL del giorno dalla mezzanotte nel fuso del report giornaliero dell'utente
+
+const CHART_URL: &str = "https://quickchart.io/chart";
+const CHART_INTERVAL: &str = "5m";
+const CHART_CANDLES: i64 = 48;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Verbosity {
+    Off,
+    Compact,
+    Full,
+}
+
+impl Verbosity {
+    pub const ALL: [&'static str; 3] = ["off", "compact", "full"];
+
+    pub fn from_code(code: &str) -> Option<Self> {
+        match code.trim().to_lowercase().as_str() {
+            "off" => Some(Self::Off),
+            "compact" => Some(Self::Compact),
+            "full" => Some(Self::Full),
+            _ => None,
+        }
+    }
+
+    pub fn code(self) -> &'static str {
+        match self {
+            Self::Off => "off",
+            Self::Compact => "compact",
+            Self::Full => "full",
+        }
+    }
+
+    pub fn from_settings(settings: &serde_json::Value) -> Self {
+        settings["trade_alerts"].as_str().and_then(Self::from_code).unwrap_or(Self::Full)
+    }
+}
+
+pub async fn set_verbosity(pool: &db::DbPool, user_id: &str, level: Verbosity) -> Result<(), sqlx::Error> {
+    db::update_setting(pool, user_id, "trade_alerts", json!(level.code())).await
+}
+
+/// Notifica di un trade: webhook dell'utente + messaggio Telegram (non blocca il chiamante)
+pub fn notify(pool: &db::DbPool, net: &Arc<network::NetworkClient>, user_id: &str, n: Notification) {
+    let (p, net, uid, alert) = (pool.clone(), net.clone(), user_id.to_string(), n.clone());
+    tokio::spawn(async move { send(&p, &net, &uid, &alert).await; });
+    notifications::notify(pool, user_id, n);
+}
+
+async fn send(pool: &db::DbPool, net: &Arc<network::NetworkClient>, user_id: &str, n: &Notification) {
+    // Utente solo Web: nessuna chat Telegram
+    if user_id.parse::<i64>().is_err() { return; }
+    let settings = db::get_settings(pool, user_id).await.unwrap_or_default();
+    let level = Verbosity::from_settings(&settings);
+    if level == Verbosity::Off { return; }
+    let lang = i18n::lang_from_settings(&settings);
+
+    let (token, tx_signature) = match n {
+        Notification::Buy { token, tx_signature, .. }
+        | Notification::Sell { token, tx_signature, .. }
+        | Notification::StopOut { token, tx_signature, .. } => (token.as_str(), tx_signature.as_str()),
+        _ => return,
+    };
+    let symbol = metadata::symbol(pool, net, token).await;
+
+    let mut lines = vec![match n {
+        Notification::Buy { amount_sol, route, .. } => {
+            let price = jupiter::get_token_market_data(token).await.map(|m| m.price).unwrap_or(0.0);
+            i18n::tr(lang, "alert.buy", &[&n.title(lang), &symbol, &format!("{:.4}", amount_sol), &format_price(price), route])
+        },
+        Notification::Sell { pnl_sol, reason, entry_price, exit_price, .. }
+        | Notification::StopOut { pnl_sol, reason, entry_price, exit_price, .. } => {
+            let icon = if *pnl_sol >= 0.0 { "🟢" } else { "🔴" };
+            let pct = if *entry_price > 0.0 && *exit_price > 0.0 { format!("{:+.1}%", (exit_price / entry_price - 1.0) * 100.0) } else { "n/d".into() };
+            i18n::tr(lang, "alert.sell", &[&n.title(lang), &symbol, reason, &icon, &format!("{:+.4}", pnl_sol), &pct])
+        },
+        _ => return,
+    }];
+
+    if level == Verbosity::Full {
+        if let Notification::Sell { entry_price, exit_price, hold_secs, .. } | Notification::StopOut { entry_price, exit_price, hold_secs, .. } = n {
+            lines.push(i18n::tr(lang, "alert.prices", &[&format_price(*entry_price), &format_price(*exit_price), &format_hold(*hold_secs)]));
+        }
+        let (balance, daily_pnl) = account_context(pool, net, user_id, &settings).await;
+        lines.push(i18n::tr(lang, "alert.account", &[&format!("{:.4}", balance), &format!("{:+.4}", daily_pnl)]));
+    }
+    lines.push(i18n::tr(lang, "alert.links", &[&tx_signature, &token]));
+    let text = lines.join("\n");
+
+    if level == Verbosity::Full {
+        if let Some(url) = chart_url(token, &symbol).await {
+            if telegram_bot::notify_user_with_photo(user_id, &url, text.clone()).await { return; }
+        }
+    }
+    telegram_bot::notify_user(user_id, text).await;
+}
+
+// Saldo SOL del wallet e PnL realizzato da mezzanotte (fuso del report)
+async fn account_context(pool: &db::DbPool, net: &Arc<network::NetworkClient>, user_id: &str, settings: &serde_json::Value) -> (f64, f64) {
+    let mut balance = 0.0;
+    if let Some(pk) = crate::wallet_manager::create_user_wallet(pool, user_id).await.ok().and_then(|s| Pubkey::from_str(&s).ok()) {
+        balance = net.get_balance_fast(&pk).await as f64 / 1_000_000_000.0;
+    }
+    let tz = report::ReportSettings::from_settings(settings).tz();
+    let midnight = Utc::now().with_timezone(&tz).date_naive().and_hms_opt(0, 0, 0)
+        .and_then(|m| tz.from_local_datetime(&m).earliest())
+        .map(|m| m.with_timezone(&Utc))
+        .unwrap_or_else(|| Utc::now() - chrono::Duration::hours(24));
+    let daily_pnl = db::get_stats_since(pool, user_id, midnight).await.map(|(_, _, pnl)| pnl).unwrap_or(0.0);
+    (balance, daily_pnl)
+}
+
+// Grafico a linee delle chiusure recenti (None senza candele Birdeye)
+async fn chart_url(token: &str, symbol: &str) -> Option<String> {
+    let secs = birdeye::interval_secs(CHART_INTERVAL)?;
+    let now = Utc::now().timestamp();
+    let candles = birdeye::get_ohlcv(token, CHART_INTERVAL, now - secs * CHART_CANDLES, now).await.ok()?;
+    if candles.len() < 2 { return None; }
+
+    let labels: Vec<String> = candles.iter()
+        .map(|c| Utc.timestamp_opt(c.time, 0).single().map(|t| t.format("%H:%M").to_string()).unwrap_or_default())
+        .collect();
+    let closes: Vec<f64> = candles.iter().map(|c| c.close).collect();
+    let config = json!({
+        "type": "line",
+        "data": { "labels": labels, "datasets": [{ "label": symbol, "data": closes, "fill": false, "pointRadius": 0, "borderColor": "#3498DB" }] },
+        "options": { "legend": { "display": false }, "title": { "display": true, "text": format!("{} ({})", symbol, CHART_INTERVAL) } },
+    });
+    let url = reqwest::Url::parse_with_params(CHART_URL, &[("w", "600"), ("h", "300"), ("c", config.to_string().as_str())]).ok()?;
+    Some(url.to_string())
+}
+
+fn format_price(price: f64) -> String {
+    if price <= 0.0 { "n/d".into() } else if price >= 1.0 { format!("{:.4}", price) } else { format!("{:.8}", price) }
+}
+
+fn format_hold(secs: i64) -> String {
+    match secs {
+        s if s <= 0 => "n/d".into(),
+        s if s < 3_600 => format!("{}m", s / 60),
+        s if s < 86_400 => format!("{}h {}m", s / 3_600, s % 3_600 / 60),
+        s => format!("{}g {}h", s / 86_400, s % 86_400 / 3_600),
+    }
+}
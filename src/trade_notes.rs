@@ -0,0 +1,75 @@
+use serde::Deserialize;
+use crate::db;
+
+// --- NOTE E TAG DEI TRADE (Per rivedere cosa ha funzionato) ---
+// 1. Testo libero e tag ("sniped from tg group", "long term") sui trade dell'utente, da API e Telegram
+// 2. Tag normalizzati (minuscolo, spazi compattati) e salvati separati da virgole: filtro ?tag= nell'export
+// 3. Nota e tag finiscono nell'export CSV/JSON; la quota venduta di una vendita parziale li eredita
+
+const MAX_NOTE_CHARS: usize = 500;
+const MAX_TAGS: usize = 10;
+const MAX_TAG_CHARS: usize = 32;
+
+// PATCH /trades/{id}/notes: campo assente = invariato, nota vuota o lista vuota = rimossi
+#[derive(Deserialize, Default)]
+pub struct TradeNotes {
+    pub note: Option<String>,
+    pub tags: Option<Vec<String>>,
+}
+
+/// Tag normalizzato: minuscolo, spazi compattati, solo lettere, cifre, spazi, '-' e '.'
+pub fn normalize_tag(raw: &str) -> Result<String, String> {
+    let tag = raw.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+    if tag.is_empty() { return Err("Tag vuoto".into()); }
+    // Il testo non valido non viene ripetuto nell'errore (risposte Telegram in HTML)
+    if !tag.chars().all(|c| c.is_alphanumeric() || c == ' ' || c == '-' || c == '.') {
+        return Err("Tag non valido (solo lettere, cifre, spazi, '-' e '.')".into());
+    }
+    if tag.chars().count() > MAX_TAG_CHARS { return Err(format!("Tag troppo lungo (max {} caratteri): {}", MAX_TAG_CHARS, tag)); }
+    Ok(tag)
+}
+
+/// Lista di tag normalizzata e senza doppioni (ordine dell'utente)
+pub fn normalize_tags(raw: &[String]) -> Result<Vec<String>, String> {
+    let mut tags: Vec<String> = Vec::new();
+    for t in raw {
+        let tag = normalize_tag(t)?;
+        if !tags.contains(&tag) { tags.push(tag); }
+    }
+    if tags.len() > MAX_TAGS { return Err(format!("Troppi tag (max {})", MAX_TAGS)); }
+    Ok(tags)
+}
+
+/// Tag da testo libero separato da virgole (Telegram: /tag ID long term, sniped)
+pub fn parse_tags(text: &str) -> Result<Vec<String>, String> {
+    let raw: Vec<String> = text.split(',').map(str::trim).filter(|t| !t.is_empty()).map(String::from).collect();
+    normalize_tags(&raw)
+}
+
+/// Tag salvati nel DB (separati da virgole) -> lista
+pub fn split_tags(stored: Option<&str>) -> Vec<String> {
+    stored.map(|s| s.split(',').filter(|t| !t.is_empty()).map(String::from).collect()).unwrap_or_default()
+}
+
+/// Aggiorna nota e/o tag del trade dell'utente
+pub async fn annotate(pool: &db::DbPool, user_id: &str, trade_id: i64, update: TradeNotes) -> Result<(), String> {
+    let note = match update.note {
+        Some(n) => {
+            let n = n.trim().to_string();
+            if n.chars().count() > MAX_NOTE_CHARS { return Err(format!("Nota troppo lunga (max {} caratteri)", MAX_NOTE_CHARS)); }
+            Some(n)
+        },
+        None => None,
+    };
+    let tags = match update.tags {
+        Some(t) => Some(normalize_tags(&t)?.join(",")),
+        None => None,
+    };
+    if note.is_none() && tags.is_none() { return Err("Niente da aggiornare (note o tags)".into()); }
+
+    match db::annotate_trade(pool, user_id, trade_id, note.as_deref(), tags.as_deref()).await {
+        Ok(true) => Ok(()),
+        Ok(false) => Err("Trade non trovato".into()),
+        Err(e) => Err(format!("Errore Database: {}", e)),
+    }
+}
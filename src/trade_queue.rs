@@ -0,0 +1,206 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::env;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::sync::Arc;
+use futures::FutureExt;
+use tokio::sync::{Mutex, Notify};
+use tokio::time::{Duration, Instant};
+use tracing::Instrument;
+use log::{debug, error, info, warn};
+use crate::{db, metrics::METRICS, network, signals, AppState};
+
+// --- CODA ACQUISTI (Auto-Buy con concorrenza limitata) ---
+// 1. Ogni segnale mette in coda un job per utente invece di lanciare un task a testa
+// 2. Un numero fisso di worker (TRADE_WORKERS) esegue i job: RPC e Jupiter non vengono presi d'assalto
+// 3. Equità: si serve un job per utente a giro (round-robin), chi ha molti segnali non blocca gli altri
+//    Un utente ha al massimo un job in esecuzione: i controlli di auto_buy_for_user (posizioni aperte, esposizione)
+//    non corrono contro un altro acquisto dello stesso utente
+// 4. Backpressure: coda piena, troppi job dello stesso utente o attesa troppo lunga = job scartato (con metrica)
+
+const DEFAULT_WORKERS: usize = 8;
+const DEFAULT_CAPACITY: usize = 1000;
+const DEFAULT_PER_USER: usize = 8;
+// Oltre questa attesa il prezzo del segnale è vecchio: meglio non comprare
+const DEFAULT_MAX_WAIT_SECS: u64 = 60;
+
+pub struct BuyJob {
+    pub user_id: String,
+    pub token: String,
+    pub round_trip_loss: f64,
+    pub signal: signals::EntrySignal,
+    pub span: tracing::Span,
+    enqueued: Instant,
+}
+
+impl BuyJob {
+    pub fn new(user_id: String, token: String, round_trip_loss: f64, signal: signals::EntrySignal, span: tracing::Span) -> Self {
+        Self { user_id, token, round_trip_loss, signal, span, enqueued: Instant::now() }
+    }
+}
+
+#[derive(Default)]
+struct Queues {
+    // Job in attesa per utente (FIFO)
+    per_user: HashMap<String, VecDeque<BuyJob>>,
+    // Turno degli utenti con job in attesa e nessun job in esecuzione
+    ring: VecDeque<String>,
+    // Utenti con un job in esecuzione: rientrano nel turno con `finish`
+    running: HashSet<String>,
+    len: usize,
+}
+
+pub struct TradeQueue {
+    pub workers: usize,
+    capacity: usize,
+    per_user_limit: usize,
+    max_wait: Duration,
+    queues: Mutex<Queues>,
+    ready: Notify,
+}
+
+impl TradeQueue {
+    /// Limiti da env: TRADE_WORKERS, TRADE_QUEUE_CAPACITY, TRADE_QUEUE_PER_USER, TRADE_QUEUE_MAX_WAIT_SECS
+    pub fn from_env() -> Self {
+        let var = |name: &str, default: usize| env::var(name).ok().and_then(|v| v.parse().ok()).filter(|v| *v > 0).unwrap_or(default);
+        Self {
+            workers: var("TRADE_WORKERS", DEFAULT_WORKERS),
+            capacity: var("TRADE_QUEUE_CAPACITY", DEFAULT_CAPACITY),
+            per_user_limit: var("TRADE_QUEUE_PER_USER", DEFAULT_PER_USER),
+            max_wait: Duration::from_secs(var("TRADE_QUEUE_MAX_WAIT_SECS", DEFAULT_MAX_WAIT_SECS as usize) as u64),
+            queues: Mutex::new(Queues::default()),
+            ready: Notify::new(),
+        }
+    }
+
+    /// Mette in coda il job. Se rifiutato viene restituito al chiamante (che libera la prenotazione).
+    pub async fn push(&self, job: BuyJob) -> Result<(), BuyJob> {
+        let mut q = self.queues.lock().await;
+        if q.len >= self.capacity {
+            METRICS.trade_queue_jobs.with_label_values(&["rejected_full"]).inc();
+            return Err(job);
+        }
+        let user_id = job.user_id.clone();
+        let user_jobs = q.per_user.entry(user_id.clone()).or_default();
+        if user_jobs.len() >= self.per_user_limit {
+            METRICS.trade_queue_jobs.with_label_values(&["rejected_user"]).inc();
+            return Err(job);
+        }
+        let first = user_jobs.is_empty();
+        user_jobs.push_back(job);
+        if first && !q.running.contains(&user_id) { q.ring.push_back(user_id); }
+        q.len += 1;
+        METRICS.trade_queue_depth.set(q.len as i64);
+        METRICS.trade_queue_jobs.with_label_values(&["enqueued"]).inc();
+        drop(q);
+        self.ready.notify_one();
+        Ok(())
+    }
+
+    /// Prossimo job, un utente per volta a turno. L'utente resta fuori dal turno finché il job non chiama `finish`.
+    async fn pop(&self) -> Option<BuyJob> {
+        let mut q = self.queues.lock().await;
+        let user_id = q.ring.pop_front()?;
+        let user_jobs = q.per_user.get_mut(&user_id)?;
+        let job = user_jobs.pop_front();
+        if user_jobs.is_empty() { q.per_user.remove(&user_id); }
+        if job.is_some() {
+            q.len -= 1;
+            q.running.insert(user_id);
+        }
+        METRICS.trade_queue_depth.set(q.len as i64);
+        job
+    }
+
+    /// Job dell'utente terminato (eseguito o scartato): se ha altri job in attesa torna in coda al turno
+    async fn finish(&self, user_id: &str) {
+        let mut q = self.queues.lock().await;
+        q.running.remove(user_id);
+        if !q.per_user.contains_key(user_id) { return; }
+        q.ring.push_back(user_id.to_string());
+        drop(q);
+        self.ready.notify_one();
+    }
+
+    /// Svuota la coda (shutdown): i job mai partiti liberano la prenotazione
+    async fn cancel_all(&self, state: &Arc<AppState>) -> usize {
+        let mut q = self.queues.lock().await;
+        let jobs: Vec<BuyJob> = q.per_user.drain().flat_map(|(_, jobs)| jobs).collect();
+        q.ring.clear();
+        q.running.clear();
+        q.len = 0;
+        METRICS.trade_queue_depth.set(0);
+        drop(q);
+        for job in &jobs { crate::trading::release_buy(state, &job.user_id, &job.token); }
+        jobs.len()
+    }
+}
+
+/// Esegue l'acquisto e libera sempre utente, prenotazione e gauge: un panic non lascia l'utente fuori dalla coda
+async fn run_job(state: &Arc<AppState>, user_id: &str, token: &str, buy: impl Future<Output = ()>) {
+    METRICS.trade_workers_busy.inc();
+    let outcome = if AssertUnwindSafe(buy).catch_unwind().await.is_ok() { "executed" } else {
+        error!("💥 Auto-Buy in panic per {} su {}: job scartato.", user_id, token);
+        "panicked"
+    };
+    crate::trading::release_buy(state, user_id, token);
+    state.trade_queue.finish(user_id).await;
+    METRICS.trade_workers_busy.dec();
+    METRICS.trade_queue_jobs.with_label_values(&[outcome]).inc();
+}
+
+pub async fn run_trade_worker(pool: db::DbPool, net: Arc<network::NetworkClient>, state: Arc<AppState>, id: usize) {
+    if id == 0 { info!("🧵 Coda Acquisti: ONLINE ({} worker)", state.trade_queue.workers); }
+
+    loop {
+        if state.is_shutting_down() { break; }
+        let Some(job) = state.trade_queue.pop().await else {
+            tokio::select! {
+                _ = state.trade_queue.ready.notified() => continue,
+                _ = state.shutdown_signal() => break,
+            }
+        };
+
+        let waited = job.enqueued.elapsed();
+        METRICS.trade_queue_wait.observe(waited.as_secs_f64());
+        if waited > state.trade_queue.max_wait {
+            debug!("⌛ Auto-Buy scartato per {} su {}: in coda da {}s.", job.user_id, job.token, waited.as_secs());
+            METRICS.trade_queue_jobs.with_label_values(&["expired"]).inc();
+            crate::trading::release_buy(&state, &job.user_id, &job.token);
+            state.trade_queue.finish(&job.user_id).await;
+            continue;
+        }
+
+        let BuyJob { user_id, token, round_trip_loss, signal, span, .. } = job;
+        let buy = crate::trading::auto_buy_for_user(&pool, &net, &state, &user_id, &token, round_trip_loss, &signal).instrument(span);
+        run_job(&state, &user_id, &token, buy).await;
+    }
+
+    let cancelled = state.trade_queue.cancel_all(&state).await;
+    if cancelled > 0 { warn!("🧵 Coda Acquisti: {} acquisti annullati per lo shutdown.", cancelled); }
+    if id == 0 { info!("🛑 Coda Acquisti fermata."); }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job(user_id: &str) -> BuyJob {
+        let signal = signals::EntrySignal { symbol: "TEST".into(), source: "MARKET", score: 90, balance_fraction: None };
+        BuyJob::new(user_id.into(), "So11111111111111111111111111111111111111112".into(), 0.0, signal, tracing::Span::none())
+    }
+
+    #[tokio::test]
+    async fn panicking_job_releases_the_user() {
+        let state = Arc::new(AppState::default());
+        let q = &state.trade_queue;
+        assert!(q.push(job("u1")).await.is_ok());
+        let j = q.pop().await.expect("job in coda");
+
+        run_job(&state, &j.user_id, &j.token, async { panic!("acquisto esploso") }).await;
+
+        // L'utente non resta "in esecuzione": il job successivo parte
+        assert!(q.push(job("u1")).await.is_ok());
+        assert_eq!(q.pop().await.map(|j| j.user_id).as_deref(), Some("u1"));
+    }
+}
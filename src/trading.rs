@@ -0,0 +1,524 @@
+use log::{info, warn, debug};
+use std::sync::Arc;
+use solana_sdk::pubkey::Pubkey;
+use crate::chain::{self, ChainClient};
+use crate::{activity, auto_park, db, i18n, jupiter, metadata, metrics, network, notifications, portfolio, price_oracle, raydium, risk, safety, signals, token_filter, trade_alerts, trade_queue, AppState, LiveEvent, OpenPosition};
+
+// --- OPERAZIONI DI TRADING (Percorsi condivisi da task, API e Telegram) ---
+// 1. Auto-Buy dei segnali (Sniper, Market Strategy, Copy-Trading) tramite la coda dei worker
+// 2. Acquisti e vendite manuali, parziali e d'emergenza con chiusura dei trade nel DB
+// 3. Modifica delle uscite di una posizione e avvio dell'Auto-Bot
+
+// --- HELPER: ACQUISTI IN CORSO ---
+// Cooldown, frequenza e limite posizioni sono verificati sul DB (sopravvivono ai riavvii);
+// qui si evita solo che due segnali paralleli comprino lo stesso token insieme.
+// Trade chiusi considerati per il Kelly sizing (i più recenti)
+const KELLY_LOOKBACK_TRADES: i64 = 100;
+
+fn try_reserve_buy(state: &Arc<AppState>, user_id: &str, token: &str) -> bool {
+    state.buys_in_flight.insert(format!("{}:{}", user_id, token))
+}
+
+pub fn release_buy(state: &Arc<AppState>, user_id: &str, token: &str) {
+    state.buys_in_flight.remove(&format!("{}:{}", user_id, token));
+}
+
+// --- SMART AUTO-BUY (Sicuro) ---
+// `users`: utenti candidati (già filtrati da watchlist/parametri strategia dal chiamante)
+// `signal`: origine e score dell'ingresso, quota del saldo per il Copy-Trading
+pub async fn execute_smart_auto_buy(
+    pool: &db::DbPool,
+    net: &Arc<network::NetworkClient>,
+    state: &Arc<AppState>,
+    token_mint: &Pubkey,
+    users: Vec<String>,
+    signal: signals::EntrySignal
+) {
+    let mint_str = token_mint.to_string();
+
+    // Blacklist / Whitelist dell'utente (mint o simbolo)
+    let users = token_filter::retain_allowed(pool, users, &mint_str, &signal.symbol).await;
+    if users.is_empty() { return; }
+    
+    info!("🤖 AUTO-BUY CHECK: {} utenti potenziali per {}", users.len(), mint_str);
+
+    // Senza pool Raydium il token non è ancora tradabile: inutile provare
+    if raydium::fetch_pool_keys_by_mint(net, token_mint).await.is_err() { return; }
+
+    // ANTI-HONEYPOT: giro SOL -> Token -> SOL simulato prima di rischiare fondi veri
+    let honeypot = match safety::check_honeypot(net, token_mint, 10_000_000).await {
+        Ok(r) => r,
+        Err(e) => { warn!("🍯 Check Honeypot non riuscito per {}: {}", mint_str, e); return; }
+    };
+    if !honeypot.can_sell {
+        warn!("🍯 HONEYPOT BLOCCATO {}: {}", mint_str, honeypot.reason);
+        return;
+    }
+    let round_trip_loss = honeypot.round_trip_loss_pct;
+
+    for uid in users {
+
+        // 1. ACQUISTO GIÀ IN CORSO (stesso utente, stesso token)
+        if !try_reserve_buy(state, &uid, &mint_str) {
+            debug!("🚫 Auto-Buy saltato per {} su {}: acquisto già in corso.", uid, mint_str);
+            continue;
+        }
+
+        // Span del ciclo BUY: user_id e token su ogni riga, trade_id appena il trade è registrato
+        let span = tracing::info_span!("auto_buy", user_id = %uid, token = %mint_str, trade_id = tracing::field::Empty);
+
+        // Eseguito da un worker della coda: coda piena = acquisto saltato
+        let job = trade_queue::BuyJob::new(uid, mint_str.clone(), round_trip_loss, signal.clone(), span);
+        if let Err(job) = state.trade_queue.push(job).await {
+            warn!("🧵 Auto-Buy saltato per {} su {}: coda acquisti satura.", job.user_id, job.token);
+            release_buy(state, &job.user_id, &job.token);
+        }
+    }
+}
+
+pub async fn auto_buy_for_user(
+    pool: &db::DbPool,
+    net: &Arc<network::NetworkClient>,
+    state: &Arc<AppState>,
+    uid: &str,
+    token: &str,
+    round_trip_loss: f64,
+    signal: &signals::EntrySignal
+) {
+    let params = db::get_strategy_params(pool, uid).await;
+    // Honeypot: perdita stimata di andata e ritorno oltre il limite dell'utente
+    if round_trip_loss > params.max_round_trip_loss_pct {
+        debug!("🍯 Auto-Buy saltato per {} su {}: Round-Trip -{:.1}%", uid, token, round_trip_loss);
+        return;
+    }
+    let Some((out, amt_lam)) = auto_buy_on_chain(pool, net, state, uid, token, &params, signal).await else { return };
+    // Nome e simbolo in cache per storico ed export
+    metadata::resolve(pool, net, token).await;
+    trade_alerts::notify(pool, net, uid, notifications::Notification::Buy {
+        token: token.to_string(), amount_sol: amt_lam as f64 / 1_000_000_000.0, route: out.route.into(), tx_signature: out.signature,
+    });
+}
+
+/// Controlli di rischio, sizing, swap e record_buy dell'Auto-Buy su qualsiasi ChainClient (anche la catena simulata
+/// dello stress test). Ritorna lo swap e i lamports spesi, None = acquisto saltato o fallito.
+pub async fn auto_buy_on_chain(
+    pool: &db::DbPool,
+    chain: &dyn ChainClient,
+    state: &AppState,
+    uid: &str,
+    token: &str,
+    params: &crate::strategy::StrategyParams,
+    signal: &signals::EntrySignal
+) -> Option<(chain::Swap, u64)> {
+    let balance_fraction = signal.balance_fraction;
+
+    // 1b. CIRCUIT BREAKER (Perdita giornaliera oltre il limite)
+    if risk::auto_buy_blocked(pool, uid).await {
+        debug!("🧯 Auto-Buy saltato per {}: Circuit Breaker attivo.", uid);
+        return None;
+    }
+
+    // 2. COOLDOWN, FREQUENZA & LIMITE POSIZIONI (Fonte di verità: tabella trades)
+    match risk::trade_frequency(pool, uid, params).await.map(|f| f.blocks(token)) {
+        Ok(None) => {},
+        Ok(Some(reason)) => { debug!("🚫 Auto-Buy saltato per {} su {}: {}.", uid, token, reason); return None; },
+        Err(e) => { warn!("⚠️ Cooldown non verificabile per {}: {}", uid, e); return None; }
+    }
+    match db::count_open_trades(pool, uid).await {
+        Ok(n) if n < params.max_open_positions => {},
+        Ok(n) => { debug!("🚫 Auto-Buy saltato per {}: {} posizioni aperte (max {}).", uid, n, params.max_open_positions); return None; },
+        Err(e) => { warn!("⚠️ Posizioni non verificabili per {}: {}", uid, e); return None; }
+    }
+
+    let wallet = chain.load_wallet(pool, uid).await.ok()?;
+
+    // 3. CHECK SALDO & RISK MANAGEMENT
+    let bal = chain.native_balance(&wallet.address()).await.unwrap_or(0);
+    let bal_sol = bal as f64 / 1_000_000_000.0;
+
+    // Limiti dell'utente: per trade, esposizione totale, riserva nel wallet
+    let budget = match risk::buy_budget(pool, uid, params, bal).await {
+        Ok(b) => b,
+        Err(e) => { warn!("⚠️ Limiti di investimento non verificabili per {}: {}", uid, e); return None; }
+    };
+    if budget.max() == 0 {
+        debug!("🚫 Auto-Buy saltato per {}: nessun budget (riserva o esposizione massima).", uid);
+        return None;
+    }
+
+    let amt_sol = match balance_fraction {
+        // Frazione dei SOL spendibili (oltre riserva e fee), non del saldo lordo
+        Some(f) => budget.spendable as f64 / 1_000_000_000.0 * f,
+        None if params.sizing == "kelly" => {
+            let returns = db::get_strategy_returns(pool, uid, &params.engine, KELLY_LOOKBACK_TRADES).await.unwrap_or_default();
+            crate::strategy::sized_investment_amount(bal_sol, params, Some(&crate::strategy::EdgeStats { returns }))
+        },
+        None => crate::strategy::calculate_investment_amount(bal_sol),
+    };
+    if amt_sol <= 0.0 {
+        debug!("📐 Auto-Buy saltato per {} su {}: edge Kelly non positivo ({}).", uid, token, params.engine);
+        return None;
+    }
+    
+    // TETTO MASSIMO DI SICUREZZA (max_per_trade_sol, esposizione e riserva: l'importo si riduce al budget)
+    let amt_lam = ((amt_sol * 1_000_000_000.0) as u64).min(budget.max());
+    if amt_lam == 0 { return None; }
+
+    // Shutdown in corso: niente nuovi swap
+    if state.is_shutting_down() { return None; }
+
+    // 3b. SOLO SEGNALI: l'ingresso diventa un trade in attesa di approvazione
+    if params.signals_only {
+        let trade = db::PendingTrade {
+            id: 0, user_id: uid.to_string(), token: token.to_string(), symbol: signal.symbol.clone(),
+            engine: params.engine.clone(), source: signal.source.to_string(), score: signal.score,
+            amount_lamports: amt_lam, quoted_out: 0, slippage_bps: params.slippage_bps,
+            status: "PENDING".into(), tx_signature: None, created_at: String::new(),
+        };
+        if let Err(e) = signals::suggest(pool, trade).await { warn!("⚠️ Trade suggerito non registrato per {}: {}", uid, e); }
+        return None;
+    }
+
+    // 4. ROUTER (Jupiter / Orca per miglior quote, Raydium come fallback)
+    match chain.buy(pool, uid, &wallet, token, amt_lam, params.slippage_bps).await {
+        Ok(out) => {
+            info!("✅ BUY {} ({}) -> TX: {}", out.route, uid, out.signature);
+            // Copy-Trade a parte: le sue statistiche non devono pesare sul sizing della strategia
+            let source = if balance_fraction.is_some() { "copy" } else { params.engine.as_str() };
+            let _ = db::record_buy(pool, uid, token, &out.signature, amt_lam, price_oracle::sol_usd().await, source).await;
+            Some((out, amt_lam))
+        },
+        Err(e) => {
+            warn!("⚠️ Auto-Buy fallito per {} su {}: {}", uid, token, e);
+            activity::record(pool, uid, activity::Event::error(Some(token), format!("Auto-Buy fallito ({}): {}", signal.source, e))).await;
+            None
+        },
+    }
+}
+
+
+// --- VENDITA TOTALE (Token -> nativo, via ChainClient) ---
+pub async fn execute_sell(
+    pool: &db::DbPool,
+    chain: &dyn chain::ChainClient,
+    user_id: &str,
+    wallet: &chain::Wallet,
+    token: &str,
+    slippage_bps: u16
+) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+    let amount = chain.token_balance(&wallet.address(), token).await?;
+    if amount == 0 { return Err("Token non trovato nel wallet".into()); }
+
+    let out = chain.sell(pool, user_id, wallet, token, amount, slippage_bps).await?;
+    Ok(out.signature)
+}
+
+// PnL in SOL del trade chiuso al prezzo d'uscita (0 se uno dei due prezzi non è noto)
+pub fn realized_pnl_sol(amount_in: u64, entry_price: f64, exit_price: f64) -> f64 {
+    if entry_price <= 0.0 || exit_price <= 0.0 { return 0.0; }
+    amount_in as f64 * (exit_price / entry_price - 1.0) / 1_000_000_000.0
+}
+
+// --- CHIUSURA POSIZIONI (La vendita svuota tutto il token: chiude ogni trade aperto su di esso) ---
+pub async fn close_token_positions(
+    pool: &db::DbPool,
+    state: &Arc<AppState>,
+    user_id: &str,
+    token: &str,
+    exit_price: f64,
+    sig: &str,
+    reason: &str
+) -> f64 {
+    let trades = db::get_user_token_open_trades(pool, user_id, token).await.unwrap_or_default();
+    let mut total_pnl = 0.0;
+    let sol_usd = price_oracle::sol_usd().await;
+    for (trade_id, amount_in) in trades {
+        let entry = state.open_positions.get(&trade_id).map(|p| p.entry_price);
+        let pnl_sol = entry.map(|e| realized_pnl_sol(amount_in, e, exit_price)).unwrap_or(0.0);
+        if db::close_position(pool, trade_id, pnl_sol, sig, sol_usd).await.is_ok() {
+            metrics::METRICS.trades_closed.with_label_values(&[if pnl_sol >= 0.0 { "win" } else { "loss" }]).inc();
+            state.open_positions.remove(&trade_id);
+            total_pnl += pnl_sol;
+        }
+    }
+    // La conferma on-chain può essere arrivata prima della chiusura: si ricalcola subito se possibile
+    let _ = db::settle_sell(pool, sig).await;
+    if let Err(e) = portfolio::refresh(pool, user_id).await { warn!("⚠️ Statistiche portafoglio non aggiornate ({}): {}", user_id, e); }
+    // Venduto (anche a mano): il token esce dalla coda dei retry
+    let _ = db::clear_sell_retry(pool, user_id, token).await;
+    state.publish(LiveEvent::Sell { user_id: user_id.to_string(), token: token.to_string(), pnl_sol: total_pnl, tx_signature: sig.to_string(), reason: reason.to_string() });
+    total_pnl
+}
+
+// Prezzo medio d'ingresso (pesato sugli importi), secondi dalla prima apertura e lamports investiti sul token:
+// da leggere prima della chiusura
+pub fn position_entry(state: &AppState, user_id: &str, token: &str) -> (f64, i64, u64) {
+    let positions: Vec<OpenPosition> = state.positions_snapshot(Some(user_id)).into_iter().filter(|p| p.token == token && p.entry_price > 0.0).collect();
+    let invested: u64 = positions.iter().map(|p| p.amount_in_lamports).sum();
+    if invested == 0 { return (0.0, 0, 0); }
+    let entry = positions.iter().map(|p| p.entry_price * p.amount_in_lamports as f64).sum::<f64>() / invested as f64;
+    let opened_at = positions.iter().map(|p| p.opened_at).min().unwrap_or(0);
+    (entry, chrono::Utc::now().timestamp() - opened_at, invested)
+}
+
+// --- SWAP NATIVO -> TOKEN (ChainClient), senza registrare il trade ---
+pub async fn swap_sol_for_token(
+    pool: &db::DbPool,
+    chain: &dyn chain::ChainClient,
+    user_id: &str,
+    token: &str,
+    amount_lamports: u64,
+    slippage_bps: u16
+) -> Result<(String, &'static str), Box<dyn std::error::Error + Send + Sync>> {
+    let wallet = chain.load_wallet(pool, user_id).await?;
+    let bal = chain.native_balance(&wallet.address()).await?;
+    // Importo scelto dall'utente: oltre i suoi limiti si rifiuta, non si riduce
+    let params = db::get_strategy_params(pool, user_id).await;
+    let lang = i18n::user_lang(pool, user_id).await;
+    risk::buy_budget(pool, user_id, &params, bal).await?.check(amount_lamports, lang)?;
+
+    let out = chain.buy(pool, user_id, &wallet, token, amount_lamports, slippage_bps).await?;
+    Ok((out.signature, out.route))
+}
+
+// --- ACQUISTO MANUALE (Percorso unico per API e Telegram, via Router) ---
+pub async fn execute_buy(
+    pool: &db::DbPool,
+    net: &Arc<network::NetworkClient>,
+    user_id: &str,
+    token: &str,
+    amount_lamports: u64,
+    slippage_bps: u16
+) -> Result<(String, &'static str), Box<dyn std::error::Error + Send + Sync>> {
+    let (sig, route) = swap_sol_for_token(pool, net, user_id, token, amount_lamports, slippage_bps).await?;
+    let _ = db::record_buy(pool, user_id, token, &sig, amount_lamports, price_oracle::sol_usd().await, "manual").await;
+    metadata::resolve(pool, net, token).await;
+    trade_alerts::notify(pool, net, user_id, notifications::Notification::Buy {
+        token: token.to_string(), amount_sol: amount_lamports as f64 / 1_000_000_000.0, route: route.into(), tx_signature: sig.clone(),
+    });
+    Ok((sig, route))
+}
+
+// --- VENDITA MANUALE (Percorso unico per API e Telegram) ---
+pub async fn sell_position_now(
+    pool: &db::DbPool,
+    net: &Arc<network::NetworkClient>,
+    state: &Arc<AppState>,
+    user_id: &str,
+    token: &str
+) -> Result<(String, f64), Box<dyn std::error::Error + Send + Sync>> {
+    let wallet = net.load_wallet(pool, user_id).await?;
+    let params = db::get_strategy_params(pool, user_id).await;
+    let price = jupiter::get_token_market_data(token).await.map(|m| m.price).unwrap_or(0.0);
+
+    let sig = execute_sell(pool, net, user_id, &wallet, token, params.slippage_bps * 2).await?;
+    info!("✅ SELL MANUALE ({}) -> TX: {}", user_id, sig);
+    let (entry_price, hold_secs, invested) = position_entry(state, user_id, token);
+    let pnl = close_token_positions(pool, state, user_id, token, price, &sig, "Vendita Manuale").await;
+    auto_park::park_profit(pool, net, user_id, invested, pnl);
+    trade_alerts::notify(pool, net, user_id, notifications::Notification::Sell {
+        token: token.to_string(), pnl_sol: pnl, reason: "Vendita Manuale".into(), tx_signature: sig.clone(),
+        entry_price, exit_price: price, hold_secs,
+    });
+    Ok((sig, pnl))
+}
+
+// --- VENDITA PARZIALE (Presa di profitto: la parte non venduta resta OPEN) ---
+// 1. La quantità (percentuale o unità del token) si valida sul saldo reale dell'ATA
+// 2. Se copre tutto il saldo si ripiega sulla vendita completa (chiusura di ogni trade)
+// 3. Altrimenti ogni trade OPEN sul token viene diviso: quota venduta SOLD, il resto OPEN e tracciato
+pub enum SellSize { Percent(f64), Tokens(u64) }
+
+pub async fn sell_position_part(
+    pool: &db::DbPool,
+    net: &Arc<network::NetworkClient>,
+    state: &Arc<AppState>,
+    user_id: &str,
+    token: &str,
+    size: SellSize
+) -> Result<(String, f64), Box<dyn std::error::Error + Send + Sync>> {
+    let wallet = net.load_wallet(pool, user_id).await?;
+    let balance = net.token_balance(&wallet.address(), token).await?;
+    if balance == 0 { return Err("Token non trovato nel wallet".into()); }
+
+    let amount = match size {
+        SellSize::Percent(p) if p > 0.0 && p <= 100.0 => (balance as f64 * p / 100.0) as u64,
+        SellSize::Tokens(n) if n <= balance => n,
+        SellSize::Percent(p) => return Err(format!("Percentuale non valida: {}", p).into()),
+        SellSize::Tokens(n) => return Err(format!("Quantità {} oltre il saldo ({})", n, balance).into()),
+    };
+    if amount == 0 { return Err("Quantità da vendere nulla".into()); }
+    if amount >= balance { return sell_position_now(pool, net, state, user_id, token).await; }
+
+    let params = db::get_strategy_params(pool, user_id).await;
+    let price = jupiter::get_token_market_data(token).await.map(|m| m.price).unwrap_or(0.0);
+    let fraction = amount as f64 / balance as f64;
+    let reason = format!("Vendita Parziale {:.0}%", fraction * 100.0);
+
+    let sig = net.sell(pool, user_id, &wallet, token, amount, params.slippage_bps * 2).await?.signature;
+    info!("✅ SELL PARZIALE {:.1}% ({}) -> TX: {}", fraction * 100.0, user_id, sig);
+    let (entry_price, hold_secs, invested) = position_entry(state, user_id, token);
+    let pnl = reduce_token_positions(pool, state, user_id, token, fraction, price, &sig).await;
+    auto_park::park_profit(pool, net, user_id, (invested as f64 * fraction) as u64, pnl);
+    state.publish(LiveEvent::Sell { user_id: user_id.to_string(), token: token.to_string(), pnl_sol: pnl, tx_signature: sig.clone(), reason: reason.clone() });
+    trade_alerts::notify(pool, net, user_id, notifications::Notification::Sell {
+        token: token.to_string(), pnl_sol: pnl, reason, tx_signature: sig.clone(),
+        entry_price, exit_price: price, hold_secs,
+    });
+    Ok((sig, pnl))
+}
+
+// Divide i trade OPEN sul token: la quota venduta si chiude col PnL stimato, il resto rimane nel Position Manager
+async fn reduce_token_positions(
+    pool: &db::DbPool,
+    state: &Arc<AppState>,
+    user_id: &str,
+    token: &str,
+    fraction: f64,
+    exit_price: f64,
+    sig: &str
+) -> f64 {
+    let trades = db::get_user_token_open_trades(pool, user_id, token).await.unwrap_or_default();
+    let mut total_pnl = 0.0;
+    let sol_usd = price_oracle::sol_usd().await;
+    for (trade_id, amount_in) in trades {
+        let sold_in = (amount_in as f64 * fraction) as u64;
+        if sold_in == 0 || sold_in >= amount_in { continue; }
+        let entry = state.open_positions.get(&trade_id).map(|p| p.entry_price);
+        let pnl_sol = match entry {
+            Some(e) if e > 0.0 && exit_price > 0.0 => sold_in as f64 * (exit_price / e - 1.0) / 1_000_000_000.0,
+            _ => 0.0,
+        };
+        match db::split_trade(pool, trade_id, sold_in, pnl_sol, sig, sol_usd).await {
+            Ok(sold_id) => {
+                metrics::METRICS.trades_closed.with_label_values(&[if pnl_sol >= 0.0 { "win" } else { "loss" }]).inc();
+                if let Some(mut pos) = state.open_positions.get_mut(&trade_id) {
+                    let keep = 1.0 - sold_in as f64 / amount_in as f64;
+                    pos.amount_in_lamports -= sold_in;
+                    pos.highest_value_lamports = (pos.highest_value_lamports as f64 * keep) as u64;
+                    pos.stop_floor_lamports = (pos.stop_floor_lamports as f64 * keep) as u64;
+                    pos.take_profit_lamports = (pos.take_profit_lamports as f64 * keep) as u64;
+                }
+                info!("✂️ Trade #{} diviso: quota venduta #{} ({} lamports)", trade_id, sold_id, sold_in);
+                total_pnl += pnl_sol;
+            },
+            Err(e) => warn!("⚠️ Divisione trade #{} non riuscita: {}", trade_id, e),
+        }
+    }
+    let _ = db::settle_sell(pool, sig).await;
+    if let Err(e) = portfolio::refresh(pool, user_id).await { warn!("⚠️ Statistiche portafoglio non aggiornate ({}): {}", user_id, e); }
+    total_pnl
+}
+
+// --- VENDITA D'EMERGENZA (Rug: slippage imposto, nessun controllo di trailing o coda) ---
+pub async fn emergency_sell(
+    pool: &db::DbPool,
+    net: &Arc<network::NetworkClient>,
+    state: &Arc<AppState>,
+    user_id: &str,
+    token: &str,
+    slippage_bps: u16,
+    reason: &str
+) -> Result<(String, f64), Box<dyn std::error::Error + Send + Sync>> {
+    let wallet = net.load_wallet(pool, user_id).await?;
+    let price = jupiter::get_token_market_data(token).await.map(|m| m.price).unwrap_or(0.0);
+
+    let sig = execute_sell(pool, net, user_id, &wallet, token, slippage_bps).await?;
+    info!("✅ SELL EMERGENZA ({}) {} -> TX: {}", user_id, token, sig);
+    let (entry_price, hold_secs, _) = position_entry(state, user_id, token);
+    let pnl = close_token_positions(pool, state, user_id, token, price, &sig, reason).await;
+    // Solo webhook: l'avviso Telegram del rug lo manda il chiamante
+    notifications::notify(pool, user_id, notifications::Notification::StopOut {
+        token: token.to_string(), pnl_sol: pnl, reason: reason.to_string(), tx_signature: sig.clone(),
+        entry_price, exit_price: price, hold_secs,
+    });
+    Ok((sig, pnl))
+}
+
+
+// Modifica manuale delle uscite (PATCH /positions/{id} e /position su Telegram).
+// Percentuali sul PnL della posizione. Campo assente = invariato, null = rimosso.
+#[derive(serde::Deserialize, Default)]
+pub struct ExitUpdate {
+    // Es. -20 = vendi a -20%, 0 = break-even, +10 = protegge il 10% di profitto
+    #[serde(default, deserialize_with = "nullable_pct")]
+    pub stop_loss_pct: Option<Option<f64>>,
+    #[serde(default, deserialize_with = "nullable_pct")]
+    pub take_profit_pct: Option<Option<f64>>,
+    pub trailing: Option<bool>,
+}
+
+fn nullable_pct<'de, D: serde::Deserializer<'de>>(d: D) -> Result<Option<Option<f64>>, D::Error> {
+    serde::Deserialize::deserialize(d).map(Some)
+}
+
+pub async fn update_position_exits(pool: &db::DbPool, state: &Arc<AppState>, user_id: &str, trade_id: i64, update: ExitUpdate) -> Result<OpenPosition, String> {
+    let mut pos = match state.open_positions.get(&trade_id).map(|p| p.value().clone()) {
+        Some(p) if p.user_id == user_id => p,
+        _ => return Err("Posizione non trovata".into()),
+    };
+    let invested = pos.amount_in_lamports as f64;
+    let level = |pct: f64| (invested * (1.0 + pct / 100.0)) as u64;
+
+    if let Some(sl) = update.stop_loss_pct {
+        pos.stop_floor_lamports = match sl {
+            Some(p) if p.is_finite() && p > -100.0 => level(p),
+            Some(_) => return Err("Stop loss non valido (deve essere sopra -100%)".into()),
+            None => 0,
+        };
+    }
+    if let Some(tp) = update.take_profit_pct {
+        pos.take_profit_lamports = match tp {
+            Some(p) if p.is_finite() && p > 0.0 => level(p),
+            Some(_) => return Err("Take profit non valido (deve essere sopra 0%)".into()),
+            None => 0,
+        };
+    }
+    if let Some(on) = update.trailing { pos.trailing_disabled = !on; }
+    if pos.stop_floor_lamports > 0 && pos.take_profit_lamports > 0 && pos.stop_floor_lamports >= pos.take_profit_lamports {
+        return Err("Lo stop loss deve stare sotto il take profit".into());
+    }
+
+    db::update_position_exits(pool, trade_id, pos.stop_floor_lamports, pos.take_profit_lamports, pos.trailing_disabled).await
+        .map_err(|e| format!("Errore Database: {}", e))?;
+    // Il Position Manager potrebbe averla chiusa nel frattempo
+    let updated = state.open_positions.get_mut(&trade_id).map(|mut p| {
+        p.stop_floor_lamports = pos.stop_floor_lamports;
+        p.take_profit_lamports = pos.take_profit_lamports;
+        p.trailing_disabled = pos.trailing_disabled;
+        p.value().clone()
+    });
+    match updated {
+        Some(p) => {
+            state.publish(LiveEvent::Position(p.clone()));
+            Ok(p)
+        },
+        None => Err("Posizione non più aperta".into()),
+    }
+}
+
+/// Avvia il ciclo Auto-Bot, opzionalmente cambiando strategia e universo di token. Ritorna la strategia attiva.
+pub async fn start_auto_bot(pool: &db::DbPool, user_id: &str, engine: Option<&str>, universe: Option<&[String]>) -> Result<String, String> {
+    let mut params = db::get_strategy_params(pool, user_id).await;
+    let mut overrides = serde_json::Map::new();
+    if let Some(engine) = engine {
+        overrides.insert("engine".into(), engine.trim().to_lowercase().into());
+    }
+    if let Some(universe) = universe {
+        let mut list: Vec<String> = Vec::new();
+        for u in universe.iter().map(|u| u.trim().to_lowercase()) {
+            if !list.contains(&u) { list.push(u); }
+        }
+        overrides.insert("universe".into(), list.into());
+    }
+    if !overrides.is_empty() {
+        params = params.with_overrides(&serde_json::Value::Object(overrides))?;
+        db::save_strategy_params(pool, user_id, &params).await.map_err(|e| format!("Errore Database: {}", e))?;
+    }
+    if !db::start_daily_cycle(pool, user_id).await.map_err(|e| format!("Errore Database: {}", e))? {
+        return Err("Completa prima la configurazione iniziale su Telegram (/start): disclaimer sui rischi e preferenze".into());
+    }
+    info!("🤖 Auto-Bot {} avviato con strategia {} (universo: {})", user_id, params.engine, params.universe.join(", "));
+    activity::record(pool, user_id, activity::Event::bot_start(&params.engine)).await;
+    Ok(params.engine)
+}
@@ -1,8 +1,9 @@
 use solana_sdk::signature::{Keypair, Signer};
-use sqlx::{SqlitePool, Row}; // Importante: Row
+use sqlx::Row; // Importante: Row
+use crate::db::DbPool;
 use aes_gcm::{
     aead::{Aead, KeyInit},
-    Aes256Gcm, Nonce
+    Aes256Gcm
 };
 use rand::{rngs::OsRng, RngCore};
 use std::env;
@@ -12,9 +13,9 @@ use log::{info};
 type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
 
 /// 1. CREA WALLET UTENTE
-pub async fn create_user_wallet(pool: &SqlitePool, tg_id: &str) -> Result<String> {
+pub async fn create_user_wallet(pool: &DbPool, tg_id: &str) -> Result<String> {
     // FIX: Usa sqlx::query() invece di query!() per evitare errori di compilazione
-    let exists = sqlx::query("SELECT pubkey FROM users WHERE tg_id = ?")
+    let exists = sqlx::query("SELECT pubkey FROM users WHERE tg_id = $1")
         .bind(tg_id)
         .fetch_optional(pool)
         .await?;
@@ -27,26 +28,11 @@ pub async fn create_user_wallet(pool: &SqlitePool, tg_id: &str) -> Result<String
     // Genera Keypair Solana Random
     let kp = Keypair::new();
     let pubkey = kp.pubkey().to_string();
-    let secret_bytes = kp.to_bytes();
-
-    // Criptazione AES-256
-    let master_key = env::var("MASTER_KEY").expect("❌ Manca MASTER_KEY nel .env");
-    let cipher = Aes256Gcm::new_from_slice(master_key.as_bytes())
-        .map_err(|_| "Lunghezza MASTER_KEY invalida (serve 32 chars)")?;
-    
-    let mut nonce_bytes = [0u8; 12];
-    OsRng.fill_bytes(&mut nonce_bytes);
-    let nonce = Nonce::from_slice(&nonce_bytes);
-
-    let encrypted_sk = cipher.encrypt(nonce, secret_bytes.as_ref())
-        .map_err(|_| "Errore Criptazione")?;
-
-    // Salvataggio
-    let stored_value = format!("{}:{}", hex::encode(nonce_bytes), hex::encode(encrypted_sk));
+    let stored_value = encrypt_with_master_key(&kp)?;
     let now_str = chrono::Utc::now().to_rfc3339();
 
     // FIX: Query standard per INSERT
-    sqlx::query("INSERT INTO users (tg_id, private_key_enc, pubkey, created_at) VALUES (?, ?, ?, ?)")
+    sqlx::query("INSERT INTO users (tg_id, private_key_enc, pubkey, created_at) VALUES ($1, $2, $3, $4)")
         .bind(tg_id)
         .bind(stored_value)
         .bind(&pubkey)
@@ -59,9 +45,9 @@ pub async fn create_user_wallet(pool: &SqlitePool, tg_id: &str) -> Result<String
 }
 
 /// 2. RECUPERA WALLET DECRIPTATO
-pub async fn get_decrypted_wallet(pool: &SqlitePool, tg_id: &str) -> Result<Keypair> {
+pub async fn get_decrypted_wallet(pool: &DbPool, tg_id: &str) -> Result<Keypair> {
     // FIX: Query standard per SELECT
-    let record = sqlx::query("SELECT private_key_enc FROM users WHERE tg_id = ?")
+    let record = sqlx::query("SELECT private_key_enc FROM users WHERE tg_id = $1")
         .bind(tg_id)
         .fetch_optional(pool)
         .await?;
@@ -82,11 +68,108 @@ pub async fn get_decrypted_wallet(pool: &SqlitePool, tg_id: &str) -> Result<Keyp
 
     let master_key = env::var("MASTER_KEY").expect("Manca MASTER_KEY");
     let cipher = Aes256Gcm::new_from_slice(master_key.as_bytes())?;
-    let nonce = Nonce::from_slice(&nonce_bytes);
+    let nonce = nonce_bytes.as_slice().into();
 
     let decrypted_bytes = cipher.decrypt(nonce, ciphertext.as_ref())
         .map_err(|_| "Decriptazione Fallita! Master Key errata?")?;
 
     let kp = Keypair::from_bytes(&decrypted_bytes).map_err(|_| "Keypair invalida")?;
     Ok(kp)
-}
\ No newline at end of file
+}
+/// Criptazione AES-256 con MASTER_KEY (formato DB "nonce_hex:ciphertext_hex")
+fn encrypt_with_master_key(kp: &Keypair) -> Result<String> {
+    let secret_bytes = kp.to_bytes();
+
+    let master_key = env::var("MASTER_KEY").expect("❌ Manca MASTER_KEY nel .env");
+    let cipher = Aes256Gcm::new_from_slice(master_key.as_bytes())
+        .map_err(|_| "Lunghezza MASTER_KEY invalida (serve 32 chars)")?;
+    
+    let mut nonce_bytes = [0u8; 12];
+    OsRng.fill_bytes(&mut nonce_bytes);
+    let nonce = nonce_bytes.as_slice().into();
+
+    let encrypted_sk = cipher.encrypt(nonce, secret_bytes.as_ref())
+        .map_err(|_| "Errore Criptazione")?;
+
+    Ok(format!("{}:{}", hex::encode(nonce_bytes), hex::encode(encrypted_sk)))
+}
+
+// --- EXPORT / IMPORT (Recupero fondi se il server muore) ---
+// Formato export: "gsx1:salt_hex:nonce_hex:ciphertext_hex"
+// Chiave = Argon2id(passphrase utente, salt): il server non può decriptare senza la passphrase.
+const EXPORT_PREFIX: &str = "gsx1";
+pub const MIN_PASSPHRASE_LEN: usize = 8;
+
+fn derive_export_key(passphrase: &str, salt: &[u8]) -> Result<[u8; 32]> {
+    let mut key = [0u8; 32];
+    argon2::Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| format!("Errore derivazione chiave: {}", e))?;
+    Ok(key)
+}
+
+/// 3. EXPORT: chiave privata dell'utente criptata con la sua passphrase
+pub async fn export_wallet_encrypted(pool: &DbPool, tg_id: &str, passphrase: &str) -> Result<String> {
+    if passphrase.len() < MIN_PASSPHRASE_LEN { return Err("Passphrase troppo corta".into()); }
+    let kp = get_decrypted_wallet(pool, tg_id).await?;
+
+    let mut salt = [0u8; 16];
+    let mut nonce_bytes = [0u8; 12];
+    OsRng.fill_bytes(&mut salt);
+    OsRng.fill_bytes(&mut nonce_bytes);
+
+    let key = derive_export_key(passphrase, &salt)?;
+    let cipher = Aes256Gcm::new_from_slice(&key).map_err(|_| "Chiave export invalida")?;
+    let encrypted = cipher.encrypt(nonce_bytes.as_slice().into(), kp.to_bytes().as_ref())
+        .map_err(|_| "Errore Criptazione")?;
+
+    info!("📤 Export wallet richiesto da TG {}", tg_id);
+    Ok(format!("{}:{}:{}:{}", EXPORT_PREFIX, hex::encode(salt), hex::encode(nonce_bytes), hex::encode(encrypted)))
+}
+
+/// Decripta un export "gsx1:..." con la passphrase
+pub fn decrypt_export(blob: &str, passphrase: &str) -> Result<Keypair> {
+    let parts: Vec<&str> = blob.trim().split(':').collect();
+    if parts.len() != 4 || parts[0] != EXPORT_PREFIX { return Err("Formato export non valido".into()); }
+
+    let salt = hex::decode(parts[1])?;
+    let nonce_bytes = hex::decode(parts[2])?;
+    let ciphertext = hex::decode(parts[3])?;
+    if nonce_bytes.len() != 12 { return Err("Formato export non valido".into()); }
+
+    let key = derive_export_key(passphrase, &salt)?;
+    let cipher = Aes256Gcm::new_from_slice(&key).map_err(|_| "Chiave export invalida")?;
+    let secret = cipher.decrypt(nonce_bytes.as_slice().into(), ciphertext.as_ref())
+        .map_err(|_| "Passphrase errata o export corrotto")?;
+
+    Ok(Keypair::from_bytes(&secret).map_err(|_| "Keypair invalida")?)
+}
+
+/// Chiave privata in chiaro (Base58, formato Phantom/Solflare)
+pub fn keypair_from_base58(secret: &str) -> Result<Keypair> {
+    let bytes = bs58::decode(secret.trim()).into_vec().map_err(|_| "Chiave Base58 non valida")?;
+    Ok(Keypair::from_bytes(&bytes).map_err(|_| "Keypair invalida")?)
+}
+
+/// 4. IMPORT: carica una chiave esistente in un NUOVO utente (mai sovrascrivere un wallet con fondi)
+pub async fn import_user_wallet(pool: &DbPool, tg_id: &str, kp: &Keypair) -> Result<String> {
+    let exists = sqlx::query("SELECT pubkey FROM users WHERE tg_id = $1")
+        .bind(tg_id)
+        .fetch_optional(pool)
+        .await?;
+    if exists.is_some() { return Err("Hai già un wallet: l'import è consentito solo su un nuovo account".into()); }
+
+    let pubkey = kp.pubkey().to_string();
+    let stored_value = encrypt_with_master_key(kp)?;
+
+    sqlx::query("INSERT INTO users (tg_id, private_key_enc, pubkey, created_at) VALUES ($1, $2, $3, $4)")
+        .bind(tg_id)
+        .bind(stored_value)
+        .bind(&pubkey)
+        .bind(chrono::Utc::now().to_rfc3339())
+        .execute(pool)
+        .await?;
+
+    info!("📥 Importato wallet per TG {}: {}", tg_id, pubkey);
+    Ok(pubkey)
+}
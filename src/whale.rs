@@ -0,0 +1,114 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use tokio::time::{sleep, Duration};
+use log::{debug, info, warn};
+use crate::{birdeye, db, jupiter, telegram_bot, AppState, DEFAULT_WATCHLIST};
+
+// --- WHALE MONITOR (Swap grandi sui token in watchlist e in posizione) ---
+// 1. Ogni 30s si leggono gli ultimi swap (Birdeye) dei token seguiti
+// 2. Sopra WHALE_MIN_USD (default 50k$) parte l'alert Telegram a chi ha il token in watchlist o in posizione
+// 3. Il flusso netto delle whale degli ultimi 15 minuti diventa `whale_pressure` per la Market Strategy
+
+const DEFAULT_MIN_USD: f64 = 50_000.0;
+const PRESSURE_WINDOW_SECS: i64 = 900;
+
+// Swap whale recenti di un token: (timestamp, +USD acquisto / -USD vendita)
+#[derive(Default)]
+pub struct WhaleFlow {
+    events: VecDeque<(i64, f64)>,
+}
+
+impl WhaleFlow {
+    fn push(&mut self, time: i64, signed_usd: f64) {
+        self.events.push_back((time, signed_usd));
+        let cutoff = chrono::Utc::now().timestamp() - PRESSURE_WINDOW_SECS;
+        while self.events.front().is_some_and(|(t, _)| *t < cutoff) { self.events.pop_front(); }
+    }
+
+    /// +1 = solo acquisti whale, -1 = solo vendite, 0 = nessuna whale (o equilibrio)
+    pub fn pressure(&self, now: i64) -> f64 {
+        let (net, total) = self.events.iter()
+            .filter(|(t, _)| *t >= now - PRESSURE_WINDOW_SECS)
+            .fold((0.0, 0.0), |(net, total), (_, usd)| (net + usd, total + usd.abs()));
+        if total > 0.0 { net / total } else { 0.0 }
+    }
+}
+
+/// Pressione whale corrente sul token (0 se nessun dato)
+pub fn pressure(state: &AppState, token: &str) -> f64 {
+    state.whale_flows.get(token).map(|f| f.pressure(chrono::Utc::now().timestamp())).unwrap_or(0.0)
+}
+
+/// "$120k", "$1.2M"
+fn format_usd(usd: f64) -> String {
+    if usd >= 1_000_000.0 { format!("${:.1}M", usd / 1_000_000.0) } else { format!("${:.0}k", usd / 1_000.0) }
+}
+
+pub async fn run_whale_monitor(pool: db::DbPool, state: Arc<AppState>) {
+    if std::env::var("BIRDEYE_API_KEY").is_err() {
+        warn!("🐋 Whale Monitor disattivato: manca BIRDEYE_API_KEY");
+        return;
+    }
+    let min_usd = std::env::var("WHALE_MIN_USD").ok().and_then(|v| v.parse().ok()).unwrap_or(DEFAULT_MIN_USD);
+    info!("🐋 Whale Monitor: ONLINE (soglia {})", format_usd(min_usd));
+
+    // Ultimo swap visto per token: al primo giro si fissa solo il punto di partenza (niente alert storici)
+    let mut last_seen: HashMap<String, i64> = HashMap::new();
+
+    loop {
+        let mut tokens: Vec<String> = DEFAULT_WATCHLIST.iter().map(|t| t.to_string()).collect();
+        tokens.extend(db::get_watched_tokens(&pool).await.unwrap_or_default());
+        tokens.extend(state.positions_snapshot(None).into_iter().map(|p| p.token));
+        tokens.sort();
+        tokens.dedup();
+
+        for token in &tokens {
+            if state.is_shutting_down() { break; }
+            let trades = match birdeye::get_token_trades(token, 50).await {
+                Ok(t) => t,
+                Err(e) => { debug!("🐋 Swap {} non disponibili: {}", token, e); continue; }
+            };
+            let newest = trades.iter().map(|t| t.time).max().unwrap_or(0);
+            let since = match last_seen.get(token).copied() {
+                Some(s) => s,
+                None => { last_seen.insert(token.clone(), newest); continue; }
+            };
+            last_seen.insert(token.clone(), since.max(newest));
+
+            for trade in trades.iter().filter(|t| t.time > since && t.volume_usd >= min_usd) {
+                state.whale_flows.entry(token.clone()).or_default()
+                    .push(trade.time, if trade.is_buy { trade.volume_usd } else { -trade.volume_usd });
+                alert(&pool, &state, token, trade).await;
+            }
+            sleep(Duration::from_millis(300)).await;
+        }
+
+        last_seen.retain(|t, _| tokens.contains(t));
+        state.whale_flows.retain(|t, _| tokens.contains(t));
+        if state.sleep_or_shutdown(Duration::from_secs(30)).await { break; }
+    }
+    info!("🛑 Whale Monitor fermato.");
+}
+
+async fn alert(pool: &db::DbPool, state: &Arc<AppState>, token: &str, trade: &birdeye::TokenTrade) {
+    let symbol = jupiter::get_token_market_data(token).await.map(|m| m.symbol).unwrap_or_else(|_| "???".into());
+    let (icon, side) = if trade.is_buy { ("🟢", "ACQUISTO") } else { ("🔴", "VENDITA") };
+    info!("🐋 {} {} {} ({})", format_usd(trade.volume_usd), symbol, side, trade.tx_hash);
+
+    // Destinatari: watchlist personale + chi ha una posizione aperta sul token
+    let mut users = db::get_token_watchers(pool, token).await.unwrap_or_default();
+    users.extend(state.positions_snapshot(None).into_iter().filter(|p| p.token == token).map(|p| p.user_id));
+    users.sort();
+    users.dedup();
+
+    let text = format!(
+        "🐋 <b>{} {}</b> — {} {} whale rilevato\n\n\
+        <code>{}</code>\n\
+        👤 <code>{}</code>\n\
+        🔗 <a href=\"https://solscan.io/tx/{}\">Vedi su Solscan</a>",
+        format_usd(trade.volume_usd), symbol, icon, side, token, trade.owner, trade.tx_hash
+    );
+    for user_id in users {
+        telegram_bot::notify_user(&user_id, text.clone()).await;
+    }
+}
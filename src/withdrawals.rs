@@ -0,0 +1,215 @@
+use std::str::FromStr;
+use std::sync::Arc;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signer;
+use solana_sdk::system_instruction;
+use solana_sdk::transaction::Transaction;
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup};
+use tokio::time::Duration;
+use log::{info, warn};
+use crate::{db, i18n, network, telegram_bot, wallet_manager, AppState};
+
+// --- PRELIEVI SICURI (Whitelist indirizzi + 2FA Telegram) ---
+// 1. Si preleva solo verso indirizzi registrati dall'utente (max MAX_ADDRESSES)
+// 2. Ogni nuovo indirizzo va confermato dal pulsante Telegram (anche se aggiunto dalla Web App)
+// 3. Dopo la conferma l'indirizzo resta bloccato WITHDRAW_ADDRESS_COOLDOWN_HOURS (default 24h):
+//    chi ruba la sessione non può svuotare il wallet verso un indirizzo appena aggiunto
+// 4. Sopra WITHDRAW_2FA_THRESHOLD_SOL (default 1 SOL) il prelievo aspetta "Approva" su Telegram:
+//    senza risposta entro APPROVAL_TTL_MINUTES viene annullato
+
+pub const MAX_ADDRESSES: usize = 10;
+const DEFAULT_COOLDOWN_HOURS: i64 = 24;
+const DEFAULT_2FA_THRESHOLD_SOL: f64 = 1.0;
+pub const APPROVAL_TTL_MINUTES: i64 = 15;
+// Fee della TX di trasferimento lasciata nel wallet
+const TRANSFER_FEE_LAMPORTS: u64 = 5_000;
+
+pub enum WithdrawOutcome {
+    Sent(String),
+    AwaitingApproval(i64),
+}
+
+fn cooldown_secs() -> i64 {
+    std::env::var("WITHDRAW_ADDRESS_COOLDOWN_HOURS").ok().and_then(|v| v.parse().ok()).unwrap_or(DEFAULT_COOLDOWN_HOURS).max(0) * 3600
+}
+
+/// Unix da cui l'indirizzo confermato è utilizzabile
+pub fn usable_from(addr: &db::WithdrawalAddress) -> Option<i64> {
+    addr.added_at.map(|t| t + cooldown_secs())
+}
+
+// Errore DB: dettaglio nel log, all'utente il messaggio tradotto
+fn db_error(lang: i18n::Lang, e: sqlx::Error) -> String {
+    warn!("⚠️ Prelievi: errore DB: {}", e);
+    i18n::t(lang, "api.db_error").into()
+}
+
+/// Registra un indirizzo e chiede la conferma su Telegram: messaggio per l'utente oppure errore (nella sua lingua)
+pub async fn request_address(pool: &db::DbPool, user_id: &str, address: &str, label: Option<&str>) -> Result<String, String> {
+    let lang = i18n::user_lang(pool, user_id).await;
+    let address = Pubkey::from_str(address.trim()).map_err(|_| i18n::t(lang, "withdraw.invalid_address"))?.to_string();
+    let own = crate::wallet_manager::create_user_wallet(pool, user_id).await.unwrap_or_default();
+    if own == address { return Err(i18n::t(lang, "withdraw.own_wallet").into()); }
+    let count = db::get_withdrawal_addresses(pool, user_id).await.map_err(|e| db_error(lang, e))?.len();
+    if count >= MAX_ADDRESSES { return Err(i18n::tr(lang, "withdraw.max_addresses", &[&MAX_ADDRESSES])); }
+
+    let label = label.map(str::trim).filter(|l| !l.is_empty()).map(|l| l.chars().take(32).collect::<String>());
+    let id = db::add_withdrawal_address(pool, user_id, &address, label.as_deref()).await
+        .map_err(|e| db_error(lang, e))?
+        .ok_or(i18n::t(lang, "withdraw.address_exists"))?;
+
+    let kb = InlineKeyboardMarkup::new(vec![vec![
+        InlineKeyboardButton::callback(i18n::t(lang, "withdraw.confirm_button"), format!("wl_confirm:{}", id)),
+        InlineKeyboardButton::callback(i18n::t(lang, "withdraw.reject_button"), format!("wl_reject:{}", id)),
+    ]]);
+    let text = i18n::tr(lang, "withdraw.address_request", &[
+        &address, &label.map(|l| format!(" ({})", l)).unwrap_or_default(), &(cooldown_secs() / 3600),
+    ]);
+    if !telegram_bot::notify_user_with_buttons(user_id, text, kb).await {
+        let _ = db::delete_withdrawal_address(pool, user_id, id).await;
+        return Err(i18n::t(lang, "withdraw.address_telegram_required").into());
+    }
+    info!("🔐 Indirizzo prelievo {} richiesto da {}", address, user_id);
+    Ok(i18n::t(lang, "withdraw.address_confirm_pending").into())
+}
+
+/// Esito del pulsante Conferma / Rifiuta
+pub async fn resolve_address(pool: &db::DbPool, user_id: &str, id: i64, approve: bool) -> Result<String, String> {
+    let lang = i18n::user_lang(pool, user_id).await;
+    let res = if approve {
+        db::confirm_withdrawal_address(pool, user_id, id).await
+    } else {
+        db::delete_withdrawal_address(pool, user_id, id).await
+    };
+    match res {
+        Ok(true) if approve => {
+            info!("🔐 Indirizzo prelievo #{} confermato da {}", id, user_id);
+            Ok(i18n::tr(lang, "withdraw.address_confirmed", &[&(cooldown_secs() / 3600)]))
+        },
+        Ok(true) => Ok(i18n::t(lang, "withdraw.address_rejected").into()),
+        Ok(false) => Err(i18n::t(lang, "withdraw.request_not_found").into()),
+        Err(e) => Err(db_error(lang, e)),
+    }
+}
+
+/// Il prelievo verso `dest` è consentito? (in whitelist, confermato, attesa trascorsa)
+pub async fn check_destination(pool: &db::DbPool, user_id: &str, dest: &str, lang: i18n::Lang) -> Result<(), String> {
+    let addr = db::get_withdrawal_address(pool, user_id, dest).await
+        .map_err(|e| db_error(lang, e))?
+        .ok_or(i18n::t(lang, "withdraw.not_whitelisted"))?;
+    let from = usable_from(&addr).ok_or(i18n::t(lang, "withdraw.not_confirmed"))?;
+    let wait = from - chrono::Utc::now().timestamp();
+    if wait > 0 {
+        return Err(i18n::tr(lang, "withdraw.cooldown", &[&(wait / 3600), &((wait % 3600) / 60)]));
+    }
+    Ok(())
+}
+
+fn approval_threshold_lamports() -> u64 {
+    let sol = std::env::var("WITHDRAW_2FA_THRESHOLD_SOL").ok().and_then(|v| v.parse::<f64>().ok()).unwrap_or(DEFAULT_2FA_THRESHOLD_SOL);
+    (sol.max(0.0) * 1_000_000_000.0) as u64
+}
+
+// Controlli comuni a richiesta e approvazione (la situazione può cambiare nei 15 minuti)
+async fn precheck(pool: &db::DbPool, net: &Arc<network::NetworkClient>, user_id: &str, amount: u64, dest: &str, lang: i18n::Lang) -> Result<(), String> {
+    if let Ok((false, msg)) = db::can_withdraw(pool, user_id).await { return Err(msg); }
+    check_destination(pool, user_id, dest, lang).await?;
+    let owner = wallet_manager::create_user_wallet(pool, user_id).await.ok()
+        .and_then(|s| Pubkey::from_str(&s).ok())
+        .ok_or(i18n::t(lang, "withdraw.wallet_error"))?;
+    if net.get_balance_fast(&owner).await < amount + TRANSFER_FEE_LAMPORTS {
+        return Err(i18n::t(lang, "withdraw.insufficient_funds").into());
+    }
+    Ok(())
+}
+
+/// Prelievo SOL verso un indirizzo in whitelist: inviato subito oppure in attesa di approvazione Telegram
+pub async fn withdraw(pool: &db::DbPool, net: &Arc<network::NetworkClient>, user_id: &str, amount: u64, dest: &str) -> Result<WithdrawOutcome, String> {
+    let lang = i18n::user_lang(pool, user_id).await;
+    if amount == 0 { return Err(i18n::t(lang, "withdraw.invalid_amount").into()); }
+    precheck(pool, net, user_id, amount, dest, lang).await?;
+
+    if amount <= approval_threshold_lamports() {
+        let id = db::record_withdrawal_request(pool, user_id, amount, dest).await.map_err(|e| db_error(lang, e))?;
+        return send_transfer(pool, net, user_id, id, amount, dest, lang).await.map(WithdrawOutcome::Sent);
+    }
+
+    let id = db::record_withdrawal_approval(pool, user_id, amount, dest).await.map_err(|e| db_error(lang, e))?;
+    let kb = InlineKeyboardMarkup::new(vec![vec![
+        InlineKeyboardButton::callback(i18n::t(lang, "withdraw.approve_button"), format!("wd_approve:{}", id)),
+        InlineKeyboardButton::callback(i18n::t(lang, "withdraw.reject_button"), format!("wd_reject:{}", id)),
+    ]]);
+    let text = i18n::tr(lang, "withdraw.approval_request", &[
+        &id, &format!("{:.4}", amount as f64 / 1_000_000_000.0), &dest, &APPROVAL_TTL_MINUTES,
+    ]);
+    if !telegram_bot::notify_user_with_buttons(user_id, text, kb).await {
+        let _ = db::cancel_withdrawal_approval(pool, user_id, id).await;
+        return Err(i18n::t(lang, "withdraw.approval_telegram_required").into());
+    }
+    info!("🛡 Prelievo #{} ({}) in attesa di approvazione", id, user_id);
+    Ok(WithdrawOutcome::AwaitingApproval(id))
+}
+
+/// Esito dei pulsanti Approva / Rifiuta
+pub async fn resolve_withdrawal(pool: &db::DbPool, net: &Arc<network::NetworkClient>, user_id: &str, id: i64, approve: bool) -> Result<String, String> {
+    let lang = i18n::user_lang(pool, user_id).await;
+    if !approve {
+        return match db::cancel_withdrawal_approval(pool, user_id, id).await {
+            Ok(true) => Ok(i18n::tr(lang, "withdraw.rejected", &[&id])),
+            Ok(false) => Err(i18n::t(lang, "withdraw.expired").into()),
+            Err(e) => Err(db_error(lang, e)),
+        };
+    }
+
+    let (amount, dest) = db::claim_withdrawal_approval(pool, user_id, id, APPROVAL_TTL_MINUTES).await
+        .map_err(|e| db_error(lang, e))?
+        .ok_or(i18n::t(lang, "withdraw.expired"))?;
+    if let Err(e) = precheck(pool, net, user_id, amount, &dest, lang).await {
+        db::fail_withdrawal(pool, id).await;
+        return Err(e);
+    }
+    let sig = send_transfer(pool, net, user_id, id, amount, &dest, lang).await?;
+    Ok(i18n::tr(lang, "withdraw.sent", &[&id, &format!("{:.4}", amount as f64 / 1_000_000_000.0), &sig]))
+}
+
+// Trasferimento vero e proprio (il record esiste già: crash protection)
+async fn send_transfer(pool: &db::DbPool, net: &Arc<network::NetworkClient>, user_id: &str, id: i64, amount: u64, dest: &str, lang: i18n::Lang) -> Result<String, String> {
+    let res = async {
+        let payer = wallet_manager::get_decrypted_wallet(pool, user_id).await.map_err(|_| "Wallet Error".to_string())?;
+        let dest = Pubkey::from_str(dest).map_err(|_| "Indirizzo Invalido".to_string())?;
+        let ix = system_instruction::transfer(&payer.pubkey(), &dest, amount);
+        let ixs = net.with_compute_budget(network::TxProfile::Transfer, &payer.pubkey(), vec![ix], &[]).await;
+        let bh = net.rpc.get_latest_blockhash().await.map_err(|e| e.to_string())?;
+        let tx = Transaction::new_signed_with_payer(&ixs, Some(&payer.pubkey()), &[&payer], bh);
+        net.rpc.send_transaction(&tx).await.map(|s| s.to_string()).map_err(|e| e.to_string())
+    }.await;
+
+    match res {
+        Ok(sig) => {
+            db::confirm_withdrawal(pool, id, &sig).await;
+            info!("💸 Prelievo #{} ({}): {} lamports -> {} ({})", id, user_id, amount, dest, sig);
+            Ok(sig)
+        },
+        Err(e) => {
+            warn!("⚠️ Prelievo #{} fallito: {}", id, e);
+            db::fail_withdrawal(pool, id).await;
+            Err(i18n::t(lang, "withdraw.transfer_failed").into())
+        }
+    }
+}
+
+/// Annulla le approvazioni scadute e avvisa l'utente
+pub async fn run_approval_expiry(pool: db::DbPool, state: Arc<AppState>) {
+    loop {
+        match db::expire_withdrawal_approvals(&pool, APPROVAL_TTL_MINUTES).await {
+            Ok(expired) => for (id, user_id) in expired {
+                info!("⌛ Prelievo #{} ({}) annullato: approvazione scaduta", id, user_id);
+                let lang = i18n::user_lang(&pool, &user_id).await;
+                telegram_bot::notify_user(&user_id, i18n::tr(lang, "notify.withdrawal_expired", &[&id, &APPROVAL_TTL_MINUTES])).await;
+            },
+            Err(e) => warn!("⚠️ Scadenza approvazioni prelievo: {}", e),
+        }
+        if state.sleep_or_shutdown(Duration::from_secs(60)).await { break; }
+    }
+    info!("🛑 Scadenza Prelievi fermata.");
+}